@@ -5,4 +5,6 @@ move-right = Move Right
 jump = Jump
 grab-drop = Grab / Drop
 shoot = Shoot
-slide = Slide
\ No newline at end of file
+slide = Slide
+emote = Emote
+dash = Dash
@@ -1,19 +1,42 @@
 debug-tools = Debug Tools
-show-kinematic-colliders = Show Kinematic Colliders
-show-damage-regions = Show Damage Regions
+show-collision-shapes = Show Collision Shapes
+show-emote-regions = Show Emote Regions
+show-force-regions = Show Force Regions
+show-camera-framing = Show Camera Framing
 show-world-inspector = Show World Inspector
+show-core-inspector = Show Core World Inspector
 show-frame-time-diagnostics = Show Frame Time Diagnostics
 show-network-visualizer = Show Network Visualizer
 show-profiler = Show Profiler
+show-network-diagnostics = Show Network Diagnostics
 show-pathfinding-lines = Show Pathfinding Lines
+checksum-every-frame = Check For Network De-syncs Every Frame
 
 profiler = Profiler
 
+core-inspector = Core World Inspector
+core-inspector-read-only = The game can only be edited in local games.
+
 frame-diagnostics = Frame Diagnostics
 frames-per-second = Frames Per Second
 frame-time = Frame Time
+synced-colliders = Synced Colliders
 reset-min-max = Reset Min/Max
 
 snapshot = Snapshot
 take-snapshot = Take Snapshot
-restore-snapshot = Restore Snapshot
\ No newline at end of file
+restore-snapshot = Restore Snapshot
+
+frame-stepping = Frame Stepping
+step-frames = Step Frames
+advance-frame = Advance 1 Frame
+advance-10-frames = Advance 10 Frames
+dump-world = Dump World
+network-diagnostics = Network Diagnostics
+not-available = n/a
+player-ping = Player { $player } Ping
+rollback-frames = Rollback Frames
+predicted-frames = Predicted Frames
+input-delay-frames = Input Delay Frames
+bytes-sent = Sent
+bytes-received = Received
@@ -15,9 +15,17 @@ element-layer-icon = E
 add-element = Add Element
 delete-element = Delete Element
 toggle-visibility = Toggle Visibility
+toggle-lock = Toggle Lock
 delete-layer = Delete Layer
+delete-layer-contents = This layer has { $tiles } tiles and { $elements } elements, which will be deleted with it.
+delete-layer-spawners-warning = ⚠ This layer has { $spawners } of the map's { $total } player spawners!
+delete-layer-all-spawners-warning = ⚠ This layer has all of the map's player spawners! Without them, players will have nowhere to spawn.
 delete = Delete
 randomize = Randomize
+undo = Undo
+redo = Redo
+playtest = Playtest
+playtest-hint = Play the map with a single player. Press F4 to return to the editor.
 
 create = Create
 layer-kind = Layer Kind
@@ -30,6 +38,19 @@ copy-to-clipboard = Copy to Clipboard
 tilemap = Tilemap
 tilemap-path = Tilemap Path
 
+backgrounds = Backgrounds
+add-background = Add Background
+background-image = Image
+background-depth = Depth
+background-scale = Scale
+background-offset = Offset
+background-tiling = Tiling
+move-back = Move Back
+move-forward = Move Forward
+no-background-images = No background images are loaded.
+delete-background = Delete Background
+delete-background-warning = Deleting a background layer can't be undone.
+
 collision = Collision
 collisions = Collisions
 
@@ -38,6 +59,11 @@ none = None
 jump-through = Jump Through
 empty = Empty
 solid = Solid
+breakable = Breakable
+
+surface = Surface
+surface-friction = Friction
+surface-sticky = Sticky
 
 open-map = Open Map
 no-map-loaded = No map loaded
@@ -47,3 +73,35 @@ view-offset = Camera Offset [ { $x }, { $y } ]
 cursor-position = Cursor Position [ { $x }, { $y } ]
 view-reset = Reset View
 show-grid = Show Grid
+
+snap = Snap
+snap-off = Off
+snap-half-tile = Half Tile
+snap-full-tile = Full Tile
+snap-custom = Custom
+
+import-map = Import Map
+export-map = Export to File
+map-imported = Imported map
+map-import-invalid = The map has errors and was not imported.
+map-import-unreadable = The file could not be read as a map
+
+element-inspector = Element Inspector
+position = Position
+deselect = Deselect
+reset-to-default = Reset to Default
+waypoints = Waypoints
+add-waypoint = Add Waypoint
+delete-waypoint = Delete Waypoint
+spawn-weight = Spawn Weight
+
+remote-editor = Player { $player }
+editing-layer = Player { $player } is editing this layer
+
+recover-autosave = Recover Unsaved Changes?
+recover-autosave-hint = These maps were autosaved after they were last saved.
+recover = Recover
+discard = Discard
+minutes-ago = { $minutes } min ago
+hours-ago = { $hours } h ago
+days-ago = { $days } d ago
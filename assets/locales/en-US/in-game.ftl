@@ -0,0 +1,45 @@
+spectating = Spectating { $player }
+player-spectating = Player { $number } Spectating { $player }
+replay-finished = Replay Finished
+replay-pause = Pause
+replay-resume = Resume
+replay-step = Step
+desync-detected = Game out of sync with player { $player } at frame { $frame }
+player-disconnected = Player { $player } Disconnected
+player-left = Player { $player } Left the Match
+chat-sender = Player { $player }
+chat-hint = Press Enter to send
+player-lives = { $player } - Lives: { $lives }
+player-eliminated = { $player } - Eliminated
+player-score = Score: { $score } / { $points_to_win }
+no-item = No Item
+item-ammo = Ammo: { $ammo } / { $max_ammo }
+reloading = Reloading
+player-wins = { $player } Wins!
+match-draw = Draw!
+match-mutators = Mutators: { $mutators }
+match-countdown-go = GO!
+waiting-for-players = Waiting for players...
+sudden-death = Sudden Death!
+training-spawn-item = Click the map to spawn:
+training-no-item = Nothing
+training-invincible = Invincible
+training-reset-position = Reset Position
+training-speed = Speed
+training-freeze = Freeze
+training-step = Step Frame
+training-state = State
+training-velocity = Velocity: { $x }, { $y }
+
+# Tutorial
+tutorial-progress = Tutorial { $done } / { $total }
+skip-tutorial = Skip Tutorial
+tutorial-move = Walk with { $input }
+tutorial-jump = Jump with { $input }
+tutorial-crouch = Hold down on { $input } to crouch
+tutorial-grab = Pick up the crate with { $input }
+tutorial-throw = Throw it with { $input }
+
+# Screenshots
+screenshot-saved = Screenshot saved:
+screenshot-failed = Could not save the screenshot:
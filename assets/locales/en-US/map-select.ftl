@@ -4,4 +4,41 @@ waiting-for-map = Waiting for other player to select map...
 default-maps = Default Maps
 experimental-maps = Experimental Maps
 user-maps = User Maps
-builtin-maps = Builtin Maps
\ No newline at end of file
+custom-maps = Custom Maps
+builtin-maps = Builtin Maps
+
+map-vote-countdown = Vote for a map: { $seconds }
+map-vote-start-now = Start Now
+
+random-map = Random Map
+map-playlist = Map Playlist
+edit-map-playlist = Edit Map Playlist
+map-playlist-empty = The playlist is empty.
+add-to-playlist = Add to Playlist
+playlist-move-up = Up
+playlist-move-down = Down
+playlist-remove = Remove
+missing-map = Missing Map
+done = Done
+
+map-problems = Map Problems
+map-has-errors = This map has errors and can't be played.
+map-has-warnings = This map has problems, but can still be played.
+play-anyway = Play Anyway
+map-problem-error = Error
+map-problem-warning = Warning
+map-problem-empty-grid = The map has no tiles.
+map-problem-duplicate-layer-name = Layer { $layer } has the same name as another layer
+map-problem-tile-out-of-bounds = Tile at ({ $x }, { $y }) is outside of the map.
+map-problem-tile-index-out-of-range = Tile at ({ $x }, { $y }) uses tile { $idx }, but the tilemap only has { $count } tiles.
+map-problem-missing-tilemap = The layer has tiles, but no tilemap.
+map-problem-unknown-element = Element at ({ $x }, { $y }) doesn't exist
+map-problem-unknown-element-behavior = Element at ({ $x }, { $y }) has a behavior that doesn't exist
+map-problem-spawn-point-out-of-bounds = Player spawner at ({ $x }, { $y }) is outside of the map.
+map-problem-no-spawn-points = The map has no player spawners.
+
+loading-map = Loading Map
+map-load-failed = The map couldn't be loaded, because this file is missing
+
+map-author = Author
+map-recommended-players = Players
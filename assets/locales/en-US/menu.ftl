@@ -1,3 +1,6 @@
+# The name of this language, shown in the language settings
+language-name = English
+
 # Menu Pages
 local-game = Local Game
 network-game = Network Game
@@ -7,7 +10,15 @@ main-menu = Main Menu
 map-editor = Map Editor
 settings = Settings
 paused = Paused
+menu = Menu
+game-still-running = The game keeps running in online matches
+controller-disconnected = Controller disconnected for player { $player }
 credits = Credits
+open-source-licenses = Open-Source Licenses
+replays = Replays
+training = Training
+tutorial = Tutorial
+stats = Stats
 
 # Actions
 close = Close
@@ -22,3 +33,28 @@ quit = Quit
 export = Export
 reload = Reload
 restart = Restart
+photo-mode = Photo Mode
+
+# Mutators
+mutators = Mutators
+mutator-low-gravity = Low Gravity
+mutator-bouncy-bodies = Bouncy Bodies
+mutator-one-hit-kill = One Hit Kill
+mutator-fast-fish = Fast Fish
+mutator-item-rain = Item Rain
+
+# Replays
+no-replays = No replays have been saved yet.
+replay-load-error = Could not load the replay. It may have been recorded with a different version of the game.
+
+# Stats
+no-stats = No matches have been played yet.
+player = Player
+matches-played = Matches
+wins = Wins
+losses = Losses
+kills = Kills
+deaths = Deaths
+favorite-weapon = Favorite Weapon
+fish-played = Fish Played
+reset-stats-warning = Reset all player stats? This can't be undone.
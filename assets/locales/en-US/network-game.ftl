@@ -5,6 +5,22 @@ join = Join
 servers = Servers
 players = Players
 no-servers = No Servers
+lan-discovery-unavailable = Can't search for LAN games, join one by its address instead.
+join-by-address = Join by Address
+invalid-address = Enter the host's address as IP:port, like 192.168.1.2:10500.
+join-failed = Couldn't join the game. Check the address, and that the host's firewall allows it.
+hosting-on-port = Other players can also join with your IP address and port { $port }.
+server-full = Full
+incompatible-version = Incompatible
+map = Map
+version = Version
+ping = Ping
+checking-versions = Checking that everybody has the same version of the game...
+handshake-version-mismatch = Player { $player } has version { $remote } of the game, which can't play with your version { $local }.
+handshake-game-data-mismatch = Player { $player } has modified game data, so you can't play together.
+handshake-invalid = Player { $player } has a version of the game that can't play with yours.
+handshake-timeout = Not every player responded in time. Check your connection and try again.
+ok = OK
 server-name = Server Name
 start-server = Start Server
 stop-server = Stop Server
@@ -1,15 +1,32 @@
 keyboard = Keyboard
 you-marker = < You >
+disconnected-marker = < Disconnected >
+you-marker-named = < { $name } (You) >
+remote-marker = < { $name } >
+player-device-keyboard = Keyboard { $number }
+player-device-gamepad = Keyboard { $keyboard } / Gamepad { $number }
+player-device-gamepad-disconnected = Keyboard { $keyboard } / Gamepad { $number } ( Disconnected )
+player-device-gamepad-only = Gamepad { $number }
+player-device-gamepad-only-disconnected = Gamepad { $number } ( Disconnected )
 pick-a-fish = Pick a Fish
+custom-player-unavailable = Custom fish isn't available to everyone
 
 player-select-ready = Ready!
 player-select-title = Player Select
 player-select-unready = Pres { $button } to unready
 
 press-button-to-join = Press { $button } to Join
+press-any-button-to-join = Press Any Button to Join
 press-button-to-lock-in = Press { $button } to Lock In
 press-button-to-remove = Press { $button } to Remove
+press-button-to-pick = Press { $button } to Pick
+press-button-to-cancel = Press { $button } to Cancel
+fish-picker-page = Page { $page } / { $pages }
 
 add-ai-player = Add AI Player
 remove-ai-player = Remove AI Player
 ai-player = AI Player
+ai-random-fish = Random Fish
+ai-difficulty-easy = Easy
+ai-difficulty-normal = Normal
+ai-difficulty-hard = Hard
@@ -2,13 +2,68 @@
 
 controls = Controls
 bind-input = Make an input to bind or press Escape to cancel
+conflicting-inputs = * This input is bound to more than one action for the same player.
+screenshot-key = Screenshot
+screenshot-key-reserved = The function keys and menu keys are already used by other shortcuts.
+conflicting-screenshot-key = * The screenshot key is also bound to a player's controls.
 
 keyboard-1 = Keyboard 1
 keyboard-2 = Keyboard 2
+keyboard-3 = Keyboard 3
+keyboard-4 = Keyboard 4
 gamepad = Gamepad
 
 action = Action
 
+# Gameplay settings
+gameplay = Gameplay
+throw-preview = Throw Preview
+camera-shake = Camera Shake
+kill-cam = Kill Cam
+double-tap-dash = Double-Tap to Dash
+show-hud = HUD
+discord-presence = Discord Status
+language = Language
+player-indicator = Player Indicator
+player-indicator-outline = Outline
+player-indicator-arrow = Arrow
+player-indicator-outline-and-arrow = Outline & Arrow
+on = On
+off = Off
+editor-autosave = Editor Autosave
+editor-autosave-interval = Every { $seconds }s
+
+# Profile settings
+profiles = Profiles
+player-number = Player { $number }
+player-name-hint = Name
+no-preferred-fish = Any Fish
+
+# Display settings
+display = Display
+window-resolution = Resolution
+window-mode = Window Mode
+fullscreen = Fullscreen
+windowed = Windowed
+vsync = VSync
+ui-scale = UI Scale
+
+# Sound settings
+sound = Sound
+master-volume = Master Volume
+music-volume = Music Volume
+effects-volume = Effects Volume
+mute-audio = Mute
+positional-audio = Positional Audio
+
 # Networking settings
 networking = Networking
 matchmaking-server = Matchmaking Server
+input-delay = Input Delay (Frames)
+adaptive-input-delay = Adaptive Input Delay
+
+# Settings storage
+settings-recovered-from-backup = Your settings couldn't be read, and were recovered from a backup.
+settings-reset = Your settings couldn't be read, and were reset.
+settings-save-failed = Your settings couldn't be saved:
+settings-quota-exceeded = Your settings couldn't be saved, because the browser's storage is full.
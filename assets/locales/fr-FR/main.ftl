@@ -1,3 +1,6 @@
+# The name of this language, shown in the language settings
+language-name = Français
+
 # Main Menu
 start-game = Démarrer Jeu
 
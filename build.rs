@@ -0,0 +1,115 @@
+//! Build script that lists the crates the game is built with, and their licenses, for the credits.
+//!
+//! The crates are read from the `Cargo.lock`, and their licenses from the manifests that Cargo has
+//! downloaded into its registry. The list is written to `dependency_licenses.rs` in the `OUT_DIR`,
+//! as an expression that is included into the credits menu.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let lock = fs::read_to_string(manifest_dir.join("Cargo.lock")).unwrap_or_default();
+    let registry_dirs = registry_source_dirs();
+
+    let mut entries = String::from("&[\n");
+    for package in locked_packages(&lock) {
+        let license = registry_dirs
+            .iter()
+            .map(|dir| {
+                dir.join(format!("{}-{}", package.name, package.version))
+                    .join("Cargo.toml")
+            })
+            .find_map(|manifest| fs::read_to_string(manifest).ok())
+            .map(|manifest| manifest_license(&manifest))
+            .unwrap_or_else(|| "Unknown".into());
+
+        entries.push_str(&format!(
+            "    DependencyLicense {{ name: {:?}, version: {:?}, license: {:?} }},\n",
+            package.name, package.version, license
+        ));
+    }
+    entries.push(']');
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("dependency_licenses.rs"), entries).unwrap();
+}
+
+/// A package from the `Cargo.lock`.
+struct LockedPackage {
+    name: String,
+    version: String,
+}
+
+/// Get the packages in a `Cargo.lock` that come from a registry, which leaves out the game's own
+/// crates.
+fn locked_packages(lock: &str) -> Vec<LockedPackage> {
+    let mut packages = Vec::new();
+    for block in lock.split("[[package]]").skip(1) {
+        let field = |key: &str| {
+            block.lines().find_map(|line| {
+                let value = line.strip_prefix(key)?.trim().strip_prefix('=')?;
+                Some(value.trim().trim_matches('"').to_string())
+            })
+        };
+        let (Some(name), Some(version), Some(source)) =
+            (field("name"), field("version"), field("source"))
+        else {
+            continue;
+        };
+        if source.starts_with("registry+") {
+            packages.push(LockedPackage { name, version });
+        }
+    }
+    packages
+}
+
+/// Get the directories that Cargo extracts the crates from each registry into.
+fn registry_source_dirs() -> Vec<PathBuf> {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))
+        .or_else(|| env::var_os("USERPROFILE").map(|home| Path::new(&home).join(".cargo")));
+    let Some(cargo_home) = cargo_home else {
+        return Vec::new();
+    };
+    let Ok(dirs) = fs::read_dir(cargo_home.join("registry").join("src")) else {
+        return Vec::new();
+    };
+
+    let mut dirs = dirs
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
+/// Get the license of a crate from its manifest.
+fn manifest_license(manifest: &str) -> String {
+    let mut has_license_file = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("license-file") {
+            if value.trim_start().starts_with('=') {
+                has_license_file = true;
+            }
+        } else if let Some(value) = line.strip_prefix("license") {
+            if let Some(value) = value.trim_start().strip_prefix('=') {
+                return value.trim().trim_matches('"').to_string();
+            }
+        }
+    }
+
+    if has_license_file {
+        "See license file".into()
+    } else {
+        "Unknown".into()
+    }
+}
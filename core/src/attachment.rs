@@ -12,6 +12,11 @@ pub fn install(session: &mut CoreSession) {
 
 /// Component for attaching an entity to another entity.
 ///
+/// Any number of entities may be attached to the same entity, and each of them has its own offset
+/// and settings. Attachments are updated in entity order, and are despawned along with the entity
+/// they are attached to. To move an attachment to another entity, just insert a new [`Attachment`]
+/// over it, or remove the component to detach it.
+///
 /// > **Warning:** attachments are not a general-purpose hierarchy system. Generally, only expect an
 /// > attachment to work properly if the entity it is attached to is not also attached to another
 /// > entity.
@@ -25,24 +30,91 @@ pub struct Attachment {
     /// The entity to attach to.
     pub entity: Entity,
     /// The offset to the attached entity.
+    ///
+    /// The `z` offset sets whether the attachment is drawn in front of or behind the entity.
     pub offset: Vec3,
     /// Synchronize [`AtlasSprite`] animation with entity animation
     pub sync_animation: bool,
     /// Synchronize [`Sprite`] color with entity color
     pub sync_color: bool,
+    /// Flip the offset and the sprite of the attachment when the entity's sprite is flipped.
+    pub inherit_flip: bool,
+    /// Rotate the attachment along with the entity.
+    pub inherit_rotation: bool,
+}
+
+impl Attachment {
+    /// Attach to the given entity, with no offset.
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            offset: Vec3::ZERO,
+            sync_animation: false,
+            sync_color: false,
+            inherit_flip: true,
+            inherit_rotation: true,
+        }
+    }
+
+    /// Set the horizontal and vertical offset from the entity.
+    pub fn with_offset(self, offset: Vec2) -> Self {
+        Self {
+            offset: offset.extend(self.offset.z),
+            ..self
+        }
+    }
+
+    /// Set the z offset from the entity, where a positive value is in front of it.
+    pub fn with_z(self, z: f32) -> Self {
+        Self {
+            offset: self.offset.truncate().extend(z),
+            ..self
+        }
+    }
+
+    pub fn with_sync_animation(self, sync_animation: bool) -> Self {
+        Self {
+            sync_animation,
+            ..self
+        }
+    }
+
+    pub fn with_sync_color(self, sync_color: bool) -> Self {
+        Self { sync_color, ..self }
+    }
+
+    pub fn with_inherit_flip(self, inherit_flip: bool) -> Self {
+        Self {
+            inherit_flip,
+            ..self
+        }
+    }
+
+    pub fn with_inherit_rotation(self, inherit_rotation: bool) -> Self {
+        Self {
+            inherit_rotation,
+            ..self
+        }
+    }
 }
 
 /// System to update the transforms of entities with the [`Attachment`] component.
 pub fn update_attachments(
     time: Res<Time>,
-    entities: Res<Entities>,
+    mut entities: ResMut<Entities>,
     mut sprites: CompMut<Sprite>,
     attachments: Comp<Attachment>,
     invincibles: Comp<Invincibility>,
     mut transforms: CompMut<Transform>,
     mut atlas_sprites: CompMut<AtlasSprite>,
 ) {
+    let mut orphaned_attachments = Vec::new();
     for (ent, attachment) in entities.iter_with(&attachments) {
+        if !entities.is_alive(attachment.entity) {
+            // Despawn attachments along with the entity they are attached to
+            orphaned_attachments.push(ent);
+            continue;
+        }
         let Some(attached_transform) = transforms.get(attachment.entity).copied() else {
             continue;
         };
@@ -50,13 +122,18 @@ pub fn update_attachments(
             .get_mut(ent)
             .expect("Entities with `Attachment` component must also have a `Transform` component.");
 
+        let rotation = transform.rotation;
         *transform = attached_transform;
+        if !attachment.inherit_rotation {
+            transform.rotation = rotation;
+        }
 
         let mut offset = attachment.offset;
         if let Some((flip_x, flip_y)) = atlas_sprites
             .get(attachment.entity)
             .map(|x| (x.flip_x, x.flip_y))
             .or_else(|| sprites.get(attachment.entity).map(|x| (x.flip_x, x.flip_y)))
+            .filter(|_| attachment.inherit_flip)
         {
             if flip_x {
                 offset.x *= -1.0;
@@ -110,6 +187,10 @@ pub fn update_attachments(
 
         transform.translation += offset;
     }
+
+    for ent in orphaned_attachments {
+        entities.kill(ent);
+    }
 }
 
 /// A component for attaching an entity to the player's body.
@@ -157,18 +238,24 @@ fn update_player_body_attachments(
         let current_frame = player_sprite.index;
         let current_anim = animation_banks.get(player_ent).unwrap().current;
 
-        let current_body_offset =
-            meta.layers.body.animations.body_offsets[&current_anim][current_frame];
+        let current_body_offset = meta
+            .layers
+            .body
+            .animations
+            .body_offsets
+            .get(&current_anim)
+            .and_then(|offsets| offsets.get(current_frame))
+            .copied()
+            .unwrap_or_default();
 
         player_body_attachment_markers.insert(ent, HadPlayerBodyAttachmentMarker);
         attachments.insert(
             ent,
-            Attachment {
-                entity: player_ent,
-                sync_color: body_attachment.sync_color,
-                sync_animation: body_attachment.sync_animation,
-                offset: current_body_offset.extend(0.0) + body_attachment.offset,
-            },
+            Attachment::new(player_ent)
+                .with_offset(current_body_offset + body_attachment.offset.truncate())
+                .with_z(body_attachment.offset.z)
+                .with_sync_animation(body_attachment.sync_animation)
+                .with_sync_color(body_attachment.sync_color),
         );
     }
 }
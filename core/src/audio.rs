@@ -0,0 +1,67 @@
+//! Sound effects played by the game.
+//!
+//! Systems queue up sounds in the [`SoundEvents`] resource, which is drained by the game every frame
+//! to play them. Sounds may be given the world position that they come from, so that they can be
+//! panned and attenuated according to where they are relative to the camera.
+
+use std::collections::VecDeque;
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<SoundEvents>();
+}
+
+/// A sound effect to be played.
+#[derive(Clone, Debug)]
+pub struct SoundEvent {
+    pub sound_source: Handle<AudioSource>,
+    pub volume: f64,
+    /// The position in the world that the sound comes from, if any.
+    ///
+    /// Sounds without a position are played centered at full volume.
+    pub position: Option<Vec2>,
+}
+
+/// Resource containing the queue of sounds to be played.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H4Z8N2C5VQWJ7D3XKT0BRM9F"]
+pub struct SoundEvents {
+    pub queue: VecDeque<SoundEvent>,
+}
+
+impl SoundEvents {
+    /// Play a sound that doesn't come from anywhere in particular.
+    pub fn play(&mut self, sound_source: Handle<AudioSource>, volume: f64) {
+        self.queue.push_back(SoundEvent {
+            sound_source,
+            volume,
+            position: None,
+        });
+    }
+
+    /// Play a sound coming from the given position in the world.
+    pub fn play_at(&mut self, sound_source: Handle<AudioSource>, volume: f64, position: Vec2) {
+        self.queue.push_back(SoundEvent {
+            sound_source,
+            volume,
+            position: Some(position),
+        });
+    }
+
+    /// Play a sound coming from an entity with the given transform.
+    ///
+    /// The sound is played centered if there is no transform.
+    pub fn play_from(
+        &mut self,
+        sound_source: Handle<AudioSource>,
+        volume: f64,
+        transform: Option<&Transform>,
+    ) {
+        self.queue.push_back(SoundEvent {
+            sound_source,
+            volume,
+            position: transform.map(|x| x.translation.truncate()),
+        });
+    }
+}
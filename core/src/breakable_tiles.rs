@@ -0,0 +1,252 @@
+//! Map tiles that can be destroyed by bullets and explosions.
+//!
+//! Tiles with the [`TileCollisionKind::Breakable`] collision are solid until they have taken enough
+//! damage, and then they are removed from the map, optionally respawning later. Anything that
+//! damages tiles adds the area it hits to the [`TileDamageQueue`], which is applied at the end of
+//! the frame.
+
+use crate::{physics::collisions::TileCollisionKind, prelude::*};
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<TileDamageQueue>();
+    session.world.init_resource::<BrokenTiles>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, respawn_broken_tiles)
+        .add_system_to_stage(CoreStage::PostUpdate, damage_tiles_with_explosions)
+        .add_system_to_stage(CoreStage::Last, apply_tile_damage);
+}
+
+/// Component containing the hit points left of a breakable tile that has been damaged.
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H1BSSN0M20XC8P9GYADM0KZ0"]
+pub struct TileHitPoints(pub u32);
+
+/// Marker component for damage regions that have already damaged the tiles they overlap.
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H13WPQHMG40NA9JHP0NX9YST"]
+pub struct TilesDamaged;
+
+/// Resource containing the areas to damage breakable tiles in this frame.
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H1ZWZRTAF9DQ38FT3B08CVPJ"]
+pub struct TileDamageQueue(Vec<(Rect, u32)>);
+
+impl TileDamageQueue {
+    /// Damage all of the breakable tiles overlapping `rect`.
+    pub fn damage(&mut self, rect: Rect, damage: u32) {
+        self.0.push((rect, damage));
+    }
+
+    /// Get the areas that have been queued to be damaged in this frame.
+    pub fn rects(&self) -> impl Iterator<Item = &Rect> + '_ {
+        self.0.iter().map(|(rect, _)| rect)
+    }
+}
+
+/// A tile that has been broken and is waiting to respawn.
+#[derive(Clone, Copy, Debug)]
+pub struct BrokenTile {
+    pub layer_idx: usize,
+    pub pos: UVec2,
+    /// The tilemap index of the tile.
+    pub idx: usize,
+    /// How many frames are left before the tile respawns.
+    pub frames_left: u32,
+}
+
+/// Resource containing the broken tiles that will respawn.
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H39K9CZC434CV80QEK2CRVJ2"]
+pub struct BrokenTiles(pub Vec<BrokenTile>);
+
+/// Get the grid positions of the tiles in the layer that overlap `rect`.
+fn tiles_in_rect(layer: &TileLayer, rect: &Rect) -> impl Iterator<Item = UVec2> {
+    let max_tile = layer.grid_size.as_vec2() - 1.0;
+    let min = (rect.min / layer.tile_size)
+        .floor()
+        .clamp(Vec2::ZERO, max_tile);
+    let max = (rect.max / layer.tile_size)
+        .floor()
+        .clamp(Vec2::ZERO, max_tile);
+    let (min, max) = (min.as_uvec2(), max.as_uvec2());
+
+    (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| uvec2(x, y)))
+}
+
+/// Get the rect of the tile at the given grid position.
+fn tile_rect(tile_size: Vec2, pos: UVec2) -> Rect {
+    let center = pos.as_vec2() * tile_size + tile_size / 2.0;
+    Rect::new(center.x, center.y, tile_size.x, tile_size.y)
+}
+
+/// Explosions damage the tiles they overlap once, when they go off.
+///
+/// Damage regions with an owner, like sword swings, don't damage tiles.
+fn damage_tiles_with_explosions(
+    entities: Res<Entities>,
+    core_meta: Res<CoreMetaArc>,
+    mut queue: ResMut<TileDamageQueue>,
+    damage_regions: Comp<DamageRegion>,
+    damage_region_owners: Comp<DamageRegionOwner>,
+    transforms: Comp<Transform>,
+    mut tiles_damaged: CompMut<TilesDamaged>,
+) {
+    let mut bitset = damage_regions.bitset().clone();
+    bitset.bit_and(transforms.bitset());
+    bitset.bit_andnot(damage_region_owners.bitset());
+    bitset.bit_andnot(tiles_damaged.bitset());
+
+    for ent in entities.iter_with_bitset(&bitset) {
+        let region = damage_regions.get(ent).unwrap();
+        let transform = transforms.get(ent).unwrap();
+        queue.damage(
+            region.collider_rect(transform.translation),
+            core_meta.breakable_tiles.explosion_damage,
+        );
+        tiles_damaged.insert(ent, TilesDamaged);
+    }
+}
+
+fn apply_tile_damage(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    core_meta: Res<CoreMetaArc>,
+    mut queue: ResMut<TileDamageQueue>,
+    mut broken_tiles: ResMut<BrokenTiles>,
+    mut audio_events: ResMut<SoundEvents>,
+    tile_layers: Comp<TileLayer>,
+    spawned_map_layer_metas: Comp<SpawnedMapLayerMeta>,
+    tiles: Comp<Tile>,
+    tile_collisions: Comp<TileCollisionKind>,
+    mut hit_points: CompMut<TileHitPoints>,
+) {
+    let meta = &core_meta.breakable_tiles;
+
+    for (rect, damage) in queue.0.drain(..) {
+        for (_ent, (layer, layer_meta)) in
+            entities.iter_with((&tile_layers, &spawned_map_layer_metas))
+        {
+            for pos in tiles_in_rect(layer, &rect) {
+                let Some(tile_ent) = layer.get(pos) else {
+                    continue;
+                };
+                if tile_collisions.get(tile_ent) != Some(&TileCollisionKind::Breakable) {
+                    continue;
+                }
+
+                let hp = hit_points
+                    .get(tile_ent)
+                    .map(|x| x.0)
+                    .unwrap_or(meta.hit_points);
+                // The tile may have already been broken earlier this frame
+                if hp == 0 {
+                    continue;
+                }
+                let hp = hp.saturating_sub(damage);
+                hit_points.insert(tile_ent, TileHitPoints(hp));
+                if hp > 0 {
+                    continue;
+                }
+
+                let layer_idx = layer_meta.layer_idx;
+                audio_events.play_at(
+                    meta.break_sound.clone(),
+                    meta.break_sound_volume,
+                    rect.center(),
+                );
+                if let Some(respawn_delay) = meta.respawn_delay {
+                    broken_tiles.0.push(BrokenTile {
+                        layer_idx,
+                        pos,
+                        idx: tiles.get(tile_ent).map(|x| x.idx).unwrap_or_default(),
+                        frames_left: respawn_delay,
+                    });
+                }
+
+                commands.add(move |mut collision_world: CollisionWorld| {
+                    collision_world.remove_tile(layer_idx, pos);
+                });
+                commands.add(
+                    move |mut entities: ResMut<Entities>,
+                          mut tile_layers: CompMut<TileLayer>,
+                          spawned_map_layer_metas: Comp<SpawnedMapLayerMeta>| {
+                        entities.kill(tile_ent);
+                        if let Some((_, (layer, _))) = entities
+                            .iter_with((&mut tile_layers, &spawned_map_layer_metas))
+                            .find(|(_, (_, meta))| meta.layer_idx == layer_idx)
+                        {
+                            layer.set(pos, None);
+                        }
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn respawn_broken_tiles(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    map: Res<SpawnedMapMeta>,
+    mut broken_tiles: ResMut<BrokenTiles>,
+    player_indexes: Comp<PlayerIdx>,
+    transforms: Comp<Transform>,
+    bodies: Comp<KinematicBody>,
+) {
+    let player_rects = entities
+        .iter_with((&player_indexes, &transforms, &bodies))
+        .map(|(_ent, (_idx, transform, body))| body.bounding_box(*transform))
+        .collect::<Vec<_>>();
+
+    broken_tiles.0.retain_mut(|broken| {
+        if broken.frames_left > 0 {
+            broken.frames_left -= 1;
+            return true;
+        }
+
+        // Wait for players to get out of the way, so they don't get stuck in the tile
+        let rect = tile_rect(map.tile_size, broken.pos);
+        if player_rects.iter().any(|player| player.overlaps(&rect)) {
+            return true;
+        }
+
+        let BrokenTile {
+            layer_idx,
+            pos,
+            idx,
+            ..
+        } = *broken;
+        commands.add(
+            move |mut entities: ResMut<Entities>,
+                  mut tile_layers: CompMut<TileLayer>,
+                  mut tiles: CompMut<Tile>,
+                  mut tile_collisions: CompMut<TileCollisionKind>,
+                  spawned_map_layer_metas: Comp<SpawnedMapLayerMeta>| {
+                let Some(layer_ent) = entities
+                    .iter_with(&spawned_map_layer_metas)
+                    .find(|(ent, meta)| meta.layer_idx == layer_idx && tile_layers.contains(*ent))
+                    .map(|(ent, _)| ent) else {
+                    return;
+                };
+                // Something else, like the editor, may have put a tile here in the meantime
+                if tile_layers.get(layer_ent).unwrap().get(pos).is_some() {
+                    return;
+                }
+
+                let tile_ent = entities.create();
+                tiles.insert(tile_ent, Tile { idx, ..default() });
+                tile_collisions.insert(tile_ent, TileCollisionKind::Breakable);
+                tile_layers
+                    .get_mut(layer_ent)
+                    .unwrap()
+                    .set(pos, Some(tile_ent));
+            },
+        );
+        commands.add(move |mut collision_world: CollisionWorld| {
+            collision_world.update_tile(layer_idx, pos);
+        });
+
+        false
+    });
+}
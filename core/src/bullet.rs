@@ -1,7 +1,8 @@
 //! Universal bullet implementation.
 
 use crate::{
-    physics::collisions::{Actor, Collider, TileCollisionKind},
+    elements::water_volume::WaterVolume,
+    physics::collisions::{Actor, Collider},
     prelude::*,
 };
 
@@ -10,6 +11,34 @@ pub fn install(session: &mut CoreSession) {
         .stages
         .add_system_to_stage(CoreStage::PreUpdate, hydrate)
         .add_system_to_stage(CoreStage::PostUpdate, update);
+
+    register_pooled_components(&mut session.pooled_components);
+
+    session.inspectors.register::<Bullet>("Bullet");
+    session.inspectors.register::<BulletSpeed>("BulletSpeed");
+    session
+        .inspectors
+        .register::<BulletWaterDistance>("BulletWaterDistance");
+    session
+        .inspectors
+        .register::<BulletBounces>("BulletBounces");
+    session
+        .inspectors
+        .register::<BulletPenetration>("BulletPenetration");
+}
+
+/// Register the components added to bullets, which are pooled so that rapid-fire weapons don't
+/// create and kill entities for every shot.
+fn register_pooled_components(pooled: &mut PooledComponents) {
+    pooled.register::<Bullet>();
+    pooled.register::<BulletHandle>();
+    pooled.register::<BulletHoming>();
+    pooled.register::<BulletSpeed>();
+    pooled.register::<BulletWaterDistance>();
+    pooled.register::<BulletBounces>();
+    pooled.register::<BulletPenetration>();
+    pooled.register::<Actor>();
+    pooled.register::<Collider>();
 }
 
 #[derive(Clone, Debug, TypeUlid, Copy)]
@@ -19,6 +48,109 @@ pub struct Bullet {
     pub owner: Entity,
 }
 
+impl Inspect for Bullet {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.number("direction", &mut self.direction);
+        fields.text("owner", format!("{:?}", self.owner));
+    }
+}
+
+/// Component that makes a bullet home in on the nearest player in front of it.
+///
+/// This may be added to weapon metadata in a `homing:` block to make the weapon's bullets homing.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H3117CYZFPXGJ5RP0GBE92RX"]
+#[serde(deny_unknown_fields)]
+pub struct BulletHoming {
+    /// The maximum number of degrees per second that the bullet can turn towards its target.
+    pub turn_rate_degrees: f32,
+    /// How close a player has to be for the bullet to lock on to them.
+    pub acquisition_range: f32,
+    /// The player the bullet is homing in on.
+    #[serde(skip)]
+    pub target: HomingTarget,
+}
+
+/// The target of a [`BulletHoming`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HomingTarget {
+    /// The bullet is looking for a player to lock on to.
+    #[default]
+    Searching,
+    /// The bullet is turning towards the given player.
+    Locked(Entity),
+    /// The player the bullet was locked on to died, so it flies straight from now on.
+    Lost,
+}
+
+impl BonesBevyAssetLoad for HomingTarget {}
+
+/// Component that overrides the speed of a bullet's [`BulletMeta::velocity`], for example when it
+/// was fired from a charged up [`ItemCharge`].
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H1GZHS7483CXMNY3BRGYF2QJ"]
+pub struct BulletSpeed(pub f32);
+
+impl Inspect for BulletSpeed {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.number("speed", &mut self.0);
+    }
+}
+
+/// Component containing how far a bullet has travelled under water.
+#[derive(Clone, Copy, Debug, TypeUlid, Default)]
+#[ulid = "01H3NQ69F5B2QX7EXE6TMR9ETW"]
+pub struct BulletWaterDistance(pub f32);
+
+impl Inspect for BulletWaterDistance {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.number("distance", &mut self.0);
+    }
+}
+
+/// Component tracking how many more times a bullet can ricochet off of solid tiles.
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H4C2M7D1A9V3B8FX0TQW5K6E"]
+pub struct BulletBounces {
+    pub remaining: u32,
+    /// Multiplied with the bullet's velocity, to flip it on the axes it has bounced off of and
+    /// slow it down.
+    pub velocity_factor: Vec2,
+}
+
+impl Inspect for BulletBounces {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("remaining", self.remaining);
+        fields.vec2("velocity_factor", &mut self.velocity_factor);
+    }
+}
+
+impl BulletBounces {
+    pub fn new(bounces: u32) -> Self {
+        Self {
+            remaining: bounces,
+            velocity_factor: Vec2::ONE,
+        }
+    }
+}
+
+/// Component tracking how many more players a bullet can pass through.
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H4C2MFJ8N2S6ZQ1E4R7YHX3C"]
+pub struct BulletPenetration {
+    pub remaining: u32,
+    /// The players the bullet has already hit, so that they aren't hit again while the bullet
+    /// passes through them.
+    pub hit_players: Vec<Entity>,
+}
+
+impl Inspect for BulletPenetration {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("remaining", self.remaining);
+        fields.text("hit_players", format!("{:?}", self.hit_players));
+    }
+}
+
 /// Component containing the bullet's metadata handle.
 #[derive(Deref, DerefMut, TypeUlid, Clone)]
 #[ulid = "01GR1WH27X84VX22G0JY9J71PC"]
@@ -30,6 +162,8 @@ fn hydrate(
     mut colliders: CompMut<Collider>,
     mut lifetimes: CompMut<Lifetime>,
     mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bounces: CompMut<BulletBounces>,
+    mut penetrations: CompMut<BulletPenetration>,
     bullet_assets: BevyAssets<BulletMeta>,
     bullet_handles: Comp<BulletHandle>,
 ) {
@@ -70,6 +204,14 @@ fn hydrate(
         );
 
         lifetimes.insert(entity, Lifetime::new(bullet_meta.lifetime));
+        bounces.insert(entity, BulletBounces::new(bullet_meta.bounces));
+        penetrations.insert(
+            entity,
+            BulletPenetration {
+                remaining: bullet_meta.penetration,
+                hit_players: default(),
+            },
+        );
     }
 }
 
@@ -83,10 +225,31 @@ fn update(
     collision_world: CollisionWorld,
     mut transforms: CompMut<Transform>,
     mut bullets: CompMut<Bullet>,
-    mut audio_events: ResMut<AudioEvents>,
+    mut homings: CompMut<BulletHoming>,
+    players_killed: Comp<PlayerKilled>,
+    mut audio_events: ResMut<SoundEvents>,
+    mut tile_damage: ResMut<TileDamageQueue>,
+    core_meta: Res<CoreMetaArc>,
     invincibles: CompMut<Invincibility>,
+    water_volumes: Comp<WaterVolume>,
+    mut water_distances: CompMut<BulletWaterDistance>,
+    bullet_speeds: Comp<BulletSpeed>,
+    mut bounces: CompMut<BulletBounces>,
+    mut penetrations: CompMut<BulletPenetration>,
+    mut camera_trauma: ResMut<CameraTrauma>,
+    pool: Res<EntityPool>,
+    inventories: Comp<Inventory>,
 ) {
+    let water_rects = entities
+        .iter_with((&water_volumes, &transforms))
+        .map(|(_ent, (water, transform))| (water.rect(transform.translation), water))
+        .collect::<Vec<_>>();
+
     for (entity, (bullet, bullet_handle)) in entities.iter_with((&mut bullets, &bullet_handles)) {
+        // Skip bullets that have already been returned to the pool this frame
+        if pool.is_released(entity) {
+            continue;
+        }
         let Some(bullet_meta) = bullet_assets.get(&bullet_handle.get_bevy_handle()) else {
             continue;
         };
@@ -100,40 +263,160 @@ fn update(
             explosion_atlas,
             explosion_frames,
             explosion_lifetime,
+            explosion_trauma,
+            explosion_particles,
+            bounce_speed_loss,
             ..
         } = bullet_meta;
 
-        // Move bullet
-        let position = {
-            let position = transforms.get_mut(entity).unwrap();
-            position.translation += bullet.direction * velocity.extend(0.0);
-            *position
+        let velocity = match bullet_speeds.get(entity) {
+            Some(speed) => velocity.normalize_or_zero() * speed.0,
+            None => *velocity,
+        };
+        let mut velocity = bullet.direction * velocity;
+        if let Some(homing) = homings.get_mut(entity) {
+            let is_valid_target = |player: Entity| {
+                player != bullet.owner
+                    && entities.is_alive(player)
+                    && player_indexes.contains(player)
+                    && !players_killed.contains(player)
+            };
+            let transform = transforms.get(entity).unwrap();
+            let position = transform.translation.xy();
+            let angle = transform.rotation.to_euler(glam::EulerRot::XYZ).2;
+            velocity = Vec2::from_angle(angle).rotate(velocity);
+
+            homing.target = match homing.target {
+                HomingTarget::Searching => {
+                    // Lock on to the nearest player in front of the bullet, using the entity index
+                    // to break ties so that every client picks the same one.
+                    entities
+                        .iter_with((&player_indexes, &transforms))
+                        .map(|(player, (_idx, transform))| {
+                            (player, transform.translation.xy() - position)
+                        })
+                        .filter(|(player, offset)| {
+                            is_valid_target(*player)
+                                && offset.length() <= homing.acquisition_range
+                                && offset.dot(velocity) > 0.0
+                        })
+                        .min_by(|(a, a_offset), (b, b_offset)| {
+                            a_offset
+                                .length_squared()
+                                .total_cmp(&b_offset.length_squared())
+                                .then(a.index().cmp(&b.index()))
+                        })
+                        .map(|(player, _)| HomingTarget::Locked(player))
+                        .unwrap_or(HomingTarget::Searching)
+                }
+                HomingTarget::Locked(player) if !is_valid_target(player) => HomingTarget::Lost,
+                target => target,
+            };
+
+            // Turn towards the target
+            if let HomingTarget::Locked(player) = homing.target {
+                let offset = transforms.get(player).unwrap().translation.xy() - position;
+                let max_turn = homing.turn_rate_degrees.to_radians() / crate::FPS;
+                let turn = velocity.angle_between(offset).clamp(-max_turn, max_turn);
+                velocity = Vec2::from_angle(turn).rotate(velocity);
+                transforms.get_mut(entity).unwrap().rotation = Quat::from_rotation_z(angle + turn);
+            }
+        }
+
+        // Water slows bullets down, and stops them after a short distance
+        let bullet_pos = transforms.get(entity).unwrap().translation.xy();
+        if let Some((_, water)) = water_rects
+            .iter()
+            .find(|(rect, _)| rect.contains(bullet_pos))
+        {
+            velocity *= water.bullet_speed_multiplier;
+
+            let distance = water_distances.get(entity).copied().unwrap_or_default().0;
+            let distance = distance + velocity.length();
+            if distance > water.bullet_range {
+                commands.add(move |mut pool: ResMut<EntityPool>| {
+                    pool.release(entity);
+                });
+                continue;
+            }
+            water_distances.insert(entity, BulletWaterDistance(distance));
+        }
+
+        // Move bullet, ricocheting off of solid tiles if it has bounces left
+        let shape = ColliderShape::Circle {
+            diameter: *body_diameter,
+        };
+        let (position, hit_solid) = {
+            let transform = transforms.get_mut(entity).unwrap();
+            let mut bullet_bounces = bounces
+                .get(entity)
+                .copied()
+                .unwrap_or_else(|| BulletBounces::new(0));
+            let (new_position, hit_solid) = move_bullet(
+                transform.translation.xy(),
+                velocity,
+                &mut bullet_bounces,
+                *body_diameter / 2.0,
+                *bounce_speed_loss,
+                |pos| {
+                    let mut test_transform = *transform;
+                    test_transform.translation = pos.extend(transform.translation.z);
+                    collision_world
+                        .tile_collision(test_transform, shape)
+                        .is_solid()
+                },
+            );
+            bounces.insert(entity, bullet_bounces);
+            transform.translation = new_position.extend(transform.translation.z);
+            (*transform, hit_solid)
         };
 
-        // Check actor collisions
+        // Check actor collisions, passing through players while the bullet has penetration left
         let mut hit_player = false;
-        collision_world
-            .actor_collisions_filtered(entity, |e| {
-                player_indexes.contains(e) && invincibles.get(e).is_none()
-            })
-            .into_iter()
-            .filter(|player| *player != bullet.owner)
-            .for_each(|player| {
+        let penetration = penetrations.get_mut(entity);
+        let mut no_penetration = BulletPenetration::default();
+        let penetration = penetration.unwrap_or(&mut no_penetration);
+        for player in collision_world.actor_collisions_filtered(entity, |e| {
+            player_indexes.contains(e) && invincibles.get(e).is_none()
+        }) {
+            if player == bullet.owner || penetration.hit_players.contains(&player) {
+                continue;
+            }
+            penetration.hit_players.push(player);
+            // The bullet was fired by the item the owner is holding
+            let weapon = inventories
+                .get(bullet.owner)
+                .and_then(|inventory| inventory.0);
+            commands.add(PlayerCommand::kill_by(
+                player,
+                Some(position.translation.xy()),
+                bullet.owner,
+                weapon,
+            ));
+            if penetration.remaining == 0 {
                 hit_player = true;
-                commands.add(PlayerCommand::kill(player, Some(position.translation.xy())));
-            });
-
-        // check solid tile collisions
-        let hit_solid = collision_world.tile_collision(
-            position,
-            ColliderShape::Circle {
-                diameter: *body_diameter,
-            },
-        ) == TileCollisionKind::Solid;
+                break;
+            }
+            penetration.remaining -= 1;
+        }
+
+        // Damage the solid tile the bullet hit
+        if hit_solid {
+            tile_damage.damage(
+                Rect::new(
+                    position.translation.x,
+                    position.translation.y,
+                    *body_diameter,
+                    *body_diameter,
+                ),
+                core_meta.breakable_tiles.bullet_damage,
+            );
+        }
 
         // Bullet hit something
         if hit_player || hit_solid {
-            audio_events.play(explosion_sound.clone(), *explosion_volume);
+            audio_events.play_from(explosion_sound.clone(), *explosion_volume, Some(&position));
+            camera_trauma.add(*explosion_trauma);
 
             let mut explosion_transform = *transforms.get(entity).unwrap();
             explosion_transform.translation.z += 1.0;
@@ -143,18 +426,28 @@ fn update(
             let explosion_lifetime = *explosion_lifetime;
             let explosion_atlas = explosion_atlas.clone();
 
+            if let Some(particles) = explosion_particles {
+                commands.add(spawn_particle_effect(
+                    particles.clone(),
+                    explosion_transform,
+                    false,
+                ));
+            }
+
             commands.add(
                 move |mut entities: ResMut<Entities>,
+                      mut pool: ResMut<EntityPool>,
+                      mut pooled: CompMut<Pooled>,
                       mut transforms: CompMut<Transform>,
                       mut lifetimes: CompMut<Lifetime>,
                       mut sprites: CompMut<AtlasSprite>,
                       mut animated_sprites: CompMut<AnimatedSprite>| {
                     // Despawn the bullet
-                    entities.kill(entity);
+                    pool.release(entity);
 
                     // spawn bullet explosion animation
                     {
-                        let ent = entities.create();
+                        let ent = pool.spawn(&mut entities, &mut pooled);
                         transforms.insert(ent, explosion_transform);
                         sprites.insert(
                             ent,
@@ -180,3 +473,178 @@ fn update(
         }
     }
 }
+
+/// Move a bullet by its `velocity` for one frame, in steps no bigger than `step_size` so that it
+/// can't tunnel through tiles.
+///
+/// When the bullet runs into a solid tile and has bounces left, its velocity is reflected on the
+/// axis that it collided on, and it loses `speed_loss` of its speed. Returns the bullet's new
+/// position, and whether it hit a solid tile without any bounces left.
+fn move_bullet(
+    mut position: Vec2,
+    velocity: Vec2,
+    bounces: &mut BulletBounces,
+    step_size: f32,
+    speed_loss: f32,
+    is_solid: impl Fn(Vec2) -> bool,
+) -> (Vec2, bool) {
+    let velocity = velocity * bounces.velocity_factor;
+    let steps = (velocity.abs().max_element() / step_size.max(1.0))
+        .ceil()
+        .max(1.0);
+    let mut step = velocity / steps;
+    let speed_kept = (1.0 - speed_loss).clamp(0.0, 1.0);
+
+    for _ in 0..steps as usize {
+        // Move on each axis separately, so we know which side of the tile was hit
+        for axis in 0..2 {
+            position[axis] += step[axis];
+            if !is_solid(position) {
+                continue;
+            }
+            if bounces.remaining == 0 {
+                return (position, true);
+            }
+
+            position[axis] -= step[axis];
+            bounces.remaining -= 1;
+            step[axis] = -step[axis];
+            step *= speed_kept;
+            bounces.velocity_factor[axis] = -bounces.velocity_factor[axis];
+            bounces.velocity_factor *= speed_kept;
+        }
+    }
+
+    (position, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE_SIZE: f32 = 16.0;
+
+    /// A vertical shaft three tiles wide, with a floor below it.
+    fn shaft_is_solid(pos: Vec2) -> bool {
+        pos.x < 0.0 || pos.x >= TILE_SIZE * 3.0 || pos.y < 0.0
+    }
+
+    #[test]
+    fn bullet_ricochets_between_shaft_walls() {
+        let mut bounces = BulletBounces::new(3);
+        let mut position = Vec2::new(TILE_SIZE * 1.5, 200.0);
+        let velocity = Vec2::new(10.0, 0.0);
+
+        let mut hit_solid = false;
+        for _ in 0..100 {
+            (position, hit_solid) =
+                move_bullet(position, velocity, &mut bounces, 4.0, 0.0, shaft_is_solid);
+            if hit_solid {
+                break;
+            }
+            assert!(
+                !shaft_is_solid(position),
+                "Bullet left the shaft at {position}"
+            );
+        }
+
+        assert!(
+            hit_solid,
+            "Bullet should hit a wall once it runs out of bounces"
+        );
+        assert_eq!(bounces.remaining, 0);
+        assert_eq!(position.y, 200.0);
+    }
+
+    #[test]
+    fn fast_bullet_does_not_tunnel_through_shaft_walls() {
+        let mut bounces = BulletBounces::new(1);
+        let position = Vec2::new(TILE_SIZE * 1.5, 200.0);
+        // Fast enough to cross the whole shaft in one frame
+        let velocity = Vec2::new(TILE_SIZE * 4.0, 0.0);
+
+        let (position, hit_solid) =
+            move_bullet(position, velocity, &mut bounces, 4.0, 0.0, shaft_is_solid);
+
+        assert!(!hit_solid);
+        assert!(!shaft_is_solid(position), "Bullet tunneled to {position}");
+        assert_eq!(bounces.remaining, 0);
+        assert_eq!(bounces.velocity_factor, Vec2::new(-1.0, 1.0));
+    }
+
+    #[test]
+    fn diagonal_bounces_lose_speed() {
+        let mut bounces = BulletBounces::new(10);
+        let mut position = Vec2::new(TILE_SIZE * 1.5, 100.0);
+        let velocity = Vec2::new(12.0, -6.0);
+
+        for _ in 0..30 {
+            let (new_position, hit_solid) =
+                move_bullet(position, velocity, &mut bounces, 4.0, 0.25, shaft_is_solid);
+            assert!(!hit_solid);
+            assert!(!shaft_is_solid(new_position));
+            position = new_position;
+        }
+
+        let bounced = 10 - bounces.remaining;
+        assert!(bounced >= 2, "Bullet only bounced {bounced} times");
+        let expected_speed = 0.75f32.powi(bounced as i32);
+        assert!((bounces.velocity_factor.x.abs() - expected_speed).abs() < 1e-4);
+        assert!((bounces.velocity_factor.y.abs() - expected_speed).abs() < 1e-4);
+    }
+
+    #[test]
+    fn reused_bouncing_bullet_does_not_bounce() {
+        let mut world = World::new();
+        let mut pooled_components = PooledComponents::default();
+        register_pooled_components(&mut pooled_components);
+
+        // Fire a bouncing bullet, and return it to the pool
+        let bouncing = world
+            .run_initialized_system(
+                |mut entities: ResMut<Entities>,
+                 mut pool: ResMut<EntityPool>,
+                 mut pooled: CompMut<Pooled>,
+                 mut bounces: CompMut<BulletBounces>| {
+                    let entity = pool.spawn(&mut entities, &mut pooled);
+                    bounces.insert(entity, BulletBounces::new(3));
+                    pool.release(entity);
+                    Ok(entity)
+                },
+            )
+            .unwrap();
+        crate::pool::reset_released(&world, &pooled_components);
+        world.maintain();
+
+        // Fire a straight bullet, which reuses the same entity
+        let (straight, mut bullet_bounces) = world
+            .run_initialized_system(
+                |mut entities: ResMut<Entities>,
+                 mut pool: ResMut<EntityPool>,
+                 mut pooled: CompMut<Pooled>,
+                 bounces: Comp<BulletBounces>| {
+                    assert_eq!(pool.free_count(), 1);
+                    let entity = pool.spawn(&mut entities, &mut pooled);
+                    Ok((
+                        entity,
+                        bounces
+                            .get(entity)
+                            .copied()
+                            .unwrap_or_else(|| BulletBounces::new(0)),
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(straight, bouncing);
+
+        let (_, hit_solid) = move_bullet(
+            Vec2::new(TILE_SIZE * 2.5, 200.0),
+            Vec2::new(10.0, 0.0),
+            &mut bullet_bounces,
+            4.0,
+            0.0,
+            shaft_is_solid,
+        );
+        assert!(hit_solid, "Reused bullet bounced off of the wall");
+    }
+}
@@ -1,11 +1,17 @@
-//! Camera controller and parallax.
+//! Camera controller, camera shake, and parallax.
 
-use crate::prelude::*;
+use crate::{prelude::*, random::GlobalRng};
 
 pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<CameraTrauma>();
+    session.world.init_resource::<HitPause>();
+    session.world.init_resource::<KillCam>();
     session
         .stages
-        .add_system_to_stage(CoreStage::Last, camera_controller);
+        .add_system_to_stage(CoreStage::Last, update_spectators)
+        .add_system_to_stage(CoreStage::Last, start_kill_cam)
+        .add_system_to_stage(CoreStage::Last, camera_controller)
+        .add_system_to_stage(CoreStage::Last, apply_camera_trauma);
     session
         .stages
         .add_system_to_stage(CoreStage::Last, camera_parallax);
@@ -14,7 +20,10 @@ pub fn install(session: &mut CoreSession) {
 #[derive(Clone, TypeUlid)]
 #[ulid = "01GPP1V3PCENFWC8H6H705ST80"]
 pub struct ParallaxBackgroundSprite {
+    /// The index of this sprite in the horizontal repetition of the layer's image.
     pub idx: i32,
+    /// The index of the layer in the map's background layers.
+    pub layer_idx: usize,
     pub meta: ParallaxLayerMeta,
 }
 
@@ -25,6 +34,277 @@ pub struct CameraState {
     /// Disables the default camera controller. Useful, for example, when taking over the camera
     /// from the editor.
     pub disable_controller: bool,
+    /// The point the camera is following, which only moves when the players leave the
+    /// [deadzone][CameraMeta::deadzone] around it.
+    pub target: Option<Vec2>,
+    /// The rectangle the camera is moving to show, after look-ahead and bounds are applied.
+    pub target_frame: Rect,
+    /// The velocity of the camera, used for critically damped smoothing.
+    pub velocity: Vec2,
+}
+
+/// How many frames it takes the camera shake to move to its next random offset.
+const SHAKE_NOISE_FRAMES: u32 = 4;
+
+/// Resource that systems can add trauma to, to shake the camera.
+///
+/// The shake is as strong as the square of the trauma, which decays over time according to the
+/// [`CameraMeta`]. The shake is made from random offsets taken from the [`GlobalRng`], so it is
+/// deterministic and rolls back with the rest of the game.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H1XBWX8WEFBC5Q4Z0AS5P0PT"]
+pub struct CameraTrauma {
+    /// The current trauma, from `0.0` to `1.0`.
+    pub trauma: f32,
+    /// Whether the shake is applied to the camera.
+    ///
+    /// This is set from the local player's settings. The shake is still simulated when it is
+    /// disabled, so that the random numbers used stay the same for every player in a network game.
+    pub shake_enabled: bool,
+    /// The shake offset that was added to the camera last frame.
+    applied_offset: Vec2,
+    /// The random offset the shake is moving from.
+    noise_from: Vec2,
+    /// The random offset the shake is moving to.
+    noise_to: Vec2,
+    /// How many frames the shake has been moving to `noise_to`.
+    noise_frame: u32,
+}
+
+impl Default for CameraTrauma {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            shake_enabled: true,
+            applied_offset: Vec2::ZERO,
+            noise_from: Vec2::ZERO,
+            noise_to: Vec2::ZERO,
+            noise_frame: 0,
+        }
+    }
+}
+
+impl CameraTrauma {
+    /// Add trauma, up to a maximum of `1.0`.
+    pub fn add(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+}
+
+/// Resource used to pause the whole game simulation for a few frames, such as when a player is
+/// killed, to make the hit feel heavier.
+///
+/// While paused, [`CoreSession::advance`] skips running the session's stages.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H1XBWX8W3CY4KGY4BQ3Z8M6E"]
+pub struct HitPause {
+    /// How many more frames the game stays paused.
+    pub remaining_frames: u32,
+}
+
+impl HitPause {
+    /// Pause the game for the given number of frames, unless it is already paused for longer.
+    pub fn start(&mut self, frames: u32) {
+        self.remaining_frames = self.remaining_frames.max(frames);
+    }
+
+    /// Count down the pause by one frame, returning whether the game is paused this frame.
+    pub fn tick(&mut self) -> bool {
+        if self.remaining_frames > 0 {
+            self.remaining_frames -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Resource that plays the game in slow motion, with the camera zoomed in, after the kill that
+/// ends a match.
+///
+/// While the kill cam plays, [`CoreSession::advance`] only runs the session's stages once every
+/// [`kill_cam_step_interval`][CameraMeta::kill_cam_step_interval] frames. Like a [`HitPause`], it
+/// is counted in frames of the session, so every player of a network game sees the same kill cam.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H5KC4M8Q2ZJ7T3V9WXRB6NDA"]
+pub struct KillCam {
+    /// Whether the kill cam is played when a match ends.
+    ///
+    /// This is set from the local player's settings, and is always on in network games.
+    pub enabled: bool,
+    /// How many more frames the kill cam plays for.
+    pub remaining_frames: u32,
+    /// How many frames it takes to simulate a single frame of the game.
+    pub step_interval: u32,
+    /// The point the camera zooms in on, between the killer and the victim.
+    pub focus: Vec2,
+    /// The number of kills in the [`MatchScore`] last frame.
+    kill_count: usize,
+    /// Whether a player was killed last frame.
+    killed_last_frame: bool,
+    /// Whether the match had a result last frame.
+    had_result: bool,
+}
+
+impl Default for KillCam {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            remaining_frames: 0,
+            step_interval: 1,
+            focus: Vec2::ZERO,
+            kill_count: 0,
+            killed_last_frame: false,
+            had_result: false,
+        }
+    }
+}
+
+impl KillCam {
+    /// Whether the kill cam is playing.
+    pub fn is_playing(&self) -> bool {
+        self.remaining_frames > 0
+    }
+
+    /// Count down the kill cam by one frame, returning whether the frame is skipped to slow the
+    /// game down.
+    pub fn tick(&mut self) -> bool {
+        if self.remaining_frames > 0 {
+            self.remaining_frames -= 1;
+            self.remaining_frames % self.step_interval.max(1) != 0
+        } else {
+            false
+        }
+    }
+}
+
+/// Component for an entity that tracks an active player who doesn't currently have a fish in the
+/// game, either because they are waiting to respawn or because they have been eliminated.
+///
+/// While spectating, the player's shoot and grab inputs cycle forward and backward through the
+/// living players. Once every human player is spectating, the camera only frames the players that
+/// they are watching.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H1R6RG11ZXB8SCE4JZXQG2A1"]
+pub struct Spectating {
+    /// The index of the player that is spectating.
+    pub player: usize,
+    /// The player entity being watched, or [`None`] if there is nobody left alive to watch.
+    pub target: Option<Entity>,
+}
+
+/// System that creates, updates, and removes [`Spectating`] entities for players without a living
+/// fish.
+fn update_spectators(
+    mut entities: ResMut<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    mut spectators: CompMut<Spectating>,
+) {
+    // Collect the living players, sorted by player index so that cycling through them is stable.
+    let mut living_players = entities
+        .iter_with(&player_indexes)
+        .filter(|(ent, _)| !players_killed.contains(*ent))
+        .map(|(ent, idx)| (idx.0, ent))
+        .collect::<Vec<_>>();
+    living_players.sort_by_key(|(idx, _)| *idx);
+
+    let mut spectator_ents = [None; MAX_PLAYERS];
+    for (ent, spectating) in entities.iter_with(&spectators) {
+        spectator_ents[spectating.player] = Some(ent);
+    }
+
+    for (player_idx, input) in player_inputs.players.iter().enumerate() {
+        let is_alive = living_players.iter().any(|(idx, _)| *idx == player_idx);
+        let should_spectate = input.active && !is_alive;
+
+        let spectator_ent = match (should_spectate, spectator_ents[player_idx]) {
+            (true, Some(ent)) => ent,
+            (true, None) => {
+                let ent = entities.create();
+                spectators.insert(
+                    ent,
+                    Spectating {
+                        player: player_idx,
+                        target: None,
+                    },
+                );
+                ent
+            }
+            (false, Some(ent)) => {
+                entities.kill(ent);
+                continue;
+            }
+            (false, None) => continue,
+        };
+        let spectating = spectators.get_mut(spectator_ent).unwrap();
+
+        if living_players.is_empty() {
+            spectating.target = None;
+            continue;
+        }
+
+        let count = living_players.len();
+        let current = spectating
+            .target
+            .and_then(|target| living_players.iter().position(|(_, ent)| *ent == target));
+        let next = match current {
+            // Start watching the first living player if we weren't watching anybody, or the player
+            // we were watching died.
+            None => 0,
+            Some(i) if input.control.shoot_just_pressed => (i + 1) % count,
+            Some(i) if input.control.grab_just_pressed => (i + count - 1) % count,
+            Some(i) => i,
+        };
+        spectating.target = Some(living_players[next].1);
+    }
+}
+
+/// System that starts the [`KillCam`] when a kill ends the match with a winner.
+///
+/// Kills are counted in the [`MatchScore`] a frame before the game mode decides the match is over,
+/// so a kill in either frame can start it. Draws and timeouts don't get a kill cam.
+fn start_kill_cam(
+    game_meta: Res<CoreMetaArc>,
+    entities: Res<Entities>,
+    score: Res<MatchScore>,
+    mut kill_cam: ResMut<KillCam>,
+    player_indexes: Comp<PlayerIdx>,
+    transforms: Comp<Transform>,
+) {
+    let meta = &game_meta.camera;
+
+    let killed = score.kills.len() > kill_cam.kill_count;
+    let recent_kill = killed || kill_cam.killed_last_frame;
+    kill_cam.kill_count = score.kills.len();
+    kill_cam.killed_last_frame = killed;
+
+    let match_ended = score.result.is_some() && !kill_cam.had_result;
+    kill_cam.had_result = score.result.is_some();
+
+    if !match_ended || !recent_kill || !kill_cam.enabled || meta.kill_cam_frames == 0 {
+        return;
+    }
+    let (Some(MatchResult::Winner(_)), Some(kill)) = (score.result, score.kills.last()) else {
+        return;
+    };
+
+    let position = |idx: usize| {
+        entities
+            .iter_with((&player_indexes, &transforms))
+            .find(|(_, (player_idx, _))| player_idx.0 == idx)
+            .map(|(_, (_, transform))| transform.translation.truncate())
+    };
+    let focus = match (position(kill.victim), kill.killer.and_then(position)) {
+        (Some(victim), Some(killer)) => (victim + killer) / 2.0,
+        (Some(pos), None) | (None, Some(pos)) => pos,
+        (None, None) => return,
+    };
+
+    kill_cam.focus = focus;
+    kill_cam.remaining_frames = meta.kill_cam_frames;
+    kill_cam.step_interval = meta.kill_cam_step_interval;
 }
 
 fn camera_controller(
@@ -37,7 +317,12 @@ fn camera_controller(
     transforms: Comp<Transform>,
     player_indexes: Comp<PlayerIdx>,
     bodies: Comp<KinematicBody>,
+    spectators: Comp<Spectating>,
+    player_inputs: Res<PlayerInputs>,
     window: Res<Window>,
+    camera_trauma: Res<CameraTrauma>,
+    sudden_death: Res<SuddenDeath>,
+    kill_cam: Res<KillCam>,
 ) {
     let meta = &game_meta.camera;
 
@@ -48,10 +333,16 @@ fn camera_controller(
         return;
     }
 
+    // Take the shake back out of the camera position, so it doesn't affect the camera movement.
+    camera_shake.center -= camera_trauma.applied_offset.extend(0.0);
+
     // Update player camera rects
+    let mut player_velocities = [Vec2::ZERO; MAX_PLAYERS];
     for (_ent, (transform, player_idx, body)) in
         entities.iter_with((&transforms, &player_indexes, &bodies))
     {
+        player_velocities[player_idx.0] = body.velocity;
+
         let camera_box_size = meta.player_camera_box_size;
 
         // Get the player's camera box
@@ -103,14 +394,36 @@ fn camera_controller(
     let mut min = Vec2::new(f32::MAX, f32::MAX);
     let mut max = Vec2::new(f32::MIN, f32::MIN);
 
-    let players: Vec<usize> = entities
+    let mut players: Vec<usize> = entities
         .iter_with(&player_indexes)
         .map(|x| x.1 .0)
         .collect();
+
+    // Once every human player is spectating, only frame the players that they are watching
+    let spectated = entities
+        .iter_with(&spectators)
+        .filter_map(|(_, spectating)| spectating.target.and_then(|x| player_indexes.get(x)))
+        .map(|x| x.0)
+        .collect::<Vec<_>>();
+    let all_spectating = player_inputs
+        .players
+        .iter()
+        .enumerate()
+        .filter(|(_, input)| input.active && !input.is_ai)
+        .all(|(player_idx, _)| {
+            entities
+                .iter_with(&spectators)
+                .any(|(_, spectating)| spectating.player == player_idx)
+        });
+    if all_spectating && !spectated.is_empty() {
+        players.retain(|player_idx| spectated.contains(player_idx));
+    }
     let player_count = players.len();
 
+    let mut average_velocity = Vec2::ZERO;
     for player_idx in players {
         let rect = camera_state.player_camera_rects[player_idx];
+        average_velocity += player_velocities[player_idx] / player_count as f32;
 
         min = (rect.min - vec2(meta.border_left, meta.border_bottom))
             .min(min)
@@ -124,7 +437,15 @@ fn camera_controller(
     let mut middle_point = if player_count == 0 {
         camera_pos.truncate()
     } else {
-        Rect { min, max }.center()
+        // Only move the target once the players leave the deadzone around it
+        let target = camera_state
+            .target
+            .get_or_insert(Rect { min, max }.center());
+        let offset = Rect { min, max }.center() - *target;
+        let half_deadzone = meta.deadzone / 2.0;
+        *target += offset - offset.clamp(-half_deadzone, half_deadzone);
+
+        *target + average_velocity * meta.look_ahead
     };
 
     let size = max - min;
@@ -133,6 +454,16 @@ fn camera_controller(
     let rh = size.y / default_height;
     let rw = size.x / default_width;
     let r_target = if rh > rw { rh } else { rw };
+    let mut r_target = r_target.clamp(
+        meta.min_height / default_height,
+        meta.max_height / default_height,
+    );
+
+    // Zoom in on the kill that ended the match
+    if kill_cam.is_playing() {
+        middle_point = kill_cam.focus;
+        r_target = meta.kill_cam_height / default_height;
+    }
     let r_diff = r_target - scale;
     if r_diff > 0.0 {
         scale += r_diff * meta.zoom_out_lerp_factor;
@@ -145,10 +476,143 @@ fn camera_controller(
         middle_point.y = size.y / 2.0;
     }
 
-    let delta = camera_pos.truncate() - middle_point;
-    let dist = delta * meta.move_lerp_factor;
+    // Keep the view inside of the map's camera bounds, centering it on the bounds if they are too
+    // small to fill it.
+    let view_size = vec2(default_width, default_height) * scale;
+    let mut camera_bounds = map.camera_bounds;
+    // Shrink the bounds with the playable area when the sudden death border closes in
+    if sudden_death.active && game_meta.config.sudden_death.border_speed.is_some() {
+        let area = sudden_death.playable_area(map_size);
+        let bounds = camera_bounds.unwrap_or(MapCameraBoundsMeta {
+            min: Vec2::ZERO,
+            max: map_size,
+        });
+        camera_bounds = Some(MapCameraBoundsMeta {
+            min: bounds.min.max(area.min),
+            max: bounds.max.min(area.max),
+        });
+    }
+    // Don't show past the edges of the map that bodies can't leave through
+    let is_closed = |edge| {
+        matches!(
+            map.boundary.edge(edge),
+            MapEdgeBehavior::Wall | MapEdgeBehavior::Wrap
+        )
+    };
+    if MapEdge::ALL.into_iter().any(is_closed) {
+        let mut bounds = camera_bounds.unwrap_or(MapCameraBoundsMeta {
+            min: Vec2::NEG_INFINITY,
+            max: Vec2::INFINITY,
+        });
+        if is_closed(MapEdge::Left) {
+            bounds.min.x = bounds.min.x.max(0.0);
+        }
+        if is_closed(MapEdge::Right) {
+            bounds.max.x = bounds.max.x.min(map_size.x);
+        }
+        if is_closed(MapEdge::Bottom) {
+            bounds.min.y = bounds.min.y.max(0.0);
+        }
+        if is_closed(MapEdge::Top) {
+            bounds.max.y = bounds.max.y.min(map_size.y);
+        }
+        camera_bounds = Some(bounds);
+    }
+    if let Some(bounds) = camera_bounds {
+        let half_view = view_size / 2.0;
+        for axis in 0..2 {
+            let (low, high) = (
+                bounds.min[axis] + half_view[axis],
+                bounds.max[axis] - half_view[axis],
+            );
+            middle_point[axis] = if low > high {
+                (bounds.min[axis] + bounds.max[axis]) / 2.0
+            } else {
+                middle_point[axis].clamp(low, high)
+            };
+        }
+    }
+    camera_state.target_frame = Rect::new(middle_point.x, middle_point.y, view_size.x, view_size.y);
+
     camera.height = scale * default_height;
-    *camera_pos -= dist.extend(0.0);
+    if meta.move_smooth_time > 0.0 {
+        let position = smooth_damp(
+            camera_pos.truncate(),
+            middle_point,
+            &mut camera_state.velocity,
+            meta.move_smooth_time,
+            1.0 / crate::FPS,
+        );
+        *camera_pos = position.extend(camera_pos.z);
+    } else {
+        let delta = camera_pos.truncate() - middle_point;
+        let dist = delta * meta.move_lerp_factor;
+        *camera_pos -= dist.extend(0.0);
+    }
+}
+
+/// Move `current` towards `target` with a critically damped spring, that takes about
+/// `smooth_time` seconds to get there.
+///
+/// `delta` is the fixed time step, so that the result is the same on every machine.
+fn smooth_damp(
+    current: Vec2,
+    target: Vec2,
+    velocity: &mut Vec2,
+    smooth_time: f32,
+    delta: f32,
+) -> Vec2 {
+    let omega = 2.0 / smooth_time;
+    let x = omega * delta;
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + omega * change) * delta;
+    *velocity = (*velocity - omega * temp) * decay;
+    target + (change + temp) * decay
+}
+
+/// Decay the [`CameraTrauma`] and shake the camera with it.
+fn apply_camera_trauma(
+    game_meta: Res<CoreMetaArc>,
+    entities: Res<Entities>,
+    rng: Res<GlobalRng>,
+    mut camera_trauma: ResMut<CameraTrauma>,
+    mut camera_shakes: CompMut<CameraShake>,
+    camera_states: Comp<CameraState>,
+) {
+    let meta = &game_meta.camera;
+    let trauma = &mut *camera_trauma;
+
+    let Some((_ent, (camera_shake, camera_state))) = entities
+        .iter_with((&mut camera_shakes, &camera_states))
+        .next() else {
+        return;
+    };
+    if camera_state.disable_controller || trauma.trauma <= 0.0 {
+        trauma.applied_offset = Vec2::ZERO;
+        return;
+    }
+
+    // Smoothly move between random offsets, so the shake looks like noise instead of jitter.
+    if trauma.noise_frame >= SHAKE_NOISE_FRAMES {
+        trauma.noise_from = trauma.noise_to;
+        trauma.noise_to = vec2(rng.f32_normalized(), rng.f32_normalized());
+        trauma.noise_frame = 0;
+    }
+    trauma.noise_frame += 1;
+    let t = trauma.noise_frame as f32 / SHAKE_NOISE_FRAMES as f32;
+    let t = t * t * (3.0 - 2.0 * t);
+    let noise = trauma.noise_from.lerp(trauma.noise_to, t);
+
+    let offset = noise * meta.max_shake_offset * trauma.trauma.powi(2);
+    trauma.applied_offset = if trauma.shake_enabled {
+        offset
+    } else {
+        Vec2::ZERO
+    };
+    camera_shake.center += trauma.applied_offset.extend(0.0);
+
+    trauma.trauma = (trauma.trauma - meta.trauma_decay / crate::FPS).max(0.0);
 }
 
 fn camera_parallax(
@@ -178,7 +642,7 @@ fn camera_parallax(
         let display_size = transform.scale.truncate() * bg.meta.size;
         transform.translation.x = bg.idx as f32 * display_size.x;
         transform.translation.y = map_size.y / 2.0;
-        transform.translation.z = -FAR_PLANE + 1.0 - bg.meta.depth / 100.0;
+        transform.translation.z = -FAR_PLANE + 1.0 + bg.layer_idx as f32 / 100.0;
         transform.translation += bg.meta.offset.extend(1.0);
 
         transform.translation.x -= camera_offset.x * bg.meta.depth * map.background.speed.x;
@@ -0,0 +1,77 @@
+//! Gameplay state checksums, used to detect network de-syncs.
+
+use std::hash::{Hash, Hasher};
+
+use crate::{prelude::*, random::GlobalRng};
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<FrameChecksum>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::Last, update_frame_checksum);
+}
+
+/// Resource containing a checksum of the gameplay-relevant state at the end of the last frame.
+///
+/// Two sessions that were given the same inputs should always have the same checksum. The
+/// checksum only covers the state that is most likely to visibly diverge: player transforms,
+/// kinematic body velocities, inventories, and the random number generator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, TypeUlid)]
+#[ulid = "01H2ANPWE0NRRZNRJKRYHEJ7PN"]
+pub struct FrameChecksum(pub u64);
+
+fn update_frame_checksum(
+    mut checksum: ResMut<FrameChecksum>,
+    entities: Res<Entities>,
+    rng: Res<GlobalRng>,
+    player_indexes: Comp<PlayerIdx>,
+    transforms: Comp<Transform>,
+    bodies: Comp<KinematicBody>,
+    inventories: Comp<Inventory>,
+) {
+    // The hasher must give the same hashes on every peer, whatever Rust version built it
+    let mut hasher = FnvHasher::default();
+
+    // Entities are sorted by index so that the checksum doesn't depend on iteration order.
+    let mut players = entities
+        .iter_with((&player_indexes, &transforms))
+        .map(|(ent, (idx, transform))| (ent.index(), idx.0, *transform))
+        .collect::<Vec<_>>();
+    players.sort_unstable_by_key(|(ent_idx, ..)| *ent_idx);
+    for (ent_idx, player_idx, transform) in players {
+        ent_idx.hash(&mut hasher);
+        player_idx.hash(&mut hasher);
+        hash_floats(&mut hasher, &transform.translation.to_array());
+        hash_floats(&mut hasher, &transform.rotation.to_array());
+        hash_floats(&mut hasher, &transform.scale.to_array());
+    }
+
+    let mut velocities = entities
+        .iter_with(&bodies)
+        .map(|(ent, body)| (ent.index(), body.velocity, body.angular_velocity))
+        .collect::<Vec<_>>();
+    velocities.sort_unstable_by_key(|(ent_idx, ..)| *ent_idx);
+    for (ent_idx, velocity, angular_velocity) in velocities {
+        ent_idx.hash(&mut hasher);
+        hash_floats(&mut hasher, &[velocity.x, velocity.y, angular_velocity]);
+    }
+
+    let mut held_items = entities
+        .iter_with(&inventories)
+        .map(|(ent, inventory)| (ent.index(), inventory.map(|item| item.index())))
+        .collect::<Vec<_>>();
+    held_items.sort_unstable_by_key(|(ent_idx, _)| *ent_idx);
+    held_items.hash(&mut hasher);
+
+    // Generating a number from a copy of the RNG tells us its state without advancing it.
+    GlobalRng::clone(&rng).gen_u64().hash(&mut hasher);
+
+    *checksum = FrameChecksum(hasher.finish());
+}
+
+/// Hash floats by their bit patterns, since `f32` doesn't implement [`Hash`].
+fn hash_floats(hasher: &mut impl Hasher, floats: &[f32]) {
+    for float in floats {
+        float.to_bits().hash(hasher);
+    }
+}
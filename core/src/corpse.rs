@@ -0,0 +1,130 @@
+//! The bodies that dead players leave behind.
+//!
+//! When a player's death animation is over, the player entity is despawned and replaced with a
+//! corpse. The corpse is a separate item entity that tumbles around with its own physics body, can be
+//! picked up and thrown by the other players, and is despawned after the [`CorpseMeta::lifetime`].
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, update_corpses);
+}
+
+/// Marker component for the body left behind by a dead player.
+///
+/// Corpses aren't players, so they don't block bullets or get hit by damage regions.
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H4F2N6T8KXQ3VZB9M1RW5JDC"]
+pub struct Corpse;
+
+/// Spawn the corpse of a dead player.
+///
+/// The corpse inherits the player's velocity, and is pushed away from where they were hit from.
+pub fn spawn_corpse(player: Entity) -> System {
+    (move |mut entities: ResMut<Entities>,
+           core_meta: Res<CoreMetaArc>,
+           player_indexes: Comp<PlayerIdx>,
+           player_inputs: Res<PlayerInputs>,
+           player_assets: BevyAssets<PlayerMeta>,
+           players_killed: Comp<PlayerKilled>,
+           mut corpses: CompMut<Corpse>,
+           mut items: CompMut<Item>,
+           mut item_throws: CompMut<ItemThrow>,
+           mut item_grabs: CompMut<ItemGrab>,
+           mut lifetimes: CompMut<Lifetime>,
+           mut atlas_sprites: CompMut<AtlasSprite>,
+           mut bodies: CompMut<KinematicBody>,
+           mut transforms: CompMut<Transform>| {
+        let Some(player_idx) = player_indexes.get(player) else {
+            return;
+        };
+        let player_handle = &player_inputs.players[player_idx.0].selected_player;
+        let Some(player_meta) = player_assets.get(&player_handle.get_bevy_handle()) else {
+            return;
+        };
+        let meta = &core_meta.corpse;
+        let transform = *transforms.get(player).unwrap();
+        let sprite = atlas_sprites.get(player).unwrap().clone();
+        let player_velocity = bodies.get(player).map(|x| x.velocity).unwrap_or_default();
+
+        // Push the corpse away from whatever killed it
+        let position = transform.translation.truncate();
+        let push_direction = players_killed
+            .get(player)
+            .and_then(|killed| killed.hit_from)
+            .map(|hit_from| (position - hit_from).normalize_or_zero())
+            .unwrap_or_default();
+        let spin_direction = if push_direction.x == 0.0 {
+            0.0
+        } else {
+            -push_direction.x.signum()
+        };
+
+        let corpse = entities.create();
+        corpses.insert(corpse, Corpse);
+        items.insert(corpse, Item);
+        item_throws.insert(
+            corpse,
+            ItemThrow::strength(meta.throw_velocity).with_spin(meta.angular_velocity),
+        );
+        item_grabs.insert(
+            corpse,
+            ItemGrab {
+                fin_anim: meta.fin_anim,
+                grab_offset: meta.grab_offset,
+                sync_animation: false,
+            },
+        );
+        lifetimes.insert(corpse, Lifetime::new(meta.lifetime));
+        transforms.insert(corpse, transform);
+        atlas_sprites.insert(
+            corpse,
+            AtlasSprite {
+                color: Color::WHITE,
+                ..sprite
+            },
+        );
+        bodies.insert(
+            corpse,
+            KinematicBody {
+                shape: ColliderShape::Rectangle {
+                    size: player_meta.body_size,
+                },
+                velocity: player_velocity + push_direction * meta.kill_impulse,
+                angular_velocity: spin_direction * meta.angular_velocity,
+                has_mass: true,
+                has_friction: true,
+                can_rotate: true,
+                bounciness: meta.bounciness,
+                gravity: core_meta.physics.gravity,
+                ..default()
+            },
+        );
+    })
+    .system()
+}
+
+/// Corpses can't be used, and are dropped by the player holding them when they are about to be
+/// despawned.
+fn update_corpses(
+    entities: Res<Entities>,
+    corpses: Comp<Corpse>,
+    lifetimes: Comp<Lifetime>,
+    mut items_used: CompMut<ItemUsed>,
+    mut inventories: CompMut<Inventory>,
+) {
+    for (corpse, (_corpse, lifetime)) in entities.iter_with((&corpses, &lifetimes)) {
+        items_used.remove(corpse);
+
+        // The lifetime system will despawn the corpse this frame
+        if lifetime.age + 1.0 / crate::FPS > lifetime.lifetime {
+            for (_player, inventory) in entities.iter_with(&mut inventories) {
+                if inventory.0 == Some(corpse) {
+                    inventory.0 = None;
+                }
+            }
+        }
+    }
+}
@@ -6,6 +6,9 @@ pub fn install(session: &mut CoreSession) {
     session
         .stages
         .add_system_to_stage(CoreStage::PostUpdate, kill_players_in_damage_region);
+
+    session.inspectors.register::<DamageRegion>("DamageRegion");
+    session.inspectors.register::<LastHitBy>("LastHitBy");
 }
 
 /// A rectangular damage region.
@@ -26,6 +29,12 @@ impl DamageRegion {
     }
 }
 
+impl Inspect for DamageRegion {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.vec2("size", &mut self.size);
+    }
+}
+
 /// A component that may be added to a damage region entity to indicate the triggering entity.
 ///
 /// If this entity is a player, it will not be harmed by the damage region.
@@ -33,16 +42,40 @@ impl DamageRegion {
 #[ulid = "01GP1X4NM7GMEKKZ4FEZ1RK3T0"]
 pub struct DamageRegionOwner(pub Entity);
 
+/// Component recording the last player that hit a player with an attack, like the knockback of
+/// an explosion, without killing them.
+///
+/// If the player is then killed by something in the environment, like spikes or falling off of the
+/// map, the kill is credited to the player that hit them, as long as it was within the
+/// [`kill_attribution_frames`][CoreConfigMeta::kill_attribution_frames].
+#[derive(Debug, Clone, Copy, TypeUlid)]
+#[ulid = "01H64A2RZC5T8PXKQ0W3VNGB7J"]
+pub struct LastHitBy {
+    /// The index of the player that made the hit.
+    pub player: usize,
+    /// The [`SessionFrame`] that the hit was made on.
+    pub frame: u32,
+}
+
+impl Inspect for LastHitBy {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("player", self.player);
+        fields.text("frame", self.frame);
+    }
+}
+
 /// System that will eliminate players that are intersecting with a damage region.
 fn kill_players_in_damage_region(
     entities: Res<Entities>,
     mut commands: Commands,
+    spatial_hash: Res<SpatialHash>,
     player_indexes: Comp<PlayerIdx>,
     transforms: Comp<Transform>,
     damage_regions: Comp<DamageRegion>,
     damage_region_owners: Comp<DamageRegionOwner>,
     bodies: Comp<KinematicBody>,
     invincibles: CompMut<Invincibility>,
+    inventories: Comp<Inventory>,
 ) {
     let mut bitset = player_indexes.bitset().clone();
     bitset.bit_and(transforms.bitset());
@@ -54,8 +87,12 @@ fn kill_players_in_damage_region(
         let body = bodies.get(player_ent).unwrap();
 
         let player_rect = body.bounding_box(*transform);
-        for (ent, (damage_region, transform)) in entities.iter_with((&damage_regions, &transforms))
-        {
+        for ent in spatial_hash.entities_overlapping(player_rect) {
+            let (Some(damage_region), Some(transform)) =
+                (damage_regions.get(ent), transforms.get(ent))
+            else {
+                continue;
+            };
             let owner = damage_region_owners.get(ent);
             // Don't damage the player that owns this damage region
             if let Some(owner) = owner {
@@ -64,13 +101,167 @@ fn kill_players_in_damage_region(
                 }
             }
 
+            // The spatial hash was built at the start of the stage, so make sure the region is
+            // still in the same place.
             let damage_rect = damage_region.collider_rect(transform.translation);
             if player_rect.overlaps(&damage_rect) {
-                commands.add(PlayerCommand::kill(
-                    player_ent,
-                    Some(transform.translation.xy()),
-                ));
+                let hit_from = Some(transform.translation.xy());
+                if let Some(owner) = owner {
+                    // Credit the owner with the item they are holding
+                    let weapon = inventories.get(owner.0).and_then(|inventory| inventory.0);
+                    commands.add(PlayerCommand::kill_by(
+                        player_ent, hit_from, owner.0, weapon,
+                    ));
+                } else {
+                    commands.add(PlayerCommand::kill_environmental(player_ent, hit_from));
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestSession, TestSessionBuilder};
+
+    /// A room with a player standing at each of the given x positions.
+    fn players_session(xs: &[f32], meta: CoreMeta) -> TestSession {
+        let mut builder = TestSessionBuilder::room(40, 10);
+        builder.meta = meta;
+        for &x in xs {
+            builder.add_player(Vec2::new(x, 48.0));
+        }
+        let mut session = builder.build();
+        session.step(10);
+        session
+    }
+
+    /// Get the last player to hit a player.
+    fn last_hit_by(session: &TestSession, player: usize) -> Option<usize> {
+        let entity = session.player_entity(player)?;
+        session
+            .session
+            .world
+            .run_initialized_system(move |last_hits: Comp<LastHitBy>| {
+                Ok(last_hits.get(entity).map(|hit| hit.player))
+            })
+            .unwrap()
+    }
+
+    /// Spawn an entity at a player's position, with the components added by `insert`.
+    fn spawn_at_player(session: &mut TestSession, player: usize, insert: impl Fn(&World, Entity)) {
+        let position = session.player_position(player).unwrap();
+        let world = &session.session.world;
+        let entity = world.resource::<Entities>().borrow_mut().create();
+        world
+            .components
+            .get::<Transform>()
+            .borrow_mut()
+            .insert(entity, Transform::from_translation(position.extend(0.0)));
+        insert(world, entity);
+    }
+
+    /// Move a player far below the map, so that they are killed for being out of bounds.
+    fn drop_off_map(session: &mut TestSession, player: usize) {
+        let entity = session.player_entity(player).unwrap();
+        session
+            .session
+            .world
+            .run_initialized_system(move |mut transforms: CompMut<Transform>| {
+                transforms.get_mut(entity).unwrap().translation.y = -1000.0;
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn falling_off_map_is_credited_to_recent_attacker() {
+        let mut meta = testing::core_meta();
+        meta.config.kill_attribution_frames = 60;
+        let mut session = players_session(&[64.0, 200.0], meta);
+
+        let hit = |session: &mut TestSession| {
+            let frame = session.frame() as u32;
+            let victim = session.player_entity(0).unwrap();
+            session
+                .session
+                .world
+                .run_initialized_system(move |mut last_hits: CompMut<LastHitBy>| {
+                    last_hits.insert(victim, LastHitBy { player: 1, frame });
+                    Ok(())
+                })
+                .unwrap();
+        };
+
+        // Falling off of the map soon after being hit is the attacker's kill
+        hit(&mut session);
+        session.step(30);
+        drop_off_map(&mut session, 0);
+        session.step(2);
+        let kill = session.kills().last().cloned().unwrap();
+        assert_eq!((kill.victim, kill.killer), (0, Some(1)));
+
+        // Once the window has passed, it's an environmental death
+        for _ in 0..600 {
+            if session.is_player_alive(0) {
+                break;
+            }
+            session.step(1);
+        }
+        assert!(session.is_player_alive(0));
+        hit(&mut session);
+        session.step(61);
+        drop_off_map(&mut session, 0);
+        session.step(2);
+        assert_eq!(session.kills().len(), 2);
+        let kill = session.kills().last().cloned().unwrap();
+        assert_eq!((kill.victim, kill.killer), (0, None));
+    }
+
+    #[test]
+    fn explosion_finishing_knocked_player_is_credited_to_its_owner() {
+        let mut session = players_session(&[64.0, 300.0, 560.0], testing::core_meta());
+        let owner = |player| session.player_entity(player).unwrap();
+        let (a, c) = (owner(0), owner(2));
+
+        // Player A's explosion knocks player B back
+        spawn_at_player(&mut session, 1, |world, entity| {
+            world.components.get::<Knockback>().borrow_mut().insert(
+                entity,
+                Knockback {
+                    meta: KnockbackMeta {
+                        radius: 64.0,
+                        strength: 4.0,
+                        falloff: 0.0,
+                        grounded_player_multiplier: 1.0,
+                        exempt_owner: false,
+                    },
+                    owner: Some(a),
+                },
+            );
+        });
+        session.step(1);
+        assert!(session.is_player_alive(1));
+        assert_eq!(last_hit_by(&session, 1), Some(0));
+
+        // And then player C's explosion kills them
+        spawn_at_player(&mut session, 1, |world, entity| {
+            world.components.get::<DamageRegion>().borrow_mut().insert(
+                entity,
+                DamageRegion {
+                    size: Vec2::splat(64.0),
+                },
+            );
+            world
+                .components
+                .get::<DamageRegionOwner>()
+                .borrow_mut()
+                .insert(entity, DamageRegionOwner(c));
+        });
+        session.step(2);
+        assert!(!session.is_player_alive(1));
+        let kill = session.kills().last().cloned().unwrap();
+        assert_eq!((kill.victim, kill.killer), (1, Some(2)));
+    }
+}
@@ -1,164 +1,146 @@
-//! Debug rendering for collision boxes, etc.
+//! Debug rendering for force regions, the camera framing, etc.
+//!
+//! The collision shapes are drawn outside of the game session, by the collision debug view in the
+//! Bevy app, so that drawing them can't change the simulation.
 
-use crate::prelude::*;
-use rapier2d::prelude as rapier;
+use std::fmt::Write;
+
+use crate::{checksum::FrameChecksum, prelude::*, random::GlobalRng};
 
 pub fn install(session: &mut CoreSession) {
     session
         .stages
-        .add_system_to_stage(CoreStage::Last, debug_render_colliders)
-        .add_system_to_stage(CoreStage::Last, debug_render_damage_regions)
-        .add_system_to_stage(CoreStage::Last, debug_render_emote_regions);
+        .add_system_to_stage(CoreStage::Last, debug_render_force_regions)
+        .add_system_to_stage(CoreStage::Last, debug_render_emote_regions)
+        .add_system_to_stage(CoreStage::Last, debug_render_camera_framing);
 }
 
 /// Resource configuring various debugging settings.
 #[derive(Copy, Clone, TypeUlid, Default)]
 #[ulid = "01GQG8DR27S5A5S4CR84NE7CXH"]
 pub struct DebugSettings {
-    /// Whether or not to render kinematic collider shapes.
-    pub show_kinematic_colliders: bool,
-    /// Whether or not to render damage region collider shapes.
-    pub show_damage_regions: bool,
+    /// Whether or not to render the emote regions that make players react.
+    pub show_emote_regions: bool,
+    /// Whether or not to render the force regions as a field of arrows.
+    pub show_force_regions: bool,
     /// Whether or not to show the pathfinding lines.
     pub show_pathfinding_lines: bool,
+    /// Whether or not to show the camera's deadzone and the frame it is moving to.
+    pub show_camera_framing: bool,
 }
 
-/// Resource containing the physics debug line entity.
-#[derive(TypeUlid)]
-#[ulid = "01GRYZGG44HPZB3X0N2MT7RMT2"]
-pub struct RapierDebugContext {
-    path_entity: Entity,
-    debug_pipeline: rapier::DebugRenderPipeline,
-}
-
-struct RapierDebugBackend<'a> {
-    points: &'a mut Vec<Vec2>,
-    line_breaks: &'a mut Vec<usize>,
-}
+/// Write a textual dump of the state of the world, used to find where two sessions diverge.
+///
+/// The dump only depends on the game state, so two sessions that were given the same inputs will
+/// have byte-identical dumps on the same frame. The header has the frame number and the state of
+/// the random number generator, followed by every entity sorted by index, with the fields of its
+/// components that are registered in the [`ComponentInspectors`].
+pub fn dump_world(world: &World, inspectors: &ComponentInspectors) -> String {
+    let (frame, checksum, next_random, mut entities) = world
+        .run_initialized_system(
+            |frame: Res<SessionFrame>,
+             checksum: Res<FrameChecksum>,
+             rng: Res<GlobalRng>,
+             entities: Res<Entities>| {
+                // Generating a number from a copy of the RNG tells us its state without advancing
+                // it.
+                Ok((
+                    frame.0,
+                    checksum.0,
+                    GlobalRng::clone(&rng).gen_u64(),
+                    entities
+                        .iter_with_bitset(entities.bitset())
+                        .collect::<Vec<_>>(),
+                ))
+            },
+        )
+        .unwrap();
+    entities.sort_unstable_by_key(|ent| ent.index());
 
-impl<'a> rapier::DebugRenderBackend for RapierDebugBackend<'a> {
-    fn draw_line(
-        &mut self,
-        object: rapier::DebugRenderObject,
-        a: rapier::Point<rapier::Real>,
-        b: rapier::Point<rapier::Real>,
-        // TODO: implement multi-colored rendering
-        _color: [f32; 4],
-    ) {
-        let render = match object {
-            rapier::DebugRenderObject::RigidBody(_, body) => body.is_enabled(),
-            rapier::DebugRenderObject::Collider(_, collider) => collider.is_enabled(),
-            rapier::DebugRenderObject::ImpulseJoint(_, _) => true,
-            rapier::DebugRenderObject::MultibodyJoint(_, _, _) => true,
-            rapier::DebugRenderObject::Other => true,
-        };
-        if render {
-            self.points.push(vec2(a.x, a.y));
-            self.points.push(vec2(b.x, b.y));
-            self.line_breaks.push(self.points.len());
+    let mut dump = String::new();
+    writeln!(dump, "frame: {frame}").unwrap();
+    writeln!(dump, "checksum: {checksum:#018x}").unwrap();
+    writeln!(dump, "next random number: {next_random:#018x}").unwrap();
+    for entity in entities {
+        writeln!(dump, "\nentity {}:", entity.index()).unwrap();
+        for inspector in inspectors.iter() {
+            let mut fields = InspectorFields::default();
+            if !inspector.inspect(world, entity, &mut fields) {
+                continue;
+            }
+            writeln!(dump, "  {}:", inspector.name).unwrap();
+            for field in fields.fields {
+                writeln!(dump, "    {}: {}", field.name, field.value).unwrap();
+            }
         }
     }
-}
 
-impl Clone for RapierDebugContext {
-    fn clone(&self) -> Self {
-        Self {
-            path_entity: self.path_entity,
-            debug_pipeline: default(),
-        }
-    }
+    dump
 }
 
-impl FromWorld for RapierDebugContext {
-    fn from_world(world: &mut World) -> Self {
-        let entities = world.resource::<Entities>();
-        let mut entities = entities.borrow_mut();
-        let path_entity = entities.create();
-
-        let transforms = world.components.get::<Transform>();
-        let mut transforms = transforms.borrow_mut();
-        transforms.insert(
-            path_entity,
-            Transform::from_translation(vec3(0.0, 0.0, -1.0)),
-        );
-
-        Self {
-            path_entity,
-            debug_pipeline: default(),
-        }
-    }
-}
+/// Marker component for the entity that draws the camera framing debug lines.
+#[derive(Clone, Copy, TypeUlid, Default)]
+#[ulid = "01H1XS7T8G0Y0S1Z1WBZ2MNT4N"]
+pub struct CameraFramingDebugPath;
 
-fn debug_render_colliders(
-    settings: Res<DebugSettings>,
-    mut collision_world: CollisionWorld,
-    transforms: Comp<Transform>,
-    mut paths: CompMut<Path2d>,
-    mut debug_context: ResMut<RapierDebugContext>,
-) {
-    if settings.show_kinematic_colliders {
-        // TODO: It's unfortunate that we are doing an extra sync here, just for debug rendering. We
-        // should try find a way to avoid this. Without this, the collider body positions will be
-        // out of sync when they are rendered.
-        collision_world.update(&transforms);
-
-        let mut points = Vec::new();
-        let mut line_breaks = Vec::new();
-
-        debug_context.debug_pipeline.render_colliders(
-            &mut RapierDebugBackend {
-                points: &mut points,
-                line_breaks: &mut line_breaks,
-            },
-            &collision_world.ctx.rigid_body_set,
-            &collision_world.ctx.collider_set,
-        );
-
-        // TODO: Provide a way to change the collider colors
-        paths.insert(
-            debug_context.path_entity,
-            Path2d {
-                // An orange-y color
-                color: Color::from([205.0 / 255.0, 94.0 / 255.0, 15.0 / 255.0, 1.0]),
-                points,
-                line_breaks,
-                ..default()
-            },
-        );
-    } else {
-        paths.remove(debug_context.path_entity);
-    }
-}
+/// The distance between the arrows drawn in force regions.
+const FORCE_ARROW_SPACING: f32 = 24.0;
+/// The length of the arrows drawn in force regions.
+const FORCE_ARROW_LENGTH: f32 = 12.0;
 
-fn debug_render_damage_regions(
+fn debug_render_force_regions(
     settings: Res<DebugSettings>,
     entities: Res<Entities>,
-    regions: Comp<DamageRegion>,
+    regions: Comp<ForceRegion>,
     transforms: Comp<Transform>,
     mut paths: CompMut<Path2d>,
 ) {
-    let path_for_region = |rotation: f32, region: &DamageRegion| {
+    let path_for_region = |rotation: f32, region: &ForceRegion| {
         let rect = Rect::new(0.0, 0.0, region.size.x, region.size.y);
-
-        // The collision boxes don't rotate, so apply the opposite rotation of the object to the
-        // debug lines to keep it upright.
         let angle = Vec2::from_angle(-rotation);
+        let direction = region.direction.normalize_or_zero();
+
+        let mut points = vec![
+            rect.top_left(),
+            rect.top_right(),
+            rect.bottom_right(),
+            rect.bottom_left(),
+            rect.top_left(),
+        ];
+        let mut line_breaks = vec![points.len()];
+
+        // Fill the region with arrows pointing in the direction of the force
+        if direction != Vec2::ZERO {
+            let columns = (region.size.x / FORCE_ARROW_SPACING).floor().max(1.0) as usize;
+            let rows = (region.size.y / FORCE_ARROW_SPACING).floor().max(1.0) as usize;
+            let cell = region.size / vec2(columns as f32, rows as f32);
+            let head_left = Vec2::from_angle(0.75 * std::f32::consts::PI).rotate(direction);
+            let head_right = Vec2::from_angle(-0.75 * std::f32::consts::PI).rotate(direction);
+            for column in 0..columns {
+                for row in 0..rows {
+                    let center = rect.min + cell * vec2(column as f32 + 0.5, row as f32 + 0.5);
+                    let tip = center + direction * FORCE_ARROW_LENGTH / 2.0;
+                    points.push(center - direction * FORCE_ARROW_LENGTH / 2.0);
+                    points.push(tip);
+                    line_breaks.push(points.len());
+                    points.push(tip + head_left * FORCE_ARROW_LENGTH / 3.0);
+                    points.push(tip);
+                    points.push(tip + head_right * FORCE_ARROW_LENGTH / 3.0);
+                    line_breaks.push(points.len());
+                }
+            }
+        }
 
         Path2d {
-            color: Color::RED,
-            points: vec![
-                angle.rotate(rect.top_left()),
-                angle.rotate(rect.top_right()),
-                angle.rotate(rect.bottom_right()),
-                angle.rotate(rect.bottom_left()),
-                angle.rotate(rect.top_left()),
-            ],
+            color: Color::rgb(0.0, 1.0, 1.0),
+            points: points.into_iter().map(|x| angle.rotate(x)).collect(),
             thickness: 1.0,
+            line_breaks,
             ..default()
         }
     };
 
-    if settings.show_damage_regions {
+    if settings.show_force_regions {
         for (ent, (region, transform)) in entities.iter_with((&regions, &transforms)) {
             paths.insert(
                 ent,
@@ -201,7 +183,7 @@ fn debug_render_emote_regions(
         }
     };
 
-    if settings.show_damage_regions {
+    if settings.show_emote_regions {
         for (ent, (region, transform)) in entities.iter_with((&regions, &transforms)) {
             paths.insert(
                 ent,
@@ -214,3 +196,63 @@ fn debug_render_emote_regions(
         }
     }
 }
+
+/// Draw the camera's deadzone around its target, and the frame the camera is moving to.
+fn debug_render_camera_framing(
+    settings: Res<DebugSettings>,
+    game_meta: Res<CoreMetaArc>,
+    mut entities: ResMut<Entities>,
+    camera_states: Comp<CameraState>,
+    mut debug_paths: CompMut<CameraFramingDebugPath>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+) {
+    let path_ent = entities.iter_with(&debug_paths).next().map(|x| x.0);
+
+    let camera_state = entities.iter_with(&camera_states).next().map(|x| x.1);
+    let (true, Some(camera_state)) = (settings.show_camera_framing, camera_state) else {
+        if let Some(ent) = path_ent {
+            paths.remove(ent);
+        }
+        return;
+    };
+
+    let path_ent = path_ent.unwrap_or_else(|| {
+        let ent = entities.create();
+        debug_paths.insert(ent, CameraFramingDebugPath);
+        transforms.insert(ent, Transform::from_translation(vec3(0.0, 0.0, 900.0)));
+        ent
+    });
+
+    let rect_points = |rect: Rect| {
+        [
+            rect.top_left(),
+            rect.top_right(),
+            rect.bottom_right(),
+            rect.bottom_left(),
+            rect.top_left(),
+        ]
+    };
+
+    let frame = camera_state.target_frame;
+    let mut points = rect_points(frame).to_vec();
+    let mut line_breaks = vec![points.len()];
+    if let Some(target) = camera_state.target {
+        let deadzone = game_meta.camera.deadzone;
+        points.extend(rect_points(Rect::new(
+            target.x, target.y, deadzone.x, deadzone.y,
+        )));
+        line_breaks.push(points.len());
+    }
+
+    paths.insert(
+        path_ent,
+        Path2d {
+            color: Color::rgb(1.0, 0.0, 1.0),
+            points,
+            thickness: 1.0,
+            line_breaks,
+            ..default()
+        },
+    );
+}
@@ -0,0 +1,206 @@
+//! Handling for network players who lose their connection in the middle of a match.
+//!
+//! The network session marks players as [`ConnectionStatus::Disconnected`] in the
+//! [`PlayerInputs`], and this module takes care of their fish from there. Everything here is
+//! driven by the player inputs, so all of the remaining players agree on what happens to the
+//! disconnected player, and on which frame.
+//!
+//! A player that rejoins before the grace period is over is marked as
+//! [`ConnectionStatus::Connected`] again, and gets control of their fish back. Otherwise they are
+//! marked as [`ConnectionStatus::Removed`], and their fish is taken out of the game without
+//! counting as a death, so that their score is left as it was.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<DisconnectTimers>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, handle_disconnected_players);
+}
+
+/// Resource tracking how many frames each player has been disconnected for.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H3F2HDSTB3B2BHC2X60MA8W3"]
+pub struct DisconnectTimers {
+    pub frames_disconnected: [u32; MAX_PLAYERS],
+}
+
+fn handle_disconnected_players(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    core_meta: Res<CoreMetaArc>,
+    mut timers: ResMut<DisconnectTimers>,
+    mut player_inputs: ResMut<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    mut bodies: CompMut<KinematicBody>,
+    mut ai_players: CompMut<AiPlayer>,
+) {
+    let config = &core_meta.config;
+    let grace_frames = (config.disconnect_grace_period.as_secs_f32() * crate::FPS) as u32;
+
+    for (player_idx, input) in player_inputs.players.iter_mut().enumerate() {
+        let frames_disconnected = &mut timers.frames_disconnected[player_idx];
+        let player_ent = entities
+            .iter_with(&player_indexes)
+            .find(|(_, idx)| idx.0 == player_idx)
+            .map(|(ent, _)| ent);
+
+        // Take the fish of a removed player out of the game, the frame after they dropped their
+        // item, without counting it as a death
+        if input.connection == ConnectionStatus::Removed {
+            if let Some(player_ent) = player_ent {
+                commands.add(PlayerCommand::despawn(player_ent));
+            }
+            continue;
+        }
+
+        if input.connection != ConnectionStatus::Disconnected {
+            // Hand the fish back to a player who rejoined in time
+            if *frames_disconnected > 0 && input.connection == ConnectionStatus::Connected {
+                info!(%player_idx, "Disconnected player rejoined the match");
+                match config.disconnect_behavior {
+                    DisconnectBehavior::Freeze => {
+                        if let Some(body) = player_ent.and_then(|ent| bodies.get_mut(ent)) {
+                            body.is_deactivated = false;
+                        }
+                    }
+                    DisconnectBehavior::Ai => {
+                        input.is_ai = false;
+                        if let Some(player_ent) = player_ent {
+                            ai_players.remove(player_ent);
+                        }
+                    }
+                }
+            }
+            *frames_disconnected = 0;
+            continue;
+        }
+        *frames_disconnected += 1;
+
+        // Remove the player from the match once the grace period is over
+        if *frames_disconnected > grace_frames {
+            info!(%player_idx, "Removing disconnected player from the match");
+            input.connection = ConnectionStatus::Removed;
+            input.active = false;
+            if let Some(player_ent) = player_ent {
+                commands.add(PlayerCommand::set_inventory(player_ent, None));
+            }
+            continue;
+        }
+
+        match config.disconnect_behavior {
+            DisconnectBehavior::Freeze => {
+                input.control = default();
+                if let Some(body) = player_ent.and_then(|ent| bodies.get_mut(ent)) {
+                    body.velocity = Vec2::ZERO;
+                    body.is_deactivated = true;
+                }
+            }
+            DisconnectBehavior::Ai => {
+                // Make sure the player stays an AI if they respawn
+                input.is_ai = true;
+                if let Some(player_ent) = player_ent {
+                    if !ai_players.contains(player_ent) {
+                        ai_players.insert(player_ent, AiPlayer::new(input.ai_difficulty));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestInput, TestSession, TestSessionBuilder};
+
+    fn set_connection(session: &mut TestSession, connection: ConnectionStatus) {
+        session
+            .session
+            .update_input(|inputs| inputs.players[0].connection = connection);
+    }
+
+    #[test]
+    fn rejoined_player_gets_their_fish_back() {
+        let mut builder = TestSessionBuilder::room(20, 10);
+        builder.add_player(Vec2::new(48.0, 48.0));
+        builder.meta.config.disconnect_behavior = DisconnectBehavior::Ai;
+
+        let mut session = builder.build();
+        session.step(10);
+        let player_ent = session.player_entity(0).unwrap();
+        let is_ai = |session: &TestSession| {
+            let has_ai_player = session
+                .session
+                .world
+                .run_initialized_system(move |ai_players: Comp<AiPlayer>| {
+                    Ok(ai_players.contains(player_ent))
+                })
+                .unwrap();
+            let input_is_ai = session
+                .session
+                .world
+                .resource::<PlayerInputs>()
+                .borrow()
+                .players[0]
+                .is_ai;
+            has_ai_player && input_is_ai
+        };
+
+        set_connection(&mut session, ConnectionStatus::Disconnected);
+        session.step(10);
+        assert!(is_ai(&session), "Disconnected player should be an AI");
+
+        set_connection(&mut session, ConnectionStatus::Connected);
+        session.step(1);
+        assert!(
+            !is_ai(&session),
+            "Rejoined player should control their fish"
+        );
+        let timers = session.session.world.resource::<DisconnectTimers>();
+        assert_eq!(timers.borrow().frames_disconnected[0], 0);
+    }
+
+    #[test]
+    fn removed_player_leaves_the_score_alone() {
+        let mut builder = TestSessionBuilder::room(20, 10);
+        builder.add_player(Vec2::new(48.0, 48.0));
+        builder.add_player(Vec2::new(200.0, 48.0));
+        builder.add_element("/grenade", testing::grenade_meta(), Vec2::new(48.0, 40.0));
+        builder.meta.config.disconnect_grace_period = std::time::Duration::from_millis(500);
+        builder.meta.camera.kill_hit_pause_frames = 10;
+
+        let mut session = builder.build();
+        session.step(10);
+        let grab = TestInput {
+            grab: true,
+            ..default()
+        };
+        session.step_with(0, grab, 1);
+        session.step(1);
+        let item = session.player_inventory(0).unwrap();
+
+        set_connection(&mut session, ConnectionStatus::Disconnected);
+        for _ in 0..60 {
+            session.step(1);
+            let hit_pause = session.session.world.resource::<HitPause>();
+            assert_eq!(hit_pause.borrow().remaining_frames, 0);
+        }
+
+        let connection = session
+            .session
+            .world
+            .resource::<PlayerInputs>()
+            .borrow()
+            .players[0]
+            .connection;
+        assert_eq!(connection, ConnectionStatus::Removed);
+        assert_eq!(session.player_entity(0), None);
+        assert!(session.kills().is_empty());
+        assert!(
+            session.is_alive(item),
+            "Removed player's item should be dropped"
+        );
+    }
+}
@@ -2,12 +2,19 @@
 //!
 //! Allows you to edit the game map while the game is running.
 
+use std::collections::VecDeque;
+
 use crate::impl_system_param;
 use crate::map_constructor::shiftnanigans::ShiftnanigansMapConstructor;
 use crate::map_constructor::MapConstructor;
-use crate::{map::z_depth_for_map_layer, prelude::*};
+use crate::{
+    elements::player_spawner::{CurrentSpawner, PlayerRespawns, PlayerSpawner},
+    map::z_depth_for_map_layer,
+    prelude::*,
+};
 
 pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<EditorHistory>();
     session
         .stages
         .add_system_to_stage(CoreStage::PreUpdate, handle_editor_input);
@@ -19,12 +26,17 @@ impl_system_param! {
         entities: ResMut<'a, Entities>,
         spawned_map_meta: ResMut<'a, SpawnedMapMeta>,
         element_handles: CompMut<'a, ElementHandle>,
+        element_properties: CompMut<'a, ElementProperties>,
+        element_waypoints: CompMut<'a, ElementWaypoints>,
         transforms: CompMut<'a, Transform>,
         spawned_map_layer_metas: CompMut<'a, SpawnedMapLayerMeta>,
         tile_layers: CompMut<'a, TileLayer>,
         tiles: CompMut<'a, Tile>,
         tile_collisions: CompMut<'a, TileCollisionKind>,
+        tile_surfaces: CompMut<'a, TileSurface>,
         map: Res<'a, LoadedMap>,
+        sprites: CompMut<'a, Sprite>,
+        parallax_bg_sprites: CompMut<'a, ParallaxBackgroundSprite>,
         element_kill_callbacks: Comp<'a, ElementKillCallback>,
         spawner_manager: SpawnerManager<'a>,
     }
@@ -36,7 +48,7 @@ impl<'a> MapManager<'a> {
         element_meta_handle: &Handle<ElementMeta>,
         translation: &Vec2,
         layer_index: usize,
-    ) {
+    ) -> Entity {
         let entity = self.entities.create();
         // TODO remove element handles as the underlying elements are removed
         self.element_handles
@@ -52,6 +64,8 @@ impl<'a> MapManager<'a> {
                 layer_idx: layer_index,
             },
         );
+
+        entity
     }
     pub fn create_layer(&mut self, name: String) {
         let entity = self.entities.create();
@@ -133,6 +147,37 @@ impl<'a> MapManager<'a> {
         transform.translation.x = position.x;
         transform.translation.y = position.y;
     }
+    pub fn set_element_property(
+        &mut self,
+        entity: Entity,
+        key: &str,
+        value: Option<ElementPropertyValue>,
+    ) {
+        if !self.element_handles.contains(entity) {
+            return;
+        }
+        let mut properties = self
+            .element_properties
+            .get(entity)
+            .cloned()
+            .unwrap_or_default();
+        if let Some(value) = value {
+            properties.insert(key.to_string(), value);
+        } else {
+            properties.remove(key);
+        }
+        self.element_properties.insert(entity, properties);
+    }
+    pub fn set_element_waypoints(&mut self, entity: Entity, waypoints: &ElementWaypoints) {
+        if !self.element_handles.contains(entity) {
+            return;
+        }
+        if waypoints.is_empty() {
+            self.element_waypoints.remove(entity);
+        } else {
+            self.element_waypoints.insert(entity, waypoints.clone());
+        }
+    }
     pub fn delete_element(&mut self, entity: Entity) {
         if let Some(element_kill_callback) = self.element_kill_callbacks.get(entity) {
             let system = element_kill_callback.system.clone();
@@ -163,12 +208,38 @@ impl<'a> MapManager<'a> {
             }
         };
     }
+    /// Get the tile layer for the map layer at the given index.
+    pub fn tile_layer(&self, layer_index: usize) -> Option<&TileLayer> {
+        self.entities
+            .iter_with((&self.tile_layers, &self.spawned_map_layer_metas))
+            .find(|x| x.1 .1.layer_idx == layer_index)
+            .map(|(_, (tile_layer, _))| tile_layer)
+    }
+    /// Get the tilemap index, collision kind, and surface of the tile at the given position, if
+    /// there is one.
+    pub fn get_tile(
+        &self,
+        layer_index: usize,
+        position: UVec2,
+    ) -> Option<(usize, TileCollisionKind, TileSurface)> {
+        let entity = self.tile_layer(layer_index)?.get(position)?;
+        let idx = self.tiles.get(entity)?.idx;
+        let collision = self
+            .tile_collisions
+            .get(entity)
+            .copied()
+            .unwrap_or(TileCollisionKind::Empty);
+        let surface = self.tile_surfaces.get(entity).copied().unwrap_or_default();
+
+        Some((idx, collision, surface))
+    }
     pub fn set_tile(
         &mut self,
         layer_index: usize,
         position: UVec2,
         tilemap_tile_index: &Option<usize>,
         tile_collision_kind: TileCollisionKind,
+        tile_surface: TileSurface,
     ) {
         if let Some((_, (tile_layer, _))) = self
             .entities
@@ -187,6 +258,7 @@ impl<'a> MapManager<'a> {
                     } else {
                         self.tile_collisions.remove(entity);
                     }
+                    self.set_tile_surface(entity, tile_surface);
                 } else {
                     self.entities.kill(entity);
                     tile_layer.set(position, None);
@@ -210,6 +282,7 @@ impl<'a> MapManager<'a> {
                 } else {
                     self.tile_collisions.remove(entity);
                 }
+                self.set_tile_surface(entity, tile_surface);
             }
 
             self.commands
@@ -218,6 +291,14 @@ impl<'a> MapManager<'a> {
                 });
         };
     }
+    /// Set the surface of a tile entity, leaving default surfaces off of the tile.
+    fn set_tile_surface(&mut self, entity: Entity, surface: TileSurface) {
+        if surface.is_default() {
+            self.tile_surfaces.remove(entity);
+        } else {
+            self.tile_surfaces.insert(entity, surface);
+        }
+    }
     pub fn swap_layer(&mut self, layer_index: usize, is_downward: bool) {
         let origin_layer_index = layer_index;
         let other_layer_index = if is_downward {
@@ -242,15 +323,303 @@ impl<'a> MapManager<'a> {
             }
         }
     }
+    /// Move a layer to the index `to`, shifting the layers in between it and its old index.
+    pub fn reorder_layer(&mut self, layer_index: usize, to: usize) {
+        let layer_count = self.get_layers_total();
+        if layer_index >= layer_count || to >= layer_count {
+            return;
+        }
+        if to > layer_index {
+            for i in layer_index..to {
+                self.swap_layer(i, true);
+            }
+        } else {
+            for i in (to + 1..=layer_index).rev() {
+                self.swap_layer(i, false);
+            }
+        }
+    }
     pub fn rename_map(&mut self, name: String) {
         self.spawned_map_meta.name = name.into();
     }
+    pub fn create_background_layer(&mut self, meta: ParallaxLayerMeta) {
+        self.update_background(|background| background.layers.push(meta));
+    }
+    pub fn update_background_layer(&mut self, layer_index: usize, meta: ParallaxLayerMeta) {
+        self.update_background(|background| {
+            if let Some(layer) = background.layers.get_mut(layer_index) {
+                *layer = meta;
+            }
+        });
+    }
+    pub fn swap_background_layer(&mut self, layer_index: usize, is_downward: bool) {
+        self.update_background(|background| {
+            let other_layer_index = if is_downward {
+                layer_index + 1
+            } else {
+                layer_index.wrapping_sub(1)
+            };
+            if layer_index < background.layers.len() && other_layer_index < background.layers.len()
+            {
+                background.layers.swap(layer_index, other_layer_index);
+            }
+        });
+    }
+    pub fn delete_background_layer(&mut self, layer_index: usize) {
+        self.update_background(|background| {
+            if layer_index < background.layers.len() {
+                background.layers.remove(layer_index);
+            }
+        });
+    }
+    /// Modify the map's parallax background, and re-spawn the background sprites to match it.
+    fn update_background(&mut self, update: impl FnOnce(&mut BackgroundMeta)) {
+        update(Arc::make_mut(&mut self.spawned_map_meta.background));
+
+        let to_kill = self
+            .entities
+            .iter_with(&self.parallax_bg_sprites)
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in to_kill {
+            self.entities.kill(entity);
+        }
+        spawn_parallax_background(
+            &self.spawned_map_meta.background,
+            &mut self.entities,
+            &mut self.sprites,
+            &mut self.transforms,
+            &mut self.parallax_bg_sprites,
+        );
+    }
     pub fn get_size(&self) -> UVec2 {
         self.spawned_map_meta.grid_size
     }
     pub fn get_layers_total(&self) -> usize {
         self.spawned_map_meta.layer_names.len()
     }
+    /// Get the history entries that will reverse the given input, or [`None`] if it can't be
+    /// reversed.
+    ///
+    /// This must be called before the input is applied. Spawned elements are handled by
+    /// [`apply_editor_input`], because the reverse needs to know the spawned entity.
+    fn inverse_of(&self, input: &EditorInput) -> Option<Vec<HistoryEntry>> {
+        let inverse = match input {
+            EditorInput::SpawnElement { .. } | EditorInput::Undo | EditorInput::Redo => Vec::new(),
+            EditorInput::MoveEntity { entity, .. } => self
+                .transforms
+                .get(*entity)
+                .map(|transform| EditorInput::MoveEntity {
+                    entity: *entity,
+                    pos: transform.translation.truncate(),
+                })
+                .into_iter()
+                .map(HistoryEntry::from)
+                .collect(),
+            EditorInput::SetElementProperty { entity, key, .. } => {
+                vec![EditorInput::SetElementProperty {
+                    entity: *entity,
+                    key: key.clone(),
+                    value: self
+                        .element_properties
+                        .get(*entity)
+                        .and_then(|properties| properties.get(key))
+                        .copied(),
+                }
+                .into()]
+            }
+            EditorInput::SetElementWaypoints { entity, .. } => {
+                vec![EditorInput::SetElementWaypoints {
+                    entity: *entity,
+                    waypoints: self
+                        .element_waypoints
+                        .get(*entity)
+                        .cloned()
+                        .unwrap_or_default(),
+                }
+                .into()]
+            }
+            EditorInput::DeleteEntity { entity } => {
+                let mut entries = self.respawn_element(*entity)?;
+                entries.reverse();
+                entries
+            }
+            EditorInput::CreateLayer { .. } => vec![EditorInput::DeleteLayer {
+                layer: self.get_layers_total() as u8,
+            }
+            .into()],
+            EditorInput::DeleteLayer { layer } => self.restore_layer(*layer as usize)?,
+            EditorInput::RenameLayer { layer, .. } => self
+                .spawned_map_meta
+                .layer_names
+                .get(*layer as usize)
+                .map(|name| EditorInput::RenameLayer {
+                    layer: *layer,
+                    name: name.clone(),
+                })
+                .into_iter()
+                .map(HistoryEntry::from)
+                .collect(),
+            EditorInput::MoveLayer { layer, down } => {
+                let other_layer = if *down { layer + 1 } else { layer - 1 };
+                vec![EditorInput::MoveLayer {
+                    layer: other_layer,
+                    down: !down,
+                }
+                .into()]
+            }
+            EditorInput::ReorderLayer { layer, to } => vec![EditorInput::ReorderLayer {
+                layer: *to,
+                to: *layer,
+            }
+            .into()],
+            EditorInput::SetTilemap { layer, .. } => self
+                .tile_layer(*layer as usize)
+                .map(|tile_layer| EditorInput::SetTilemap {
+                    layer: *layer,
+                    handle: Some(tile_layer.atlas.clone()),
+                })
+                .into_iter()
+                .map(HistoryEntry::from)
+                .collect(),
+            EditorInput::SetTile {
+                layer,
+                pos,
+                tilemap_tile_idx,
+                collision,
+                surface,
+            } => {
+                let current = self.get_tile(*layer as usize, *pos);
+                if current == tilemap_tile_idx.map(|idx| (idx, *collision, *surface)) {
+                    Vec::new()
+                } else {
+                    vec![EditorInput::SetTile {
+                        layer: *layer,
+                        pos: *pos,
+                        tilemap_tile_idx: current.map(|(idx, ..)| idx),
+                        collision: current
+                            .map(|(_, collision, _)| collision)
+                            .unwrap_or(TileCollisionKind::Empty),
+                        surface: current.map(|(.., surface)| surface).unwrap_or_default(),
+                    }
+                    .into()]
+                }
+            }
+            EditorInput::RenameMap { .. } => vec![EditorInput::RenameMap {
+                name: self.spawned_map_meta.name.to_string(),
+            }
+            .into()],
+            EditorInput::RandomizeTiles { .. } => return None,
+            // Background changes aren't kept in the history, and none of the recorded edits
+            // depend on the background.
+            EditorInput::CreateBackgroundLayer { .. }
+            | EditorInput::UpdateBackgroundLayer { .. }
+            | EditorInput::MoveBackgroundLayer { .. }
+            | EditorInput::DeleteBackgroundLayer { .. } => Vec::new(),
+        };
+
+        Some(inverse)
+    }
+    /// Get the history entries that will spawn a deleted element back, along with its overridden
+    /// properties and waypoints, in the order they need to be applied.
+    fn respawn_element(&self, entity: Entity) -> Option<Vec<HistoryEntry>> {
+        let handle = self.element_handles.get(entity)?.0.clone();
+        let translation = self.transforms.get(entity)?.translation.truncate();
+        let layer = self.spawned_map_layer_metas.get(entity)?.layer_idx as u8;
+
+        let mut entries = vec![HistoryEntry {
+            input: EditorInput::SpawnElement {
+                handle,
+                translation,
+                layer,
+            },
+            respawns: Some(entity),
+        }];
+        if let Some(properties) = self.element_properties.get(entity) {
+            entries.extend(properties.iter().map(|(key, value)| {
+                HistoryEntry::from(EditorInput::SetElementProperty {
+                    entity,
+                    key: key.clone(),
+                    value: Some(*value),
+                })
+            }));
+        }
+        if let Some(waypoints) = self.element_waypoints.get(entity) {
+            entries.push(
+                EditorInput::SetElementWaypoints {
+                    entity,
+                    waypoints: waypoints.clone(),
+                }
+                .into(),
+            );
+        }
+
+        Some(entries)
+    }
+    /// Get the history entries that will re-create a deleted layer, with its tiles and elements.
+    fn restore_layer(&self, layer_index: usize) -> Option<Vec<HistoryEntry>> {
+        let name = self.spawned_map_meta.layer_names.get(layer_index)?.clone();
+        let tile_layer = self.tile_layer(layer_index)?;
+        let layer = layer_index as u8;
+
+        // The layer is re-created on top, and moved back into place from there.
+        let last_layer = self.get_layers_total() - 1;
+        let mut entries: Vec<HistoryEntry> = vec![EditorInput::CreateLayer { id: name }.into()];
+        entries.extend((layer_index + 1..=last_layer).rev().map(|i| {
+            HistoryEntry::from(EditorInput::MoveLayer {
+                layer: i as u8,
+                down: false,
+            })
+        }));
+        entries.push(
+            EditorInput::SetTilemap {
+                layer,
+                handle: Some(tile_layer.atlas.clone()),
+            }
+            .into(),
+        );
+
+        let grid_size = self.get_size();
+        for y in 0..grid_size.y {
+            for x in 0..grid_size.x {
+                let pos = UVec2::new(x, y);
+                let Some(tile) = tile_layer.get(pos) else {
+                    continue;
+                };
+                if let Some(idx) = self.tiles.get(tile).map(|tile| tile.idx) {
+                    let collision = self
+                        .tile_collisions
+                        .get(tile)
+                        .copied()
+                        .unwrap_or(TileCollisionKind::Empty);
+                    let surface = self.tile_surfaces.get(tile).copied().unwrap_or_default();
+                    entries.push(
+                        EditorInput::SetTile {
+                            layer,
+                            pos,
+                            tilemap_tile_idx: Some(idx),
+                            collision,
+                            surface,
+                        }
+                        .into(),
+                    );
+                }
+            }
+        }
+
+        entries.extend(
+            self.entities
+                .iter_with((&self.element_handles, &self.spawned_map_layer_metas))
+                .filter(|(_, (_, layer_meta))| layer_meta.layer_idx == layer_index)
+                .filter_map(|(entity, _)| self.respawn_element(entity))
+                .flatten(),
+        );
+
+        // History steps are applied from last to first.
+        entries.reverse();
+
+        Some(entries)
+    }
     pub fn clear_tiles(&mut self) {
         let empty_tile: Option<usize> = Option::None;
         for y in 0..self.spawned_map_meta.grid_size.y {
@@ -262,6 +631,7 @@ impl<'a> MapManager<'a> {
                         position,
                         &empty_tile,
                         crate::physics::TileCollisionKind::Empty,
+                        default(),
                     );
                 }
             }
@@ -281,64 +651,446 @@ impl<'a> MapManager<'a> {
     }
 }
 
-fn handle_editor_input(player_inputs: Res<PlayerInputs>, mut map_manager: MapManager) {
+/// The maximum number of undo steps kept in the [`EditorHistory`].
+pub const EDITOR_HISTORY_LEN: usize = 256;
+
+/// The number of frames without any editor input after which a continuous edit, such as painting
+/// tiles by dragging the mouse, is considered finished.
+const EDIT_GROUP_IDLE_FRAMES: u32 = 10;
+
+/// Resource containing the undo and redo stacks of the map editor.
+///
+/// Each step is a list of [`EditorInput`]s that reverse an edit, which are applied from last to
+/// first. Undoing and redoing is requested with [`EditorInput::Undo`] and [`EditorInput::Redo`], so
+/// that the reversing edits go through the same input handling as any other edit.
+///
+/// The history lives in the session world, so it starts out empty whenever a map is loaded.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H048JKT1MQPNCSHPZKCN0J7Z"]
+pub struct EditorHistory {
+    undo: VecDeque<Vec<HistoryEntry>>,
+    redo: Vec<Vec<HistoryEntry>>,
+    /// The continuous edit that the latest undo step was made by, if it is still going.
+    open_group: Option<EditGroup>,
+    /// The number of frames since the last editor input.
+    idle_frames: u32,
+    /// The number of edits made to the map, including undos and redos.
+    edits: u64,
+}
+
+/// An entry in an [`EditorHistory`] step.
+#[derive(Clone)]
+struct HistoryEntry {
+    input: EditorInput,
+    /// The deleted entity that this entry spawns back.
+    ///
+    /// The spawned element is a new entity, so any other entries that refer to the deleted entity
+    /// are updated to refer to the new one.
+    respawns: Option<Entity>,
+}
+
+impl From<EditorInput> for HistoryEntry {
+    fn from(input: EditorInput) -> Self {
+        Self {
+            input,
+            respawns: None,
+        }
+    }
+}
+
+/// A kind of edit that is made continuously over several frames, and is undone all at once.
+#[derive(Clone, Copy, PartialEq)]
+enum EditGroup {
+    /// Painting tiles or tile collisions.
+    Tiles,
+    /// Dragging an element.
+    Move(Entity),
+    /// Dragging the value of an element property.
+    Property(Entity),
+    /// Dragging the waypoints of an element.
+    Waypoints(Entity),
+}
+
+impl EditGroup {
+    fn of(input: &EditorInput) -> Option<Self> {
+        match input {
+            EditorInput::SetTile { .. } => Some(Self::Tiles),
+            EditorInput::MoveEntity { entity, .. } => Some(Self::Move(*entity)),
+            EditorInput::SetElementProperty { entity, .. } => Some(Self::Property(*entity)),
+            EditorInput::SetElementWaypoints { entity, .. } => Some(Self::Waypoints(*entity)),
+            _ => None,
+        }
+    }
+}
+
+impl EditorHistory {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Get the number of edits made to the map since it was loaded.
+    ///
+    /// This can be compared with an earlier count to tell whether the map has changed.
+    pub fn edits(&self) -> u64 {
+        self.edits
+    }
+
+    /// Record the entries that reverse an edit that was just made.
+    fn record(&mut self, input: &EditorInput, inverse: Option<Vec<HistoryEntry>>) {
+        // Older steps may not apply cleanly after an edit that we can't reverse, so drop them.
+        let Some(inverse) = inverse else {
+            *self = Self {
+                edits: self.edits,
+                ..default()
+            };
+            return;
+        };
+        if inverse.is_empty() {
+            return;
+        }
+        self.redo.clear();
+
+        let group = EditGroup::of(input);
+        match self.undo.back_mut() {
+            Some(step) if group.is_some() && group == self.open_group => step.extend(inverse),
+            _ => self.push_undo(inverse),
+        }
+        self.open_group = group;
+    }
+
+    fn push_undo(&mut self, step: Vec<HistoryEntry>) {
+        if self.undo.len() == EDITOR_HISTORY_LEN {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(step);
+    }
+
+    fn undo(&mut self, map_manager: &mut MapManager) {
+        self.open_group = None;
+        if let Some(step) = self.undo.pop_back() {
+            let reverse = self.apply_step(map_manager, step);
+            self.redo.push(reverse);
+        }
+    }
+
+    fn redo(&mut self, map_manager: &mut MapManager) {
+        self.open_group = None;
+        if let Some(step) = self.redo.pop() {
+            let reverse = self.apply_step(map_manager, step);
+            self.push_undo(reverse);
+        }
+    }
+
+    /// Apply the entries of an undo or redo step, returning the step that reverses it.
+    fn apply_step(
+        &mut self,
+        map_manager: &mut MapManager,
+        mut step: Vec<HistoryEntry>,
+    ) -> Vec<HistoryEntry> {
+        let mut reverse = Vec::new();
+        while let Some(entry) = step.pop() {
+            let inverse = apply_editor_input(map_manager, &entry.input).unwrap_or_default();
+
+            // The reverse of spawning an element is deleting the entity that was spawned.
+            if let (Some(old), Some(EditorInput::DeleteEntity { entity: new })) =
+                (entry.respawns, inverse.first().map(|first| &first.input))
+            {
+                let new = *new;
+                self.undo
+                    .iter_mut()
+                    .chain(self.redo.iter_mut())
+                    .chain([&mut step])
+                    .flatten()
+                    .for_each(|other| other.remap_entity(old, new));
+            }
+
+            reverse.extend(inverse);
+        }
+
+        reverse
+    }
+
+    /// Finish the current continuous edit once there hasn't been any editor input for a while.
+    fn tick(&mut self, had_input: bool) {
+        if had_input {
+            self.idle_frames = 0;
+        } else {
+            self.idle_frames = self.idle_frames.saturating_add(1);
+            if self.idle_frames >= EDIT_GROUP_IDLE_FRAMES {
+                self.open_group = None;
+            }
+        }
+    }
+}
+
+impl HistoryEntry {
+    fn remap_entity(&mut self, old: Entity, new: Entity) {
+        match &mut self.input {
+            EditorInput::MoveEntity { entity, .. }
+            | EditorInput::DeleteEntity { entity }
+            | EditorInput::SetElementProperty { entity, .. }
+            | EditorInput::SetElementWaypoints { entity, .. }
+                if *entity == old =>
+            {
+                *entity = new;
+            }
+            _ => (),
+        }
+        if self.respawns == Some(old) {
+            self.respawns = Some(new);
+        }
+    }
+}
+
+fn handle_editor_input(
+    player_inputs: Res<PlayerInputs>,
+    mut map_manager: MapManager,
+    mut history: ResMut<EditorHistory>,
+) {
+    let mut had_input = false;
     for player in &player_inputs.players {
-        if let Some(editor_input) = &player.editor_input {
-            match editor_input {
-                EditorInput::SpawnElement {
-                    handle,
-                    translation,
-                    layer,
-                } => {
-                    map_manager.create_element(handle, translation, *layer as usize);
-                }
-                EditorInput::CreateLayer { id } => {
-                    map_manager.create_layer(id.clone());
-                }
-                EditorInput::DeleteLayer { layer } => {
-                    map_manager.delete_layer(*layer as usize);
-                }
-                EditorInput::RenameLayer {
-                    layer,
-                    name: new_name,
-                } => map_manager.rename_layer(*layer as usize, new_name),
-                EditorInput::MoveEntity { entity, pos } => {
-                    map_manager.move_element(*entity, pos);
-                }
-                EditorInput::DeleteEntity { entity } => {
-                    map_manager.delete_element(*entity);
-                }
-                EditorInput::SetTilemap { layer, handle } => {
-                    map_manager.set_layer_tilemap(*layer as usize, handle);
-                }
-                EditorInput::SetTile {
-                    layer,
-                    pos,
-                    tilemap_tile_idx,
-                    collision,
-                } => {
-                    map_manager.set_tile(*layer as usize, *pos, tilemap_tile_idx, *collision);
+        let Some(editor_input) = &player.editor_input else {
+            continue;
+        };
+        had_input = true;
+        history.edits += 1;
+
+        match editor_input {
+            EditorInput::Undo => history.undo(&mut map_manager),
+            EditorInput::Redo => history.redo(&mut map_manager),
+            input => {
+                let inverse = apply_editor_input(&mut map_manager, input);
+                history.record(input, inverse);
+            }
+        }
+    }
+
+    history.tick(had_input);
+}
+
+/// Apply an editor input to the map.
+///
+/// Returns the history entries that will reverse the input, or [`None`] if it can't be reversed.
+fn apply_editor_input(
+    map_manager: &mut MapManager,
+    input: &EditorInput,
+) -> Option<Vec<HistoryEntry>> {
+    let inverse = map_manager.inverse_of(input);
+    match input {
+        EditorInput::SpawnElement {
+            handle,
+            translation,
+            layer,
+        } => {
+            let entity = map_manager.create_element(handle, translation, *layer as usize);
+            return Some(vec![EditorInput::DeleteEntity { entity }.into()]);
+        }
+        EditorInput::CreateLayer { id } => {
+            map_manager.create_layer(id.clone());
+        }
+        EditorInput::DeleteLayer { layer } => {
+            map_manager.delete_layer(*layer as usize);
+        }
+        EditorInput::RenameLayer {
+            layer,
+            name: new_name,
+        } => map_manager.rename_layer(*layer as usize, new_name),
+        EditorInput::MoveEntity { entity, pos } => {
+            map_manager.move_element(*entity, pos);
+        }
+        EditorInput::DeleteEntity { entity } => {
+            map_manager.delete_element(*entity);
+        }
+        EditorInput::SetElementProperty { entity, key, value } => {
+            map_manager.set_element_property(*entity, key, *value);
+        }
+        EditorInput::SetElementWaypoints { entity, waypoints } => {
+            map_manager.set_element_waypoints(*entity, waypoints);
+        }
+        EditorInput::SetTilemap { layer, handle } => {
+            map_manager.set_layer_tilemap(*layer as usize, handle);
+        }
+        EditorInput::SetTile {
+            layer,
+            pos,
+            tilemap_tile_idx,
+            collision,
+            surface,
+        } => {
+            map_manager.set_tile(
+                *layer as usize,
+                *pos,
+                tilemap_tile_idx,
+                *collision,
+                *surface,
+            );
+        }
+        EditorInput::MoveLayer { layer, down } => map_manager.swap_layer(*layer as usize, *down),
+        EditorInput::ReorderLayer { layer, to } => {
+            map_manager.reorder_layer(*layer as usize, *to as usize)
+        }
+        EditorInput::RenameMap { name } => {
+            map_manager.rename_map(name.clone());
+        }
+        EditorInput::RandomizeTiles {
+            tile_layers,
+            element_layers,
+            tile_size,
+        } => {
+            let map_constructor = ShiftnanigansMapConstructor::new(
+                map_manager.get_size(),
+                *tile_size,
+                tile_layers,
+                element_layers,
+            );
+            map_constructor.construct_map(map_manager);
+        }
+        EditorInput::CreateBackgroundLayer { meta } => {
+            map_manager.create_background_layer(meta.clone());
+        }
+        EditorInput::UpdateBackgroundLayer { layer, meta } => {
+            map_manager.update_background_layer(*layer as usize, meta.clone());
+        }
+        EditorInput::MoveBackgroundLayer { layer, down } => {
+            map_manager.swap_background_layer(*layer as usize, *down);
+        }
+        EditorInput::DeleteBackgroundLayer { layer } => {
+            map_manager.delete_background_layer(*layer as usize);
+        }
+        EditorInput::Undo | EditorInput::Redo => (),
+    }
+
+    inverse
+}
+
+/// Replace the players in the game with a single local player, who will be spawned at the player
+/// spawner closest to `pos`.
+///
+/// This is used to playtest a map from the editor. Players are still respawned after dying, so the
+/// playtest lasts until the editor is opened again.
+pub fn start_playtest(world: &World, pos: Vec2) {
+    world
+        .run_initialized_system(
+            move |entities: Res<Entities>,
+                  mut commands: Commands,
+                  core_meta: Res<CoreMetaArc>,
+                  mut player_inputs: ResMut<PlayerInputs>,
+                  mut current_spawner: ResMut<CurrentSpawner>,
+                  mut respawns: ResMut<PlayerRespawns>,
+                  mut stocks: ResMut<PlayerStocks>,
+                  mut score: ResMut<MatchScore>,
+                  player_indexes: Comp<PlayerIdx>,
+                  player_spawners: Comp<PlayerSpawner>,
+                  transforms: Comp<Transform>| {
+                for (player, _) in entities.iter_with(&player_indexes) {
+                    commands.add(PlayerCommand::despawn(player));
                 }
-                EditorInput::MoveLayer { layer, down } => {
-                    map_manager.swap_layer(*layer as usize, *down)
+
+                for (i, player) in player_inputs.players.iter_mut().enumerate() {
+                    if i == 0 && !player.active {
+                        player.selected_player = core_meta.players[0].clone();
+                    }
+                    player.active = i == 0;
+                    player.is_ai = false;
                 }
-                EditorInput::RenameMap { name } => {
-                    map_manager.rename_map(name.clone());
+                // Don't make the player wait out the respawn delay to start playing
+                respawns.countdowns[0] = Some(0);
+                *stocks = default();
+                *score = default();
+
+                let spawn_points = entities
+                    .iter_with((&player_spawners, &transforms))
+                    .map(|(_ent, (_spawner, transform))| transform.translation.truncate())
+                    .collect::<Vec<_>>();
+                let nearest = spawn_points
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| {
+                        a.distance_squared(pos).total_cmp(&b.distance_squared(pos))
+                    })
+                    .map(|(i, _)| i);
+
+                // The spawner moves on to the next spawn point before spawning a player, so start
+                // it just before the nearest one.
+                if let Some(nearest) = nearest {
+                    current_spawner.0 = (nearest + spawn_points.len() - 1) % spawn_points.len();
                 }
-                EditorInput::RandomizeTiles {
-                    tile_layers,
-                    element_layers,
-                    tile_size,
-                } => {
-                    let map_constructor = ShiftnanigansMapConstructor::new(
-                        map_manager.get_size(),
-                        *tile_size,
-                        tile_layers,
-                        element_layers,
-                    );
-                    map_constructor.construct_map(&mut map_manager);
+            },
+        )
+        .unwrap();
+}
+
+/// Component added to the map layer entities that are hidden in the editor, remembering how they
+/// looked so that they can be shown again.
+#[derive(Clone, TypeUlid)]
+#[ulid = "01H6F0T8Q2JX5R3VBYKZ4N7MDA"]
+pub struct HiddenInEditor {
+    scale: Vec3,
+    alpha: Option<f32>,
+}
+
+/// Hide the tiles and elements of the given map layers, and show those of all the other layers.
+///
+/// Hiding a layer only changes how it is drawn: its tiles keep colliding and its elements keep
+/// working. The editor calls this every frame with the layers that the player has hidden, and with
+/// no layers when it is closed.
+pub fn set_hidden_map_layers(world: &World, hidden: &[usize]) {
+    let hidden = hidden.to_vec();
+    world
+        .run_initialized_system(
+            move |entities: Res<Entities>,
+                  spawned_map_layer_metas: Comp<SpawnedMapLayerMeta>,
+                  tile_layers: Comp<TileLayer>,
+                  mut transforms: CompMut<Transform>,
+                  mut sprites: CompMut<Sprite>,
+                  mut atlas_sprites: CompMut<AtlasSprite>,
+                  mut hidden_in_editor: CompMut<HiddenInEditor>| {
+                for (entity, (layer_meta, transform)) in
+                    entities.iter_with((&spawned_map_layer_metas, &mut transforms))
+                {
+                    let should_hide = hidden.contains(&layer_meta.layer_idx);
+                    let color = sprites
+                        .get_mut(entity)
+                        .map(|sprite| &mut sprite.color)
+                        .or_else(|| {
+                            atlas_sprites
+                                .get_mut(entity)
+                                .map(|sprite| &mut sprite.color)
+                        });
+
+                    match (should_hide, hidden_in_editor.get(entity).cloned()) {
+                        (true, None) => {
+                            // Tile layers are hidden by scaling them down to nothing, since tiles
+                            // don't have a color.
+                            let is_tile_layer = tile_layers.contains(entity);
+                            hidden_in_editor.insert(
+                                entity,
+                                HiddenInEditor {
+                                    scale: transform.scale,
+                                    alpha: color.as_ref().map(|color| color.a()),
+                                },
+                            );
+                            if is_tile_layer {
+                                transform.scale = Vec3::ZERO;
+                            }
+                            if let Some(color) = color {
+                                color.set_a(0.0);
+                            }
+                        }
+                        (false, Some(hidden)) => {
+                            hidden_in_editor.remove(entity);
+                            transform.scale = hidden.scale;
+                            if let (Some(color), Some(alpha)) = (color, hidden.alpha) {
+                                color.set_a(alpha);
+                            }
+                        }
+                        _ => (),
+                    }
                 }
-            }
-        }
-    }
+            },
+        )
+        .unwrap();
 }
@@ -11,13 +11,19 @@ use ::bevy::{
 
 use crate::{impl_system_param, prelude::*};
 
+pub mod behaviors;
+pub mod breakable_crate;
+pub mod control_zone;
 pub mod crab;
 pub mod crate_item;
 pub mod decoration;
 pub mod fish_school;
+pub mod force_region;
 pub mod grenade;
+pub mod item_spawner;
 pub mod kick_bomb;
 pub mod mine;
+pub mod moving_platform;
 pub mod musket;
 pub mod player_spawner;
 pub mod slippery;
@@ -26,7 +32,9 @@ pub mod snail;
 pub mod sproinger;
 pub mod stomp_boots;
 pub mod sword;
+pub mod trigger_region;
 pub mod urchin;
+pub mod water_volume;
 
 /// Marker component added to map elements that have been hydrated.
 #[derive(Clone, TypeUlid)]
@@ -42,6 +50,22 @@ pub struct MapElementHydrated;
 #[ulid = "01GP9NY0Y50Y2A8M4A7E9NN8VE"]
 pub struct DehydrateOutOfBounds(pub Entity);
 
+/// The key of the element property that sets how many seconds an item spawner waits before
+/// spawning its item again.
+pub const RESPAWN_DELAY_PROPERTY: &str = "respawn_delay";
+
+/// Component tracking the respawn delay of an item spawner, added once the spawner has spawned its
+/// first item.
+///
+/// While the delay is running, the spawner is kept hydrated so that it doesn't spawn its item yet.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H0PWFRVSAW6BF7TWM46JESDZ"]
+pub struct ElementRespawnDelay {
+    timer: Option<Timer>,
+    /// Whether the delay has finished, and the spawner is about to spawn its item again.
+    respawning: bool,
+}
+
 /// Component containing an element's metadata handle.
 #[derive(Clone, TypeUlid, Deref, DerefMut, Default)]
 #[ulid = "01GP421CHN323T2614F19PA5E9"]
@@ -179,6 +203,22 @@ impl<'a> SpawnerManager<'a> {
             .expect("There should exist a cooresponding SpawnerEntities for this spawner group identifier.")
             .retain(|entity| *entity != spawned_entity);
     }
+    /// Swaps two spawned entities, so that each of them belongs to the spawner of the other one
+    pub fn swap_spawned_entities(&mut self, a: Entity, b: Entity) {
+        for spawned_entities in self
+            .spawner_entities
+            .entities_per_spawner_group_identifier
+            .values_mut()
+        {
+            for spawned_entity in spawned_entities {
+                if *spawned_entity == a {
+                    *spawned_entity = b;
+                } else if *spawned_entity == b {
+                    *spawned_entity = a;
+                }
+            }
+        }
+    }
     /// Returns if the entity provided is a spawner
     pub fn is_entity_a_spawner(&self, entity: Entity) -> bool {
         self.spawners.contains(entity)
@@ -229,7 +269,8 @@ impl<'a> SpawnerManager<'a> {
 pub fn install(session: &mut CoreSession) {
     session
         .stages
-        .add_system_to_stage(CoreStage::First, handle_out_of_bounds_items);
+        .add_system_to_stage(CoreStage::First, handle_out_of_bounds_items)
+        .add_system_to_stage(CoreStage::First, delay_respawns);
 
     decoration::install(session);
     urchin::install(session);
@@ -247,6 +288,14 @@ pub fn install(session: &mut CoreSession) {
     crate_item::install(session);
     slippery_seaweed::install(session);
     slippery::install(session);
+    control_zone::install(session);
+    moving_platform::install(session);
+    water_volume::install(session);
+    force_region::install(session);
+    item_spawner::install(session);
+    breakable_crate::install(session);
+    trigger_region::install(session);
+    behaviors::install(session);
 }
 
 fn handle_out_of_bounds_items(
@@ -266,3 +315,56 @@ fn handle_out_of_bounds_items(
         }
     }
 }
+
+/// Hold back item spawners that have a [`RESPAWN_DELAY_PROPERTY`] from spawning their item again
+/// until the delay has passed.
+fn delay_respawns(
+    entities: Res<Entities>,
+    time: Res<Time>,
+    element_assets: BevyAssets<ElementMeta>,
+    element_handles: Comp<ElementHandle>,
+    element_properties: Comp<ElementProperties>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut respawn_delays: CompMut<ElementRespawnDelay>,
+) {
+    let no_overrides = ElementProperties::default();
+    for (entity, element_handle) in entities.iter_with(&element_handles) {
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let properties = element_properties.get(entity).unwrap_or(&no_overrides);
+        let Some(delay) = element_meta
+            .editor
+            .property(properties, RESPAWN_DELAY_PROPERTY)
+            .and_then(|value| value.as_number())
+            .filter(|delay| *delay > 0.0) else {
+            continue;
+        };
+        let is_hydrated = hydrated.contains(entity);
+
+        let Some(respawn_delay) = respawn_delays.get_mut(entity) else {
+            // Let the first item spawn right away.
+            if is_hydrated {
+                respawn_delays.insert(entity, default());
+            }
+            continue;
+        };
+
+        if let Some(timer) = &mut respawn_delay.timer {
+            timer.tick(time.delta());
+            if timer.finished() {
+                respawn_delay.timer = None;
+                respawn_delay.respawning = true;
+                hydrated.remove(entity);
+            }
+        } else if respawn_delay.respawning {
+            if is_hydrated {
+                respawn_delay.respawning = false;
+            }
+        } else if !is_hydrated {
+            // The spawner's item was just removed, so wait before spawning it again.
+            respawn_delay.timer = Some(Timer::from_seconds(delay, TimerMode::Once));
+            hydrated.insert(entity, MapElementHydrated);
+        }
+    }
+}
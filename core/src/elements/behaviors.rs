@@ -0,0 +1,261 @@
+//! Simple behaviors that can be added to any element with its [`ElementMeta::behaviors`].
+//!
+//! The behaviors are added to the entity that the element is hydrated into: the element itself for
+//! elements like decorations, or the entity that it spawns for elements like items. Elements
+//! without a [`BuiltinElementKind`] are hydrated here, so an element may be made only out of
+//! behaviors.
+
+use std::f32::consts::TAU;
+
+use crate::{prelude::*, FPS};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        // This must run after the hydration systems of the other elements in the same stage.
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update_spin_behaviors)
+        .add_system_to_stage(CoreStage::PostUpdate, update_oscillate_behaviors)
+        .add_system_to_stage(CoreStage::PostUpdate, update_emit_particles_behaviors)
+        .add_system_to_stage(CoreStage::PostUpdate, update_despawn_after_behaviors);
+}
+
+/// Marker component added to entities that have had the behaviors of their element added.
+#[derive(Clone, TypeUlid)]
+#[ulid = "01H5TB2M6QF8ZC3R0WJXKD4N7E"]
+pub struct ElementBehaviorsHydrated;
+
+/// Component that rotates an entity, added by [`SpinBehaviorMeta`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H5TB3A9VN2XG6HP1CYRM8QDF"]
+pub struct SpinBehavior {
+    pub radians_per_frame: f32,
+}
+
+/// Component that moves an entity back and forth, added by [`OscillateBehaviorMeta`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H5TB3QKD7TW4BZ9F2EVX6HNM"]
+pub struct OscillateBehavior {
+    /// The position that the entity moves around.
+    pub origin: Vec3,
+    /// The offset from the origin at the furthest point in the positive direction.
+    pub extent: Vec2,
+    /// How many frames it takes to go back and forth once.
+    pub period_frames: f32,
+    /// The number of frames that the entity has been moving for.
+    pub frame: u32,
+}
+
+impl OscillateBehavior {
+    /// Get the offset from the origin at the given frame.
+    pub fn offset(&self, frame: u32) -> Vec2 {
+        let phase = (frame as f32 / self.period_frames).fract();
+        self.extent * sine_between(-1.0, 1.0, phase * TAU)
+    }
+}
+
+/// Component that plays a particle effect at an entity over and over, added by
+/// [`EmitParticlesBehaviorMeta`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H5TB46R3HS0MEQ5KT8CZJW2V"]
+pub struct EmitParticlesBehavior {
+    pub effect: Handle<ParticleEffectMeta>,
+    /// How many frames to wait before playing the effect again.
+    pub interval: u32,
+    /// The number of frames since the effect was last played.
+    pub frame: u32,
+}
+
+/// Component that despawns an entity after a number of frames, added by
+/// [`DespawnAfterBehaviorMeta`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H5TB4MX0PA7YDG3N9QB5ESKR"]
+pub struct DespawnAfterBehavior {
+    pub frames_left: u32,
+}
+
+fn hydrate(
+    entities: Res<Entities>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    spawner_manager: SpawnerManager,
+    respawn_points: Comp<DehydrateOutOfBounds>,
+    transforms: Comp<Transform>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut behaviors_hydrated: CompMut<ElementBehaviorsHydrated>,
+    mut spins: CompMut<SpinBehavior>,
+    mut oscillates: CompMut<OscillateBehavior>,
+    mut damage_regions: CompMut<DamageRegion>,
+    mut despawns: CompMut<DespawnAfterBehavior>,
+    mut particle_emitters: CompMut<EmitParticlesBehavior>,
+) {
+    let mut not_hydrated_bitset = behaviors_hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    // The elements that have spawned a separate entity, which gets the behaviors instead
+    let spawners = entities
+        .iter_with(&respawn_points)
+        .map(|(_, spawner)| **spawner)
+        .collect::<Vec<_>>();
+
+    for entity in entities.iter_with_bitset(&not_hydrated_bitset) {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        if element_meta.behaviors.is_empty() {
+            continue;
+        }
+
+        if !hydrated.contains(entity) {
+            // Elements with a built-in kind are hydrated by their own module first
+            if !matches!(element_meta.builtin, BuiltinElementKind::None) {
+                continue;
+            }
+            hydrated.insert(entity, MapElementHydrated);
+        }
+        if spawner_manager.is_entity_a_spawner(entity) || spawners.contains(&entity) {
+            continue;
+        }
+        let Some(transform) = transforms.get(entity) else {
+            continue;
+        };
+
+        behaviors_hydrated.insert(entity, ElementBehaviorsHydrated);
+        for behavior in &element_meta.behaviors {
+            match behavior {
+                ElementBehaviorMeta::Spin(meta) => {
+                    spins.insert(
+                        entity,
+                        SpinBehavior {
+                            radians_per_frame: meta.degrees_per_second.to_radians() / FPS,
+                        },
+                    );
+                }
+                ElementBehaviorMeta::Oscillate(meta) => {
+                    if meta.period <= 0.0 {
+                        continue;
+                    }
+                    oscillates.insert(
+                        entity,
+                        OscillateBehavior {
+                            origin: transform.translation,
+                            extent: meta.axis.normalize_or_zero() * meta.amplitude,
+                            period_frames: meta.period * FPS,
+                            frame: 0,
+                        },
+                    );
+                }
+                ElementBehaviorMeta::HurtOnTouch(meta) => {
+                    damage_regions.insert(entity, DamageRegion { size: meta.size });
+                }
+                ElementBehaviorMeta::DespawnAfter(meta) => {
+                    despawns.insert(
+                        entity,
+                        DespawnAfterBehavior {
+                            frames_left: meta.frames,
+                        },
+                    );
+                }
+                ElementBehaviorMeta::EmitParticles(meta) => {
+                    particle_emitters.insert(
+                        entity,
+                        EmitParticlesBehavior {
+                            effect: meta.effect.clone(),
+                            interval: meta.interval.max(1),
+                            frame: 0,
+                        },
+                    );
+                }
+                // These are reported when the map is validated
+                ElementBehaviorMeta::Unknown(_) => (),
+            }
+        }
+    }
+}
+
+fn update_spin_behaviors(
+    entities: Res<Entities>,
+    spins: Comp<SpinBehavior>,
+    mut transforms: CompMut<Transform>,
+) {
+    for (_ent, (spin, transform)) in entities.iter_with((&spins, &mut transforms)) {
+        transform.rotation *= Quat::from_rotation_z(spin.radians_per_frame);
+    }
+}
+
+fn update_oscillate_behaviors(
+    entities: Res<Entities>,
+    mut oscillates: CompMut<OscillateBehavior>,
+    mut transforms: CompMut<Transform>,
+) {
+    for (_ent, (oscillate, transform)) in entities.iter_with((&mut oscillates, &mut transforms)) {
+        oscillate.frame = oscillate.frame.wrapping_add(1);
+        transform.translation = oscillate.origin + oscillate.offset(oscillate.frame).extend(0.0);
+    }
+}
+
+fn update_emit_particles_behaviors(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    transforms: Comp<Transform>,
+    mut particle_emitters: CompMut<EmitParticlesBehavior>,
+) {
+    for (_ent, (emitter, transform)) in entities.iter_with((&mut particle_emitters, &transforms)) {
+        if emitter.frame == 0 {
+            commands.add(spawn_particle_effect(
+                emitter.effect.clone(),
+                *transform,
+                false,
+            ));
+        }
+        emitter.frame = (emitter.frame + 1) % emitter.interval;
+    }
+}
+
+/// Despawn the entities that have run out of frames, dehydrating their spawners like items that
+/// fall off of the map.
+fn update_despawn_after_behaviors(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    respawn_points: Comp<DehydrateOutOfBounds>,
+    mut despawns: CompMut<DespawnAfterBehavior>,
+    mut hydrated: CompMut<MapElementHydrated>,
+) {
+    for (entity, despawn) in entities.iter_with(&mut despawns) {
+        if despawn.frames_left > 0 {
+            despawn.frames_left -= 1;
+            continue;
+        }
+
+        if let Some(spawner) = respawn_points.get(entity) {
+            hydrated.remove(**spawner);
+        }
+        commands.add(move |mut entities: ResMut<Entities>| {
+            entities.kill(entity);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oscillation_goes_back_and_forth() {
+        let oscillate = OscillateBehavior {
+            origin: Vec3::ZERO,
+            extent: Vec2::new(0.0, 10.0),
+            period_frames: 60.0,
+            frame: 0,
+        };
+
+        assert!(oscillate.offset(0).abs_diff_eq(Vec2::ZERO, 1e-4));
+        assert!(oscillate.offset(15).abs_diff_eq(Vec2::new(0.0, 10.0), 1e-4));
+        assert!(oscillate
+            .offset(45)
+            .abs_diff_eq(Vec2::new(0.0, -10.0), 1e-4));
+        assert!(oscillate.offset(60).abs_diff_eq(Vec2::ZERO, 1e-4));
+    }
+}
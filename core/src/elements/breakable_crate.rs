@@ -0,0 +1,550 @@
+//! Crates that can be stood on and pushed around, and that drop an item when they are broken.
+//!
+//! The element itself stays where it was placed in the map, and spawns a separate crate entity
+//! that collides like a solid map tile. Crates are hit by anything that damages breakable tiles,
+//! like bullets and explosions, and by weapons like sword swings. Once a crate has taken enough
+//! hits, it breaks and drops an item picked from its loot table, which is spawned as a map element
+//! without a layer, like the items of an [`ItemSpawner`][super::item_spawner::ItemSpawner].
+//!
+//! Crates with the [`RESPAWN_PROPERTY`] set are put back by their element once they are broken,
+//! after the element's [`RESPAWN_DELAY_PROPERTY`].
+
+use crate::{
+    elements::item_spawner::{choose_weighted, release_taken_items, weight_property_key},
+    physics::collisions::TileCollisionKind,
+    prelude::*,
+    random::GlobalRng,
+};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, push_crates)
+        .add_system_to_stage(CoreStage::Last, break_crates);
+
+    session
+        .inspectors
+        .register::<BreakableCrate>("BreakableCrate");
+}
+
+/// The key of the element property that sets whether the crate comes back after it is broken.
+pub const RESPAWN_PROPERTY: &str = "respawn";
+
+/// How far past the sides of a crate a player may be for them to be pushing it.
+const PUSH_DISTANCE: f32 = 1.0;
+
+/// How far past the sides of a crate the areas hit by bullets and explosions may be for them to
+/// hit the crate, since bullets stop just short of the solids they run into.
+const HIT_DISTANCE: f32 = 2.0;
+
+/// Component for a crate spawned by a breakable crate element.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H63E4XKQ9V2N7BJSW0TFDM8R"]
+pub struct BreakableCrate {
+    /// The element entity that spawned the crate.
+    pub element: Entity,
+    /// How many more hits the crate takes before it breaks.
+    pub hit_points: u32,
+    /// The damage regions with an owner, like sword swings, that have already hit the crate.
+    pub hit_by: Vec<Entity>,
+}
+
+impl Inspect for BreakableCrate {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("hit_points", self.hit_points);
+        fields.text("hit_by", format!("{:?}", self.hit_by));
+    }
+}
+
+/// Component for a breakable crate element, containing the items dropped by its crates.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H63E5BT2DZ8WQX4HMV6K1NJC"]
+pub struct BreakableCrateLoot {
+    /// The item elements that have been dropped and are waiting to be picked up.
+    pub spawned: Vec<Entity>,
+}
+
+fn hydrate(
+    mut entities: ResMut<Entities>,
+    game_meta: Res<CoreMetaArc>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    element_properties: Comp<ElementProperties>,
+    player_indexes: Comp<PlayerIdx>,
+    mut breakable_crates: CompMut<BreakableCrate>,
+    mut crate_loot: CompMut<BreakableCrateLoot>,
+    mut element_kill_callbacks: CompMut<ElementKillCallback>,
+    mut transforms: CompMut<Transform>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut tile_collisions: CompMut<TileCollisionKind>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let player_rects = entities
+        .iter_with((&player_indexes, &transforms, &bodies))
+        .map(|(_ent, (_idx, transform, body))| body.bounding_box(*transform))
+        .collect::<Vec<_>>();
+    let no_overrides = ElementProperties::default();
+
+    let spawners = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for entity in spawners {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::BreakableCrate(meta) = &element_meta.builtin else {
+            continue;
+        };
+
+        // Wait for players to get out of the way, so they don't get stuck in the crate
+        let transform = *transforms.get(entity).unwrap();
+        let rect = Rect::new(
+            transform.translation.x,
+            transform.translation.y,
+            meta.body_size.x,
+            meta.body_size.y,
+        );
+        if player_rects.iter().any(|player| player.overlaps(&rect)) {
+            continue;
+        }
+
+        hydrated.insert(entity, MapElementHydrated);
+        if !crate_loot.contains(entity) {
+            crate_loot.insert(entity, default());
+            element_kill_callbacks.insert(entity, ElementKillCallback::new(kill_crate(entity)));
+        }
+
+        let crate_ent = entities.create();
+        transforms.insert(crate_ent, transform);
+        atlas_sprites.insert(crate_ent, AtlasSprite::new(meta.atlas.clone()));
+        bodies.insert(
+            crate_ent,
+            KinematicBody {
+                shape: ColliderShape::Rectangle {
+                    size: meta.body_size,
+                },
+                has_mass: true,
+                has_friction: true,
+                gravity: game_meta.physics.gravity,
+                ..default()
+            },
+        );
+        tile_collisions.insert(crate_ent, TileCollisionKind::Solid);
+        breakable_crates.insert(
+            crate_ent,
+            BreakableCrate {
+                element: entity,
+                hit_points: meta.hit_points,
+                hit_by: default(),
+            },
+        );
+
+        // Crates that respawn are put back when they fall off of the map, too
+        let properties = element_properties.get(entity).unwrap_or(&no_overrides);
+        let respawns = element_meta
+            .editor
+            .property(properties, RESPAWN_PROPERTY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or_default();
+        if respawns {
+            respawn_points.insert(crate_ent, DehydrateOutOfBounds(entity));
+        }
+    }
+}
+
+/// Push crates along with the players walking into their sides, and keep them from landing on
+/// top of players.
+fn push_crates(
+    entities: Res<Entities>,
+    map: Res<LoadedMap>,
+    player_inputs: Res<PlayerInputs>,
+    player_assets: BevyAssets<PlayerMeta>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    player_indexes: Comp<PlayerIdx>,
+    breakable_crates: Comp<BreakableCrate>,
+    respawn_points: Comp<DehydrateOutOfBounds>,
+    mut transforms: CompMut<Transform>,
+    mut bodies: CompMut<KinematicBody>,
+    mut commands: Commands,
+) {
+    let players = entities
+        .iter_with((&player_indexes, &transforms, &bodies))
+        .map(|(_ent, (idx, transform, body))| (idx.0, body.bounding_box(*transform)))
+        .collect::<Vec<_>>();
+
+    for (crate_ent, breakable_crate) in entities.iter_with(&breakable_crates) {
+        let Some(element_meta) = element_handles
+            .get(breakable_crate.element)
+            .and_then(|handle| element_assets.get(&handle.get_bevy_handle())) else {
+            continue;
+        };
+        let BuiltinElementKind::BreakableCrate(meta) = &element_meta.builtin else {
+            continue;
+        };
+        let transform = transforms.get_mut(crate_ent).unwrap();
+
+        // Crates that don't respawn are removed once they fall off of the map
+        if !respawn_points.contains(crate_ent) && map.is_out_of_bounds(&transform.translation) {
+            commands.add(move |mut entities: ResMut<Entities>| {
+                entities.kill(crate_ent);
+            });
+            continue;
+        }
+
+        let body = bodies.get_mut(crate_ent).unwrap();
+        let crate_rect = body.bounding_box(*transform);
+        for (player_idx, player_rect) in &players {
+            // A crate that fell onto a player is put on top of them, so they don't get stuck in it
+            if player_rect.overlaps(&crate_rect) {
+                transform.translation.y = player_rect.max.y + meta.body_size.y / 2.0;
+                body.velocity.y = body.velocity.y.max(0.0);
+                continue;
+            }
+
+            // Players standing on the crate or hanging below it don't push it
+            if player_rect.min.y >= crate_rect.max.y || player_rect.max.y <= crate_rect.min.y {
+                continue;
+            }
+            let player_input = &player_inputs.players[*player_idx];
+            let direction = player_input.control.move_direction.x;
+            let is_pushing = if direction > 0.0 {
+                (crate_rect.min.x - player_rect.max.x).abs() <= PUSH_DISTANCE
+            } else if direction < 0.0 {
+                (player_rect.min.x - crate_rect.max.x).abs() <= PUSH_DISTANCE
+            } else {
+                false
+            };
+            if !is_pushing {
+                continue;
+            }
+            let Some(player_meta) =
+                player_assets.get(&player_input.selected_player.get_bevy_handle()) else {
+                continue;
+            };
+
+            let push_speed = direction * player_meta.stats.walk_speed * meta.push_factor;
+            if push_speed.abs() > body.velocity.x.abs() {
+                body.velocity.x = push_speed;
+            }
+        }
+    }
+}
+
+/// Hit the crates with the damage queued for breakable tiles and with the damage regions of
+/// weapons, and break the crates that have taken enough hits.
+///
+/// This runs before the tile damage is applied, which empties the [`TileDamageQueue`].
+fn break_crates(
+    mut entities: ResMut<Entities>,
+    rng: Res<GlobalRng>,
+    tile_damage: Res<TileDamageQueue>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_properties: Comp<ElementProperties>,
+    inventories: Comp<Inventory>,
+    damage_regions: Comp<DamageRegion>,
+    damage_region_owners: Comp<DamageRegionOwner>,
+    bodies: Comp<KinematicBody>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut breakable_crates: CompMut<BreakableCrate>,
+    mut crate_loot: CompMut<BreakableCrateLoot>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut transforms: CompMut<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
+    mut commands: Commands,
+) {
+    // Let go of the loot that has been picked up or destroyed
+    let elements = entities
+        .iter_with(&crate_loot)
+        .map(|(ent, _)| ent)
+        .collect::<Vec<_>>();
+    for element in elements {
+        let loot = crate_loot.get_mut(element).unwrap();
+        release_taken_items(
+            &mut entities,
+            &mut loot.spawned,
+            &hydrated,
+            &inventories,
+            &mut respawn_points,
+        );
+    }
+
+    let weapon_rects = entities
+        .iter_with((&damage_regions, &damage_region_owners, &transforms))
+        .map(|(ent, (region, _owner, transform))| {
+            (ent, region.collider_rect(transform.translation))
+        })
+        .collect::<Vec<_>>();
+    let no_overrides = ElementProperties::default();
+
+    let mut broken = Vec::new();
+    for (crate_ent, (breakable_crate, body, transform)) in
+        entities.iter_with((&mut breakable_crates, &bodies, &transforms))
+    {
+        let crate_rect = body.bounding_box(*transform);
+        let hit_rect = Rect::new(
+            crate_rect.center().x,
+            crate_rect.center().y,
+            crate_rect.width() + HIT_DISTANCE * 2.0,
+            crate_rect.height() + HIT_DISTANCE * 2.0,
+        );
+
+        // Every bullet and explosion is one hit
+        let mut hits = tile_damage
+            .rects()
+            .filter(|rect| rect.overlaps(&hit_rect))
+            .count() as u32;
+
+        // Weapons only hit the crate once for each swing
+        breakable_crate
+            .hit_by
+            .retain(|region| weapon_rects.iter().any(|(ent, _)| ent == region));
+        for (region, rect) in &weapon_rects {
+            if rect.overlaps(&crate_rect) && !breakable_crate.hit_by.contains(region) {
+                breakable_crate.hit_by.push(*region);
+                hits += 1;
+            }
+        }
+
+        breakable_crate.hit_points = breakable_crate.hit_points.saturating_sub(hits);
+        if breakable_crate.hit_points == 0 {
+            broken.push((crate_ent, breakable_crate.element, *transform));
+        }
+    }
+
+    for (crate_ent, element, transform) in broken {
+        entities.kill(crate_ent);
+        let Some(element_meta) = element_handles
+            .get(element)
+            .and_then(|handle| element_assets.get(&handle.get_bevy_handle())) else {
+            continue;
+        };
+        let BuiltinElementKind::BreakableCrate(meta) = &element_meta.builtin else {
+            continue;
+        };
+
+        audio_events.play_from(
+            meta.break_sound.clone(),
+            meta.break_sound_volume,
+            Some(&transform),
+        );
+        if let Some(particles) = &meta.break_particles {
+            commands.add(spawn_particle_effect(particles.clone(), transform, false));
+        }
+
+        // Drop an item, using the instance's weight overrides
+        let properties = element_properties.get(element).unwrap_or(&no_overrides);
+        let weights = meta
+            .loot
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                properties
+                    .get(&weight_property_key(i))
+                    .and_then(|value| value.as_number())
+                    .unwrap_or(entry.weight)
+            })
+            .collect::<Vec<_>>();
+        if let Some(loot_idx) = choose_weighted(&weights, rng.f32()) {
+            let item_element = entities.create();
+            element_handles.insert(
+                item_element,
+                ElementHandle(meta.loot[loot_idx].element.clone()),
+            );
+            transforms.insert(item_element, transform);
+            if let Some(loot) = crate_loot.get_mut(element) {
+                loot.spawned.push(item_element);
+            }
+        }
+
+        // Un-hydrate the element so that it puts the crate back, after its respawn delay
+        let respawns = element_meta
+            .editor
+            .property(properties, RESPAWN_PROPERTY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or_default();
+        if respawns {
+            hydrated.remove(element);
+        }
+    }
+}
+
+/// Kill a crate element along with its crate, and the loot that hasn't been picked up yet.
+fn kill_crate(element_ent: Entity) -> System {
+    (move |mut entities: ResMut<Entities>,
+           breakable_crates: Comp<BreakableCrate>,
+           crate_loot: Comp<BreakableCrateLoot>,
+           respawn_points: Comp<DehydrateOutOfBounds>| {
+        let crates = entities
+            .iter_with(&breakable_crates)
+            .filter(|(_, breakable_crate)| breakable_crate.element == element_ent)
+            .map(|(ent, _)| ent)
+            .collect::<Vec<_>>();
+        let loot = crate_loot
+            .get(element_ent)
+            .map(|loot| loot.spawned.clone())
+            .unwrap_or_default();
+        let items = entities
+            .iter_with(&respawn_points)
+            .filter(|(_, respawn_point)| loot.contains(&respawn_point.0))
+            .map(|(item, _)| item)
+            .collect::<Vec<_>>();
+        for entity in crates.into_iter().chain(items).chain(loot) {
+            entities.kill(entity);
+        }
+        entities.kill(element_ent);
+    })
+    .system()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestInput, TestSession, TestSessionBuilder};
+
+    /// A player with a musket, and a crate a few tiles to their right that drops a grenade.
+    ///
+    /// Returns the session, and the grenade element.
+    fn crate_session() -> (TestSession, Handle<ElementMeta>) {
+        let mut builder = TestSessionBuilder::room(30, 10);
+        builder.add_player(Vec2::new(64.0, 48.0));
+        let grenade = builder.assets.insert("/grenade", testing::grenade_meta());
+        let bullet = builder.assets.insert(
+            "/bullet",
+            BulletMeta {
+                bounces: 0,
+                ..testing::bullet_meta()
+            },
+        );
+        builder.add_element(
+            "/musket",
+            testing::musket_meta(bullet),
+            Vec2::new(64.0, 40.0),
+        );
+        builder.add_element(
+            "/crate",
+            ElementMeta {
+                name: "Breakable Crate".into(),
+                category: "Map".into(),
+                builtin: BuiltinElementKind::BreakableCrate(BreakableCrateMeta {
+                    atlas: default(),
+                    body_size: Vec2::new(32.0, 32.0),
+                    hit_points: 2,
+                    push_factor: 0.25,
+                    loot: vec![ItemSpawnerEntryMeta {
+                        element: grenade.clone(),
+                        weight: 1.0,
+                    }],
+                    break_particles: None,
+                    break_sound: default(),
+                    break_sound_volume: 0.0,
+                }),
+                ..default()
+            },
+            Vec2::new(200.0, 40.0),
+        );
+
+        let session = builder.build();
+        (session, grenade)
+    }
+
+    /// Inputs that pick up the musket, push the crate, back off, and shoot the crate twice.
+    fn crate_inputs(frame: u64) -> [TestInput; MAX_PLAYERS] {
+        let mut inputs = [TestInput::default(); MAX_PLAYERS];
+        inputs[0] = match frame {
+            10 => TestInput {
+                grab: true,
+                ..default()
+            },
+            20..=99 => TestInput::moving(Vec2::X),
+            100..=119 => TestInput::moving(-Vec2::X),
+            120 => TestInput::moving(Vec2::X),
+            140 | 200 => TestInput {
+                shoot: true,
+                ..default()
+            },
+            _ => default(),
+        };
+        inputs
+    }
+
+    /// Get the positions of the breakable crates in the session.
+    fn crates(session: &TestSession) -> Vec<Vec2> {
+        session
+            .session
+            .world
+            .run_initialized_system(
+                |entities: Res<Entities>,
+                 breakable_crates: Comp<BreakableCrate>,
+                 transforms: Comp<Transform>| {
+                    Ok(entities
+                        .iter_with((&breakable_crates, &transforms))
+                        .map(|(_ent, (_crate, transform))| transform.translation.truncate())
+                        .collect())
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn crate_is_pushed_and_drops_loot_when_shot() {
+        let (mut session, grenade) = crate_session();
+        let step_until = |session: &mut TestSession, frame: u64| {
+            while session.frame() < frame {
+                session.step_scripted(&crate_inputs);
+            }
+        };
+
+        step_until(&mut session, 20);
+        assert!(session.player_inventory(0).is_some());
+        let start = crates(&session)[0];
+        assert!(
+            (start.y - (testing::TILE_SIZE + 16.0)).abs() < 1.0,
+            "Crate isn't on the floor: {start}"
+        );
+
+        // Walking into the crate pushes it along, without walking through it
+        step_until(&mut session, 100);
+        let pushed = crates(&session)[0];
+        let player = session.player_position(0).unwrap();
+        assert!(pushed.x > start.x + 8.0, "Crate wasn't pushed: {pushed}");
+        assert!(
+            player.x + 16.0 <= pushed.x - 16.0 + 1.0,
+            "Player walked into the crate: {player}, {pushed}"
+        );
+
+        // It takes two bullets to break the crate
+        step_until(&mut session, 190);
+        assert_eq!(crates(&session).len(), 1);
+        step_until(&mut session, 260);
+        assert!(crates(&session).is_empty());
+
+        let grenade_path = grenade.path.clone();
+        let has_grenade = session
+            .session
+            .world
+            .run_initialized_system(
+                move |entities: Res<Entities>,
+                      items: Comp<Item>,
+                      element_handles: Comp<ElementHandle>| {
+                    Ok(entities
+                        .iter_with((&items, &element_handles))
+                        .any(|(_ent, (_item, handle))| handle.path == grenade_path))
+                },
+            )
+            .unwrap();
+        assert!(has_grenade, "The crate didn't drop its loot");
+
+        // The crate breaks the same way when the session is rolled back
+        testing::assert_rollback_deterministic(|| crate_session().0, crate_inputs, 260, 8);
+    }
+}
@@ -0,0 +1,213 @@
+//! King-of-the-hill control zones.
+//!
+//! While exactly one player is standing in a control zone, they score points for it in the
+//! [`MatchScore`], and the first player to reach [`CoreConfigMeta::points_to_win`] wins the match.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update)
+        .add_system_to_stage(CoreStage::Last, render_capture_progress);
+}
+
+/// The height of the capture progress bar above the zone.
+const PROGRESS_BAR_THICKNESS: f32 = 4.0;
+/// The space between the top of the zone and the capture progress bar.
+const PROGRESS_BAR_MARGIN: f32 = 8.0;
+
+/// Component for a control zone.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H1QX3QFN0T2E5TPMD83W8QQA"]
+pub struct ControlZone {
+    pub size: Vec2,
+    pub points_per_second: f32,
+    pub capture_color: Color,
+    /// The player that last held the zone, whose capture progress is shown above it.
+    pub holder: Option<usize>,
+    /// The entity that the capture progress bar is rendered with.
+    pub progress_bar: Entity,
+}
+
+impl ControlZone {
+    /// Get the rectangle that players must overlap to stand in the zone, given its transform.
+    pub fn rect(&self, position: Vec3) -> Rect {
+        Rect::new(position.x, position.y, self.size.x, self.size.y)
+    }
+}
+
+/// Component for the entity that renders the capture progress bar of a control zone.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H3MPRCDNCTC5D65PKW2ZJQX8"]
+pub struct ControlZoneProgressBar {
+    pub zone: Entity,
+}
+
+fn hydrate(
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut control_zones: CompMut<ControlZone>,
+    mut progress_bars: CompMut<ControlZoneProgressBar>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawners = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for entity in spawners {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::ControlZone(meta) = &element_meta.builtin {
+            hydrated.insert(entity, MapElementHydrated);
+
+            let rect = Rect::new(0.0, 0.0, meta.size.x, meta.size.y);
+            paths.insert(
+                entity,
+                Path2d {
+                    color: meta.capture_color.0,
+                    points: vec![
+                        rect.top_left(),
+                        rect.top_right(),
+                        rect.bottom_right(),
+                        rect.bottom_left(),
+                        rect.top_left(),
+                    ],
+                    thickness: 2.0,
+                    ..default()
+                },
+            );
+
+            let progress_bar = entities.create();
+            transforms.insert(progress_bar, default());
+            progress_bars.insert(progress_bar, ControlZoneProgressBar { zone: entity });
+
+            control_zones.insert(
+                entity,
+                ControlZone {
+                    size: meta.size,
+                    points_per_second: meta.points_per_second,
+                    capture_color: meta.capture_color.0,
+                    holder: None,
+                    progress_bar,
+                },
+            );
+        }
+    }
+}
+
+/// Score points for the players holding control zones, and end the match once somebody has scored
+/// enough of them.
+fn update(
+    entities: Res<Entities>,
+    core_meta: Res<CoreMetaArc>,
+    mut score: ResMut<MatchScore>,
+    mut control_zones: CompMut<ControlZone>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    transforms: Comp<Transform>,
+    bodies: Comp<KinematicBody>,
+) {
+    if score.result.is_some() {
+        return;
+    }
+
+    let mut bitset = player_indexes.bitset().clone();
+    bitset.bit_and(transforms.bitset());
+    bitset.bit_and(bodies.bitset());
+    bitset.bit_andnot(players_killed.bitset());
+
+    for (_ent, (zone, transform)) in entities.iter_with((&mut control_zones, &transforms)) {
+        let zone_rect = zone.rect(transform.translation);
+
+        let mut occupants = entities
+            .iter_with_bitset(&bitset)
+            .filter(|player_ent| {
+                let transform = transforms.get(*player_ent).unwrap();
+                let body = bodies.get(*player_ent).unwrap();
+                body.bounding_box(*transform).overlaps(&zone_rect)
+            })
+            .map(|player_ent| player_indexes.get(player_ent).unwrap().0);
+
+        // Contested and empty zones don't score anything
+        let Some(holder) = occupants.next() else {
+            continue;
+        };
+        if occupants.any(|other| other != holder) {
+            continue;
+        }
+
+        zone.holder = Some(holder);
+        let points = &mut score.points[holder];
+        *points += zone.points_per_second / crate::FPS;
+
+        if *points >= core_meta.config.points_to_win {
+            info!(
+                player_idx = holder,
+                "Player won by holding the control zone"
+            );
+            score.result = Some(MatchResult::Winner(holder));
+            return;
+        }
+    }
+}
+
+/// Render the capture progress of the player holding each zone above it.
+fn render_capture_progress(
+    mut entities: ResMut<Entities>,
+    core_meta: Res<CoreMetaArc>,
+    score: Res<MatchScore>,
+    control_zones: Comp<ControlZone>,
+    progress_bars: Comp<ControlZoneProgressBar>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+) {
+    let mut orphaned_bars = Vec::new();
+    for (bar_ent, bar) in entities.iter_with(&progress_bars) {
+        let (Some(zone), Some(zone_transform)) =
+            (control_zones.get(bar.zone), transforms.get(bar.zone).copied()) else {
+            // Remove progress bars for zones that have been deleted
+            orphaned_bars.push(bar_ent);
+            continue;
+        };
+
+        let progress = zone
+            .holder
+            .map(|holder| score.points[holder] / core_meta.config.points_to_win)
+            .unwrap_or_default()
+            .clamp(0.0, 1.0);
+        if progress == 0.0 {
+            paths.remove(bar_ent);
+            continue;
+        }
+
+        let mut translation = zone_transform.translation;
+        translation.y += zone.size.y / 2.0 + PROGRESS_BAR_MARGIN;
+        transforms.insert(bar_ent, Transform::from_translation(translation));
+
+        let left = -zone.size.x / 2.0;
+        paths.insert(
+            bar_ent,
+            Path2d {
+                color: zone.capture_color,
+                points: vec![vec2(left, 0.0), vec2(left + zone.size.x * progress, 0.0)],
+                thickness: PROGRESS_BAR_THICKNESS,
+                ..default()
+            },
+        );
+    }
+
+    for bar_ent in orphaned_bars {
+        entities.kill(bar_ent);
+    }
+}
@@ -169,7 +169,7 @@ fn update_thrown_crates(
     players: Comp<PlayerIdx>,
     collision_world: CollisionWorld,
     mut bodies: CompMut<KinematicBody>,
-    mut audio_events: ResMut<AudioEvents>,
+    mut audio_events: ResMut<SoundEvents>,
     transforms: Comp<Transform>,
     spawners: Comp<DehydrateOutOfBounds>,
     invincibles: CompMut<Invincibility>,
@@ -223,7 +223,7 @@ fn update_thrown_crates(
         if colliding_with_tile && !thrown_crate.was_colliding {
             thrown_crate.was_colliding = true;
             thrown_crate.crate_break_state += 1;
-            audio_events.play(bounce_sound.clone(), *bounce_sound_volume);
+            audio_events.play_from(bounce_sound.clone(), *bounce_sound_volume, Some(transform));
         } else if !colliding_with_tile {
             thrown_crate.was_colliding = false;
         }
@@ -249,12 +249,15 @@ fn update_thrown_crates(
             .collect::<Vec<_>>();
 
         for player_entity in &colliding_with_players {
-            commands.add(PlayerCommand::kill(
+            commands.add(PlayerCommand::kill_by(
                 *player_entity,
                 Some(transform.translation.xy()),
+                thrown_crate.owner,
+                Some(entity),
             ));
         }
         let kill_nearby_colliding: bool = kill_all_colliding_if_freshly_thrown(
+            entity,
             thrown_crate,
             &collision_world,
             &players,
@@ -275,7 +278,7 @@ fn update_thrown_crates(
             let breaking_anim_fps = *breaking_anim_fps;
             let atlas = breaking_atlas.clone();
 
-            audio_events.play(break_sound.clone(), *break_sound_volume);
+            audio_events.play_from(break_sound.clone(), *break_sound_volume, Some(transform));
 
             commands.add(
                 move |mut entities: ResMut<Entities>,
@@ -311,6 +314,7 @@ fn update_thrown_crates(
 }
 
 fn kill_all_colliding_if_freshly_thrown(
+    entity: Entity,
     thrown_crate: &ThrownCrate,
     collision_world: &CollisionWorld,
     players: &Comp<PlayerIdx>,
@@ -330,9 +334,11 @@ fn kill_all_colliding_if_freshly_thrown(
     if !colliding_with_players.is_empty() {
         for player_entity in &colliding_with_players {
             if invincibles.get(*player_entity).is_none() {
-                commands.add(PlayerCommand::kill(
+                commands.add(PlayerCommand::kill_by(
                     *player_entity,
                     Some(transform.translation.xy()),
+                    thrown_crate.owner,
+                    Some(entity),
                 ));
             }
         }
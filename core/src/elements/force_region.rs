@@ -0,0 +1,36 @@
+//! Map elements for [`ForceRegion`]s, such as wind tunnels, fans and conveyor belts.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate);
+}
+
+fn hydrate(
+    entities: Res<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut force_regions: CompMut<ForceRegion>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawners = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for entity in spawners {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::ForceRegion(meta) = &element_meta.builtin {
+            hydrated.insert(entity, MapElementHydrated);
+            force_regions.insert(entity, meta.clone());
+        }
+    }
+}
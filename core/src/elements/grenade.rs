@@ -19,6 +19,8 @@ pub struct IdleGrenade;
 pub struct LitGrenade {
     /// The amount of time left until the grenade explodes.
     pub fuse_time: Timer,
+    /// The player that lit the grenade.
+    pub owner: Option<Entity>,
 }
 
 fn hydrate(
@@ -111,10 +113,12 @@ fn update_idle_grenades(
     entities: Res<Entities>,
     element_handles: Comp<ElementHandle>,
     element_assets: BevyAssets<ElementMeta>,
-    mut audio_events: ResMut<AudioEvents>,
+    mut audio_events: ResMut<SoundEvents>,
+    transforms: Comp<Transform>,
     mut idle_grenades: CompMut<IdleGrenade>,
     mut animated_sprites: CompMut<AnimatedSprite>,
     mut items_used: CompMut<ItemUsed>,
+    player_inventories: PlayerInventories,
     mut commands: Commands,
 ) {
     for (entity, (_grenade, element_handle)) in
@@ -135,12 +139,20 @@ fn update_idle_grenades(
         let fuse_time = *fuse_time;
 
         if items_used.get(entity).is_some() {
-            audio_events.play(fuse_sound.clone(), *fuse_sound_volume);
+            audio_events.play_from(
+                fuse_sound.clone(),
+                *fuse_sound_volume,
+                transforms.get(entity),
+            );
             items_used.remove(entity);
             let animated_sprite = animated_sprites.get_mut(entity).unwrap();
             animated_sprite.frames = Arc::from([3, 4, 5]);
             animated_sprite.repeat = true;
             animated_sprite.fps = 8.0;
+            let owner = player_inventories
+                .iter()
+                .find_map(|x| x.filter(|x| x.inventory == entity))
+                .map(|x| x.player);
             commands.add(
                 move |mut idle: CompMut<IdleGrenade>, mut lit: CompMut<LitGrenade>| {
                     idle.remove(entity);
@@ -151,6 +163,7 @@ fn update_idle_grenades(
                                 Duration::from_secs_f32(fuse_time),
                                 TimerMode::Once,
                             ),
+                            owner,
                         },
                     );
                 },
@@ -164,8 +177,8 @@ fn update_lit_grenades(
     element_handles: Comp<ElementHandle>,
     element_assets: BevyAssets<ElementMeta>,
     transforms: CompMut<Transform>,
-    mut audio_events: ResMut<AudioEvents>,
-    mut trauma_events: ResMut<CameraTraumaEvents>,
+    mut audio_events: ResMut<SoundEvents>,
+    mut camera_trauma: ResMut<CameraTrauma>,
     mut lit_grenades: CompMut<LitGrenade>,
     mut hydrated: CompMut<MapElementHydrated>,
     mut emote_regions: CompMut<EmoteRegion>,
@@ -191,6 +204,7 @@ fn update_lit_grenades(
             explosion_atlas,
             explosion_fps,
             explosion_frames,
+            explosion_particles,
             fin_anim,
             ..
         } = &element_meta.builtin else {
@@ -230,9 +244,13 @@ fn update_lit_grenades(
 
         // If it's time to explode
         if grenade.fuse_time.finished() {
-            audio_events.play(explosion_sound.clone(), *explosion_volume);
+            audio_events.play_from(
+                explosion_sound.clone(),
+                *explosion_volume,
+                transforms.get(entity),
+            );
 
-            trauma_events.send(5.0);
+            camera_trauma.add(0.5);
 
             // Cause the item to respawn by un-hydrating it's spawner.
             hydrated.remove(**spawner);
@@ -240,6 +258,14 @@ fn update_lit_grenades(
             explosion_transform.translation.z = -10.0; // On top of almost everything
             explosion_transform.rotation = Quat::IDENTITY;
 
+            if let Some(particles) = explosion_particles {
+                commands.add(spawn_particle_effect(
+                    particles.clone(),
+                    explosion_transform,
+                    false,
+                ));
+            }
+
             // Clone types for move into closure
             let damage_region_size = *damage_region_size;
             let damage_region_lifetime = *damage_region_lifetime;
@@ -247,10 +273,13 @@ fn update_lit_grenades(
             let explosion_atlas = explosion_atlas.clone();
             let explosion_fps = *explosion_fps;
             let explosion_frames = *explosion_frames;
+            let knockback = element_meta.knockback.clone();
+            let owner = grenade.owner;
             commands.add(
                 move |mut entities: ResMut<Entities>,
                       mut transforms: CompMut<Transform>,
                       mut damage_regions: CompMut<DamageRegion>,
+                      mut knockbacks: CompMut<Knockback>,
                       mut lifetimes: CompMut<Lifetime>,
                       mut sprites: CompMut<AtlasSprite>,
                       mut animated_sprites: CompMut<AnimatedSprite>| {
@@ -267,6 +296,15 @@ fn update_lit_grenades(
                         },
                     );
                     lifetimes.insert(ent, Lifetime::new(damage_region_lifetime));
+                    if let Some(meta) = &knockback {
+                        knockbacks.insert(
+                            ent,
+                            Knockback {
+                                meta: meta.clone(),
+                                owner,
+                            },
+                        );
+                    }
 
                     // Spawn the explosion animation
                     let ent = entities.create();
@@ -0,0 +1,315 @@
+//! Generic item spawners, that spawn items picked at random from a weighted table.
+//!
+//! Each spawned item is created as a map element without a layer, which is hydrated into the item
+//! by the item's own element implementation. Once the item is picked up or destroyed, the element
+//! is removed, and the spawner waits for its cooldown before spawning another item.
+
+use crate::{prelude::*, random::GlobalRng};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::First, update);
+}
+
+/// Get the key of the element property that overrides the weight of the item at `idx` in a
+/// spawner's table.
+pub fn weight_property_key(idx: usize) -> String {
+    format!("weight_{idx}")
+}
+
+/// Component for an item spawner.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H4B8W4C5JQ0TQ4Y6T3ZB0D2N"]
+pub struct ItemSpawner {
+    /// The item elements that have been spawned and are waiting to be picked up.
+    pub spawned: Vec<Entity>,
+    /// Counts down to spawning the next item.
+    pub timer: Timer,
+    /// The telegraph animation that is playing before an item appears, if any.
+    pub telegraph: Option<ItemSpawnerTelegraph>,
+    /// Whether the spawner has already logged that it has nothing to spawn.
+    pub logged_nothing_to_spawn: bool,
+}
+
+/// The telegraph animation of an [`ItemSpawner`], and the item that will appear once it's done.
+#[derive(Clone, Debug)]
+pub struct ItemSpawnerTelegraph {
+    pub entity: Entity,
+    pub element: Handle<ElementMeta>,
+    pub timer: Timer,
+}
+
+fn hydrate(
+    entities: Res<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut item_spawners: CompMut<ItemSpawner>,
+    mut element_kill_callbacks: CompMut<ElementKillCallback>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawners = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for entity in spawners {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::ItemSpawner(meta) = &element_meta.builtin {
+            hydrated.insert(entity, MapElementHydrated);
+            item_spawners.insert(
+                entity,
+                ItemSpawner {
+                    spawned: default(),
+                    timer: Timer::new(meta.initial_delay, TimerMode::Once),
+                    telegraph: None,
+                    logged_nothing_to_spawn: false,
+                },
+            );
+            element_kill_callbacks.insert(entity, ElementKillCallback::new(kill_spawner(entity)));
+        }
+    }
+}
+
+fn update(
+    mut entities: ResMut<Entities>,
+    time: Res<Time>,
+    rng: Res<GlobalRng>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut element_handles: CompMut<ElementHandle>,
+    element_properties: Comp<ElementProperties>,
+    hydrated: Comp<MapElementHydrated>,
+    inventories: Comp<Inventory>,
+    mut item_spawners: CompMut<ItemSpawner>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut transforms: CompMut<Transform>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut animated_sprites: CompMut<AnimatedSprite>,
+) {
+    let spawners = entities
+        .iter_with(&item_spawners)
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    for spawner_ent in spawners {
+        let Some(element_meta) = element_handles
+            .get(spawner_ent)
+            .and_then(|handle| element_assets.get(&handle.get_bevy_handle())) else {
+            continue;
+        };
+        let BuiltinElementKind::ItemSpawner(meta) = &element_meta.builtin else {
+            continue;
+        };
+        let transform = *transforms.get(spawner_ent).unwrap();
+        let spawner = item_spawners.get_mut(spawner_ent).unwrap();
+
+        // Let go of the items that have been picked up or destroyed
+        if release_taken_items(
+            &mut entities,
+            &mut spawner.spawned,
+            &hydrated,
+            &inventories,
+            &mut respawn_points,
+        ) {
+            spawner.timer = Timer::new(meta.cooldown, TimerMode::Once);
+        }
+
+        // Spawn the item once the telegraph animation is done
+        if let Some(telegraph) = &mut spawner.telegraph {
+            telegraph.timer.tick(time.delta());
+            if telegraph.timer.finished() {
+                entities.kill(telegraph.entity);
+                let item_element = entities.create();
+                element_handles.insert(item_element, ElementHandle(telegraph.element.clone()));
+                transforms.insert(item_element, transform);
+                spawner.spawned.push(item_element);
+                spawner.telegraph = None;
+                spawner.timer = Timer::new(meta.cooldown, TimerMode::Once);
+            }
+            continue;
+        }
+
+        if spawner.spawned.len() >= meta.max_concurrent as usize {
+            continue;
+        }
+        spawner.timer.tick(time.delta());
+        if !spawner.timer.finished() {
+            continue;
+        }
+
+        // Pick the item, using the instance's weight overrides
+        let properties = element_properties.get(spawner_ent);
+        let weights = meta
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                properties
+                    .and_then(|properties| properties.get(&weight_property_key(i)))
+                    .and_then(|value| value.as_number())
+                    .unwrap_or(entry.weight)
+            })
+            .collect::<Vec<_>>();
+        let Some(item_idx) = choose_weighted(&weights, rng.f32()) else {
+            if !spawner.logged_nothing_to_spawn {
+                spawner.logged_nothing_to_spawn = true;
+                if weights.is_empty() {
+                    warn!(element = %element_meta.name, "Item spawner has an empty spawn table");
+                } else {
+                    warn!(element = %element_meta.name, "Item spawner's weights sum to zero");
+                }
+            }
+            continue;
+        };
+        let element = meta.items[item_idx].element.clone();
+
+        if let Some(telegraph) = &meta.telegraph {
+            let entity = entities.create();
+            transforms.insert(entity, transform);
+            atlas_sprites.insert(entity, AtlasSprite::new(telegraph.atlas.clone()));
+            animated_sprites.insert(
+                entity,
+                AnimatedSprite {
+                    frames: (0..telegraph.frames).collect(),
+                    fps: telegraph.fps,
+                    repeat: false,
+                    ..default()
+                },
+            );
+            spawner.telegraph = Some(ItemSpawnerTelegraph {
+                entity,
+                element,
+                timer: Timer::from_seconds(
+                    telegraph.frames as f32 / telegraph.fps,
+                    TimerMode::Once,
+                ),
+            });
+        } else {
+            let item_element = entities.create();
+            element_handles.insert(item_element, ElementHandle(element));
+            transforms.insert(item_element, transform);
+            spawner.spawned.push(item_element);
+            spawner.timer = Timer::new(meta.cooldown, TimerMode::Once);
+        }
+    }
+}
+
+/// Let go of the spawned item elements whose items have been picked up or destroyed, so that
+/// they can be replaced. Returns whether any items were let go of.
+pub fn release_taken_items(
+    entities: &mut Entities,
+    spawned: &mut Vec<Entity>,
+    hydrated: &ComponentStore<MapElementHydrated>,
+    inventories: &Comp<Inventory>,
+    respawn_points: &mut CompMut<DehydrateOutOfBounds>,
+) -> bool {
+    let mut released = Vec::new();
+    spawned.retain(|&item_element| {
+        // The element hasn't been hydrated into its item yet
+        if !hydrated.contains(item_element) {
+            return true;
+        }
+        let item = entities
+            .iter_with(&*respawn_points)
+            .find(|(_, respawn_point)| respawn_point.0 == item_element)
+            .map(|(item, _)| item);
+        let is_held = |item| {
+            entities
+                .iter_with(inventories)
+                .any(|(_, inventory)| inventory.0 == Some(item))
+        };
+        if item.map(is_held).unwrap_or(true) {
+            released.push((item_element, item));
+            false
+        } else {
+            true
+        }
+    });
+    for &(item_element, item) in &released {
+        // Keep the item from respawning at its element when it goes out of bounds
+        if let Some(item) = item {
+            respawn_points.remove(item);
+        }
+        entities.kill(item_element);
+    }
+    !released.is_empty()
+}
+
+/// Pick an index from a list of weights, using a `roll` from `0.0` to `1.0`.
+///
+/// Negative weights are treated as zero. Returns [`None`] if there are no weights, or they sum to
+/// zero.
+pub fn choose_weighted(weights: &[f32], roll: f32) -> Option<usize> {
+    let total = weights.iter().map(|weight| weight.max(0.0)).sum::<f32>();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut remaining = roll * total;
+    let mut last_picked = None;
+    for (i, weight) in weights.iter().enumerate() {
+        let weight = weight.max(0.0);
+        if weight == 0.0 {
+            continue;
+        }
+        if remaining < weight {
+            return Some(i);
+        }
+        remaining -= weight;
+        last_picked = Some(i);
+    }
+
+    // Rounding errors can leave a little bit left over for a roll close to `1.0`
+    last_picked
+}
+
+/// Kill a spawner along with the items it spawned that haven't been picked up yet.
+fn kill_spawner(spawner_ent: Entity) -> System {
+    (move |mut entities: ResMut<Entities>,
+           item_spawners: Comp<ItemSpawner>,
+           respawn_points: Comp<DehydrateOutOfBounds>| {
+        if let Some(spawner) = item_spawners.get(spawner_ent) {
+            let items = entities
+                .iter_with(&respawn_points)
+                .filter(|(_, respawn_point)| spawner.spawned.contains(&respawn_point.0))
+                .map(|(item, _)| item)
+                .collect::<Vec<_>>();
+            for entity in items
+                .into_iter()
+                .chain(spawner.spawned.iter().copied())
+                .chain(spawner.telegraph.as_ref().map(|telegraph| telegraph.entity))
+            {
+                entities.kill(entity);
+            }
+        }
+        entities.kill(spawner_ent);
+    })
+    .system()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choose_weighted_follows_weights() {
+        assert_eq!(choose_weighted(&[1.0, 3.0], 0.0), Some(0));
+        assert_eq!(choose_weighted(&[1.0, 3.0], 0.3), Some(1));
+        assert_eq!(choose_weighted(&[1.0, 3.0], 1.0), Some(1));
+        assert_eq!(choose_weighted(&[0.0, 2.0, -1.0], 0.9), Some(1));
+    }
+
+    #[test]
+    fn choose_weighted_with_nothing_to_pick() {
+        assert_eq!(choose_weighted(&[], 0.5), None);
+        assert_eq!(choose_weighted(&[0.0, 0.0], 0.5), None);
+        assert_eq!(choose_weighted(&[-1.0], 0.5), None);
+    }
+}
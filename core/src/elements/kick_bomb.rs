@@ -109,7 +109,8 @@ fn update_idle_kick_bombs(
     entities: Res<Entities>,
     mut commands: Commands,
     mut items_used: CompMut<ItemUsed>,
-    mut audio_events: ResMut<AudioEvents>,
+    mut audio_events: ResMut<SoundEvents>,
+    transforms: Comp<Transform>,
     element_handles: Comp<ElementHandle>,
     mut idle_bombs: CompMut<IdleKickBomb>,
     element_assets: BevyAssets<ElementMeta>,
@@ -136,7 +137,11 @@ fn update_idle_kick_bombs(
         let fuse_time = *fuse_time;
 
         if items_used.get(entity).is_some() {
-            audio_events.play(fuse_sound.clone(), *fuse_sound_volume);
+            audio_events.play_from(
+                fuse_sound.clone(),
+                *fuse_sound_volume,
+                transforms.get(entity),
+            );
             items_used.remove(entity);
             let animated_sprite = animated_sprites.get_mut(entity).unwrap();
             animated_sprite.frames = Arc::from([3, 4, 5]);
@@ -165,8 +170,8 @@ fn update_lit_kick_bombs(
 
     collision_world: CollisionWorld,
     player_indexes: Comp<PlayerIdx>,
-    mut audio_events: ResMut<AudioEvents>,
-    mut trauma_events: ResMut<CameraTraumaEvents>,
+    mut audio_events: ResMut<SoundEvents>,
+    mut camera_trauma: ResMut<CameraTrauma>,
     mut lit_grenades: CompMut<LitKickBomb>,
     mut sprites: CompMut<AtlasSprite>,
     mut bodies: CompMut<KinematicBody>,
@@ -198,6 +203,7 @@ fn update_lit_kick_bombs(
             explosion_atlas,
             explosion_fps,
             explosion_frames,
+            explosion_particles,
             fin_anim,
             ..
         } = &element_meta.builtin else {
@@ -263,9 +269,13 @@ fn update_lit_kick_bombs(
 
         // If it's time to explode
         if kick_bomb.fuse_time.finished() || should_explode {
-            audio_events.play(explosion_sound.clone(), *explosion_volume);
+            audio_events.play_from(
+                explosion_sound.clone(),
+                *explosion_volume,
+                transforms.get(entity),
+            );
 
-            trauma_events.send(7.5);
+            camera_trauma.add(0.75);
 
             // Cause the item to respawn by un-hydrating it's spawner.
             hydrated.remove(**spawner);
@@ -273,6 +283,14 @@ fn update_lit_kick_bombs(
             explosion_transform.translation.z = -10.0; // On top of almost everything
             explosion_transform.rotation = Quat::IDENTITY;
 
+            if let Some(particles) = explosion_particles {
+                commands.add(spawn_particle_effect(
+                    particles.clone(),
+                    explosion_transform,
+                    false,
+                ));
+            }
+
             // Clone types for move into closure
             let damage_region_size = *damage_region_size;
             let damage_region_lifetime = *damage_region_lifetime;
@@ -280,10 +298,12 @@ fn update_lit_kick_bombs(
             let explosion_atlas = explosion_atlas.clone();
             let explosion_fps = *explosion_fps;
             let explosion_frames = *explosion_frames;
+            let knockback = element_meta.knockback.clone();
             commands.add(
                 move |mut entities: ResMut<Entities>,
                       mut transforms: CompMut<Transform>,
                       mut damage_regions: CompMut<DamageRegion>,
+                      mut knockbacks: CompMut<Knockback>,
                       mut lifetimes: CompMut<Lifetime>,
                       mut sprites: CompMut<AtlasSprite>,
                       mut animated_sprites: CompMut<AnimatedSprite>| {
@@ -300,6 +320,15 @@ fn update_lit_kick_bombs(
                         },
                     );
                     lifetimes.insert(ent, Lifetime::new(damage_region_lifetime));
+                    if let Some(meta) = &knockback {
+                        knockbacks.insert(
+                            ent,
+                            Knockback {
+                                meta: meta.clone(),
+                                owner: None,
+                            },
+                        );
+                    }
 
                     // Spawn the explosion animation
                     let ent = entities.create();
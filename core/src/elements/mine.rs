@@ -150,8 +150,8 @@ fn update_thrown_mines(
     entities: Res<Entities>,
     element_handles: Comp<ElementHandle>,
     element_assets: BevyAssets<ElementMeta>,
-    mut audio_events: ResMut<AudioEvents>,
-    mut trauma_events: ResMut<CameraTraumaEvents>,
+    mut audio_events: ResMut<SoundEvents>,
+    mut camera_trauma: ResMut<CameraTrauma>,
     mut thrown_mines: CompMut<ThrownMine>,
     mut animated_sprites: CompMut<AnimatedSprite>,
     mut hydrated: CompMut<MapElementHydrated>,
@@ -186,14 +186,15 @@ fn update_thrown_mines(
             armed_frames,
             armed_fps,
             damage_region_size,
-            damage_region_lifetime, explosion_volume, arm_sound_volume, explosion_lifetime, .. } = &element_meta.builtin else {
+            damage_region_lifetime, explosion_volume, arm_sound_volume, explosion_lifetime,
+            explosion_particles, .. } = &element_meta.builtin else {
             unreachable!();
         };
 
         thrown_mine.arm_delay.tick(time.delta());
 
         if thrown_mine.arm_delay.just_finished() {
-            audio_events.play(arm_sound.clone(), *arm_sound_volume);
+            audio_events.play_from(arm_sound.clone(), *arm_sound_volume, transforms.get(entity));
 
             sprite.frames = (0..*armed_frames).collect();
             sprite.fps = *armed_fps;
@@ -210,7 +211,7 @@ fn update_thrown_mines(
         if !colliding_with_players.is_empty() && thrown_mine.arm_delay.finished() {
             let mine_transform = *transforms.get(entity).unwrap();
 
-            trauma_events.send(6.0);
+            camera_trauma.add(0.6);
 
             for player in &colliding_with_players {
                 commands.add(PlayerCommand::kill(
@@ -219,10 +220,24 @@ fn update_thrown_mines(
                 ));
             }
 
-            audio_events.play(explosion_sound.clone(), *explosion_volume);
+            audio_events.play_from(
+                explosion_sound.clone(),
+                *explosion_volume,
+                Some(&mine_transform),
+            );
 
             hydrated.remove(**spawner);
 
+            if let Some(particles) = explosion_particles {
+                let mut explosion_transform = mine_transform;
+                explosion_transform.rotation = Quat::IDENTITY;
+                commands.add(spawn_particle_effect(
+                    particles.clone(),
+                    explosion_transform,
+                    false,
+                ));
+            }
+
             // Clone types for move into closure
             let damage_region_size = *damage_region_size;
             let damage_region_lifetime = *damage_region_lifetime;
@@ -230,10 +245,12 @@ fn update_thrown_mines(
             let explosion_atlas = explosion_atlas.clone();
             let explosion_fps = *explosion_fps;
             let explosion_frames = *explosion_frames;
+            let knockback = element_meta.knockback.clone();
             commands.add(
                 move |mut entities: ResMut<Entities>,
                       mut transforms: CompMut<Transform>,
                       mut damage_regions: CompMut<DamageRegion>,
+                      mut knockbacks: CompMut<Knockback>,
                       mut lifetimes: CompMut<Lifetime>,
                       mut sprites: CompMut<AtlasSprite>,
                       mut animated_sprites: CompMut<AnimatedSprite>| {
@@ -254,6 +271,15 @@ fn update_thrown_mines(
                         },
                     );
                     lifetimes.insert(damage_ent, Lifetime::new(damage_region_lifetime));
+                    if let Some(meta) = &knockback {
+                        knockbacks.insert(
+                            damage_ent,
+                            Knockback {
+                                meta: meta.clone(),
+                                owner: None,
+                            },
+                        );
+                    }
 
                     // Spawn the explosion animation
                     let ent = entities.create();
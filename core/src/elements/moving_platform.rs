@@ -0,0 +1,248 @@
+//! Platforms that move along the waypoints of their element, carrying anything standing on them.
+//!
+//! The element itself stays where it was placed in the map, and spawns a separate platform entity
+//! that collides like a map tile. The platform's path starts at the element's position and goes
+//! through each of the element's [`ElementWaypoints`], which are relative to the element.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update);
+}
+
+/// The key of the element property that sets how fast the platform moves, in pixels per second.
+pub const SPEED_PROPERTY: &str = "speed";
+/// The key of the element property that sets how many seconds the platform waits at each waypoint.
+pub const WAIT_TIME_PROPERTY: &str = "wait_time";
+/// The key of the element property that sets whether the platform goes back and forth along its
+/// path, instead of going from the last waypoint straight back to the first one.
+pub const PING_PONG_PROPERTY: &str = "ping_pong";
+
+/// How far above or below the top of a platform the bottom of a body may be for it to be riding
+/// the platform.
+const RIDER_TOLERANCE: f32 = 2.0;
+
+/// Component for a moving platform.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H2TQVDXPQKNYDXJMCJ10Y7GH"]
+pub struct MovingPlatform {
+    /// The element entity that the platform's path is relative to.
+    pub anchor: Entity,
+    pub size: Vec2,
+    /// The index of the point in the path that the platform is moving to, where `0` is the
+    /// anchor's position.
+    pub target: usize,
+    /// Whether the platform is going back through the path, when it is ping-ponging.
+    pub reversing: bool,
+    /// The seconds left to wait before moving on from the current waypoint.
+    pub wait_time_left: f32,
+    /// The velocity of the platform in this frame, in [`KinematicBody::velocity`] units.
+    pub velocity: Vec2,
+    /// The bodies that were standing on the platform in the last frame.
+    pub riders: Vec<Entity>,
+}
+
+impl MovingPlatform {
+    /// Get the area that the bottom of a body must be in for it to ride the platform.
+    fn rider_rect(&self, position: Vec3) -> Rect {
+        let top = position.y + self.size.y / 2.0;
+        Rect::new(position.x, top, self.size.x, RIDER_TOLERANCE * 2.0)
+    }
+
+    /// Move on to the next point of a path with `len` points.
+    fn advance_target(&mut self, len: usize, ping_pong: bool) {
+        if !ping_pong {
+            self.reversing = false;
+            self.target = (self.target + 1) % len;
+            return;
+        }
+
+        if self.target == 0 {
+            self.reversing = false;
+        } else if self.target == len - 1 {
+            self.reversing = true;
+        }
+        if self.reversing {
+            self.target -= 1;
+        } else {
+            self.target += 1;
+        }
+    }
+}
+
+fn hydrate(
+    mut entities: ResMut<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut moving_platforms: CompMut<MovingPlatform>,
+    mut transforms: CompMut<Transform>,
+    mut colliders: CompMut<Collider>,
+    mut tile_collisions: CompMut<TileCollisionKind>,
+    mut paths: CompMut<Path2d>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawners = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for entity in spawners {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::MovingPlatform(meta) = &element_meta.builtin {
+            hydrated.insert(entity, MapElementHydrated);
+
+            let transform = *transforms.get(entity).unwrap();
+            let platform = entities.create();
+            transforms.insert(platform, transform);
+            colliders.insert(
+                platform,
+                Collider {
+                    shape: ColliderShape::Rectangle { size: meta.size },
+                    ..default()
+                },
+            );
+            tile_collisions.insert(platform, meta.collision);
+            paths.insert(
+                platform,
+                Path2d {
+                    color: meta.color.0,
+                    points: vec![vec2(-meta.size.x / 2.0, 0.0), vec2(meta.size.x / 2.0, 0.0)],
+                    thickness: meta.size.y,
+                    ..default()
+                },
+            );
+            moving_platforms.insert(
+                platform,
+                MovingPlatform {
+                    anchor: entity,
+                    size: meta.size,
+                    target: 0,
+                    reversing: false,
+                    wait_time_left: 0.0,
+                    velocity: Vec2::ZERO,
+                    riders: default(),
+                },
+            );
+        }
+    }
+}
+
+/// Move the platforms along their paths, and the bodies standing on them along with them.
+fn update(
+    mut entities: ResMut<Entities>,
+    time: Res<Time>,
+    element_assets: BevyAssets<ElementMeta>,
+    element_handles: Comp<ElementHandle>,
+    element_properties: Comp<ElementProperties>,
+    element_waypoints: Comp<ElementWaypoints>,
+    mut moving_platforms: CompMut<MovingPlatform>,
+    mut transforms: CompMut<Transform>,
+    mut bodies: CompMut<KinematicBody>,
+) {
+    let delta = time.delta().as_secs_f32();
+    let no_overrides = ElementProperties::default();
+    let mut orphaned_platforms = Vec::new();
+
+    for (platform_ent, platform) in entities.iter_with(&mut moving_platforms) {
+        let anchor = platform.anchor;
+        let (Some(element_handle), Some(anchor_pos)) = (
+            element_handles.get(anchor),
+            transforms.get(anchor).map(|x| x.translation.truncate()),
+        ) else {
+            // Remove platforms for elements that have been deleted
+            orphaned_platforms.push(platform_ent);
+            continue;
+        };
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let properties = element_properties.get(anchor).unwrap_or(&no_overrides);
+        let property = |key: &str| element_meta.editor.property(properties, key);
+        let speed = property(SPEED_PROPERTY)
+            .and_then(|x| x.as_number())
+            .unwrap_or_default();
+        let wait_time = property(WAIT_TIME_PROPERTY)
+            .and_then(|x| x.as_number())
+            .unwrap_or_default();
+        let ping_pong = property(PING_PONG_PROPERTY)
+            .and_then(|x| x.as_bool())
+            .unwrap_or_default();
+
+        let path = [Vec2::ZERO]
+            .into_iter()
+            .chain(
+                element_waypoints
+                    .get(anchor)
+                    .into_iter()
+                    .flat_map(|x| x.iter().copied()),
+            )
+            .map(|point| anchor_pos + point)
+            .collect::<Vec<_>>();
+        platform.target %= path.len();
+
+        let translation = transforms.get(platform_ent).unwrap().translation;
+        let position = translation.truncate();
+        let mut movement = Vec2::ZERO;
+        if path.len() == 1 {
+            // Follow the element around when it doesn't have a path
+            movement = path[0] - position;
+        } else if platform.wait_time_left > 0.0 {
+            platform.wait_time_left -= delta;
+        } else {
+            let to_target = path[platform.target] - position;
+            let step = speed * delta;
+            if to_target.length() <= step {
+                movement = to_target;
+                platform.wait_time_left = wait_time;
+                platform.advance_target(path.len(), ping_pong);
+            } else {
+                movement = to_target.normalize_or_zero() * step;
+            }
+        }
+        platform.velocity = if delta > 0.0 {
+            movement / delta / crate::FPS
+        } else {
+            Vec2::ZERO
+        };
+
+        // Find the bodies standing on the platform before it moves
+        let rider_rect = platform.rider_rect(translation);
+        let riders = entities
+            .iter_with((&bodies, &transforms))
+            .filter(|(_, (body, transform))| {
+                if body.is_deactivated || !body.is_on_ground || body.velocity.y > 0.0 {
+                    return false;
+                }
+                let rect = body.bounding_box(**transform);
+                rect.overlaps(&rider_rect) && rect.min.y >= rider_rect.min.y
+            })
+            .map(|(ent, _)| ent)
+            .collect::<Vec<_>>();
+
+        transforms.get_mut(platform_ent).unwrap().translation += movement.extend(0.0);
+        for rider in &riders {
+            transforms.get_mut(*rider).unwrap().translation += movement.extend(0.0);
+        }
+
+        // Bodies that jump or walk off of the platform keep its horizontal momentum
+        for former_rider in platform.riders.iter().filter(|x| !riders.contains(*x)) {
+            if let Some(body) = bodies.get_mut(*former_rider) {
+                body.velocity.x += platform.velocity.x;
+            }
+        }
+        platform.riders = riders;
+    }
+
+    for platform_ent in orphaned_platforms {
+        entities.kill(platform_ent);
+    }
+}
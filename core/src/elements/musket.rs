@@ -116,11 +116,12 @@ fn update(
     mut muskets: CompMut<Musket>,
     transforms: CompMut<Transform>,
     mut sprites: CompMut<AtlasSprite>,
-    mut audio_events: ResMut<AudioEvents>,
+    mut audio_events: ResMut<SoundEvents>,
 
     player_inventories: PlayerInventories,
     mut items_used: CompMut<ItemUsed>,
     items_dropped: CompMut<ItemDropped>,
+    item_charges: Comp<ItemCharge>,
     time: Res<Time>,
 ) {
     for (entity, (musket, element_handle)) in entities.iter_with((&mut muskets, &element_handles)) {
@@ -136,10 +137,12 @@ fn update(
             shoot_lifetime,
             cooldown,
             bullet_meta,
+            homing,
             shoot_sound,
             empty_shoot_sound,
             shoot_sound_volume,
             empty_shoot_sound_volume,
+            shoot_particles,
             ..
         } = &element_meta.builtin else {
             unreachable!();
@@ -162,14 +165,22 @@ fn update(
             if item_used && musket.cooldown.finished() {
                 // Empty
                 if musket.ammo.eq(&0) {
-                    audio_events.play(empty_shoot_sound.clone(), *empty_shoot_sound_volume);
+                    audio_events.play_from(
+                        empty_shoot_sound.clone(),
+                        *empty_shoot_sound_volume,
+                        transforms.get(entity),
+                    );
                     continue;
                 }
 
                 // Reset fire cooldown and subtract ammo
                 musket.cooldown = Timer::new(*cooldown, TimerMode::Once);
                 musket.ammo = musket.ammo.saturating_sub(1).clamp(0, musket.ammo);
-                audio_events.play(shoot_sound.clone(), *shoot_sound_volume);
+                audio_events.play_from(
+                    shoot_sound.clone(),
+                    *shoot_sound_volume,
+                    transforms.get(entity),
+                );
 
                 let player_sprite = sprites.get_mut(player).unwrap();
                 let player_flip_x = player_sprite.flip_x;
@@ -184,19 +195,33 @@ fn update(
                 let shoot_lifetime = *shoot_lifetime;
                 let shoot_atlas = shoot_atlas.clone();
 
+                if let Some(particles) = shoot_particles {
+                    commands.add(spawn_particle_effect(
+                        particles.clone(),
+                        shoot_animation_transform,
+                        player_flip_x,
+                    ));
+                }
+
                 let bullet_meta = bullet_meta.clone();
+                let homing = *homing;
+                let speed = item_charges.get(entity).map(|x| x.speed());
 
                 commands.add(
                     move |mut entities: ResMut<Entities>,
+                          mut pool: ResMut<EntityPool>,
+                          mut pooled: CompMut<Pooled>,
                           mut lifetimes: CompMut<Lifetime>,
                           mut sprites: CompMut<AtlasSprite>,
                           mut transforms: CompMut<Transform>,
                           mut bullets: CompMut<Bullet>,
                           mut bullet_handles: CompMut<BulletHandle>,
+                          mut homings: CompMut<BulletHoming>,
+                          mut bullet_speeds: CompMut<BulletSpeed>,
                           mut animated_sprites: CompMut<AnimatedSprite>| {
                         // spawn fire animation
                         {
-                            let ent = entities.create();
+                            let ent = pool.spawn(&mut entities, &mut pooled);
                             transforms.insert(ent, shoot_animation_transform);
                             sprites.insert(
                                 ent,
@@ -221,7 +246,7 @@ fn update(
 
                         // spawn bullet
                         {
-                            let ent = entities.create();
+                            let ent = pool.spawn(&mut entities, &mut pooled);
                             bullets.insert(
                                 ent,
                                 Bullet {
@@ -231,6 +256,12 @@ fn update(
                             );
                             transforms.insert(ent, shoot_animation_transform);
                             bullet_handles.insert(ent, BulletHandle(bullet_meta.clone()));
+                            if let Some(homing) = homing {
+                                homings.insert(ent, homing);
+                            }
+                            if let Some(speed) = speed {
+                                bullet_speeds.insert(ent, BulletSpeed(speed));
+                            }
                         }
                     },
                 );
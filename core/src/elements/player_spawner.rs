@@ -1,4 +1,4 @@
-use crate::{prelude::*, MAX_PLAYERS};
+use crate::{prelude::*, random::GlobalRng, MAX_PLAYERS};
 
 pub fn install(session: &mut CoreSession) {
     session
@@ -44,10 +44,25 @@ fn hydrate(
     }
 }
 
+/// Resource that tracks when each player respawns.
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H0JFFST5QKZ7HYX3P1DHSG7W"]
+pub struct PlayerRespawns {
+    /// Whether each player has spawned before, since a player's first spawn isn't delayed.
+    pub has_spawned: [bool; MAX_PLAYERS],
+    /// How many frames are left before each dead player respawns.
+    pub countdowns: [Option<u32>; MAX_PLAYERS],
+}
+
 fn update(
     game_meta: Res<CoreMetaArc>,
+    map_meta: Res<SpawnedMapMeta>,
+    rng: Res<GlobalRng>,
     mut entities: ResMut<Entities>,
     mut current_spawner: ResMut<CurrentSpawner>,
+    mut respawns: ResMut<PlayerRespawns>,
+    stocks: Res<PlayerStocks>,
+    sudden_death: Res<SuddenDeath>,
     player_spawners: Comp<PlayerSpawner>,
     mut player_indexes: CompMut<PlayerIdx>,
     mut transforms: CompMut<Transform>,
@@ -56,9 +71,20 @@ fn update(
     mut spawner_manager: SpawnerManager,
     mut element_kill_callbacks: CompMut<ElementKillCallback>,
 ) {
-    let alive_players = entities
-        .iter_with(&player_indexes)
-        .map(|(_ent, pidx)| pidx.0)
+    // Maps may override the game's respawn rules
+    let config = &game_meta.config;
+    let respawn = &map_meta.respawn;
+    let respawn_delay = respawn.delay.unwrap_or(config.respawn_delay);
+    let invincibility_time = respawn
+        .invincibility_time
+        .unwrap_or(config.respawn_invincibility_time);
+    let spawn_point_selection = respawn
+        .spawn_point_selection
+        .unwrap_or(config.spawn_point_selection);
+
+    let mut alive_players = entities
+        .iter_with((&player_indexes, &transforms))
+        .map(|(_ent, (pidx, transform))| (pidx.0, transform.translation.truncate()))
         .collect::<Vec<_>>();
     let spawn_points = entities
         .iter_with((&player_spawners, &transforms))
@@ -69,38 +95,120 @@ fn update(
     for i in 0..MAX_PLAYERS {
         let player = &player_inputs.players[i];
 
-        // If the player is active, but not alive
-        if player.active && !alive_players.contains(&i) {
-            // Increment the spawner index
-            current_spawner.0 += 1;
-            current_spawner.0 %= spawn_points.len().max(1);
+        // Only spawn players that are active and still have lives left, but not alive
+        if !player.active
+            || stocks.is_eliminated(i)
+            || sudden_death.is_eliminated(i)
+            || alive_players.iter().any(|(idx, _)| *idx == i)
+        {
+            continue;
+        }
+
+        // Wait out the respawn delay
+        let delay = if respawns.has_spawned[i] {
+            respawn_delay
+        } else {
+            0
+        };
+        let countdown = respawns.countdowns[i].get_or_insert(delay);
+        if *countdown > 0 {
+            *countdown -= 1;
+            continue;
+        }
+
+        let enemies = alive_players
+            .iter()
+            .map(|(_idx, pos)| *pos)
+            .collect::<Vec<_>>();
+        let Some(spawner_idx) = choose_spawn_point(
+            spawn_point_selection,
+            i,
+            &spawn_points,
+            &enemies,
+            &mut current_spawner.0,
+            &rng,
+        ) else { return };
+        let mut spawn_point = spawn_points[spawner_idx];
 
-            let Some(mut spawn_point) = spawn_points.get(current_spawner.0).copied() else { return };
+        // Make sure each player spawns at a different z level ( give enough room for 10 players
+        // to fit between map layers )
+        spawn_point.z += i as f32 * MAP_LAYERS_GAP_DEPTH / 10.0;
 
-            // Make sure each player spawns at a different z level ( give enough room for 10 players
-            // to fit between map layers )
-            spawn_point.z += i as f32 * MAP_LAYERS_GAP_DEPTH / 10.0;
+        let player_ent = entities.create();
+        player_indexes.insert(player_ent, PlayerIdx(i));
+        transforms.insert(player_ent, Transform::from_translation(spawn_point));
+        invincibles.insert(player_ent, Invincibility::new(invincibility_time));
 
-            let player_ent = entities.create();
-            player_indexes.insert(player_ent, PlayerIdx(i));
-            transforms.insert(player_ent, Transform::from_translation(spawn_point));
-            invincibles.insert(
-                player_ent,
-                Invincibility::new(game_meta.config.respawn_invincibility_time),
-            );
+        element_kill_callbacks.insert(
+            player_ent,
+            ElementKillCallback::new(player_kill_callback(player_ent)),
+        );
 
-            element_kill_callbacks.insert(
-                player_ent,
-                ElementKillCallback::new(player_kill_callback(player_ent)),
-            );
+        spawner_manager.insert_spawned_entity_into_grouped_spawner(
+            player_ent,
+            &player_spawners,
+            &entities,
+        );
 
-            spawner_manager.insert_spawned_entity_into_grouped_spawner(
-                player_ent,
-                &player_spawners,
-                &entities,
+        respawns.has_spawned[i] = true;
+        respawns.countdowns[i] = None;
+        alive_players.push((i, spawn_point.truncate()));
+    }
+}
+
+/// Choose which of the `spawn_points` a player spawns at, given the positions of the other living
+/// players.
+///
+/// Returns [`None`] if there are no spawn points.
+fn choose_spawn_point(
+    selection: SpawnPointSelection,
+    player_idx: usize,
+    spawn_points: &[Vec3],
+    enemies: &[Vec2],
+    current_spawner: &mut usize,
+    rng: &AtomicRng,
+) -> Option<usize> {
+    if spawn_points.is_empty() {
+        return None;
+    }
+
+    let spawner_idx = match selection {
+        SpawnPointSelection::Sequential => {
+            *current_spawner = (*current_spawner + 1) % spawn_points.len();
+            *current_spawner
+        }
+        SpawnPointSelection::Random => rng.usize(0..spawn_points.len()),
+        // With nobody else around, every spawn point is as good as any other
+        SpawnPointSelection::FurthestFromEnemies if enemies.is_empty() => {
+            return choose_spawn_point(
+                SpawnPointSelection::Sequential,
+                player_idx,
+                spawn_points,
+                enemies,
+                current_spawner,
+                rng,
             );
         }
-    }
+        SpawnPointSelection::FurthestFromEnemies => {
+            let nearest_enemy_distance = |point: &Vec3| {
+                enemies
+                    .iter()
+                    .map(|enemy| enemy.distance_squared(point.truncate()))
+                    .fold(f32::INFINITY, f32::min)
+            };
+            spawn_points
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| {
+                    nearest_enemy_distance(a).total_cmp(&nearest_enemy_distance(b))
+                })
+                .map(|(i, _)| i)
+                .unwrap()
+        }
+        SpawnPointSelection::FixedPerPlayer => player_idx % spawn_points.len(),
+    };
+
+    Some(spawner_idx)
 }
 
 fn player_kill_callback(player_entity: Entity) -> System {
@@ -123,3 +231,47 @@ fn player_kill_callback(player_entity: Entity) -> System {
     })
     .system()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn furthest_from_enemies_avoids_spawn_points_near_enemies() {
+        const MIN_DISTANCE: f32 = 100.0;
+        let rng = AtomicRng::with_seed(0);
+        let random_pos = || Vec2::new(rng.f32() * 1000.0, rng.f32() * 600.0);
+
+        for _ in 0..500 {
+            let spawn_points = (0..rng.usize(1..8))
+                .map(|_| random_pos().extend(0.0))
+                .collect::<Vec<_>>();
+            let enemies = (0..rng.usize(1..4))
+                .map(|_| random_pos())
+                .collect::<Vec<_>>();
+            let is_near_enemy = |point: Vec3| {
+                enemies
+                    .iter()
+                    .any(|enemy| enemy.distance(point.truncate()) < MIN_DISTANCE)
+            };
+
+            let chosen = choose_spawn_point(
+                SpawnPointSelection::FurthestFromEnemies,
+                0,
+                &spawn_points,
+                &enemies,
+                &mut 0,
+                &rng,
+            )
+            .unwrap();
+
+            if spawn_points.iter().any(|point| !is_near_enemy(*point)) {
+                assert!(
+                    !is_near_enemy(spawn_points[chosen]),
+                    "Spawned near an enemy at {:?} with enemies at {enemies:?}",
+                    spawn_points[chosen]
+                );
+            }
+        }
+    }
+}
@@ -86,7 +86,8 @@ fn update(
     mut atlas_sprites: CompMut<AtlasSprite>,
     mut bodies: CompMut<KinematicBody>,
     collision_world: CollisionWorld,
-    mut audio_events: ResMut<AudioEvents>,
+    transforms: Comp<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
 ) {
     for (entity, (sproinger, sprite)) in entities.iter_with((&mut sproingers, &mut atlas_sprites)) {
         let element_handle = element_handles.get(entity).unwrap();
@@ -122,7 +123,7 @@ fn update(
         for collider_ent in collision_world.actor_collisions(entity) {
             if let Some(body) = bodies.get_mut(collider_ent) {
                 if body.velocity.y < *spring_velocity - body.gravity {
-                    audio_events.play(sound.clone(), *sound_volume);
+                    audio_events.play_from(sound.clone(), *sound_volume, transforms.get(entity));
                     body.velocity.y = *spring_velocity;
                     sproinger.sproinging = true;
                 }
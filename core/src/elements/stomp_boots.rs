@@ -137,12 +137,7 @@ fn update(
                         entities.kill(entity);
 
                         let attachment_ent = entities.create();
-                        let attachment = Attachment {
-                            entity: player,
-                            sync_color: false,
-                            offset: Vec3::ZERO,
-                            sync_animation: true,
-                        };
+                        let attachment = Attachment::new(player).with_sync_animation(true);
                         attachments.insert(attachment_ent, attachment);
                         sprites.insert(attachment_ent, AtlasSprite::new(player_decoration.clone()));
                         transforms.insert(attachment_ent, Transform::default());
@@ -186,9 +181,11 @@ fn update_wearer(
                         .center()
                         .y
                 {
-                    commands.add(PlayerCommand::kill(
+                    commands.add(PlayerCommand::kill_by(
                         player,
                         Some(player_transform.translation.xy()),
+                        entity,
+                        None,
                     ))
                 }
             });
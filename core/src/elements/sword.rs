@@ -119,7 +119,7 @@ fn update(
     element_handles: Comp<ElementHandle>,
     element_assets: BevyAssets<ElementMeta>,
     collision_world: CollisionWorld,
-    mut audio_events: ResMut<AudioEvents>,
+    mut audio_events: ResMut<SoundEvents>,
     mut swords: CompMut<Sword>,
     mut sprites: CompMut<AtlasSprite>,
     bodies: CompMut<KinematicBody>,
@@ -262,7 +262,7 @@ fn update(
                 if matches!(sword.state, SwordState::Idle) {
                     sprite.index = 8;
                     sword.state = SwordState::Swinging { frame: 0 };
-                    audio_events.play(sound.clone(), *sound_volume);
+                    audio_events.play_from(sound.clone(), *sound_volume, transforms.get(entity));
                 }
             }
         } else {
@@ -0,0 +1,326 @@
+//! Regions that are triggered by a player performing an action inside of them.
+//!
+//! The regions are triggered in the order set by their [`TRIGGER_ORDER_PROPERTY`], so that a map
+//! can walk the player through a sequence of actions, like the tutorial map does. Only the regions
+//! with the lowest order that haven't been triggered yet are active at a time.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::PostUpdate, update);
+}
+
+/// The key of the element property that sets the order that the trigger regions of a map are
+/// triggered in.
+pub const TRIGGER_ORDER_PROPERTY: &str = "order";
+
+/// Component for a trigger region.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H66QZ4J8YB3TN0WD7CKMRF5E"]
+pub struct TriggerRegion {
+    pub size: Vec2,
+    pub action: TriggerAction,
+    /// The localization key of the instruction shown above the region.
+    pub label: String,
+    pub color: Color,
+    /// The order of the region in the map's sequence of regions.
+    pub order: u32,
+    pub triggered: bool,
+}
+
+impl TriggerRegion {
+    /// Get the rectangle that players must overlap to be in the region, given its transform.
+    pub fn rect(&self, position: Vec3) -> Rect {
+        Rect::new(position.x, position.y, self.size.x, self.size.y)
+    }
+}
+
+/// How many of the trigger regions in a map have been triggered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TriggerProgress {
+    pub triggered: usize,
+    pub total: usize,
+    /// The order of the regions that are waiting to be triggered, if any are left.
+    pub active_order: Option<u32>,
+}
+
+impl TriggerProgress {
+    /// Get the progress of the given trigger regions.
+    pub fn new<'a>(regions: impl IntoIterator<Item = &'a TriggerRegion>) -> Self {
+        let mut progress = Self::default();
+        for region in regions {
+            progress.total += 1;
+            if region.triggered {
+                progress.triggered += 1;
+            } else {
+                progress.active_order = Some(
+                    progress
+                        .active_order
+                        .map_or(region.order, |order| order.min(region.order)),
+                );
+            }
+        }
+        progress
+    }
+
+    /// Whether the map has trigger regions, and all of them have been triggered.
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.triggered == self.total
+    }
+
+    /// Whether the region is waiting for a player to trigger it.
+    pub fn is_active(&self, region: &TriggerRegion) -> bool {
+        !region.triggered && self.active_order == Some(region.order)
+    }
+}
+
+fn hydrate(
+    entities: Res<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    element_properties: Comp<ElementProperties>,
+    mut trigger_regions: CompMut<TriggerRegion>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+    let no_overrides = ElementProperties::default();
+
+    let spawners = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for entity in spawners {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let BuiltinElementKind::TriggerRegion(meta) = &element_meta.builtin else {
+            continue;
+        };
+
+        hydrated.insert(entity, MapElementHydrated);
+
+        let properties = element_properties.get(entity).unwrap_or(&no_overrides);
+        let order = element_meta
+            .editor
+            .property(properties, TRIGGER_ORDER_PROPERTY)
+            .and_then(|x| x.as_number())
+            .unwrap_or_default()
+            .max(0.0) as u32;
+        trigger_regions.insert(
+            entity,
+            TriggerRegion {
+                size: meta.size,
+                action: meta.action,
+                label: meta.label.clone(),
+                color: meta.color.0,
+                order,
+                triggered: false,
+            },
+        );
+    }
+}
+
+/// Trigger the active regions that a player performs their action in, and outline the regions
+/// that are still active.
+fn update(
+    entities: Res<Entities>,
+    frame: Res<SessionFrame>,
+    player_inputs: Res<PlayerInputs>,
+    mut trigger_regions: CompMut<TriggerRegion>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    player_states: Comp<PlayerState>,
+    inventories: Comp<Inventory>,
+    items_thrown: Comp<ItemThrownBy>,
+    transforms: Comp<Transform>,
+    bodies: Comp<KinematicBody>,
+    mut paths: CompMut<Path2d>,
+) {
+    let progress = TriggerProgress::new(entities.iter_with(&trigger_regions).map(|(_, x)| x));
+    if progress.active_order.is_none() {
+        return;
+    }
+
+    // The players that threw an item since the last update
+    let throwers = entities
+        .iter_with(&items_thrown)
+        .filter(|(_, thrown)| frame.0 <= thrown.frame + 1)
+        .map(|(_, thrown)| thrown.player)
+        .collect::<Vec<_>>();
+
+    let mut bitset = player_indexes.bitset().clone();
+    bitset.bit_and(transforms.bitset());
+    bitset.bit_and(bodies.bitset());
+    bitset.bit_andnot(players_killed.bitset());
+
+    for (region_ent, (region, transform)) in entities.iter_with((&mut trigger_regions, &transforms))
+    {
+        if !progress.is_active(region) {
+            paths.remove(region_ent);
+            continue;
+        }
+
+        let region_rect = region.rect(transform.translation);
+        region.triggered = entities.iter_with_bitset(&bitset).any(|player_ent| {
+            let transform = transforms.get(player_ent).unwrap();
+            let body = bodies.get(player_ent).unwrap();
+            if !body.bounding_box(*transform).overlaps(&region_rect) {
+                return false;
+            }
+
+            let player_idx = player_indexes.get(player_ent).unwrap().0;
+            let control = &player_inputs.players[player_idx].control;
+            match region.action {
+                TriggerAction::Move => control.move_direction.x != 0.0,
+                TriggerAction::Jump => control.jump_just_pressed,
+                TriggerAction::Grab => inventories
+                    .get(player_ent)
+                    .map_or(false, |inventory| inventory.is_some()),
+                TriggerAction::Throw => throwers.contains(&player_ent),
+                TriggerAction::Crouch => player_states
+                    .get(player_ent)
+                    .map_or(false, |state| state.is_crouching()),
+            }
+        });
+
+        if region.triggered {
+            debug!(?region.action, region.order, "Trigger region triggered");
+            paths.remove(region_ent);
+        } else {
+            let rect = Rect::new(0.0, 0.0, region.size.x, region.size.y);
+            paths.insert(
+                region_ent,
+                Path2d {
+                    color: region.color,
+                    points: vec![
+                        rect.top_left(),
+                        rect.top_right(),
+                        rect.bottom_right(),
+                        rect.bottom_left(),
+                        rect.top_left(),
+                    ],
+                    thickness: 2.0,
+                    ..default()
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestInput, TestSession, TestSessionBuilder};
+
+    fn region(order: u32, triggered: bool) -> TriggerRegion {
+        TriggerRegion {
+            size: Vec2::splat(32.0),
+            action: TriggerAction::Jump,
+            label: default(),
+            color: Color::WHITE,
+            order,
+            triggered,
+        }
+    }
+
+    #[test]
+    fn only_lowest_untriggered_order_is_active() {
+        let regions = [region(0, true), region(1, false), region(2, false)];
+        let progress = TriggerProgress::new(&regions);
+        assert_eq!(progress.active_order, Some(1));
+        assert!(!progress.is_active(&regions[0]));
+        assert!(progress.is_active(&regions[1]));
+        assert!(!progress.is_active(&regions[2]));
+        assert!(!progress.is_complete());
+    }
+
+    #[test]
+    fn progress_is_complete_once_every_region_is_triggered() {
+        assert!(!TriggerProgress::new(&[]).is_complete());
+        let progress = TriggerProgress::new(&[region(0, true), region(3, true)]);
+        assert_eq!(progress.active_order, None);
+        assert!(progress.is_complete());
+    }
+
+    fn trigger_progress(session: &TestSession) -> TriggerProgress {
+        session
+            .session
+            .world
+            .run_initialized_system(|entities: Res<Entities>, regions: Comp<TriggerRegion>| {
+                Ok(TriggerProgress::new(
+                    entities.iter_with(&regions).map(|(_, x)| x),
+                ))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn trigger_regions_are_triggered_in_order() {
+        let mut builder = TestSessionBuilder::room(20, 10);
+        builder.add_player(Vec2::new(64.0, 48.0));
+        let trigger_meta = |action| ElementMeta {
+            name: "Trigger".into(),
+            category: "Tutorial".into(),
+            builtin: BuiltinElementKind::TriggerRegion(TriggerRegionMeta {
+                size: Vec2::splat(128.0),
+                action,
+                label: default(),
+                color: default(),
+            }),
+            editor: ElementEditorMeta {
+                properties: vec![ElementPropertyMeta {
+                    key: TRIGGER_ORDER_PROPERTY.into(),
+                    name: "Order".into(),
+                    default: ElementPropertyValue::Number(0.0),
+                }],
+                ..default()
+            },
+            ..default()
+        };
+        builder.add_element(
+            "/jump",
+            trigger_meta(TriggerAction::Jump),
+            Vec2::new(64.0, 48.0),
+        );
+        let walk = builder
+            .assets
+            .insert("/walk", trigger_meta(TriggerAction::Move));
+        builder.map.layers[0].elements.push(ElementSpawn {
+            pos: Vec2::new(64.0, 48.0),
+            element: walk,
+            properties: ElementProperties(
+                [(
+                    TRIGGER_ORDER_PROPERTY.to_string(),
+                    ElementPropertyValue::Number(1.0),
+                )]
+                .into(),
+            ),
+            ..default()
+        });
+        let mut session = builder.build();
+        session.step(10);
+        assert_eq!(trigger_progress(&session).active_order, Some(0));
+
+        // Walking doesn't count until the jump has been done
+        session.step_with(0, TestInput::moving(Vec2::X), 2);
+        assert_eq!(trigger_progress(&session).triggered, 0);
+
+        let jump = TestInput {
+            jump: true,
+            ..default()
+        };
+        session.step_with(0, jump, 1);
+        session.step_with(0, default(), 30);
+        assert_eq!(trigger_progress(&session).active_order, Some(1));
+
+        session.step_with(0, TestInput::moving(-Vec2::X), 2);
+        let progress = trigger_progress(&session);
+        assert_eq!(progress.triggered, 2);
+        assert!(progress.is_complete());
+    }
+}
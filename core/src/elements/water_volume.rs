@@ -0,0 +1,198 @@
+//! Volumes of water that bodies float and swim in.
+//!
+//! The buoyancy and drag of the water is applied to [`KinematicBody`]s in the physics update, which
+//! keeps track of how deep each body is in [`KinematicBody::submersion`]. Bullets are slowed down
+//! by the water in the bullet update.
+//!
+//! Anything crossing the surface of the water makes a splash. Whether an entity is under water is
+//! kept in the [`Underwater`] component, so that the splash happens once for each crossing, even
+//! when frames are re-simulated.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate)
+        .add_system_to_stage(CoreStage::Last, splash);
+
+    session.pooled_components.register::<Underwater>();
+}
+
+/// How far past the surface of the water something must go before it counts as having crossed it.
+///
+/// This keeps things bobbing at the surface from splashing over and over.
+const SURFACE_MARGIN: f32 = 4.0;
+/// How far from the surface something can cross in or out of the water, for example from the side
+/// of the volume, and still make a splash.
+const SPLASH_DEPTH: f32 = 32.0;
+
+/// Component for a volume of water.
+#[derive(Clone, Debug, TypeUlid, Deref)]
+#[ulid = "01H1JH0PKJ2F92NY2MJ1RT6FBT"]
+pub struct WaterVolume(pub WaterVolumeMeta);
+
+impl WaterVolume {
+    /// Get the rectangle of the water, given its transform.
+    pub fn rect(&self, position: Vec3) -> Rect {
+        Rect::new(position.x, position.y, self.size.x, self.size.y)
+    }
+}
+
+/// Get the fraction of the height of `rect` that is under the surface of the water in `water_rect`.
+pub fn submersion(water_rect: &Rect, rect: &Rect) -> f32 {
+    if !water_rect.overlaps(rect) {
+        return 0.0;
+    }
+    let height = rect.height();
+    if height <= 0.0 {
+        return 1.0;
+    }
+    let under_water = rect.max.y.min(water_rect.max.y) - rect.min.y.max(water_rect.min.y);
+
+    (under_water / height).clamp(0.0, 1.0)
+}
+
+/// Marker component for bodies and bullets that are under water.
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H3Q91AC02GPC4ATH54T33ZW7"]
+pub struct Underwater;
+
+fn hydrate(
+    entities: Res<Entities>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut water_volumes: CompMut<WaterVolume>,
+    mut paths: CompMut<Path2d>,
+) {
+    let mut not_hydrated_bitset = hydrated.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let spawners = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for entity in spawners {
+        let element_handle = element_handles.get(entity).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+
+        if let BuiltinElementKind::WaterVolume(meta) = &element_meta.builtin {
+            hydrated.insert(entity, MapElementHydrated);
+
+            // Fill the volume with a line as thick as it is tall
+            paths.insert(
+                entity,
+                Path2d {
+                    color: meta.color.0,
+                    points: vec![vec2(-meta.size.x / 2.0, 0.0), vec2(meta.size.x / 2.0, 0.0)],
+                    thickness: meta.size.y,
+                    ..default()
+                },
+            );
+            water_volumes.insert(entity, WaterVolume(meta.clone()));
+        }
+    }
+}
+
+/// Make a splash when bodies or bullets go in or out of the water.
+fn splash(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    mut audio_events: ResMut<SoundEvents>,
+    water_volumes: Comp<WaterVolume>,
+    bodies: Comp<KinematicBody>,
+    bullets: Comp<Bullet>,
+    transforms: Comp<Transform>,
+    mut underwater: CompMut<Underwater>,
+    pool: Res<EntityPool>,
+) {
+    let water_rects = entities
+        .iter_with((&water_volumes, &transforms))
+        .map(|(_ent, (water, transform))| (water.rect(transform.translation), water))
+        .collect::<Vec<_>>();
+
+    // Held items move with the player holding them, so they don't splash by themselves
+    let splashers = entities
+        .iter_with(&bodies)
+        .filter(|(_ent, body)| !body.is_deactivated)
+        .map(|(ent, _)| ent)
+        .chain(
+            entities
+                .iter_with(&bullets)
+                .map(|(ent, _)| ent)
+                .filter(|ent| !pool.is_released(*ent)),
+        )
+        .collect::<Vec<_>>();
+
+    for entity in splashers {
+        let Some(transform) = transforms.get(entity) else {
+            continue;
+        };
+        let pos = transform.translation.truncate();
+        let was_underwater = underwater.contains(entity);
+        let is_underwater = water_rects.iter().any(|(rect, _)| {
+            let surface = if was_underwater {
+                rect.max.y + SURFACE_MARGIN
+            } else {
+                rect.max.y - SURFACE_MARGIN
+            };
+            pos.x >= rect.min.x && pos.x <= rect.max.x && pos.y >= rect.min.y && pos.y < surface
+        });
+        if is_underwater == was_underwater {
+            continue;
+        }
+
+        if is_underwater {
+            underwater.insert(entity, Underwater);
+        } else {
+            underwater.remove(entity);
+        }
+
+        let Some((rect, water)) = water_rects.iter().find(|(rect, _)| {
+            pos.x >= rect.min.x
+                && pos.x <= rect.max.x
+                && (pos.y - rect.max.y).abs() <= SPLASH_DEPTH
+        }) else {
+            continue;
+        };
+
+        audio_events.play_at(water.splash_sound.clone(), water.splash_volume, pos);
+
+        let splash_transform =
+            Transform::from_translation(vec3(pos.x, rect.max.y, transform.translation.z + 1.0));
+        let atlas = water.splash_atlas.clone();
+        let frames = water.splash_frames;
+        let fps = water.splash_fps;
+        let lifetime = water.splash_lifetime;
+        commands.add(
+            move |mut entities: ResMut<Entities>,
+                  mut transforms: CompMut<Transform>,
+                  mut lifetimes: CompMut<Lifetime>,
+                  mut sprites: CompMut<AtlasSprite>,
+                  mut animated_sprites: CompMut<AnimatedSprite>| {
+                let ent = entities.create();
+                transforms.insert(ent, splash_transform);
+                sprites.insert(
+                    ent,
+                    AtlasSprite {
+                        atlas: atlas.clone(),
+                        ..default()
+                    },
+                );
+                animated_sprites.insert(
+                    ent,
+                    AnimatedSprite {
+                        frames: (0..frames).collect(),
+                        fps,
+                        repeat: false,
+                        ..default()
+                    },
+                );
+                lifetimes.insert(ent, Lifetime::new(lifetime));
+            },
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Force regions, such as wind, fans, and conveyor belts.
+//!
+//! A [`ForceRegion`] pushes every [`KinematicBody`] that overlaps it, using the same overlap test
+//! as [`DamageRegion`]s. Forces are applied before the physics update, and the forces of
+//! overlapping regions are added together, in entity order, before being applied to each body.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, apply_force_regions);
+}
+
+/// A rectangular region that pushes the bodies in it.
+///
+/// This may be used as element metadata, and may also be added to any other entity with a
+/// [`Transform`].
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H00VTJR691WA2XHZYPH2MGAV"]
+#[serde(deny_unknown_fields)]
+pub struct ForceRegion {
+    /// The size of the region in pixels.
+    pub size: Vec2,
+    /// The direction of the force. This doesn't need to be normalized.
+    pub direction: Vec2,
+    /// How strong the force is, in the units of the force's [`ForceMode`].
+    pub strength: f32,
+    #[serde(default)]
+    pub mode: ForceMode,
+    #[serde(default)]
+    pub affects: ForceTargets,
+    /// The friction of bodies standing in the region, overriding the normal ground friction.
+    ///
+    /// Conveyor belts can set this closer to `1.0` so that the ground doesn't stop their bodies
+    /// from being carried along.
+    #[serde(default)]
+    pub friction: Option<f32>,
+}
+
+impl ForceRegion {
+    /// Get the collision rectangle of this force region, given it's transform.
+    pub fn collider_rect(&self, position: Vec3) -> Rect {
+        Rect::new(position.x, position.y, self.size.x, self.size.y)
+    }
+
+    /// Get the force vector of the region.
+    pub fn force(&self) -> Vec2 {
+        self.direction.normalize_or_zero() * self.strength
+    }
+}
+
+/// How a [`ForceRegion`] changes the velocity of the bodies in it.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ForceMode {
+    /// Accelerate bodies by the force each frame, in pixels per frame, per frame.
+    #[default]
+    Acceleration,
+    /// Make sure bodies move at least as fast as the force in its direction, in pixels per frame.
+    ///
+    /// Bodies moving faster than that in the force's direction are left alone.
+    VelocityBias,
+}
+
+impl BonesBevyAssetLoad for ForceMode {}
+
+/// Which bodies a [`ForceRegion`] pushes.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ForceTargets {
+    /// Only bodies standing on the ground.
+    Grounded,
+    /// Only bodies in the air.
+    Airborne,
+    /// Every body in the region.
+    #[default]
+    Both,
+}
+
+impl ForceTargets {
+    /// Whether a body is affected, depending on whether or not it is on the ground.
+    pub fn contains(&self, is_on_ground: bool) -> bool {
+        match self {
+            ForceTargets::Grounded => is_on_ground,
+            ForceTargets::Airborne => !is_on_ground,
+            ForceTargets::Both => true,
+        }
+    }
+}
+
+impl BonesBevyAssetLoad for ForceTargets {}
+
+/// A component that may be added to a force region entity to indicate the entity that made it.
+///
+/// The owner will not be pushed by the region.
+#[derive(Debug, Clone, TypeUlid)]
+#[ulid = "01H0VDNSDHFMJGS68DQHM12Q3W"]
+pub struct ForceRegionOwner(pub Entity);
+
+/// System that pushes the bodies that are in force regions.
+fn apply_force_regions(
+    entities: Res<Entities>,
+    transforms: Comp<Transform>,
+    force_regions: Comp<ForceRegion>,
+    force_region_owners: Comp<ForceRegionOwner>,
+    mut bodies: CompMut<KinematicBody>,
+) {
+    let regions = entities
+        .iter_with((&force_regions, &transforms))
+        .map(|(ent, (region, transform))| {
+            (
+                region,
+                region.collider_rect(transform.translation),
+                force_region_owners.get(ent).map(|x| x.0),
+            )
+        })
+        .collect::<Vec<_>>();
+    if regions.is_empty() {
+        return;
+    }
+
+    for (body_ent, (body, transform)) in entities.iter_with((&mut bodies, &transforms)) {
+        if body.is_deactivated {
+            continue;
+        }
+        let body_rect = body.bounding_box(*transform);
+
+        let mut acceleration = Vec2::ZERO;
+        let mut velocity_bias = Vec2::ZERO;
+        let mut friction = None;
+        for (region, rect, owner) in &regions {
+            if *owner == Some(body_ent)
+                || !region.affects.contains(body.is_on_ground)
+                || !body_rect.overlaps(rect)
+            {
+                continue;
+            }
+
+            match region.mode {
+                ForceMode::Acceleration => acceleration += region.force(),
+                ForceMode::VelocityBias => velocity_bias += region.force(),
+            }
+            if body.is_on_ground {
+                if let Some(region_friction) = region.friction {
+                    // Use the least friction of all of the regions the body is standing in
+                    friction = Some(f32::max(region_friction, friction.unwrap_or(0.0)));
+                }
+            }
+        }
+
+        body.velocity += acceleration;
+        let bias_speed = velocity_bias.length();
+        if bias_speed > 0.0 {
+            let direction = velocity_bias / bias_speed;
+            let speed = body.velocity.dot(direction);
+            if speed < bias_speed {
+                body.velocity += direction * (bias_speed - speed);
+            }
+        }
+        if friction.is_some() {
+            body.frame_friction_override = friction;
+        }
+    }
+}
@@ -0,0 +1,487 @@
+//! Running a [`CoreSession`] without the game client, for example to host matches on a dedicated
+//! server, or to simulate matches between AI players.
+//!
+//! The game systems read the metadata they need, such as the [`ElementMeta`] of the map elements,
+//! out of the [`CoreAssets`] passed to [`CoreSession::advance()`]. The game client passes its Bevy
+//! world, where the Bevy asset server has loaded the metadata, and a headless host uses
+//! [`HeadlessAssets`] instead, which loads the metadata from plain files or bytes:
+//!
+//! ```ignore
+//! let mut assets = HeadlessAssets::new();
+//! let meta = assets.load_core_meta(Path::new("assets"), "default.core.yaml")?;
+//! let map_meta = assets.get(&meta.stable_maps[0]).unwrap().clone();
+//!
+//! let mut session = HeadlessSession::new(
+//!     assets,
+//!     CoreSessionInfo {
+//!         meta: Arc::new(meta),
+//!         map_meta,
+//!         player_info,
+//!         mutators: default(),
+//!         seed,
+//!     },
+//! );
+//! loop {
+//!     session.advance(&player_controls);
+//!     send_to_clients(&session.snapshot());
+//! }
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::prelude::*;
+
+/// Storage for the metadata that the game systems read while a [`CoreSession`] is advanced.
+///
+/// The systems read the metadata with [`BevyAssets`], which borrow the Bevy asset storage of a
+/// Bevy world for the length of the frame.
+pub trait CoreAssets {
+    /// Get the Bevy world containing the metadata.
+    fn bevy_world(&mut self) -> &mut ::bevy::ecs::world::World;
+}
+
+impl CoreAssets for ::bevy::ecs::world::World {
+    fn bevy_world(&mut self) -> &mut ::bevy::ecs::world::World {
+        self
+    }
+}
+
+/// A source of metadata files, such as the asset folder, or files held in memory.
+pub trait MetaSource {
+    /// Read the file at the given path, relative to the root of the assets.
+    fn read(&self, path: &str) -> std::io::Result<Vec<u8>>;
+}
+
+impl MetaSource for Path {
+    fn read(&self, path: &str) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.join(path.trim_start_matches('/')))
+    }
+}
+
+impl MetaSource for PathBuf {
+    fn read(&self, path: &str) -> std::io::Result<Vec<u8>> {
+        self.as_path().read(path)
+    }
+}
+
+/// Files held in memory, by their path relative to the root of the assets, such as
+/// `default.core.yaml`.
+impl MetaSource for HashMap<String, Vec<u8>> {
+    fn read(&self, path: &str) -> std::io::Result<Vec<u8>> {
+        self.get(path.trim_start_matches('/'))
+            .cloned()
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, format!("{path} not found"))
+            })
+    }
+}
+
+/// The asset IDs of the metadata that is loaded along with the [`CoreMeta`], which are also the
+/// extensions of their files, such as `.element.yaml`.
+const META_ASSET_IDS: [&str; 5] = ["player", "map", "element", "bullet", "particles"];
+
+/// Errors that may occur when loading metadata with [`HeadlessAssets`].
+#[derive(Debug)]
+pub enum MetaLoadError {
+    /// A file could not be read.
+    Read(String, std::io::Error),
+    /// A file could not be deserialized.
+    Deserialize(String, serde_yaml::Error),
+}
+
+impl std::fmt::Display for MetaLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetaLoadError::Read(path, e) => write!(f, "Could not read {path}: {e}"),
+            MetaLoadError::Deserialize(path, e) => write!(f, "Could not deserialize {path}: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for MetaLoadError {}
+
+/// Bevy asset storage for the metadata used by a [`CoreSession`], that is filled without the Bevy
+/// asset server.
+///
+/// Metadata may be loaded from a [`MetaSource`] with [`load_core_meta()`][Self::load_core_meta],
+/// or built in code and added with [`insert()`][Self::insert].
+pub struct HeadlessAssets {
+    app: ::bevy::app::App,
+}
+
+impl Default for HeadlessAssets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeadlessAssets {
+    /// Create empty asset storage for all of the core metadata types.
+    pub fn new() -> Self {
+        let mut app = ::bevy::app::App::new();
+        // The asset server is only needed to register the asset types, nothing is ever loaded.
+        app.add_plugin(::bevy::asset::AssetPlugin::default())
+            .add_plugin(JumpyCoreAssetsPlugin);
+
+        Self { app }
+    }
+
+    /// Add some metadata, returning the handle that it may be referred to with.
+    pub fn insert<T>(&mut self, path: &str, meta: T) -> Handle<T>
+    where
+        T: ::bevy::asset::Asset + TypeUlid,
+    {
+        let handle = UntypedHandle {
+            path: AssetPath::new(path, None),
+        }
+        .typed::<T>();
+        self.app
+            .world
+            .resource_mut::<::bevy::asset::Assets<T>>()
+            .set_untracked(handle.get_bevy_handle_untyped().id(), meta);
+
+        handle
+    }
+
+    /// Get the metadata that a handle refers to, if it has been added.
+    pub fn get<T>(&self, handle: &Handle<T>) -> Option<&T>
+    where
+        T: ::bevy::asset::Asset + TypeUlid,
+    {
+        self.app
+            .world
+            .resource::<::bevy::asset::Assets<T>>()
+            .get(&handle.get_bevy_handle())
+    }
+
+    /// The Bevy world containing the metadata.
+    pub fn world(&mut self) -> &mut ::bevy::ecs::world::World {
+        &mut self.app.world
+    }
+
+    /// Load the [`CoreMeta`] at `path` from the `source`, along with all of the player, map,
+    /// element, bullet, and particle metadata that it refers to.
+    ///
+    /// Paths in the metadata that start with `/` are relative to the root of the assets, and other
+    /// paths are relative to the file they are in, like they are for the Bevy asset server. The
+    /// handles in the loaded metadata are changed to the paths relative to the root.
+    ///
+    /// Only the metadata is loaded. Images, sounds, and atlases are only needed to show the game,
+    /// so their handles don't refer to anything.
+    pub fn load_core_meta<S>(&mut self, source: &S, path: &str) -> Result<CoreMeta, MetaLoadError>
+    where
+        S: MetaSource + ?Sized,
+    {
+        let mut dependencies = Vec::new();
+        let meta = read_meta::<CoreMeta, _>(source, &resolve_path("/", path), &mut dependencies)?;
+
+        let mut loaded = HashSet::new();
+        while let Some(path) = dependencies.pop() {
+            if !loaded.insert(path.clone()) {
+                continue;
+            }
+            match meta_asset_id(&path) {
+                Some("player") => self.load::<PlayerMeta, _>(source, &path, &mut dependencies)?,
+                Some("map") => self.load::<MapMeta, _>(source, &path, &mut dependencies)?,
+                Some("element") => self.load::<ElementMeta, _>(source, &path, &mut dependencies)?,
+                Some("bullet") => self.load::<BulletMeta, _>(source, &path, &mut dependencies)?,
+                Some("particles") => {
+                    self.load::<ParticleEffectMeta, _>(source, &path, &mut dependencies)?
+                }
+                _ => unreachable!("Only metadata is added to the dependencies"),
+            }
+        }
+
+        Ok(meta)
+    }
+
+    /// Load the metadata at `path` into the storage.
+    fn load<T, S>(
+        &mut self,
+        source: &S,
+        path: &str,
+        dependencies: &mut Vec<String>,
+    ) -> Result<(), MetaLoadError>
+    where
+        T: ::bevy::asset::Asset + TypeUlid + DeserializeOwned,
+        S: MetaSource + ?Sized,
+    {
+        let meta = read_meta::<T, _>(source, path, dependencies)?;
+        self.insert(path, meta);
+        Ok(())
+    }
+}
+
+impl CoreAssets for HeadlessAssets {
+    fn bevy_world(&mut self) -> &mut ::bevy::ecs::world::World {
+        self.world()
+    }
+}
+
+/// Read the metadata at `path`, adding the paths of the metadata it refers to to the
+/// `dependencies`.
+fn read_meta<T, S>(
+    source: &S,
+    path: &str,
+    dependencies: &mut Vec<String>,
+) -> Result<T, MetaLoadError>
+where
+    T: DeserializeOwned,
+    S: MetaSource + ?Sized,
+{
+    let bytes = source
+        .read(path)
+        .map_err(|e| MetaLoadError::Read(path.into(), e))?;
+    let mut value: serde_yaml::Value =
+        serde_yaml::from_slice(&bytes).map_err(|e| MetaLoadError::Deserialize(path.into(), e))?;
+    resolve_handles(&mut value, path, dependencies);
+    serde_yaml::from_value(value).map_err(|e| MetaLoadError::Deserialize(path.into(), e))
+}
+
+/// Change the paths of the metadata that the file at `base` refers to so that they are relative to
+/// the root of the assets, and add them to the `dependencies`.
+fn resolve_handles(value: &mut serde_yaml::Value, base: &str, dependencies: &mut Vec<String>) {
+    use serde_yaml::Value;
+    match value {
+        Value::String(path) if meta_asset_id(path).is_some() => {
+            *path = resolve_path(base, path);
+            dependencies.push(path.clone());
+        }
+        Value::Sequence(values) => {
+            for value in values {
+                resolve_handles(value, base, dependencies);
+            }
+        }
+        Value::Mapping(values) => {
+            for (_key, value) in values.iter_mut() {
+                resolve_handles(value, base, dependencies);
+            }
+        }
+        Value::Tagged(tagged) => resolve_handles(&mut tagged.value, base, dependencies),
+        _ => (),
+    }
+}
+
+/// Get the asset ID of the metadata in the file at `path`, such as `element` for
+/// `/elements/item/sword/sword.element.yaml`, if it is one of the [`META_ASSET_IDS`].
+fn meta_asset_id(path: &str) -> Option<&str> {
+    let name = path.rsplit('/').next()?;
+    let name = name
+        .strip_suffix(".yaml")
+        .or_else(|| name.strip_suffix(".json"))?;
+    let (_, id) = name.rsplit_once('.')?;
+    META_ASSET_IDS.contains(&id).then_some(id)
+}
+
+/// Get the path of the asset at `path`, referred to by the file at `base`, relative to the root of
+/// the assets.
+fn resolve_path(base: &str, path: &str) -> String {
+    let mut parts = Vec::new();
+    if !path.starts_with('/') {
+        parts.extend(base.split('/').filter(|part| !part.is_empty()));
+        // Remove the file name of the base
+        parts.pop();
+    }
+    for part in path.split('/') {
+        match part {
+            "" | "." => (),
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    format!("/{}", parts.join("/"))
+}
+
+/// A [`CoreSession`] advanced with the player inputs from a headless host, such as a dedicated
+/// server.
+pub struct HeadlessSession {
+    /// The game session.
+    pub session: CoreSession,
+    /// The metadata used by the session.
+    pub assets: HeadlessAssets,
+}
+
+impl HeadlessSession {
+    /// Start a session with the given info, whose metadata must have been added to the `assets`.
+    pub fn new(assets: HeadlessAssets, info: CoreSessionInfo) -> Self {
+        Self {
+            session: CoreSession::new(info),
+            assets,
+        }
+    }
+
+    /// Advance the session by one frame, with the control input of each player.
+    ///
+    /// AI players decide on their own input, so their control input is ignored. Remote players can
+    /// send their input as a [`ReplayInput`], which is serializable.
+    pub fn advance(&mut self, controls: &[PlayerControl]) {
+        self.session.update_input(|player_inputs| {
+            for (control, player) in controls.iter().zip(&mut player_inputs.players) {
+                player.control = control.clone();
+            }
+        });
+        self.session.advance(&mut self.assets);
+    }
+
+    /// Get the number of frames that the session has been advanced.
+    pub fn frame(&self) -> u64 {
+        **self.session.world.resource::<SessionFrame>().borrow()
+    }
+
+    /// Get a serializable summary of the current state of the match.
+    pub fn snapshot(&self) -> SessionSnapshot {
+        let players = self
+            .session
+            .world
+            .run_initialized_system(
+                |entities: Res<Entities>,
+                 player_indexes: Comp<PlayerIdx>,
+                 killed_players: Comp<PlayerKilled>,
+                 transforms: Comp<Transform>,
+                 bodies: Comp<KinematicBody>,
+                 inventories: Comp<Inventory>,
+                 element_handles: Comp<ElementHandle>| {
+                    let mut players = entities
+                        .iter_with((&player_indexes, &transforms))
+                        .map(|(ent, (idx, transform))| {
+                            let item = inventories
+                                .get(ent)
+                                .and_then(|inventory| inventory.0)
+                                .and_then(|item| element_handles.get(item))
+                                .map(|handle| handle.0.clone());
+                            (
+                                PlayerSnapshot {
+                                    index: idx.0,
+                                    alive: !killed_players.contains(ent),
+                                    position: transform.translation.truncate(),
+                                    velocity: bodies
+                                        .get(ent)
+                                        .map(|body| body.velocity)
+                                        .unwrap_or_default(),
+                                    item: None,
+                                },
+                                item,
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    players.sort_by_key(|(player, _)| player.index);
+                    Ok(players)
+                },
+            )
+            .unwrap();
+        let score = self.session.world.resource::<MatchScore>();
+        let score = score.borrow();
+
+        SessionSnapshot {
+            frame: self.frame(),
+            players: players
+                .into_iter()
+                .map(|(player, item)| PlayerSnapshot {
+                    item: item
+                        .and_then(|item| self.assets.get(&item))
+                        .map(|meta| meta.name.clone()),
+                    ..player
+                })
+                .collect(),
+            points: score.points,
+            kills: score.kills.clone(),
+            result: score.result,
+        }
+    }
+}
+
+/// A serializable summary of the state of a match, made by [`HeadlessSession::snapshot()`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    /// The number of frames that the session has been advanced.
+    pub frame: u64,
+    /// The players that are spawned, in the order of their indexes.
+    pub players: Vec<PlayerSnapshot>,
+    /// The points each player has scored.
+    pub points: [f32; MAX_PLAYERS],
+    /// Every player death so far, in the order they happened.
+    pub kills: Vec<KillRecord>,
+    /// The result of the match, once it is over.
+    pub result: Option<MatchResult>,
+}
+
+/// The state of a player in a [`SessionSnapshot`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerSnapshot {
+    /// The index of the player.
+    pub index: usize,
+    /// Whether the player hasn't been killed.
+    pub alive: bool,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// The name of the item the player is holding.
+    pub item: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_resolved_like_the_asset_server() {
+        let base = "/elements/item/musket/musket.element.yaml";
+        assert_eq!(
+            resolve_path(base, "./bullet/musket.bullet.yaml"),
+            "/elements/item/musket/bullet/musket.bullet.yaml"
+        );
+        assert_eq!(
+            resolve_path(base, "../sword/sword.element.yaml"),
+            "/elements/item/sword/sword.element.yaml"
+        );
+        assert_eq!(
+            resolve_path(base, "/particles/muzzle_flash.particles.yaml"),
+            "/particles/muzzle_flash.particles.yaml"
+        );
+        assert_eq!(resolve_path("/", "default.core.yaml"), "/default.core.yaml");
+
+        assert_eq!(meta_asset_id("/a/sword.element.yaml"), Some("element"));
+        assert_eq!(meta_asset_id("/a/fishy.player.json"), Some("player"));
+        assert_eq!(meta_asset_id("/a/fishy.atlas.yaml"), None);
+        assert_eq!(meta_asset_id("/a/shoot.ogg"), None);
+    }
+
+    #[test]
+    fn core_meta_is_loaded_with_its_dependencies() {
+        let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets");
+        let mut assets = HeadlessAssets::new();
+        let meta = assets
+            .load_core_meta(&assets_dir, "default.core.yaml")
+            .unwrap();
+
+        assert!(meta
+            .players
+            .iter()
+            .all(|player| assets.get(player).is_some()));
+        let map = assets.get(&meta.stable_maps[0]).unwrap();
+        assert!(map
+            .layers
+            .iter()
+            .flat_map(|layer| &layer.elements)
+            .all(|element| assets.get(&element.element).is_some()));
+        let musket = meta
+            .map_elements
+            .iter()
+            .filter_map(|element| assets.get(element))
+            .find_map(|element| match &element.builtin {
+                BuiltinElementKind::Musket { bullet_meta, .. } => Some(bullet_meta.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert!(
+            assets.get(&musket).is_some(),
+            "The musket bullet wasn't loaded"
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Hot reloading of element metadata into a running session.
+//!
+//! When the metadata asset of an element changes, the game calls
+//! [`CoreSession::reload_element`]. Every instance of the element is then hydrated again from a
+//! temporary spawner at the same transform, and the components that come from the metadata are
+//! copied over from the new instance to the existing one. This keeps the runtime state of the
+//! instance, like its position, velocity and the player holding it.
+//!
+//! If the new instance doesn't have the same set of metadata components as the existing one, the
+//! existing instance is replaced by the new one instead.
+//!
+//! Only instances spawned by a map element, that have a [`DehydrateOutOfBounds`], are reloaded.
+//! Map elements that aren't hydrated into a separate instance pick up the new metadata the next
+//! time they are hydrated.
+
+use ::bevy::asset::HandleId;
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<ElementReloads>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, start_element_reloads)
+        // This must run after the element and item hydration systems in the same stage.
+        .add_system_to_stage(CoreStage::PreUpdate, finish_element_reloads);
+}
+
+/// Resource containing the elements waiting to be reloaded.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H5QH7XK3MZ4T8WBN6RDC2JVA"]
+pub struct ElementReloads {
+    /// The metadata assets of the elements that changed.
+    pub modified: Vec<HandleId>,
+    /// The instances that are being hydrated again, paired with the temporary spawner they are
+    /// being hydrated from.
+    rehydrating: Vec<(Entity, Entity)>,
+}
+
+/// Create a temporary spawner for each instance of the modified elements, so that the element's
+/// hydration system creates a new instance from the new metadata.
+fn start_element_reloads(
+    mut entities: ResMut<Entities>,
+    mut reloads: ResMut<ElementReloads>,
+    mut element_handles: CompMut<ElementHandle>,
+    mut element_properties: CompMut<ElementProperties>,
+    mut transforms: CompMut<Transform>,
+    respawn_points: Comp<DehydrateOutOfBounds>,
+) {
+    if reloads.modified.is_empty() {
+        return;
+    }
+    let modified = std::mem::take(&mut reloads.modified);
+
+    let instances = entities
+        .iter_with((&element_handles, &respawn_points))
+        .filter(|(_, (handle, _))| modified.contains(&handle.get_bevy_handle().id()))
+        .map(|(entity, (handle, spawner))| (entity, handle.clone(), **spawner))
+        .collect::<Vec<_>>();
+
+    for (instance, element_handle, spawner) in instances {
+        let Some(transform) = transforms.get(instance).copied() else {
+            continue;
+        };
+
+        let temp_spawner = entities.create();
+        element_handles.insert(temp_spawner, element_handle);
+        transforms.insert(temp_spawner, transform);
+        if let Some(properties) = element_properties.get(spawner).cloned() {
+            element_properties.insert(temp_spawner, properties);
+        }
+
+        reloads.rehydrating.push((instance, temp_spawner));
+    }
+}
+
+/// Update the instances of the modified elements from the new instances that were hydrated from
+/// their temporary spawners, and remove the temporary spawners.
+fn finish_element_reloads(
+    mut commands: Commands,
+    mut entities: ResMut<Entities>,
+    mut reloads: ResMut<ElementReloads>,
+    element_kill_callbacks: Comp<ElementKillCallback>,
+    mut spawner_manager: SpawnerManager,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut transforms: CompMut<Transform>,
+    mut inventories: CompMut<Inventory>,
+    mut items_grabbed: CompMut<ItemGrabbed>,
+    mut attachments: CompMut<PlayerBodyAttachment>,
+    mut item_throws: CompMut<ItemThrow>,
+    mut item_grabs: CompMut<ItemGrab>,
+    mut item_charges: CompMut<ItemCharge>,
+    mut item_ammo: CompMut<ItemAmmo>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+    mut animation_banks: CompMut<AnimationBankSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut damage_regions: CompMut<DamageRegion>,
+) {
+    for (instance, temp_spawner) in std::mem::take(&mut reloads.rehydrating) {
+        let new_instance = entities
+            .iter_with(&respawn_points)
+            .find(|(_, spawner)| ***spawner == temp_spawner)
+            .map(|(entity, _)| entity);
+
+        // The instance that is killed along with the temporary spawner
+        let discarded = match new_instance {
+            // The element isn't hydrated into an instance anymore, or the existing instance was
+            // killed in the meantime.
+            None => None,
+            Some(new_instance) if !entities.is_alive(instance) => Some(new_instance),
+
+            Some(new_instance) => {
+                let component_set = |entity| {
+                    [
+                        item_throws.contains(entity),
+                        item_grabs.contains(entity),
+                        item_charges.contains(entity),
+                        item_ammo.contains(entity),
+                        atlas_sprites.contains(entity),
+                        animation_banks.contains(entity),
+                        bodies.contains(entity),
+                        damage_regions.contains(entity),
+                    ]
+                };
+
+                if component_set(instance) == component_set(new_instance) {
+                    // Update the metadata components of the existing instance in place
+                    if let Some(item_throw) = item_throws.get(new_instance).cloned() {
+                        item_throws.insert(instance, item_throw);
+                    }
+                    if let Some(item_grab) = item_grabs.get(new_instance).copied() {
+                        if let Some(attachment) = attachments.get_mut(instance) {
+                            attachment.sync_animation = item_grab.sync_animation;
+                            attachment.offset = item_grab
+                                .grab_offset
+                                .extend(PlayerLayers::FIN_Z_OFFSET / 2.0);
+                        }
+                        item_grabs.insert(instance, item_grab);
+                    }
+                    if let Some(meta) = item_charges.get(new_instance).map(|x| x.meta.clone()) {
+                        item_charges.get_mut(instance).unwrap().meta = meta;
+                    }
+                    if let Some(meta) = item_ammo.get(new_instance).map(|x| x.meta.clone()) {
+                        let item_ammo = item_ammo.get_mut(instance).unwrap();
+                        item_ammo.ammo = item_ammo.ammo.min(meta.max_ammo);
+                        item_ammo.meta = meta;
+                    }
+                    if let Some(atlas) = atlas_sprites.get(new_instance).map(|x| x.atlas.clone()) {
+                        atlas_sprites.get_mut(instance).unwrap().atlas = atlas;
+                    }
+                    if let Some(animations) = animation_banks
+                        .get(new_instance)
+                        .map(|x| x.animations.clone())
+                    {
+                        animation_banks.get_mut(instance).unwrap().animations = animations;
+                    }
+                    if let Some(new_body) = bodies.get(new_instance).cloned() {
+                        let body = bodies.get_mut(instance).unwrap();
+                        body.shape = new_body.shape;
+                        body.has_mass = new_body.has_mass;
+                        body.has_friction = new_body.has_friction;
+                        body.can_rotate = new_body.can_rotate;
+                        body.bounciness = new_body.bounciness;
+                        body.gravity = new_body.gravity;
+                    }
+                    if let Some(damage_region) = damage_regions.get(new_instance).cloned() {
+                        damage_regions.insert(instance, damage_region);
+                    }
+
+                    Some(new_instance)
+                } else {
+                    // Replace the existing instance with the new one, at the same transform,
+                    // moving over its velocity and the player holding it.
+                    let spawner = **respawn_points.get(instance).unwrap();
+                    spawner_manager.swap_spawned_entities(instance, new_instance);
+                    respawn_points.insert(new_instance, DehydrateOutOfBounds(spawner));
+                    respawn_points.insert(instance, DehydrateOutOfBounds(temp_spawner));
+
+                    if let Some(transform) = transforms.get(instance).copied() {
+                        transforms.insert(new_instance, transform);
+                    }
+                    if let Some(body) = bodies.get(instance).cloned() {
+                        if let Some(new_body) = bodies.get_mut(new_instance) {
+                            new_body.velocity = body.velocity;
+                            new_body.angular_velocity = body.angular_velocity;
+                        }
+                    }
+                    for (player, inventory) in entities.iter_with(&mut inventories) {
+                        if inventory.0 == Some(instance) {
+                            inventory.0 = Some(new_instance);
+                            items_grabbed.insert(new_instance, ItemGrabbed { player });
+                        }
+                    }
+
+                    Some(instance)
+                }
+            }
+        };
+
+        if spawner_manager.is_entity_a_spawner(temp_spawner) {
+            spawner_manager.kill_spawner_entity(
+                temp_spawner,
+                &mut entities,
+                &element_kill_callbacks,
+                &mut commands,
+            );
+        } else {
+            if let Some(discarded) = discarded {
+                entities.kill(discarded);
+            }
+            entities.kill(temp_spawner);
+        }
+    }
+}
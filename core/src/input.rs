@@ -39,6 +39,26 @@ pub struct PlayerInput {
     pub editor_input: Option<EditorInput>,
     /// Whether or not this is an AI player.
     pub is_ai: bool,
+    /// How skilled the AI is, if this is an AI player.
+    pub ai_difficulty: AiDifficulty,
+    /// Whether or not the player's network connection is still alive.
+    pub connection: ConnectionStatus,
+}
+
+/// The network connection status of a player.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// The player is connected, or is a local player.
+    #[default]
+    Connected,
+    /// The player's connection has been lost. Their fish is handled according to the
+    /// [`DisconnectBehavior`] until they rejoin, or the [`disconnect_grace_period`] runs out.
+    ///
+    /// [`disconnect_grace_period`]: CoreConfigMeta::disconnect_grace_period
+    Disconnected,
+    /// The player was disconnected for longer than the grace period and has been removed from the
+    /// match.
+    Removed,
 }
 
 /// Player control input state
@@ -60,8 +80,17 @@ pub struct PlayerControl {
 
     pub slide_pressed: bool,
     pub slide_just_pressed: bool,
+
+    pub emote_pressed: bool,
+    pub emote_just_pressed: bool,
+
+    pub dash_pressed: bool,
+    pub dash_just_pressed: bool,
 }
 
+/// The largest input delay, in frames, that can be used in network matches.
+pub const MAX_INPUT_DELAY: u8 = 6;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TileLayer {
     pub layer_index: usize,
@@ -96,6 +125,22 @@ pub enum EditorInput {
         /// The entity to delete.
         entity: Entity,
     },
+    /// Override one of the properties of a placed element.
+    SetElementProperty {
+        /// The element entity to update.
+        entity: Entity,
+        /// The key of the property to set.
+        key: String,
+        /// The new value of the property, or [`None`] to go back to the element's default.
+        value: Option<ElementPropertyValue>,
+    },
+    /// Set the path that a placed element follows.
+    SetElementWaypoints {
+        /// The element entity to update.
+        entity: Entity,
+        /// The new waypoints, relative to the element's position.
+        waypoints: ElementWaypoints,
+    },
     /// Create a new layer
     CreateLayer {
         /// The name of the layer.
@@ -118,6 +163,13 @@ pub enum EditorInput {
         /// Whether or not to move the layer down. If false, move the layer up.
         down: bool,
     },
+    /// Move a layer to another position in the layer list, shifting the layers in between.
+    ReorderLayer {
+        /// The layer to move.
+        layer: u8,
+        /// The index that the layer will have once it is moved.
+        to: u8,
+    },
     /// Update the tilemap of a layer.
     SetTilemap {
         /// The layer index of the layer to update.
@@ -134,6 +186,8 @@ pub enum EditorInput {
         tilemap_tile_idx: Option<usize>,
         /// The tile collision kind
         collision: TileCollisionKind,
+        /// The tile surface properties
+        surface: TileSurface,
     },
     RenameMap {
         name: String,
@@ -143,4 +197,30 @@ pub enum EditorInput {
         element_layers: Vec<ElementLayer>,
         tile_size: Vec2,
     },
+    /// Add a parallax background layer in front of the existing ones.
+    CreateBackgroundLayer {
+        /// The settings for the new layer.
+        meta: ParallaxLayerMeta,
+    },
+    /// Change the settings of a parallax background layer.
+    UpdateBackgroundLayer {
+        /// The index of the background layer to update.
+        layer: u8,
+        /// The new settings for the layer.
+        meta: ParallaxLayerMeta,
+    },
+    /// Move a parallax background layer back or forward.
+    MoveBackgroundLayer {
+        /// The index of the background layer to move.
+        layer: u8,
+        /// Whether to move the layer down the list, towards the front. If false, move it back.
+        down: bool,
+    },
+    DeleteBackgroundLayer {
+        layer: u8,
+    },
+    /// Reverse the last step in the [`EditorHistory`][crate::editor::EditorHistory].
+    Undo,
+    /// Re-apply the last step that was undone.
+    Redo,
 }
@@ -0,0 +1,235 @@
+//! Registration of the components shown in the core world inspector.
+//!
+//! The inspector is drawn by the `jumpy` debug tools, but it doesn't know about the components in
+//! the game. Instead, each module implements [`Inspect`] for the components it wants to show, and
+//! registers them in its `install()` function with [`ComponentInspectors::register()`]:
+//!
+//! ```ignore
+//! pub fn install(session: &mut CoreSession) {
+//!     session.inspectors.register::<MyComponent>("MyComponent");
+//! }
+//! ```
+//!
+//! Components describe their fields with [`InspectorFields`], which is also used to write edits
+//! made in the inspector back to the component.
+
+use std::fmt::Display;
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.inspectors.register::<Transform>("Transform");
+}
+
+/// Implemented by components that can be shown in the core world inspector.
+pub trait Inspect {
+    /// Describe the fields of the component.
+    fn inspect(&mut self, fields: &mut InspectorFields);
+}
+
+/// The value of a field shown in the inspector.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InspectorValue {
+    Text(String),
+    Bool(bool),
+    Number(f32),
+    Vec2(Vec2),
+}
+
+impl Display for InspectorValue {
+    /// Floats are printed with enough digits to tell apart any two different values.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InspectorValue::Text(text) => write!(f, "{text}"),
+            InspectorValue::Bool(value) => write!(f, "{value}"),
+            InspectorValue::Number(value) => write!(f, "{value:?}"),
+            InspectorValue::Vec2(value) => write!(f, "[{:?}, {:?}]", value.x, value.y),
+        }
+    }
+}
+
+/// A field shown in the inspector.
+#[derive(Clone, Debug)]
+pub struct InspectorField {
+    pub name: &'static str,
+    pub value: InspectorValue,
+    /// Whether the field may be edited in the inspector.
+    pub editable: bool,
+}
+
+/// The fields of a component, collected by [`Inspect::inspect()`].
+#[derive(Clone, Debug, Default)]
+pub struct InspectorFields {
+    pub fields: Vec<InspectorField>,
+    /// The index of the field to change, and the value to change it to.
+    edit: Option<(usize, InspectorValue)>,
+}
+
+impl InspectorFields {
+    /// Create fields that will change the field at the given index to the given value when the
+    /// component is inspected.
+    pub fn with_edit(index: usize, value: InspectorValue) -> Self {
+        Self {
+            fields: default(),
+            edit: Some((index, value)),
+        }
+    }
+
+    /// Add a read-only field, displayed as text.
+    pub fn text(&mut self, name: &'static str, value: impl Display) {
+        self.fields.push(InspectorField {
+            name,
+            value: InspectorValue::Text(value.to_string()),
+            editable: false,
+        });
+    }
+
+    /// Add an editable boolean field.
+    pub fn bool(&mut self, name: &'static str, value: &mut bool) {
+        self.editable(name, value, InspectorValue::Bool, |value| match value {
+            InspectorValue::Bool(x) => Some(*x),
+            _ => None,
+        });
+    }
+
+    /// Add an editable number field.
+    pub fn number(&mut self, name: &'static str, value: &mut f32) {
+        self.editable(name, value, InspectorValue::Number, |value| match value {
+            InspectorValue::Number(x) => Some(*x),
+            _ => None,
+        });
+    }
+
+    /// Add an editable vector field.
+    pub fn vec2(&mut self, name: &'static str, value: &mut Vec2) {
+        self.editable(name, value, InspectorValue::Vec2, |value| match value {
+            InspectorValue::Vec2(x) => Some(*x),
+            _ => None,
+        });
+    }
+
+    fn editable<T: Copy>(
+        &mut self,
+        name: &'static str,
+        value: &mut T,
+        to_value: fn(T) -> InspectorValue,
+        from_value: fn(&InspectorValue) -> Option<T>,
+    ) {
+        let index = self.fields.len();
+        if let Some((_, edit)) = self.edit.as_ref().filter(|(i, _)| *i == index) {
+            if let Some(edit) = from_value(edit) {
+                *value = edit;
+            }
+        }
+        self.fields.push(InspectorField {
+            name,
+            value: to_value(*value),
+            editable: true,
+        });
+    }
+}
+
+/// A component type registered with the inspector.
+#[derive(Clone, Copy)]
+pub struct ComponentInspector {
+    /// The name of the component.
+    pub name: &'static str,
+    has: fn(&World, Entity) -> bool,
+    inspect: fn(&World, Entity, &mut InspectorFields) -> bool,
+}
+
+impl ComponentInspector {
+    /// Whether the given entity has the component.
+    pub fn has(&self, world: &World, entity: Entity) -> bool {
+        (self.has)(world, entity)
+    }
+
+    /// Inspect the component on the given entity, returning `false` if it doesn't have the
+    /// component.
+    pub fn inspect(&self, world: &World, entity: Entity, fields: &mut InspectorFields) -> bool {
+        (self.inspect)(world, entity, fields)
+    }
+}
+
+/// The component types that are shown in the core world inspector.
+#[derive(Clone, Default)]
+pub struct ComponentInspectors(Vec<ComponentInspector>);
+
+impl ComponentInspectors {
+    /// Register a component type to be shown in the inspector.
+    pub fn register<T: Inspect + TypeUlid + Clone + Send + Sync + 'static>(
+        &mut self,
+        name: &'static str,
+    ) {
+        self.0.push(ComponentInspector {
+            name,
+            has: |world, entity| world.components.get::<T>().borrow().contains(entity),
+            inspect: |world, entity, fields| {
+                let components = world.components.get::<T>();
+                let mut components = components.borrow_mut();
+                let Some(component) = components.get_mut(entity) else {
+                    return false;
+                };
+                component.inspect(fields);
+                true
+            },
+        });
+    }
+
+    /// Iterate over the registered component types.
+    pub fn iter(&self) -> impl Iterator<Item = &ComponentInspector> {
+        self.0.iter()
+    }
+}
+
+impl Inspect for Transform {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        // Only write the fields back when they are edited, so that converting them doesn't change
+        // the transform when it is just being looked at.
+        let translation = self.translation.truncate();
+        let mut new_translation = translation;
+        fields.vec2("translation", &mut new_translation);
+        if new_translation != translation {
+            self.translation = new_translation.extend(self.translation.z);
+        }
+        fields.number("z", &mut self.translation.z);
+
+        let rotation = self.rotation.to_euler(EulerRot::XYZ).2;
+        let mut new_rotation = rotation;
+        fields.number("rotation", &mut new_rotation);
+        if new_rotation != rotation {
+            self.rotation = Quat::from_rotation_z(new_rotation);
+        }
+
+        let scale = self.scale.truncate();
+        let mut new_scale = scale;
+        fields.vec2("scale", &mut new_scale);
+        if new_scale != scale {
+            self.scale = new_scale.extend(self.scale.z);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_changes_only_its_field() {
+        let mut lifetime = Lifetime::new(2.0);
+        let mut fields = InspectorFields::with_edit(1, InspectorValue::Number(0.5));
+        lifetime.inspect(&mut fields);
+        assert_eq!(lifetime.lifetime, 2.0);
+        assert_eq!(lifetime.age, 0.5);
+        assert_eq!(fields.fields[1].value, InspectorValue::Number(0.5));
+    }
+
+    #[test]
+    fn values_print_full_precision() {
+        assert_eq!(InspectorValue::Number(1.0 / 3.0).to_string(), "0.33333334");
+        assert_eq!(
+            InspectorValue::Vec2(vec2(1.0, -0.1)).to_string(),
+            "[1.0, -0.1]"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Interpolation of entity transforms between simulation frames.
+//!
+//! The session is always advanced in fixed steps of `1 /` [`FPS`][crate::FPS] seconds, which
+//! usually doesn't line up with the rate that the game is rendered at. To keep the motion smooth,
+//! the entities can be shown part of the way between the last two frames with
+//! [`CoreSession::interpolate_transforms()`], which uses the [`TransformSnapshots`] kept by the
+//! session.
+
+use std::collections::HashMap;
+
+use crate::prelude::*;
+
+/// The furthest, in pixels, that an entity may move in one frame and still be interpolated.
+///
+/// Anything that moves further was teleported, like a body wrapping around the edges of the map, and
+/// is shown at its new position straight away.
+pub const MAX_INTERPOLATION_DISTANCE: f32 = 100.0;
+
+/// Snapshots of the entity transforms around the last simulation frame, used to show the session
+/// between frames.
+///
+/// Entities are matched including their generation, so an entity that is killed and spawned again,
+/// like a player that respawns, isn't interpolated from where it was before. Neither are pooled
+/// entities that are reused, since their transforms are removed when they are released.
+///
+/// Interpolated transforms are written into the session's world, since that is what is rendered,
+/// so they must be put back with [`restore()`][Self::restore] before the simulation continues.
+/// [`CoreSession::advance()`] does this itself, and [`CoreSession::snapshot()`] and
+/// [`CoreSession::dump_world()`] use the actual transforms without having to put them back.
+#[derive(Default)]
+pub struct TransformSnapshots {
+    /// Whether snapshots are taken, which starts the first time the session is interpolated, so
+    /// that sessions that are never rendered don't pay for them.
+    enabled: bool,
+    /// The transforms of the entities at the start of the last frame.
+    previous: HashMap<Entity, Transform>,
+    /// The entities that have an interpolated transform in the world, with their actual transform
+    /// and the interpolated one.
+    interpolated: Vec<(Entity, Transform, Transform)>,
+}
+
+impl TransformSnapshots {
+    /// Take a snapshot of the entity transforms before a frame is simulated.
+    pub fn take_previous(&mut self, world: &World) {
+        self.restore(world);
+        if !self.enabled {
+            return;
+        }
+
+        self.previous = world
+            .run_initialized_system(|entities: Res<Entities>, transforms: Comp<Transform>| {
+                Ok(entities
+                    .iter_with(&transforms)
+                    .map(|(entity, transform)| (entity, *transform))
+                    .collect::<HashMap<_, _>>())
+            })
+            .unwrap();
+    }
+
+    /// Move the entities `alpha` of the way from their transforms at the start of the last frame to
+    /// their current ones, where `0.0` shows the previous frame and `1.0` shows the current one.
+    pub fn interpolate(&mut self, world: &World, alpha: f32) {
+        self.restore(world);
+        self.enabled = true;
+        if alpha >= 1.0 {
+            return;
+        }
+
+        let alpha = alpha.max(0.0);
+        let previous = std::mem::take(&mut self.previous);
+        let (previous, interpolated) = world
+            .run_initialized_system(
+                move |entities: Res<Entities>, mut transforms: CompMut<Transform>| {
+                    let mut interpolated = Vec::new();
+                    for (entity, transform) in entities.iter_with(&mut transforms) {
+                        let Some(shown) = previous
+                            .get(&entity)
+                            .and_then(|previous| interpolate_transform(previous, transform, alpha))
+                        else {
+                            continue;
+                        };
+                        interpolated.push((entity, *transform, shown));
+                        *transform = shown;
+                    }
+                    Ok((previous, interpolated))
+                },
+            )
+            .unwrap();
+        self.previous = previous;
+        self.interpolated = interpolated;
+    }
+
+    /// Put back the actual transforms of the entities that were interpolated.
+    ///
+    /// Transforms that were changed since they were interpolated, like in the world inspector, are
+    /// kept.
+    pub fn restore(&mut self, world: &World) {
+        if self.interpolated.is_empty() {
+            return;
+        }
+
+        let interpolated = std::mem::take(&mut self.interpolated);
+        world
+            .run_initialized_system(move |mut transforms: CompMut<Transform>| {
+                for (entity, actual, shown) in &interpolated {
+                    if let Some(transform) = transforms.get_mut(*entity) {
+                        if transforms_equal(transform, shown) {
+                            *transform = *actual;
+                        }
+                    }
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    /// Whether any entity is currently shown at an interpolated transform.
+    pub fn is_interpolated(&self) -> bool {
+        !self.interpolated.is_empty()
+    }
+
+    /// Write the actual transforms of the interpolated entities into a copy of the session's world,
+    /// while the session's world keeps showing the interpolated ones.
+    pub fn write_actual_transforms(&self, world: &World) {
+        if self.interpolated.is_empty() {
+            return;
+        }
+
+        let transforms = world.components.get::<Transform>();
+        let mut transforms = transforms.borrow_mut();
+        for (entity, actual, shown) in &self.interpolated {
+            if let Some(transform) = transforms.get_mut(*entity) {
+                if transforms_equal(transform, shown) {
+                    *transform = *actual;
+                }
+            }
+        }
+    }
+
+    /// Get the actual transforms of the entities that are currently interpolated.
+    pub fn actual_transforms(&self) -> HashMap<Entity, Transform> {
+        self.interpolated
+            .iter()
+            .map(|(entity, actual, _)| (*entity, *actual))
+            .collect()
+    }
+}
+
+/// Get the transform `alpha` of the way from `previous` to `current`, or [`None`] if the entity
+/// didn't move, or moved too far to be interpolated.
+fn interpolate_transform(
+    previous: &Transform,
+    current: &Transform,
+    alpha: f32,
+) -> Option<Transform> {
+    let distance = previous
+        .translation
+        .truncate()
+        .distance(current.translation.truncate());
+    if transforms_equal(previous, current) || distance > MAX_INTERPOLATION_DISTANCE {
+        return None;
+    }
+
+    let mut shown = *current;
+    shown.translation = previous.translation.lerp(current.translation, alpha);
+    shown.rotation = previous.rotation.slerp(current.rotation, alpha);
+    shown.scale = previous.scale.lerp(current.scale, alpha);
+    Some(shown)
+}
+
+fn transforms_equal(a: &Transform, b: &Transform) -> bool {
+    a.translation == b.translation && a.rotation == b.rotation && a.scale == b.scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_between_frames() {
+        let previous = Transform::from_translation(Vec3::new(0.0, 10.0, 1.0));
+        let current = Transform::from_translation(Vec3::new(20.0, 10.0, 1.0));
+        let shown = interpolate_transform(&previous, &current, 0.25).unwrap();
+        assert_eq!(shown.translation, Vec3::new(5.0, 10.0, 1.0));
+    }
+
+    #[test]
+    fn teleports_are_not_interpolated() {
+        let previous = Transform::from_translation(Vec3::ZERO);
+        let current =
+            Transform::from_translation(Vec3::new(MAX_INTERPOLATION_DISTANCE + 1.0, 0.0, 0.0));
+        assert!(interpolate_transform(&previous, &current, 0.5).is_none());
+        assert!(interpolate_transform(&current, &current, 0.5).is_none());
+    }
+}
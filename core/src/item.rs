@@ -7,10 +7,39 @@ use crate::prelude::*;
 pub fn install(session: &mut CoreSession) {
     session
         .stages
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate_item_charges)
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate_item_ammo)
+        .add_system_to_stage(CoreStage::PreUpdate, hydrate_item_lifetimes)
+        .add_system_to_stage(CoreStage::PostUpdate, reload_items)
+        .add_system_to_stage(CoreStage::PostUpdate, collect_duplicate_ammo)
         .add_system_to_stage(CoreStage::Last, grab_items)
-        .add_system_to_stage(CoreStage::Last, throw_dropped_items);
+        .add_system_to_stage(CoreStage::Last, cancel_dropped_item_charges)
+        .add_system_to_stage(CoreStage::Last, throw_dropped_items)
+        .add_system_to_stage(CoreStage::Last, render_item_charges)
+        .add_system_to_stage(CoreStage::Last, render_item_ammo);
+
+    session.inspectors.register::<Inventory>("Inventory");
+    session.inspectors.register::<Item>("Item");
+    session.inspectors.register::<ItemCharge>("ItemCharge");
+    session.inspectors.register::<ItemAmmo>("ItemAmmo");
+    session.inspectors.register::<ItemThrownBy>("ItemThrownBy");
 }
 
+/// The height of the charge bar shown above an item that is charging.
+const CHARGE_BAR_THICKNESS: f32 = 3.0;
+/// The width of the charge bar when the item is fully charged.
+const CHARGE_BAR_WIDTH: f32 = 24.0;
+/// The space between the item and its charge bar.
+const CHARGE_BAR_MARGIN: f32 = 16.0;
+/// The size of each of the ammo pips shown above a held item.
+const AMMO_PIP_SIZE: f32 = 2.0;
+/// The space between each of the ammo pips.
+const AMMO_PIP_SPACING: f32 = 2.0;
+/// The space between the item and its ammo pips.
+const AMMO_PIPS_MARGIN: f32 = 12.0;
+/// How many frames a player has to wait after throwing an item before they can grab it again.
+pub const REGRAB_GRACE_FRAMES: u64 = 15;
+
 /// Marker component for items.
 ///
 /// Items are any entity that players can pick up and use.
@@ -18,11 +47,21 @@ pub fn install(session: &mut CoreSession) {
 #[ulid = "01GP4DBSEB3R6ZNBNNTSY36GW4"]
 pub struct Item;
 
+impl Inspect for Item {
+    fn inspect(&mut self, _fields: &mut InspectorFields) {}
+}
+
 /// An intventory component, indicating another entity that the player is carrying.
 #[derive(Clone, TypeUlid, Default, Deref, DerefMut)]
 #[ulid = "01GP4D6M2QBSKZMEZMM22YGG41"]
 pub struct Inventory(pub Option<Entity>);
 
+impl Inspect for Inventory {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("item", format!("{:?}", self.0));
+    }
+}
+
 /// A helper struct containing a player-inventory pair that indicates the given player is holding
 /// the other entity in their inventory.
 #[derive(Debug, Clone, Copy)]
@@ -78,6 +117,84 @@ pub struct ItemDropped {
     pub player: Entity,
 }
 
+/// Component added to items when they are thrown, which keeps the player that threw them from
+/// grabbing them again for [`REGRAB_GRACE_FRAMES`].
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H65VKR3Z8QW2NTXJ4D7MBCE6"]
+pub struct ItemThrownBy {
+    /// The player that threw the item.
+    pub player: Entity,
+    /// The frame that the item was thrown on.
+    pub frame: u64,
+}
+
+impl Inspect for ItemThrownBy {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("player", format!("{:?}", self.player));
+        fields.text("frame", self.frame);
+    }
+}
+
+impl ItemThrownBy {
+    /// Whether the item was thrown too recently for `player` to grab it again on `frame`.
+    pub fn blocks_grab(&self, player: Entity, frame: u64) -> bool {
+        self.player == player && frame < self.frame + REGRAB_GRACE_FRAMES
+    }
+}
+
+/// Get the items overlapping `rect` that `player` is able to grab, with their positions.
+///
+/// Items that are held by a player, in `held_items`, and items that `player` just threw, can't be
+/// grabbed.
+pub fn grabbable_items(
+    player: Entity,
+    rect: Rect,
+    spatial_hash: &SpatialHash,
+    items: &Comp<Item>,
+    held_items: &[Entity],
+    thrown_by: &Comp<ItemThrownBy>,
+    frame: u64,
+    transforms: &Comp<Transform>,
+) -> Vec<(Entity, Vec2)> {
+    spatial_hash
+        .entities_overlapping(rect)
+        .filter(|ent| *ent != player)
+        .filter(|ent| items.contains(*ent))
+        // TODO: Use the ItemGrabbed tag for this detection after fixing the ItemGrabbed handling
+        .filter(|ent| !held_items.contains(ent))
+        .filter(|ent| {
+            !thrown_by
+                .get(*ent)
+                .map(|thrown| thrown.blocks_grab(player, frame))
+                .unwrap_or(false)
+        })
+        .filter_map(|ent| {
+            transforms
+                .get(ent)
+                .map(|transform| (ent, transform.translation.truncate()))
+        })
+        .collect()
+}
+
+/// Pick the item that a player grabs out of the items they are touching.
+///
+/// The item closest to the center of the player is picked, and items that are just as close are
+/// picked by their entity index, so that every machine picks the same one.
+pub fn choose_item_to_grab(
+    player_center: Vec2,
+    items: impl IntoIterator<Item = (Entity, Vec2)>,
+) -> Option<Entity> {
+    items
+        .into_iter()
+        .min_by(|(a, a_pos), (b, b_pos)| {
+            a_pos
+                .distance_squared(player_center)
+                .total_cmp(&b_pos.distance_squared(player_center))
+                .then(a.index().cmp(&b.index()))
+        })
+        .map(|(item, _)| item)
+}
+
 /// Marker component added to items when they are grabbed.
 #[derive(Clone, Copy, TypeUlid)]
 #[ulid = "01GP4DJ2RPYTDPKSKEK8JKK9VT"]
@@ -91,6 +208,507 @@ pub struct ItemGrabbed {
 #[ulid = "01GP4DJ84TFB8Z7H9VY7Y0R47H"]
 pub struct ItemUsed;
 
+/// Component for items that charge up while the use button is held down.
+///
+/// Added to items whose [`ElementMeta`] has a [`charge`][ElementMeta::charge] block. While
+/// charging, the item is not used. When the use button is let go, the item's
+/// [`power`][Self::power] is set and it gets an [`ItemUsed`], so the item can read the
+/// [`speed`][Self::speed] to use.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H0FHG88QH3MS3C35C0CQDK78"]
+pub struct ItemCharge {
+    pub meta: ItemChargeMeta,
+    /// Whether the use button is being held down.
+    pub charging: bool,
+    /// How many frames the item has been charging for.
+    pub frames: u32,
+    /// How charged up the item was when it was last let go, from `0.0` to `1.0`.
+    pub power: f32,
+    /// The entity that the charge bar is rendered with.
+    pub bar: Entity,
+}
+
+impl Inspect for ItemCharge {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.bool("charging", &mut self.charging);
+        fields.text("frames", self.frames);
+        fields.number("power", &mut self.power);
+    }
+}
+
+impl ItemCharge {
+    /// Start charging from nothing.
+    pub fn start(&mut self) {
+        self.charging = true;
+        self.frames = 0;
+    }
+
+    /// Charge up for one frame, returning `false` if the item was already fully charged.
+    pub fn tick(&mut self) -> bool {
+        if self.frames >= self.meta.max_charge_frames {
+            return false;
+        }
+        self.frames += 1;
+        true
+    }
+
+    /// Stop charging and set the [`power`][Self::power] to how charged up the item is.
+    pub fn release(&mut self) {
+        self.power = self.progress();
+        self.charging = false;
+        self.frames = 0;
+    }
+
+    /// Stop charging without using the item.
+    pub fn cancel(&mut self) {
+        self.charging = false;
+        self.frames = 0;
+    }
+
+    /// How charged up the item is, from `0.0` to `1.0`.
+    pub fn progress(&self) -> f32 {
+        if self.meta.max_charge_frames == 0 {
+            1.0
+        } else {
+            self.frames as f32 / self.meta.max_charge_frames as f32
+        }
+    }
+
+    /// The speed of the projectile for the [`power`][Self::power] of the last release.
+    pub fn speed(&self) -> f32 {
+        self.meta.min_speed + (self.meta.max_speed - self.meta.min_speed) * self.power
+    }
+}
+
+/// Component for the entity that renders the charge bar of an [`ItemCharge`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H3GKVHHDQYDZ6CG0SK2GXKZ4"]
+pub struct ItemChargeBar {
+    pub item: Entity,
+}
+
+/// Add [`ItemCharge`]s to items that have a charge block in their metadata.
+fn hydrate_item_charges(
+    mut entities: ResMut<Entities>,
+    items: Comp<Item>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut item_charges: CompMut<ItemCharge>,
+    mut charge_bars: CompMut<ItemChargeBar>,
+    mut transforms: CompMut<Transform>,
+) {
+    let mut not_hydrated_bitset = item_charges.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(items.bitset());
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let items = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for item in items {
+        let element_handle = element_handles.get(item).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let Some(meta) = &element_meta.charge else {
+            continue;
+        };
+
+        let bar = entities.create();
+        transforms.insert(bar, default());
+        charge_bars.insert(bar, ItemChargeBar { item });
+        item_charges.insert(
+            item,
+            ItemCharge {
+                meta: meta.clone(),
+                charging: false,
+                frames: 0,
+                power: 0.0,
+                bar,
+            },
+        );
+    }
+}
+
+/// Stop charging items that have been dropped, or that were dropped by their player dying.
+fn cancel_dropped_item_charges(
+    entities: Res<Entities>,
+    items_dropped: Comp<ItemDropped>,
+    mut item_charges: CompMut<ItemCharge>,
+) {
+    for (_ent, (_dropped, charge)) in entities.iter_with((&items_dropped, &mut item_charges)) {
+        charge.cancel();
+    }
+}
+
+/// Render the charge bar above each item that is charging.
+fn render_item_charges(
+    mut entities: ResMut<Entities>,
+    item_charges: Comp<ItemCharge>,
+    charge_bars: Comp<ItemChargeBar>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+) {
+    let mut orphaned_bars = Vec::new();
+    for (bar_ent, bar) in entities.iter_with(&charge_bars) {
+        let (Some(charge), Some(item_transform)) =
+            (item_charges.get(bar.item), transforms.get(bar.item).copied()) else {
+            // Remove charge bars for items that have been deleted
+            orphaned_bars.push(bar_ent);
+            continue;
+        };
+
+        if !charge.charging || charge.frames == 0 {
+            paths.remove(bar_ent);
+            continue;
+        }
+
+        let mut translation = item_transform.translation;
+        translation.y += CHARGE_BAR_MARGIN;
+        transforms.insert(bar_ent, Transform::from_translation(translation));
+
+        let left = -CHARGE_BAR_WIDTH / 2.0;
+        paths.insert(
+            bar_ent,
+            Path2d {
+                color: charge.meta.color.0,
+                points: vec![
+                    vec2(left, 0.0),
+                    vec2(left + CHARGE_BAR_WIDTH * charge.progress(), 0.0),
+                ],
+                thickness: CHARGE_BAR_THICKNESS,
+                ..default()
+            },
+        );
+    }
+
+    for bar_ent in orphaned_bars {
+        entities.kill(bar_ent);
+    }
+}
+
+/// Component for items that have a limited amount of ammo.
+///
+/// Added to items whose [`ElementMeta`] has an [`ammo`][ElementMeta::ammo] block. Using the item
+/// uses up its ammo, and the item won't get an [`ItemUsed`] when it doesn't have enough left.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H1DQXD73ZGVS7783F925RWDB"]
+pub struct ItemAmmo {
+    pub meta: ItemAmmoMeta,
+    /// How much ammo the item has left.
+    pub ammo: u32,
+    /// How many frames are left until the item is reloaded, if it is reloading.
+    pub reload_frames_left: Option<u32>,
+    /// The entity that the ammo pips are rendered with.
+    pub pips: Entity,
+}
+
+impl Inspect for ItemAmmo {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("ammo", self.ammo);
+        fields.text(
+            "reload_frames_left",
+            format!("{:?}", self.reload_frames_left),
+        );
+    }
+}
+
+impl ItemAmmo {
+    /// Create a full [`ItemAmmo`].
+    pub fn new(meta: ItemAmmoMeta, pips: Entity) -> Self {
+        Self {
+            ammo: meta.max_ammo,
+            meta,
+            reload_frames_left: None,
+            pips,
+        }
+    }
+
+    /// Whether the item has enough ammo to be used.
+    pub fn can_use(&self) -> bool {
+        self.reload_frames_left.is_none() && self.ammo >= self.meta.shots_per_use
+    }
+
+    /// Use up the ammo for one use of the item, returning `false` if it can't be used.
+    pub fn use_ammo(&mut self) -> bool {
+        if !self.can_use() {
+            return false;
+        }
+        self.ammo -= self.meta.shots_per_use;
+        true
+    }
+
+    /// Whether the item is out of ammo and should start reloading.
+    pub fn needs_reload(&self) -> bool {
+        self.reload_frames_left.is_none()
+            && self.ammo < self.meta.shots_per_use
+            && self.meta.when_empty == AmmoEmptyBehavior::Reload
+    }
+
+    /// Start reloading the item.
+    pub fn start_reload(&mut self) {
+        self.reload_frames_left = Some(self.meta.reload_frames.unwrap_or_default());
+    }
+
+    /// Reload for one frame, returning `true` if the item has finished reloading.
+    pub fn tick_reload(&mut self) -> bool {
+        let Some(frames_left) = &mut self.reload_frames_left else {
+            return false;
+        };
+        *frames_left = frames_left.saturating_sub(1);
+        if *frames_left == 0 {
+            self.refill();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Fill the item back up with ammo, stopping any reload.
+    pub fn refill(&mut self) {
+        self.ammo = self.meta.max_ammo;
+        self.reload_frames_left = None;
+    }
+
+    /// Add ammo to the item, up to its [`max_ammo`][ItemAmmoMeta::max_ammo].
+    pub fn add_ammo(&mut self, ammo: u32) {
+        self.ammo = (self.ammo + ammo).min(self.meta.max_ammo);
+        if self.ammo >= self.meta.shots_per_use {
+            self.reload_frames_left = None;
+        }
+    }
+}
+
+/// Component for the entity that renders the ammo pips of an [`ItemAmmo`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H3NSE2G6J7DG5KB37SMJ8REB"]
+pub struct ItemAmmoPips {
+    pub item: Entity,
+}
+
+/// Add [`ItemAmmo`]s to items that have an ammo block in their metadata.
+fn hydrate_item_ammo(
+    mut entities: ResMut<Entities>,
+    items: Comp<Item>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut item_ammo: CompMut<ItemAmmo>,
+    mut ammo_pips: CompMut<ItemAmmoPips>,
+    mut transforms: CompMut<Transform>,
+) {
+    let mut not_hydrated_bitset = item_ammo.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(items.bitset());
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    let items = entities
+        .iter_with_bitset(&not_hydrated_bitset)
+        .collect::<Vec<_>>();
+    for item in items {
+        let element_handle = element_handles.get(item).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let Some(meta) = &element_meta.ammo else {
+            continue;
+        };
+
+        let pips = entities.create();
+        transforms.insert(pips, default());
+        ammo_pips.insert(pips, ItemAmmoPips { item });
+        item_ammo.insert(item, ItemAmmo::new(meta.clone(), pips));
+    }
+}
+
+/// Add [`ItemLifetime`]s to items that have a ground lifetime in their metadata.
+fn hydrate_item_lifetimes(
+    entities: Res<Entities>,
+    items: Comp<Item>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    mut item_lifetimes: CompMut<ItemLifetime>,
+) {
+    let mut not_hydrated_bitset = item_lifetimes.bitset().clone();
+    not_hydrated_bitset.bit_not();
+    not_hydrated_bitset.bit_and(items.bitset());
+    not_hydrated_bitset.bit_and(element_handles.bitset());
+
+    for item in entities.iter_with_bitset(&not_hydrated_bitset) {
+        let element_handle = element_handles.get(item).unwrap();
+        let Some(element_meta) = element_assets.get(&element_handle.get_bevy_handle()) else {
+            continue;
+        };
+        let Some(lifetime) = element_meta.ground_lifetime else {
+            continue;
+        };
+
+        item_lifetimes.insert(item, ItemLifetime { lifetime });
+    }
+}
+
+/// Reload items that have run out of ammo, playing the reload animation for the player holding
+/// them.
+fn reload_items(
+    entities: Res<Entities>,
+    player_inventories: PlayerInventories,
+    item_grabs: Comp<ItemGrab>,
+    mut item_ammo: CompMut<ItemAmmo>,
+    mut player_layers: CompMut<PlayerLayers>,
+    transforms: Comp<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
+) {
+    for (item, ammo) in entities.iter_with(&mut item_ammo) {
+        let holder = player_inventories
+            .iter()
+            .find_map(|x| x.filter(|x| x.inventory == item))
+            .map(|x| x.player);
+
+        if ammo.needs_reload() {
+            ammo.start_reload();
+            if let Some(sound) = &ammo.meta.reload_sound {
+                audio_events.play_from(
+                    sound.clone(),
+                    ammo.meta.reload_sound_volume,
+                    transforms.get(item),
+                );
+            }
+        }
+        if ammo.reload_frames_left.is_none() {
+            continue;
+        }
+
+        let finished = ammo.tick_reload();
+        let Some(layers) = holder.and_then(|x| player_layers.get_mut(x)) else {
+            continue;
+        };
+        if finished {
+            if let Some(item_grab) = item_grabs.get(item) {
+                layers.fin_anim = item_grab.fin_anim;
+            }
+        } else if let Some(fin_anim) = ammo.meta.reload_fin_anim {
+            layers.fin_anim = fin_anim;
+        }
+    }
+}
+
+/// Top up the ammo of held items with the ammo of the same kind of item when the player holding
+/// it touches one, instead of the player having to pick it up.
+fn collect_duplicate_ammo(
+    entities: Res<Entities>,
+    player_inventories: PlayerInventories,
+    collision_world: CollisionWorld,
+    element_handles: Comp<ElementHandle>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    mut item_ammo: CompMut<ItemAmmo>,
+    mut hydrated: CompMut<MapElementHydrated>,
+    mut commands: Commands,
+) {
+    let held_items = player_inventories
+        .iter()
+        .flatten()
+        .map(|x| x.inventory)
+        .collect::<Vec<_>>();
+    let mut collected = Vec::new();
+
+    for Inv { player, inventory } in player_inventories.iter().flatten() {
+        let Some(handle) = element_handles.get(*inventory) else {
+            continue;
+        };
+        if !item_ammo.contains(*inventory) {
+            continue;
+        }
+
+        let duplicates = collision_world
+            .actor_collisions(*player)
+            .into_iter()
+            .filter(|ent| {
+                !held_items.contains(ent)
+                    && !collected.contains(ent)
+                    && item_ammo.contains(*ent)
+                    && element_handles
+                        .get(*ent)
+                        .map(|x| x.get_bevy_handle() == handle.get_bevy_handle())
+                        .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+        for duplicate in duplicates {
+            let ammo = item_ammo.get(duplicate).unwrap().ammo;
+            item_ammo.get_mut(*inventory).unwrap().add_ammo(ammo);
+
+            // Remove the duplicate and let its spawner spawn it again
+            if let Some(spawner) = spawners.get(duplicate) {
+                hydrated.remove(**spawner);
+            }
+            collected.push(duplicate);
+        }
+    }
+
+    for item in collected {
+        if entities.is_alive(item) {
+            commands.add(move |mut entities: ResMut<Entities>| {
+                entities.kill(item);
+            });
+        }
+    }
+}
+
+/// Render the ammo left in each held item as a row of pips above it.
+fn render_item_ammo(
+    mut entities: ResMut<Entities>,
+    player_inventories: PlayerInventories,
+    item_ammo: Comp<ItemAmmo>,
+    ammo_pips: Comp<ItemAmmoPips>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+) {
+    let mut orphaned_pips = Vec::new();
+    for (pips_ent, pips) in entities.iter_with(&ammo_pips) {
+        let (Some(ammo), Some(item_transform)) =
+            (item_ammo.get(pips.item), transforms.get(pips.item).copied()) else {
+            // Remove pips for items that have been deleted
+            orphaned_pips.push(pips_ent);
+            continue;
+        };
+
+        let is_held = player_inventories
+            .iter()
+            .flatten()
+            .any(|x| x.inventory == pips.item);
+        if !is_held || ammo.ammo == 0 {
+            paths.remove(pips_ent);
+            continue;
+        }
+
+        let mut translation = item_transform.translation;
+        translation.y += AMMO_PIPS_MARGIN;
+        transforms.insert(pips_ent, Transform::from_translation(translation));
+
+        let width = ammo.ammo as f32 * (AMMO_PIP_SIZE + AMMO_PIP_SPACING) - AMMO_PIP_SPACING;
+        let mut points = Vec::new();
+        let mut line_breaks = Vec::new();
+        for i in 0..ammo.ammo {
+            let left = -width / 2.0 + i as f32 * (AMMO_PIP_SIZE + AMMO_PIP_SPACING);
+            points.push(vec2(left, 0.0));
+            points.push(vec2(left + AMMO_PIP_SIZE, 0.0));
+            line_breaks.push(points.len());
+        }
+        paths.insert(
+            pips_ent,
+            Path2d {
+                color: ammo.meta.color.0,
+                points,
+                thickness: AMMO_PIP_SIZE,
+                line_breaks,
+                ..default()
+            },
+        );
+    }
+
+    for pips_ent in orphaned_pips {
+        entities.kill(pips_ent);
+    }
+}
+
 /// Component defining the grab settings when an item is grabbed.
 ///
 /// Mainly handled by the [`grab_items`] system which consumes the
@@ -170,8 +788,8 @@ impl ItemThrow {
         Self {
             normal: Vec2::new(1.5, 1.2).normalize() * 0.6,
             fast: Vec2::new(1.5, 1.2).normalize(),
-            up: Vec2::new(0.0, 1.1),
-            drop: Vec2::new(0.0, 0.0),
+            up: Vec2::new(1.0, 1.0).normalize() * 0.9,
+            drop: Vec2::new(0.15, 0.1),
             lob: Vec2::new(1.0, 2.5).normalize() * 1.1,
             roll: Vec2::new(0.4, -0.1),
             spin: 0.0,
@@ -207,26 +825,67 @@ impl ItemThrow {
 
     /// Chooses one of the throw values based on a [`PlayerControl`]
     pub fn velocity_from_control(&self, player_control: &PlayerControl) -> Vec2 {
+        self.velocity(ThrowKind::from_control(player_control))
+    }
+
+    /// Get the throw value for a kind of throw.
+    pub fn velocity(&self, kind: ThrowKind) -> Vec2 {
+        match kind {
+            ThrowKind::Normal => self.normal,
+            ThrowKind::Fast => self.fast,
+            ThrowKind::Up => self.up,
+            ThrowKind::Drop => self.drop,
+            ThrowKind::Lob => self.lob,
+            ThrowKind::Roll => self.roll,
+        }
+    }
+
+    /// Get the throw value for a kind of throw, unless the item's metadata overrides it.
+    pub fn velocity_with_overrides(
+        &self,
+        kind: ThrowKind,
+        overrides: Option<&ItemThrowMeta>,
+    ) -> Vec2 {
+        overrides
+            .and_then(|overrides| overrides.get(kind))
+            .unwrap_or_else(|| self.velocity(kind))
+    }
+}
+
+/// The kinds of throws, picked by the direction the player is holding when they throw an item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThrowKind {
+    Normal,
+    Fast,
+    Up,
+    Drop,
+    Lob,
+    Roll,
+}
+
+impl ThrowKind {
+    /// Chooses the kind of throw based on a [`PlayerControl`]
+    pub fn from_control(player_control: &PlayerControl) -> Self {
         let PlayerControl { move_direction, .. } = player_control;
         let y = move_direction.y;
         let moving = move_direction.x.abs() > 0.0;
         if y < 0.0 {
             if moving {
-                return self.roll;
+                return Self::Roll;
             } else {
-                return self.drop;
+                return Self::Drop;
             }
         }
         if moving {
             if y > 0.0 {
-                self.lob
+                Self::Lob
             } else {
-                self.fast
+                Self::Fast
             }
         } else if y > 0.0 {
-            self.up
+            Self::Up
         } else {
-            self.normal
+            Self::Normal
         }
     }
 }
@@ -244,6 +903,10 @@ pub fn throw_dropped_items(
     mut transforms: CompMut<Transform>,
     item_spawners: Comp<DehydrateOutOfBounds>,
     map_layers: Comp<SpawnedMapLayerMeta>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    frame: Res<SessionFrame>,
+    mut thrown_by: CompMut<ItemThrownBy>,
     mut commands: Commands,
 ) {
     for (entity, (_items, item_throw, body, transform)) in
@@ -264,13 +927,25 @@ pub fn throw_dropped_items(
                 Vec2::ONE
             };
 
-            let throw_velocity = item_throw.velocity_from_control(
+            let throw_kind = ThrowKind::from_control(
                 &player_inputs
                     .players
                     .get(player_indexes.get(player).unwrap().0)
                     .unwrap()
                     .control,
             );
+            let overrides = element_handles
+                .get(entity)
+                .and_then(|handle| element_assets.get(&handle.get_bevy_handle()))
+                .map(|element_meta| &element_meta.throw);
+            let throw_velocity = item_throw.velocity_with_overrides(throw_kind, overrides);
+            thrown_by.insert(
+                entity,
+                ItemThrownBy {
+                    player,
+                    frame: **frame,
+                },
+            );
 
             if let Some(item_spawner) = item_spawners.get(entity) {
                 let map_layer = map_layers.get(item_spawner.0).unwrap();
@@ -285,3 +960,281 @@ pub fn throw_dropped_items(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestInput, TestSession, TestSessionBuilder};
+
+    fn ammo_meta(when_empty: AmmoEmptyBehavior) -> ItemAmmoMeta {
+        ItemAmmoMeta {
+            max_ammo: 3,
+            shots_per_use: 1,
+            when_empty,
+            reload_frames: Some(10),
+            reload_fin_anim: None,
+            reload_sound: None,
+            reload_sound_volume: 0.0,
+            color: ColorMeta(Color::WHITE),
+        }
+    }
+
+    #[test]
+    fn ammo_empties_reloads_and_refills() {
+        let mut ammo = ItemAmmo::new(ammo_meta(AmmoEmptyBehavior::Reload), Entity::new(0, 0));
+
+        for _ in 0..3 {
+            assert!(ammo.use_ammo());
+        }
+        assert_eq!(ammo.ammo, 0);
+        assert!(!ammo.use_ammo());
+        assert!(ammo.needs_reload());
+
+        ammo.start_reload();
+        for _ in 0..9 {
+            assert!(!ammo.tick_reload());
+            assert!(!ammo.can_use());
+        }
+        assert!(ammo.tick_reload());
+        assert_eq!(ammo.ammo, 3);
+        assert!(!ammo.needs_reload());
+        assert!(ammo.use_ammo());
+    }
+
+    #[test]
+    fn inert_items_stay_empty() {
+        let mut ammo = ItemAmmo::new(ammo_meta(AmmoEmptyBehavior::Inert), Entity::new(0, 0));
+
+        for _ in 0..3 {
+            assert!(ammo.use_ammo());
+        }
+        assert!(!ammo.needs_reload());
+        assert!(!ammo.tick_reload());
+        assert!(!ammo.use_ammo());
+    }
+
+    #[test]
+    fn collecting_ammo_stops_reloading() {
+        let mut meta = ammo_meta(AmmoEmptyBehavior::Reload);
+        meta.shots_per_use = 2;
+        let mut ammo = ItemAmmo::new(meta, Entity::new(0, 0));
+
+        assert!(ammo.use_ammo());
+        assert!(ammo.needs_reload());
+        ammo.start_reload();
+        ammo.add_ammo(5);
+        assert_eq!(ammo.ammo, 3);
+        assert_eq!(ammo.reload_frames_left, None);
+    }
+
+    #[test]
+    fn closest_item_is_grabbed_with_ties_broken_by_entity() {
+        let center = Vec2::new(10.0, 10.0);
+        let near = Entity::new(5, 0);
+        let far = Entity::new(1, 0);
+        let also_near = Entity::new(3, 0);
+
+        let items = [(far, Vec2::new(20.0, 10.0)), (near, Vec2::new(12.0, 10.0))];
+        assert_eq!(choose_item_to_grab(center, items), Some(near));
+
+        // Items that are just as close are picked the same way, whatever order they come in
+        let items = [
+            (near, Vec2::new(12.0, 10.0)),
+            (also_near, Vec2::new(8.0, 10.0)),
+            (far, Vec2::new(20.0, 10.0)),
+        ];
+        assert_eq!(choose_item_to_grab(center, items), Some(also_near));
+        let mut reversed = items;
+        reversed.reverse();
+        assert_eq!(choose_item_to_grab(center, reversed), Some(also_near));
+
+        assert_eq!(choose_item_to_grab(center, []), None);
+    }
+
+    #[test]
+    fn thrown_items_only_block_the_thrower_for_a_while() {
+        let thrower = Entity::new(0, 0);
+        let other = Entity::new(1, 0);
+        let thrown = ItemThrownBy {
+            player: thrower,
+            frame: 100,
+        };
+
+        assert!(thrown.blocks_grab(thrower, 100));
+        assert!(thrown.blocks_grab(thrower, 100 + REGRAB_GRACE_FRAMES - 1));
+        assert!(!thrown.blocks_grab(thrower, 100 + REGRAB_GRACE_FRAMES));
+        assert!(!thrown.blocks_grab(other, 100));
+    }
+
+    /// Get the number of frames until an item despawns, if it has a lifetime.
+    fn frames_left(session: &TestSession, item: Entity) -> Option<f32> {
+        session
+            .session
+            .world
+            .run_initialized_system(move |lifetimes: Comp<Lifetime>| {
+                Ok(lifetimes
+                    .get(item)
+                    .map(|lifetime| (lifetime.lifetime - lifetime.age) * crate::FPS))
+            })
+            .unwrap()
+    }
+
+    /// A player standing on a grenade that despawns after a second on the ground.
+    fn ground_lifetime_session() -> TestSession {
+        let mut builder = TestSessionBuilder::room(20, 10);
+        builder.add_player(Vec2::new(64.0, 48.0));
+        builder.add_element(
+            "/grenade",
+            ElementMeta {
+                ground_lifetime: Some(1.0),
+                ..testing::grenade_meta()
+            },
+            Vec2::new(64.0, 40.0),
+        );
+        builder.build()
+    }
+
+    #[test]
+    fn item_picked_up_just_before_despawn_is_kept_after_rollback() {
+        let grab = TestInput {
+            grab: true,
+            ..default()
+        };
+        let mut session = ground_lifetime_session();
+        session.step(2);
+        let item = session.items()[0];
+
+        // Wait until the grenade is about to despawn
+        while frames_left(&session, item).unwrap() > 2.0 {
+            session.step(1);
+        }
+        let mut snapshot = session.session.snapshot();
+
+        // Left on the ground, it despawns and is put back in its spawner
+        session.step(4);
+        assert!(!session.is_alive(item));
+
+        // Roll back, and pick it up at the last moment instead
+        session.session.restore(&mut snapshot);
+        assert!(session.is_alive(item));
+        session.set_input(0, grab);
+        for _ in 0..30 {
+            session.step(1);
+            session.set_input(0, default());
+            assert!(session.is_alive(item));
+        }
+        assert_eq!(session.player_inventory(0), Some(item));
+        assert_eq!(frames_left(&session, item), None);
+
+        // The whole race gives the same result when rolled back over and over
+        let grab_frame = 60 - 2;
+        testing::assert_rollback_deterministic(
+            ground_lifetime_session,
+            |frame| {
+                let mut inputs = [TestInput::default(); MAX_PLAYERS];
+                inputs[0].grab = frame == grab_frame;
+                inputs
+            },
+            120,
+            7,
+        );
+    }
+
+    #[test]
+    fn world_item_limit_despawns_oldest_unheld_item() {
+        let mut builder = TestSessionBuilder::room(30, 10);
+        builder.add_player(Vec2::new(64.0, 48.0));
+        let grenade =
+            builder.add_element("/grenade", testing::grenade_meta(), Vec2::new(64.0, 40.0));
+        for x in [160.0, 256.0] {
+            testing::add_element(&mut builder.map, &grenade, Vec2::new(x, 40.0));
+        }
+        builder.meta.config.max_world_items = Some(2);
+
+        let mut session = builder.build();
+        session.step(10);
+        // Items that are put back in the map don't count towards the limit
+        let items_before = session.items();
+        assert_eq!(items_before.len(), 3);
+
+        // Hold the oldest grenade
+        session.step_with(
+            0,
+            TestInput {
+                grab: true,
+                ..default()
+            },
+            1,
+        );
+        session.step(1);
+        let held = session.player_inventory(0).unwrap();
+        assert_eq!(held, items_before[0]);
+
+        // Let go of the grenades' spawners, like an item spawner does when its item is taken
+        session
+            .session
+            .world
+            .run_initialized_system(
+                |entities: Res<Entities>, mut respawn_points: CompMut<DehydrateOutOfBounds>| {
+                    let items = entities
+                        .iter_with(&respawn_points)
+                        .map(|(ent, _)| ent)
+                        .collect::<Vec<_>>();
+                    for item in items {
+                        respawn_points.remove(item);
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+        session.step(2);
+
+        assert!(session.is_alive(held));
+        assert_eq!(session.player_inventory(0), Some(held));
+        assert!(!session.is_alive(items_before[1]));
+        assert!(session.is_alive(items_before[2]));
+        assert_eq!(session.items().len(), 2);
+    }
+
+    #[test]
+    fn thrown_item_cant_be_grabbed_again_right_away() {
+        let mut builder = TestSessionBuilder::room(20, 10);
+        builder.add_player(Vec2::new(64.0, 48.0));
+        // Let go of the grenade without throwing it, so that it lands at the player's feet
+        builder.add_element(
+            "/grenade",
+            ElementMeta {
+                throw: ItemThrowMeta {
+                    normal: Some(Vec2::ZERO),
+                    ..default()
+                },
+                ..testing::grenade_meta()
+            },
+            Vec2::new(64.0, 40.0),
+        );
+        let mut session = builder.build();
+        session.step(10);
+
+        let grab = TestInput {
+            grab: true,
+            ..default()
+        };
+        session.step_with(0, grab, 1);
+        session.step_with(0, default(), 1);
+        let item = session.player_inventory(0).unwrap();
+
+        // Drop it, and try to grab it again straight away
+        session.step_with(0, grab, 1);
+        session.step_with(0, default(), 1);
+        assert_eq!(session.player_inventory(0), None);
+        session.step_with(0, grab, 1);
+        session.step_with(0, default(), 1);
+        assert_eq!(session.player_inventory(0), None);
+
+        // Once the grace window has passed, it can be grabbed again
+        session.step(REGRAB_GRACE_FRAMES as u32);
+        session.step_with(0, grab, 1);
+        session.step_with(0, default(), 1);
+        assert_eq!(session.player_inventory(0), Some(item));
+    }
+}
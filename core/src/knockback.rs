@@ -0,0 +1,224 @@
+//! Explosion knockback, that pushes kinematic bodies away from a point.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, apply_knockback);
+}
+
+/// Component that makes an explosion push away the bodies around it.
+///
+/// The knockback is applied once, on the frame after the entity is spawned, and then this
+/// component is removed.
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H4D0QK7V2CYW3MNF8XR5T1GA"]
+pub struct Knockback {
+    pub meta: KnockbackMeta,
+    /// The player that set off the explosion.
+    ///
+    /// If this is [`None`], the owner of the entity's [`DamageRegionOwner`] is used instead.
+    pub owner: Option<Entity>,
+}
+
+/// A push applied to all of the bodies within a radius of a point.
+#[derive(Clone, Copy, Debug)]
+pub struct RadialImpulse {
+    pub center: Vec2,
+    pub radius: f32,
+    /// The speed given to bodies at the center.
+    pub strength: f32,
+    /// How quickly the impulse gets weaker further from the center. See
+    /// [`KnockbackMeta::falloff`].
+    pub falloff: f32,
+    /// Multiplies the impulse of players that are standing on the ground.
+    pub grounded_player_multiplier: f32,
+    /// A body that isn't pushed, such as the player that set off the explosion.
+    pub exempt: Option<Entity>,
+}
+
+impl RadialImpulse {
+    pub fn new(center: Vec2, radius: f32, strength: f32, falloff: f32) -> Self {
+        Self {
+            center,
+            radius,
+            strength,
+            falloff,
+            grounded_player_multiplier: 1.0,
+            exempt: None,
+        }
+    }
+
+    /// Get the strength of the impulse at the given distance from the center.
+    pub fn strength_at(&self, distance: f32) -> f32 {
+        if self.radius <= 0.0 || distance > self.radius {
+            return 0.0;
+        }
+        let closeness = 1.0 - (distance / self.radius).clamp(0.0, 1.0);
+        self.strength * closeness.powf(self.falloff.max(0.0))
+    }
+
+    /// Get the impulse given to a body with the given bounding box, or [`None`] if the body is
+    /// outside of the radius.
+    pub fn impulse_for(&self, bounding_box: Rect) -> Option<Vec2> {
+        let closest_point = self.center.clamp(bounding_box.min, bounding_box.max);
+        let distance = closest_point.distance(self.center);
+        if distance > self.radius {
+            return None;
+        }
+
+        // Push bodies straight up if they are centered on the explosion
+        let direction = (bounding_box.center() - self.center)
+            .try_normalize()
+            .unwrap_or(Vec2::Y);
+        Some(direction * self.strength_at(distance))
+    }
+
+    /// Apply the impulse to the velocity of every body within the radius, returning the bodies
+    /// that were pushed.
+    ///
+    /// Bodies that are deactivated, like items being held by a player, aren't pushed.
+    pub fn apply(
+        &self,
+        spatial_hash: &SpatialHash,
+        transforms: &Comp<Transform>,
+        bodies: &mut CompMut<KinematicBody>,
+        player_indexes: &Comp<PlayerIdx>,
+    ) -> Vec<Entity> {
+        let area = Rect {
+            min: self.center - self.radius,
+            max: self.center + self.radius,
+        };
+        let mut pushed = Vec::new();
+        for entity in spatial_hash.entities_overlapping(area) {
+            let (Some(transform), Some(body)) = (transforms.get(entity), bodies.get_mut(entity))
+            else {
+                continue;
+            };
+            if body.is_deactivated || Some(entity) == self.exempt {
+                continue;
+            }
+            let Some(mut impulse) = self.impulse_for(body.bounding_box(*transform)) else {
+                continue;
+            };
+            if body.is_on_ground && player_indexes.contains(entity) {
+                impulse *= self.grounded_player_multiplier;
+            }
+            body.velocity += impulse;
+            pushed.push(entity);
+        }
+        pushed
+    }
+}
+
+/// Push the bodies within `radius` of `center` away from it.
+///
+/// The impulse is `strength` at the center, and gets weaker towards the edge of the radius
+/// according to the `falloff`. This is meant for use in [`Commands`] by elements that burst or
+/// explode without a [`Knockback`] entity.
+pub fn apply_radial_impulse(world: &World, center: Vec2, radius: f32, strength: f32, falloff: f32) {
+    let impulse = RadialImpulse::new(center, radius, strength, falloff);
+    world
+        .run_initialized_system(
+            move |spatial_hash: Res<SpatialHash>,
+                  transforms: Comp<Transform>,
+                  mut bodies: CompMut<KinematicBody>,
+                  player_indexes: Comp<PlayerIdx>| {
+                impulse.apply(&spatial_hash, &transforms, &mut bodies, &player_indexes);
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+fn apply_knockback(
+    entities: Res<Entities>,
+    frame: Res<SessionFrame>,
+    spatial_hash: Res<SpatialHash>,
+    transforms: Comp<Transform>,
+    mut knockbacks: CompMut<Knockback>,
+    damage_region_owners: Comp<DamageRegionOwner>,
+    mut bodies: CompMut<KinematicBody>,
+    player_indexes: Comp<PlayerIdx>,
+    mut last_hits: CompMut<LastHitBy>,
+) {
+    let explosions = entities
+        .iter_with((&knockbacks, &transforms))
+        .map(|(entity, (knockback, transform))| {
+            let owner = knockback
+                .owner
+                .or_else(|| damage_region_owners.get(entity).map(|owner| owner.0));
+            let meta = &knockback.meta;
+            let impulse = RadialImpulse {
+                grounded_player_multiplier: meta.grounded_player_multiplier,
+                exempt: owner.filter(|_| meta.exempt_owner),
+                ..RadialImpulse::new(
+                    transform.translation.xy(),
+                    meta.radius,
+                    meta.strength,
+                    meta.falloff,
+                )
+            };
+            (entity, impulse, owner)
+        })
+        .collect::<Vec<_>>();
+
+    for (entity, impulse, owner) in explosions {
+        let pushed = impulse.apply(&spatial_hash, &transforms, &mut bodies, &player_indexes);
+        knockbacks.remove(entity);
+
+        // Remember who pushed the other players, so that they get the kill if it sends them to
+        // their death
+        let Some(attacker) = owner.and_then(|owner| player_indexes.get(owner)) else {
+            continue;
+        };
+        for player in pushed {
+            if Some(player) == owner || !player_indexes.contains(player) {
+                continue;
+            }
+            last_hits.insert(
+                player,
+                LastHitBy {
+                    player: attacker.0,
+                    frame: **frame as u32,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn impulse_falls_off_with_distance() {
+        let linear = RadialImpulse::new(Vec2::ZERO, 100.0, 10.0, 1.0);
+        assert_eq!(linear.strength_at(0.0), 10.0);
+        assert_eq!(linear.strength_at(50.0), 5.0);
+        assert_eq!(linear.strength_at(100.0), 0.0);
+        assert_eq!(linear.strength_at(150.0), 0.0);
+
+        let flat = RadialImpulse::new(Vec2::ZERO, 100.0, 10.0, 0.0);
+        assert_eq!(flat.strength_at(99.0), 10.0);
+    }
+
+    #[test]
+    fn impulse_uses_closest_point_of_bounding_box() {
+        let impulse = RadialImpulse::new(Vec2::ZERO, 100.0, 10.0, 1.0);
+
+        // The body's center is out of range, but its edge is in it
+        let body = Rect::new(120.0, 0.0, 60.0, 10.0);
+        let push = impulse.impulse_for(body).unwrap();
+        assert!(push.x > 0.0);
+        assert!(push.y.abs() < 1e-6);
+
+        let far_body = Rect::new(200.0, 0.0, 60.0, 10.0);
+        assert!(impulse.impulse_for(far_body).is_none());
+
+        // Bodies on top of the explosion are pushed up
+        let centered_body = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(impulse.impulse_for(centered_body), Some(Vec2::Y * 10.0));
+    }
+}
@@ -15,6 +15,8 @@ pub mod bevy_prelude {
         crate::{
             input::EditorInput,
             metadata::*,
+            mutators::{Mutator, Mutators},
+            player::AiDifficulty,
             session::{CoreSession, CoreSessionInfo, GameSessionPlayerInfo},
             MAX_PLAYERS,
         },
@@ -35,23 +37,43 @@ pub mod external {
 }
 
 pub mod attachment;
+pub mod audio;
+pub mod breakable_tiles;
 pub mod bullet;
 pub mod camera;
+pub mod checksum;
+pub mod corpse;
 pub mod damage;
 pub mod debug;
+pub mod disconnect;
 pub mod editor;
 pub mod elements;
+pub mod force_region;
 pub mod globals;
+pub mod headless;
+pub mod hot_reload;
 pub mod input;
+pub mod inspector;
+pub mod interpolation;
 pub mod item;
+pub mod knockback;
 pub mod lifetime;
 pub mod map;
 pub mod map_constructor;
+pub mod match_state;
 pub mod metadata;
+pub mod mutators;
+pub mod overtime;
+pub mod particles;
 pub mod physics;
 pub mod player;
+pub mod pool;
 pub mod random;
+pub mod replay;
+pub mod score;
 pub mod session;
+pub mod stocks;
+pub mod testing;
 pub mod utils;
 
 /// The target fixed frames-per-second that the game sumulation runs at.
@@ -90,18 +112,35 @@ pub const MAX_PLAYERS: usize = 4;
 /// the same system stage.
 pub fn install_modules(session: &mut session::CoreSession) {
     bones_lib::install(&mut session.stages);
+    inspector::install(session);
     physics::install(session);
+    audio::install(session);
     input::install(session);
+    replay::install(session);
+    disconnect::install(session);
     map::install(session);
     player::install(session);
+    match_state::install(session);
+    score::install(session);
+    stocks::install(session);
+    overtime::install(session);
+    mutators::install(session);
     elements::install(session);
     damage::install(session);
+    knockback::install(session);
+    force_region::install(session);
     camera::install(session);
+    corpse::install(session);
+    pool::install(session);
+    particles::install(session);
     lifetime::install(session);
     random::install(session);
     debug::install(session);
     item::install(session);
+    hot_reload::install(session);
     attachment::install(session);
     bullet::install(session);
+    breakable_tiles::install(session);
     editor::install(session);
+    checksum::install(session);
 }
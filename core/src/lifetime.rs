@@ -1,16 +1,36 @@
 //! Entity lifetimes for deleting an entity after a period of time.
+//!
+//! Items also get a [`Lifetime`] while they aren't being held if they have an [`ItemLifetime`], and
+//! the oldest items are despawned when there are more than the
+//! [`max_world_items`][CoreConfigMeta::max_world_items].
 
 use std::time::Duration;
 
 use crate::{prelude::*, FPS};
 
 pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<WorldItems>();
     session
         .stages
+        // These must run before the lifetime system, so that an item picked up on the frame it
+        // would have despawned is kept.
+        .add_system_to_stage(CoreStage::PostUpdate, limit_world_items)
+        .add_system_to_stage(CoreStage::PostUpdate, update_item_lifetimes)
         .add_system_to_stage(CoreStage::PostUpdate, lifetime_system)
+        .add_system_to_stage(CoreStage::PostUpdate, blink_expiring_items)
         .add_system_to_stage(CoreStage::PostUpdate, invincibility);
+
+    session.inspectors.register::<Lifetime>("Lifetime");
+    session.inspectors.register::<ItemLifetime>("ItemLifetime");
 }
 
+/// How many frames an item blinks for before its [`ItemLifetime`] runs out.
+const EXPIRY_BLINK_FRAMES: f32 = 60.0;
+/// How many frames an expiring item stays shown or hidden for each time it blinks.
+const EXPIRY_BLINK_INTERVAL: u32 = 4;
+/// The alpha of an expiring item while it is blinked out.
+const EXPIRY_BLINK_ALPHA: f32 = 0.25;
+
 /// The lifetime state of an entity
 ///
 /// > **Note:** The age represents how long the entity has had the [`Lifetime`] component on it, not
@@ -36,8 +56,140 @@ impl Lifetime {
     }
 }
 
-/// Despawns entities that have an expired lifetime
-fn lifetime_system(mut entities: ResMut<Entities>, mut lifetimes: CompMut<Lifetime>) {
+impl Inspect for Lifetime {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.number("lifetime", &mut self.lifetime);
+        fields.number("age", &mut self.age);
+    }
+}
+
+/// Component for items that despawn after lying around without being held for too long.
+///
+/// Added to items whose [`ElementMeta`] has a [`ground_lifetime`][ElementMeta::ground_lifetime].
+/// The item has a [`Lifetime`] whenever it isn't held, which is removed when it is picked up, so
+/// the time starts over the next time it is dropped.
+#[derive(Clone, Copy, Debug, TypeUlid)]
+#[ulid = "01H5ZK4A7GWE3NDQ8TXB2RCMFY"]
+pub struct ItemLifetime {
+    /// How long the item may go without being held in seconds.
+    pub lifetime: f32,
+}
+
+impl Inspect for ItemLifetime {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.number("lifetime", &mut self.lifetime);
+    }
+}
+
+/// Resource containing the items in the world, oldest first, used to limit the number of items to
+/// the [`max_world_items`][CoreConfigMeta::max_world_items].
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H5ZK3M8RTV2XQW9B4NCJ6PDE"]
+pub struct WorldItems(pub Vec<Entity>);
+
+/// Despawn the oldest items that aren't held while there are more than the
+/// [`max_world_items`][CoreConfigMeta::max_world_items].
+///
+/// The items are despawned by expiring their [`Lifetime`].
+fn limit_world_items(
+    entities: Res<Entities>,
+    core_meta: Res<CoreMetaArc>,
+    items: Comp<Item>,
+    inventories: Comp<Inventory>,
+    respawn_points: Comp<DehydrateOutOfBounds>,
+    mut world_items: ResMut<WorldItems>,
+    mut lifetimes: CompMut<Lifetime>,
+) {
+    let world_items = &mut world_items.0;
+    world_items.retain(|item| entities.is_alive(*item) && items.contains(*item));
+    for (item, _) in entities.iter_with(&items) {
+        if !world_items.contains(&item) {
+            world_items.push(item);
+        }
+    }
+
+    let Some(max_items) = core_meta.config.max_world_items else {
+        return;
+    };
+    let held_items = entities
+        .iter_with(&inventories)
+        .filter_map(|(_, inventory)| inventory.0)
+        .collect::<Vec<_>>();
+    // Items that are put back by their map element when they are despawned don't add up
+    let counted_items = world_items
+        .iter()
+        .copied()
+        .filter(|item| !respawn_points.contains(*item))
+        .collect::<Vec<_>>();
+
+    let excess = counted_items.len().saturating_sub(max_items as usize);
+    for item in counted_items
+        .into_iter()
+        .filter(|item| !held_items.contains(item))
+        .take(excess)
+    {
+        lifetimes.insert(item, Lifetime::new(0.0));
+    }
+}
+
+/// Give items with an [`ItemLifetime`] a [`Lifetime`] while they aren't being held.
+fn update_item_lifetimes(
+    entities: Res<Entities>,
+    item_lifetimes: Comp<ItemLifetime>,
+    inventories: Comp<Inventory>,
+    mut lifetimes: CompMut<Lifetime>,
+) {
+    let held_items = entities
+        .iter_with(&inventories)
+        .filter_map(|(_, inventory)| inventory.0)
+        .collect::<Vec<_>>();
+
+    for (item, item_lifetime) in entities.iter_with(&item_lifetimes) {
+        if held_items.contains(&item) {
+            lifetimes.remove(item);
+        } else if !lifetimes.contains(item) {
+            lifetimes.insert(item, Lifetime::new(item_lifetime.lifetime));
+        }
+    }
+}
+
+/// Blink items that are about to despawn, so that players know to pick them up.
+fn blink_expiring_items(
+    entities: Res<Entities>,
+    item_lifetimes: Comp<ItemLifetime>,
+    lifetimes: Comp<Lifetime>,
+    mut atlas_sprites: CompMut<AtlasSprite>,
+) {
+    for (item, (_item_lifetime, sprite)) in
+        entities.iter_with((&item_lifetimes, &mut atlas_sprites))
+    {
+        let frames_left = lifetimes
+            .get(item)
+            .map(|lifetime| (lifetime.lifetime - lifetime.age) * FPS)
+            .filter(|frames_left| *frames_left < EXPIRY_BLINK_FRAMES);
+        let blinked_out = frames_left
+            .map(|frames_left| (frames_left as u32 / EXPIRY_BLINK_INTERVAL) % 2 == 1)
+            .unwrap_or(false);
+
+        sprite
+            .color
+            .set_a(if blinked_out { EXPIRY_BLINK_ALPHA } else { 1.0 });
+    }
+}
+
+/// Despawns entities that have an expired lifetime, returning [`Pooled`] entities to the
+/// [`EntityPool`] instead of killing them.
+///
+/// Entities with a [`DehydrateOutOfBounds`] de-hydrate their spawner, the same as when they fall
+/// out of the map, so that it spawns them again.
+fn lifetime_system(
+    mut entities: ResMut<Entities>,
+    mut lifetimes: CompMut<Lifetime>,
+    mut pool: ResMut<EntityPool>,
+    pooled: Comp<Pooled>,
+    respawn_points: Comp<DehydrateOutOfBounds>,
+    mut hydrated: CompMut<MapElementHydrated>,
+) {
     let mut to_kill = Vec::new();
     for (entity, lifetime) in &mut entities.iter_with(&mut lifetimes) {
         lifetime.age += 1.0 / FPS;
@@ -46,7 +198,16 @@ fn lifetime_system(mut entities: ResMut<Entities>, mut lifetimes: CompMut<Lifeti
         }
     }
     for entity in to_kill {
-        entities.kill(entity);
+        if let Some(spawner) = respawn_points.get(entity) {
+            hydrated.remove(**spawner);
+        }
+        if pooled.contains(entity) {
+            // Stop the lifetime so that the entity isn't released again before it is reset
+            lifetimes.remove(entity);
+            pool.release(entity);
+        } else {
+            entities.kill(entity);
+        }
     }
 }
 
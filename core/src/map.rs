@@ -13,7 +13,9 @@ pub fn install(session: &mut CoreSession) {
     session
         .stages
         .add_system_to_stage(CoreStage::First, spawn_map)
-        .add_system_to_stage(CoreStage::First, handle_out_of_bounds_players);
+        .add_system_to_stage(CoreStage::First, handle_out_of_bounds_players)
+        .add_system_to_stage(CoreStage::First, handle_out_of_bounds_items_and_bullets)
+        .add_system_to_stage(CoreStage::Last, handle_map_edges);
 }
 
 /// Resource containing the map metadata for this game session.
@@ -44,22 +46,34 @@ pub fn z_depth_for_map_layer(layer_idx: usize) -> f32 {
 #[ulid = "01GSR8V683B3EH5QAB2PMGN9J7"]
 pub struct SpawnedMapMeta {
     pub name: Arc<str>,
+    pub author: Option<Arc<str>>,
+    pub recommended_players: Option<MapPlayerCountMeta>,
     pub background: Arc<BackgroundMeta>,
     pub background_color: ColorMeta,
     pub grid_size: UVec2,
     pub tile_size: Vec2,
     pub layer_names: Arc<[String]>,
+    pub respawn: MapRespawnMeta,
+    pub camera_bounds: Option<MapCameraBoundsMeta>,
+    pub music: Option<MapMusicMeta>,
+    pub boundary: MapBoundaryMeta,
 }
 
 impl Default for SpawnedMapMeta {
     fn default() -> Self {
         Self {
             name: "".into(),
+            author: default(),
+            recommended_players: default(),
             background: default(),
             background_color: default(),
             grid_size: default(),
             tile_size: default(),
             layer_names: Arc::new([]),
+            respawn: default(),
+            camera_bounds: default(),
+            music: default(),
+            boundary: default(),
         }
     }
 }
@@ -149,6 +163,38 @@ pub struct NavGraphEdge {
     pub distance: f32,
 }
 
+/// Spawn the sprites for the parallax background layers.
+pub fn spawn_parallax_background(
+    background: &BackgroundMeta,
+    entities: &mut Entities,
+    sprites: &mut CompMut<Sprite>,
+    transforms: &mut CompMut<Transform>,
+    parallax_bg_sprites: &mut CompMut<ParallaxBackgroundSprite>,
+) {
+    for (layer_idx, layer) in background.layers.iter().enumerate() {
+        let repetitions = if layer.tiling { -1..=1 } else { 0..=0 };
+        for i in repetitions {
+            let ent = entities.create();
+            sprites.insert(
+                ent,
+                Sprite {
+                    image: layer.image.clone(),
+                    ..default()
+                },
+            );
+            transforms.insert(ent, default());
+            parallax_bg_sprites.insert(
+                ent,
+                ParallaxBackgroundSprite {
+                    idx: i,
+                    layer_idx,
+                    meta: layer.clone(),
+                },
+            );
+        }
+    }
+}
+
 fn spawn_map(
     mut commands: Commands,
     mut entities: ResMut<Entities>,
@@ -159,7 +205,10 @@ fn spawn_map(
     mut tile_layers: CompMut<TileLayer>,
     mut transforms: CompMut<Transform>,
     mut element_handles: CompMut<ElementHandle>,
+    mut element_properties: CompMut<ElementProperties>,
+    mut element_waypoints: CompMut<ElementWaypoints>,
     mut tile_collisions: CompMut<TileCollisionKind>,
+    mut tile_surfaces: CompMut<TileSurface>,
     mut parallax_bg_sprites: CompMut<ParallaxBackgroundSprite>,
     mut sprites: CompMut<Sprite>,
     mut nav_graph: ResMut<NavGraph>,
@@ -176,11 +225,17 @@ fn spawn_map(
     // Fill in the spawned map metadata
     *spawned_map_meta = SpawnedMapMeta {
         name: map.name.clone().into(),
+        author: map.author.as_deref().map(Into::into),
+        recommended_players: map.recommended_players,
         background: Arc::new(map.background.clone()),
         background_color: map.background_color,
         grid_size: map.grid_size,
         tile_size: map.tile_size,
         layer_names: map.layers.iter().map(|x| x.id.to_string()).collect(),
+        respawn: map.respawn.clone(),
+        camera_bounds: map.camera_bounds,
+        music: map.music.clone(),
+        boundary: map.boundary,
     };
 
     // Spawn the camera
@@ -205,26 +260,13 @@ fn spawn_map(
     nav_graph.0 = create_nav_graph(&map);
 
     // Spawn parallax backgrounds
-    for layer in &map.background.layers {
-        for i in -1..=1 {
-            let ent = entities.create();
-            sprites.insert(
-                ent,
-                Sprite {
-                    image: layer.image.clone(),
-                    ..default()
-                },
-            );
-            transforms.insert(ent, default());
-            parallax_bg_sprites.insert(
-                ent,
-                ParallaxBackgroundSprite {
-                    idx: i,
-                    meta: layer.clone(),
-                },
-            );
-        }
-    }
+    spawn_parallax_background(
+        &map.background,
+        &mut entities,
+        &mut sprites,
+        &mut transforms,
+        &mut parallax_bg_sprites,
+    );
 
     // Load tiles
     for (layer_idx, layer) in map.layers.iter().enumerate() {
@@ -237,6 +279,11 @@ fn spawn_map(
         );
 
         for tile_meta in &layer.tiles {
+            // Skip broken tiles instead of panicking. These are reported by `MapMeta::validate`.
+            if tile_meta.pos.cmpge(map.grid_size).any() {
+                warn!(layer = %layer.id, pos = ?tile_meta.pos, "Skipping tile outside of the map");
+                continue;
+            }
             let tile_ent = entities.create();
             tile_layer.set(tile_meta.pos, Some(tile_ent));
             tiles.insert(
@@ -256,6 +303,9 @@ fn spawn_map(
             if tile_meta.collision != TileCollisionKind::Empty {
                 tile_collisions.insert(tile_ent, tile_meta.collision);
             }
+            if !tile_meta.surface.is_default() {
+                tile_surfaces.insert(tile_ent, tile_meta.surface);
+            }
         }
         let layer_ent = entities.create();
         spawned_map_layer_metas.insert(layer_ent, SpawnedMapLayerMeta { layer_idx });
@@ -274,6 +324,10 @@ fn spawn_map(
                 Transform::from_translation(element_meta.pos.extend(layer_z)),
             );
             element_handles.insert(element_ent, ElementHandle(element_meta.element.clone()));
+            element_properties.insert(element_ent, element_meta.properties.clone());
+            if !element_meta.waypoints.is_empty() {
+                element_waypoints.insert(element_ent, element_meta.waypoints.clone());
+            }
         }
     }
 
@@ -293,7 +347,112 @@ fn handle_out_of_bounds_players(
     for (player_ent, (_player_idx, transform)) in entities.iter_with((&player_indexes, &transforms))
     {
         if map.is_out_of_bounds(&transform.translation) {
-            commands.add(PlayerCommand::kill(player_ent, None));
+            commands.add(PlayerCommand::kill_environmental(player_ent, None));
+        }
+    }
+}
+
+/// Despawn the items and bullets that have gone past the kill margin of the map.
+///
+/// Items that are put back by their spawner are handled by the elements module instead.
+fn handle_out_of_bounds_items_and_bullets(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    transforms: Comp<Transform>,
+    items: Comp<Item>,
+    bullets: Comp<Bullet>,
+    spawners: Comp<DehydrateOutOfBounds>,
+    map: Res<LoadedMap>,
+) {
+    for (item_ent, (_item, transform)) in entities.iter_with((&items, &transforms)) {
+        if !spawners.contains(item_ent) && map.is_out_of_bounds(&transform.translation) {
+            commands.add(move |mut entities: ResMut<Entities>| {
+                entities.kill(item_ent);
+            });
+        }
+    }
+
+    for (bullet_ent, (_bullet, transform)) in entities.iter_with((&bullets, &transforms)) {
+        if map.is_out_of_bounds(&transform.translation) {
+            commands.add(move |mut pool: ResMut<EntityPool>| {
+                pool.release(bullet_ent);
+            });
+        }
+    }
+}
+
+/// Keep bodies inside of the map's [`MapEdgeBehavior::Wall`] edges, and move the bodies that leave
+/// through one of its [`MapEdgeBehavior::Wrap`] edges over to the opposite side of the map.
+fn handle_map_edges(
+    entities: Res<Entities>,
+    map: Res<LoadedMap>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut collision_world: CollisionWorld,
+) {
+    let boundary = map.boundary;
+    let map_size = map.grid_size.as_vec2() * map.tile_size;
+
+    let mut wrapped = Vec::new();
+    for (entity, (body, transform)) in entities.iter_with((&mut bodies, &mut transforms)) {
+        // Bodies that are being held are moved along with their holder instead
+        if body.is_deactivated {
+            continue;
+        }
+        let rect = body.bounding_box(*transform);
+
+        // Push the body back inside of the walls, stopping it from moving into them
+        for edge in MapEdge::ALL {
+            if boundary.edge(edge) != MapEdgeBehavior::Wall {
+                continue;
+            }
+            match edge {
+                MapEdge::Left if rect.min.x < 0.0 => {
+                    transform.translation.x -= rect.min.x;
+                    body.velocity.x = body.velocity.x.max(0.0);
+                }
+                MapEdge::Right if rect.max.x > map_size.x => {
+                    transform.translation.x -= rect.max.x - map_size.x;
+                    body.velocity.x = body.velocity.x.min(0.0);
+                }
+                MapEdge::Bottom if rect.min.y < 0.0 => {
+                    transform.translation.y -= rect.min.y;
+                    body.velocity.y = body.velocity.y.max(0.0);
+                    body.is_on_ground = true;
+                }
+                MapEdge::Top if rect.max.y > map_size.y => {
+                    transform.translation.y -= rect.max.y - map_size.y;
+                    body.velocity.y = body.velocity.y.min(0.0);
+                }
+                _ => (),
+            }
+        }
+
+        // Move the body to the other side of the map once its center leaves through a wrap edge
+        let mut offset = Vec2::ZERO;
+        for edge in map.edges_past(rect.center(), 0.0) {
+            if boundary.edge(edge) != MapEdgeBehavior::Wrap {
+                continue;
+            }
+            match edge {
+                MapEdge::Left => offset.x = map_size.x,
+                MapEdge::Right => offset.x = -map_size.x,
+                MapEdge::Bottom => offset.y = map_size.y,
+                MapEdge::Top => offset.y = -map_size.y,
+            }
+        }
+        if offset != Vec2::ZERO {
+            transform.translation += offset.extend(0.0);
+            wrapped.push(entity);
+        }
+    }
+
+    // Update the collisions of the wrapped bodies, so they don't get stuck in platforms that they
+    // came out inside of.
+    if !wrapped.is_empty() {
+        collision_world.update(&transforms);
+        for entity in wrapped {
+            collision_world.handle_teleport(entity);
         }
     }
 }
@@ -728,3 +887,66 @@ fn create_nav_graph(meta: &MapMeta) -> Arc<NavGraphInner> {
 
     Arc::new(graph)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestInput, TestSession, TestSessionBuilder};
+
+    /// Make a session with one player in a room that doesn't have side walls, and that has the
+    /// given map boundary.
+    fn open_sided_session(boundary: MapBoundaryMeta) -> TestSession {
+        let mut builder = TestSessionBuilder::room(20, 10);
+        builder.map.layers[0]
+            .tiles
+            .retain(|tile| tile.pos.y == 0 || (tile.pos.x != 0 && tile.pos.x != 19));
+        builder.map.boundary = boundary;
+        builder.add_player(Vec2::new(48.0, 48.0));
+        let mut session = builder.build();
+        session.step(10);
+        session
+    }
+
+    #[test]
+    fn players_wrap_around_wrap_edges() {
+        let map_width = 20.0 * testing::TILE_SIZE;
+        let mut session = open_sided_session(MapBoundaryMeta {
+            left: MapEdgeBehavior::Wrap,
+            right: MapEdgeBehavior::Wrap,
+            ..default()
+        });
+
+        // Walking off of the left side brings the player back in on the right
+        let mut wrapped = false;
+        for _ in 0..120 {
+            session.step_with(0, TestInput::moving(Vec2::new(-1.0, 0.0)), 1);
+            if session.player_position(0).unwrap().x > map_width / 2.0 {
+                wrapped = true;
+                break;
+            }
+        }
+        assert!(wrapped);
+        assert!(session.is_player_alive(0));
+        assert!(session.kills().is_empty());
+    }
+
+    #[test]
+    fn wall_edges_keep_players_in_and_kill_edges_use_the_kill_margin() {
+        let mut session = open_sided_session(MapBoundaryMeta {
+            left: MapEdgeBehavior::Wall,
+            ..default()
+        });
+        session.step_with(0, TestInput::moving(Vec2::new(-1.0, 0.0)), 120);
+        let x = session.player_position(0).unwrap().x;
+        assert!((0.0..48.0).contains(&x));
+        assert!(session.is_player_alive(0));
+
+        let mut session = open_sided_session(MapBoundaryMeta {
+            kill_margin: 32.0,
+            ..default()
+        });
+        session.step_with(0, TestInput::moving(Vec2::new(-1.0, 0.0)), 120);
+        let kill = session.kills().last().cloned().unwrap();
+        assert_eq!((kill.victim, kill.killer), (0, None));
+    }
+}
@@ -249,6 +249,7 @@ impl MapConstructor for ShiftnanigansMapConstructor {
                                         position,
                                         &Some(tile.tilemap_tile_index as usize),
                                         tile.tile_collision_kind,
+                                        default(),
                                     );
                                 }
                                 LayerPixelEntityType::Element(element) => {
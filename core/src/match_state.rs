@@ -0,0 +1,105 @@
+//! The round-start countdown.
+//!
+//! Every match starts with a countdown of [`CoreConfigMeta::start_countdown_frames`], during which
+//! the players can't move. The countdown is counted in frames, so that it plays out the same way
+//! when a network game is rolled back.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<MatchState>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, update_match_state);
+}
+
+/// Resource containing whether the match is still counting down to start or is being played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TypeUlid)]
+#[ulid = "01H4J3C8X2M7PQ9VZ5T1KDNW6B"]
+pub enum MatchState {
+    /// The match is counting down to start, and player input is ignored.
+    Starting {
+        /// The number of frames left until the match starts.
+        countdown_frames: u32,
+    },
+    /// The match has started.
+    Playing {
+        /// The number of frames since the match started.
+        frames_played: u32,
+    },
+}
+
+impl Default for MatchState {
+    fn default() -> Self {
+        Self::Playing { frames_played: 0 }
+    }
+}
+
+impl MatchState {
+    /// Create a match state that counts down for the given number of frames before the match
+    /// starts.
+    pub fn new(countdown_frames: u32) -> Self {
+        if countdown_frames == 0 {
+            Self::default()
+        } else {
+            Self::Starting { countdown_frames }
+        }
+    }
+
+    /// Whether the match has started and the players can move.
+    pub fn is_playing(&self) -> bool {
+        matches!(self, Self::Playing { .. })
+    }
+
+    /// Advance the match state by one frame.
+    pub fn tick(&mut self) {
+        *self = match *self {
+            Self::Starting { countdown_frames } if countdown_frames > 1 => Self::Starting {
+                countdown_frames: countdown_frames - 1,
+            },
+            Self::Starting { .. } => Self::Playing { frames_played: 0 },
+            Self::Playing { frames_played } => Self::Playing {
+                frames_played: frames_played.saturating_add(1),
+            },
+        };
+    }
+}
+
+/// Counts down to the start of the match, keeping the players still until it starts.
+///
+/// This runs after the AI players have decided on their inputs, so that they are stopped too.
+fn update_match_state(
+    mut match_state: ResMut<MatchState>,
+    mut player_inputs: ResMut<PlayerInputs>,
+) {
+    if !match_state.is_playing() {
+        for player in &mut player_inputs.players {
+            player.control = default();
+        }
+    }
+    match_state.tick();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countdown_finishes_after_its_frames() {
+        let mut state = MatchState::new(2);
+        assert!(!state.is_playing());
+        state.tick();
+        assert_eq!(
+            state,
+            MatchState::Starting {
+                countdown_frames: 1
+            }
+        );
+        state.tick();
+        assert_eq!(state, MatchState::Playing { frames_played: 0 });
+        state.tick();
+        assert_eq!(state, MatchState::Playing { frames_played: 1 });
+
+        assert!(MatchState::new(0).is_playing());
+    }
+}
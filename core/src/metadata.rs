@@ -8,11 +8,13 @@ use crate::prelude::*;
 mod common;
 mod element;
 mod map;
+mod particles;
 mod player;
 
 pub use common::*;
 pub use element::*;
 pub use map::*;
+pub use particles::*;
 pub use player::*;
 
 /// Resource containing the session's [`CoreMeta`].
@@ -31,7 +33,10 @@ impl ::bevy::prelude::Plugin for JumpyCoreAssetsPlugin {
             .add_bones_asset::<PlayerMeta>()
             .add_bones_asset::<MapMeta>()
             .add_bones_asset::<ElementMeta>()
-            .add_bones_asset::<BulletMeta>();
+            .add_bones_asset::<BulletMeta>()
+            .add_bones_asset::<ParticleEffectMeta>()
+            .init_resource::<PlayerMetaDiagnostics>()
+            .add_system(validate_player_meta);
     }
 }
 
@@ -42,12 +47,23 @@ impl ::bevy::prelude::Plugin for JumpyCoreAssetsPlugin {
 pub struct CoreMeta {
     pub camera: CameraMeta,
     pub physics: PhysicsMeta,
+    pub breakable_tiles: BreakableTilesMeta,
+    pub corpse: CorpseMeta,
+    /// The cosmetic particle effect played when a player lands on the ground.
+    #[serde(default)]
+    pub landing_dust: Option<Handle<ParticleEffectMeta>>,
     pub config: CoreConfigMeta,
+    /// The settings of the [`Mutator`]s that can be turned on for a match.
+    #[serde(default)]
+    pub mutators: MutatorsMeta,
     pub map_tilesets: Vec<Handle<Atlas>>,
     pub players: Vec<Handle<PlayerMeta>>,
     pub stable_maps: Vec<Handle<MapMeta>>,
     pub map_elements: Vec<Handle<ElementMeta>>,
     pub experimental_maps: Vec<Handle<MapMeta>>,
+    /// The map that new players are taken through to learn the controls.
+    #[serde(default)]
+    pub tutorial_map: Option<Handle<MapMeta>>,
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
@@ -64,6 +80,39 @@ pub struct CameraMeta {
     pub zoom_out_lerp_factor: f32,
     pub min_camera_size: Vec2,
     pub player_camera_box_size: Vec2,
+    /// The size of the box around the camera target that the players can move around in without
+    /// moving the camera.
+    pub deadzone: Vec2,
+    /// How many frames ahead of the players' average velocity the camera looks.
+    pub look_ahead: f32,
+    /// The smallest height the camera can zoom in to, in pixels.
+    pub min_height: f32,
+    /// The largest height the camera can zoom out to, in pixels.
+    pub max_height: f32,
+    /// About how many seconds the camera takes to reach its target, using critically damped
+    /// smoothing. If this is `0.0`, the camera uses the
+    /// [`move_lerp_factor`][Self::move_lerp_factor] instead.
+    pub move_smooth_time: f32,
+    /// The furthest the camera is moved when it shakes with full trauma, in pixels.
+    pub max_shake_offset: Vec2,
+    /// How much trauma the camera loses each second.
+    pub trauma_decay: f32,
+    /// The trauma added when a player is killed.
+    pub kill_trauma: f32,
+    /// How many frames the game pauses for when a player is killed.
+    pub kill_hit_pause_frames: u32,
+    /// How many frames the kill cam plays for after the kill that ends a match, or `0` to never
+    /// play it.
+    pub kill_cam_frames: u32,
+    /// How many frames the kill cam takes to simulate a single frame of the game.
+    pub kill_cam_step_interval: u32,
+    /// The height the camera zooms in to during the kill cam, in pixels.
+    pub kill_cam_height: f32,
+    /// The trauma added when a body lands on the ground falling at least
+    /// [`heavy_landing_speed`][Self::heavy_landing_speed].
+    pub heavy_landing_trauma: f32,
+    /// How fast a body must be falling for its landing to shake the camera, in pixels per frame.
+    pub heavy_landing_speed: f32,
 }
 
 impl Default for CameraMeta {
@@ -79,6 +128,20 @@ impl Default for CameraMeta {
             zoom_out_lerp_factor: 1.0,
             min_camera_size: Vec2::ZERO,
             player_camera_box_size: Vec2::ZERO,
+            deadzone: Vec2::ZERO,
+            look_ahead: 0.0,
+            min_height: 0.0,
+            max_height: f32::MAX,
+            move_smooth_time: 0.0,
+            max_shake_offset: Vec2::ZERO,
+            trauma_decay: 1.0,
+            kill_trauma: 0.0,
+            kill_hit_pause_frames: 0,
+            kill_cam_frames: 0,
+            kill_cam_step_interval: 1,
+            kill_cam_height: 400.0,
+            heavy_landing_trauma: 0.0,
+            heavy_landing_speed: f32::MAX,
         }
     }
 }
@@ -90,6 +153,63 @@ pub struct PhysicsMeta {
     pub terminal_velocity: f32,
     pub friction_lerp: f32,
     pub stop_threshold: f32,
+    /// How many times a player has to press jump to get free of a sticky tile.
+    #[serde(default = "default_sticky_jump_presses")]
+    pub sticky_jump_presses: u32,
+    /// Multiplies the gravity of every body, including the players, whose gravity comes from their
+    /// own metadata.
+    #[serde(default = "default_gravity_scale")]
+    pub gravity_scale: f32,
+    /// The least bounciness every body has when it hits a wall.
+    #[serde(default)]
+    pub min_bounciness: f32,
+    /// How many frames in a row a body may be squeezed between solids before it is crushed.
+    #[serde(default = "default_crush_tolerance_frames")]
+    pub crush_tolerance_frames: u32,
+}
+
+fn default_gravity_scale() -> f32 {
+    1.0
+}
+
+fn default_sticky_jump_presses() -> u32 {
+    2
+}
+
+fn default_crush_tolerance_frames() -> u32 {
+    2
+}
+
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BreakableTilesMeta {
+    /// How much damage a breakable tile can take before it breaks.
+    pub hit_points: u32,
+    /// How much damage a bullet does to the tile it hits.
+    pub bullet_damage: u32,
+    /// How much damage an explosion does to each of the tiles it overlaps.
+    pub explosion_damage: u32,
+    /// How many frames it takes for a broken tile to respawn, or [`None`] if it shouldn't.
+    #[serde(default)]
+    pub respawn_delay: Option<u32>,
+    pub break_sound: Handle<AudioSource>,
+    pub break_sound_volume: f64,
+}
+
+/// The bodies left behind by dead players.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CorpseMeta {
+    /// How many seconds a corpse lasts before it is despawned.
+    pub lifetime: f32,
+    pub bounciness: f32,
+    /// The speed a corpse is pushed away from whatever killed the player.
+    pub kill_impulse: f32,
+    /// How fast a corpse spins when it's knocked away or thrown.
+    pub angular_velocity: f32,
+    pub throw_velocity: f32,
+    pub grab_offset: Vec2,
+    pub fin_anim: Key,
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
@@ -98,4 +218,200 @@ pub struct CoreConfigMeta {
     #[serde(default)]
     #[serde(with = "humantime_serde")]
     pub respawn_invincibility_time: Duration,
+    /// How many frames a player waits after dying before they respawn.
+    #[serde(default)]
+    pub respawn_delay: u32,
+    /// How many frames the countdown at the start of each match lasts, during which the players
+    /// can't move.
+    #[serde(default = "default_start_countdown_frames")]
+    pub start_countdown_frames: u32,
+    /// How the spawn point is chosen when a player spawns.
+    #[serde(default)]
+    pub spawn_point_selection: SpawnPointSelection,
+    /// How many lives each player has in a stock match, or [`None`] for players to respawn
+    /// forever.
+    #[serde(default)]
+    pub stock_lives: Option<u32>,
+    /// How many points a player needs to score to win the match, for example by holding a
+    /// control zone.
+    #[serde(default = "default_points_to_win")]
+    pub points_to_win: f32,
+    /// How long a match lasts before the player with the best score wins, or [`None`] for
+    /// matches without a time limit.
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub time_limit: Option<Duration>,
+    /// What happens when the time limit runs out with several players tied for the win.
+    #[serde(default)]
+    pub sudden_death: SuddenDeathMeta,
+    /// The maximum length of match that will be recorded for replays.
+    #[serde(default = "default_max_replay_length")]
+    #[serde(with = "humantime_serde")]
+    pub max_replay_length: Duration,
+    /// What happens to a network player's fish while they are disconnected.
+    #[serde(default)]
+    pub disconnect_behavior: DisconnectBehavior,
+    /// How long a disconnected network player stays in the match before they are removed.
+    #[serde(default = "default_disconnect_grace_period")]
+    #[serde(with = "humantime_serde")]
+    pub disconnect_grace_period: Duration,
+    /// Multiplies how fast the players walk and move through the air.
+    #[serde(default = "default_player_speed_multiplier")]
+    pub player_speed_multiplier: f32,
+    /// Whether any damage kills a player right away, such as the first moment of a burn.
+    #[serde(default)]
+    pub one_hit_kill: bool,
+    /// Drop random items from the sky throughout the match.
+    #[serde(default)]
+    pub item_rain: Option<ItemRainMeta>,
+    /// The most items that may be in the map at once, or [`None`] for no limit.
+    ///
+    /// When there are too many, the oldest item that isn't being held is despawned. Items that
+    /// would be put back by their map element aren't counted, since there is only ever one of them.
+    #[serde(default)]
+    pub max_world_items: Option<u32>,
+    /// How many frames after a player is hit by another player's attack that an environmental
+    /// death, like falling off of the map, is credited to the attacker. See [`LastHitBy`].
+    #[serde(default = "default_kill_attribution_frames")]
+    pub kill_attribution_frames: u32,
+}
+
+fn default_player_speed_multiplier() -> f32 {
+    1.0
+}
+
+fn default_kill_attribution_frames() -> u32 {
+    180
+}
+
+fn default_start_countdown_frames() -> u32 {
+    180
+}
+
+fn default_points_to_win() -> f32 {
+    100.0
+}
+
+fn default_max_replay_length() -> Duration {
+    Duration::from_secs(30 * 60)
+}
+
+fn default_disconnect_grace_period() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Settings for the sudden death that decides a timed match that ends in a tie.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SuddenDeathMeta {
+    /// Whether tied matches go into sudden death. If not, they end in a draw.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// How fast the damage border closes in from the edges of the map, in pixels per second, or
+    /// [`None`] to play sudden death without a border.
+    #[serde(default)]
+    pub border_speed: Option<f32>,
+    /// How long sudden death lasts before the match is declared a draw.
+    #[serde(default = "default_sudden_death_max_duration")]
+    #[serde(with = "humantime_serde")]
+    pub max_duration: Duration,
+}
+
+impl Default for SuddenDeathMeta {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            border_speed: None,
+            max_duration: default_sudden_death_max_duration(),
+        }
+    }
+}
+
+fn default_sudden_death_max_duration() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// How strong each [`Mutator`] is.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct MutatorsMeta {
+    /// Multiplies the gravity with the [low gravity][Mutator::LowGravity] mutator.
+    pub low_gravity_scale: f32,
+    /// The least bounciness of every body with the [bouncy bodies][Mutator::BouncyBodies]
+    /// mutator.
+    pub bouncy_bodies_bounciness: f32,
+    /// Multiplies the players' movement speed with the [fast fish][Mutator::FastFish] mutator.
+    pub fast_fish_speed_multiplier: f32,
+    /// The items that fall from the sky with the [item rain][Mutator::ItemRain] mutator.
+    pub item_rain: ItemRainMeta,
+}
+
+impl Default for MutatorsMeta {
+    fn default() -> Self {
+        Self {
+            low_gravity_scale: 0.5,
+            bouncy_bodies_bounciness: 0.8,
+            fast_fish_speed_multiplier: 1.5,
+            item_rain: default(),
+        }
+    }
+}
+
+/// Settings for dropping random items from the sky throughout a match.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ItemRainMeta {
+    /// The items that may fall, like the table of an [`ItemSpawnerMeta`].
+    pub items: Vec<ItemSpawnerEntryMeta>,
+    /// How long to wait between dropping items.
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    /// The most items from the sky that can be waiting to be picked up at once.
+    pub max_items: u32,
+}
+
+impl Default for ItemRainMeta {
+    fn default() -> Self {
+        Self {
+            items: default(),
+            interval: Duration::from_secs(5),
+            max_items: 6,
+        }
+    }
 }
+
+/// What to do with the fish of a network player that has been disconnected.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DisconnectBehavior {
+    /// Freeze the fish in place.
+    #[default]
+    Freeze,
+    /// Let an AI take control of the fish.
+    Ai,
+}
+
+impl BonesBevyAssetLoad for DisconnectBehavior {}
+
+/// How a player spawner is chosen when a player spawns.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnPointSelection {
+    /// Take turns using each spawn point, in order.
+    #[default]
+    Sequential,
+    /// Use a random spawn point.
+    Random,
+    /// Use the spawn point that is furthest from the nearest living opponent.
+    FurthestFromEnemies,
+    /// Always spawn each player at the same spawn point, chosen by their player index.
+    FixedPerPlayer,
+}
+
+impl BonesBevyAssetLoad for SpawnPointSelection {}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
 use super::*;
 
@@ -11,11 +11,314 @@ pub struct ElementMeta {
     pub category: String,
     #[serde(default)]
     pub builtin: BuiltinElementKind,
+    /// Makes the item charge up while the use button is held, and only be used when it is let go.
+    #[serde(default)]
+    pub charge: Option<ItemChargeMeta>,
+    /// Gives the item a limited amount of ammo that is used up each time it is used.
+    #[serde(default)]
+    pub ammo: Option<ItemAmmoMeta>,
+    /// How many seconds the item may be left lying around without being held before it despawns.
+    ///
+    /// The time starts over whenever the item is picked up. Items placed in the map are put back
+    /// where they were placed instead.
+    #[serde(default)]
+    pub ground_lifetime: Option<f32>,
+    /// Whether the item takes both fins to hold, which keeps the player from grabbing ledges.
+    #[serde(default)]
+    pub two_handed: bool,
+    /// Overrides for the velocities that the item is thrown with.
+    #[serde(default)]
+    pub throw: ItemThrowMeta,
+    /// Makes the element's explosions push away the bodies around them.
+    #[serde(default)]
+    pub knockback: Option<KnockbackMeta>,
+    /// Simple behaviors added to the element when it is hydrated, so that new decorations and
+    /// hazards can be made without writing a new element.
+    #[serde(default)]
+    pub behaviors: Vec<ElementBehaviorMeta>,
 
     #[serde(default)]
     pub editor: ElementEditorMeta,
 }
 
+/// A behavior that may be added to any element in its [`ElementMeta::behaviors`].
+///
+/// Behaviors are written with the tag of the behavior name, followed by its parameters:
+///
+/// ```yaml
+/// behaviors:
+///   - !spin
+///     degrees_per_second: 90
+///   - !hurt_on_touch
+///     size: [16, 16]
+/// ```
+///
+/// Behaviors with names that aren't known are loaded as [`ElementBehaviorMeta::Unknown`], and
+/// reported when the maps using the element are validated.
+#[derive(BonesBevyAssetLoad, Clone, Debug)]
+pub enum ElementBehaviorMeta {
+    Spin(SpinBehaviorMeta),
+    Oscillate(OscillateBehaviorMeta),
+    HurtOnTouch(HurtOnTouchBehaviorMeta),
+    DespawnAfter(DespawnAfterBehaviorMeta),
+    EmitParticles(EmitParticlesBehaviorMeta),
+    /// A behavior that doesn't exist, with the name it was given.
+    Unknown(String),
+}
+
+impl ElementBehaviorMeta {
+    /// The names of the known behaviors.
+    pub const NAMES: &'static [&'static str] = &[
+        "spin",
+        "oscillate",
+        "hurt_on_touch",
+        "despawn_after",
+        "emit_particles",
+    ];
+}
+
+impl<'de> Deserialize<'de> for ElementBehaviorMeta {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::{EnumAccess, IgnoredAny, VariantAccess};
+
+        struct BehaviorVisitor;
+        impl<'de> serde::de::Visitor<'de> for BehaviorVisitor {
+            type Value = ElementBehaviorMeta;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("an element behavior")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+                let (name, variant) = data.variant::<String>()?;
+                Ok(match name.as_str() {
+                    "spin" => ElementBehaviorMeta::Spin(variant.newtype_variant()?),
+                    "oscillate" => ElementBehaviorMeta::Oscillate(variant.newtype_variant()?),
+                    "hurt_on_touch" => ElementBehaviorMeta::HurtOnTouch(variant.newtype_variant()?),
+                    "despawn_after" => {
+                        ElementBehaviorMeta::DespawnAfter(variant.newtype_variant()?)
+                    }
+                    "emit_particles" => {
+                        ElementBehaviorMeta::EmitParticles(variant.newtype_variant()?)
+                    }
+                    _ => {
+                        // Skip the parameters, so that the rest of the element still loads
+                        variant.newtype_variant::<IgnoredAny>()?;
+                        ElementBehaviorMeta::Unknown(name)
+                    }
+                })
+            }
+        }
+
+        deserializer.deserialize_enum(
+            "ElementBehaviorMeta",
+            ElementBehaviorMeta::NAMES,
+            BehaviorVisitor,
+        )
+    }
+}
+
+/// Rotates the element at a constant speed.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpinBehaviorMeta {
+    /// How fast the element rotates. Positive values rotate counter-clockwise.
+    pub degrees_per_second: f32,
+}
+
+/// Moves the element back and forth around the position it was spawned at.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OscillateBehaviorMeta {
+    /// The direction the element moves in.
+    pub axis: Vec2,
+    /// How far the element moves from its spawn position in each direction, in pixels.
+    pub amplitude: f32,
+    /// How many seconds it takes to go back and forth once.
+    pub period: f32,
+}
+
+/// Kills the players that touch the element.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HurtOnTouchBehaviorMeta {
+    /// The size of the [`DamageRegion`] around the element.
+    pub size: Vec2,
+}
+
+/// Despawns the element a number of frames after it is spawned.
+///
+/// Items spawned by an element are despawned the same way as when they fall off of the map, so
+/// their spawner spawns them again.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DespawnAfterBehaviorMeta {
+    pub frames: u32,
+}
+
+/// Plays a particle effect at the element over and over.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EmitParticlesBehaviorMeta {
+    pub effect: Handle<ParticleEffectMeta>,
+    /// How many frames to wait before playing the effect again.
+    pub interval: u32,
+}
+
+/// Metadata for an item that is charged up by holding down the use button.
+///
+/// The longer the button is held, the faster the item's projectile will go when it is let go.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemChargeMeta {
+    /// How many frames it takes to charge up all the way.
+    pub max_charge_frames: u32,
+    /// The speed of the projectile when the item is used without charging it.
+    pub min_speed: f32,
+    /// The speed of the projectile when the item is fully charged.
+    pub max_speed: f32,
+    /// The fin animation the player plays while charging.
+    #[serde(default)]
+    pub fin_anim: Option<Key>,
+    /// A sound that is played over and over while charging.
+    #[serde(default)]
+    pub sound: Option<Handle<AudioSource>>,
+    #[serde(default)]
+    pub sound_volume: f64,
+    /// How many frames to wait before playing the charging sound again.
+    #[serde(default = "default_charge_sound_interval")]
+    pub sound_interval_frames: u32,
+    /// The color of the charge bar shown above the item.
+    #[serde(default = "default_charge_color")]
+    pub color: ColorMeta,
+}
+
+/// Velocities that an item is thrown with, replacing the ones it would be thrown with otherwise.
+///
+/// The velocities are for a player facing right, and are flipped when the player faces left. Each
+/// one is used when the player is holding the directions noted on it while throwing.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ItemThrowMeta {
+    /// No direction.
+    #[serde(default)]
+    pub normal: Option<Vec2>,
+    /// Forward.
+    #[serde(default)]
+    pub fast: Option<Vec2>,
+    /// Up, which throws the item up at an angle.
+    #[serde(default)]
+    pub up: Option<Vec2>,
+    /// Down, which tosses the item a short way in front of the player.
+    #[serde(default)]
+    pub drop: Option<Vec2>,
+    /// Up and forward.
+    #[serde(default)]
+    pub lob: Option<Vec2>,
+    /// Down and forward.
+    #[serde(default)]
+    pub roll: Option<Vec2>,
+}
+
+impl ItemThrowMeta {
+    /// Get the override for a kind of throw, if there is one.
+    pub fn get(&self, kind: ThrowKind) -> Option<Vec2> {
+        match kind {
+            ThrowKind::Normal => self.normal,
+            ThrowKind::Fast => self.fast,
+            ThrowKind::Up => self.up,
+            ThrowKind::Drop => self.drop,
+            ThrowKind::Lob => self.lob,
+            ThrowKind::Roll => self.roll,
+        }
+    }
+}
+
+/// Metadata for an item that has a limited amount of ammo.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemAmmoMeta {
+    /// How much ammo the item has when it is full.
+    pub max_ammo: u32,
+    /// How much ammo is used up each time the item is used.
+    #[serde(default = "default_shots_per_use")]
+    pub shots_per_use: u32,
+    /// What happens when the item runs out of ammo.
+    #[serde(default)]
+    pub when_empty: AmmoEmptyBehavior,
+    /// How many frames it takes to reload the item.
+    #[serde(default)]
+    pub reload_frames: Option<u32>,
+    /// The fin animation the player plays while reloading.
+    #[serde(default)]
+    pub reload_fin_anim: Option<Key>,
+    #[serde(default)]
+    pub reload_sound: Option<Handle<AudioSource>>,
+    #[serde(default)]
+    pub reload_sound_volume: f64,
+    /// The color of the ammo pips shown above the item.
+    #[serde(default = "default_ammo_color")]
+    pub color: ColorMeta,
+}
+
+/// Metadata for the knockback of an element's explosions.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct KnockbackMeta {
+    /// How far from the explosion bodies are pushed.
+    pub radius: f32,
+    /// The speed given to bodies at the center of the explosion.
+    pub strength: f32,
+    /// How quickly the knockback gets weaker further from the explosion. `0.0` pushes everything
+    /// in the radius equally, and `1.0` makes it fall off linearly.
+    #[serde(default = "default_knockback_falloff")]
+    pub falloff: f32,
+    /// Multiplies the knockback of players that are standing on the ground.
+    #[serde(default = "default_grounded_player_multiplier")]
+    pub grounded_player_multiplier: f32,
+    /// Whether the player that set off the explosion is left out of the knockback. When this is
+    /// `false`, players can use the explosion to launch themselves.
+    #[serde(default)]
+    pub exempt_owner: bool,
+}
+
+fn default_knockback_falloff() -> f32 {
+    1.0
+}
+
+fn default_grounded_player_multiplier() -> f32 {
+    1.0
+}
+
+/// What happens to an item with [`ItemAmmoMeta`] when it runs out of ammo.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AmmoEmptyBehavior {
+    /// Reload the item after its [`reload_frames`][ItemAmmoMeta::reload_frames].
+    #[default]
+    Reload,
+    /// Leave the item empty, so that it can only be thrown.
+    Inert,
+}
+
+impl BonesBevyAssetLoad for AmmoEmptyBehavior {}
+
+fn default_shots_per_use() -> u32 {
+    1
+}
+
+fn default_ammo_color() -> ColorMeta {
+    ColorMeta(Color::WHITE)
+}
+
+fn default_charge_sound_interval() -> u32 {
+    30
+}
+
+fn default_charge_color() -> ColorMeta {
+    ColorMeta(Color::WHITE)
+}
+
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[serde(default)]
@@ -26,6 +329,8 @@ pub struct ElementEditorMeta {
     pub grab_offset: Vec2,
     /// Show the element name above the bounding rect in the editor.
     pub show_name: bool,
+    /// The properties that may be overridden for each instance of the element placed in a map.
+    pub properties: Vec<ElementPropertyMeta>,
 }
 
 impl Default for ElementEditorMeta {
@@ -34,10 +339,89 @@ impl Default for ElementEditorMeta {
             grab_size: Vec2::splat(45.0),
             grab_offset: Vec2::ZERO,
             show_name: true,
+            properties: default(),
+        }
+    }
+}
+
+impl ElementEditorMeta {
+    /// Get the value of a property for an element instance, falling back to the property's default
+    /// if the instance doesn't override it.
+    ///
+    /// Returns [`None`] if the element doesn't have the property.
+    pub fn property(
+        &self,
+        properties: &ElementProperties,
+        key: &str,
+    ) -> Option<ElementPropertyValue> {
+        let meta = self.properties.iter().find(|meta| meta.key == key)?;
+        Some(properties.get(key).copied().unwrap_or(meta.default))
+    }
+}
+
+/// A property of an element that can be overridden for each instance of it in the map editor.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ElementPropertyMeta {
+    /// The key that the property is stored under in the map.
+    pub key: String,
+    /// The name of the property shown in the editor.
+    pub name: String,
+    /// The value used for instances that don't override the property. This also sets the type of
+    /// the property.
+    #[asset(deserialize_only)]
+    pub default: ElementPropertyValue,
+}
+
+/// The value of an [`ElementPropertyMeta`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ElementPropertyValue {
+    Bool(bool),
+    Number(f32),
+}
+
+impl ElementPropertyValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            Self::Number(_) => None,
+        }
+    }
+    pub fn as_number(&self) -> Option<f32> {
+        match self {
+            Self::Number(value) => Some(*value),
+            Self::Bool(_) => None,
         }
     }
 }
 
+/// Component containing the property values that have been overridden for a placed element, by
+/// property key.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, TypeUlid, Deref, DerefMut)]
+#[ulid = "01H0NQQE5R1ZCQRAJCKFE4P5T0"]
+#[serde(transparent)]
+pub struct ElementProperties(pub BTreeMap<String, ElementPropertyValue>);
+
+impl ElementProperties {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The path points of an element instance that follows a path, like a moving platform, relative to
+/// the element's position.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, TypeUlid, Deref, DerefMut)]
+#[ulid = "01H14AT9CH9G2TH86M4BSD3MFC"]
+#[serde(transparent)]
+pub struct ElementWaypoints(pub Vec<Vec2>);
+
+impl ElementWaypoints {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
 #[derive(BonesBevyAsset, Deserialize, Clone, Debug, Default, TypeUlid)]
 #[ulid = "01GR1W2B3S7DM5QEY07RSJH2G0"]
 #[asset_id = "bullet"]
@@ -54,6 +438,173 @@ pub struct BulletMeta {
     pub explosion_frames: usize,
     pub explosion_atlas: Handle<Atlas>,
     pub explosion_sound: Handle<AudioSource>,
+    /// The camera trauma added when the bullet explodes.
+    #[serde(default)]
+    pub explosion_trauma: f32,
+    /// The particle effect played when the bullet explodes.
+    #[serde(default)]
+    pub explosion_particles: Option<Handle<ParticleEffectMeta>>,
+    /// How many times the bullet ricochets off of solid tiles before it explodes.
+    #[serde(default)]
+    pub bounces: u32,
+    /// The fraction of its speed that the bullet loses each time it ricochets.
+    #[serde(default)]
+    pub bounce_speed_loss: f32,
+    /// How many players the bullet passes through before it explodes.
+    #[serde(default)]
+    pub penetration: u32,
+}
+
+/// Metadata for a king-of-the-hill control zone, that players score points for by standing in it
+/// alone.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ControlZoneMeta {
+    /// The size of the zone in pixels.
+    pub size: Vec2,
+    /// How many points are scored each second by the player holding the zone.
+    pub points_per_second: f32,
+    /// The color the zone and its capture progress bar are drawn with.
+    pub capture_color: ColorMeta,
+}
+
+/// Metadata for an element that spawns items picked at random from a weighted table.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemSpawnerMeta {
+    /// The items that may be spawned. Each instance of the spawner can override the weights of
+    /// the items in the editor.
+    pub items: Vec<ItemSpawnerEntryMeta>,
+    /// How long to wait after the match starts before spawning the first item.
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub initial_delay: Duration,
+    /// How long to wait after an item is picked up or destroyed before spawning another one.
+    #[serde(with = "humantime_serde")]
+    pub cooldown: Duration,
+    /// The most items from the spawner that can be waiting to be picked up at once.
+    #[serde(default = "default_max_concurrent_items")]
+    pub max_concurrent: u32,
+    /// An animation played where the item is about to appear.
+    #[serde(default)]
+    pub telegraph: Option<ItemSpawnerTelegraphMeta>,
+}
+
+/// An item in the table of an [`ItemSpawnerMeta`].
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemSpawnerEntryMeta {
+    pub element: Handle<ElementMeta>,
+    /// How likely the item is to be picked, relative to the other items in the table.
+    pub weight: f32,
+}
+
+/// The animation played by an item spawner before an item appears.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemSpawnerTelegraphMeta {
+    pub atlas: Handle<Atlas>,
+    pub frames: usize,
+    pub fps: f32,
+}
+
+fn default_max_concurrent_items() -> u32 {
+    1
+}
+
+/// Metadata for a crate that is broken open by weapons, bullets, and explosions, dropping an item
+/// picked at random from its loot table.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BreakableCrateMeta {
+    pub atlas: Handle<Atlas>,
+    pub body_size: Vec2,
+    /// How many hits the crate takes before it breaks.
+    pub hit_points: u32,
+    /// The fraction of a player's walking speed that the crate is pushed with when the player
+    /// walks into it.
+    pub push_factor: f32,
+    /// The items that may be dropped when the crate breaks. Each instance of the crate can
+    /// override the weights of the items in the editor.
+    pub loot: Vec<ItemSpawnerEntryMeta>,
+    /// The particle effect played when the crate breaks.
+    #[serde(default)]
+    pub break_particles: Option<Handle<ParticleEffectMeta>>,
+    pub break_sound: Handle<AudioSource>,
+    pub break_sound_volume: f64,
+}
+
+/// Metadata for a region that is triggered once a player performs an action inside of it.
+///
+/// Trigger regions are the building blocks of the tutorial map: each one waits for the player to do
+/// one thing, like jumping or throwing an item, and the map is complete once all of them have been
+/// triggered.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TriggerRegionMeta {
+    /// The size of the region in pixels.
+    pub size: Vec2,
+    /// The action that the player has to perform in the region to trigger it.
+    pub action: TriggerAction,
+    /// The localization key of the instruction shown above the region until it is triggered.
+    ///
+    /// The inputs bound to the action are passed to the message as the `input` argument.
+    pub label: String,
+    pub color: ColorMeta,
+}
+
+/// The action that a player has to perform to set off a [`TriggerRegionMeta`].
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerAction {
+    /// Walk in either direction.
+    Move,
+    Jump,
+    /// Pick up an item.
+    Grab,
+    /// Throw the item being held.
+    Throw,
+    Crouch,
+}
+
+impl BonesBevyAssetLoad for TriggerAction {}
+
+/// Metadata for a platform that moves along the waypoints of the element.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MovingPlatformMeta {
+    /// The size of the platform in pixels.
+    pub size: Vec2,
+    /// Whether the platform is solid or can be jumped through from below.
+    pub collision: TileCollisionKind,
+    pub color: ColorMeta,
+}
+
+/// Metadata for a volume of water that bodies float and swim in.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WaterVolumeMeta {
+    /// The size of the volume in pixels.
+    pub size: Vec2,
+    pub color: ColorMeta,
+    /// How buoyant the water is compared to the bodies in it. Bodies float to the surface when this
+    /// is greater than `1.0`, and sink when it is less.
+    pub density: f32,
+    /// The fraction of a fully submerged body's velocity that it loses each frame.
+    pub drag: f32,
+    /// The terminal velocity in the water, as a multiple of the normal terminal velocity.
+    pub terminal_velocity_multiplier: f32,
+    /// The speed of bullets in the water, as a multiple of their normal speed.
+    pub bullet_speed_multiplier: f32,
+    /// How far bullets can travel in the water before they stop.
+    pub bullet_range: f32,
+
+    pub splash_sound: Handle<AudioSource>,
+    pub splash_volume: f64,
+    pub splash_atlas: Handle<Atlas>,
+    pub splash_frames: usize,
+    pub splash_fps: f32,
+    pub splash_lifetime: f32,
 }
 
 /// The kind of built-in
@@ -88,6 +639,8 @@ pub enum BuiltinElementKind {
         atlas: Handle<Atlas>,
         explosion_atlas: Handle<Atlas>,
         #[serde(default)]
+        explosion_particles: Option<Handle<ParticleEffectMeta>>,
+        #[serde(default)]
         bounciness: f32,
         #[serde(default)]
         angular_velocity: f32,
@@ -213,6 +766,8 @@ pub enum BuiltinElementKind {
         damage_region_size: Vec2,
         damage_region_lifetime: f32,
         explosion_atlas: Handle<Atlas>,
+        #[serde(default)]
+        explosion_particles: Option<Handle<ParticleEffectMeta>>,
         explosion_lifetime: f32,
         explosion_frames: usize,
         explosion_fps: f32,
@@ -264,6 +819,8 @@ pub enum BuiltinElementKind {
         atlas: Handle<Atlas>,
         explosion_atlas: Handle<Atlas>,
         #[serde(default)]
+        explosion_particles: Option<Handle<ParticleEffectMeta>>,
+        #[serde(default)]
         bounciness: f32,
         #[serde(default)]
         angular_velocity: f32,
@@ -286,6 +843,9 @@ pub enum BuiltinElementKind {
         #[serde(with = "humantime_serde")]
         cooldown: Duration,
         bullet_meta: Handle<BulletMeta>,
+        /// Makes the bullets home in on other players
+        #[serde(default)]
+        homing: Option<BulletHoming>,
 
         shoot_fps: f32,
         shoot_lifetime: f32,
@@ -295,6 +855,9 @@ pub enum BuiltinElementKind {
         shoot_atlas: Handle<Atlas>,
         shoot_sound: Handle<AudioSource>,
         empty_shoot_sound: Handle<AudioSource>,
+        /// The particle effect played at the end of the barrel when the musket is fired.
+        #[serde(default)]
+        shoot_particles: Option<Handle<ParticleEffectMeta>>,
     },
     SlipperySeaweed {
         atlas: Handle<Atlas>,
@@ -309,4 +872,18 @@ pub enum BuiltinElementKind {
         player_slide: f32,
         body_friction: f32,
     },
+    /// A king-of-the-hill control zone
+    ControlZone(ControlZoneMeta),
+    /// A platform that carries riders along a path
+    MovingPlatform(MovingPlatformMeta),
+    /// A volume of water
+    WaterVolume(WaterVolumeMeta),
+    /// A region that pushes bodies, like wind or a conveyor belt
+    ForceRegion(ForceRegion),
+    /// Spawns items picked from a weighted table
+    ItemSpawner(ItemSpawnerMeta),
+    /// A crate that drops an item picked from a weighted table when it is broken
+    BreakableCrate(BreakableCrateMeta),
+    /// A region that is triggered by a player performing an action inside of it
+    TriggerRegion(TriggerRegionMeta),
 }
@@ -6,6 +6,12 @@ use super::*;
 #[serde(deny_unknown_fields)]
 pub struct MapMeta {
     pub name: String,
+    /// The person who made the map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// How many players the map is meant to be played with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_players: Option<MapPlayerCountMeta>,
     /// The parallax background layers
     #[serde(default)]
     pub background: BackgroundMeta,
@@ -17,12 +23,161 @@ pub struct MapMeta {
     pub tile_size: Vec2,
     /// The layers of the map
     pub layers: Vec<MapLayerMeta>,
+    /// Changes to the game's respawn rules for this map.
+    #[serde(default, skip_serializing_if = "MapRespawnMeta::is_default")]
+    pub respawn: MapRespawnMeta,
+    /// The area the camera is kept inside of, in pixels. If not set, the camera may show outside
+    /// of the map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_bounds: Option<MapCameraBoundsMeta>,
+    /// The music played during matches on the map, instead of the game's fight music.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub music: Option<MapMusicMeta>,
+    /// What happens to things that leave the map through each of its edges.
+    #[serde(default, skip_serializing_if = "MapBoundaryMeta::is_default")]
+    pub boundary: MapBoundaryMeta,
+}
+
+/// The range of player counts that a map is meant for.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct MapPlayerCountMeta {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// The music played on a map.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MapMusicMeta {
+    /// The tracks to play, in a shuffled order. The next track starts when one ends.
+    pub tracks: Vec<Handle<AudioSource>>,
+    /// Whether the first track is looped seamlessly, instead of moving on to the next one.
+    #[serde(default)]
+    pub looped: bool,
+}
+
+/// A rectangle that a map's camera is kept inside of.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MapCameraBoundsMeta {
+    /// The bottom left corner of the bounds.
+    pub min: Vec2,
+    /// The top right corner of the bounds.
+    pub max: Vec2,
+}
+
+/// The boundary around a map, and what happens to things that cross each of its edges.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MapBoundaryMeta {
+    /// How far past a [`MapEdgeBehavior::Kill`] edge of the map, in pixels, players are killed and
+    /// items and bullets are despawned.
+    #[serde(default = "default_kill_margin")]
+    pub kill_margin: f32,
+    #[serde(default)]
+    pub left: MapEdgeBehavior,
+    #[serde(default)]
+    pub right: MapEdgeBehavior,
+    #[serde(default)]
+    pub bottom: MapEdgeBehavior,
+    #[serde(default = "default_top_edge")]
+    pub top: MapEdgeBehavior,
+}
+
+fn default_kill_margin() -> f32 {
+    500.0
+}
+
+fn default_top_edge() -> MapEdgeBehavior {
+    MapEdgeBehavior::Open
+}
+
+impl Default for MapBoundaryMeta {
+    fn default() -> Self {
+        Self {
+            kill_margin: default_kill_margin(),
+            left: default(),
+            right: default(),
+            bottom: default(),
+            top: default_top_edge(),
+        }
+    }
+}
+
+impl MapBoundaryMeta {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Get the behavior of the given edge of the map.
+    pub fn edge(&self, edge: MapEdge) -> MapEdgeBehavior {
+        match edge {
+            MapEdge::Left => self.left,
+            MapEdge::Right => self.right,
+            MapEdge::Bottom => self.bottom,
+            MapEdge::Top => self.top,
+        }
+    }
+}
+
+/// One of the four edges of a map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapEdge {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl MapEdge {
+    pub const ALL: [MapEdge; 4] = [Self::Left, Self::Right, Self::Bottom, Self::Top];
+}
+
+/// What happens to things that leave a map through one of its edges.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MapEdgeBehavior {
+    /// Players that go past the kill margin die, and items and bullets are despawned.
+    #[default]
+    Kill,
+    /// Bodies that leave the map come back in through the opposite edge.
+    Wrap,
+    /// An invisible solid wall keeps bodies inside of the map.
+    Wall,
+    /// Nothing happens to things past the edge.
+    Open,
+}
+
+impl BonesBevyAssetLoad for MapEdgeBehavior {}
+
+/// Respawn rules for a map, overriding the ones in the [`CoreConfigMeta`] when set.
+#[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct MapRespawnMeta {
+    /// How many frames a player waits after dying before they respawn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<u32>,
+    /// How long a player is invincible for after they respawn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(with = "humantime_serde::option")]
+    pub invincibility_time: Option<Duration>,
+    /// How the spawn point is chosen when a player spawns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_point_selection: Option<SpawnPointSelection>,
+}
+
+impl MapRespawnMeta {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 #[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(deny_unknown_fields)]
 pub struct BackgroundMeta {
     pub speed: Vec2,
+    /// The parallax layers, rendered from back to front.
     pub layers: Vec<ParallaxLayerMeta>,
 }
 
@@ -32,10 +187,18 @@ pub struct BackgroundMeta {
 pub struct ParallaxLayerMeta {
     pub image: Handle<Image>,
     pub size: Vec2,
+    /// How far back the layer is, which multiplies the speed that it scrolls with the camera.
     pub depth: f32,
     pub scale: f32,
     #[serde(default)]
     pub offset: Vec2,
+    /// Whether the image is repeated horizontally.
+    #[serde(default = "default_true")]
+    pub tiling: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug)]
@@ -55,6 +218,14 @@ pub struct MapLayerMeta {
 pub struct ElementSpawn {
     pub pos: Vec2,
     pub element: Handle<ElementMeta>,
+    /// The element properties that are overridden for this instance of the element.
+    #[serde(default, skip_serializing_if = "ElementProperties::is_empty")]
+    #[asset(deserialize_only)]
+    pub properties: ElementProperties,
+    /// The path that this instance of the element follows, if it moves along one.
+    #[serde(default, skip_serializing_if = "ElementWaypoints::is_empty")]
+    #[asset(deserialize_only)]
+    pub waypoints: ElementWaypoints,
 }
 
 #[derive(BonesBevyAssetLoad, Serialize, Deserialize, Clone, Debug)]
@@ -64,16 +235,375 @@ pub struct MapTileMeta {
     pub idx: u32,
     #[serde(default)]
     pub collision: TileCollisionKind,
+    /// How the tile changes the movement of bodies standing on it.
+    #[serde(default, skip_serializing_if = "TileSurface::is_default")]
+    pub surface: TileSurface,
 }
 
 impl MapMeta {
-    /// Checks if the given position is out of the bounds of the map.
+    /// Checks if the given position is past the kill margin of one of the map's
+    /// [`MapEdgeBehavior::Kill`] edges.
     pub fn is_out_of_bounds(&self, pos: &Vec3) -> bool {
-        const KILL_ZONE_BORDER: f32 = 500.0;
-        let map_width = self.grid_size.x as f32 * self.tile_size.x;
-        let left_kill_zone = -KILL_ZONE_BORDER;
-        let right_kill_zone = map_width + KILL_ZONE_BORDER;
-        let bottom_kill_zone = -KILL_ZONE_BORDER;
-        pos.x < left_kill_zone || pos.x > right_kill_zone || pos.y < bottom_kill_zone
+        self.edges_past(pos.truncate(), self.boundary.kill_margin)
+            .any(|edge| self.boundary.edge(edge) == MapEdgeBehavior::Kill)
+    }
+
+    /// Get the edges of the map that the given position is more than `margin` pixels past.
+    pub fn edges_past(&self, pos: Vec2, margin: f32) -> impl Iterator<Item = MapEdge> {
+        let map_size = self.grid_size.as_vec2() * self.tile_size;
+        MapEdge::ALL.into_iter().filter(move |edge| match edge {
+            MapEdge::Left => pos.x < -margin,
+            MapEdge::Right => pos.x > map_size.x + margin,
+            MapEdge::Bottom => pos.y < -margin,
+            MapEdge::Top => pos.y > map_size.y + margin,
+        })
+    }
+}
+
+/// A problem with a map's metadata, found by [`MapMeta::validate`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapValidationError {
+    /// The map has no tiles to put anything on.
+    EmptyGrid,
+    /// Two map layers have the same name.
+    DuplicateLayerName { layer: usize, name: String },
+    /// A tile is positioned outside of the map grid.
+    TileOutOfBounds { layer: usize, pos: UVec2 },
+    /// A tile uses an index that is past the end of its layer's tilemap.
+    TileIndexOutOfRange {
+        layer: usize,
+        pos: UVec2,
+        idx: u32,
+        tile_count: usize,
+    },
+    /// A layer has tiles, but no tilemap to draw them with.
+    MissingTilemap { layer: usize },
+    /// An element refers to an element asset that doesn't exist.
+    UnknownElement {
+        layer: usize,
+        pos: Vec2,
+        path: String,
+    },
+    /// An element has a behavior in its [`ElementMeta::behaviors`] that doesn't exist.
+    UnknownElementBehavior {
+        layer: usize,
+        pos: Vec2,
+        behavior: String,
+    },
+    /// A player spawner is positioned outside of the map grid.
+    SpawnPointOutOfBounds { layer: usize, pos: Vec2 },
+    /// There is nowhere for the players to spawn.
+    NoSpawnPoints,
+}
+
+impl MapValidationError {
+    /// Whether the problem stops the map from being played. Other problems are only warnings.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::EmptyGrid
+            | Self::TileOutOfBounds { .. }
+            | Self::TileIndexOutOfRange { .. }
+            | Self::UnknownElement { .. }
+            | Self::SpawnPointOutOfBounds { .. }
+            | Self::NoSpawnPoints => true,
+            Self::DuplicateLayerName { .. }
+            | Self::MissingTilemap { .. }
+            | Self::UnknownElementBehavior { .. } => false,
+        }
+    }
+}
+
+impl MapMeta {
+    /// Check the map for problems that would stop it from loading or playing properly.
+    ///
+    /// `get_element` looks up the metadata for an element handle, and `tilemap_tile_count` looks up
+    /// the number of tiles in a tilemap. Either should return [`None`] if the asset doesn't exist.
+    pub fn validate<'a>(
+        &self,
+        get_element: impl Fn(&Handle<ElementMeta>) -> Option<&'a ElementMeta>,
+        tilemap_tile_count: impl Fn(&Handle<Atlas>) -> Option<usize>,
+    ) -> Vec<MapValidationError> {
+        let mut errors = Vec::new();
+        if self.grid_size.x == 0 || self.grid_size.y == 0 {
+            errors.push(MapValidationError::EmptyGrid);
+        }
+        let map_size = self.grid_size.as_vec2() * self.tile_size;
+        let in_bounds = |pos: Vec2| pos.cmpge(Vec2::ZERO).all() && pos.cmplt(map_size).all();
+
+        let mut spawn_points = 0;
+        for (layer_idx, layer) in self.layers.iter().enumerate() {
+            if self.layers[..layer_idx].iter().any(|x| x.id == layer.id) {
+                errors.push(MapValidationError::DuplicateLayerName {
+                    layer: layer_idx,
+                    name: layer.id.clone(),
+                });
+            }
+
+            let tile_count = layer.tilemap.as_ref().and_then(&tilemap_tile_count);
+            if layer.tilemap.is_none() && !layer.tiles.is_empty() {
+                errors.push(MapValidationError::MissingTilemap { layer: layer_idx });
+            }
+            for tile in &layer.tiles {
+                if tile.pos.cmpge(self.grid_size).any() {
+                    errors.push(MapValidationError::TileOutOfBounds {
+                        layer: layer_idx,
+                        pos: tile.pos,
+                    });
+                }
+                if let Some(tile_count) = tile_count {
+                    if tile.idx as usize >= tile_count {
+                        errors.push(MapValidationError::TileIndexOutOfRange {
+                            layer: layer_idx,
+                            pos: tile.pos,
+                            idx: tile.idx,
+                            tile_count,
+                        });
+                    }
+                }
+            }
+
+            for element in &layer.elements {
+                let Some(element_meta) = get_element(&element.element) else {
+                    errors.push(MapValidationError::UnknownElement {
+                        layer: layer_idx,
+                        pos: element.pos,
+                        path: element.element.path.path.to_string_lossy().into_owned(),
+                    });
+                    continue;
+                };
+                for behavior in &element_meta.behaviors {
+                    if let ElementBehaviorMeta::Unknown(name) = behavior {
+                        errors.push(MapValidationError::UnknownElementBehavior {
+                            layer: layer_idx,
+                            pos: element.pos,
+                            behavior: name.clone(),
+                        });
+                    }
+                }
+                if let BuiltinElementKind::PlayerSpawner = element_meta.builtin {
+                    if in_bounds(element.pos) {
+                        spawn_points += 1;
+                    } else {
+                        errors.push(MapValidationError::SpawnPointOutOfBounds {
+                            layer: layer_idx,
+                            pos: element.pos,
+                        });
+                    }
+                }
+            }
+        }
+
+        if spawn_points == 0 {
+            errors.push(MapValidationError::NoSpawnPoints);
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn handle<T: TypeUlid>(path: &str) -> Handle<T> {
+        UntypedHandle {
+            path: AssetPath::new(path, None),
+        }
+        .typed()
+    }
+
+    fn spawner() -> ElementMeta {
+        ElementMeta {
+            builtin: BuiltinElementKind::PlayerSpawner,
+            ..default()
+        }
+    }
+
+    /// A small valid map with a tilemap of 4 tiles, and a spawner element at `/spawner`.
+    fn test_map() -> MapMeta {
+        MapMeta {
+            name: "Test".into(),
+            grid_size: UVec2::new(10, 10),
+            tile_size: Vec2::splat(16.0),
+            layers: vec![MapLayerMeta {
+                id: "ground".into(),
+                tilemap: Some(handle("/tilemap")),
+                tiles: vec![MapTileMeta {
+                    pos: UVec2::new(2, 3),
+                    idx: 1,
+                    collision: default(),
+                    surface: default(),
+                }],
+                elements: vec![ElementSpawn {
+                    pos: Vec2::new(40.0, 80.0),
+                    element: handle("/spawner"),
+                    ..default()
+                }],
+            }],
+            ..default()
+        }
+    }
+
+    fn validate(map: &MapMeta) -> Vec<MapValidationError> {
+        let spawner = spawner();
+        let spawner_path = AssetPath::new("/spawner", None);
+        map.validate(
+            |element| (element.path == spawner_path).then_some(&spawner),
+            |_| Some(4),
+        )
+    }
+
+    #[test]
+    fn valid_map_has_no_errors() {
+        assert_eq!(validate(&test_map()), vec![]);
+    }
+
+    #[test]
+    fn corrupt_tiles() {
+        let mut map = test_map();
+        map.layers[0].tiles.push(MapTileMeta {
+            pos: UVec2::new(10, 0),
+            idx: 0,
+            collision: default(),
+            surface: default(),
+        });
+        map.layers[0].tiles.push(MapTileMeta {
+            pos: UVec2::new(0, 0),
+            idx: 4,
+            collision: default(),
+            surface: default(),
+        });
+
+        let errors = validate(&map);
+        assert_eq!(
+            errors,
+            vec![
+                MapValidationError::TileOutOfBounds {
+                    layer: 0,
+                    pos: UVec2::new(10, 0)
+                },
+                MapValidationError::TileIndexOutOfRange {
+                    layer: 0,
+                    pos: UVec2::new(0, 0),
+                    idx: 4,
+                    tile_count: 4
+                },
+            ]
+        );
+        assert!(errors.iter().all(|x| x.is_fatal()));
+    }
+
+    #[test]
+    fn unknown_element_and_missing_spawn_points() {
+        let mut map = test_map();
+        map.layers[0].elements[0].element = handle("/missing");
+
+        assert_eq!(
+            validate(&map),
+            vec![
+                MapValidationError::UnknownElement {
+                    layer: 0,
+                    pos: Vec2::new(40.0, 80.0),
+                    path: "/missing".into(),
+                },
+                MapValidationError::NoSpawnPoints,
+            ]
+        );
+
+        let mut map = test_map();
+        map.layers[0].elements[0].pos = Vec2::new(-5.0, 80.0);
+        assert_eq!(
+            validate(&map),
+            vec![
+                MapValidationError::SpawnPointOutOfBounds {
+                    layer: 0,
+                    pos: Vec2::new(-5.0, 80.0),
+                },
+                MapValidationError::NoSpawnPoints,
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_element_behaviors_are_warnings() {
+        let mut map = test_map();
+        map.layers[0].elements.push(ElementSpawn {
+            pos: Vec2::new(8.0, 8.0),
+            element: handle("/decoration"),
+            ..default()
+        });
+        let spawner = spawner();
+        let decoration = ElementMeta {
+            behaviors: vec![
+                ElementBehaviorMeta::Spin(SpinBehaviorMeta {
+                    degrees_per_second: 90.0,
+                }),
+                ElementBehaviorMeta::Unknown("teleport".into()),
+            ],
+            ..default()
+        };
+
+        let spawner_path = AssetPath::new("/spawner", None);
+        let decoration_path = AssetPath::new("/decoration", None);
+
+        let errors = map.validate(
+            |element| {
+                if element.path == spawner_path {
+                    Some(&spawner)
+                } else if element.path == decoration_path {
+                    Some(&decoration)
+                } else {
+                    None
+                }
+            },
+            |_| Some(4),
+        );
+        assert_eq!(
+            errors,
+            vec![MapValidationError::UnknownElementBehavior {
+                layer: 0,
+                pos: Vec2::new(8.0, 8.0),
+                behavior: "teleport".into(),
+            }]
+        );
+        assert!(!errors[0].is_fatal());
+    }
+
+    #[test]
+    fn duplicate_layer_names_are_warnings() {
+        let mut map = test_map();
+        map.layers.push(MapLayerMeta {
+            id: "ground".into(),
+            tilemap: None,
+            tiles: vec![],
+            elements: vec![],
+        });
+
+        let errors = validate(&map);
+        assert_eq!(
+            errors,
+            vec![MapValidationError::DuplicateLayerName {
+                layer: 1,
+                name: "ground".into()
+            }]
+        );
+        assert!(!errors[0].is_fatal());
+    }
+
+    #[test]
+    fn only_kill_edges_are_out_of_bounds() {
+        // The map is 160 pixels square, and the top is open by default
+        let mut map = test_map();
+        assert!(!map.is_out_of_bounds(&Vec3::new(-499.0, 80.0, 0.0)));
+        assert!(map.is_out_of_bounds(&Vec3::new(-501.0, 80.0, 0.0)));
+        assert!(map.is_out_of_bounds(&Vec3::new(80.0, -501.0, 0.0)));
+        assert!(!map.is_out_of_bounds(&Vec3::new(80.0, 1000.0, 0.0)));
+
+        map.boundary.kill_margin = 10.0;
+        map.boundary.left = MapEdgeBehavior::Wrap;
+        map.boundary.top = MapEdgeBehavior::Kill;
+        assert!(!map.is_out_of_bounds(&Vec3::new(-100.0, 80.0, 0.0)));
+        assert!(map.is_out_of_bounds(&Vec3::new(171.0, 80.0, 0.0)));
+        assert!(map.is_out_of_bounds(&Vec3::new(80.0, 171.0, 0.0)));
     }
 }
@@ -0,0 +1,91 @@
+use super::*;
+
+/// Metadata for a particle effect, loaded from a `.particles.yaml` file.
+///
+/// Speeds and gravity are in pixels per frame, and angles are in degrees, counter-clockwise from
+/// the right.
+#[derive(BonesBevyAsset, Deserialize, Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H50B6ZQ3N8RKW2XDJ7TV4CME"]
+#[asset_id = "particles"]
+#[serde(deny_unknown_fields)]
+pub struct ParticleEffectMeta {
+    pub atlas: Handle<Atlas>,
+    /// The first atlas frame that particles animate through over their lifetime.
+    #[serde(default)]
+    pub first_frame: usize,
+    /// The last atlas frame that particles animate through over their lifetime.
+    #[serde(default)]
+    pub last_frame: usize,
+    /// How long each particle lives, in seconds.
+    pub lifetime: f32,
+    /// How many particles are spawned as soon as the effect starts.
+    #[serde(default)]
+    pub burst: usize,
+    /// How many particles are spawned per second while the effect is running.
+    #[serde(default)]
+    pub rate: f32,
+    /// How long the effect keeps spawning particles at its [`rate`][Self::rate], in seconds.
+    #[serde(default)]
+    pub duration: f32,
+    /// The direction that particles are sent in.
+    #[serde(default)]
+    pub direction: f32,
+    /// The width of the cone around the [`direction`][Self::direction] that particles are sent
+    /// in.
+    #[serde(default)]
+    pub spread: f32,
+    #[serde(default)]
+    pub min_speed: f32,
+    #[serde(default)]
+    pub max_speed: f32,
+    #[serde(default)]
+    pub gravity: f32,
+    /// The most particles from the same effect that may be alive at once.
+    pub max_particles: usize,
+}
+
+impl ParticleEffectMeta {
+    /// How long, in seconds, until every particle of the effect is gone.
+    pub fn total_duration(&self) -> f32 {
+        self.duration + self.lifetime
+    }
+
+    /// Get the number of particles to spawn this frame, for an effect that has been running for
+    /// `frame` frames.
+    ///
+    /// `remainder` carries the fraction of a particle left over from the last frame, so that rates
+    /// below one particle per frame still spawn the right number of particles.
+    pub fn spawn_count(&self, frame: u32, remainder: &mut f32) -> usize {
+        let mut count = if frame == 0 { self.burst } else { 0 };
+        if (frame as f32 / crate::FPS) < self.duration {
+            *remainder += self.rate / crate::FPS;
+            let whole = remainder.floor();
+            *remainder -= whole;
+            count += whole as usize;
+        }
+        count
+    }
+
+    /// Get the starting velocity of a particle.
+    ///
+    /// `spread` picks where in the cone the particle goes, from `-1.0` to `1.0`, and `speed` picks
+    /// the speed, from `0.0` for the minimum to `1.0` for the maximum. Flipped effects send their
+    /// particles the other way horizontally.
+    pub fn particle_velocity(&self, spread: f32, speed: f32, flip_x: bool) -> Vec2 {
+        let angle = (self.direction + spread * self.spread / 2.0).to_radians();
+        let speed = self.min_speed + (self.max_speed - self.min_speed) * speed;
+        let mut velocity = Vec2::from_angle(angle) * speed;
+        if flip_x {
+            velocity.x = -velocity.x;
+        }
+        velocity
+    }
+
+    /// Get the atlas frame of a particle that has lived for `age` seconds.
+    pub fn particle_frame(&self, age: f32) -> usize {
+        let frames = self.last_frame.saturating_sub(self.first_frame) + 1;
+        let progress = (age / self.lifetime.max(f32::EPSILON)).clamp(0.0, 1.0);
+        let frame = ((progress * frames as f32) as usize).min(frames - 1);
+        self.first_frame + frame
+    }
+}
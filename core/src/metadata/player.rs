@@ -9,11 +9,133 @@ pub struct PlayerMeta {
     pub body_size: Vec2,
     pub slide_body_size: Vec2,
     pub gravity: f32,
+    /// The body animation to play when the player emotes.
+    #[serde(default = "default_emote_animation")]
+    pub emote_animation: Key,
     pub sounds: PlayerSoundsMeta,
     pub stats: PlayerStatsMeta,
     pub layers: PlayerLayersMeta,
 }
 
+impl PlayerMeta {
+    /// Check that the player has every animation that the game plays, returning a warning for
+    /// each one that is missing.
+    ///
+    /// Missing animations don't crash the game, they fall back to another animation with
+    /// [`animation_or_fallback`], so this is mostly for letting skin authors know about them.
+    pub fn validate(&self) -> Vec<String> {
+        let mut required = required_player_animations();
+        if !required.contains(&self.emote_animation) {
+            required.push(self.emote_animation);
+        }
+        // The face also plays the emote animations
+        let mut face_required = required.clone();
+        face_required.extend(Emote::ALL.map(|emote| emote.animation_key()));
+
+        let mut warnings = Vec::new();
+        let body = &self.layers.body.animations;
+        for key in &required {
+            if !body.frames.contains_key(key) {
+                warnings.push(format!("The body layer is missing the {key:?} animation"));
+            } else if !body.body_offsets.contains_key(key) {
+                warnings.push(format!(
+                    "The body layer is missing the body offsets of the {key:?} animation"
+                ));
+            }
+        }
+        for (layer, animations, required) in [
+            ("fin", &self.layers.fin.animations, &required),
+            ("face", &self.layers.face.animations, &face_required),
+        ] {
+            for key in required {
+                if !animations.contains_key(key) {
+                    warnings.push(format!(
+                        "The {layer} layer is missing the {key:?} animation"
+                    ));
+                }
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Get the key of an animation of a player layer, falling back to the idle animation, or the first
+/// other animation that is available, when it is missing.
+///
+/// A warning is logged the first time each missing animation is asked for.
+pub fn animation_or_fallback<T>(animations: &std::collections::HashMap<Key, T>, key: Key) -> Key {
+    static WARNED: std::sync::Mutex<Vec<Key>> = std::sync::Mutex::new(Vec::new());
+
+    if animations.contains_key(&key) {
+        return key;
+    }
+    // The required animations start with the idle animation
+    let fallback = required_player_animations()
+        .into_iter()
+        .find(|x| animations.contains_key(x))
+        .or_else(|| {
+            // Sort the other animations by name, so that every game picks the same one
+            animations.keys().copied().min_by_key(|x| format!("{x:?}"))
+        });
+    let Some(fallback) = fallback else {
+        return key;
+    };
+
+    let mut warned = WARNED.lock().unwrap();
+    if !warned.contains(&key) {
+        warned.push(key);
+        warn!("Player animation {key:?} is missing, playing {fallback:?} instead");
+    }
+
+    fallback
+}
+
+/// Resource containing the problems found in the [`PlayerMeta`] assets that have been loaded, such
+/// as missing animations.
+#[derive(::bevy::prelude::Resource, Default, Debug)]
+pub struct PlayerMetaDiagnostics {
+    pub warnings: ::bevy::utils::HashMap<::bevy::asset::HandleId, Vec<String>>,
+}
+
+impl PlayerMetaDiagnostics {
+    /// Get the warnings of a player, which are empty if nothing is wrong with it.
+    pub fn get(&self, player: &Handle<PlayerMeta>) -> &[String] {
+        self.warnings
+            .get(&player.get_bevy_handle().id())
+            .map(|x| x.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// Bevy system that [validates][PlayerMeta::validate] the [`PlayerMeta`] assets as they are
+/// loaded, collecting the warnings in the [`PlayerMetaDiagnostics`].
+pub fn validate_player_meta(
+    mut asset_events: ::bevy::prelude::EventReader<::bevy::prelude::AssetEvent<PlayerMeta>>,
+    player_assets: ::bevy::prelude::Res<::bevy::prelude::Assets<PlayerMeta>>,
+    mut diagnostics: ::bevy::prelude::ResMut<PlayerMetaDiagnostics>,
+) {
+    use ::bevy::prelude::AssetEvent;
+
+    for event in asset_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                let Some(meta) = player_assets.get(handle) else {
+                    continue;
+                };
+                let warnings = meta.validate();
+                for warning in &warnings {
+                    warn!(player = %meta.name, "{warning}");
+                }
+                diagnostics.warnings.insert(handle.id(), warnings);
+            }
+            AssetEvent::Removed { handle } => {
+                diagnostics.warnings.remove(&handle.id());
+            }
+        }
+    }
+}
+
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
 #[serde(deny_unknown_fields)]
 pub struct PlayerLayersMeta {
@@ -57,6 +179,29 @@ pub struct PlayerStatsMeta {
     pub walk_speed: f32,
     pub slowdown: f32,
     pub accel_walk_speed: f32,
+    /// The fastest the player can swim horizontally.
+    #[serde(default = "default_swim_speed")]
+    pub swim_speed: f32,
+    #[serde(default = "default_accel_swim_speed")]
+    pub accel_swim_speed: f32,
+    /// The upward speed of a stroke, when the player jumps while swimming.
+    #[serde(default = "default_swim_stroke_speed")]
+    pub swim_stroke_speed: f32,
+    /// How far below the top of the player the top of a ledge can be for them to grab it.
+    #[serde(default = "default_ledge_grab_window")]
+    pub ledge_grab_window: f32,
+    /// How many frames it takes to pull up onto a ledge.
+    #[serde(default = "default_ledge_pull_up_frames")]
+    pub ledge_pull_up_frames: u32,
+    /// The horizontal speed of a dash.
+    #[serde(default = "default_dash_speed")]
+    pub dash_speed: f32,
+    /// How many frames a dash lasts for.
+    #[serde(default = "default_dash_frames")]
+    pub dash_frames: u32,
+    /// How many frames the player has to wait after a dash before they can dash again.
+    #[serde(default = "default_dash_cooldown_frames")]
+    pub dash_cooldown_frames: u32,
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug, Default)]
@@ -73,6 +218,16 @@ pub struct PlayerSoundsMeta {
 
     pub drop_volume: f64,
     pub drop: Handle<AudioSource>,
+
+    #[serde(default)]
+    pub emote_volume: f64,
+    #[serde(default)]
+    pub emote: Option<Handle<AudioSource>>,
+
+    #[serde(default)]
+    pub dash_volume: f64,
+    #[serde(default)]
+    pub dash: Option<Handle<AudioSource>>,
 }
 
 fn deserialize_arc<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
@@ -90,6 +245,33 @@ fn deserialize_arc_slice<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
 fn default_true() -> bool {
     true
 }
+fn default_emote_animation() -> Key {
+    key!("idle")
+}
+fn default_swim_speed() -> f32 {
+    4.0
+}
+fn default_accel_swim_speed() -> f32 {
+    0.5
+}
+fn default_swim_stroke_speed() -> f32 {
+    5.0
+}
+fn default_ledge_grab_window() -> f32 {
+    8.0
+}
+fn default_ledge_pull_up_frames() -> u32 {
+    12
+}
+fn default_dash_speed() -> f32 {
+    14.0
+}
+fn default_dash_frames() -> u32 {
+    10
+}
+fn default_dash_cooldown_frames() -> u32 {
+    45
+}
 
 fn deserialize_body_animations<'de, D: Deserializer<'de>>(
     deserializer: D,
@@ -152,3 +334,100 @@ fn deserialize_body_animations<'de, D: Deserializer<'de>>(
         frames,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The stats of a player skin made before swimming, ledge grabbing and dashing were added.
+    const OLD_STATS: &str = "
+air_speed: 6
+accel_air_speed: 1
+walk_speed: 6
+accel_walk_speed: 1
+jump_speed: 11
+slow_fall_speed: 1.5
+slowdown: 0.8
+";
+
+    #[test]
+    fn old_player_stats_load_with_defaults() {
+        let stats: PlayerStatsMeta = serde_yaml::from_str(OLD_STATS).unwrap();
+        assert_eq!(stats.walk_speed, 6.0);
+        assert_eq!(stats.swim_speed, default_swim_speed());
+        assert_eq!(stats.accel_swim_speed, default_accel_swim_speed());
+        assert_eq!(stats.swim_stroke_speed, default_swim_stroke_speed());
+        assert_eq!(stats.ledge_grab_window, default_ledge_grab_window());
+        assert_eq!(stats.ledge_pull_up_frames, default_ledge_pull_up_frames());
+        assert_eq!(stats.dash_speed, default_dash_speed());
+        assert_eq!(stats.dash_frames, default_dash_frames());
+        assert_eq!(stats.dash_cooldown_frames, default_dash_cooldown_frames());
+    }
+
+    fn animations<T: Default>(keys: &[Key]) -> Arc<std::collections::HashMap<Key, T>> {
+        Arc::new(keys.iter().map(|key| (*key, T::default())).collect())
+    }
+
+    #[test]
+    fn incomplete_player_meta_is_reported() {
+        let mut required = required_player_animations();
+        required.push(key!("emote"));
+        let mut face = required.clone();
+        face.push(Emote::Alarm.animation_key());
+
+        let mut meta = PlayerMeta {
+            emote_animation: key!("emote"),
+            ..default()
+        };
+        meta.layers.body.animations = BodyAnimationsMeta {
+            body_offsets: animations(&required),
+            frames: animations(&required),
+        };
+        meta.layers.fin.animations = animations(&required);
+        meta.layers.face.animations = animations(&face);
+        assert!(meta.validate().is_empty());
+
+        // Leave out the walk animation from the body, and the emote animations from the face
+        let without_walk = required
+            .iter()
+            .copied()
+            .filter(|key| *key != key!("walk"))
+            .collect::<Vec<_>>();
+        meta.layers.body.animations.frames = animations(&without_walk);
+        meta.layers.face.animations = animations(&required);
+        assert_eq!(meta.validate().len(), 2);
+
+        // The body offsets are checked separately from the frames
+        meta.layers.body.animations.frames = animations(&required);
+        meta.layers.body.animations.body_offsets = animations(&without_walk);
+        assert_eq!(meta.validate().len(), 2);
+    }
+
+    #[test]
+    fn missing_animations_fall_back() {
+        let with_idle = animations::<AnimatedSprite>(&[key!("idle"), key!("walk")]);
+        assert_eq!(
+            animation_or_fallback(&with_idle, key!("walk")),
+            key!("walk")
+        );
+        assert_eq!(
+            animation_or_fallback(&with_idle, key!("swim")),
+            key!("idle")
+        );
+
+        let without_idle = animations::<AnimatedSprite>(&[key!("walk"), key!("custom")]);
+        assert_eq!(
+            animation_or_fallback(&without_idle, key!("swim")),
+            key!("walk")
+        );
+
+        let only_custom = animations::<AnimatedSprite>(&[key!("custom_b"), key!("custom_a")]);
+        assert_eq!(
+            animation_or_fallback(&only_custom, key!("idle")),
+            key!("custom_a")
+        );
+
+        let empty = animations::<AnimatedSprite>(&[]);
+        assert_eq!(animation_or_fallback(&empty, key!("idle")), key!("idle"));
+    }
+}
@@ -0,0 +1,205 @@
+//! Mutators that change the rules of a match, such as low gravity or items raining from the sky.
+//!
+//! Mutators are applied to a copy of the [`CoreMeta`] before the session starts, with
+//! [`Mutators::apply`], so the rest of the game only sees the changed metadata.
+
+use crate::{prelude::*, random::GlobalRng};
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<ItemRain>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, update_item_rain);
+}
+
+/// A modifier that changes the rules of a match.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Mutator {
+    /// Everything falls slower, and the players jump higher.
+    LowGravity,
+    /// Every body bounces off of walls, including the players.
+    BouncyBodies,
+    /// Any damage kills a player right away.
+    OneHitKill,
+    /// The players move faster.
+    FastFish,
+    /// Random items fall from the sky throughout the match.
+    ItemRain,
+}
+
+impl Mutator {
+    pub const ALL: [Self; 5] = [
+        Self::LowGravity,
+        Self::BouncyBodies,
+        Self::OneHitKill,
+        Self::FastFish,
+        Self::ItemRain,
+    ];
+}
+
+/// A set of [`Mutator`]s that are turned on for a match.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Mutators {
+    enabled: Vec<Mutator>,
+}
+
+impl Mutators {
+    /// Whether a mutator is turned on.
+    pub fn contains(&self, mutator: Mutator) -> bool {
+        self.enabled.contains(&mutator)
+    }
+
+    /// Turn a mutator on or off.
+    pub fn set(&mut self, mutator: Mutator, enabled: bool) {
+        self.enabled.retain(|x| *x != mutator);
+        if enabled {
+            self.enabled.push(mutator);
+            self.enabled.sort();
+        }
+    }
+
+    /// Whether no mutators are turned on.
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_empty()
+    }
+
+    /// Iterate over the mutators that are turned on, in the order of [`Mutator::ALL`].
+    pub fn iter(&self) -> impl Iterator<Item = Mutator> + '_ {
+        self.enabled.iter().copied()
+    }
+
+    /// Make a copy of the core metadata, changed by the mutators that are turned on.
+    pub fn apply(&self, meta: &CoreMeta) -> CoreMeta {
+        let mut meta = meta.clone();
+        for mutator in self.iter() {
+            match mutator {
+                Mutator::LowGravity => {
+                    meta.physics.gravity_scale *= meta.mutators.low_gravity_scale;
+                }
+                Mutator::BouncyBodies => {
+                    meta.physics.min_bounciness = meta
+                        .physics
+                        .min_bounciness
+                        .max(meta.mutators.bouncy_bodies_bounciness);
+                }
+                Mutator::OneHitKill => meta.config.one_hit_kill = true,
+                Mutator::FastFish => {
+                    meta.config.player_speed_multiplier *= meta.mutators.fast_fish_speed_multiplier;
+                }
+                Mutator::ItemRain => meta.config.item_rain = Some(meta.mutators.item_rain.clone()),
+            }
+        }
+        meta
+    }
+}
+
+/// Resource that drops items from the sky when [`CoreConfigMeta::item_rain`] is set.
+///
+/// The items are spawned the same way as the items of an
+/// [`ItemSpawner`][item_spawner::ItemSpawner], as map elements without a layer that are hydrated
+/// into the items.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H5N2TQ6W8XJ4DK9ZB3RCF7VE"]
+pub struct ItemRain {
+    /// The item elements that have been dropped and are waiting to be picked up.
+    pub spawned: Vec<Entity>,
+    /// Counts down to dropping the next item.
+    pub timer: Option<Timer>,
+}
+
+fn update_item_rain(
+    mut entities: ResMut<Entities>,
+    game_meta: Res<CoreMetaArc>,
+    map: Res<LoadedMap>,
+    time: Res<Time>,
+    rng: Res<GlobalRng>,
+    mut item_rain: ResMut<ItemRain>,
+    mut element_handles: CompMut<ElementHandle>,
+    hydrated: Comp<MapElementHydrated>,
+    inventories: Comp<Inventory>,
+    mut respawn_points: CompMut<DehydrateOutOfBounds>,
+    mut transforms: CompMut<Transform>,
+) {
+    let Some(meta) = &game_meta.config.item_rain else {
+        return;
+    };
+    let item_rain = &mut *item_rain;
+
+    item_spawner::release_taken_items(
+        &mut entities,
+        &mut item_rain.spawned,
+        &hydrated,
+        &inventories,
+        &mut respawn_points,
+    );
+
+    if item_rain.spawned.len() >= meta.max_items as usize {
+        return;
+    }
+    let timer = item_rain
+        .timer
+        .get_or_insert_with(|| Timer::new(meta.interval, TimerMode::Once));
+    timer.tick(time.delta());
+    if !timer.finished() {
+        return;
+    }
+    item_rain.timer = None;
+
+    let weights = meta
+        .items
+        .iter()
+        .map(|entry| entry.weight)
+        .collect::<Vec<_>>();
+    let Some(item_idx) = item_spawner::choose_weighted(&weights, rng.f32()) else {
+        return;
+    };
+
+    // Drop the item from just below the top of the map, away from the walls at the sides
+    let map_size = map.grid_size.as_vec2() * map.tile_size;
+    let margin = map.tile_size.x * 2.0;
+    let x = margin + rng.f32() * (map_size.x - margin * 2.0).max(0.0);
+    let y = map_size.y - map.tile_size.y;
+
+    let item_element = entities.create();
+    element_handles.insert(
+        item_element,
+        ElementHandle(meta.items[item_idx].element.clone()),
+    );
+    transforms.insert(item_element, Transform::from_translation(vec3(x, y, 0.0)));
+    item_rain.spawned.push(item_element);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutators_change_a_copy_of_the_meta() {
+        let mut meta = CoreMeta::default();
+        meta.physics.gravity_scale = 1.0;
+        meta.config.player_speed_multiplier = 1.0;
+        meta.mutators = MutatorsMeta::default();
+
+        let mut mutators = Mutators::default();
+        mutators.set(Mutator::ItemRain, true);
+        mutators.set(Mutator::LowGravity, true);
+        mutators.set(Mutator::FastFish, true);
+        mutators.set(Mutator::FastFish, false);
+        assert_eq!(
+            mutators.iter().collect::<Vec<_>>(),
+            vec![Mutator::LowGravity, Mutator::ItemRain]
+        );
+
+        let mutated = mutators.apply(&meta);
+        assert_eq!(mutated.physics.gravity_scale, 0.5);
+        assert_eq!(mutated.config.player_speed_multiplier, 1.0);
+        assert!(mutated.config.item_rain.is_some());
+        assert!(!mutated.config.one_hit_kill);
+
+        // The original meta is left alone
+        assert_eq!(meta.physics.gravity_scale, 1.0);
+        assert!(meta.config.item_rain.is_none());
+    }
+}
@@ -0,0 +1,240 @@
+//! The round timer, and the sudden death that decides tied matches.
+//!
+//! Matches are timed by setting [`CoreConfigMeta::time_limit`]. When the time runs out, the player
+//! with the best score wins: the most lives left in a stock match, or the most points otherwise. If
+//! several players are tied, the match goes into sudden death, where the tied players are the only
+//! ones left in the match, nobody respawns, and any damage kills. A damage border may close in
+//! from the edges of the map to force the players together, and if more than one of them is still
+//! standing after [`SuddenDeathMeta::max_duration`], the match is a draw.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<SuddenDeath>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, update_overtime);
+}
+
+/// The color of the line drawn along the inside of the sudden death border.
+const BORDER_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+
+/// The thickness of the line drawn along the inside of the sudden death border.
+const BORDER_LINE_THICKNESS: f32 = 3.0;
+
+/// Resource containing the state of sudden death.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H4M7XK2QY8DBN3VTF5RZ6CWA"]
+pub struct SuddenDeath {
+    /// Whether the match is in sudden death.
+    pub active: bool,
+    /// How many frames sudden death has lasted.
+    pub frames_elapsed: u32,
+    /// The players that can still win the match. Everybody else is eliminated.
+    pub contenders: [bool; MAX_PLAYERS],
+    /// Whether each player was alive on the last frame, used to detect when they die.
+    pub was_alive: [bool; MAX_PLAYERS],
+    /// How far the damage border has closed in from the edges of the map, in pixels.
+    pub border_inset: f32,
+    /// The damage region entities making up the border, if there is one.
+    pub border: Vec<Entity>,
+}
+
+impl SuddenDeath {
+    /// Whether the player has been knocked out of sudden death.
+    pub fn is_eliminated(&self, player_idx: usize) -> bool {
+        self.active && !self.contenders[player_idx]
+    }
+
+    /// The area inside of the damage border, given the size of the map.
+    pub fn playable_area(&self, map_size: Vec2) -> MapCameraBoundsMeta {
+        let inset = self.border_inset.min(map_size.min_element() / 2.0);
+        MapCameraBoundsMeta {
+            min: Vec2::splat(inset),
+            max: map_size - inset,
+        }
+    }
+}
+
+/// Get the players with the best score, out of the given player indexes and their scores.
+fn leaders(scores: impl IntoIterator<Item = (usize, f32)>) -> Vec<usize> {
+    let scores = scores.into_iter().collect::<Vec<_>>();
+    let best = scores
+        .iter()
+        .map(|(_, score)| *score)
+        .fold(f32::MIN, f32::max);
+    scores
+        .into_iter()
+        .filter(|(_, score)| *score == best)
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Decide the match when the round timer runs out, and run sudden death if it ends in a tie.
+fn update_overtime(
+    mut entities: ResMut<Entities>,
+    mut commands: Commands,
+    core_meta: Res<CoreMetaArc>,
+    map_meta: Res<SpawnedMapMeta>,
+    match_state: Res<MatchState>,
+    player_inputs: Res<PlayerInputs>,
+    stocks: Res<PlayerStocks>,
+    mut score: ResMut<MatchScore>,
+    mut sudden_death: ResMut<SuddenDeath>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    mut damage_regions: CompMut<DamageRegion>,
+    mut transforms: CompMut<Transform>,
+    mut paths: CompMut<Path2d>,
+) {
+    let config = &core_meta.config;
+    let Some(time_limit) = config.time_limit else {
+        return;
+    };
+    if score.result.is_some() {
+        return;
+    }
+    let sudden_death = &mut *sudden_death;
+    let meta = &config.sudden_death;
+
+    let mut alive = [false; MAX_PLAYERS];
+    for (ent, idx) in entities.iter_with(&player_indexes) {
+        if !players_killed.contains(ent) {
+            alive[idx.0] = true;
+        }
+    }
+
+    if !sudden_death.active {
+        let MatchState::Playing { frames_played } = *match_state else {
+            return;
+        };
+        if (frames_played as f32) < time_limit.as_secs_f32() * crate::FPS {
+            return;
+        }
+
+        let scores = (0..MAX_PLAYERS)
+            .filter(|&i| player_inputs.players[i].active && !stocks.is_eliminated(i))
+            .map(|i| match config.stock_lives {
+                Some(_) => (i, stocks.lives[i].unwrap_or_default() as f32),
+                None => (i, score.points[i]),
+            });
+        let leaders = leaders(scores);
+        score.result = match leaders[..] {
+            [] => Some(MatchResult::Draw),
+            [winner] => {
+                info!(player_idx = winner, "Player won on time");
+                Some(MatchResult::Winner(winner))
+            }
+            _ if !meta.enabled => Some(MatchResult::Draw),
+            ref tied => {
+                info!(players = ?tied, "Match tied on time, starting sudden death");
+                sudden_death.active = true;
+                for &i in tied {
+                    sudden_death.contenders[i] = true;
+                }
+                sudden_death.was_alive = alive;
+
+                // Only the tied players are left in the match
+                for (ent, idx) in entities.iter_with(&player_indexes) {
+                    if !sudden_death.contenders[idx.0] && !players_killed.contains(ent) {
+                        commands.add(PlayerCommand::kill(ent, None));
+                    }
+                }
+
+                if meta.border_speed.is_some() {
+                    sudden_death.border = (0..4).map(|_| entities.create()).collect();
+                }
+                None
+            }
+        };
+        if !sudden_death.active {
+            return;
+        }
+    } else {
+        sudden_death.frames_elapsed += 1;
+    }
+
+    // Any death knocks a player out of sudden death
+    for i in 0..MAX_PLAYERS {
+        let died = sudden_death.was_alive[i] && !alive[i];
+        if died || !player_inputs.players[i].active || stocks.is_eliminated(i) {
+            sudden_death.contenders[i] = false;
+        }
+        sudden_death.was_alive[i] = alive[i];
+    }
+
+    let remaining = (0..MAX_PLAYERS)
+        .filter(|&i| sudden_death.contenders[i])
+        .collect::<Vec<_>>();
+    let max_frames = meta.max_duration.as_secs_f32() * crate::FPS;
+    score.result = match remaining[..] {
+        [] => Some(MatchResult::Draw),
+        [winner] => {
+            info!(player_idx = winner, "Player won sudden death");
+            Some(MatchResult::Winner(winner))
+        }
+        _ if sudden_death.frames_elapsed as f32 >= max_frames => {
+            info!("Sudden death ran out of time, the match is a draw");
+            Some(MatchResult::Draw)
+        }
+        _ => None,
+    };
+
+    // Close in the border
+    let Some(border_speed) = meta.border_speed else {
+        return;
+    };
+    let map_size = map_meta.grid_size.as_vec2() * map_meta.tile_size;
+    sudden_death.border_inset += border_speed / crate::FPS;
+    let area = sudden_death.playable_area(map_size);
+
+    // Each side of the border reaches well outside of the map, so that there's no getting around
+    // it.
+    let depth = map_size.max_element();
+    let center = map_size / 2.0;
+    let sides = [
+        (vec2(area.min.x - depth / 2.0, center.y), vec2(1.0, 0.0)),
+        (vec2(area.max.x + depth / 2.0, center.y), vec2(-1.0, 0.0)),
+        (vec2(center.x, area.min.y - depth / 2.0), vec2(0.0, 1.0)),
+        (vec2(center.x, area.max.y + depth / 2.0), vec2(0.0, -1.0)),
+    ];
+    for (&ent, (position, inward)) in sudden_death.border.iter().zip(sides) {
+        let along = inward.perp().abs();
+        let size = inward.abs() * depth + along * (map_size + 2.0 * depth);
+        let edge = inward * depth / 2.0;
+        transforms.insert(ent, Transform::from_translation(position.extend(0.0)));
+        damage_regions.insert(ent, DamageRegion { size });
+        paths.insert(
+            ent,
+            Path2d {
+                color: BORDER_COLOR,
+                points: vec![edge - along * size / 2.0, edge + along * size / 2.0],
+                thickness: BORDER_LINE_THICKNESS,
+                ..default()
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaders_include_every_tied_player() {
+        assert_eq!(leaders([(0, 2.0), (1, 3.0), (2, 1.0)]), vec![1]);
+        assert_eq!(leaders([(0, 3.0), (1, 0.0), (3, 3.0)]), vec![0, 3]);
+        assert!(leaders([]).is_empty());
+    }
+
+    #[test]
+    fn playable_area_stops_at_the_center() {
+        let sudden_death = SuddenDeath {
+            border_inset: 1000.0,
+            ..default()
+        };
+        let area = sudden_death.playable_area(vec2(400.0, 200.0));
+        assert_eq!(area.min, vec2(100.0, 100.0));
+        assert_eq!(area.max, vec2(300.0, 100.0));
+    }
+}
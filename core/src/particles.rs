@@ -0,0 +1,213 @@
+//! Particle effects, like explosion debris and muzzle flashes.
+//!
+//! Particle emitters and their particles are regular, pooled entities in the game world, so they
+//! are rolled back along with the rest of the game state. Purely cosmetic effects that don't need
+//! to be part of the simulation may be played outside of the game session instead.
+
+use crate::{prelude::*, random::GlobalRng};
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, update_particle_emitters)
+        .add_system_to_stage(CoreStage::PostUpdate, update_particles);
+
+    session.pooled_components.register::<ParticleEmitter>();
+    session.pooled_components.register::<Particle>();
+}
+
+/// Component for an entity that spawns the particles of a [`ParticleEffectMeta`].
+///
+/// The emitter is despawned by its [`Lifetime`] once all of its particles are gone.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H50B7D4KX1FZ9S6RHN2WPQ3A"]
+pub struct ParticleEmitter {
+    pub meta: Handle<ParticleEffectMeta>,
+    /// Whether the particles are sent the other way horizontally.
+    pub flip_x: bool,
+    /// The number of frames that the emitter has been running for.
+    pub frame: u32,
+    /// The fraction of a particle left over from the last frame.
+    pub remainder: f32,
+    /// The particles spawned by the emitter that are still alive.
+    pub particles: Vec<Entity>,
+}
+
+/// Component for a particle spawned by a [`ParticleEmitter`].
+#[derive(Clone, Debug, TypeUlid)]
+#[ulid = "01H50B7M8C5VJ2GTY3QEK0ND9B"]
+pub struct Particle {
+    pub meta: Handle<ParticleEffectMeta>,
+    /// The emitter that spawned the particle.
+    pub emitter: Entity,
+    pub velocity: Vec2,
+    /// How long the particle has been alive, in seconds.
+    pub age: f32,
+}
+
+/// Returns a system that starts playing a particle effect at the given transform.
+pub fn spawn_particle_effect(
+    meta: Handle<ParticleEffectMeta>,
+    transform: Transform,
+    flip_x: bool,
+) -> System {
+    (move |mut entities: ResMut<Entities>,
+           mut pool: ResMut<EntityPool>,
+           mut pooled: CompMut<Pooled>,
+           particle_assets: BevyAssets<ParticleEffectMeta>,
+           mut emitters: CompMut<ParticleEmitter>,
+           mut transforms: CompMut<Transform>,
+           mut lifetimes: CompMut<Lifetime>| {
+        let Some(effect) = particle_assets.get(&meta.get_bevy_handle()) else {
+            return;
+        };
+        let ent = pool.spawn(&mut entities, &mut pooled);
+        emitters.insert(
+            ent,
+            ParticleEmitter {
+                meta: meta.clone(),
+                flip_x,
+                ..default()
+            },
+        );
+        transforms.insert(ent, transform);
+        lifetimes.insert(ent, Lifetime::new(effect.total_duration()));
+    })
+    .system()
+}
+
+/// Spawn the particles of each emitter, up to the effect's maximum number of particles.
+fn update_particle_emitters(
+    mut entities: ResMut<Entities>,
+    mut pool: ResMut<EntityPool>,
+    mut pooled: CompMut<Pooled>,
+    rng: Res<GlobalRng>,
+    particle_assets: BevyAssets<ParticleEffectMeta>,
+    mut emitters: CompMut<ParticleEmitter>,
+    mut particles: CompMut<Particle>,
+    mut transforms: CompMut<Transform>,
+    mut sprites: CompMut<AtlasSprite>,
+    mut lifetimes: CompMut<Lifetime>,
+) {
+    let emitter_ents = entities
+        .iter_with(&emitters)
+        .map(|(ent, _)| ent)
+        .filter(|ent| !pool.is_released(*ent))
+        .collect::<Vec<_>>();
+
+    for emitter_ent in emitter_ents {
+        let emitter = emitters.get_mut(emitter_ent).unwrap();
+        let Some(effect) = particle_assets.get(&emitter.meta.get_bevy_handle()) else {
+            continue;
+        };
+        let Some(emitter_transform) = transforms.get(emitter_ent).copied() else {
+            continue;
+        };
+
+        // Forget about particles that have died, which may already be reused by another emitter
+        emitter.particles.retain(|particle| {
+            !pool.is_released(*particle)
+                && particles
+                    .get(*particle)
+                    .map(|x| x.emitter == emitter_ent)
+                    .unwrap_or(false)
+        });
+
+        let count = effect
+            .spawn_count(emitter.frame, &mut emitter.remainder)
+            .min(effect.max_particles.saturating_sub(emitter.particles.len()));
+        emitter.frame += 1;
+
+        for _ in 0..count {
+            let velocity =
+                effect.particle_velocity(rng.f32_normalized(), rng.f32(), emitter.flip_x);
+            let ent = pool.spawn(&mut entities, &mut pooled);
+            particles.insert(
+                ent,
+                Particle {
+                    meta: emitter.meta.clone(),
+                    emitter: emitter_ent,
+                    velocity,
+                    age: 0.0,
+                },
+            );
+            transforms.insert(
+                ent,
+                Transform::from_translation(emitter_transform.translation + Vec3::Z),
+            );
+            sprites.insert(
+                ent,
+                AtlasSprite {
+                    atlas: effect.atlas.clone(),
+                    index: effect.first_frame,
+                    flip_x: emitter.flip_x,
+                    ..default()
+                },
+            );
+            lifetimes.insert(ent, Lifetime::new(effect.lifetime));
+            emitter.particles.push(ent);
+        }
+    }
+}
+
+/// Move and animate particles.
+fn update_particles(
+    entities: Res<Entities>,
+    pool: Res<EntityPool>,
+    particle_assets: BevyAssets<ParticleEffectMeta>,
+    mut particles: CompMut<Particle>,
+    mut transforms: CompMut<Transform>,
+    mut sprites: CompMut<AtlasSprite>,
+) {
+    for (ent, (particle, transform, sprite)) in
+        entities.iter_with((&mut particles, &mut transforms, &mut sprites))
+    {
+        if pool.is_released(ent) {
+            continue;
+        }
+        let Some(effect) = particle_assets.get(&particle.meta.get_bevy_handle()) else {
+            continue;
+        };
+
+        particle.velocity.y -= effect.gravity;
+        transform.translation += particle.velocity.extend(0.0);
+        particle.age += 1.0 / crate::FPS;
+        sprite.index = effect.particle_frame(particle.age);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawns_burst_then_rate_until_duration() {
+        let effect = ParticleEffectMeta {
+            burst: 3,
+            rate: crate::FPS / 2.0,
+            duration: 4.0 / crate::FPS,
+            ..default()
+        };
+
+        let mut remainder = 0.0;
+        let counts = (0..6)
+            .map(|frame| effect.spawn_count(frame, &mut remainder))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [3, 1, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn particles_animate_through_frame_range() {
+        let effect = ParticleEffectMeta {
+            first_frame: 4,
+            last_frame: 7,
+            lifetime: 1.0,
+            ..default()
+        };
+
+        assert_eq!(effect.particle_frame(0.0), 4);
+        assert_eq!(effect.particle_frame(0.5), 6);
+        assert_eq!(effect.particle_frame(0.99), 7);
+        assert_eq!(effect.particle_frame(2.0), 7);
+    }
+}
@@ -1,14 +1,21 @@
 //! Physics and collision detection.
 
-use crate::prelude::*;
+use crate::{
+    elements::water_volume::{submersion, WaterVolume},
+    prelude::*,
+};
 
 pub use collisions::{
-    Actor, Collider, ColliderShape, CollisionWorld, RapierContext, RapierUserData,
-    TileCollisionKind,
+    Actor, Collider, ColliderShape, CollisionWorld, RapierContext, RapierUserData, SpatialHash,
+    TileCollisionKind, TileSurface,
 };
 
 pub mod collisions;
 
+/// How far below a kinematic body its collider is checked for tiles to decide whether it is
+/// standing on the ground.
+pub const GROUND_CHECK_OFFSET: f32 = 0.1;
+
 #[derive(Debug, Clone, Copy)]
 enum PhysicsStage {
     Update,
@@ -27,18 +34,25 @@ impl StageLabel for PhysicsStage {
 }
 
 pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<SpatialHash>();
     session
         .stages
-        // TODO: Think again about exactly how to organize the physics sync systems. At the time of
-        // writing, we have to do an extra `collision_world.step()` when debug rendering to make
-        // sure everything is synced. It'd be good to avoid this and maybe take inspiration from
-        // bevy_rapier.
+        // The spatial hash is rebuilt before anything else runs in these stages, so that it
+        // includes the damage regions spawned and the bodies moved during the player update.
+        .add_system_to_stage(CoreStage::First, collisions::update_spatial_hash)
+        .add_system_to_stage(CoreStage::PostUpdate, collisions::update_spatial_hash)
+        // TODO: Think again about exactly how to organize the physics sync systems. It'd be good to
+        // maybe take inspiration from bevy_rapier.
         .insert_stage_after(
             CoreStage::PostUpdate,
             SimpleSystemStage::new(PhysicsStage::Update),
         )
         .add_system_to_stage(PhysicsStage::Update, hydrate_physics_bodies)
         .add_system_to_stage(PhysicsStage::Update, update_kinematic_bodies);
+
+    session
+        .inspectors
+        .register::<KinematicBody>("KinematicBody");
 }
 
 /// A kinematic physics body
@@ -65,9 +79,16 @@ pub struct KinematicBody {
     /// friction while it is on the block.
     pub frame_friction_override: Option<f32>,
     pub is_on_ground: bool,
+    /// The surface of the tile the body is standing on, or the default surface if it isn't on a
+    /// tile.
+    pub ground_surface: TileSurface,
+    /// How many times the body has tried to jump off of the sticky tile it is standing on.
+    pub sticky_jump_presses: u32,
     pub was_on_ground: bool,
     /// Will be `true` if the body is currently on top of a platform/jumpthrough tile
     pub is_on_platform: bool,
+    /// How much of the body is under water, from `0.0` to `1.0`.
+    pub submersion: f32,
     /// If this is `true` the body will be affected by gravity
     pub has_mass: bool,
     pub has_friction: bool,
@@ -80,6 +101,11 @@ pub struct KinematicBody {
     /// This is important to make sure that it falls through JumpThrough platforms if it happens to
     /// spawn inside of one.
     pub is_spawning: bool,
+    /// How many frames in a row the body has been stuck in solid tiles, with nowhere to be shoved
+    /// out to.
+    ///
+    /// Bodies that are stuck for more than [`PhysicsMeta::crush_tolerance_frames`] are crushed.
+    pub stuck_frames: u32,
 }
 
 impl KinematicBody {
@@ -97,6 +123,40 @@ impl KinematicBody {
             max: vec2(aabb.maxs.x, aabb.maxs.y),
         }
     }
+
+    /// Try to jump off of the ground, returning whether the jump is allowed.
+    ///
+    /// Bodies on a sticky tile have to try `sticky_jump_presses` times before they get free.
+    pub fn try_jump(&mut self, sticky_jump_presses: u32) -> bool {
+        if !self.ground_surface.sticky {
+            return true;
+        }
+        self.sticky_jump_presses += 1;
+        if self.sticky_jump_presses >= sticky_jump_presses {
+            self.sticky_jump_presses = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Inspect for KinematicBody {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.vec2("velocity", &mut self.velocity);
+        fields.number("angular_velocity", &mut self.angular_velocity);
+        fields.number("gravity", &mut self.gravity);
+        fields.number("bounciness", &mut self.bounciness);
+        fields.text("is_on_ground", self.is_on_ground);
+        fields.text("is_on_platform", self.is_on_platform);
+        fields.text("submersion", self.submersion);
+        fields.bool("has_mass", &mut self.has_mass);
+        fields.bool("has_friction", &mut self.has_friction);
+        fields.bool("can_rotate", &mut self.can_rotate);
+        fields.text("is_deactivated", self.is_deactivated);
+        fields.text("fall_through", self.fall_through);
+        fields.text("stuck_frames", self.stuck_frames);
+    }
 }
 
 /// Hydrate newly added [`KinematicBody`]s.
@@ -125,25 +185,32 @@ fn hydrate_physics_bodies(
 }
 
 /// Update physics for kinematic bodies.
+///
+/// Bodies that are squeezed between solids, like a player under a platform that is coming down on
+/// them, are crushed. Players are killed, and other bodies are despawned.
 fn update_kinematic_bodies(
     game: Res<CoreMetaArc>,
     entities: Res<Entities>,
     mut bodies: CompMut<KinematicBody>,
     mut collision_world: CollisionWorld,
     mut transforms: CompMut<Transform>,
-    time: Res<Time>,
+    tile_surfaces: Comp<TileSurface>,
+    water_volumes: Comp<WaterVolume>,
+    player_indexes: Comp<PlayerIdx>,
+    mut camera_trauma: ResMut<CameraTrauma>,
+    mut commands: Commands,
 ) {
     puffin::profile_function!();
 
-    // TODO: By default we assume a frame rate of of crate::FPS ( 60 frames-per-second ), but we may
-    // actually run at a different rate during, for example, network games. This gets the ratio
-    // between the default frame rate and the actual frame rate and is used to adjust velocities.
-    // This is a temporary fix. In the future, we should adjust all velocities to be in terms of
-    // pixels-per-second, instead of pixels-per-1/60 of a second like they are today. This temporary
-    // fix allows us to avoid chainging the velocities throughout the game for now.
-    let time_factor = time.delta().as_secs_f32() / (1.0 / crate::FPS);
+    // Velocities are in pixels per frame, since the session always advances in fixed steps of
+    // 1 / crate::FPS seconds.
 
     collision_world.update(&transforms);
+    let water_rects = entities
+        .iter_with((&water_volumes, &transforms))
+        .map(|(_ent, (water, transform))| (water.rect(transform.translation), water))
+        .collect::<Vec<_>>();
+
     for (entity, body) in entities.iter_with(&mut bodies) {
         if body.is_deactivated {
             collision_world.colliders.get_mut(entity).unwrap().disabled = true;
@@ -156,10 +223,14 @@ fn update_kinematic_bodies(
             puffin::profile_scope!("Shove objects out of walls");
 
             // Shove objects out of walls
+            let mut stuck = false;
             loop {
                 let mut transform = transforms.get(entity).copied().unwrap();
 
-                if collision_world.tile_collision(transform, body.shape) != TileCollisionKind::Solid
+                // Bodies that collide like tiles shouldn't be shoved out of themselves
+                if !collision_world
+                    .tile_collision_filtered(transform, body.shape, |ent| ent != entity)
+                    .is_solid()
                 {
                     break;
                 }
@@ -169,11 +240,14 @@ fn update_kinematic_bodies(
                 // if it is perfectly lined up along the edge of a tile, and `solid_at` won't.
                 let border = 0.1;
 
+                // Solid colliders other than tiles, like moving platforms, can push bodies too.
+                let solid_at = |pos| collision_world.solid_at_filtered(pos, |ent| ent != entity);
+
                 let collisions = (
-                    collision_world.solid_at(vec2(rect.min.x - border, rect.max.y + border)), // Top left
-                    collision_world.solid_at(vec2(rect.max.x + border, rect.max.y + border)), // Top right
-                    collision_world.solid_at(vec2(rect.max.x + border, rect.min.y - border)), // Bottom right
-                    collision_world.solid_at(vec2(rect.min.x - border, rect.min.y - border)), // Bottom left
+                    solid_at(vec2(rect.min.x - border, rect.max.y + border)), // Top left
+                    solid_at(vec2(rect.max.x + border, rect.max.y + border)), // Top right
+                    solid_at(vec2(rect.max.x + border, rect.min.y - border)), // Bottom right
+                    solid_at(vec2(rect.min.x - border, rect.min.y - border)), // Bottom left
                 );
                 match collisions {
                     // If we have no solid collisions at any corner.
@@ -194,12 +268,25 @@ fn update_kinematic_bodies(
                     // If none of the sides of the rectangle are un-collided, then we don't know
                     // which direction to move to get out of the wall, and we just give up.
                     _ => {
+                        stuck = true;
                         break;
                     }
                 }
 
                 *transforms.get_mut(entity).unwrap() = transform;
             }
+
+            // Crush bodies that stay stuck, allowing for brief overlaps, like the ones that happen
+            // when a rollback moves something into a body.
+            body.stuck_frames = if stuck { body.stuck_frames + 1 } else { 0 };
+            if body.stuck_frames > game.physics.crush_tolerance_frames {
+                if player_indexes.contains(entity) {
+                    commands.add(PlayerCommand::kill_environmental(entity, None));
+                } else {
+                    commands.add(move |mut entities: ResMut<Entities>| entities.kill(entity));
+                    continue;
+                }
+            }
         }
 
         // Sync body attributes with collider
@@ -217,23 +304,21 @@ fn update_kinematic_bodies(
             collision_world.descent(entity);
         }
 
+        // Remember how fast the body was falling before it hits anything
+        let fall_speed = -body.velocity.y;
+
         {
             puffin::profile_scope!("move body");
 
-            if collision_world.move_vertical(&mut transforms, entity, body.velocity.y * time_factor)
-            {
-                body.velocity.y *= -body.bounciness;
+            if collision_world.move_vertical(&mut transforms, entity, body.velocity.y) {
+                body.velocity.y *= -body.bounciness.max(game.physics.min_bounciness);
             }
 
             // NOTE: It's important that we move horizontally after we move vertically, or else the
             // horizontal movement will clear our `descent` and `seen_wood` flags and we may not go
             // through drop through platforms while moving horizontally.
-            if collision_world.move_horizontal(
-                &mut transforms,
-                entity,
-                body.velocity.x * time_factor,
-            ) {
-                body.velocity.x *= -body.bounciness;
+            if collision_world.move_horizontal(&mut transforms, entity, body.velocity.x) {
+                body.velocity.x *= -body.bounciness.max(game.physics.min_bounciness);
             }
         }
 
@@ -255,14 +340,16 @@ fn update_kinematic_bodies(
             }
 
             // Move transform check down 1 slightly
-            transform.translation.y -= 0.1;
+            transform.translation.y -= GROUND_CHECK_OFFSET;
 
             body.was_on_ground = body.is_on_ground;
 
             let collider = collision_world.get_collider(entity);
 
-            let tile = collision_world.tile_collision_filtered(transform, body.shape, |ent| {
-                if collider.seen_wood {
+            let tile_ent = collision_world.tile_entity_filtered(transform, body.shape, |ent| {
+                if ent == entity {
+                    false
+                } else if collider.seen_wood {
                     collision_world
                         .tile_collision_kinds
                         .get(ent)
@@ -272,11 +359,51 @@ fn update_kinematic_bodies(
                     true
                 }
             });
+            let tile = tile_ent
+                .and_then(|ent| collision_world.tile_collision_kinds.get(ent).copied())
+                .unwrap_or_default();
 
             let on_jump_through_tile = tile == TileCollisionKind::JumpThrough;
             body.is_on_ground =
                 tile != TileCollisionKind::Empty && !(on_jump_through_tile && body.fall_through);
             body.is_on_platform = body.is_on_ground && on_jump_through_tile;
+
+            // Apply the surface of the tile we are standing on, for this frame only
+            body.ground_surface = tile_ent
+                .filter(|_| body.is_on_ground)
+                .and_then(|ent| tile_surfaces.get(ent).copied())
+                .unwrap_or_default();
+            if !body.ground_surface.sticky {
+                body.sticky_jump_presses = 0;
+            }
+            if !body.ground_surface.is_default() && body.frame_friction_override.is_none() {
+                body.frame_friction_override = Some(
+                    body.ground_surface
+                        .friction_lerp(game.physics.friction_lerp),
+                );
+            }
+
+            // Shake the camera for heavy landings
+            if body.is_on_ground
+                && !body.was_on_ground
+                && fall_speed >= game.camera.heavy_landing_speed
+            {
+                camera_trauma.add(game.camera.heavy_landing_trauma);
+            }
+        }
+
+        // Check how deep the body is in water, using the water it is deepest in
+        let water = {
+            let rect = body.bounding_box(*transforms.get(entity).unwrap());
+            water_rects
+                .iter()
+                .map(|(water_rect, water)| (submersion(water_rect, &rect), *water))
+                .filter(|(submersion, _)| *submersion > 0.0)
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+        };
+        body.submersion = water.map(|(submersion, _)| submersion).unwrap_or_default();
+        if let Some((submersion, water)) = water {
+            body.velocity *= 1.0 - (water.drag * submersion).clamp(0.0, 1.0);
         }
 
         if body.is_on_ground {
@@ -286,7 +413,6 @@ fn update_kinematic_bodies(
                 } else {
                     game.physics.friction_lerp
                 };
-                body.frame_friction_override = None;
 
                 if body.velocity.x.abs() <= game.physics.stop_threshold {
                     body.velocity.x = 0.0;
@@ -301,13 +427,24 @@ fn update_kinematic_bodies(
         }
 
         if !body.is_on_ground && body.has_mass {
-            body.velocity.y -= body.gravity * time_factor;
+            let body_gravity = body.gravity * game.physics.gravity_scale;
+            let mut gravity = body_gravity;
+            let mut terminal_velocity = game.physics.terminal_velocity;
+            if let Some((submersion, water)) = water {
+                // Buoyancy pushes up against gravity, the more so the deeper the body is
+                gravity -= body_gravity * water.density * submersion;
+                terminal_velocity *= water.terminal_velocity_multiplier;
+            }
+            body.velocity.y -= gravity;
 
-            if body.velocity.y < -game.physics.terminal_velocity {
-                body.velocity.y = -game.physics.terminal_velocity;
+            if body.velocity.y < -terminal_velocity {
+                body.velocity.y = -terminal_velocity;
             }
         }
 
+        // The friction override only lasts for this frame, even if the body wasn't on the ground
+        body.frame_friction_override = None;
+
         if body.can_rotate {
             apply_rotation(
                 transforms.get_mut(entity).unwrap(),
@@ -342,3 +479,69 @@ fn apply_rotation(
 
     transform.rotation = Quat::from_rotation_z(angle);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestSessionBuilder};
+
+    #[test]
+    fn platform_crushes_player_in_pit() {
+        let mut builder = TestSessionBuilder::room(20, 10);
+        let number = |key: &str, value| ElementPropertyMeta {
+            key: key.into(),
+            name: key.into(),
+            default: ElementPropertyValue::Number(value),
+        };
+        let platform = builder.assets.insert(
+            "/platform",
+            ElementMeta {
+                name: "Moving Platform".into(),
+                category: "Gameplay".into(),
+                builtin: BuiltinElementKind::MovingPlatform(MovingPlatformMeta {
+                    size: Vec2::new(64.0, 16.0),
+                    collision: TileCollisionKind::Solid,
+                    color: default(),
+                }),
+                editor: ElementEditorMeta {
+                    properties: vec![
+                        number(moving_platform::SPEED_PROPERTY, 60.0),
+                        number(moving_platform::WAIT_TIME_PROPERTY, 60.0),
+                    ],
+                    ..default()
+                },
+                ..default()
+            },
+        );
+
+        // Raise the floor by a tile, except for a pit that is a bit wider than the player
+        for x in (1..19).filter(|x| !(9..12).contains(x)) {
+            builder.map.layers[0].tiles.push(MapTileMeta {
+                pos: UVec2::new(x, 1),
+                idx: 0,
+                collision: TileCollisionKind::Solid,
+                surface: default(),
+            });
+        }
+        let pit_x = 10.5 * testing::TILE_SIZE;
+        builder.add_player(Vec2::new(pit_x, 48.0));
+        // Bring the platform down until it lies on the floor, over the pit
+        builder.map.layers[0].elements.push(ElementSpawn {
+            pos: Vec2::new(pit_x, 120.0),
+            element: platform,
+            properties: default(),
+            waypoints: ElementWaypoints(vec![Vec2::new(0.0, -80.0)]),
+        });
+
+        let mut session = builder.build();
+        session.step(40);
+        assert!(session.is_player_alive(0), "Player was crushed too soon");
+        assert!(session.kills().is_empty());
+
+        session.step(80);
+        let kills = session.kills();
+        assert!(!kills.is_empty(), "Player wasn't crushed");
+        assert_eq!(kills[0].victim, 0);
+        assert_eq!(kills[0].killer, None);
+    }
+}
@@ -8,8 +8,10 @@ use indexmap::IndexMap;
 
 pub use rapier2d::prelude as rapier;
 pub use shape::*;
+pub use spatial_hash::*;
 
 mod shape;
+mod spatial_hash;
 
 use crate::impl_system_param;
 use crate::prelude::*;
@@ -26,6 +28,8 @@ pub struct RapierContext {
     pub rigid_body_set: rapier::RigidBodySet,
     pub collider_shape_cache: ColliderShapeCache,
     pub collision_cache: CollisionCache,
+    /// The number of colliders whose positions were written to rapier in the last update.
+    pub synced_colliders: usize,
 }
 
 impl Clone for RapierContext {
@@ -41,6 +45,7 @@ impl Clone for RapierContext {
             rigid_body_set: self.rigid_body_set.clone(),
             collider_shape_cache: self.collider_shape_cache.clone(),
             collision_cache: self.collision_cache.clone(),
+            synced_colliders: self.synced_colliders,
         }
     }
 }
@@ -215,6 +220,9 @@ pub struct Collider {
     /// The handle to the Rapier rigid body associated to this collider, if one has been spawned as
     /// of yet.
     pub rapier_handle: Option<rapier::RigidBodyHandle>,
+    /// The translation, rotation, and `disabled` state last written to the rapier body, used to
+    /// skip colliders that haven't changed since the last update.
+    pub synced: Option<(Vec2, Quat, bool)>,
 }
 
 /// Component added to tiles that have been given corresponding rapier colliders.
@@ -253,10 +261,55 @@ pub enum TileCollisionKind {
     Empty,
     Solid,
     JumpThrough,
+    /// A solid tile that can be destroyed by bullets and explosions.
+    Breakable,
 }
 
 impl BonesBevyAssetLoad for TileCollisionKind {}
 
+impl TileCollisionKind {
+    /// Whether nothing can pass through the tile.
+    pub fn is_solid(self) -> bool {
+        matches!(self, Self::Solid | Self::Breakable)
+    }
+}
+
+/// The surface properties of a map tile, that change how bodies standing on it move.
+#[derive(Clone, Copy, Debug, PartialEq, TypeUlid, Serialize, Deserialize)]
+#[ulid = "01H4G7Q2W9VJ5DZK3XN8MTB6RE"]
+#[serde(default, deny_unknown_fields)]
+pub struct TileSurface {
+    /// Multiplies the friction of bodies standing on the tile. Ice has a very low friction.
+    pub friction: f32,
+    /// Whether the tile is sticky, which keeps players from walking, and makes them mash jump to
+    /// get free.
+    pub sticky: bool,
+}
+
+impl Default for TileSurface {
+    fn default() -> Self {
+        Self {
+            friction: 1.0,
+            sticky: false,
+        }
+    }
+}
+
+impl BonesBevyAssetLoad for TileSurface {}
+
+impl TileSurface {
+    /// Whether the surface behaves like a normal tile.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Get the per-frame velocity retention for a body with friction, given the default
+    /// [`PhysicsMeta::friction_lerp`].
+    pub fn friction_lerp(&self, default_friction_lerp: f32) -> f32 {
+        (1.0 - (1.0 - default_friction_lerp) * self.friction.max(0.0)).clamp(0.0, 1.0)
+    }
+}
+
 impl<'a> CollisionWorld<'a> {
     /// Updates the collision world with the entity's actual transforms.
     ///
@@ -285,12 +338,19 @@ impl<'a> CollisionWorld<'a> {
             ..
         } = &mut *self.ctx;
 
-        // Delete any bodies that don't have alive entities
+        // Delete any bodies that don't have alive entities, or that no longer belong to their
+        // entity's collider, like the bodies of pooled entities that have been reused.
         let mut to_delete = Vec::new();
         for (handle, body) in rigid_body_set.iter() {
             let entity = RapierUserData::entity(body.user_data);
-
-            if !self.entities.is_alive(entity) {
+            let is_current = self
+                .colliders
+                .get(entity)
+                .map(|collider| collider.rapier_handle == Some(handle))
+                .unwrap_or(false)
+                || self.tile_rapier_handles.get(entity).map(|x| **x) == Some(handle);
+
+            if !self.entities.is_alive(entity) || !is_current {
                 // Remove any collisions with the killed entity from the collision cache.
                 let mut collisions = collision_cache.collisions.borrow_mut();
                 let colliding_with = collisions.remove(&entity);
@@ -353,11 +413,26 @@ impl<'a> CollisionWorld<'a> {
             rigid_body_set,
             collider_set,
             collider_shape_cache,
+            synced_colliders,
             ..
         } = &mut *self.ctx;
+        *synced_colliders = 0;
         for (ent, (transform, collider)) in
             self.entities.iter_with((transforms, &mut self.colliders))
         {
+            // Skip colliders that haven't moved or been enabled or disabled since they were last
+            // synced, because writing the same position again wouldn't change anything.
+            let synced = (
+                transform.translation.truncate(),
+                transform.rotation,
+                collider.disabled,
+            );
+            if collider.rapier_handle.is_some() && collider.synced == Some(synced) {
+                continue;
+            }
+            collider.synced = Some(synced);
+            *synced_colliders += 1;
+
             // Get the rapier shape.
             //
             // TODO: Evaluate whether or not caching the colliders like this actually improves
@@ -391,7 +466,6 @@ impl<'a> CollisionWorld<'a> {
             );
             let rapier_collider = collider_set.get_mut(rapier_body.colliders()[0]).unwrap();
             rapier_collider.set_enabled(!collider.disabled);
-            rapier_collider.set_position_wrt_parent(rapier::Isometry::new(default(), 0.0));
         }
     }
 
@@ -408,6 +482,44 @@ impl<'a> CollisionWorld<'a> {
         self.update_tiles_with_filter(|idx, p| layer_idx == idx && pos == p);
     }
 
+    /// Remove the collider of the tile with the given layer index and map grid position, so that
+    /// nothing collides with the tile anymore.
+    ///
+    /// This doesn't remove the tile itself from its [`TileLayer`], which should be done afterward.
+    pub fn remove_tile(&mut self, layer_idx: usize, pos: UVec2) {
+        let Some(tile_ent) = self
+            .entities
+            .iter_with((&self.tile_layers, &self.spawned_map_layer_metas))
+            .find(|(_, (_, meta))| meta.layer_idx == layer_idx)
+            .and_then(|(_, (layer, _))| layer.get(pos)) else {
+            return;
+        };
+        let Some(handle) = self.tile_rapier_handles.get(tile_ent).map(|x| **x) else {
+            return;
+        };
+        self.tile_rapier_handles.remove(tile_ent);
+
+        let RapierContext {
+            rigid_body_set,
+            collider_set,
+            query_pipeline,
+            ..
+        } = &mut *self.ctx;
+        // Removing the body also removes its collider, which the broad phase will pick up the next
+        // time the collision pipeline is stepped.
+        rigid_body_set.remove(
+            handle,
+            &mut default(),
+            collider_set,
+            &mut default(),
+            &mut default(),
+            true,
+        );
+        // Update the query pipeline right away, so that queries for the rest of the frame don't
+        // collide with the removed tile.
+        query_pipeline.update(rigid_body_set, collider_set);
+    }
+
     /// Update the collisions for map tiles that pass the given filter.
     ///
     /// The filter is a function that takes the layer index and the tile position as an argument.
@@ -589,6 +701,10 @@ impl<'a> CollisionWorld<'a> {
                 rapier::QueryFilter::new().predicate(&|_handle, rapier_collider| {
                     let ent = RapierUserData::entity(rapier_collider.user_data);
 
+                    // Bodies that collide like tiles, like crates, shouldn't run into themselves
+                    if ent == entity {
+                        return false;
+                    }
                     let Some(tile_kind) = self.tile_collision_kinds.get(ent) else {
                         // Ignore non-tile collisions
                         return false;
@@ -720,6 +836,10 @@ impl<'a> CollisionWorld<'a> {
                     rapier::QueryFilter::new().predicate(&|_handle, rapier_collider| {
                         let ent = RapierUserData::entity(rapier_collider.user_data);
 
+                        // Bodies that collide like tiles shouldn't run into themselves
+                        if ent == entity {
+                            return false;
+                        }
                         let Some(tile_kind) = self.tile_collision_kinds.get(ent) else {
                         // Ignore non-tile collisions
                         return false;
@@ -805,7 +925,38 @@ impl<'a> CollisionWorld<'a> {
     /// > perfectly lined up along the edge of a tile, but `tile_collision_point` won't.
     #[allow(unused)]
     pub fn solid_at(&self, pos: Vec2) -> bool {
-        self.tile_collision_point(pos) == TileCollisionKind::Solid
+        self.tile_collision_point(pos).is_solid()
+    }
+
+    /// Returns whether there is a solid tile, or a solid collider that collides like a tile, such as
+    /// a moving platform, at the given point.
+    ///
+    /// Colliders are only checked if the `filter` returns `true` for their entity.
+    pub fn solid_at_filtered(&self, pos: Vec2, filter: impl Fn(Entity) -> bool) -> bool {
+        if self.solid_at(pos) {
+            return true;
+        }
+
+        let mut solid = false;
+        self.ctx.query_pipeline.intersections_with_point(
+            &self.ctx.rigid_body_set,
+            &self.ctx.collider_set,
+            &pos.to_array().into(),
+            rapier::QueryFilter::new().predicate(&|_handle, collider| {
+                let ent = RapierUserData::entity(collider.user_data);
+                self.tile_collision_kinds
+                    .get(ent)
+                    .map(|x| x.is_solid())
+                    .unwrap_or(false)
+                    && filter(ent)
+            }),
+            |_handle| {
+                solid = true;
+                // Stop at the first solid collider
+                false
+            },
+        );
+        solid
     }
 
     /// Returns the tile collision at the given point.
@@ -847,6 +998,19 @@ impl<'a> CollisionWorld<'a> {
         shape: ColliderShape,
         filter: impl Fn(Entity) -> bool,
     ) -> TileCollisionKind {
+        self.tile_entity_filtered(transform, shape, filter)
+            .and_then(|e| self.tile_collision_kinds.get(e).copied())
+            .unwrap_or_default()
+    }
+
+    /// Get the entity of the first tile with a collision detected colliding with the `shape` at the
+    /// given `transform`.
+    pub fn tile_entity_filtered(
+        &self,
+        transform: Transform,
+        shape: ColliderShape,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Option<Entity> {
         self.ctx
             .query_pipeline
             .intersection_with_shape(
@@ -864,8 +1028,6 @@ impl<'a> CollisionWorld<'a> {
                 }),
             )
             .map(|x| RapierUserData::entity(self.ctx.collider_set.get(x).unwrap().user_data))
-            .and_then(|e| self.tile_collision_kinds.get(e).copied())
-            .unwrap_or_default()
     }
 
     /// Get the collider for the given entity.
@@ -886,4 +1048,22 @@ mod test {
         let e2 = RapierUserData::entity(bits);
         assert_eq!(e1, e2);
     }
+
+    #[test]
+    fn tile_surface_friction() {
+        let normal = TileSurface::default();
+        assert_eq!(normal.friction_lerp(0.85), 0.85);
+
+        let ice = TileSurface {
+            friction: 0.1,
+            ..default()
+        };
+        assert!((ice.friction_lerp(0.8) - 0.98).abs() < 1e-6);
+
+        let glue = TileSurface {
+            friction: 100.0,
+            sticky: true,
+        };
+        assert_eq!(glue.friction_lerp(0.85), 0.0);
+    }
 }
@@ -0,0 +1,207 @@
+use super::*;
+
+/// The size of the spatial hash cells when there is no map to take the tile size from.
+const DEFAULT_CELL_SIZE: Vec2 = Vec2::splat(16.0);
+
+/// Entries that cover more cells than this are kept in a separate list that is checked by every
+/// query, so that huge regions, like the sudden death border, don't fill up the hash.
+const MAX_ENTRY_CELLS: i64 = 64;
+
+/// Resource containing a spatial hash of the bounding boxes of the damage regions and kinematic
+/// bodies, such as players and items, used to quickly find the ones in an area.
+///
+/// The hash is rebuilt from scratch each frame by [`update_spatial_hash`], with a cell the size of
+/// a map tile. Everything is stored in sorted lists instead of a hash map, so that queries return
+/// entities in the same order on every machine.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H4R2V7KX9MBD3NQF8TZ5WC1J"]
+pub struct SpatialHash {
+    cell_size: Vec2,
+    /// The entities in the hash, sorted by entity index, with their bounding boxes.
+    entries: Vec<(Entity, Rect)>,
+    /// The cell coordinates covered by each entry, with the entry index, sorted by cell.
+    cells: Vec<((i32, i32), usize)>,
+    /// The indexes of the entries that cover too many cells to be put in `cells`.
+    large_entries: Vec<usize>,
+}
+
+impl SpatialHash {
+    /// Replace the contents of the hash with the given entities and their bounding boxes.
+    pub fn rebuild(&mut self, cell_size: Vec2, entries: impl IntoIterator<Item = (Entity, Rect)>) {
+        self.cell_size = if cell_size.min_element() > 0.0 {
+            cell_size
+        } else {
+            DEFAULT_CELL_SIZE
+        };
+        self.entries.clear();
+        self.entries.extend(entries);
+        self.entries.sort_by_key(|(entity, _)| entity.index());
+        self.cells.clear();
+        self.large_entries.clear();
+
+        for (i, (_, rect)) in self.entries.iter().enumerate() {
+            let (min, max) = self.cell_range(rect);
+            let cell_count = (max.0 - min.0 + 1) as i64 * (max.1 - min.1 + 1) as i64;
+            if cell_count > MAX_ENTRY_CELLS {
+                self.large_entries.push(i);
+                continue;
+            }
+            for x in min.0..=max.0 {
+                for y in min.1..=max.1 {
+                    self.cells.push(((x, y), i));
+                }
+            }
+        }
+        self.cells.sort_unstable();
+    }
+
+    /// Get the entities whose bounding boxes overlap the given rectangle, sorted by entity index.
+    pub fn entities_overlapping(&self, rect: Rect) -> impl Iterator<Item = Entity> + '_ {
+        let mut candidates = self.large_entries.clone();
+        let (min, max) = self.cell_range(&rect);
+        if (max.0 - min.0 + 1) as i64 * (max.1 - min.1 + 1) as i64 > self.cells.len() as i64 {
+            // It's faster to check everything than to look up that many cells
+            candidates = (0..self.entries.len()).collect();
+        } else {
+            for x in min.0..=max.0 {
+                for y in min.1..=max.1 {
+                    let start = self.cells.partition_point(|(cell, _)| *cell < (x, y));
+                    candidates.extend(
+                        self.cells[start..]
+                            .iter()
+                            .take_while(|(cell, _)| *cell == (x, y))
+                            .map(|(_, i)| *i),
+                    );
+                }
+            }
+            candidates.sort_unstable();
+            candidates.dedup();
+        }
+
+        candidates.into_iter().filter_map(move |i| {
+            let (entity, entry_rect) = self.entries[i];
+            entry_rect.overlaps(&rect).then_some(entity)
+        })
+    }
+
+    /// Get the range of cells covered by a rectangle.
+    fn cell_range(&self, rect: &Rect) -> ((i32, i32), (i32, i32)) {
+        let min = (rect.min / self.cell_size).floor();
+        let max = (rect.max / self.cell_size).floor();
+        (
+            (min.x as i32, min.y as i32),
+            (max.x.max(min.x) as i32, max.y.max(min.y) as i32),
+        )
+    }
+}
+
+/// Rebuild the [`SpatialHash`] from the current positions of the damage regions and kinematic
+/// bodies.
+pub fn update_spatial_hash(
+    entities: Res<Entities>,
+    map_meta: Res<SpawnedMapMeta>,
+    mut spatial_hash: ResMut<SpatialHash>,
+    transforms: Comp<Transform>,
+    bodies: Comp<KinematicBody>,
+    damage_regions: Comp<DamageRegion>,
+) {
+    puffin::profile_function!();
+
+    let regions = entities
+        .iter_with((&damage_regions, &transforms))
+        .map(|(ent, (region, transform))| (ent, region.collider_rect(transform.translation)));
+    let bodies = entities
+        .iter_with((&bodies, &transforms))
+        .filter(|(ent, _)| !damage_regions.contains(*ent))
+        .map(|(ent, (body, transform))| (ent, body.bounding_box(*transform)));
+    spatial_hash.rebuild(map_meta.tile_size, regions.chain(bodies));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(index: u32) -> Entity {
+        Entity::new(index, 0)
+    }
+
+    #[test]
+    fn finds_overlapping_entities_in_index_order() {
+        let mut hash = SpatialHash::default();
+        hash.rebuild(
+            Vec2::splat(10.0),
+            [
+                (entity(3), Rect::new(5.0, 5.0, 4.0, 4.0)),
+                (entity(1), Rect::new(12.0, 5.0, 20.0, 4.0)),
+                (entity(2), Rect::new(100.0, 100.0, 4.0, 4.0)),
+                // Covers too many cells to be put in them
+                (entity(0), Rect::new(0.0, 0.0, 1000.0, 1000.0)),
+            ],
+        );
+
+        let found = hash
+            .entities_overlapping(Rect::new(8.0, 5.0, 4.0, 4.0))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![entity(0), entity(1), entity(3)]);
+
+        let found = hash
+            .entities_overlapping(Rect::new(-300.0, 100.0, 2.0, 2.0))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![entity(0)]);
+    }
+
+    /// Compares the spatial hash with checking every damage region for every player.
+    ///
+    /// Run with `cargo test --release -p jumpy_core -- --ignored --nocapture spatial_hash`.
+    #[test]
+    #[ignore]
+    fn bench_spatial_hash_vs_nested_loops() {
+        use std::time::Instant;
+
+        const FRAMES: usize = 10_000;
+        let tile_size = Vec2::splat(16.0);
+        let rng = crate::random::AtomicRng::with_seed(1);
+        let regions = (0..200)
+            .map(|i| {
+                let position = vec2(rng.f32() * 800.0, rng.f32() * 600.0);
+                (entity(i), Rect::new(position.x, position.y, 24.0, 24.0))
+            })
+            .collect::<Vec<_>>();
+        let players = (0..4)
+            .map(|i| Rect::new(i as f32 * 200.0, 300.0, 32.0, 32.0))
+            .collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let mut nested_hits = 0;
+        for _ in 0..FRAMES {
+            for player in &players {
+                nested_hits += regions
+                    .iter()
+                    .filter(|(_, region)| std::hint::black_box(player).overlaps(region))
+                    .count();
+            }
+        }
+        let nested = start.elapsed();
+
+        let start = Instant::now();
+        let mut hash = SpatialHash::default();
+        let mut hash_hits = 0;
+        for _ in 0..FRAMES {
+            hash.rebuild(tile_size, regions.iter().copied());
+            for player in &players {
+                hash_hits += hash
+                    .entities_overlapping(*std::hint::black_box(player))
+                    .count();
+            }
+        }
+        let hashed = start.elapsed();
+
+        assert_eq!(nested_hits, hash_hits);
+        println!(
+            "200 regions, 4 players, {FRAMES} frames: nested loops {:?}/frame, spatial hash \
+            {:?}/frame ( including rebuild )",
+            nested / FRAMES as u32,
+            hashed / FRAMES as u32,
+        );
+    }
+}
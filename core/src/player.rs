@@ -12,6 +12,8 @@ use crate::{
 mod state;
 use bones_lib::animation::AnimationBankSprite;
 pub use state::*;
+mod status;
+pub use status::*;
 use turborand::GenCore;
 
 const PLAYER_COLORS: [Color; 4] = [
@@ -23,16 +25,20 @@ const PLAYER_COLORS: [Color; 4] = [
 
 pub fn install(session: &mut CoreSession) {
     state::install(session);
+    status::install(session);
 
     // Add other player systems
     session
         .stages
         .add_system_to_stage(CoreStage::First, hydrate_players)
         .add_system_to_stage(CoreStage::First, player_ai_system)
+        .add_system_to_stage(CoreStage::First, player_ai_items_system)
+        .add_system_to_stage(CoreStage::PostUpdate, fall_back_missing_body_animations)
         .add_system_to_stage(CoreStage::PostUpdate, play_itemless_fin_animations)
         .add_system_to_stage(CoreStage::PostUpdate, player_facial_animations)
-        .add_system_to_stage(CoreStage::Last, delete_dead_ai_swords)
         .add_system_to_stage(CoreStage::Last, update_player_layers);
+
+    session.inspectors.register::<PlayerIdx>("PlayerIdx");
 }
 
 /// The player index, for example Player 1, Player 2, and so on.
@@ -40,6 +46,12 @@ pub fn install(session: &mut CoreSession) {
 #[ulid = "01GP49B2AMTYB6W8DWKBRF27FT"]
 pub struct PlayerIdx(pub usize);
 
+impl Inspect for PlayerIdx {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("index", self.0);
+    }
+}
+
 /// Contains the entities of the extra player layers, such as the player face and fin.
 #[derive(Clone, TypeUlid)]
 #[ulid = "01GQQRZ4V5WSRJTA1VTA816Z9T"]
@@ -104,6 +116,8 @@ pub enum Emote {
 }
 
 impl Emote {
+    pub const ALL: [Self; 1] = [Self::Alarm];
+
     pub fn animation_key(&self) -> Key {
         match self {
             Emote::Alarm => key!("emote_alarm"),
@@ -129,11 +143,45 @@ impl PlayerCommand {
     ///
     /// > **Note:** This doesn't despawn the player, it just puts the player into it's death animation.
     pub fn kill(player: Entity, hit_from: Option<Vec2>) -> System {
+        Self::kill_credited(player, hit_from, None, false)
+    }
+    /// Kill a player with something in the environment, like spikes or falling off of the map.
+    ///
+    /// The kill is credited to the player that hit them last, if they were hit recently enough.
+    /// See [`LastHitBy`].
+    pub fn kill_environmental(player: Entity, hit_from: Option<Vec2>) -> System {
+        Self::kill_credited(player, hit_from, None, true)
+    }
+    /// Kill a player, crediting the kill to the `killer` player in the [`MatchScore`].
+    ///
+    /// `weapon` is the item the killer used, if there was one.
+    pub fn kill_by(
+        player: Entity,
+        hit_from: Option<Vec2>,
+        killer: Entity,
+        weapon: Option<Entity>,
+    ) -> System {
+        Self::kill_credited(player, hit_from, Some((killer, weapon)), false)
+    }
+    fn kill_credited(
+        player: Entity,
+        hit_from: Option<Vec2>,
+        credit: Option<(Entity, Option<Entity>)>,
+        credit_last_hit: bool,
+    ) -> System {
         (move |entities: Res<Entities>,
+               frame: Res<SessionFrame>,
+               last_hits: Comp<LastHitBy>,
                mut players_killed: CompMut<PlayerKilled>,
                mut items_dropped: CompMut<ItemDropped>,
                mut inventories: CompMut<Inventory>,
-               player_indexes: Comp<PlayerIdx>| {
+               player_indexes: Comp<PlayerIdx>,
+               element_handles: Comp<ElementHandle>,
+               element_assets: BevyAssets<ElementMeta>,
+               core_meta: Res<CoreMetaArc>,
+               mut score: ResMut<MatchScore>,
+               mut camera_trauma: ResMut<CameraTrauma>,
+               mut hit_pause: ResMut<HitPause>| {
             if players_killed.contains(player) {
                 // No need to kill him again
                 return;
@@ -147,6 +195,35 @@ impl PlayerCommand {
 
             debug!("Killing player: {}", idx.0);
 
+            // Record the death, unless the match is already over
+            if score.result.is_none() {
+                // Environmental deaths go to the player that hit them last, if it was recent
+                let last_hitter = last_hits
+                    .get(player)
+                    .filter(|_| credit_last_hit)
+                    .filter(|hit| {
+                        (**frame as u32).saturating_sub(hit.frame)
+                            <= core_meta.config.kill_attribution_frames
+                    })
+                    .map(|hit| hit.player);
+                let killer = credit
+                    .and_then(|(killer, _)| player_indexes.get(killer))
+                    .map(|killer| killer.0)
+                    .or(last_hitter)
+                    .filter(|killer| *killer != idx.0);
+                let weapon = killer
+                    .and(credit)
+                    .and_then(|(_, weapon)| weapon)
+                    .and_then(|weapon| element_handles.get(weapon))
+                    .and_then(|handle| element_assets.get(&handle.get_bevy_handle()))
+                    .map(|meta| meta.name.clone());
+                score.kills.push(KillRecord {
+                    victim: idx.0,
+                    killer,
+                    weapon,
+                });
+            }
+
             // Drop any items the player was carrying
             let inventory = inventories.get(player).cloned().unwrap_or_default();
             if let Some(item) = inventory.0 {
@@ -159,6 +236,9 @@ impl PlayerCommand {
             inventories.insert(player, Inventory(None));
 
             players_killed.insert(player, PlayerKilled { hit_from });
+
+            camera_trauma.add(core_meta.camera.kill_trauma);
+            hit_pause.start(core_meta.camera.kill_hit_pause_frames);
         })
         .system()
     }
@@ -225,11 +305,41 @@ impl PlayerCommand {
         })
         .system()
     }
+    /// Apply a status effect to a player.
+    pub fn apply_status(player: Entity, effect: StatusEffect) -> System {
+        (move |player_indexes: Comp<PlayerIdx>,
+               players_killed: Comp<PlayerKilled>,
+               mut status_effects: CompMut<StatusEffects>| {
+            if !player_indexes.contains(player) || players_killed.contains(player) {
+                return;
+            }
+
+            if let Some(effects) = status_effects.get_mut(player) {
+                effects.apply(effect);
+            } else {
+                let mut effects = StatusEffects::default();
+                effects.apply(effect);
+                status_effects.insert(player, effects);
+            }
+        })
+        .system()
+    }
     /// Have the player use the item they are carrying, if any.
+    ///
+    /// Items with [`ItemAmmo`] are only used if they have enough ammo left.
     pub fn use_item(player: Entity) -> System {
-        (move |mut items_used: CompMut<ItemUsed>, inventories: CompMut<Inventory>| {
+        (move |mut items_used: CompMut<ItemUsed>,
+               mut item_ammo: CompMut<ItemAmmo>,
+               inventories: CompMut<Inventory>| {
             // If the player has an item
             if let Some(item) = inventories.get(player).and_then(|x| x.0) {
+                // That has ammo, if it needs it
+                if let Some(ammo) = item_ammo.get_mut(item) {
+                    if !ammo.use_ammo() {
+                        return;
+                    }
+                }
+
                 // Use it
                 items_used.insert(item, ItemUsed);
             }
@@ -238,9 +348,82 @@ impl PlayerCommand {
     }
 }
 
+/// How skilled an AI player is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AiDifficulty {
+    /// Wanders after a random opponent, takes frequent pauses, and has poor aim.
+    Easy,
+    /// Chases the nearest opponent, and looks further away for items when empty-handed.
+    #[default]
+    Normal,
+    /// Like [`Normal`][Self::Normal], but also looks ahead to jump over gaps and walls, and hardly
+    /// ever misses.
+    Hard,
+}
+
+impl AiDifficulty {
+    /// All of the difficulties, in order from easiest to hardest.
+    pub const ALL: [AiDifficulty; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    /// Get the next harder difficulty, wrapping around to [`Easy`][Self::Easy].
+    pub fn next(self) -> Self {
+        match self {
+            Self::Easy => Self::Normal,
+            Self::Normal => Self::Hard,
+            Self::Hard => Self::Easy,
+        }
+    }
+
+    /// Get the next easier difficulty, wrapping around to [`Hard`][Self::Hard].
+    pub fn prev(self) -> Self {
+        match self {
+            Self::Easy => Self::Hard,
+            Self::Normal => Self::Easy,
+            Self::Hard => Self::Normal,
+        }
+    }
+
+    /// The localization key for the difficulty's display name.
+    pub fn localization_key(&self) -> &'static str {
+        match self {
+            Self::Easy => "ai-difficulty-easy",
+            Self::Normal => "ai-difficulty-normal",
+            Self::Hard => "ai-difficulty-hard",
+        }
+    }
+
+    /// The chance that the AI will decide to take a pause every AI tick.
+    fn pause_chance(&self) -> f64 {
+        match self {
+            Self::Easy => 0.4,
+            Self::Normal => 0.25,
+            Self::Hard => 0.1,
+        }
+    }
+
+    /// The most that the AI misjudges the position of its target by when aiming, in pixels.
+    fn aim_error(&self) -> f32 {
+        match self {
+            Self::Easy => 128.0,
+            Self::Normal => 16.0,
+            Self::Hard => 4.0,
+        }
+    }
+
+    /// How far away the AI looks for items to pick up when it is empty-handed, in pixels.
+    fn item_search_distance(&self) -> f32 {
+        match self {
+            Self::Easy => 128.0,
+            Self::Normal | Self::Hard => 384.0,
+        }
+    }
+}
+
 #[derive(Clone, Debug, TypeUlid)]
 #[ulid = "01GQWND0P969BCZF5JET9MY944"]
 pub struct AiPlayer {
+    /// How skilled the AI is.
+    difficulty: AiDifficulty,
     /// Tick timer that is used for AI pausing logic.
     tick: Timer,
     /// Indicates the player is taking pause for the given number of ticks.
@@ -249,17 +432,45 @@ pub struct AiPlayer {
     movement_buffer: Option<VecDeque<PlayerControl>>,
     /// The player that the AI is targeting.
     target_player: Option<Entity>,
+    /// The item that the AI is going to pick up, which it heads for instead of its target player.
+    target_item: Option<Entity>,
+    /// How far off the AI is about where its target is, sampled again every tick.
+    aim_error: Option<Vec2>,
+    /// How many frames ago the AI lit the item that it is about to throw.
+    lit_frames: Option<u32>,
+    /// Whether the AI pressed shoot last frame, so that it lets go between presses.
+    shooting: bool,
+    /// Whether the AI pressed grab last frame, so that it lets go between presses.
+    grabbing: bool,
 }
 
 impl Default for AiPlayer {
     fn default() -> Self {
+        Self::new(default())
+    }
+}
+
+impl AiPlayer {
+    /// Create a new AI player with the given difficulty.
+    pub fn new(difficulty: AiDifficulty) -> Self {
         Self {
+            difficulty,
             tick: Timer::from_seconds(0.5, TimerMode::Repeating),
             pausing: 0,
             movement_buffer: Default::default(),
             target_player: Default::default(),
+            target_item: Default::default(),
+            aim_error: Default::default(),
+            lit_frames: Default::default(),
+            shooting: false,
+            grabbing: false,
         }
     }
+
+    /// Get the AI's difficulty.
+    pub fn difficulty(&self) -> AiDifficulty {
+        self.difficulty
+    }
 }
 
 #[derive(Debug, TypeUlid, Clone)]
@@ -301,28 +512,37 @@ fn player_ai_system(
     mut player_inputs: ResMut<PlayerInputs>,
     mut ai_players: CompMut<AiPlayer>,
     player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
     map: Res<LoadedMap>,
     transforms: Comp<Transform>,
     pathfinding_debug_line: ResMut<PathfindingDebugLines>,
     mut paths: CompMut<Path2d>,
     bodies: Comp<KinematicBody>,
+    collision_world: CollisionWorld,
     debug_settings: Res<DebugSettings>,
     rng: Res<GlobalRng>,
     time: Res<Time>,
 ) {
-    const SWORD_SWING_DIST: f32 = 10.0;
     const AI_SPEED_MULTIPLIER: f32 = 0.65;
 
     for (ai_ent, (player_idx, transform, ai_player)) in
         entities.iter_with((&player_indexes, &transforms, &mut ai_players))
     {
+        let difficulty = ai_player.difficulty;
+
         // Tick the AI timer
         ai_player.tick.tick(time.delta());
 
+        // Misjudge where the target is by a new amount every tick, based on the difficulty
+        if ai_player.tick.just_finished() || ai_player.aim_error.is_none() {
+            ai_player.aim_error =
+                Some(vec2(rng.f32_normalized(), rng.f32_normalized()) * difficulty.aim_error());
+        }
+
         // If a tick has elapsed
         if ai_player.tick.just_finished() {
-            // If the player isn't pausing, then there's a 40% chance
-            if ai_player.pausing == 0 && rng.chance(0.4) {
+            // If the player isn't pausing, then there's a chance, based on the difficulty,
+            if ai_player.pausing == 0 && rng.chance(difficulty.pause_chance()) {
                 // That we will pause for a random number of ticks between 0 and 2
                 ai_player.pausing = (rng.f32_normalized() * 2.0).round() as u32
             }
@@ -339,29 +559,57 @@ fn player_ai_system(
             continue;
         }
 
-        let target_transform = match ai_player.target_player {
-            Some(target_player) if transforms.contains(target_player) => {
-                transforms.get(target_player).unwrap()
-            }
-            _ => {
-                let players = entities
+        let ai_pos = transform.translation.truncate();
+
+        // Head for the item we're picking up, if we are picking one up.
+        let target_item_pos = ai_player
+            .target_item
+            .and_then(|item| transforms.get(item))
+            .map(|transform| transform.translation.truncate());
+        let target_pos = match (target_item_pos, difficulty) {
+            (Some(item_pos), _) => item_pos,
+            // Easy AI picks a random player and sticks with them.
+            (None, AiDifficulty::Easy) => match ai_player.target_player {
+                Some(target_player) if transforms.contains(target_player) => transforms
+                    .get(target_player)
+                    .unwrap()
+                    .translation
+                    .truncate(),
+                _ => {
+                    let players = entities
+                        .iter_with((&player_indexes, &transforms))
+                        .filter(|(ent, _)| *ent != ai_ent)
+                        .collect::<Vec<_>>();
+                    if players.is_empty() {
+                        continue;
+                    }
+
+                    let (target_player, (_, transform)) = players[rng.gen_usize() % players.len()];
+
+                    ai_player.target_player = Some(target_player);
+                    transform.translation.truncate()
+                }
+            },
+            // Smarter AI goes after the closest living player.
+            (None, AiDifficulty::Normal | AiDifficulty::Hard) => {
+                let nearest_player = entities
                     .iter_with((&player_indexes, &transforms))
-                    .filter(|(ent, _)| *ent != ai_ent)
-                    .collect::<Vec<_>>();
-                if players.is_empty() {
+                    .filter(|(ent, _)| *ent != ai_ent && !players_killed.contains(*ent))
+                    .map(|(ent, (_, transform))| (ent, transform.translation.truncate()))
+                    .min_by(|(_, a), (_, b)| {
+                        a.distance_squared(ai_pos)
+                            .total_cmp(&b.distance_squared(ai_pos))
+                    });
+                let Some((target_player, target_pos)) = nearest_player else {
                     continue;
-                }
-
-                let (target_player, (_, transform)) = players[rng.gen_usize() % players.len()];
+                };
 
                 ai_player.target_player = Some(target_player);
-                transform
+                target_pos
             }
         };
-        let target_pos = target_transform.translation.truncate();
         let tile = (target_pos / map.tile_size).floor().as_ivec2();
         let target_node = NavNode(tile);
-        let ai_pos = transform.translation.truncate();
         let tile = (ai_pos / map.tile_size).floor().as_ivec2();
         let current_node = NavNode(tile);
 
@@ -408,31 +656,42 @@ fn player_ai_system(
                 // Slow down the AI movement according to the fixed multiplier
                 first_movement.move_direction *= vec2(AI_SPEED_MULTIPLIER, 1.0);
 
+                let body = bodies.get(ai_ent).unwrap();
+
                 // This is a hack to prevent us from getting stuck when we think we should be falling
                 // straight down and we actually need to move off of the block we're half-standing on.
                 //
                 // If we aren't moving at all, just move in the direction of player 1
-                if bodies.get(ai_ent).unwrap().velocity == Vec2::ZERO
-                    && first_movement.move_direction == Vec2::ZERO
-                {
+                if body.velocity == Vec2::ZERO && first_movement.move_direction == Vec2::ZERO {
                     let sign = (path.get(2).unwrap_or(&next_node).x as f32 * map.tile_size.x
                         - transform.translation.x)
                         .signum();
                     first_movement.move_direction.x = sign;
                 }
 
+                // Hard AI looks ahead and jumps over gaps and walls that the nav graph edge didn't
+                // plan a jump for.
+                if difficulty == AiDifficulty::Hard
+                    && body.is_on_ground
+                    && !first_movement.jump_pressed
+                    && first_movement.move_direction.x != 0.0
+                    && ai_should_jump_ahead(
+                        &collision_world,
+                        body.bounding_box(*transform),
+                        first_movement.move_direction.x.signum(),
+                        map.tile_size,
+                        target_pos,
+                    )
+                {
+                    first_movement.jump_pressed = true;
+                    first_movement.jump_just_pressed = true;
+                }
+
                 player_inputs.players[player_idx.0].control = first_movement;
                 if !movement_buffer.is_empty() {
                     ai_player.movement_buffer = Some(movement_buffer)
                 }
             }
-
-            if (target_pos - ai_pos).length() < SWORD_SWING_DIST {
-                player_inputs.players[player_idx.0]
-                    .control
-                    .shoot_just_pressed = true;
-                player_inputs.players[player_idx.0].control.shoot_pressed = true;
-            }
         } else if debug_settings.show_pathfinding_lines {
             let pos =
                 current_node.0.as_vec2() * map.tile_size + map.tile_size / 2.0 - vec2(0.0, 4.0);
@@ -453,8 +712,346 @@ fn player_ai_system(
     }
 }
 
+/// Samples the map tiles just ahead of an AI player's body to see whether it is about to walk into
+/// a wall or off of a ledge, and should jump instead.
+fn ai_should_jump_ahead(
+    collision_world: &CollisionWorld,
+    body_rect: Rect,
+    direction: f32,
+    tile_size: Vec2,
+    target_pos: Vec2,
+) -> bool {
+    let probe = ColliderShape::Rectangle {
+        size: Vec2::splat(2.0),
+    };
+    let tile_at = |pos: Vec2| {
+        collision_world.tile_collision(Transform::from_translation(pos.extend(0.0)), probe)
+    };
+
+    let center = body_rect.center();
+    let ahead_x = center.x + direction * (body_rect.width() / 2.0 + tile_size.x / 2.0);
+
+    let wall_ahead = tile_at(vec2(ahead_x, center.y)).is_solid();
+
+    // Walking off a ledge is fine if that's where we're trying to go.
+    let target_is_below = target_pos.y < body_rect.min.y - tile_size.y;
+    let floor_y = body_rect.min.y - tile_size.y / 2.0;
+    let gap_ahead = !target_is_below && tile_at(vec2(ahead_x, floor_y)) == TileCollisionKind::Empty;
+
+    wall_ahead || gap_ahead
+}
+
+/// How an AI player uses the item that it is holding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AiItemUse {
+    /// Shoot at opponents that are lined up with the player, within the range of its bullets.
+    Ranged { range: f32 },
+    /// Swing at opponents right in front of the player.
+    Melee,
+    /// Throw the item ahead of where the opponent is going.
+    Throw {
+        /// Whether the item has to be used, to light it, before it is thrown.
+        light_first: bool,
+        /// Whether using the item throws it, instead of dropping it.
+        thrown_by_use: bool,
+    },
+}
+
+impl Default for AiItemUse {
+    /// Any item can be thrown at somebody.
+    fn default() -> Self {
+        Self::Throw {
+            light_first: false,
+            thrown_by_use: false,
+        }
+    }
+}
+
+impl AiItemUse {
+    /// Work out how to use an item from its element metadata.
+    fn new(element_meta: &ElementMeta, bullet_assets: &BevyAssets<BulletMeta>) -> Self {
+        match &element_meta.builtin {
+            BuiltinElementKind::Musket { bullet_meta, .. } => Self::Ranged {
+                range: bullet_assets
+                    .get(&bullet_meta.get_bevy_handle())
+                    .map(|bullet| bullet.velocity.length() * bullet.lifetime * crate::FPS)
+                    .unwrap_or_default(),
+            },
+            BuiltinElementKind::Sword { .. } => Self::Melee,
+            BuiltinElementKind::Grenade { .. } | BuiltinElementKind::KickBomb { .. } => {
+                Self::Throw {
+                    light_first: true,
+                    thrown_by_use: false,
+                }
+            }
+            BuiltinElementKind::Mine { .. } => Self::Throw {
+                light_first: false,
+                thrown_by_use: true,
+            },
+            _ => default(),
+        }
+    }
+}
+
+/// The kinds of throws that the AI picks from, with the direction that it holds for each of them
+/// when facing right.
+const AI_THROWS: [(ThrowKind, Vec2); 3] = [
+    (ThrowKind::Normal, Vec2::ZERO),
+    (ThrowKind::Fast, Vec2::X),
+    (ThrowKind::Lob, Vec2::new(1.0, 0.5)),
+];
+
+/// Work out how far an item thrown to the right with `velocity` lands from `offset`, or `None` if
+/// it can't get as high as `offset`.
+fn ai_throw_miss(velocity: Vec2, gravity: f32, offset: Vec2) -> Option<f32> {
+    if gravity <= 0.0 {
+        return None;
+    }
+    let discriminant = velocity.y * velocity.y - 2.0 * gravity * offset.y;
+    if discriminant < 0.0 {
+        return None;
+    }
+    // The frame that the item comes back down to the height of the offset
+    let frames = (velocity.y + discriminant.sqrt()) / gravity;
+    Some((velocity.x * frames - offset.x).abs())
+}
+
+/// Has the AI players pick up items, use them on their target, and drop them when they are empty.
+///
+/// This runs after [`player_ai_system()`], adding the buttons to the movement that it picked, and
+/// picking the item that it heads for next.
+fn player_ai_items_system(
+    entities: Res<Entities>,
+    mut player_inputs: ResMut<PlayerInputs>,
+    mut ai_players: CompMut<AiPlayer>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+    inventories: Comp<Inventory>,
+    items: Comp<Item>,
+    transforms: Comp<Transform>,
+    bodies: Comp<KinematicBody>,
+    sprites: Comp<AtlasSprite>,
+    spatial_hash: Res<SpatialHash>,
+    thrown_by: Comp<ItemThrownBy>,
+    frame: Res<SessionFrame>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    bullet_assets: BevyAssets<BulletMeta>,
+    item_throws: Comp<ItemThrow>,
+    muskets: Comp<musket::Musket>,
+    item_ammo: Comp<ItemAmmo>,
+) {
+    const MELEE_REACH: f32 = 40.0;
+    const MELEE_HEIGHT: f32 = 32.0;
+    /// How far above or below the AI its target may look to be for it to shoot.
+    const AIM_HEIGHT_BAND: f32 = 8.0;
+    /// How close to its target a throw must look like it will land for the AI to make it.
+    const THROW_TOLERANCE: f32 = 24.0;
+    /// How long the AI holds on to a lit item, waiting for a good throw, before throwing it anyway.
+    const MAX_LIT_FRAMES: u32 = 30;
+    /// How long the AI stays away from the items it threw, which may be about to explode.
+    const THROWN_ITEM_FRAMES: u64 = 180;
+
+    // Collect the items that are already in somebody's inventory, so the AI doesn't go after them.
+    let held_items = entities
+        .iter_with(&inventories)
+        .filter_map(|(_, inventory)| inventory.0)
+        .collect::<Vec<_>>();
+    // Whether an item is out of ammo, and won't be any use until it is dropped or reloaded.
+    let is_empty = |item: Entity| {
+        muskets.get(item).map_or(false, |musket| musket.ammo == 0)
+            || item_ammo.get(item).map_or(false, |ammo| {
+                !ammo.can_use() && ammo.meta.when_empty == AmmoEmptyBehavior::Inert
+            })
+    };
+
+    for (ai_ent, (player_idx, transform, ai_player)) in
+        entities.iter_with((&player_indexes, &transforms, &mut ai_players))
+    {
+        if ai_player.pausing > 0 || players_killed.contains(ai_ent) {
+            continue;
+        }
+        let Some(body) = bodies.get(ai_ent) else {
+            continue;
+        };
+        let ai_rect = body.bounding_box(*transform);
+        let ai_pos = transform.translation.truncate();
+        let aim_error = ai_player.aim_error.unwrap_or_default();
+        let facing_left = sprites.get(ai_ent).map_or(false, |sprite| sprite.flip_x);
+        let is_facing = |offset: Vec2| (offset.x < 0.0) == facing_left;
+        let control = &mut player_inputs.players[player_idx.0].control;
+
+        let mut shoot = false;
+        let mut light = false;
+        let mut grab = false;
+        match inventories.get(ai_ent).and_then(|inventory| inventory.0) {
+            // Look for an item to pick up
+            None => {
+                ai_player.lit_frames = None;
+
+                let distance = ai_player.difficulty.item_search_distance();
+                let search_rect = Rect {
+                    min: ai_rect.min - distance,
+                    max: ai_rect.max + distance,
+                };
+                let nearby_items = grabbable_items(
+                    ai_ent,
+                    search_rect,
+                    &spatial_hash,
+                    &items,
+                    &held_items,
+                    &thrown_by,
+                    **frame,
+                    &transforms,
+                )
+                .into_iter()
+                .filter(|(item, _)| !is_empty(*item))
+                .filter(|(item, _)| {
+                    !thrown_by.get(*item).map_or(false, |thrown| {
+                        thrown.player == ai_ent && **frame < thrown.frame + THROWN_ITEM_FRAMES
+                    })
+                });
+                ai_player.target_item = choose_item_to_grab(ai_rect.center(), nearby_items);
+
+                // Grab the item once we are touching it, finding it the same way the grab does
+                if let Some(target_item) = ai_player.target_item {
+                    grab = grabbable_items(
+                        ai_ent,
+                        ai_rect,
+                        &spatial_hash,
+                        &items,
+                        &held_items,
+                        &thrown_by,
+                        **frame,
+                        &transforms,
+                    )
+                    .iter()
+                    .any(|(item, _)| *item == target_item);
+                }
+            }
+            // Drop empty weapons
+            Some(item) if is_empty(item) => {
+                ai_player.target_item = None;
+                grab = true;
+            }
+            Some(item) => {
+                ai_player.target_item = None;
+                ai_player.lit_frames = ai_player.lit_frames.map(|frames| frames + 1);
+
+                let element_meta = element_handles
+                    .get(item)
+                    .and_then(|handle| element_assets.get(&handle.get_bevy_handle()));
+                let item_use = element_meta
+                    .map(|meta| AiItemUse::new(meta, &bullet_assets))
+                    .unwrap_or_default();
+                let target = ai_player
+                    .target_player
+                    .filter(|target| !players_killed.contains(*target))
+                    .and_then(|target| {
+                        Some((
+                            transforms.get(target)?.translation.truncate(),
+                            bodies.get(target)?.velocity,
+                        ))
+                    });
+
+                match (item_use, target) {
+                    (AiItemUse::Ranged { range }, Some((target_pos, _))) => {
+                        let offset = target_pos + aim_error - ai_pos;
+                        if offset.x.abs() <= range && offset.y.abs() <= AIM_HEIGHT_BAND {
+                            // Stop to take the shot, turning around first if we have to
+                            if is_facing(offset) {
+                                control.move_direction = Vec2::ZERO;
+                                shoot = true;
+                            } else {
+                                control.move_direction = vec2(offset.x.signum(), 0.0);
+                            }
+                        }
+                    }
+                    (AiItemUse::Melee, Some((target_pos, _))) => {
+                        let offset = target_pos - ai_pos;
+                        if offset.x.abs() <= MELEE_REACH && offset.y.abs() <= MELEE_HEIGHT {
+                            if is_facing(offset) {
+                                shoot = true;
+                            } else {
+                                control.move_direction = vec2(offset.x.signum(), 0.0);
+                            }
+                        }
+                    }
+                    (
+                        AiItemUse::Throw {
+                            light_first,
+                            thrown_by_use,
+                        },
+                        target,
+                    ) => {
+                        let gravity = bodies.get(item).map_or(0.0, |body| body.gravity);
+                        let throw_overrides = element_meta.map(|meta| &meta.throw);
+                        let best_throw = target.and_then(|(target_pos, target_velocity)| {
+                            let offset = target_pos + aim_error - ai_pos;
+                            let direction = offset.x.signum();
+                            AI_THROWS
+                                .iter()
+                                // Throwing without holding a direction throws the way we face
+                                .filter(|(kind, _)| *kind != ThrowKind::Normal || is_facing(offset))
+                                .filter_map(|(kind, hold)| {
+                                    let velocity = item_throws
+                                        .get(item)?
+                                        .velocity_with_overrides(*kind, throw_overrides);
+                                    if velocity.x <= 0.0 {
+                                        return None;
+                                    }
+                                    // Lead the target by where it will be when the item gets there
+                                    let lead =
+                                        offset + target_velocity * offset.x.abs() / velocity.x;
+                                    let miss = ai_throw_miss(
+                                        velocity,
+                                        gravity,
+                                        vec2(lead.x * direction, lead.y),
+                                    )?;
+                                    Some((vec2(hold.x * direction, hold.y), miss))
+                                })
+                                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                        });
+                        let good_throw = best_throw.filter(|(_, miss)| *miss <= THROW_TOLERANCE);
+
+                        if light_first && ai_player.lit_frames.is_none() {
+                            // Light the item once there is a good throw to make with it
+                            light = good_throw.is_some();
+                        } else if good_throw.is_some()
+                            || ai_player.lit_frames >= Some(MAX_LIT_FRAMES)
+                        {
+                            control.move_direction = best_throw.map_or(
+                                vec2(if facing_left { -1.0 } else { 1.0 }, 0.0),
+                                |(hold, _)| hold,
+                            );
+                            if thrown_by_use {
+                                shoot = true;
+                            } else {
+                                grab = true;
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        // Let go of the buttons between presses, so that holding them isn't ignored
+        let shoot = (shoot || light) && !ai_player.shooting;
+        let grab = grab && !ai_player.grabbing;
+        if shoot && light {
+            ai_player.lit_frames = Some(0);
+        }
+        control.shoot_pressed = shoot;
+        control.shoot_just_pressed = shoot;
+        control.grab_pressed = grab;
+        control.grab_just_pressed = grab;
+        ai_player.shooting = shoot;
+        ai_player.grabbing = grab;
+    }
+}
+
 fn hydrate_players(
-    mut commands: Commands,
     mut entities: ResMut<Entities>,
     player_inputs: Res<PlayerInputs>,
     player_indexes: Comp<PlayerIdx>,
@@ -484,6 +1081,7 @@ fn hydrate_players(
         let player_idx = player_indexes.get(player_entity).unwrap();
         let player_handle = &player_inputs.players[player_idx.0].selected_player;
         let is_ai = player_inputs.players[player_idx.0].is_ai;
+        let ai_difficulty = player_inputs.players[player_idx.0].ai_difficulty;
 
         let Some(meta) = player_assets.get(&player_handle.get_bevy_handle()) else {
             continue;
@@ -590,100 +1188,23 @@ fn hydrate_players(
 
         // Handle AI players
         if is_ai {
-            ai_players.insert(player_entity, default());
-
-            // Give the player a sword NOTE: It's not good that we're duplicating the sword hydrate
-            // functionality here, and this is pretty hacky, but the AI as it stands is temporary
-            // anyway, so it's fine for now.
-            commands.add(
-                move |mut entities: ResMut<Entities>,
-                      mut swords: CompMut<sword::Sword>,
-                      mut element_handles: CompMut<ElementHandle>,
-                      element_assets: BevyAssets<ElementMeta>,
-                      mut hydrated: CompMut<MapElementHydrated>,
-                      mut bodies: CompMut<KinematicBody>,
-                      mut atlas_sprites: CompMut<AtlasSprite>,
-                      mut items: CompMut<Item>,
-                      mut transforms: CompMut<Transform>,
-                      game_meta: Res<CoreMetaArc>,
-                      mut attachments: CompMut<PlayerBodyAttachment>,
-                      mut inventories: CompMut<Inventory>| {
-                    let element_handle = game_meta
-                        .map_elements
-                        .iter()
-                        .find(|handle| {
-                            matches!(
-                                element_assets
-                                    .get(&handle.get_bevy_handle())
-                                    .unwrap()
-                                    .builtin,
-                                BuiltinElementKind::Sword { .. }
-                            )
-                        })
-                        .unwrap();
-                    let element_meta = element_assets
-                        .get(&element_handle.get_bevy_handle())
-                        .unwrap();
-                    if let BuiltinElementKind::Sword {
-                        atlas,
-                        body_size,
-                        can_rotate,
-                        bounciness,
-                        grab_offset,
-                        ..
-                    } = &element_meta.builtin
-                    {
-                        let sword_ent = entities.create();
-                        inventories.insert(player_entity, Inventory(Some(sword_ent)));
-                        items.insert(sword_ent, Item);
-                        swords.insert(sword_ent, sword::Sword::default());
-                        atlas_sprites.insert(sword_ent, AtlasSprite::new(atlas.clone()));
-                        transforms.insert(sword_ent, default());
-                        element_handles.insert(sword_ent, ElementHandle(element_handle.clone()));
-                        hydrated.insert(sword_ent, MapElementHydrated);
-                        bodies.insert(
-                            sword_ent,
-                            KinematicBody {
-                                shape: ColliderShape::Rectangle { size: *body_size },
-                                has_mass: true,
-                                has_friction: true,
-                                can_rotate: *can_rotate,
-                                bounciness: *bounciness,
-                                gravity: game_meta.physics.gravity,
-                                ..default()
-                            },
-                        );
-                        attachments.insert(
-                            sword_ent,
-                            PlayerBodyAttachment {
-                                sync_color: false,
-                                sync_animation: false,
-                                player: player_entity,
-                                offset: grab_offset.extend(1.0),
-                            },
-                        );
-                    }
-                },
-            );
+            ai_players.insert(player_entity, AiPlayer::new(ai_difficulty));
         }
     }
 }
 
-/// System that makes sure the swords held by AI are despawned when they are killed.
-fn delete_dead_ai_swords(
-    mut entities: ResMut<Entities>,
-    ai_players: Comp<AiPlayer>,
-    items: Comp<Item>,
-    dropped: Comp<ItemDropped>,
+/// Play another animation on the player's body when the player is missing the animation that the
+/// player state picked.
+fn fall_back_missing_body_animations(
+    entities: Res<Entities>,
+    player_indexes: Comp<PlayerIdx>,
+    mut animation_bank_sprites: CompMut<AnimationBankSprite>,
 ) {
-    let mut to_kill = Vec::new();
-    for (ent, (_item, dropped)) in entities.iter_with((&items, &dropped)) {
-        if ai_players.contains(dropped.player) {
-            to_kill.push(ent);
-        }
-    }
-    for entity in to_kill {
-        entities.kill(entity);
+    for (_, (_, animation_bank)) in
+        entities.iter_with((&player_indexes, &mut animation_bank_sprites))
+    {
+        animation_bank.current =
+            animation_or_fallback(&animation_bank.animations, animation_bank.current);
     }
 }
 
@@ -789,7 +1310,7 @@ fn update_player_layers(
         };
 
         let face_bank = animation_bank_sprites.get_mut(layers.face_ent).unwrap();
-        face_bank.current = layers.face_anim;
+        face_bank.current = animation_or_fallback(&face_bank.animations, layers.face_anim);
 
         let base_fin_offset = player_meta.layers.fin.offset;
         let total_fin_offset = base_fin_offset + layers.fin_offset;
@@ -798,6 +1319,62 @@ fn update_player_layers(
         fin_attachment.offset.y = total_fin_offset.y;
 
         let fin_bank = animation_bank_sprites.get_mut(layers.fin_ent).unwrap();
-        fin_bank.current = layers.fin_anim;
+        fin_bank.current = animation_or_fallback(&fin_bank.animations, layers.fin_anim);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestSession, TestSessionBuilder};
+
+    /// A match of an AI player standing on a musket, and a player that stands still further along
+    /// the floor.
+    fn ai_gunner_session(difficulty: AiDifficulty, seed: u64) -> TestSession {
+        let mut builder = TestSessionBuilder::room(30, 16);
+        builder.seed = seed;
+        builder.add_ai_player(Vec2::new(64.0, 48.0), difficulty);
+        builder.add_player(Vec2::new(256.0, 48.0));
+        let bullet = builder.assets.insert("/bullet", testing::bullet_meta());
+        builder.add_element(
+            "/musket",
+            testing::musket_meta(bullet),
+            Vec2::new(64.0, 40.0),
+        );
+        builder.build()
+    }
+
+    /// Whether the AI player shoots the other player within the given number of seconds.
+    fn ai_gunner_kills(difficulty: AiDifficulty, seed: u64, seconds: u32) -> bool {
+        let mut session = ai_gunner_session(difficulty, seed);
+        for _ in 0..seconds * crate::FPS as u32 {
+            session.step(1);
+            if session
+                .kills()
+                .iter()
+                .any(|kill| kill.victim == 1 && kill.killer == Some(0))
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn ai_picks_up_a_gun_and_shoots() {
+        for seed in 0..5 {
+            assert!(
+                ai_gunner_kills(AiDifficulty::Hard, seed, 2),
+                "hard AI missed with seed {seed}"
+            );
+        }
+
+        let easy_kills = (0..20)
+            .filter(|seed| ai_gunner_kills(AiDifficulty::Easy, *seed, 2))
+            .count();
+        assert!(
+            easy_kills < 10,
+            "easy AI hit the player in {easy_kills} of 20 matches"
+        );
     }
 }
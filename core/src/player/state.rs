@@ -35,6 +35,19 @@ impl PlayerState {
             .stages
             .add_system_to_stage(CoreStage::PreUpdate, system);
     }
+
+    /// Whether the player is currently crouching.
+    pub fn is_crouching(&self) -> bool {
+        self.current == crouch::ID
+    }
+}
+
+impl Inspect for PlayerState {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.text("current", format!("{:?}", self.current));
+        fields.text("age", self.age);
+        fields.text("last", format!("{:?}", self.last));
+    }
 }
 
 pub fn install(session: &mut CoreSession) {
@@ -46,14 +59,46 @@ pub fn install(session: &mut CoreSession) {
     session
         .stages
         .add_system_to_stage(CoreStage::Last, update_player_state_age);
+    session.inspectors.register::<PlayerState>("PlayerState");
 
     default::install(session);
     idle::install(session);
     crouch::install(session);
+    dash::install(session);
     midair::install(session);
     walk::install(session);
     dead::install(session);
     incapacitated::install(session);
+    ledge_grab::install(session);
+    emote::install(session);
+    swim::install(session);
+    stunned::install(session);
+}
+
+/// Get the body animations that the player states play, which every player needs to have.
+///
+/// This doesn't include the [`PlayerMeta::emote_animation`], which is picked by each player.
+pub fn required_player_animations() -> Vec<Key> {
+    let mut animations = Vec::new();
+    for state_animations in [
+        idle::ANIMATIONS,
+        crouch::ANIMATIONS,
+        dash::ANIMATIONS,
+        midair::ANIMATIONS,
+        walk::ANIMATIONS,
+        dead::ANIMATIONS,
+        incapacitated::ANIMATIONS,
+        ledge_grab::ANIMATIONS,
+        swim::ANIMATIONS,
+        stunned::ANIMATIONS,
+    ] {
+        for animation in state_animations {
+            if !animations.contains(animation) {
+                animations.push(*animation);
+            }
+        }
+    }
+    animations
 }
 
 fn update_player_state_age(entities: Res<Entities>, mut player_states: CompMut<PlayerState>) {
@@ -63,15 +108,32 @@ fn update_player_state_age(entities: Res<Entities>, mut player_states: CompMut<P
 }
 
 fn use_drop_or_grab_items_system(id: Key) -> System {
+    items_system(id, true)
+}
+
+/// Like [`use_drop_or_grab_items_system()`], but the player can't drop, or throw, the item that
+/// they are holding.
+fn use_or_grab_items_system(id: Key) -> System {
+    items_system(id, false)
+}
+
+fn items_system(id: Key, can_drop: bool) -> System {
     (move |entities: Res<Entities>,
            player_inputs: Res<PlayerInputs>,
            player_indexes: Comp<PlayerIdx>,
            player_states: Comp<PlayerState>,
            player_assets: BevyAssets<PlayerMeta>,
            items: Comp<Item>,
-           collision_world: CollisionWorld,
+           item_grabs: Comp<ItemGrab>,
+           thrown_by: Comp<ItemThrownBy>,
+           frame: Res<SessionFrame>,
+           spatial_hash: Res<SpatialHash>,
+           bodies: Comp<KinematicBody>,
+           transforms: Comp<Transform>,
            mut inventories: CompMut<Inventory>,
-           mut audio_events: ResMut<AudioEvents>,
+           mut item_charges: CompMut<ItemCharge>,
+           mut player_layers: CompMut<PlayerLayers>,
+           mut audio_events: ResMut<SoundEvents>,
            mut commands: Commands| {
         // Collect a list of items that are being held by players
         let held_items = entities
@@ -95,39 +157,82 @@ fn use_drop_or_grab_items_system(id: Key) -> System {
             if control.grab_just_pressed {
                 if inventory.is_none() {
                     // If we don't have an item
-                    let colliders = collision_world
-                        // Get all things colliding with the player
-                        .actor_collisions(player_ent)
-                        .into_iter()
-                        // Filter out anything not an item
-                        .filter(|ent| items.contains(*ent))
-                        // TODO: Use the ItemGrabbed tag for this detection after fixing the ItemGrabbed handling
-                        // Filter out any items held by other players
-                        .filter(|ent| !held_items.contains(ent))
-                        .collect::<Vec<_>>();
-
-                    // Grab the first item we are touching
-                    if let Some(item) = colliders.get(0) {
+                    let (Some(body), Some(transform)) =
+                        (bodies.get(player_ent), transforms.get(player_ent))
+                    else {
+                        continue;
+                    };
+                    let rect = body.bounding_box(*transform);
+                    // Get the items overlapping the player that they can grab
+                    let colliders = grabbable_items(
+                        player_ent,
+                        rect,
+                        &spatial_hash,
+                        &items,
+                        &held_items,
+                        &thrown_by,
+                        **frame,
+                        &transforms,
+                    );
+
+                    // Grab the item closest to us
+                    if let Some(item) = choose_item_to_grab(rect.center(), colliders) {
                         // Add the item to the player inventory
-                        commands.add(PlayerCommand::set_inventory(player_ent, Some(*item)));
+                        commands.add(PlayerCommand::set_inventory(player_ent, Some(item)));
 
                         // Play grab sound
-                        audio_events.play(meta.sounds.grab.clone(), meta.sounds.grab_volume);
+                        audio_events.play_from(
+                            meta.sounds.grab.clone(),
+                            meta.sounds.grab_volume,
+                            transforms.get(player_ent),
+                        );
                     }
 
                 // If we are already carrying an item
-                } else {
+                } else if can_drop {
                     // Drop it
                     commands.add(PlayerCommand::set_inventory(player_ent, None));
 
                     // Play drop sound
-                    audio_events.play(meta.sounds.drop.clone(), meta.sounds.drop_volume);
+                    audio_events.play_from(
+                        meta.sounds.drop.clone(),
+                        meta.sounds.drop_volume,
+                        transforms.get(player_ent),
+                    );
                 }
             }
 
             // If we are using an item
-            if control.shoot_just_pressed && inventory.is_some() {
-                commands.add(PlayerCommand::use_item(player_ent));
+            if let Some(item) = inventory.0 {
+                if let Some(charge) = item_charges.get_mut(item) {
+                    // Charge up the item while the button is held, and use it when it's let go
+                    if control.shoot_just_pressed {
+                        charge.start();
+                        if let Some(fin_anim) = charge.meta.fin_anim {
+                            player_layers.get_mut(player_ent).unwrap().fin_anim = fin_anim;
+                        }
+                    } else if charge.charging && control.shoot_pressed {
+                        let interval = charge.meta.sound_interval_frames.max(1);
+                        if charge.tick() && (charge.frames - 1) % interval == 0 {
+                            if let Some(sound) = &charge.meta.sound {
+                                audio_events.play_from(
+                                    sound.clone(),
+                                    charge.meta.sound_volume,
+                                    transforms.get(player_ent),
+                                );
+                            }
+                        }
+                    } else if charge.charging {
+                        charge.release();
+                        if let Some(item_grab) = item_grabs.get(item) {
+                            player_layers.get_mut(player_ent).unwrap().fin_anim =
+                                item_grab.fin_anim;
+                        }
+                        commands.add(PlayerCommand::use_item(player_ent));
+                    }
+                } else if control.shoot_just_pressed {
+                    commands.add(PlayerCommand::use_item(player_ent));
+                }
             }
         }
     })
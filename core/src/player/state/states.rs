@@ -1,9 +1,14 @@
 use super::*;
 
 pub mod crouch;
+pub mod dash;
 pub mod dead;
 pub mod default;
+pub mod emote;
 pub mod idle;
 pub mod incapacitated;
+pub mod ledge_grab;
 pub mod midair;
+pub mod stunned;
+pub mod swim;
 pub mod walk;
@@ -2,6 +2,9 @@ use super::*;
 
 pub const ID: Key = key!("core::crouch");
 
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("crouch"), key!("slide")];
+
 pub fn install(session: &mut CoreSession) {
     PlayerState::add_player_state_transition_system(session, player_state_transition);
     PlayerState::add_player_state_update_system(session, handle_player_state);
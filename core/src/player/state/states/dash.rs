@@ -0,0 +1,308 @@
+use super::*;
+
+pub const ID: Key = key!("core::dash");
+
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("slide")];
+
+pub fn install(session: &mut CoreSession) {
+    PlayerState::add_player_state_transition_system(session, player_state_transition);
+    PlayerState::add_player_state_update_system(session, update_dash_cooldowns);
+    PlayerState::add_player_state_update_system(session, handle_player_state);
+    PlayerState::add_player_state_update_system(session, use_or_grab_items_system(ID));
+
+    session.inspectors.register::<Dash>("Dash");
+}
+
+/// Component keeping track of a player's dash, and of when they may dash again.
+#[derive(Clone, Copy, Debug, Default, TypeUlid)]
+#[ulid = "01H61QK7D3ZRV8N2WXGT5BM4CE"]
+pub struct Dash {
+    /// The direction of the dash: `1.0` for the right, and `-1.0` for the left.
+    pub direction: f32,
+    /// How many frames are left in the current dash.
+    pub frames_left: u32,
+    /// How many frames are left before the player may dash again.
+    pub cooldown_frames_left: u32,
+    /// Whether the player has used up their air dash, which they get back when they land.
+    pub air_dash_used: bool,
+}
+
+impl Inspect for Dash {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.number("direction", &mut self.direction);
+        fields.text("frames_left", self.frames_left);
+        fields.text("cooldown_frames_left", self.cooldown_frames_left);
+        fields.text("air_dash_used", self.air_dash_used);
+    }
+}
+
+/// Check whether there is room for a sliding player to stand back up.
+fn can_stand_up(
+    collision_world: &CollisionWorld,
+    meta: &PlayerMeta,
+    transform: &Transform,
+) -> bool {
+    // Shrink the standing body a little so that it doesn't touch the floor or the walls
+    let standing = ColliderShape::Rectangle {
+        size: meta.body_size - Vec2::splat(2.0),
+    };
+    let mut transform = *transform;
+    transform.translation.y += (meta.body_size.y - meta.slide_body_size.y) / 2.0;
+    !collision_world
+        .tile_collision(transform, standing)
+        .is_solid()
+}
+
+pub fn player_state_transition(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    player_assets: BevyAssets<PlayerMeta>,
+    collision_world: CollisionWorld,
+    sprites: Comp<AtlasSprite>,
+    mut player_states: CompMut<PlayerState>,
+    mut dashes: CompMut<Dash>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
+) {
+    for (player_ent, (player_idx, player_state, body, transform, sprite)) in entities.iter_with((
+        &player_indexes,
+        &mut player_states,
+        &mut bodies,
+        &mut transforms,
+        &sprites,
+    )) {
+        let meta_handle = player_inputs.players[player_idx.0]
+            .selected_player
+            .get_bevy_handle();
+        let Some(meta) = player_assets.get(&meta_handle) else {
+            continue;
+        };
+        let control = &player_inputs.players[player_idx.0].control;
+        let dash = dashes.get(player_ent).copied().unwrap_or_default();
+
+        // Reset the body size and position once we stop dashing
+        if player_state.last == ID && player_state.current != ID {
+            if let ColliderShape::Rectangle { size } = &body.shape {
+                if *size != meta.body_size {
+                    body.shape = ColliderShape::Rectangle {
+                        size: meta.body_size,
+                    };
+                    transform.translation.y += (meta.body_size.y - meta.slide_body_size.y) / 2.0;
+                }
+            }
+        }
+
+        if player_state.current == ID {
+            // Keep sliding until there is room to stand up, so that we don't get stuck in the
+            // ceiling after sliding under a gap.
+            let is_standing_blocked =
+                body.is_on_ground && !can_stand_up(&collision_world, meta, transform);
+            if dash.frames_left == 0 && !is_standing_blocked {
+                player_state.current = if body.is_on_ground {
+                    idle::ID
+                } else {
+                    midair::ID
+                };
+            }
+            continue;
+        }
+
+        let can_dash = [idle::ID, walk::ID, crouch::ID, midair::ID].contains(&player_state.current)
+            && dash.cooldown_frames_left == 0
+            && (body.is_on_ground || !dash.air_dash_used);
+        if !control.dash_just_pressed || !can_dash {
+            continue;
+        }
+
+        // Dash in the direction we are moving, or the way we are facing if we aren't moving
+        let direction = if control.move_direction.x > 0.0 {
+            1.0
+        } else if control.move_direction.x < 0.0 {
+            -1.0
+        } else if sprite.flip_x {
+            -1.0
+        } else {
+            1.0
+        };
+        dashes.insert(
+            player_ent,
+            Dash {
+                direction,
+                frames_left: meta.stats.dash_frames,
+                cooldown_frames_left: meta.stats.dash_cooldown_frames,
+                air_dash_used: dash.air_dash_used || !body.is_on_ground,
+            },
+        );
+        player_state.current = ID;
+
+        // Play dash sound
+        if let Some(sound) = &meta.sounds.dash {
+            audio_events.play_from(sound.clone(), meta.sounds.dash_volume, Some(&*transform));
+        }
+    }
+}
+
+/// Count down the dash cooldowns, and give players back their air dash when they land.
+pub fn update_dash_cooldowns(
+    entities: Res<Entities>,
+    player_states: Comp<PlayerState>,
+    bodies: Comp<KinematicBody>,
+    mut dashes: CompMut<Dash>,
+) {
+    for (_ent, (player_state, body, dash)) in
+        entities.iter_with((&player_states, &bodies, &mut dashes))
+    {
+        if player_state.current == ID {
+            continue;
+        }
+        dash.cooldown_frames_left = dash.cooldown_frames_left.saturating_sub(1);
+        if body.is_on_ground {
+            dash.air_dash_used = false;
+        }
+    }
+}
+
+pub fn handle_player_state(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    player_states: Comp<PlayerState>,
+    player_assets: BevyAssets<PlayerMeta>,
+    mut dashes: CompMut<Dash>,
+    mut sprites: CompMut<AtlasSprite>,
+    mut animations: CompMut<AnimationBankSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+) {
+    let players = entities.iter_with((
+        &player_states,
+        &player_indexes,
+        &mut dashes,
+        &mut animations,
+        &mut sprites,
+        &mut bodies,
+    ));
+    for (player_ent, (player_state, player_idx, dash, animation, sprite, body)) in players {
+        if player_state.current != ID {
+            continue;
+        }
+        let meta_handle = player_inputs.players[player_idx.0]
+            .selected_player
+            .get_bevy_handle();
+        let Some(meta) = player_assets.get(&meta_handle) else {
+            continue;
+        };
+        let control = &player_inputs.players[player_idx.0].control;
+
+        // Lower the body, so that we can slide under gaps
+        animation.current = key!("slide");
+        if let ColliderShape::Rectangle { size } = &body.shape {
+            if *size != meta.slide_body_size {
+                body.shape = ColliderShape::Rectangle {
+                    size: meta.slide_body_size,
+                };
+                if let Some(transform) = transforms.get_mut(player_ent) {
+                    transform.translation.y -= (meta.body_size.y - meta.slide_body_size.y) / 2.0;
+                }
+            }
+        }
+
+        if dash.frames_left > 0 {
+            // Dash in a straight line, without being slowed down by friction
+            dash.frames_left -= 1;
+            body.velocity.x = dash.direction * meta.stats.dash_speed;
+            body.velocity.y = 0.0;
+            body.frame_friction_override = Some(1.0);
+            sprite.flip_x = dash.direction < 0.0;
+        } else {
+            // Crawl along until there's room to stand back up
+            body.velocity.x = control.move_direction.x * meta.stats.walk_speed;
+            if control.move_direction.x != 0.0 {
+                sprite.flip_x = control.move_direction.x < 0.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TestInput, TestSession, TestSessionBuilder};
+
+    /// A player standing at the left end of a long room.
+    fn dash_session(meta: PlayerMeta) -> TestSession {
+        let mut builder = TestSessionBuilder::room(40, 12);
+        builder.player = meta;
+        builder.add_player(Vec2::new(48.0, 48.0));
+
+        let mut session = builder.build();
+        session.step(10);
+        session
+    }
+
+    fn press_dash(session: &mut TestSession) {
+        session.step_with(
+            0,
+            TestInput {
+                dash: true,
+                ..default()
+            },
+            1,
+        );
+    }
+
+    #[test]
+    fn player_dashes_and_waits_for_cooldown() {
+        let mut session = dash_session(testing::player_meta());
+        let start = session.player_position(0).unwrap();
+
+        press_dash(&mut session);
+        assert_eq!(session.player_state(0), Some(ID));
+        session.step(10);
+        assert_eq!(session.player_state(0), Some(key!("core::idle")));
+        let dashed = session.player_position(0).unwrap();
+        assert!(dashed.x > start.x + 120.0, "{dashed}");
+        assert!((dashed.y - start.y).abs() < 1.0, "{dashed}");
+
+        // Can't dash again until the cooldown is over
+        press_dash(&mut session);
+        assert_eq!(session.player_state(0), Some(key!("core::idle")));
+        session.step(45);
+        press_dash(&mut session);
+        assert_eq!(session.player_state(0), Some(ID));
+    }
+
+    #[test]
+    fn player_air_dashes_once_until_landing() {
+        let mut meta = testing::player_meta();
+        meta.stats.dash_cooldown_frames = 0;
+        let mut session = dash_session(meta);
+
+        session.step_with(
+            0,
+            TestInput {
+                jump: true,
+                ..default()
+            },
+            1,
+        );
+        session.step(3);
+        press_dash(&mut session);
+        assert_eq!(session.player_state(0), Some(ID));
+        session.step(10);
+        assert_eq!(session.player_state(0), Some(key!("core::midair")));
+
+        // The air dash has been used up
+        press_dash(&mut session);
+        assert_eq!(session.player_state(0), Some(key!("core::midair")));
+
+        // Landing gives it back
+        session.step(60);
+        assert_eq!(session.player_state(0), Some(key!("core::idle")));
+        press_dash(&mut session);
+        assert_eq!(session.player_state(0), Some(ID));
+    }
+}
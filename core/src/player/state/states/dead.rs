@@ -2,6 +2,9 @@ use super::*;
 
 pub const ID: Key = key!("core::dead");
 
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("death_spine"), key!("death_belly")];
+
 pub fn install(session: &mut CoreSession) {
     PlayerState::add_player_state_transition_system(session, player_state_transition);
     PlayerState::add_player_state_update_system(session, handle_player_state);
@@ -52,6 +55,8 @@ pub fn handle_player_state(
         }
 
         if state.age >= 80 {
+            // Leave the body behind before the player is removed
+            commands.add(spawn_corpse(player_ent));
             commands.add(PlayerCommand::despawn(player_ent));
         }
     }
@@ -0,0 +1,83 @@
+use super::*;
+
+pub const ID: Key = key!("core::emote");
+
+/// How many frames an emote lasts for, if it isn't interrupted.
+const EMOTE_FRAMES: u64 = 90;
+
+pub fn install(session: &mut CoreSession) {
+    PlayerState::add_player_state_transition_system(session, player_state_transition);
+    PlayerState::add_player_state_update_system(session, handle_player_state);
+}
+
+pub fn player_state_transition(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    mut player_states: CompMut<PlayerState>,
+    bodies: Comp<KinematicBody>,
+) {
+    for (_ent, (player_idx, player_state, body)) in
+        entities.iter_with((&player_indexes, &mut player_states, &bodies))
+    {
+        if player_state.current != ID {
+            continue;
+        }
+
+        let control = &player_inputs.players[player_idx.0].control;
+
+        // Moving or jumping interrupts the emote
+        if !body.is_on_ground {
+            player_state.current = midair::ID;
+        } else if control.move_direction != Vec2::ZERO
+            || control.jump_just_pressed
+            || player_state.age >= EMOTE_FRAMES
+        {
+            player_state.current = idle::ID;
+        }
+    }
+}
+
+pub fn handle_player_state(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    player_states: Comp<PlayerState>,
+    player_assets: BevyAssets<PlayerMeta>,
+    mut sprites: CompMut<AnimationBankSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    transforms: Comp<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
+) {
+    let players = entities.iter_with((&player_states, &player_indexes, &mut sprites, &mut bodies));
+    for (player_ent, (player_state, player_idx, animation, body)) in players {
+        if player_state.current != ID {
+            continue;
+        }
+        let meta_handle = player_inputs.players[player_idx.0]
+            .selected_player
+            .get_bevy_handle();
+        let Some(meta) = player_assets.get(&meta_handle) else {
+            continue;
+        };
+
+        // If this is the first frame of this state
+        if player_state.age == 0 {
+            animation.current = meta.emote_animation;
+            if let Some(sound) = &meta.sounds.emote {
+                audio_events.play_from(
+                    sound.clone(),
+                    meta.sounds.emote_volume,
+                    transforms.get(player_ent),
+                );
+            }
+        }
+
+        // Come to a stop while emoting
+        if body.velocity.x.is_sign_positive() {
+            body.velocity.x = (body.velocity.x - meta.stats.slowdown).max(0.0);
+        } else {
+            body.velocity.x = (body.velocity.x + meta.stats.slowdown).min(0.0);
+        }
+    }
+}
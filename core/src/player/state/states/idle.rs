@@ -3,6 +3,9 @@ use crate::player::slippery::Slippery;
 
 pub const ID: Key = key!("core::idle");
 
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("idle")];
+
 pub fn install(session: &mut CoreSession) {
     PlayerState::add_player_state_transition_system(session, player_state_transition);
     PlayerState::add_player_state_update_system(session, handle_player_state);
@@ -31,6 +34,8 @@ pub fn player_state_transition(
             player_state.current = crouch::ID;
         } else if control.move_direction.x != 0.0 {
             player_state.current = walk::ID;
+        } else if control.emote_just_pressed {
+            player_state.current = emote::ID;
         }
     }
 }
@@ -41,9 +46,11 @@ pub fn handle_player_state(
     player_indexes: Comp<PlayerIdx>,
     player_states: Comp<PlayerState>,
     player_assets: BevyAssets<PlayerMeta>,
+    core_meta: Res<CoreMetaArc>,
     mut sprites: CompMut<AnimationBankSprite>,
     mut bodies: CompMut<KinematicBody>,
-    mut audio_events: ResMut<AudioEvents>,
+    transforms: Comp<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
     collision_world: CollisionWorld,
     slippery: CompMut<Slippery>,
 ) {
@@ -67,16 +74,21 @@ pub fn handle_player_state(
 
         let control = &player_inputs.players[player_idx.0].control;
 
-        // If we are jumping
-        if control.jump_just_pressed {
+        // If we are jumping, and aren't stuck to the ground
+        if control.jump_just_pressed && body.try_jump(core_meta.physics.sticky_jump_presses) {
             // Play jump sound
-            audio_events.play(meta.sounds.jump.clone(), meta.sounds.jump_volume);
+            audio_events.play_from(
+                meta.sounds.jump.clone(),
+                meta.sounds.jump_volume,
+                transforms.get(player_ent),
+            );
 
             // Move up
             body.velocity.y = meta.stats.jump_speed;
         }
 
-        let mut slide_factor = 1.;
+        // Slide further on slippery tiles
+        let mut slide_factor = body.ground_surface.friction;
         for (slippery_ent, slippery_meta) in entities.iter_with(&slippery) {
             if collision_world
                 .actor_collisions(player_ent)
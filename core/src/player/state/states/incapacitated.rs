@@ -2,6 +2,9 @@ use super::*;
 
 pub const ID: Key = key!("core::incapacitated");
 
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("rise"), key!("idle")];
+
 pub fn install(session: &mut CoreSession) {
     PlayerState::add_player_state_update_system(session, handle_player_state);
 }
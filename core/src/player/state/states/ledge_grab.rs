@@ -0,0 +1,319 @@
+use super::*;
+
+pub const ID: Key = key!("core::ledge_grab");
+
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("ledge_hang")];
+
+/// How far out from the side of the player to look for a ledge.
+const LEDGE_PROBE_DISTANCE: f32 = 2.0;
+/// How many frames the player has to wait after letting go of a ledge before grabbing one again,
+/// so that they don't grab the same ledge right after dropping from it.
+const REGRAB_COOLDOWN_FRAMES: u64 = 15;
+
+pub fn install(session: &mut CoreSession) {
+    PlayerState::add_player_state_transition_system(session, player_state_transition);
+    PlayerState::add_player_state_update_system(session, handle_player_state);
+
+    session.inspectors.register::<LedgeGrab>("LedgeGrab");
+}
+
+/// Component containing the ledge that a player is hanging from, added when they grab it.
+#[derive(Clone, Copy, Debug, Default, TypeUlid)]
+#[ulid = "01H60B8Q2KXM4TRW7DNE3VCJ5F"]
+pub struct LedgeGrab {
+    /// The side of the player that the ledge is on: `1.0` for the right, and `-1.0` for the left.
+    pub direction: f32,
+    /// How many frames the player has been pulling up onto the ledge, if they are pulling up.
+    pub pull_up_frame: Option<u32>,
+}
+
+impl Inspect for LedgeGrab {
+    fn inspect(&mut self, fields: &mut InspectorFields) {
+        fields.number("direction", &mut self.direction);
+        fields.text("pull_up_frame", format!("{:?}", self.pull_up_frame));
+    }
+}
+
+/// Find the top of a ledge next to the head of a player, if there is one.
+///
+/// There is a ledge when the tile a little below the top of the player is solid, or a jump-through
+/// platform, and the space just above the player is empty.
+fn find_ledge(
+    collision_world: &CollisionWorld,
+    rect: Rect,
+    direction: f32,
+    grab_window: f32,
+    tile_height: f32,
+) -> Option<f32> {
+    let x = if direction > 0.0 {
+        rect.max.x + LEDGE_PROBE_DISTANCE
+    } else {
+        rect.min.x - LEDGE_PROBE_DISTANCE
+    };
+    let head = vec2(x, rect.max.y - grab_window);
+    let above = vec2(x, rect.max.y + LEDGE_PROBE_DISTANCE);
+
+    let is_ledge = collision_world.tile_collision_point(head) != TileCollisionKind::Empty
+        && collision_world.tile_collision_point(above) == TileCollisionKind::Empty;
+    is_ledge.then(|| ((head.y / tile_height).floor() + 1.0) * tile_height)
+}
+
+/// Get how many frames it takes a player to pull up onto a ledge.
+///
+/// Pulling up takes at least two frames: one to lift the player, and one to move them over.
+fn pull_up_frames(meta: &PlayerMeta) -> u32 {
+    meta.stats.ledge_pull_up_frames.max(2)
+}
+
+pub fn player_state_transition(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    player_assets: BevyAssets<PlayerMeta>,
+    element_handles: Comp<ElementHandle>,
+    element_assets: BevyAssets<ElementMeta>,
+    inventories: Comp<Inventory>,
+    collision_world: CollisionWorld,
+    map: Res<LoadedMap>,
+    sprites: Comp<AtlasSprite>,
+    mut player_states: CompMut<PlayerState>,
+    mut ledge_grabs: CompMut<LedgeGrab>,
+    mut bodies: CompMut<KinematicBody>,
+    mut transforms: CompMut<Transform>,
+) {
+    for (player_ent, (player_idx, player_state, body, transform, sprite)) in entities.iter_with((
+        &player_indexes,
+        &mut player_states,
+        &mut bodies,
+        &mut transforms,
+        &sprites,
+    )) {
+        let meta_handle = player_inputs.players[player_idx.0]
+            .selected_player
+            .get_bevy_handle();
+        let Some(meta) = player_assets.get(&meta_handle) else {
+            continue;
+        };
+        let control = &player_inputs.players[player_idx.0].control;
+
+        // Give the body its weight back once we let go of the ledge
+        if player_state.last == ID && player_state.current != ID {
+            body.has_mass = true;
+        }
+
+        if player_state.current == ID {
+            let Some(ledge_grab) = ledge_grabs.get(player_ent) else {
+                player_state.current = midair::ID;
+                continue;
+            };
+
+            if let Some(frame) = ledge_grab.pull_up_frame {
+                // Finish pulling up onto the ledge
+                if frame >= pull_up_frames(meta) {
+                    player_state.current = if body.is_on_ground {
+                        idle::ID
+                    } else {
+                        midair::ID
+                    };
+                }
+                continue;
+            }
+
+            // Drop from the ledge, or let go of it by moving away from the wall
+            let is_moving_away = control.move_direction.x * ledge_grab.direction < -0.5;
+            if control.grab_just_pressed || control.move_direction.y < -0.5 || is_moving_away {
+                player_state.current = midair::ID;
+            }
+        } else if player_state.current == midair::ID {
+            let just_let_go = player_state.last == ID && player_state.age < REGRAB_COOLDOWN_FRAMES;
+            if body.velocity.y > 0.0 || control.move_direction.y < -0.5 || just_let_go {
+                continue;
+            }
+
+            // Some items take both fins to hold, so the player can't grab ledges with them
+            let is_two_handed = inventories
+                .get(player_ent)
+                .and_then(|inventory| inventory.0)
+                .and_then(|item| element_handles.get(item))
+                .and_then(|handle| element_assets.get(&handle.get_bevy_handle()))
+                .map(|element_meta| element_meta.two_handed)
+                .unwrap_or(false);
+            if is_two_handed {
+                continue;
+            }
+
+            let direction = if sprite.flip_x { -1.0 } else { 1.0 };
+            let rect = body.bounding_box(*transform);
+            let Some(ledge_top) = find_ledge(
+                &collision_world,
+                rect,
+                direction,
+                meta.stats.ledge_grab_window,
+                map.tile_size.y,
+            ) else {
+                continue;
+            };
+
+            // Hang with the top of the player lined up with the top of the ledge
+            transform.translation.y += ledge_top - rect.max.y;
+            ledge_grabs.insert(
+                player_ent,
+                LedgeGrab {
+                    direction,
+                    pull_up_frame: None,
+                },
+            );
+            player_state.current = ID;
+        }
+    }
+}
+
+pub fn handle_player_state(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    player_states: Comp<PlayerState>,
+    player_assets: BevyAssets<PlayerMeta>,
+    transforms: Comp<Transform>,
+    mut ledge_grabs: CompMut<LedgeGrab>,
+    mut sprites: CompMut<AtlasSprite>,
+    mut animations: CompMut<AnimationBankSprite>,
+    mut bodies: CompMut<KinematicBody>,
+) {
+    let players = entities.iter_with((
+        &player_states,
+        &player_indexes,
+        &mut ledge_grabs,
+        &mut animations,
+        &mut sprites,
+        &mut bodies,
+    ));
+    for (player_ent, (player_state, player_idx, ledge_grab, animation, sprite, body)) in players {
+        if player_state.current != ID {
+            continue;
+        }
+        let meta_handle = player_inputs.players[player_idx.0]
+            .selected_player
+            .get_bevy_handle();
+        let Some(meta) = player_assets.get(&meta_handle) else {
+            continue;
+        };
+        let control = &player_inputs.players[player_idx.0].control;
+
+        // Hang still from the ledge, facing it
+        animation.current = key!("ledge_hang");
+        sprite.flip_x = ledge_grab.direction < 0.0;
+        body.has_mass = false;
+        body.velocity = Vec2::ZERO;
+
+        if ledge_grab.pull_up_frame.is_none()
+            && (control.jump_just_pressed || control.move_direction.y > 0.5)
+        {
+            ledge_grab.pull_up_frame = Some(0);
+        }
+
+        // Pull up onto the ledge: first lift the body above it, and then move over onto it
+        if let Some(frame) = &mut ledge_grab.pull_up_frame {
+            let Some(transform) = transforms.get(player_ent) else {
+                continue;
+            };
+            let rect = body.bounding_box(*transform);
+            let size = rect.max - rect.min;
+            let total_frames = pull_up_frames(meta);
+            let lift_frames = total_frames / 2;
+            let over_frames = total_frames - lift_frames;
+
+            if *frame < lift_frames {
+                body.velocity.y = (size.y + 1.0) / lift_frames as f32;
+            } else if *frame < total_frames {
+                body.velocity.x = ledge_grab.direction * size.x / over_frames as f32;
+            }
+            *frame += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestInput, TestSession, TestSessionBuilder};
+
+    /// A player falling past the left side of a block, with its top at a height of `80`.
+    fn ledge_session() -> TestSession {
+        let mut builder = TestSessionBuilder::room(20, 12);
+        for x in 10..19 {
+            for y in 1..5 {
+                builder.map.layers[0].tiles.push(MapTileMeta {
+                    pos: UVec2::new(x, y),
+                    idx: 0,
+                    collision: TileCollisionKind::Solid,
+                    surface: default(),
+                });
+            }
+        }
+        // Close enough to the block to grab it, and low enough to not fall past it too fast
+        builder.add_player(Vec2::new(143.0, 104.0));
+        builder.build()
+    }
+
+    /// Step the session until the player grabs the ledge.
+    fn grab_ledge(session: &mut TestSession) {
+        for _ in 0..60 {
+            session.step(1);
+            if session.player_state(0) == Some(ID) {
+                return;
+            }
+        }
+        panic!("The player didn't grab the ledge");
+    }
+
+    #[test]
+    fn player_grabs_ledge_and_pulls_up() {
+        let mut session = ledge_session();
+        grab_ledge(&mut session);
+
+        // Hang from the ledge with the top of the player lined up with it
+        let hanging = session.player_position(0).unwrap();
+        assert!((hanging.y - 56.0).abs() < 1.0, "{hanging}");
+        session.step(30);
+        assert_eq!(session.player_state(0), Some(ID));
+        assert_eq!(session.player_position(0), Some(hanging));
+
+        // Pull up and stand on top of the block
+        session.step_with(
+            0,
+            TestInput {
+                jump: true,
+                ..default()
+            },
+            1,
+        );
+        session.step(30);
+        assert_eq!(session.player_state(0), Some(key!("core::idle")));
+        let standing = session.player_position(0).unwrap();
+        assert!(standing.x > 160.0, "{standing}");
+        assert!((standing.y - 104.0).abs() < 2.0, "{standing}");
+    }
+
+    #[test]
+    fn player_drops_from_ledge() {
+        let mut session = ledge_session();
+        grab_ledge(&mut session);
+
+        session.step_with(
+            0,
+            TestInput {
+                grab: true,
+                ..default()
+            },
+            1,
+        );
+        session.step(60);
+
+        // Fall all the way to the floor without grabbing the ledge again
+        let position = session.player_position(0).unwrap();
+        assert!(position.x < 160.0, "{position}");
+        assert!((position.y - 40.0).abs() < 2.0, "{position}");
+    }
+}
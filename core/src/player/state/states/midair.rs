@@ -2,6 +2,9 @@ use super::*;
 
 pub const ID: Key = key!("core::midair");
 
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("rise"), key!("fall")];
+
 pub fn install(session: &mut CoreSession) {
     PlayerState::add_player_state_transition_system(session, player_state_transition);
     PlayerState::add_player_state_update_system(session, handle_player_state);
@@ -15,9 +18,10 @@ pub fn player_state_transition(
     player_assets: BevyAssets<PlayerMeta>,
     mut player_states: CompMut<PlayerState>,
     bodies: Comp<KinematicBody>,
-    mut audio_events: ResMut<AudioEvents>,
+    transforms: Comp<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
 ) {
-    for (_ent, (player_idx, player_state, body)) in
+    for (player_ent, (player_idx, player_state, body)) in
         entities.iter_with((&player_indexes, &mut player_states, &bodies))
     {
         let meta_handle = player_inputs.players[player_idx.0]
@@ -32,7 +36,11 @@ pub fn player_state_transition(
 
         if body.is_on_ground {
             // Play land sound
-            audio_events.play(meta.sounds.land.clone(), meta.sounds.land_volume);
+            audio_events.play_from(
+                meta.sounds.land.clone(),
+                meta.sounds.land_volume,
+                transforms.get(player_ent),
+            );
             // Switch to idle state
             player_state.current = idle::ID;
         }
@@ -45,9 +53,11 @@ pub fn handle_player_state(
     player_indexes: Comp<PlayerIdx>,
     player_states: Comp<PlayerState>,
     player_assets: BevyAssets<PlayerMeta>,
+    core_meta: Res<CoreMetaArc>,
     mut sprites: CompMut<AtlasSprite>,
     mut animations: CompMut<AnimationBankSprite>,
     mut bodies: CompMut<KinematicBody>,
+    status_effects: Comp<StatusEffects>,
 ) {
     let players = entities.iter_with((
         &player_states,
@@ -56,7 +66,7 @@ pub fn handle_player_state(
         &mut sprites,
         &mut bodies,
     ));
-    for (_player_ent, (player_state, player_idx, animation, sprite, body)) in players {
+    for (player_ent, (player_state, player_idx, animation, sprite, body)) in players {
         if player_state.current != ID {
             continue;
         }
@@ -67,6 +77,11 @@ pub fn handle_player_state(
             continue;
         };
         let control = &player_inputs.players[player_idx.0].control;
+        let speed_multiplier = core_meta.config.player_speed_multiplier
+            * status_effects
+                .get(player_ent)
+                .map(|x| x.move_speed_multiplier())
+                .unwrap_or(1.0);
 
         if body.velocity.y > 0.0 {
             animation.current = key!("rise");
@@ -80,11 +95,14 @@ pub fn handle_player_state(
         }
 
         // Walk in movement direction
-        body.velocity.x += meta.stats.accel_air_speed * control.move_direction.x;
+        body.velocity.x += meta.stats.accel_air_speed * speed_multiplier * control.move_direction.x;
         if control.move_direction.x.is_sign_positive() {
-            body.velocity.x = body.velocity.x.min(meta.stats.air_speed);
+            body.velocity.x = body.velocity.x.min(meta.stats.air_speed * speed_multiplier);
         } else {
-            body.velocity.x = body.velocity.x.max(-meta.stats.air_speed);
+            body.velocity.x = body
+                .velocity
+                .x
+                .max(-meta.stats.air_speed * speed_multiplier);
         }
 
         if control.move_direction.x == 0.0 {
@@ -0,0 +1,62 @@
+use super::*;
+
+pub const ID: Key = key!("core::stunned");
+
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("idle")];
+
+pub fn install(session: &mut CoreSession) {
+    PlayerState::add_player_state_transition_system(session, player_state_transition);
+    PlayerState::add_player_state_update_system(session, handle_player_state);
+}
+
+pub fn player_state_transition(
+    entities: Res<Entities>,
+    status_effects: Comp<StatusEffects>,
+    killed_players: Comp<PlayerKilled>,
+    mut player_states: CompMut<PlayerState>,
+    bodies: Comp<KinematicBody>,
+) {
+    for (player_ent, (player_state, effects, body)) in
+        entities.iter_with((&mut player_states, &status_effects, &bodies))
+    {
+        if killed_players.contains(player_ent) {
+            continue;
+        }
+
+        if player_state.current == ID {
+            if !effects.is_stunned() {
+                player_state.current = if body.is_on_ground {
+                    idle::ID
+                } else {
+                    midair::ID
+                };
+            }
+        } else if effects.is_stunned()
+            && player_state.current != dead::ID
+            && player_state.current != incapacitated::ID
+        {
+            player_state.current = ID;
+        }
+    }
+}
+
+pub fn handle_player_state(
+    entities: Res<Entities>,
+    player_states: Comp<PlayerState>,
+    mut animations: CompMut<AnimationBankSprite>,
+) {
+    for (_player_ent, (player_state, animation)) in
+        entities.iter_with((&player_states, &mut animations))
+    {
+        if player_state.current != ID {
+            continue;
+        }
+
+        // The player can't do anything while stunned, so just stand still and let the physics
+        // slow the player down.
+        if player_state.age == 0 {
+            animation.current = key!("idle");
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::*;
+
+pub const ID: Key = key!("core::swim");
+
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("swim")];
+
+/// How much of the player has to be under water for them to start swimming.
+const START_SUBMERSION: f32 = 0.75;
+/// How little of the player can be under water before they stop swimming.
+///
+/// This is lower than [`START_SUBMERSION`] so that bobbing at the surface doesn't make the player
+/// switch in and out of swimming every frame.
+const STOP_SUBMERSION: f32 = 0.4;
+
+pub fn install(session: &mut CoreSession) {
+    PlayerState::add_player_state_transition_system(session, player_state_transition);
+    PlayerState::add_player_state_update_system(session, handle_player_state);
+    PlayerState::add_player_state_update_system(session, use_drop_or_grab_items_system(ID));
+}
+
+pub fn player_state_transition(
+    entities: Res<Entities>,
+    player_indexes: Comp<PlayerIdx>,
+    mut player_states: CompMut<PlayerState>,
+    bodies: Comp<KinematicBody>,
+) {
+    for (_ent, (_player_idx, player_state, body)) in
+        entities.iter_with((&player_indexes, &mut player_states, &bodies))
+    {
+        if player_state.current == ID {
+            if body.submersion < STOP_SUBMERSION {
+                player_state.current = if body.is_on_ground {
+                    idle::ID
+                } else {
+                    midair::ID
+                };
+            }
+        } else if [idle::ID, walk::ID, midair::ID].contains(&player_state.current)
+            && body.submersion >= START_SUBMERSION
+        {
+            player_state.current = ID;
+        }
+    }
+}
+
+pub fn handle_player_state(
+    entities: Res<Entities>,
+    player_inputs: Res<PlayerInputs>,
+    player_indexes: Comp<PlayerIdx>,
+    player_states: Comp<PlayerState>,
+    player_assets: BevyAssets<PlayerMeta>,
+    mut sprites: CompMut<AtlasSprite>,
+    mut animations: CompMut<AnimationBankSprite>,
+    mut bodies: CompMut<KinematicBody>,
+    transforms: Comp<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
+) {
+    let players = entities.iter_with((
+        &player_states,
+        &player_indexes,
+        &mut animations,
+        &mut sprites,
+        &mut bodies,
+    ));
+    for (player_ent, (player_state, player_idx, animation, sprite, body)) in players {
+        if player_state.current != ID {
+            continue;
+        }
+        let meta_handle = player_inputs.players[player_idx.0]
+            .selected_player
+            .get_bevy_handle();
+        let Some(meta) = player_assets.get(&meta_handle) else {
+            continue;
+        };
+        let control = &player_inputs.players[player_idx.0].control;
+
+        animation.current = key!("swim");
+
+        // Jumping is a stroke upwards
+        if control.jump_just_pressed {
+            audio_events.play_from(
+                meta.sounds.jump.clone(),
+                meta.sounds.jump_volume,
+                transforms.get(player_ent),
+            );
+            body.velocity.y = body.velocity.y.max(meta.stats.swim_stroke_speed);
+        }
+
+        // Dive when holding down
+        if control.move_direction.y < -0.5 {
+            body.velocity.y -= meta.stats.accel_swim_speed;
+        }
+
+        // Swim in movement direction
+        body.velocity.x += meta.stats.accel_swim_speed * control.move_direction.x;
+        body.velocity.x = body
+            .velocity
+            .x
+            .clamp(-meta.stats.swim_speed, meta.stats.swim_speed);
+
+        // Point in movement direction
+        if control.move_direction.x > 0.0 {
+            sprite.flip_x = false;
+        } else if control.move_direction.x < 0.0 {
+            sprite.flip_x = true;
+        }
+    }
+}
@@ -2,6 +2,9 @@ use super::*;
 
 pub const ID: Key = key!("core::walk");
 
+/// The animations that the player plays in this state.
+pub const ANIMATIONS: &[Key] = &[key!("walk")];
+
 pub fn install(session: &mut CoreSession) {
     PlayerState::add_player_state_transition_system(session, player_state_transition);
     PlayerState::add_player_state_update_system(session, handle_player_state);
@@ -40,10 +43,13 @@ pub fn handle_player_state(
     player_indexes: Comp<PlayerIdx>,
     player_states: Comp<PlayerState>,
     player_assets: BevyAssets<PlayerMeta>,
+    core_meta: Res<CoreMetaArc>,
     mut sprites: CompMut<AtlasSprite>,
     mut animations: CompMut<AnimationBankSprite>,
     mut bodies: CompMut<KinematicBody>,
-    mut audio_events: ResMut<AudioEvents>,
+    status_effects: Comp<StatusEffects>,
+    transforms: Comp<Transform>,
+    mut audio_events: ResMut<SoundEvents>,
 ) {
     let players = entities.iter_with((
         &player_states,
@@ -52,7 +58,7 @@ pub fn handle_player_state(
         &mut sprites,
         &mut bodies,
     ));
-    for (_player_ent, (player_state, player_idx, animation, sprite, body)) in players {
+    for (player_ent, (player_state, player_idx, animation, sprite, body)) in players {
         if player_state.current != ID {
             continue;
         }
@@ -63,6 +69,11 @@ pub fn handle_player_state(
             continue;
         };
         let control = &player_inputs.players[player_idx.0].control;
+        let speed_multiplier = core_meta.config.player_speed_multiplier
+            * status_effects
+                .get(player_ent)
+                .map(|x| x.move_speed_multiplier())
+                .unwrap_or(1.0);
 
         // If this is the first frame of this state
         if player_state.age == 0 {
@@ -70,26 +81,42 @@ pub fn handle_player_state(
             animation.current = key!("walk");
         }
 
-        // If we are jumping
-        if control.jump_just_pressed {
-            audio_events.play(meta.sounds.jump.clone(), meta.sounds.jump_volume);
+        // If we are jumping, and aren't stuck to the ground
+        if control.jump_just_pressed && body.try_jump(core_meta.physics.sticky_jump_presses) {
+            audio_events.play_from(
+                meta.sounds.jump.clone(),
+                meta.sounds.jump_volume,
+                transforms.get(player_ent),
+            );
 
             // Move up
             body.velocity.y = meta.stats.jump_speed;
         }
 
-        // Walk in movement direction
-        body.velocity.x += meta.stats.accel_walk_speed * control.move_direction.x;
+        // Sticky tiles keep us from walking, so just slow down like we are idling
+        if body.ground_surface.sticky {
+            body.velocity.x = if body.velocity.x.is_sign_positive() {
+                (body.velocity.x - meta.stats.slowdown).max(0.0)
+            } else {
+                (body.velocity.x + meta.stats.slowdown).min(0.0)
+            };
+        } else {
+            // Walk in movement direction. Slippery tiles make it harder to speed up.
+            body.velocity.x += meta.stats.accel_walk_speed
+                * speed_multiplier
+                * body.ground_surface.friction.min(1.0)
+                * control.move_direction.x;
+        }
         if control.move_direction.x.is_sign_positive() {
             body.velocity.x = body
                 .velocity
                 .x
-                .min(meta.stats.walk_speed * control.move_direction.x);
+                .min(meta.stats.walk_speed * speed_multiplier * control.move_direction.x);
         } else {
             body.velocity.x = body
                 .velocity
                 .x
-                .max(meta.stats.walk_speed * control.move_direction.x);
+                .max(meta.stats.walk_speed * speed_multiplier * control.move_direction.x);
         }
 
         // Point in movement direction
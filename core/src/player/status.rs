@@ -0,0 +1,248 @@
+//! Temporary status effects on players, such as being slowed, burned, or stunned.
+//!
+//! Effects are applied with [`PlayerCommand::apply_status`] and kept in the player's
+//! [`StatusEffects`]. The walk and midair states read the slow effect, and the stunned state takes
+//! over the player while they are stunned.
+
+use super::*;
+
+pub fn install(session: &mut CoreSession) {
+    session
+        .stages
+        .add_system_to_stage(CoreStage::PostUpdate, update_status_effects)
+        .add_system_to_stage(CoreStage::Last, tint_players_with_status_effects);
+}
+
+/// The maximum number of different status effects a player can have at once.
+pub const MAX_STATUS_EFFECTS: usize = 3;
+
+/// The amount of burn damage that kills a player.
+///
+/// Players don't have health, so burns add up their damage until it reaches this, and then the
+/// player dies.
+pub const BURN_DAMAGE_TO_KILL: f32 = 1.0;
+
+/// A kind of status effect.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusEffectKind {
+    /// Slows the player's movement down by the strength of the effect, where `1.0` stops them.
+    Slow,
+    /// Damages the player by the strength of the effect each second.
+    Burn,
+    /// Stops the player from doing anything.
+    Stun,
+}
+
+impl BonesBevyAssetLoad for StatusEffectKind {}
+
+impl StatusEffectKind {
+    /// The color the player is tinted while they have the effect.
+    pub fn tint(&self) -> Color {
+        match self {
+            StatusEffectKind::Slow => Color::rgb(0.6, 0.8, 1.0),
+            StatusEffectKind::Burn => Color::rgb(1.0, 0.55, 0.35),
+            StatusEffectKind::Stun => Color::rgb(1.0, 1.0, 0.5),
+        }
+    }
+}
+
+/// A status effect on a player.
+///
+/// This may also be used in element metadata, for items and elements that apply status effects.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    /// How many more frames the effect lasts.
+    pub remaining_frames: u32,
+    /// How strong the effect is. What this means depends on the [`StatusEffectKind`].
+    pub strength: f32,
+}
+
+/// Component containing the status effects on a player.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H16KEVS8RJCWQ40MWHVZA5Q6"]
+pub struct StatusEffects {
+    effects: [Option<StatusEffect>; MAX_STATUS_EFFECTS],
+    /// The burn damage the player has taken so far.
+    pub burn_damage: f32,
+}
+
+impl StatusEffects {
+    /// Apply an effect.
+    ///
+    /// If the player already has an effect of the same kind, it lasts for the longer of the two
+    /// durations, with the stronger of the two strengths. If the player already has the maximum
+    /// number of effects, the effect that would run out first is replaced.
+    pub fn apply(&mut self, effect: StatusEffect) {
+        if let Some(existing) = self
+            .effects
+            .iter_mut()
+            .flatten()
+            .find(|x| x.kind == effect.kind)
+        {
+            existing.remaining_frames = existing.remaining_frames.max(effect.remaining_frames);
+            existing.strength = existing.strength.max(effect.strength);
+            return;
+        }
+
+        let slot = self
+            .effects
+            .iter()
+            .position(|x| x.is_none())
+            .or_else(|| {
+                (0..MAX_STATUS_EFFECTS).min_by_key(|i| {
+                    self.effects[*i]
+                        .map(|x| x.remaining_frames)
+                        .unwrap_or_default()
+                })
+            })
+            .unwrap();
+        self.effects[slot] = Some(effect);
+    }
+
+    /// Get the effect of the given kind, if the player has it.
+    pub fn get(&self, kind: StatusEffectKind) -> Option<&StatusEffect> {
+        self.iter().find(|x| x.kind == kind)
+    }
+
+    /// Iterate over the current effects.
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.effects.iter().flatten()
+    }
+
+    /// Whether the player has no effects.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Remove all effects.
+    pub fn clear(&mut self) {
+        *self = default();
+    }
+
+    /// Count down the effects by one frame, removing the ones that have run out.
+    pub fn tick(&mut self) {
+        for slot in &mut self.effects {
+            if let Some(effect) = slot {
+                effect.remaining_frames = effect.remaining_frames.saturating_sub(1);
+                if effect.remaining_frames == 0 {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    /// The amount to multiply the player's movement speed by.
+    pub fn move_speed_multiplier(&self) -> f32 {
+        self.get(StatusEffectKind::Slow)
+            .map(|x| (1.0 - x.strength).clamp(0.0, 1.0))
+            .unwrap_or(1.0)
+    }
+
+    /// Whether the player is stunned.
+    pub fn is_stunned(&self) -> bool {
+        self.get(StatusEffectKind::Stun).is_some()
+    }
+
+    /// The color to tint the player with, from the first of their effects.
+    pub fn tint(&self) -> Color {
+        self.iter()
+            .next()
+            .map(|x| x.kind.tint())
+            .unwrap_or(Color::WHITE)
+    }
+}
+
+/// Count down the status effects on players, and burn the players that are burning.
+fn update_status_effects(
+    entities: Res<Entities>,
+    mut commands: Commands,
+    core_meta: Res<CoreMetaArc>,
+    sudden_death: Res<SuddenDeath>,
+    players_killed: Comp<PlayerKilled>,
+    mut status_effects: CompMut<StatusEffects>,
+) {
+    for (player_ent, effects) in entities.iter_with(&mut status_effects) {
+        if players_killed.contains(player_ent) {
+            effects.clear();
+            continue;
+        }
+
+        if let Some(burn) = effects.get(StatusEffectKind::Burn) {
+            effects.burn_damage += burn.strength / crate::FPS;
+            // Any damage kills in sudden death, or with the one hit kill mutator
+            if effects.burn_damage >= BURN_DAMAGE_TO_KILL
+                || sudden_death.active
+                || core_meta.config.one_hit_kill
+            {
+                commands.add(PlayerCommand::kill(player_ent, None));
+            }
+        } else {
+            effects.burn_damage = 0.0;
+        }
+
+        effects.tick();
+    }
+}
+
+/// Tint players and their fins and faces with the color of their status effects.
+fn tint_players_with_status_effects(
+    entities: Res<Entities>,
+    status_effects: Comp<StatusEffects>,
+    player_layers: Comp<PlayerLayers>,
+    mut sprites: CompMut<AtlasSprite>,
+) {
+    for (player_ent, (effects, layers)) in entities.iter_with((&status_effects, &player_layers)) {
+        let tint = effects.tint();
+        for ent in [player_ent, layers.fin_ent, layers.face_ent] {
+            if let Some(sprite) = sprites.get_mut(ent) {
+                let mut color = tint;
+                color.set_a(sprite.color.a());
+                sprite.color = color;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(kind: StatusEffectKind, remaining_frames: u32, strength: f32) -> StatusEffect {
+        StatusEffect {
+            kind,
+            remaining_frames,
+            strength,
+        }
+    }
+
+    #[test]
+    fn effects_of_the_same_kind_stack() {
+        let mut effects = StatusEffects::default();
+        effects.apply(effect(StatusEffectKind::Slow, 10, 0.5));
+        effects.apply(effect(StatusEffectKind::Slow, 30, 0.25));
+
+        assert_eq!(effects.iter().count(), 1);
+        assert_eq!(
+            effects.get(StatusEffectKind::Slow),
+            Some(&effect(StatusEffectKind::Slow, 30, 0.5))
+        );
+    }
+
+    #[test]
+    fn effects_run_out() {
+        let mut effects = StatusEffects::default();
+        effects.apply(effect(StatusEffectKind::Stun, 2, 1.0));
+        effects.apply(effect(StatusEffectKind::Slow, 3, 0.5));
+
+        effects.tick();
+        assert!(effects.is_stunned());
+        effects.tick();
+        assert!(!effects.is_stunned());
+        assert_eq!(effects.move_speed_multiplier(), 0.5);
+        effects.tick();
+        assert!(effects.is_empty());
+    }
+}
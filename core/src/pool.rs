@@ -0,0 +1,234 @@
+//! Entity pooling for short-lived entities like bullets and particles.
+//!
+//! Instead of killing a pooled entity, it is released back into the [`EntityPool`], which strips
+//! its components at the end of the frame so that the next spawn can reuse it. This avoids creating
+//! and killing entities every time a rapid-fire weapon shoots. The pool only keeps up to
+//! [`MAX_FREE_ENTITIES`] free entities, and kills the rest, so that a burst of particles doesn't
+//! leave empty entities in the world for good.
+//!
+//! Components that may be added to pooled entities must be registered in the module's `install()`
+//! function, so that they are removed from the entities released back into the pool:
+//!
+//! ```ignore
+//! pub fn install(session: &mut CoreSession) {
+//!     session.pooled_components.register::<MyComponent>();
+//! }
+//! ```
+
+use crate::prelude::*;
+
+/// The most entities that the [`EntityPool`] keeps free for reuse. Entities released when the pool
+/// is full are killed instead.
+pub const MAX_FREE_ENTITIES: usize = 256;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<EntityPool>();
+
+    session.pooled_components.register::<Transform>();
+    session.pooled_components.register::<AtlasSprite>();
+    session.pooled_components.register::<AnimatedSprite>();
+    session.pooled_components.register::<Lifetime>();
+}
+
+/// Marker component for entities that are returned to the [`EntityPool`] instead of being killed.
+#[derive(Clone, Copy, Debug, Default, TypeUlid)]
+#[ulid = "01H4V3K8QZ2N7MBX5DFT9RCW6E"]
+pub struct Pooled;
+
+/// Resource containing the pooled entities that are free to be reused.
+///
+/// Entities are always taken from the end of the free list, so that the same entities are reused in
+/// the same order on every machine.
+#[derive(Clone, Debug, Default, TypeUlid)]
+#[ulid = "01H4V3KF7JW0XAY6NS4PGE8DQB"]
+pub struct EntityPool {
+    /// The entities that have been reset and may be spawned again.
+    free: Vec<Entity>,
+    /// The entities released this frame, that still need their components removed.
+    released: Vec<Entity>,
+}
+
+impl EntityPool {
+    /// Get an entity from the pool, only creating a new one if the pool is empty.
+    ///
+    /// The entity is marked [`Pooled`], and doesn't have any other components.
+    pub fn spawn(
+        &mut self,
+        entities: &mut Entities,
+        pooled: &mut ComponentStore<Pooled>,
+    ) -> Entity {
+        // Skip entities that were killed instead of being released
+        while let Some(entity) = self.free.pop() {
+            if entities.is_alive(entity) && pooled.contains(entity) {
+                return entity;
+            }
+        }
+
+        let entity = entities.create();
+        pooled.insert(entity, Pooled);
+        entity
+    }
+
+    /// Return an entity to the pool.
+    ///
+    /// The entity keeps its components until the end of the frame, and may be spawned again on the
+    /// next frame.
+    pub fn release(&mut self, entity: Entity) {
+        if !self.released.contains(&entity) && !self.free.contains(&entity) {
+            self.released.push(entity);
+        }
+    }
+
+    /// Whether the entity was released this frame, and will be reset at the end of the frame.
+    pub fn is_released(&self, entity: Entity) -> bool {
+        self.released.contains(&entity)
+    }
+
+    /// The number of entities that are free to be reused.
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+}
+
+/// The component types that are removed from entities released into the [`EntityPool`].
+#[derive(Clone, Default)]
+pub struct PooledComponents(Vec<fn(&World, &[Entity])>);
+
+impl PooledComponents {
+    /// Register a component type to be removed from released entities.
+    pub fn register<T: TypeUlid + Clone + Send + Sync + 'static>(&mut self) {
+        self.0.push(|world, entities| {
+            let components = world.components.get::<T>();
+            let mut components = components.borrow_mut();
+            for entity in entities {
+                components.remove(*entity);
+            }
+        });
+    }
+}
+
+/// Remove the registered components from the entities released this frame and make them free to be
+/// spawned again, killing the ones that don't fit in the pool.
+///
+/// This is run by [`CoreSession::advance()`] at the end of every frame, before the world is
+/// maintained, so that the components of the killed entities are removed along with the rest.
+pub fn reset_released(world: &World, components: &PooledComponents) {
+    let pool = world.resource::<EntityPool>();
+    let mut pool = pool.borrow_mut();
+    if pool.released.is_empty() {
+        return;
+    }
+
+    let entities = world.resource::<Entities>();
+    let mut entities = entities.borrow_mut();
+    // Skip entities that were already killed instead
+    let released = std::mem::take(&mut pool.released)
+        .into_iter()
+        .filter(|entity| entities.is_alive(*entity))
+        .collect::<Vec<_>>();
+    let kept = released
+        .len()
+        .min(MAX_FREE_ENTITIES.saturating_sub(pool.free.len()));
+    let (kept, killed) = released.split_at(kept);
+
+    for reset in &components.0 {
+        reset(world, kept);
+    }
+    pool.free.extend_from_slice(kept);
+    for entity in killed {
+        entities.kill(*entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, Default, TypeUlid)]
+    #[ulid = "01H5Q0D3ZC8W2V6XJ1T4NYKB7R"]
+    struct Registered;
+
+    fn pooled_components() -> PooledComponents {
+        let mut components = PooledComponents::default();
+        components.register::<Transform>();
+        components.register::<Registered>();
+        components
+    }
+
+    /// Spawn `count` pooled entities with a [`Transform`] and a [`Registered`] component, and
+    /// release them back into the pool.
+    fn spawn_and_release(world: &World, count: usize) -> Vec<Entity> {
+        world
+            .run_initialized_system(
+                move |mut entities: ResMut<Entities>,
+                      mut pool: ResMut<EntityPool>,
+                      mut pooled: CompMut<Pooled>,
+                      mut transforms: CompMut<Transform>,
+                      mut registered: CompMut<Registered>| {
+                    let spawned = (0..count)
+                        .map(|_| pool.spawn(&mut entities, &mut pooled))
+                        .collect::<Vec<_>>();
+                    for entity in &spawned {
+                        transforms.insert(*entity, default());
+                        registered.insert(*entity, Registered);
+                        pool.release(*entity);
+                    }
+                    Ok(spawned)
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn reused_entities_have_no_components_left() {
+        let mut world = World::new();
+        world.init_resource::<EntityPool>();
+        let components = pooled_components();
+
+        let released = spawn_and_release(&world, 1)[0];
+        reset_released(&world, &components);
+        world.maintain();
+
+        world
+            .run_initialized_system(
+                move |mut entities: ResMut<Entities>,
+                      mut pool: ResMut<EntityPool>,
+                      mut pooled: CompMut<Pooled>,
+                      transforms: Comp<Transform>,
+                      registered: Comp<Registered>| {
+                    assert_eq!(pool.free_count(), 1);
+                    let entity = pool.spawn(&mut entities, &mut pooled);
+                    assert_eq!(pool.free_count(), 0);
+                    assert_eq!(entity, released, "The released entity should be reused");
+                    assert!(pooled.contains(entity));
+                    assert!(!transforms.contains(entity));
+                    assert!(!registered.contains(entity));
+                    Ok(())
+                },
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn entities_that_dont_fit_in_the_pool_are_killed() {
+        let mut world = World::new();
+        world.init_resource::<EntityPool>();
+        let components = pooled_components();
+
+        let released = spawn_and_release(&world, MAX_FREE_ENTITIES + 10);
+        reset_released(&world, &components);
+        world.maintain();
+
+        let entities = world.resource::<Entities>();
+        let entities = entities.borrow();
+        assert_eq!(
+            world.resource::<EntityPool>().borrow().free_count(),
+            MAX_FREE_ENTITIES
+        );
+        let alive = released
+            .iter()
+            .filter(|entity| entities.is_alive(**entity))
+            .count();
+        assert_eq!(alive, MAX_FREE_ENTITIES);
+    }
+}
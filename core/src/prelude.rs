@@ -2,9 +2,11 @@
 
 pub use {
     crate::{
-        attachment::*, bullet::*, camera::*, damage::*, debug::*, debug::*, elements::*,
-        globals::*, input::*, item::*, item::*, lifetime::*, map::*, metadata::*, physics::*,
-        player::*, session::*, utils::*, MAX_PLAYERS,
+        attachment::*, audio::*, breakable_tiles::*, bullet::*, camera::*, corpse::*, damage::*,
+        debug::*, debug::*, elements::*, force_region::*, globals::*, hot_reload::*, input::*,
+        inspector::*, item::*, item::*, knockback::*, lifetime::*, map::*, match_state::*,
+        metadata::*, mutators::*, overtime::*, particles::*, physics::*, player::*, pool::*,
+        replay::*, score::*, session::*, stocks::*, utils::*, MAX_PLAYERS,
     },
     bones_bevy_asset::{BevyAssets, BonesBevyAsset, BonesBevyAssetLoad},
     bones_lib::prelude::*,
@@ -1,4 +1,9 @@
 //! Global, deterministic random resource.
+//!
+//! All of the randomness in the core session must come from the [`GlobalRng`], which starts from
+//! the [`seed`][CoreSessionInfo::seed] of the session. Any other source of random numbers would be
+//! different for every peer in a network game, and the game would desync. Debug builds check for
+//! this by running some of the frames twice, in [`CoreSession::advance()`].
 
 use crate::prelude::*;
 pub use turborand::prelude::*;
@@ -14,8 +19,102 @@ pub fn install(session: &mut CoreSession) {
 #[ulid = "01GQ0K6DDA9KKQTM3WDK1R91TE"]
 pub struct GlobalRng(AtomicRng);
 
+impl GlobalRng {
+    /// The seed used when the session doesn't need a particular one, like in the map editor.
+    pub const SEED: u64 = 7;
+
+    /// Create a random number generator that starts with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self(AtomicRng::with_seed(seed))
+    }
+}
+
 impl Default for GlobalRng {
     fn default() -> Self {
-        Self(AtomicRng::with_seed(7))
+        Self::new(Self::SEED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use crate::{prelude::*, testing::*};
+
+    use super::GlobalRng;
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "All randomness in the game systems must come from the `GlobalRng`")]
+    fn frames_are_checked_for_outside_randomness() {
+        let mut session = TestSession::new(TestAssets::new(), core_meta(), room_map(10, 10), &[]);
+
+        // Stands in for random numbers from outside of the session, which are different each time
+        // that the frame runs.
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let core = &mut session.session;
+        core.stages
+            .add_system_to_stage(CoreStage::Update, |rng: Res<GlobalRng>| {
+                for _ in 0..RUNS.fetch_add(1, Ordering::Relaxed) {
+                    rng.gen_u64();
+                }
+            });
+        for stage in &mut core.stages.stages {
+            stage.initialize(&mut core.world);
+        }
+
+        session.step(1);
+    }
+
+    /// Make sure that no core module gets its random numbers from somewhere other than the
+    /// [`GlobalRng`][super::GlobalRng].
+    #[test]
+    fn core_only_uses_global_rng() {
+        // Split up so that this file doesn't match itself
+        let forbidden = [
+            ["thread", "_rng"].concat(),
+            ["rand", "::random"].concat(),
+            ["from", "_entropy"].concat(),
+            ["Rng", "::new()"].concat(),
+        ];
+
+        fn check_dir(dir: &Path, forbidden: &[String], found: &mut Vec<String>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    check_dir(&path, forbidden, found);
+                    continue;
+                }
+                if path.extension().map_or(true, |ext| ext != "rs") {
+                    continue;
+                }
+                let source = std::fs::read_to_string(&path).unwrap();
+                for (i, line) in source.lines().enumerate() {
+                    if line.trim_start().starts_with("//") {
+                        continue;
+                    }
+                    for pattern in forbidden {
+                        if line.contains(pattern.as_str()) {
+                            found.push(format!("{}:{}: {pattern}", path.display(), i + 1));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut found = Vec::new();
+        check_dir(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &forbidden,
+            &mut found,
+        );
+        assert!(
+            found.is_empty(),
+            "Randomness must come from the `GlobalRng`:\n{}",
+            found.join("\n")
+        );
     }
 }
@@ -0,0 +1,334 @@
+//! Match replay recording and playback.
+//!
+//! Because the core simulation is deterministic, the initial session info and the player inputs
+//! for every frame are all that is needed to reproduce a match exactly.
+
+use std::sync::Mutex;
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<ReplayPlayback>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, play_replay_frame)
+        .add_system_to_stage(CoreStage::First, record_replay_frame);
+}
+
+/// The version of the replay file format.
+///
+/// This must be bumped whenever [`ReplayData`] changes, or whenever the simulation changes in a way
+/// that would make old replays play back differently.
+pub const REPLAY_FORMAT_VERSION: u32 = 4;
+
+/// The file extension used for saved replays.
+pub const REPLAY_FILE_EXTENSION: &str = "jumpyreplay";
+
+/// The magic bytes at the beginning of every replay file.
+const REPLAY_MAGIC: &[u8; 8] = b"JUMPYRPL";
+
+/// Errors that may occur when loading a replay.
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The data is not a replay file.
+    NotAReplay,
+    /// The replay was recorded with a different version of the replay format.
+    UnsupportedVersion(u32),
+    /// The replay data could not be deserialized.
+    Deserialize(postcard::Error),
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::NotAReplay => write!(f, "Data is not a replay file"),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "Replay format version {version} is not supported, \
+                expected version {REPLAY_FORMAT_VERSION}"
+            ),
+            ReplayError::Deserialize(e) => write!(f, "Could not deserialize replay: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// A recorded match: everything needed to re-simulate it from the beginning.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReplayData {
+    /// Metadata for the map the match was played on.
+    pub map_meta: MapMeta,
+    /// The player selections.
+    pub player_info: [Option<ReplayPlayerInfo>; MAX_PLAYERS],
+    /// The mutators the match was played with.
+    pub mutators: Mutators,
+    /// The seed of the session's [`GlobalRng`][crate::random::GlobalRng].
+    pub rng_seed: u64,
+    /// The inputs of every player, for every frame.
+    pub frames: Vec<[ReplayInput; MAX_PLAYERS]>,
+    /// Whether or not recording stopped early because the replay hit its maximum length.
+    pub truncated: bool,
+}
+
+/// The serializable subset of [`GameSessionPlayerInfo`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayPlayerInfo {
+    pub handle: Handle<PlayerMeta>,
+    pub is_ai: bool,
+    pub ai_difficulty: AiDifficulty,
+}
+
+impl ReplayData {
+    /// Get the info needed to start a session that plays back this replay.
+    pub fn session_info(&self, meta: Arc<CoreMeta>) -> CoreSessionInfo {
+        CoreSessionInfo {
+            meta,
+            map_meta: self.map_meta.clone(),
+            player_info: std::array::from_fn(|i| {
+                self.player_info[i]
+                    .as_ref()
+                    .map(|player| GameSessionPlayerInfo {
+                        handle: player.handle.clone(),
+                        is_ai: player.is_ai,
+                        ai_difficulty: player.ai_difficulty,
+                    })
+            }),
+            mutators: self.mutators.clone(),
+            seed: self.rng_seed,
+        }
+    }
+
+    /// Serialize the replay, including the format version header.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.extend_from_slice(&REPLAY_FORMAT_VERSION.to_le_bytes());
+        postcard::to_extend(self, bytes).expect("Serialize replay")
+    }
+
+    /// Deserialize a replay, returning an error if it was saved with a different format version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let header_len = REPLAY_MAGIC.len() + 4;
+        if bytes.len() < header_len || &bytes[..REPLAY_MAGIC.len()] != REPLAY_MAGIC {
+            return Err(ReplayError::NotAReplay);
+        }
+
+        let version_bytes = bytes[REPLAY_MAGIC.len()..header_len].try_into().unwrap();
+        let version = u32::from_le_bytes(version_bytes);
+        if version != REPLAY_FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        postcard::from_bytes(&bytes[header_len..]).map_err(ReplayError::Deserialize)
+    }
+}
+
+/// A single player's control input for one frame, with the buttons packed into a bitfield.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct ReplayInput {
+    buttons: u16,
+    move_direction: Vec2,
+}
+
+impl ReplayInput {
+    const MOVING: u16 = 1 << 0;
+    const JUST_MOVED: u16 = 1 << 1;
+    const JUMP_PRESSED: u16 = 1 << 2;
+    const JUMP_JUST_PRESSED: u16 = 1 << 3;
+    const SHOOT_PRESSED: u16 = 1 << 4;
+    const SHOOT_JUST_PRESSED: u16 = 1 << 5;
+    const GRAB_PRESSED: u16 = 1 << 6;
+    const GRAB_JUST_PRESSED: u16 = 1 << 7;
+    const SLIDE_PRESSED: u16 = 1 << 8;
+    const SLIDE_JUST_PRESSED: u16 = 1 << 9;
+    const EMOTE_PRESSED: u16 = 1 << 10;
+    const EMOTE_JUST_PRESSED: u16 = 1 << 11;
+    const DASH_PRESSED: u16 = 1 << 12;
+    const DASH_JUST_PRESSED: u16 = 1 << 13;
+
+    pub fn from_control(control: &PlayerControl) -> Self {
+        let mut buttons = 0;
+        for (pressed, bit) in [
+            (control.moving, Self::MOVING),
+            (control.just_moved, Self::JUST_MOVED),
+            (control.jump_pressed, Self::JUMP_PRESSED),
+            (control.jump_just_pressed, Self::JUMP_JUST_PRESSED),
+            (control.shoot_pressed, Self::SHOOT_PRESSED),
+            (control.shoot_just_pressed, Self::SHOOT_JUST_PRESSED),
+            (control.grab_pressed, Self::GRAB_PRESSED),
+            (control.grab_just_pressed, Self::GRAB_JUST_PRESSED),
+            (control.slide_pressed, Self::SLIDE_PRESSED),
+            (control.slide_just_pressed, Self::SLIDE_JUST_PRESSED),
+            (control.emote_pressed, Self::EMOTE_PRESSED),
+            (control.emote_just_pressed, Self::EMOTE_JUST_PRESSED),
+            (control.dash_pressed, Self::DASH_PRESSED),
+            (control.dash_just_pressed, Self::DASH_JUST_PRESSED),
+        ] {
+            if pressed {
+                buttons |= bit;
+            }
+        }
+
+        Self {
+            buttons,
+            move_direction: control.move_direction,
+        }
+    }
+
+    pub fn to_control(&self) -> PlayerControl {
+        let pressed = |bit: u16| self.buttons & bit != 0;
+
+        PlayerControl {
+            move_direction: self.move_direction,
+            just_moved: pressed(Self::JUST_MOVED),
+            moving: pressed(Self::MOVING),
+            jump_pressed: pressed(Self::JUMP_PRESSED),
+            jump_just_pressed: pressed(Self::JUMP_JUST_PRESSED),
+            shoot_pressed: pressed(Self::SHOOT_PRESSED),
+            shoot_just_pressed: pressed(Self::SHOOT_JUST_PRESSED),
+            grab_pressed: pressed(Self::GRAB_PRESSED),
+            grab_just_pressed: pressed(Self::GRAB_JUST_PRESSED),
+            slide_pressed: pressed(Self::SLIDE_PRESSED),
+            slide_just_pressed: pressed(Self::SLIDE_JUST_PRESSED),
+            emote_pressed: pressed(Self::EMOTE_PRESSED),
+            emote_just_pressed: pressed(Self::EMOTE_JUST_PRESSED),
+            dash_pressed: pressed(Self::DASH_PRESSED),
+            dash_just_pressed: pressed(Self::DASH_JUST_PRESSED),
+        }
+    }
+}
+
+/// Resource that records the player inputs for every frame of the session.
+///
+/// The default recorder doesn't record anything, which is what replay playback sessions use.
+///
+/// The recorded data is shared between clones of the resource so that snapshotting the world for
+/// network rollback doesn't copy the whole buffer. Frames are stored by frame number, so frames
+/// that are re-simulated after a rollback replace the ones that were recorded before it.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H0MZ74V797WF62AYGQWEVQ1M"]
+pub struct ReplayRecorder {
+    /// The frame that will be recorded next.
+    frame: usize,
+    /// The maximum number of frames to record.
+    max_frames: usize,
+    data: Arc<Mutex<ReplayData>>,
+}
+
+impl ReplayRecorder {
+    /// Create a recorder for a session with the given info.
+    pub fn new(info: &CoreSessionInfo) -> Self {
+        let max_frames = (info.meta.config.max_replay_length.as_secs_f32() * crate::FPS) as usize;
+        let player_info = std::array::from_fn(|i| {
+            info.player_info[i].as_ref().map(|player| ReplayPlayerInfo {
+                handle: player.handle.clone(),
+                is_ai: player.is_ai,
+                ai_difficulty: player.ai_difficulty,
+            })
+        });
+
+        Self {
+            frame: 0,
+            max_frames,
+            data: Arc::new(Mutex::new(ReplayData {
+                map_meta: info.map_meta.clone(),
+                player_info,
+                mutators: info.mutators.clone(),
+                rng_seed: info.seed,
+                frames: Vec::new(),
+                truncated: false,
+            })),
+        }
+    }
+
+    /// Record the inputs for the current frame.
+    pub fn record(&mut self, player_inputs: &PlayerInputs) {
+        let mut data = self.data.lock().unwrap();
+
+        // Discard anything recorded for this frame or later, in case we were rolled back.
+        data.frames.truncate(self.frame);
+
+        if data.frames.len() >= self.max_frames {
+            data.truncated = true;
+            return;
+        }
+
+        data.frames.push(std::array::from_fn(|i| {
+            ReplayInput::from_control(&player_inputs.players[i].control)
+        }));
+        self.frame += 1;
+    }
+
+    /// Get a copy of the data recorded so far.
+    pub fn data(&self) -> ReplayData {
+        self.data.lock().unwrap().clone()
+    }
+}
+
+fn record_replay_frame(mut recorder: ResMut<ReplayRecorder>, player_inputs: Res<PlayerInputs>) {
+    recorder.record(&player_inputs);
+}
+
+/// Resource that feeds recorded inputs into the session when playing back a replay.
+///
+/// The default value doesn't play anything back, leaving the player inputs to be set from outside
+/// of the session as usual.
+#[derive(Clone, TypeUlid, Default)]
+#[ulid = "01H0GC99CHQAZ9GE238BGJNAMH"]
+pub struct ReplayPlayback {
+    /// The replay being played back, if any.
+    replay: Option<Arc<ReplayData>>,
+    /// The frame that will be played next.
+    frame: usize,
+}
+
+impl ReplayPlayback {
+    /// Create a playback resource that will play the given replay from the beginning.
+    pub fn new(replay: Arc<ReplayData>) -> Self {
+        Self {
+            replay: Some(replay),
+            frame: 0,
+        }
+    }
+
+    /// Get the replay being played back, if any.
+    pub fn replay(&self) -> Option<&Arc<ReplayData>> {
+        self.replay.as_ref()
+    }
+
+    /// Get the number of frames that have been played back.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Whether or not all of the recorded inputs have been played back.
+    ///
+    /// Always `false` if this isn't a replay session.
+    pub fn is_finished(&self) -> bool {
+        self.replay
+            .as_ref()
+            .map(|replay| self.frame >= replay.frames.len())
+            .unwrap_or(false)
+    }
+}
+
+fn play_replay_frame(
+    mut playback: ResMut<ReplayPlayback>,
+    mut player_inputs: ResMut<PlayerInputs>,
+) {
+    let Some(replay) = playback.replay.clone() else {
+        return;
+    };
+
+    // Once the inputs run out, let go of all the controls.
+    let frame = replay.frames.get(playback.frame);
+    for (i, player) in player_inputs.players.iter_mut().enumerate() {
+        player.control = frame
+            .map(|inputs| inputs[i].to_control())
+            .unwrap_or_default();
+    }
+
+    if frame.is_some() {
+        playback.frame += 1;
+    }
+}
@@ -0,0 +1,40 @@
+//! Match scoring, shared by the game modes that decide a winner.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<MatchScore>();
+}
+
+/// The outcome of a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchResult {
+    /// The player with the given index won the match.
+    Winner(usize),
+    /// Nobody won, for example because the last players standing were eliminated at the same time.
+    Draw,
+}
+
+/// Resource containing each player's score and the result of the match, once it is over.
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H2HSK7JM9REMZC68RG294BRH"]
+pub struct MatchScore {
+    /// The points each player has scored.
+    pub points: [f32; MAX_PLAYERS],
+    /// The result of the match, once it is over.
+    pub result: Option<MatchResult>,
+    /// Every player death in the match, in the order they happened.
+    pub kills: Vec<KillRecord>,
+}
+
+/// A player death recorded in the [`MatchScore`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KillRecord {
+    /// The index of the player that died.
+    pub victim: usize,
+    /// The index of the player credited with the kill, or [`None`] if the player died on their
+    /// own.
+    pub killer: Option<usize>,
+    /// The name of the element the killer used, if it is known.
+    pub weapon: Option<String>,
+}
@@ -1,6 +1,9 @@
 //! [`CoreSession`] implementation: the entrypoint for using `jumpy_core`.
 
-use crate::prelude::*;
+use crate::{
+    checksum::FrameChecksum, headless::CoreAssets, interpolation::TransformSnapshots, prelude::*,
+    random::GlobalRng,
+};
 
 /// Implementation of the Jumpy match session.
 ///
@@ -46,21 +49,47 @@ pub struct CoreSession {
     pub stages: SystemStages,
     /// The information necessary to initialize the session.
     pub info: CoreSessionInfo,
-    /// The number of seconds in simulation time between frames.
+    /// The number of seconds in simulation time between frames, which is always `1 /`
+    /// [`FPS`][crate::FPS].
     ///
     /// **Important Note:** This sets how much time advances in the game world whenever you call
     /// [`advance()`][Self::advance], irrespective of how much real-life time actually elapsed
     /// between your calls to `advance()`.
     ///
     /// This means that you must manually provide some sort of fixed-update logic in order to make
-    /// sure that `advance()` is called as many times per second as you desire.
+    /// sure that `advance()` is called [`FPS`][crate::FPS] times per second. The frames can then be
+    /// rendered smoothly at any frame rate with
+    /// [`interpolate_transforms()`][Self::interpolate_transforms].
     pub time_step: f32,
+    /// The entity transforms around the last frame, used to render the session between frames.
+    ///
+    /// This is kept outside of the [`world`][Self::world], so that it isn't part of the
+    /// simulation state that is saved and restored in network games.
+    pub transform_snapshots: TransformSnapshots,
+    /// The component types that are shown in the core world inspector.
+    ///
+    /// Game modules register their components here when they are installed.
+    pub inspectors: ComponentInspectors,
+    /// The component types that are removed from entities released into the [`EntityPool`].
+    ///
+    /// Game modules register the components they add to pooled entities when they are installed.
+    pub pooled_components: PooledComponents,
     /// Implementation detail.
     ///
     /// Used during [`advance()`][Self::advance] to borrow the bevy world.
     pub scratch_world: Option<::bevy::ecs::world::World>,
 }
 
+/// How often, in frames, debug builds check that running a frame twice gives the same result.
+///
+/// See [`CoreSession::advance()`].
+pub const DETERMINISM_CHECK_INTERVAL: u64 = 60;
+
+/// Resource containing the number of frames that the session has been advanced.
+#[derive(Clone, Copy, Debug, Default, TypeUlid, Deref, DerefMut)]
+#[ulid = "01H4QX8B3N5KDV7ZR2TWMF9GYC"]
+pub struct SessionFrame(pub u64);
+
 /// Information needed to start a game session.
 #[derive(Debug, Clone)]
 pub struct CoreSessionInfo {
@@ -70,15 +99,28 @@ pub struct CoreSessionInfo {
     pub map_meta: MapMeta,
     /// The player selections.
     pub player_info: [Option<GameSessionPlayerInfo>; MAX_PLAYERS],
+    /// The mutators that change the rules of the match.
+    ///
+    /// These are applied to a copy of the [`meta`][Self::meta] when the session starts.
+    pub mutators: Mutators,
+    /// The seed of the session's [`GlobalRng`].
+    ///
+    /// Everything random in the session, like the fish picked for AI players, comes from this
+    /// seed, so every peer in a network game must start with the same one.
+    pub seed: u64,
 }
 
 /// Info for a player in the [`CoreSessionInfo`] struct.
 #[derive(Debug, Clone)]
 pub struct GameSessionPlayerInfo {
     /// The asset handle for the player skin.
+    ///
+    /// This is ignored for AI players, which are given a random fish when the session starts.
     pub handle: Handle<PlayerMeta>,
     /// Whether or not the player is an AI player.
     pub is_ai: bool,
+    /// The difficulty of the AI, if this is an AI player.
+    pub ai_difficulty: AiDifficulty,
 }
 
 impl CoreSession {
@@ -91,6 +133,9 @@ impl CoreSession {
             scratch_world: Some(::bevy::ecs::world::World::new()),
             info: info.clone(),
             time_step: 1.0 / crate::FPS,
+            transform_snapshots: default(),
+            inspectors: default(),
+            pooled_components: default(),
         };
 
         // Install modules
@@ -103,29 +148,75 @@ impl CoreSession {
 
         // Initialize time resource
         session.world.init_resource::<Time>();
+        session.world.init_resource::<SessionFrame>();
         // Initialize bevy world resource with an empty bevy world
         session.world.init_resource::<BevyWorld>();
         // Set the map
         session
             .world
             .insert_resource(LoadedMap(Arc::new(info.map_meta)));
+        // Seed the random number generator
+        session.world.insert_resource(GlobalRng::new(info.seed));
 
         // Set player initial character selections
+        let players = &info.meta.players;
+        let rng = session.world.resource::<GlobalRng>();
+        let rng = rng.borrow();
         let player_inputs = session.world.resource::<PlayerInputs>();
         let mut player_inputs = player_inputs.borrow_mut();
         for i in 0..MAX_PLAYERS {
             if let Some(info) = info.player_info[i].take() {
+                // Pick the AI's fish from the session seed, so that every peer picks the same one
+                let handle = if info.is_ai && !players.is_empty() {
+                    players[rng.usize(0..players.len())].clone()
+                } else {
+                    info.handle
+                };
                 player_inputs.players[i].active = true;
-                player_inputs.players[i].selected_player = info.handle;
+                player_inputs.players[i].selected_player = handle;
                 player_inputs.players[i].is_ai = info.is_ai;
+                player_inputs.players[i].ai_difficulty = info.ai_difficulty;
             }
         }
 
-        session.set_metadata(info.meta);
+        session
+            .world
+            .insert_resource(ReplayRecorder::new(&session.info));
+
+        let meta = if info.mutators.is_empty() {
+            info.meta
+        } else {
+            Arc::new(info.mutators.apply(&info.meta))
+        };
+
+        // Count down to the start of the match
+        session
+            .world
+            .insert_resource(MatchState::new(meta.config.start_countdown_frames));
+
+        session.set_metadata(meta);
+
+        session
+    }
+
+    /// Create a new [`CoreSession`] that plays back a recorded replay.
+    ///
+    /// The session ignores any input set from outside of it and instead plays back the recorded
+    /// inputs, one frame per [`advance()`][Self::advance]. Once the recorded inputs run out,
+    /// [`ReplayPlayback::is_finished()`] will return `true`.
+    pub fn new_replay(meta: Arc<CoreMeta>, replay: ReplayData) -> Self {
+        let mut session = Self::new(replay.session_info(meta));
+        session.start_playback(Arc::new(replay));
 
         session
     }
 
+    fn start_playback(&mut self, replay: Arc<ReplayData>) {
+        // Don't record a replay of the replay
+        self.world.insert_resource(ReplayRecorder::default());
+        self.world.insert_resource(ReplayPlayback::new(replay));
+    }
+
     /// Set the game metadata.
     ///
     /// This may be used to change game metadata in the middle of the session.
@@ -133,6 +224,17 @@ impl CoreSession {
         self.world.insert_resource(CoreMetaArc(metadata));
     }
 
+    /// Update the existing instances of an element after its metadata asset was modified.
+    ///
+    /// See the [`hot_reload`][crate::hot_reload] module.
+    pub fn reload_element(&mut self, element: ::bevy::asset::HandleId) {
+        self.world
+            .resource::<ElementReloads>()
+            .borrow_mut()
+            .modified
+            .push(element);
+    }
+
     /// Provide a closure to update the game inputs.
     pub fn update_input<R, F: FnOnce(&mut PlayerInputs) -> R>(&mut self, update: F) -> R {
         let inputs = self.world.resource::<PlayerInputs>();
@@ -142,12 +244,76 @@ impl CoreSession {
     }
 
     pub fn restart(&mut self) {
+        let replay = self
+            .world
+            .resource::<ReplayPlayback>()
+            .borrow()
+            .replay()
+            .cloned();
         *self = Self::new(self.info.clone());
+
+        // Start replays over from the beginning
+        if let Some(replay) = replay {
+            self.start_playback(replay);
+        }
+    }
+
+    /// Show the entities `alpha` of the way between the last two frames, where `0.0` shows the
+    /// previous frame and `1.0` shows the current one.
+    ///
+    /// This changes the entity transforms in the [`world`][Self::world] until the next call to
+    /// [`advance()`][Self::advance] or [`restore_transforms()`][Self::restore_transforms]. See the
+    /// [`interpolation`][crate::interpolation] module.
+    pub fn interpolate_transforms(&mut self, alpha: f32) {
+        self.transform_snapshots.interpolate(&self.world, alpha);
+    }
+
+    /// Put back the actual entity transforms after they were changed by
+    /// [`interpolate_transforms()`][Self::interpolate_transforms].
+    ///
+    /// This must be done before the world is saved, such as for network rollback.
+    pub fn restore_transforms(&mut self) {
+        self.transform_snapshots.restore(&self.world);
     }
 
     /// Run a single simulation frame
-    pub fn advance(&mut self, bevy_world: &mut ::bevy::prelude::World) {
+    ///
+    /// The game systems read their metadata from the `assets`. See the [`headless`][crate::headless]
+    /// module for running a session without the Bevy asset server.
+    ///
+    /// In debug builds, every [`DETERMINISM_CHECK_INTERVAL`] frames the frame is run twice from the
+    /// same state, asserting that both runs end with the same [`FrameChecksum`]. This catches game
+    /// systems that get random numbers from somewhere other than the [`GlobalRng`], like
+    /// `thread_rng()`, which would make the peers of a network game desync.
+    pub fn advance(&mut self, assets: &mut impl CoreAssets) {
         puffin::profile_function!();
+        self.transform_snapshots.take_previous(&self.world);
+
+        #[cfg(debug_assertions)]
+        {
+            let frame = **self.world.resource::<SessionFrame>().borrow();
+            if frame % DETERMINISM_CHECK_INTERVAL == 0 {
+                let initial_state = self.world.clone();
+                self.run_frame(assets);
+                let first_checksum = *self.world.resource::<FrameChecksum>().borrow();
+                self.world = initial_state;
+                self.run_frame(assets);
+                let second_checksum = *self.world.resource::<FrameChecksum>().borrow();
+                debug_assert_eq!(
+                    first_checksum, second_checksum,
+                    "Frame {frame} ended differently when it was run twice. All randomness in the \
+                    game systems must come from the `GlobalRng`."
+                );
+                return;
+            }
+        }
+
+        self.run_frame(assets);
+    }
+
+    /// Run the game systems for one frame.
+    fn run_frame(&mut self, assets: &mut impl CoreAssets) {
+        let bevy_world = assets.bevy_world();
 
         // Update the window resource
         let window_resource = self.world.resource::<Window>();
@@ -166,18 +332,30 @@ impl CoreSession {
             std::mem::swap(&mut scratch_world, bevy_world);
             world_resource.0 = Some(scratch_world);
         }
-        for stage in &mut self.stages.stages {
-            let stage_name = stage.name();
-            puffin::profile_scope!("Run Stage", stage_name);
-            stage.run(&mut self.world).unwrap();
+        // Skip the whole frame during a hit pause, and most frames during the kill cam. This
+        // freezes or slows down the game without falling out of step with the frames of a network
+        // game.
+        let hit_paused = self.world.resource::<HitPause>().borrow_mut().tick();
+        let kill_cam_skipped = self.world.resource::<KillCam>().borrow_mut().tick();
+        if !hit_paused && !kill_cam_skipped {
+            for stage in &mut self.stages.stages {
+                let stage_name = stage.name();
+                puffin::profile_scope!("Run Stage", stage_name);
+                stage.run(&mut self.world).unwrap();
+            }
+
+            // Advance the simulation time
+            let time_resource = self.world.resource::<Time>();
+            time_resource
+                .borrow_mut()
+                .advance_exact(std::time::Duration::from_secs_f32(self.time_step));
         }
 
-        // Advance the simulation time
-        let time_resource = self.world.resource::<Time>();
-        time_resource
-            .borrow_mut()
-            .advance_exact(std::time::Duration::from_secs_f32(self.time_step));
+        // Frames skipped for a hit pause or the kill cam are counted too, so that the count matches
+        // the frames of a network game.
+        **self.world.resource::<SessionFrame>().borrow_mut() += 1;
 
+        crate::pool::reset_released(&self.world, &self.pooled_components);
         self.world.maintain();
 
         // Swap the bevy world back to normal.
@@ -202,15 +380,20 @@ impl CoreSession {
     /// Export the current map metadata by scanning the world entities. This means that the export
     /// will include any modifications to the map made at runtime ( most likely by the editor ).
     pub fn export_map(&self) -> MapMeta {
+        // Export where the elements actually are, not where they are shown between frames
+        let actual_transforms = self.transform_snapshots.actual_transforms();
         let export_system =
             move |map_meta: Res<SpawnedMapMeta>,
                   entities: Res<Entities>,
                   tile_layers: Comp<TileLayer>,
                   spawned_map_layer_metas: Comp<SpawnedMapLayerMeta>,
                   tile_collisions: Comp<TileCollisionKind>,
+                  tile_surfaces: Comp<TileSurface>,
                   tiles: Comp<Tile>,
                   transforms: Comp<Transform>,
-                  element_handles: Comp<ElementHandle>| {
+                  element_handles: Comp<ElementHandle>,
+                  element_properties: Comp<ElementProperties>,
+                  element_waypoints: Comp<ElementWaypoints>| {
                 let mut layers = map_meta
                     .layer_names
                     .iter()
@@ -249,6 +432,7 @@ impl CoreSession {
                                     pos: UVec2::new(x, y),
                                     idx: tile.idx as u32,
                                     collision,
+                                    surface: tile_surfaces.get(ent).copied().unwrap_or_default(),
                                 }
                             })
                         })
@@ -256,41 +440,73 @@ impl CoreSession {
                 }
 
                 // Export the entity layers
-                for (_ent, (element_handle, transform, layer_meta)) in
+                for (ent, (element_handle, transform, layer_meta)) in
                     entities.iter_with((&element_handles, &transforms, &spawned_map_layer_metas))
                 {
                     let layer_idx = layer_meta.layer_idx;
                     let layer = &mut layers[layer_idx];
 
+                    let transform = actual_transforms.get(&ent).unwrap_or(transform);
                     layer.elements.push(ElementSpawn {
                         pos: transform.translation.truncate(),
                         element: element_handle.0.clone(),
+                        properties: element_properties.get(ent).cloned().unwrap_or_default(),
+                        waypoints: element_waypoints.get(ent).cloned().unwrap_or_default(),
                     });
                 }
 
                 // Return complete map metadata
                 Ok(MapMeta {
                     name: map_meta.name.to_string(),
+                    author: map_meta.author.as_deref().map(String::from),
+                    recommended_players: map_meta.recommended_players,
                     background: (*map_meta.background).clone(),
                     background_color: map_meta.background_color,
                     grid_size: map_meta.grid_size,
                     tile_size: map_meta.tile_size,
                     layers,
+                    respawn: map_meta.respawn.clone(),
+                    camera_bounds: map_meta.camera_bounds,
+                    music: map_meta.music.clone(),
+                    boundary: map_meta.boundary,
                 })
             };
 
         self.world.run_initialized_system(export_system).unwrap()
     }
 
+    /// Get the replay data recorded for this session so far.
+    pub fn replay_data(&self) -> ReplayData {
+        self.world.resource::<ReplayRecorder>().borrow().data()
+    }
+
+    /// Get a textual dump of the world state, for finding where two sessions diverge.
+    ///
+    /// See [`dump_world()`].
+    pub fn dump_world(&self) -> String {
+        if self.transform_snapshots.is_interpolated() {
+            dump_world(&self.snapshot(), &self.inspectors)
+        } else {
+            dump_world(&self.world, &self.inspectors)
+        }
+    }
+
     /// Snapshot the world state
+    ///
+    /// The snapshot has the actual entity transforms, even while they are shown between frames
+    /// with [`interpolate_transforms()`][Self::interpolate_transforms].
     pub fn snapshot(&self) -> World {
-        self.world.clone()
+        let world = self.world.clone();
+        self.transform_snapshots.write_actual_transforms(&world);
+        world
     }
 
     /// Restore the world state
     ///
     /// Will write the current state to `world`.
     pub fn restore(&mut self, world: &mut World) {
+        // The interpolated transforms belong to the current state
+        self.restore_transforms();
         std::mem::swap(&mut self.world, world)
     }
 }
@@ -0,0 +1,91 @@
+//! Stock matches, where each player only has a limited number of lives.
+//!
+//! Stock matches are enabled by setting [`CoreConfigMeta::stock_lives`]. Every time a player dies
+//! they lose a life, and once they are out of lives they are eliminated and won't respawn. The
+//! match is over when there is only one player left standing, or nobody at all if the last
+//! players die on the same frame.
+
+use crate::prelude::*;
+
+pub fn install(session: &mut CoreSession) {
+    session.world.init_resource::<PlayerStocks>();
+    session
+        .stages
+        .add_system_to_stage(CoreStage::First, update_stocks);
+}
+
+/// Resource tracking the lives of each player in a stock match.
+#[derive(Clone, Debug, TypeUlid, Default)]
+#[ulid = "01H3XQJFJD3MVF93DWZB8CDSH8"]
+pub struct PlayerStocks {
+    /// How many lives each player has left, or [`None`] if the player isn't in the match.
+    pub lives: [Option<u32>; MAX_PLAYERS],
+    /// Whether each player was alive on the last frame, used to detect when they die.
+    pub was_alive: [bool; MAX_PLAYERS],
+}
+
+impl PlayerStocks {
+    /// Whether the player has run out of lives.
+    pub fn is_eliminated(&self, player_idx: usize) -> bool {
+        self.lives[player_idx] == Some(0)
+    }
+}
+
+fn update_stocks(
+    entities: Res<Entities>,
+    core_meta: Res<CoreMetaArc>,
+    player_inputs: Res<PlayerInputs>,
+    mut stocks: ResMut<PlayerStocks>,
+    mut score: ResMut<MatchScore>,
+    player_indexes: Comp<PlayerIdx>,
+    players_killed: Comp<PlayerKilled>,
+) {
+    let Some(stock_lives) = core_meta.config.stock_lives else {
+        return;
+    };
+    let stocks = &mut *stocks;
+    if score.result.is_some() {
+        return;
+    }
+
+    // A player counts as dead as soon as they are killed, so that players who die at the same
+    // time lose their lives on the same frame, even if their death animations differ.
+    let mut alive = [false; MAX_PLAYERS];
+    for (ent, idx) in entities.iter_with(&player_indexes) {
+        if !players_killed.contains(ent) {
+            alive[idx.0] = true;
+        }
+    }
+
+    for (i, player) in player_inputs.players.iter().enumerate() {
+        if !player.active {
+            continue;
+        }
+        let lives = stocks.lives[i].get_or_insert(stock_lives);
+        if stocks.was_alive[i] && !alive[i] {
+            *lives = lives.saturating_sub(1);
+            if *lives == 0 {
+                info!(player_idx = i, "Player eliminated");
+            }
+        }
+        stocks.was_alive[i] = alive[i];
+    }
+
+    // Players that leave the match don't count towards the players left standing
+    let participants = (0..MAX_PLAYERS)
+        .filter(|i| player_inputs.players[*i].active && stocks.lives[*i].is_some())
+        .collect::<Vec<_>>();
+    if participants.len() < 2 {
+        return;
+    }
+    let remaining = participants
+        .into_iter()
+        .filter(|i| !stocks.is_eliminated(*i))
+        .collect::<Vec<_>>();
+
+    score.result = match remaining[..] {
+        [] => Some(MatchResult::Draw),
+        [winner] => Some(MatchResult::Winner(winner)),
+        _ => None,
+    };
+}
@@ -0,0 +1,941 @@
+//! Helpers for running the game simulation in automated tests.
+//!
+//! A [`TestSession`] runs a [`CoreSession`] without a renderer, and without loading any asset
+//! files. The metadata that the game would normally load from YAML, such as the [`PlayerMeta`] and
+//! the [`ElementMeta`] of the map elements, is built in code and added to the [`TestAssets`]
+//! instead. Most tests start from an empty room, with a [`TestSessionBuilder`]:
+//!
+//! ```ignore
+//! let mut builder = TestSessionBuilder::room(20, 10);
+//! builder.add_player(Vec2::new(48.0, 48.0));
+//! builder.add_element("/grenade", testing::grenade_meta(), Vec2::new(96.0, 40.0));
+//!
+//! let mut session = builder.build();
+//! session.set_input(0, TestInput::moving(Vec2::X));
+//! session.step(60);
+//! assert!(session.player_position(0).unwrap().x > 48.0);
+//! ```
+//!
+//! Network games only stay in sync if the simulation is deterministic, which is checked by
+//! [`assert_deterministic()`] and [`assert_rollback_deterministic()`]. They play the same inputs in
+//! several sessions and compare the components registered in the [`ComponentInspectors`], so new
+//! components that affect gameplay should be registered there to be checked too.
+
+use crate::{headless::HeadlessAssets, prelude::*, random::GlobalRng};
+
+/// The size of the tiles in the maps made by [`room_map()`].
+pub const TILE_SIZE: f32 = 16.0;
+
+/// Bevy asset storage for the metadata used by a [`TestSession`].
+///
+/// The game systems read their metadata out of the Bevy world that is passed to
+/// [`CoreSession::advance()`], so the metadata is added to that world directly, under the path of
+/// the handle that the game will look it up with.
+pub type TestAssets = HeadlessAssets;
+
+/// Get [`CoreMeta`] for tests, with the physics of the game's default metadata.
+///
+/// The match starts right away, without a countdown, and each player always spawns at the spawn
+/// point with the same index as the player.
+pub fn core_meta() -> CoreMeta {
+    CoreMeta {
+        physics: PhysicsMeta {
+            gravity: 0.6,
+            terminal_velocity: 30.0,
+            friction_lerp: 0.85,
+            stop_threshold: 1.0,
+            sticky_jump_presses: 2,
+            gravity_scale: 1.0,
+            min_bounciness: 0.0,
+            crush_tolerance_frames: 2,
+        },
+        config: CoreConfigMeta {
+            start_countdown_frames: 0,
+            spawn_point_selection: SpawnPointSelection::FixedPerPlayer,
+            points_to_win: 100.0,
+            max_replay_length: std::time::Duration::from_secs(60),
+            disconnect_grace_period: std::time::Duration::from_secs(30),
+            player_speed_multiplier: 1.0,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// Get [`PlayerMeta`] for tests, with the stats of the default fish.
+///
+/// Every animation that the game plays has a single frame, so that the player has no missing
+/// animations.
+pub fn player_meta() -> PlayerMeta {
+    let emote_animation = key!("emote");
+    let mut body_animations = required_player_animations();
+    body_animations.push(emote_animation);
+    let mut face_animations = body_animations.clone();
+    face_animations.extend(Emote::ALL.map(|emote| emote.animation_key()));
+
+    let sprites = |keys: &[Key]| {
+        Arc::new(
+            keys.iter()
+                .map(|key| {
+                    (
+                        *key,
+                        AnimatedSprite {
+                            frames: Arc::from([0]),
+                            fps: 1.0,
+                            ..default()
+                        },
+                    )
+                })
+                .collect::<std::collections::HashMap<_, _>>(),
+        )
+    };
+
+    PlayerMeta {
+        name: "Test Fish".into(),
+        body_size: Vec2::new(32.0, 48.0),
+        slide_body_size: Vec2::new(48.0, 32.0),
+        gravity: 0.6,
+        emote_animation,
+        sounds: default(),
+        stats: PlayerStatsMeta {
+            jump_speed: 11.0,
+            slow_fall_speed: 1.5,
+            air_speed: 6.0,
+            accel_air_speed: 1.0,
+            walk_speed: 6.0,
+            slowdown: 0.8,
+            accel_walk_speed: 1.0,
+            swim_speed: 4.0,
+            accel_swim_speed: 0.5,
+            swim_stroke_speed: 5.0,
+            ledge_grab_window: 8.0,
+            ledge_pull_up_frames: 12,
+            dash_speed: 14.0,
+            dash_frames: 10,
+            dash_cooldown_frames: 45,
+        },
+        layers: PlayerLayersMeta {
+            body: PlayerBodyLayerMeta {
+                atlas: default(),
+                animations: BodyAnimationsMeta {
+                    body_offsets: Arc::new(
+                        body_animations
+                            .iter()
+                            .map(|key| (*key, vec![Vec2::ZERO]))
+                            .collect(),
+                    ),
+                    frames: sprites(&body_animations),
+                },
+            },
+            fin: PlayerLayerMeta {
+                atlas: default(),
+                offset: Vec2::ZERO,
+                animations: sprites(&body_animations),
+            },
+            face: PlayerLayerMeta {
+                atlas: default(),
+                offset: Vec2::ZERO,
+                animations: sprites(&face_animations),
+            },
+        },
+    }
+}
+
+/// Get the [`ElementMeta`] of a player spawn point.
+pub fn player_spawner_meta() -> ElementMeta {
+    ElementMeta {
+        name: "Player Spawner".into(),
+        category: "Utility".into(),
+        builtin: BuiltinElementKind::PlayerSpawner,
+        ..default()
+    }
+}
+
+/// Get the [`ElementMeta`] of a grenade that explodes a second after it is lit.
+pub fn grenade_meta() -> ElementMeta {
+    ElementMeta {
+        name: "Grenade".into(),
+        category: "Weapons".into(),
+        builtin: BuiltinElementKind::Grenade {
+            body_diameter: 14.0,
+            fin_anim: key!("grab_2"),
+            grab_offset: Vec2::new(8.0, 0.0),
+            damage_region_size: Vec2::new(96.0, 64.0),
+            damage_region_lifetime: 0.5,
+            throw_velocity: 20.0,
+            explosion_lifetime: 0.5,
+            explosion_frames: 1,
+            explosion_fps: 1.0,
+            explosion_sound: default(),
+            explosion_volume: 0.0,
+            fuse_sound: default(),
+            fuse_sound_volume: 0.0,
+            fuse_time: 1.0,
+            can_rotate: false,
+            atlas: default(),
+            explosion_atlas: default(),
+            explosion_particles: None,
+            bounciness: 0.0,
+            angular_velocity: 0.0,
+        },
+        ..default()
+    }
+}
+
+/// Get the [`ElementMeta`] of a musket with four shots, that fires the given bullets.
+pub fn musket_meta(bullet_meta: Handle<BulletMeta>) -> ElementMeta {
+    ElementMeta {
+        name: "Musket".into(),
+        category: "Weapons".into(),
+        builtin: BuiltinElementKind::Musket {
+            grab_offset: Vec2::new(23.0, 0.0),
+            fin_anim: key!("grab_2"),
+            body_size: Vec2::new(32.0, 8.0),
+            bounciness: 0.3,
+            can_rotate: true,
+            throw_velocity: 6.0,
+            angular_velocity: 0.1,
+            atlas: default(),
+            max_ammo: 4,
+            cooldown: std::time::Duration::from_millis(600),
+            bullet_meta,
+            homing: None,
+            shoot_fps: 15.0,
+            shoot_lifetime: 0.2,
+            shoot_frames: 3,
+            shoot_sound_volume: 0.0,
+            empty_shoot_sound_volume: 0.0,
+            shoot_atlas: default(),
+            shoot_sound: default(),
+            empty_shoot_sound: default(),
+            shoot_particles: None,
+        },
+        ..default()
+    }
+}
+
+/// Get the [`BulletMeta`] of a musket ball that bounces once.
+pub fn bullet_meta() -> BulletMeta {
+    BulletMeta {
+        velocity: Vec2::new(10.0, 0.0),
+        body_diameter: 15.0,
+        lifetime: 1.0,
+        explosion_fps: 12.0,
+        explosion_frames: 3,
+        explosion_lifetime: 0.4,
+        bounces: 1,
+        bounce_speed_loss: 0.25,
+        ..default()
+    }
+}
+
+/// Create a map of an empty room, with a solid floor, ceiling, and walls around it.
+///
+/// The room is `width` by `height` tiles of [`TILE_SIZE`], including the walls, and has a single
+/// layer that elements may be added to with [`add_element()`].
+pub fn room_map(width: u32, height: u32) -> MapMeta {
+    let mut tiles = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                tiles.push(MapTileMeta {
+                    pos: UVec2::new(x, y),
+                    idx: 0,
+                    collision: TileCollisionKind::Solid,
+                    surface: default(),
+                });
+            }
+        }
+    }
+
+    MapMeta {
+        name: "Test Room".into(),
+        grid_size: UVec2::new(width, height),
+        tile_size: Vec2::splat(TILE_SIZE),
+        layers: vec![MapLayerMeta {
+            id: "room".into(),
+            tilemap: None,
+            tiles,
+            elements: vec![],
+        }],
+        ..default()
+    }
+}
+
+/// Add an element to the first layer of a map.
+pub fn add_element(map: &mut MapMeta, element: &Handle<ElementMeta>, pos: Vec2) {
+    map.layers[0].elements.push(ElementSpawn {
+        pos,
+        element: element.clone(),
+        ..default()
+    });
+}
+
+/// Builds a [`TestSession`] in a [`room_map()`], with players standing at the given spawn points.
+///
+/// Every player uses the same skin, which is the [`player_meta()`] unless it is replaced. The
+/// metadata and the map can be changed through the public fields before the session is built.
+pub struct TestSessionBuilder {
+    /// The metadata used by the session.
+    pub assets: TestAssets,
+    /// The core metadata, which starts out as the [`core_meta()`].
+    pub meta: CoreMeta,
+    /// The map that the session is played on.
+    pub map: MapMeta,
+    /// The skin of the players.
+    pub player: PlayerMeta,
+    /// The seed of the session's random numbers.
+    pub seed: u64,
+    spawner: Handle<ElementMeta>,
+    /// The AI difficulty of each player, or [`None`] for the players that are controlled by the
+    /// test.
+    players: Vec<Option<AiDifficulty>>,
+}
+
+impl TestSessionBuilder {
+    /// Start building a session in an empty room, `width` by `height` tiles in size.
+    pub fn room(width: u32, height: u32) -> Self {
+        let mut assets = TestAssets::new();
+        let spawner = assets.insert("/spawner", player_spawner_meta());
+        Self {
+            assets,
+            meta: core_meta(),
+            map: room_map(width, height),
+            player: player_meta(),
+            seed: GlobalRng::SEED,
+            spawner,
+            players: Vec::new(),
+        }
+    }
+
+    /// Add a player controlled by the test, who spawns at `pos`.
+    pub fn add_player(&mut self, pos: Vec2) -> &mut Self {
+        add_element(&mut self.map, &self.spawner, pos);
+        self.players.push(None);
+        self
+    }
+
+    /// Add an AI player, who spawns at `pos`.
+    pub fn add_ai_player(&mut self, pos: Vec2, difficulty: AiDifficulty) -> &mut Self {
+        add_element(&mut self.map, &self.spawner, pos);
+        self.players.push(Some(difficulty));
+        self
+    }
+
+    /// Add an element to the assets, under the given path, and place it in the map at `pos`.
+    pub fn add_element(&mut self, path: &str, meta: ElementMeta, pos: Vec2) -> Handle<ElementMeta> {
+        let element = self.assets.insert(path, meta);
+        add_element(&mut self.map, &element, pos);
+        element
+    }
+
+    /// Start the session, with the players in the order that they were added.
+    pub fn build(self) -> TestSession {
+        assert!(self.players.len() <= MAX_PLAYERS, "Too many players");
+        let mut assets = self.assets;
+        let player = assets.insert("/player", self.player);
+        let player_info = std::array::from_fn(|i| {
+            self.players.get(i).map(|ai| GameSessionPlayerInfo {
+                handle: player.clone(),
+                is_ai: ai.is_some(),
+                ai_difficulty: ai.unwrap_or_default(),
+            })
+        });
+        TestSession::with_player_info(assets, self.meta, self.map, player_info, self.seed)
+    }
+}
+
+/// The buttons held down by a player during a [`TestSession`] frame.
+///
+/// The `just_pressed` fields of the [`PlayerControl`] are worked out from the buttons held in the
+/// previous frame, like they are for real players.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TestInput {
+    pub move_direction: Vec2,
+    pub jump: bool,
+    pub shoot: bool,
+    pub grab: bool,
+    pub emote: bool,
+    pub dash: bool,
+}
+
+impl TestInput {
+    /// Hold the movement stick in the given direction, without pressing any buttons.
+    pub fn moving(direction: Vec2) -> Self {
+        Self {
+            move_direction: direction,
+            ..default()
+        }
+    }
+
+    fn apply(&self, control: &mut PlayerControl) {
+        control.jump_just_pressed = self.jump && !control.jump_pressed;
+        control.jump_pressed = self.jump;
+
+        control.shoot_just_pressed = self.shoot && !control.shoot_pressed;
+        control.shoot_pressed = self.shoot;
+
+        control.grab_just_pressed = self.grab && !control.grab_pressed;
+        control.grab_pressed = self.grab;
+
+        control.emote_just_pressed = self.emote && !control.emote_pressed;
+        control.emote_pressed = self.emote;
+
+        control.dash_just_pressed = self.dash && !control.dash_pressed;
+        control.dash_pressed = self.dash;
+
+        let was_moving = control.move_direction.length_squared() > f32::MIN_POSITIVE;
+        control.move_direction = self.move_direction;
+        let is_moving = control.move_direction.length_squared() > f32::MIN_POSITIVE;
+        control.just_moved = !was_moving && is_moving;
+    }
+}
+
+/// A [`CoreSession`] that is advanced with scripted player inputs, for testing gameplay.
+pub struct TestSession {
+    /// The game session.
+    pub session: CoreSession,
+    /// The metadata used by the session.
+    pub assets: TestAssets,
+    /// The input held by each player.
+    inputs: [TestInput; MAX_PLAYERS],
+}
+
+impl TestSession {
+    /// Start a session on the given map, with a player for each of the given skins.
+    ///
+    /// The skins and the map elements must have been added to the `assets`.
+    pub fn new(
+        assets: TestAssets,
+        meta: CoreMeta,
+        map_meta: MapMeta,
+        players: &[Handle<PlayerMeta>],
+    ) -> Self {
+        assert!(players.len() <= MAX_PLAYERS, "Too many players");
+        let player_info = std::array::from_fn(|i| {
+            players.get(i).map(|handle| GameSessionPlayerInfo {
+                handle: handle.clone(),
+                is_ai: false,
+                ai_difficulty: default(),
+            })
+        });
+        Self::with_player_info(assets, meta, map_meta, player_info, GlobalRng::SEED)
+    }
+
+    /// Start a session on the given map, with the given players, some of which may be AI, and
+    /// random seed.
+    pub fn with_player_info(
+        assets: TestAssets,
+        meta: CoreMeta,
+        map_meta: MapMeta,
+        player_info: [Option<GameSessionPlayerInfo>; MAX_PLAYERS],
+        seed: u64,
+    ) -> Self {
+        let session = CoreSession::new(CoreSessionInfo {
+            meta: Arc::new(meta),
+            map_meta,
+            player_info,
+            mutators: default(),
+            seed,
+        });
+
+        Self {
+            session,
+            assets,
+            inputs: default(),
+        }
+    }
+
+    /// Set the input that a player holds, until it is set again.
+    pub fn set_input(&mut self, player: usize, input: TestInput) {
+        self.inputs[player] = input;
+    }
+
+    /// Advance the session by the given number of frames.
+    pub fn step(&mut self, frames: u32) {
+        for _ in 0..frames {
+            let inputs = self.inputs;
+            self.session.update_input(|player_inputs| {
+                for (input, player) in inputs.iter().zip(&mut player_inputs.players) {
+                    input.apply(&mut player.control);
+                }
+            });
+            self.session.advance(&mut self.assets);
+        }
+    }
+
+    /// Hold an input for the given number of frames, and then let go of everything.
+    pub fn step_with(&mut self, player: usize, input: TestInput, frames: u32) {
+        self.set_input(player, input);
+        self.step(frames);
+        self.set_input(player, default());
+    }
+
+    /// Get the entity of a player, if they are spawned.
+    ///
+    /// A player who has been killed keeps their entity until their death animation is over.
+    pub fn player_entity(&self, player: usize) -> Option<Entity> {
+        self.session
+            .world
+            .run_initialized_system(
+                move |entities: Res<Entities>, player_indexes: Comp<PlayerIdx>| {
+                    Ok(entities
+                        .iter_with(&player_indexes)
+                        .find(|(_ent, idx)| idx.0 == player)
+                        .map(|(ent, _idx)| ent))
+                },
+            )
+            .unwrap()
+    }
+
+    /// Get the position of a player, if they are spawned.
+    pub fn player_position(&self, player: usize) -> Option<Vec2> {
+        let entity = self.player_entity(player)?;
+        self.session
+            .world
+            .run_initialized_system(move |transforms: Comp<Transform>| {
+                Ok(transforms
+                    .get(entity)
+                    .map(|transform| transform.translation.truncate()))
+            })
+            .unwrap()
+    }
+
+    /// Whether a player is spawned and hasn't been killed.
+    pub fn is_player_alive(&self, player: usize) -> bool {
+        let Some(entity) = self.player_entity(player) else {
+            return false;
+        };
+        self.session
+            .world
+            .run_initialized_system(move |killed_players: Comp<PlayerKilled>| {
+                Ok(!killed_players.contains(entity))
+            })
+            .unwrap()
+    }
+
+    /// Get the item that a player is holding.
+    pub fn player_inventory(&self, player: usize) -> Option<Entity> {
+        let entity = self.player_entity(player)?;
+        self.session
+            .world
+            .run_initialized_system(move |inventories: Comp<Inventory>| {
+                Ok(inventories.get(entity).and_then(|inventory| inventory.0))
+            })
+            .unwrap()
+    }
+
+    /// Get the element of the item that a player is holding.
+    pub fn player_item(&self, player: usize) -> Option<Handle<ElementMeta>> {
+        let item = self.player_inventory(player)?;
+        self.session
+            .world
+            .run_initialized_system(move |element_handles: Comp<ElementHandle>| {
+                Ok(element_handles.get(item).map(|handle| handle.0.clone()))
+            })
+            .unwrap()
+    }
+
+    /// Get the state that a player is in, if they are spawned.
+    pub fn player_state(&self, player: usize) -> Option<Key> {
+        let entity = self.player_entity(player)?;
+        self.session
+            .world
+            .run_initialized_system(move |player_states: Comp<PlayerState>| {
+                Ok(player_states.get(entity).map(|state| state.current))
+            })
+            .unwrap()
+    }
+
+    /// Get the items in the session.
+    pub fn items(&self) -> Vec<Entity> {
+        self.session
+            .world
+            .run_initialized_system(|entities: Res<Entities>, items: Comp<Item>| {
+                Ok(entities.iter_with(&items).map(|(ent, _)| ent).collect())
+            })
+            .unwrap()
+    }
+
+    /// Whether an entity hasn't been killed.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.session
+            .world
+            .resource::<Entities>()
+            .borrow()
+            .is_alive(entity)
+    }
+
+    /// Get every player death so far, in the order they happened.
+    pub fn kills(&self) -> Vec<KillRecord> {
+        self.session
+            .world
+            .resource::<MatchScore>()
+            .borrow()
+            .kills
+            .clone()
+    }
+
+    /// Get the number of frames that the session has been advanced.
+    ///
+    /// This is part of the world state, so it goes back when the session is rolled back.
+    pub fn frame(&self) -> u64 {
+        **self.session.world.resource::<SessionFrame>().borrow()
+    }
+
+    /// Advance the session by one frame with the inputs that `inputs` returns for the current
+    /// frame.
+    pub fn step_scripted(&mut self, inputs: &impl Fn(u64) -> [TestInput; MAX_PLAYERS]) {
+        self.inputs = inputs(self.frame());
+        self.step(1);
+    }
+}
+
+/// Find the first difference between the world states of two sessions.
+///
+/// The sessions are compared with their [`dump_world()`][CoreSession::dump_world], so only the
+/// components registered in the [`ComponentInspectors`] are compared. The difference is described
+/// with the entity and component that it was found in.
+pub fn world_difference(a: &CoreSession, b: &CoreSession) -> Option<String> {
+    let (a, b) = (a.dump_world(), b.dump_world());
+    let frame = a.lines().next().unwrap_or_default();
+    let describe = |location: &str, a_line: Option<&str>, b_line: Option<&str>| {
+        format!(
+            "Sessions diverged at {frame}, in {location}\n  left:  {}\n  right: {}",
+            a_line.unwrap_or("<nothing>").trim(),
+            b_line.unwrap_or("<nothing>").trim(),
+        )
+    };
+
+    let mut a_lines = a.lines();
+    let mut b_lines = b.lines();
+    // The checksum in the header changes along with the components, so a difference in the
+    // header is only reported if none of the components are different.
+    let mut header_difference = None;
+    // The entity and component headers that the current line is under
+    let mut entity = None;
+    let mut component = None;
+    loop {
+        let (a_line, b_line) = (a_lines.next(), b_lines.next());
+        if a_line.is_none() && b_line.is_none() {
+            return header_difference;
+        }
+
+        if let Some(line) = a_line {
+            if let Some(name) = line.strip_prefix("entity ") {
+                entity = Some(name.trim_end_matches(':'));
+                component = None;
+            } else if line.starts_with("  ") && !line.starts_with("    ") {
+                component = Some(line.trim().trim_end_matches(':'));
+            }
+        }
+
+        if a_line != b_line {
+            match (entity, component) {
+                (None, _) => {
+                    header_difference.get_or_insert_with(|| describe("the header", a_line, b_line));
+                }
+                (Some(entity), None) => {
+                    return Some(describe(&format!("entity {entity}"), a_line, b_line));
+                }
+                (Some(entity), Some(component)) => {
+                    return Some(describe(
+                        &format!("the {component} of entity {entity}"),
+                        a_line,
+                        b_line,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Check that a session always plays out the same way when given the same inputs.
+///
+/// Two sessions are created with `new_session` and advanced side by side for `frames` frames,
+/// with the inputs that `inputs` returns for each frame. Every `interval` frames their world
+/// states are compared, panicking with the first difference.
+pub fn assert_deterministic(
+    new_session: impl Fn() -> TestSession,
+    inputs: impl Fn(u64) -> [TestInput; MAX_PLAYERS],
+    frames: u32,
+    interval: u32,
+) {
+    let mut a = new_session();
+    let mut b = new_session();
+
+    for frame in 1..=frames {
+        a.step_scripted(&inputs);
+        b.step_scripted(&inputs);
+
+        if frame % interval == 0 || frame == frames {
+            if let Some(difference) = world_difference(&a.session, &b.session) {
+                panic!("{difference}");
+            }
+        }
+    }
+}
+
+/// Check that rolling a session back and simulating the same frames again gives the same result
+/// as simulating them once, like a network game does when it receives inputs late.
+///
+/// Two sessions are created with `new_session` and advanced side by side for `frames` frames,
+/// with the inputs that `inputs` returns for each frame. Every `interval` frames, one of them takes
+/// a snapshot, simulates `interval` frames, restores the snapshot, and then simulates those frames
+/// again. Its world state is then compared with the session that never rolled back, panicking with
+/// the first difference.
+pub fn assert_rollback_deterministic(
+    new_session: impl Fn() -> TestSession,
+    inputs: impl Fn(u64) -> [TestInput; MAX_PLAYERS],
+    frames: u32,
+    interval: u32,
+) {
+    let mut reference = new_session();
+    let mut rolled_back = new_session();
+
+    let mut frame = 0;
+    while frame < frames {
+        let steps = interval.min(frames - frame);
+
+        let mut snapshot = rolled_back.session.snapshot();
+        for _ in 0..steps {
+            rolled_back.step_scripted(&inputs);
+        }
+        rolled_back.session.restore(&mut snapshot);
+        for _ in 0..steps {
+            rolled_back.step_scripted(&inputs);
+            reference.step_scripted(&inputs);
+        }
+        frame += steps;
+
+        if let Some(difference) = world_difference(&reference.session, &rolled_back.session) {
+            panic!("After rolling back: {difference}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A match of two players fighting over a grenade and a musket.
+    fn fight_session() -> TestSession {
+        let mut builder = TestSessionBuilder::room(30, 16);
+        builder
+            .add_player(Vec2::new(64.0, 48.0))
+            .add_player(Vec2::new(400.0, 48.0));
+        let bullet = builder.assets.insert("/bullet", bullet_meta());
+        builder.add_element("/grenade", grenade_meta(), Vec2::new(64.0, 40.0));
+        builder.add_element("/musket", musket_meta(bullet), Vec2::new(400.0, 40.0));
+        builder.build()
+    }
+
+    /// Inputs that make the players run back and forth, jump, pick things up, and use them.
+    fn fight_inputs(frame: u64) -> [TestInput; MAX_PLAYERS] {
+        let mut inputs = [TestInput::default(); MAX_PLAYERS];
+        for (i, input) in inputs.iter_mut().take(2).enumerate() {
+            let t = frame + i as u64 * 37;
+            let direction = if (t / 80) % 2 == 0 { 1.0 } else { -1.0 };
+            *input = TestInput {
+                move_direction: Vec2::new(direction, 0.0),
+                jump: t % 50 < 10,
+                shoot: t % 30 < 3,
+                grab: t % 140 < 2,
+                emote: false,
+                dash: false,
+            };
+        }
+        inputs
+    }
+
+    #[test]
+    fn world_difference_finds_diverging_component() {
+        let mut a = fight_session();
+        let mut b = fight_session();
+        a.step(10);
+        b.step(10);
+        assert_eq!(world_difference(&a.session, &b.session), None);
+
+        let player = b.player_entity(0).unwrap();
+        b.session
+            .world
+            .run_initialized_system(move |mut bodies: CompMut<KinematicBody>| {
+                bodies.get_mut(player).unwrap().velocity.x += 1.0;
+                Ok(())
+            })
+            .unwrap();
+        let difference = world_difference(&a.session, &b.session).unwrap();
+        assert!(
+            difference.contains(&format!("KinematicBody of entity {}", player.index())),
+            "{difference}"
+        );
+    }
+
+    #[test]
+    fn sessions_are_deterministic() {
+        assert_deterministic(fight_session, fight_inputs, 3000, 30);
+    }
+
+    #[test]
+    fn rollback_is_deterministic() {
+        assert_rollback_deterministic(fight_session, fight_inputs, 1200, 8);
+    }
+
+    #[test]
+    fn ai_fish_comes_from_session_seed() {
+        let mut meta = core_meta();
+        meta.players = (0..8)
+            .map(|i| {
+                UntypedHandle {
+                    path: AssetPath::new(format!("/fish{i}"), None),
+                }
+                .typed()
+            })
+            .collect();
+        let meta = Arc::new(meta);
+        let ai_fish = |seed: u64| {
+            let session = CoreSession::new(CoreSessionInfo {
+                meta: meta.clone(),
+                map_meta: room_map(10, 10),
+                player_info: std::array::from_fn(|_| {
+                    Some(GameSessionPlayerInfo {
+                        handle: default(),
+                        is_ai: true,
+                        ai_difficulty: default(),
+                    })
+                }),
+                mutators: default(),
+                seed,
+            });
+            let player_inputs = session.world.resource::<PlayerInputs>();
+            let player_inputs = player_inputs.borrow();
+            player_inputs
+                .players
+                .iter()
+                .map(|player| player.selected_player.path.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ai_fish(1), ai_fish(1));
+        assert_ne!(ai_fish(1), ai_fish(2));
+    }
+
+    #[test]
+    fn test_player_has_every_animation() {
+        assert_eq!(player_meta().validate(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn player_walks_right_and_stops_at_wall() {
+        let mut builder = TestSessionBuilder::room(20, 10);
+        builder.add_player(Vec2::new(48.0, 48.0));
+        let wall_x = 19.0 * TILE_SIZE;
+
+        let mut session = builder.build();
+        session.step(10);
+        let start = session.player_position(0).unwrap();
+
+        session.set_input(0, TestInput::moving(Vec2::X));
+        session.step(120);
+        let at_wall = session.player_position(0).unwrap();
+        assert!(at_wall.x > start.x + 100.0, "Player didn't walk: {at_wall}");
+        assert!(
+            at_wall.x + 16.0 <= wall_x + 0.5,
+            "Player walked into the wall: {at_wall}"
+        );
+
+        session.step(10);
+        let pushing = session.player_position(0).unwrap();
+        assert!((pushing.x - at_wall.x).abs() < 0.5, "Player kept moving");
+        assert!(session.is_player_alive(0));
+    }
+
+    #[test]
+    fn player_falls_into_damage_region_and_dies() {
+        let mut builder = TestSessionBuilder::room(12, 16);
+        builder.add_player(Vec2::new(96.0, 200.0));
+        builder.add_element(
+            "/spikes",
+            ElementMeta {
+                name: "Spikes".into(),
+                category: "Environment".into(),
+                behaviors: vec![ElementBehaviorMeta::HurtOnTouch(HurtOnTouchBehaviorMeta {
+                    size: Vec2::new(64.0, 32.0),
+                })],
+                ..default()
+            },
+            Vec2::new(96.0, 32.0),
+        );
+
+        let mut session = builder.build();
+        session.step(2);
+        assert!(session.is_player_alive(0));
+
+        session.step(60);
+        assert!(!session.is_player_alive(0));
+        assert_eq!(
+            session.kills(),
+            vec![KillRecord {
+                victim: 0,
+                killer: None,
+                weapon: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn thrown_grenade_kills_nearby_player() {
+        let mut builder = TestSessionBuilder::room(30, 16);
+        builder
+            .add_player(Vec2::new(64.0, 48.0))
+            .add_player(Vec2::new(440.0, 48.0));
+        let grenade = builder.add_element("/grenade", grenade_meta(), Vec2::new(64.0, 40.0));
+
+        let mut session = builder.build();
+        session.step(10);
+
+        // Pick up the grenade and light it
+        session.step_with(
+            0,
+            TestInput {
+                grab: true,
+                ..default()
+            },
+            1,
+        );
+        session.step(1);
+        assert_eq!(
+            session.player_item(0).map(|item| item.path),
+            Some(grenade.path.clone())
+        );
+        session.step_with(
+            0,
+            TestInput {
+                shoot: true,
+                ..default()
+            },
+            1,
+        );
+        session.step(1);
+
+        // Throw it at the other player, who is standing against the far wall
+        session.step_with(
+            0,
+            TestInput {
+                move_direction: Vec2::X,
+                grab: true,
+                ..default()
+            },
+            1,
+        );
+        session.step(2);
+        assert_eq!(session.player_inventory(0), None);
+
+        session.step(90);
+        assert!(session.is_player_alive(0));
+        assert!(!session.is_player_alive(1));
+        let kills = session.kills();
+        assert!(kills.iter().any(|kill| kill.victim == 1));
+        assert!(!kills.iter().any(|kill| kill.victim == 0));
+    }
+}
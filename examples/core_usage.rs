@@ -15,7 +15,8 @@ use bevy_kira_audio::prelude::*;
 use bones_bevy_renderer::*;
 
 use jumpy_core::{
-    bevy_prelude::*, metadata::JumpyCoreAssetsPlugin, session::GameSessionPlayerInfo,
+    bevy_prelude::*, metadata::JumpyCoreAssetsPlugin, random::GlobalRng,
+    session::GameSessionPlayerInfo,
 };
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -104,15 +105,19 @@ fn load(
             Some(GameSessionPlayerInfo {
                 handle: meta.players[0].clone(),
                 is_ai: false,
+                ai_difficulty: default(),
             }),
             Some(GameSessionPlayerInfo {
                 handle: meta.players[0].clone(),
                 is_ai: true,
+                ai_difficulty: AiDifficulty::Normal,
             }),
             None,
             None,
         ],
         meta: Arc::new(meta),
+        mutators: default(),
+        seed: GlobalRng::SEED,
     });
 
     commands.insert_resource(Session(session));
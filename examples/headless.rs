@@ -0,0 +1,86 @@
+//! This is an example of simulating a Jumpy match on a server, without a window, and without the
+//! Bevy asset server.
+//!
+//! Two AI players fight on the first map for 30 seconds, and then the winner is printed.
+
+use std::{cmp::Ordering, path::Path, sync::Arc};
+
+use jumpy_core::{
+    bevy_prelude::*,
+    headless::{HeadlessAssets, HeadlessSession},
+    random::GlobalRng,
+    score::MatchResult,
+};
+
+/// How long the match is simulated for, in seconds.
+const MATCH_SECONDS: f32 = 30.0;
+
+pub fn main() {
+    // Load the metadata from the asset folder
+    let asset_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let mut assets = HeadlessAssets::new();
+    let meta = assets
+        .load_core_meta(&asset_dir, "default.core.yaml")
+        .unwrap_or_else(|e| panic!("{e}"));
+    let map_meta = assets.get(&meta.stable_maps[0]).unwrap().clone();
+
+    let player_info = std::array::from_fn(|i| {
+        let ai_difficulty = match i {
+            0 => AiDifficulty::Hard,
+            1 => AiDifficulty::Normal,
+            _ => return None,
+        };
+        Some(GameSessionPlayerInfo {
+            handle: meta.players[0].clone(),
+            is_ai: true,
+            ai_difficulty,
+        })
+    });
+    let mut session = HeadlessSession::new(
+        assets,
+        CoreSessionInfo {
+            meta: Arc::new(meta),
+            map_meta,
+            player_info,
+            mutators: default(),
+            seed: GlobalRng::SEED,
+        },
+    );
+
+    let frames = (MATCH_SECONDS * jumpy_core::FPS) as u64;
+    while session.frame() < frames {
+        // There are no human players, the AI players decide on their own input.
+        session.advance(&[]);
+        if session.snapshot().result.is_some() {
+            break;
+        }
+    }
+
+    let snapshot = session.snapshot();
+    let kills = |player: usize| {
+        snapshot
+            .kills
+            .iter()
+            .filter(|kill| kill.killer == Some(player))
+            .count()
+    };
+    for player in &snapshot.players {
+        println!(
+            "Player {}: {} kills, holding {}",
+            player.index + 1,
+            kills(player.index),
+            player.item.as_deref().unwrap_or("nothing"),
+        );
+    }
+
+    match snapshot.result {
+        Some(MatchResult::Winner(player)) => println!("Player {} won the match", player + 1),
+        Some(MatchResult::Draw) => println!("The match was a draw"),
+        // The match isn't over, so the player with the most kills wins
+        None => match kills(0).cmp(&kills(1)) {
+            Ordering::Greater => println!("Player 1 won with {} kills", kills(0)),
+            Ordering::Less => println!("Player 2 won with {} kills", kills(1)),
+            Ordering::Equal => println!("Nobody won, with {} kills each", kills(0)),
+        },
+    }
+}
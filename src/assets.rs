@@ -5,7 +5,10 @@ use bevy::{
 use bevy_egui::egui;
 use bones_bevy_asset::BonesBevyAssetAppExt;
 
-use crate::{metadata::GameMeta, prelude::*};
+use crate::{
+    metadata::{CreditsMeta, GameMeta},
+    prelude::*,
+};
 
 mod asset_handle;
 pub use asset_handle::AssetHandle;
@@ -15,6 +18,7 @@ pub struct JumpyAssetPlugin;
 impl Plugin for JumpyAssetPlugin {
     fn build(&self, app: &mut App) {
         app.add_bones_asset::<GameMeta>()
+            .add_bones_asset::<CreditsMeta>()
             .add_asset::<EguiFont>()
             .add_asset_loader(EguiFontLoader);
     }
@@ -1,11 +1,18 @@
 use std::time::Duration;
 
+use bevy::asset::LoadState;
 use bevy_kira_audio::{
     AudioApp, AudioChannel, AudioControl, AudioInstance, AudioSource, PlaybackState,
 };
+use jumpy_core::score::MatchScore;
 use rand::{seq::SliceRandom, thread_rng};
 
-use crate::{main_menu::MenuPage, metadata::GameMeta, prelude::*};
+use crate::{
+    main_menu::{settings::ModifiedSettings, MenuPage},
+    metadata::{GameMeta, Settings},
+    platform::Storage,
+    prelude::*,
+};
 
 pub struct JumpyAudioPlugin;
 
@@ -14,10 +21,13 @@ impl Plugin for JumpyAudioPlugin {
         app.add_plugin(bevy_kira_audio::AudioPlugin)
             .init_resource::<MusicState>()
             .init_resource::<ShuffledPlaylist>()
+            .init_resource::<EffectsVolume>()
+            .init_resource::<PositionalAudio>()
             .add_audio_channel::<MusicChannel>()
             .add_audio_channel::<EffectsChannel>()
             .add_startup_system(setup_audio_defaults)
-            .add_system(music_system.run_if(resource_exists::<GameMeta>()));
+            .add_system(music_system.run_if(resource_exists::<GameMeta>()))
+            .add_system(apply_volume_settings.run_if(resource_exists::<GameMeta>()));
     }
 }
 
@@ -26,6 +36,26 @@ pub struct MusicChannel;
 #[derive(Resource)]
 pub struct EffectsChannel;
 
+/// The factor that sound effect volumes are multiplied by, according to the user's settings.
+#[derive(Resource, Deref, DerefMut, Clone, Copy, Debug)]
+pub struct EffectsVolume(pub f64);
+
+impl Default for EffectsVolume {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Whether sound effects are panned and attenuated according to the user's settings.
+#[derive(Resource, Deref, DerefMut, Clone, Copy, Debug)]
+pub struct PositionalAudio(pub bool);
+
+impl Default for PositionalAudio {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
 #[derive(Resource, Clone, Debug, Default)]
 pub enum MusicState {
     #[default]
@@ -37,6 +67,8 @@ pub enum MusicState {
         instance: Handle<AudioInstance>,
         idx: usize,
     },
+    /// The victory sting played once the match is over.
+    Results(Handle<AudioInstance>),
 }
 
 impl MusicState {
@@ -47,115 +79,269 @@ impl MusicState {
             MusicState::CharacterSelect(i) => Some(i),
             MusicState::Credits(i) => Some(i),
             MusicState::Fight { instance, .. } => Some(instance),
+            MusicState::Results(i) => Some(i),
         }
     }
 }
 
-#[derive(Resource, Deref, DerefMut, Clone, Debug, Default)]
-pub struct ShuffledPlaylist(pub Vec<AssetHandle<AudioSource>>);
+/// The tracks played during a match, in a shuffled order.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ShuffledPlaylist {
+    /// The name of the map that the playlist was made for.
+    pub map_name: Option<String>,
+    pub tracks: Vec<Handle<AudioSource>>,
+    /// Whether the first track is looped instead of moving on to the next one.
+    pub looped: bool,
+}
+
+impl ShuffledPlaylist {
+    /// Make the playlist for a map, using the map's own music if it has any, and the game's fight
+    /// music otherwise.
+    ///
+    /// If any of the map's tracks are missing, the menu music is played instead.
+    fn new(game: &GameMeta, map_meta: &MapMeta, asset_server: &AssetServer) -> Self {
+        let mut playlist = Self {
+            map_name: Some(map_meta.name.clone()),
+            tracks: game.music.fight.iter().map(|x| x.inner.clone()).collect(),
+            looped: false,
+        };
+
+        if let Some(music) = map_meta.music.as_ref().filter(|x| !x.tracks.is_empty()) {
+            let tracks = music
+                .tracks
+                .iter()
+                .map(|track| track.get_bevy_handle_untyped().typed::<AudioSource>())
+                .collect::<Vec<_>>();
+            let is_missing = |track: &Handle<AudioSource>| match asset_server.get_load_state(track)
+            {
+                LoadState::Failed => true,
+                LoadState::NotLoaded => asset_server.get_handle_path(track).is_none(),
+                _ => false,
+            };
+            if tracks.iter().any(is_missing) {
+                warn!(map = %map_meta.name, "Map music is missing, playing the menu music instead");
+                playlist.tracks = vec![game.music.title_screen.inner.clone()];
+                playlist.looped = true;
+            } else {
+                playlist.tracks = tracks;
+                playlist.looped = music.looped;
+            }
+        }
+
+        playlist.tracks.shuffle(&mut thread_rng());
+        playlist
+    }
+}
 
 fn setup_audio_defaults(
     music: Res<AudioChannel<MusicChannel>>,
     effects: Res<AudioChannel<EffectsChannel>>,
 ) {
-    music.set_volume(0.22);
-    effects.set_volume(0.1);
+    music.set_volume(MUSIC_VOLUME);
+    effects.set_volume(EFFECTS_VOLUME);
 }
 
-const MUSIC_FADE_DURATION: Duration = Duration::from_millis(500);
+/// The music channel volume when the music volume setting is at 100%.
+const MUSIC_VOLUME: f64 = 0.22;
+/// The effects channel volume when the effects volume setting is at 100%.
+const EFFECTS_VOLUME: f64 = 0.1;
+
+/// Applies the user's volume settings to the audio channels.
+///
+/// While the settings menu is open, the settings being edited are used, so that volume changes
+/// can be heard before they are saved.
+fn apply_volume_settings(
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    modified_settings: Res<ModifiedSettings>,
+    engine_state: Res<State<EngineState>>,
+    in_game_state: Res<State<InGameState>>,
+    menu_page: Res<MenuPage>,
+    music: Res<AudioChannel<MusicChannel>>,
+    mut effects_volume: ResMut<EffectsVolume>,
+    mut positional_audio: ResMut<PositionalAudio>,
+    mut current_music_factor: Local<Option<f64>>,
+) {
+    // The settings can be opened from the main menu or the pause menu
+    let editing_settings = matches!(*menu_page, MenuPage::Settings)
+        && (engine_state.0 == EngineState::MainMenu || in_game_state.0 == InGameState::Paused);
+    let settings = match &**modified_settings {
+        Some(settings) if editing_settings => std::borrow::Cow::Borrowed(settings),
+        _ => Settings::get_stored_or_default(&game, &mut storage),
+    };
+
+    let music_factor = settings.music_volume_factor() as f64;
+    if *current_music_factor != Some(music_factor) {
+        music.set_volume(MUSIC_VOLUME * music_factor);
+        *current_music_factor = Some(music_factor);
+    }
+
+    let effects_factor = settings.effects_volume_factor() as f64;
+    if **effects_volume != effects_factor {
+        **effects_volume = effects_factor;
+    }
+
+    if **positional_audio != settings.positional_audio {
+        **positional_audio = settings.positional_audio;
+    }
+}
+
+/// How far outside of the camera's view a sound can be heard, relative to the view height.
+const SOUND_FALLOFF_DISTANCE: f32 = 1.0;
+/// How far sounds at the edges of the camera's view are panned, from `0.0` to `0.5`.
+const MAX_SOUND_PAN: f32 = 0.35;
+
+/// Get the panning and volume factor for a sound coming from `position`, according to where it is
+/// relative to the camera's view.
+///
+/// Sounds inside of the view are played at full volume, getting quieter the further outside of it
+/// they are. Returns `None` if the sound is too far away to be heard.
+pub fn positional_sound(view_center: Vec2, view_size: Vec2, position: Vec2) -> Option<(f64, f64)> {
+    let half_size = (view_size / 2.0).max(Vec2::ONE);
+    let offset = position - view_center;
+    let distance_outside = (offset.abs() - half_size).max(Vec2::ZERO).length();
+    let falloff = half_size.y * 2.0 * SOUND_FALLOFF_DISTANCE;
+    if distance_outside >= falloff {
+        return None;
+    }
+
+    let pan = 0.5 + (offset.x / half_size.x).clamp(-1.0, 1.0) * MAX_SOUND_PAN;
+    let volume = 1.0 - distance_outside / falloff;
+    Some((pan as f64, volume as f64))
+}
+
+/// Stop the current music, fading it out, and start playing something else.
+fn crossfade(
+    music_state: &mut MusicState,
+    audio_instances: &mut Assets<AudioInstance>,
+    fade: Duration,
+    play: impl FnOnce() -> MusicState,
+) {
+    if let Some(instance) = music_state
+        .current_instance()
+        .and_then(|instance| audio_instances.get_mut(instance))
+    {
+        instance.stop(AudioTween::linear(fade));
+    }
+    *music_state = play();
+}
 
 /// Plays music according to the game mode.
 fn music_system(
     game: Res<GameMeta>,
-    mut shuffled_fight_music: ResMut<ShuffledPlaylist>,
+    mut playlist: ResMut<ShuffledPlaylist>,
     mut music_state: ResMut<MusicState>,
     mut audio_instances: ResMut<Assets<AudioInstance>>,
     music: Res<AudioChannel<MusicChannel>>,
     engine_state: Res<State<EngineState>>,
     menu_page: Res<MenuPage>,
+    asset_server: Res<AssetServer>,
+    session: Option<ResMut<Session>>,
 ) {
-    if shuffled_fight_music.is_empty() || engine_state.is_changed() {
-        let mut songs = game.music.fight.clone();
-        songs.shuffle(&mut thread_rng());
-        **shuffled_fight_music = songs;
-    }
+    let fade = Duration::from_secs_f32(game.music.crossfade_duration.max(0.0));
+    let audio_instances = &mut *audio_instances;
+
+    // Menu tracks loop seamlessly until the page changes
+    let mut play_looped =
+        |music_state: &mut MusicState,
+         track: &AssetHandle<AudioSource>,
+         state: fn(Handle<AudioInstance>) -> MusicState| {
+            crossfade(music_state, audio_instances, fade, || {
+                state(
+                    music
+                        .play(track.inner.clone_weak())
+                        .linear_fade_in(fade)
+                        .looped()
+                        .handle(),
+                )
+            });
+        };
 
     match engine_state.0 {
         EngineState::LoadingPlatformStorage | EngineState::LoadingGameData => (),
         EngineState::InGame => {
-            if let MusicState::Fight { instance, idx } = &mut *music_state {
-                let inst = audio_instances.get(instance).unwrap();
-                if let PlaybackState::Stopped = inst.state() {
-                    *idx += 1;
-                    *idx %= shuffled_fight_music.len();
-
-                    *instance = music
-                        .play(shuffled_fight_music[*idx].inner.clone_weak())
-                        .linear_fade_in(MUSIC_FADE_DURATION)
-                        .handle();
+            let Some(mut session) = session else {
+                return;
+            };
+            let map_meta = &session.core_session().info.map_meta;
+            if engine_state.is_changed() || playlist.map_name.as_ref() != Some(&map_meta.name) {
+                *playlist = ShuffledPlaylist::new(&game, map_meta, &asset_server);
+            }
+
+            let match_over = session
+                .world()
+                .run_initialized_system(|score: bones::Res<MatchScore>| Ok(score.result.is_some()))
+                .unwrap();
+            if match_over {
+                if !matches!(*music_state, MusicState::Results(..)) {
+                    crossfade(&mut music_state, audio_instances, fade, || {
+                        MusicState::Results(
+                            music
+                                .play(game.music.results_screen.inner.clone_weak())
+                                .linear_fade_in(fade)
+                                .handle(),
+                        )
+                    });
                 }
-            } else {
-                if let Some(instance) = music_state.current_instance() {
-                    let instance = audio_instances.get_mut(instance).unwrap();
-                    instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
+                return;
+            }
+
+            if playlist.tracks.is_empty() {
+                return;
+            }
+            let play_track = |idx: usize| {
+                let mut command = music.play(playlist.tracks[idx].clone_weak());
+                command.linear_fade_in(fade);
+                if playlist.looped {
+                    command.looped();
                 }
+                command.handle()
+            };
 
-                if let Some(song) = shuffled_fight_music.get(0) {
-                    *music_state = MusicState::Fight {
-                        instance: music
-                            .play(song.inner.clone_weak())
-                            .linear_fade_in(MUSIC_FADE_DURATION)
-                            .looped()
-                            .handle(),
-                        idx: 0,
-                    };
+            if let MusicState::Fight { instance, idx } = &mut *music_state {
+                // Move on to the next track when one ends
+                let stopped = audio_instances
+                    .get(instance)
+                    .map(|x| matches!(x.state(), PlaybackState::Stopped))
+                    .unwrap_or(true);
+                if stopped {
+                    *idx = (*idx + 1) % playlist.tracks.len();
+                    *instance = play_track(*idx);
                 }
+            } else {
+                crossfade(&mut music_state, audio_instances, fade, || {
+                    MusicState::Fight {
+                        instance: play_track(0),
+                        idx: 0,
+                    }
+                });
             }
         }
         EngineState::MainMenu => match &*menu_page {
-            MenuPage::PlayerSelect | MenuPage::MapSelect { .. } | MenuPage::NetworkGame => {
+            MenuPage::PlayerSelect
+            | MenuPage::Mutators
+            | MenuPage::MapSelect { .. }
+            | MenuPage::NetworkGame => {
                 if !matches!(*music_state, MusicState::CharacterSelect(..)) {
-                    if let Some(instance) = music_state.current_instance() {
-                        let instance = audio_instances.get_mut(instance).unwrap();
-                        instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
-                    }
-                    *music_state = MusicState::CharacterSelect(
-                        music
-                            .play(game.music.character_screen.inner.clone_weak())
-                            .linear_fade_in(MUSIC_FADE_DURATION)
-                            .looped()
-                            .handle(),
+                    play_looped(
+                        &mut music_state,
+                        &game.music.character_screen,
+                        MusicState::CharacterSelect,
                     );
                 }
             }
-            MenuPage::Home | MenuPage::Settings => {
+            MenuPage::Home | MenuPage::Settings | MenuPage::Replays | MenuPage::Stats => {
                 if !matches!(*music_state, MusicState::MainMenu(..)) {
-                    if let Some(instance) = music_state.current_instance() {
-                        let instance = audio_instances.get_mut(instance).unwrap();
-                        instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
-                    }
-                    *music_state = MusicState::MainMenu(
-                        music
-                            .play(game.music.title_screen.inner.clone_weak())
-                            .linear_fade_in(MUSIC_FADE_DURATION)
-                            .looped()
-                            .handle(),
+                    play_looped(
+                        &mut music_state,
+                        &game.music.title_screen,
+                        MusicState::MainMenu,
                     );
                 }
             }
             MenuPage::Credits => {
                 if !matches!(*music_state, MusicState::Credits(..)) {
-                    if let Some(instance) = music_state.current_instance() {
-                        let instance = audio_instances.get_mut(instance).unwrap();
-                        instance.stop(AudioTween::linear(MUSIC_FADE_DURATION));
-                    }
-                    *music_state = MusicState::Credits(
-                        music
-                            .play(game.music.credits.inner.clone_weak())
-                            .linear_fade_in(MUSIC_FADE_DURATION)
-                            .looped()
-                            .handle(),
-                    );
+                    play_looped(&mut music_state, &game.music.credits, MusicState::Credits);
                 }
             }
         },
@@ -2,6 +2,7 @@ use once_cell::sync::Lazy;
 
 pub const SERVER_MODE_ENV_VAR: &str = "JUMPY_SERVER_MODE";
 pub const ASSET_DIR_ENV_VAR: &str = "JUMPY_ASSET_DIR";
+pub const DATA_DIR_ENV_VAR: &str = "JUMPY_DATA_DIR";
 
 const DEFAULT_LOG_LEVEL: &str = "info,wgpu=error,bevy_fluent=warn,symphonia_core=warn,symphonia_format_ogg=warn,symphonia_bundle_mp3=warn";
 
@@ -41,9 +42,48 @@ pub struct EngineConfig {
     /// `module=level` items.
     #[arg(short = 'l', long, default_value = DEFAULT_LOG_LEVEL)]
     pub log_level: String,
+
+    /// Skip the menu and start a local match on the given .map.yaml asset
+    #[arg(long, value_parser = parse_map_path)]
+    pub map: Option<String>,
+
+    /// The number of players in the match started with `--map`
+    ///
+    /// The first player is controlled by the first gamepad or keyboard, and the other players are
+    /// AI. All of them play the default fish.
+    #[arg(
+        long,
+        default_value = "1",
+        requires = "map",
+        value_parser = clap::value_parser!(u8).range(1..=jumpy_core::MAX_PLAYERS as i64)
+    )]
+    pub players: u8,
+
+    /// Skip the menu and open the given .map.yaml asset in the map editor
+    #[arg(long, value_parser = parse_map_path, conflicts_with = "map")]
+    pub editor: Option<String>,
+
+    /// Start in fullscreen, regardless of the display settings
+    #[arg(long, conflicts_with = "windowed")]
+    pub fullscreen: bool,
+
+    /// Start in a window, regardless of the display settings
+    #[arg(long)]
+    pub windowed: bool,
+
+    /// Use a fixed seed for the random number generator of local matches
+    ///
+    /// Useful for reproducing bugs, since the same inputs on the same map will play out the same.
+    #[arg(long)]
+    pub seed: Option<u64>,
 }
 
 impl EngineConfig {
+    /// Whether the game should skip the main menu, because a map was given to start with.
+    pub fn skips_menu(&self) -> bool {
+        self.map.is_some() || self.editor.is_some()
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn from_web_params() -> Self {
         if let Some(query) = web_sys::window().and_then(|w| w.location().search().ok()) {
@@ -61,6 +101,37 @@ impl EngineConfig {
                 config.log_level = log_level.into();
             }
 
+            if let Some(map) = parse_url_query_string(&query, "map") {
+                match parse_map_path(map) {
+                    Ok(map) => config.map = Some(map),
+                    Err(e) => web_sys::console::error_1(&e.into()),
+                }
+            }
+
+            if let Some(players) = parse_url_query_string(&query, "players") {
+                match players.parse::<u8>() {
+                    Ok(players) if (1..=jumpy_core::MAX_PLAYERS as u8).contains(&players) => {
+                        config.players = players
+                    }
+                    _ => web_sys::console::error_1(
+                        &format!(
+                            "Invalid player count `{players}`: expected a number from 1 to {}",
+                            jumpy_core::MAX_PLAYERS
+                        )
+                        .into(),
+                    ),
+                }
+            }
+
+            if let Some(seed) = parse_url_query_string(&query, "seed") {
+                match seed.parse::<u64>() {
+                    Ok(seed) => config.seed = Some(seed),
+                    Err(e) => {
+                        web_sys::console::error_1(&format!("Invalid seed `{seed}`: {e}").into())
+                    }
+                }
+            }
+
             config
         } else {
             Self::web_default()
@@ -77,10 +148,27 @@ impl EngineConfig {
             game_asset: "default.game.yaml".into(),
             log_level: DEFAULT_LOG_LEVEL.into(),
             sync_test_check_distance: 0,
+            map: None,
+            players: 1,
+            editor: None,
+            fullscreen: false,
+            windowed: false,
+            seed: None,
         }
     }
 }
 
+/// Check that a path given on the command line is a map asset.
+fn parse_map_path(path: &str) -> Result<String, String> {
+    if path.ends_with(".map.yaml") {
+        Ok(path.into())
+    } else {
+        Err(format!(
+            "`{path}` is not a map: expected the path of a .map.yaml file in the asset directory"
+        ))
+    }
+}
+
 #[cfg(any(target_arch = "wasm32", test))]
 /// Parse the query string as returned by `web_sys::window()?.location().search()?` and get a
 /// specific key out of it.
@@ -132,4 +220,42 @@ mod test {
             parse_url_query_string("?hello=world&foo=bar", "RUST_LOG")
         );
     }
+
+    #[test]
+    fn launch_flags_compose() {
+        let config = <EngineConfig as clap::Parser>::try_parse_from([
+            "jumpy",
+            "--map",
+            "map/levels/level_1.map.yaml",
+            "--players",
+            "3",
+            "--seed",
+            "42",
+            "--windowed",
+        ])
+        .unwrap();
+        assert_eq!(config.map.as_deref(), Some("map/levels/level_1.map.yaml"));
+        assert_eq!(config.players, 3);
+        assert_eq!(config.seed, Some(42));
+        assert!(config.windowed);
+        assert!(config.skips_menu());
+    }
+
+    #[test]
+    fn invalid_launch_flags_are_rejected() {
+        for args in [
+            &["jumpy", "--map", "level_1.png"][..],
+            &["jumpy", "--map", "a.map.yaml", "--players", "5"],
+            &["jumpy", "--map", "a.map.yaml", "--players", "0"],
+            &["jumpy", "--players", "2"],
+            &["jumpy", "--seed", "-1"],
+            &["jumpy", "--fullscreen", "--windowed"],
+            &["jumpy", "--map", "a.map.yaml", "--editor", "b.map.yaml"],
+        ] {
+            assert!(
+                <EngineConfig as clap::Parser>::try_parse_from(args).is_err(),
+                "{args:?} should be rejected"
+            );
+        }
+    }
 }
@@ -0,0 +1,216 @@
+//! Custom player skins, loaded from the user's data directory.
+//!
+//! Every folder in the `custom/players/` folder of the [data directory][crate::platform::data_dir]
+//! that has a file ending in `.player.yaml` is loaded as an extra fish, which is added to the
+//! [`CoreMeta::players`] so that it can be picked in the player selection. The player file refers
+//! to its atlases with paths relative to itself, just like the built-in players.
+//!
+//! Custom players aren't loaded on web, since there is no data directory there.
+
+use bevy::asset::LoadState;
+use bevy_egui::EguiContexts;
+
+use crate::{main_menu::player_select::PlayerAtlasEguiTextures, prelude::*};
+
+pub struct CustomPlayersPlugin;
+
+impl Plugin for CustomPlayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CustomPlayers>().add_system(
+            register_custom_players
+                .run_if(resource_exists::<CoreMetaArc>())
+                .run_if(resource_exists::<PlayerAtlasEguiTextures>()),
+        );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_startup_system(scan_custom_players);
+    }
+}
+
+/// A player skin loaded from the user's data directory.
+#[derive(Clone, Debug)]
+pub struct CustomPlayer {
+    pub handle: bones::Handle<PlayerMeta>,
+    /// A hash of the files in the player's folder.
+    ///
+    /// The path of a custom player is different on every computer, so network games use the hash
+    /// to check that everybody has the same player.
+    pub content_hash: u64,
+}
+
+/// Resource containing the custom players.
+#[derive(Resource, Default)]
+pub struct CustomPlayers {
+    /// The custom players that have been loaded.
+    pub players: Vec<CustomPlayer>,
+    /// The custom players that are still loading.
+    loading: Vec<(CustomPlayer, Handle<PlayerMeta>)>,
+    /// Strong handles to the loaded [`players`][Self::players], that keep them from being unloaded.
+    loaded_handles: Vec<Handle<PlayerMeta>>,
+}
+
+impl CustomPlayers {
+    /// Get the content hash of a player, if it is a custom player.
+    pub fn content_hash(&self, player: &bones::Handle<PlayerMeta>) -> Option<u64> {
+        self.players
+            .iter()
+            .find(|custom| custom.handle.path == player.path)
+            .map(|custom| custom.content_hash)
+    }
+
+    /// Find the custom player with the given content hash.
+    pub fn find_by_hash(&self, content_hash: u64) -> Option<&bones::Handle<PlayerMeta>> {
+        self.players
+            .iter()
+            .find(|custom| custom.content_hash == content_hash)
+            .map(|custom| &custom.handle)
+    }
+}
+
+/// Start loading the custom players in the data directory.
+#[cfg(not(target_arch = "wasm32"))]
+fn scan_custom_players(asset_server: Res<AssetServer>, mut custom_players: ResMut<CustomPlayers>) {
+    use std::hash::Hasher;
+
+    let dir = crate::platform::data_dir().join("custom").join("players");
+    let Ok(folders) = std::fs::read_dir(&dir) else {
+        // There are no custom players
+        return;
+    };
+
+    let mut folders = folders
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    folders.sort();
+
+    for folder in folders {
+        let Ok(files) = std::fs::read_dir(&folder) else {
+            continue;
+        };
+        let mut files = files
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        // Sort the files so that the hash is the same on every computer
+        files.sort();
+
+        let Some(player_file) = files.iter().find(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| name.ends_with(".player.yaml"))
+        }) else {
+            warn!(folder = %folder.display(), "Custom player folder doesn't have a .player.yaml file");
+            continue;
+        };
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        for file in &files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            match std::fs::read(file) {
+                Ok(data) => {
+                    hasher.write(name.as_bytes());
+                    hasher.write(&data);
+                }
+                Err(e) => warn!(file = %file.display(), "Could not read custom player file: {e}"),
+            }
+        }
+
+        info!(path = %player_file.display(), "Loading custom player");
+        let handle = asset_server.load(player_file.clone());
+        custom_players.loading.push((
+            CustomPlayer {
+                handle: bones::UntypedHandle {
+                    path: bones::AssetPath::new(player_file.clone(), None),
+                }
+                .typed(),
+                content_hash: hasher.finish(),
+            },
+            handle,
+        ));
+    }
+}
+
+/// Add the custom players that have loaded to the [`CoreMeta::players`], and their atlases to the
+/// [`PlayerAtlasEguiTextures`].
+///
+/// This also adds them back after the game metadata is hot reloaded.
+fn register_custom_players(
+    mut custom_players: ResMut<CustomPlayers>,
+    mut core_meta: ResMut<CoreMetaArc>,
+    mut player_atlas_egui_textures: ResMut<PlayerAtlasEguiTextures>,
+    mut egui_ctx: EguiContexts,
+    asset_server: Res<AssetServer>,
+    player_assets: Res<Assets<PlayerMeta>>,
+    atlas_assets: Res<Assets<TextureAtlas>>,
+) {
+    let CustomPlayers {
+        players,
+        loading,
+        loaded_handles,
+    } = &mut *custom_players;
+
+    loading.retain(|(custom_player, handle)| {
+        if asset_server.get_load_state(handle.id()) == LoadState::Failed {
+            warn!(path = ?custom_player.handle.path, "Custom player failed to load");
+            return false;
+        }
+        let Some(player_meta) = player_assets.get(handle) else {
+            return true;
+        };
+        for atlas in [
+            &player_meta.layers.body.atlas,
+            &player_meta.layers.fin.atlas,
+            &player_meta.layers.face.atlas,
+        ] {
+            let atlas_handle = atlas.get_bevy_handle_untyped();
+            if asset_server.get_load_state(atlas_handle.id()) == LoadState::Failed {
+                warn!(path = ?atlas.path, "Custom player atlas failed to load");
+                return false;
+            }
+            if atlas_assets.get(&atlas_handle.typed()).is_none() {
+                return true;
+            }
+        }
+
+        players.push(custom_player.clone());
+        loaded_handles.push(handle.clone());
+        false
+    });
+
+    for (custom_player, handle) in players.iter().zip(loaded_handles.iter()) {
+        let Some(player_meta) = player_assets.get(handle) else {
+            continue;
+        };
+
+        for atlas in [
+            &player_meta.layers.body.atlas,
+            &player_meta.layers.fin.atlas,
+            &player_meta.layers.face.atlas,
+        ] {
+            if player_atlas_egui_textures.0.contains_key(&atlas.path) {
+                continue;
+            }
+            let Some(texture_atlas) = atlas_assets.get(&atlas.get_bevy_handle_untyped().typed())
+            else {
+                continue;
+            };
+            let egui_texture = egui_ctx.add_image(texture_atlas.texture.clone_weak());
+            player_atlas_egui_textures
+                .0
+                .insert(atlas.path.clone(), egui_texture);
+        }
+
+        if !core_meta
+            .players
+            .iter()
+            .any(|player| player.path == custom_player.handle.path)
+        {
+            let mut core = CoreMeta::clone(&core_meta.0);
+            core.players.push(custom_player.handle.clone());
+            core_meta.0 = Arc::new(core);
+        }
+    }
+}
@@ -1,10 +1,21 @@
+use bevy::input::gamepad::GamepadConnectionEvent;
+use leafwing_input_manager::user_input::InputKind;
+
 use crate::prelude::*;
 
 pub struct JumpyPlayerInputPlugin;
 
 impl Plugin for JumpyPlayerInputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(InputManagerPlugin::<PlayerAction>::default());
+        app.add_plugin(InputManagerPlugin::<PlayerAction>::default())
+            .init_resource::<PlayerDevices>()
+            .add_event::<PlayerGamepadDisconnected>()
+            .add_systems((
+                update_player_devices,
+                apply_player_devices
+                    .after(update_player_devices)
+                    .run_if(resource_exists::<GameMeta>()),
+            ));
     }
 }
 
@@ -16,8 +27,280 @@ pub enum PlayerAction {
     Shoot,
     Grab,
     Slide,
+    Emote,
+    Dash,
+}
+
+/// The inputs bound to some of a player's actions, used to show the player which buttons to press.
+#[derive(Debug)]
+pub struct PlayerActionMap<'a>(HashMap<PlayerAction, Vec<&'a UserInput>>);
+
+impl<'a> PlayerActionMap<'a> {
+    /// Get the inputs bound to the given actions from the player's input map, with the keyboard
+    /// inputs listed before the gamepad inputs.
+    pub fn new(map: &'a InputMap<PlayerAction>, actions: &[PlayerAction]) -> Self {
+        Self(HashMap::from_iter(actions.iter().map(|&action| {
+            let mut inputs = map.get(action).iter().collect::<Vec<_>>();
+            inputs.sort_by_key(|input| is_gamepad_input(input));
+            (action, inputs)
+        })))
+    }
+
+    /// Get the text listing the inputs bound to an action.
+    pub fn get_text(&self, action: PlayerAction) -> String {
+        self.0
+            .get(&action)
+            .unwrap_or(&vec![])
+            .iter()
+            .map(|action| action.to_string())
+            .fold("".to_string(), |acc, curr| {
+                if acc.is_empty() {
+                    curr
+                } else {
+                    format!("{acc} / {curr}")
+                }
+            })
+    }
+}
+
+fn is_gamepad_input(input: &UserInput) -> bool {
+    matches!(
+        input,
+        UserInput::Single(
+            InputKind::GamepadButton(_) | InputKind::SingleAxis(_) | InputKind::DualAxis(_)
+        )
+    )
 }
 
 /// Bevy resource containing the editor action to perform for this frame.
 #[derive(Resource, Default, Deref, DerefMut)]
 pub struct CurrentEditorInput(Option<jumpy_core::input::EditorInput>);
+
+/// A gamepad assigned to a local player slot in the [`PlayerDevices`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssignedGamepad {
+    pub gamepad: Gamepad,
+    /// Whether or not the gamepad is currently connected.
+    pub connected: bool,
+}
+
+/// Resource keeping track of the input devices used by each local player slot.
+///
+/// Each slot may have one of the keyboard binding sets from the [`Settings`] and a gamepad assigned
+/// to it. By default each slot uses the keyboard binding set with the same index, and devices are
+/// only re-assigned when players join on the player select screen.
+///
+/// Gamepads stay assigned to their slot by ID when they disconnect, so that plugging a gamepad back
+/// in gives it to the same player, no matter what order the gamepads are reconnected in.
+#[derive(Resource, Debug)]
+pub struct PlayerDevices {
+    keyboards: [Option<usize>; MAX_PLAYERS],
+    gamepads: [Option<AssignedGamepad>; MAX_PLAYERS],
+}
+
+impl Default for PlayerDevices {
+    fn default() -> Self {
+        Self {
+            keyboards: std::array::from_fn(Some),
+            gamepads: default(),
+        }
+    }
+}
+
+impl PlayerDevices {
+    /// Get the index of the keyboard binding set assigned to a slot.
+    pub fn keyboard(&self, slot: usize) -> Option<usize> {
+        self.keyboards[slot]
+    }
+
+    /// Get the slot a keyboard binding set is assigned to, if any.
+    pub fn keyboard_slot(&self, keyboard: usize) -> Option<usize> {
+        self.keyboards.iter().position(|x| *x == Some(keyboard))
+    }
+
+    /// Assign a keyboard binding set to a slot, removing it from any other slot it was assigned
+    /// to.
+    pub fn assign_keyboard(&mut self, slot: usize, keyboard: usize) {
+        if let Some(old_slot) = self.keyboard_slot(keyboard) {
+            self.keyboards[old_slot] = None;
+        }
+        self.keyboards[slot] = Some(keyboard);
+    }
+
+    /// Whether or not any device is assigned to a slot.
+    pub fn has_devices(&self, slot: usize) -> bool {
+        self.keyboards[slot].is_some() || self.gamepads[slot].is_some()
+    }
+
+    /// Remove all of the devices from a slot, returning them to the unassigned pool.
+    pub fn release(&mut self, slot: usize) {
+        self.keyboards[slot] = None;
+        self.gamepads[slot] = None;
+    }
+
+    /// Remove all of the devices from every slot.
+    pub fn release_all(&mut self) {
+        *self = Self {
+            keyboards: default(),
+            gamepads: default(),
+        };
+    }
+
+    /// Get the input map for a slot, from the controls of the devices assigned to it.
+    pub fn input_map(
+        &self,
+        controls: &PlayerControlMethods,
+        slot: usize,
+    ) -> InputMap<PlayerAction> {
+        controls.get_input_map(self.keyboard(slot), self.gamepad(slot))
+    }
+
+    /// Get the gamepad assigned to a slot, whether or not it is connected.
+    pub fn gamepad(&self, slot: usize) -> Option<AssignedGamepad> {
+        self.gamepads[slot]
+    }
+
+    /// Get the slot a gamepad is assigned to, if any.
+    pub fn slot(&self, gamepad: Gamepad) -> Option<usize> {
+        self.gamepads
+            .iter()
+            .position(|x| x.map(|x| x.gamepad) == Some(gamepad))
+    }
+
+    /// Assign a connected gamepad to a slot, removing it from any other slot it was assigned to.
+    pub fn assign(&mut self, slot: usize, gamepad: Gamepad) {
+        if let Some(old_slot) = self.slot(gamepad) {
+            self.gamepads[old_slot] = None;
+        }
+        self.gamepads[slot] = Some(AssignedGamepad {
+            gamepad,
+            connected: true,
+        });
+    }
+
+    /// Mark a gamepad as connected, returning the slot it is assigned to, if any.
+    pub fn connect(&mut self, gamepad: Gamepad) -> Option<usize> {
+        self.set_connected(gamepad, true)
+    }
+
+    /// Mark a gamepad as disconnected, returning the slot it is assigned to, if any.
+    pub fn disconnect(&mut self, gamepad: Gamepad) -> Option<usize> {
+        self.set_connected(gamepad, false)
+    }
+
+    fn set_connected(&mut self, gamepad: Gamepad, connected: bool) -> Option<usize> {
+        let slot = self.slot(gamepad)?;
+        if let Some(assigned) = &mut self.gamepads[slot] {
+            assigned.connected = connected;
+        }
+        Some(slot)
+    }
+
+    /// Iterate over the slots that have a disconnected gamepad.
+    pub fn disconnected_slots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.gamepads
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| matches!(x, Some(x) if !x.connected))
+            .map(|(slot, _)| slot)
+    }
+}
+
+/// Event sent when the gamepad assigned to a local player slot is disconnected.
+pub struct PlayerGamepadDisconnected {
+    pub slot: usize,
+}
+
+/// Keep the [`PlayerDevices`] up-to-date as gamepads are connected and disconnected.
+fn update_player_devices(
+    mut devices: ResMut<PlayerDevices>,
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    mut disconnected_events: EventWriter<PlayerGamepadDisconnected>,
+) {
+    for event in connection_events.iter() {
+        if event.connected() {
+            if let Some(slot) = devices.connect(event.gamepad) {
+                info!(
+                    "Gamepad {} reconnected for player {}",
+                    event.gamepad.id,
+                    slot + 1
+                );
+            }
+        } else if let Some(slot) = devices.disconnect(event.gamepad) {
+            warn!(
+                "Gamepad {} disconnected for player {}",
+                event.gamepad.id,
+                slot + 1
+            );
+            disconnected_events.send(PlayerGamepadDisconnected { slot });
+        }
+    }
+}
+
+/// Update the input maps of the local players when their devices change.
+fn apply_player_devices(
+    devices: Res<PlayerDevices>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    mut collectors: Query<(&PlayerInputCollector, &mut InputMap<PlayerAction>)>,
+    added_collectors: Query<(), Added<PlayerInputCollector>>,
+) {
+    if !devices.is_changed() && added_collectors.is_empty() {
+        return;
+    }
+
+    let settings = Settings::get_stored_or_default(&game, &mut storage);
+    for (collector, mut input_map) in &mut collectors {
+        *input_map = devices.input_map(&settings.player_controls, collector.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamepads_reconnect_to_the_same_slot() {
+        let mut devices = PlayerDevices::default();
+        devices.assign(0, Gamepad::new(0));
+        devices.assign(1, Gamepad::new(1));
+
+        assert_eq!(devices.disconnect(Gamepad::new(0)), Some(0));
+        assert_eq!(devices.disconnect(Gamepad::new(1)), Some(1));
+        assert_eq!(devices.disconnected_slots().collect::<Vec<_>>(), vec![0, 1]);
+
+        // Reconnect the gamepads in the opposite order
+        assert_eq!(devices.connect(Gamepad::new(1)), Some(1));
+        assert_eq!(devices.connect(Gamepad::new(0)), Some(0));
+        assert_eq!(devices.disconnected_slots().count(), 0);
+        assert_eq!(devices.gamepad(0).map(|x| x.gamepad), Some(Gamepad::new(0)));
+        assert_eq!(devices.gamepad(1).map(|x| x.gamepad), Some(Gamepad::new(1)));
+    }
+
+    #[test]
+    fn released_devices_can_join_another_slot() {
+        let mut devices = PlayerDevices::default();
+        assert_eq!(devices.keyboard(1), Some(1));
+
+        devices.release_all();
+        assert!(!devices.has_devices(0));
+
+        devices.assign_keyboard(0, 1);
+        devices.assign(1, Gamepad::new(0));
+        assert_eq!(devices.keyboard_slot(1), Some(0));
+
+        devices.release(0);
+        assert_eq!(devices.keyboard_slot(1), None);
+        devices.assign_keyboard(2, 1);
+        assert_eq!(devices.keyboard(2), Some(1));
+        assert!(devices.has_devices(1));
+    }
+
+    #[test]
+    fn unassigned_gamepads_are_ignored() {
+        let mut devices = PlayerDevices::default();
+        assert_eq!(devices.connect(Gamepad::new(2)), None);
+        assert_eq!(devices.disconnect(Gamepad::new(2)), None);
+        assert!(devices.gamepad(0).is_none());
+    }
+}
@@ -0,0 +1,122 @@
+//! Starting the game straight into a match or the map editor, as requested on the command line.
+//!
+//! This is mostly useful while developing, to get back to the map being worked on without going
+//! through the menus every time. See the `--map` and `--editor` flags of [`EngineConfig`].
+
+use bevy::{app::AppExit, asset::LoadState};
+use jumpy_core::random::GlobalRng;
+
+use crate::{
+    prelude::*,
+    ui::{
+        main_menu::player_select::PlayerNames,
+        map_loading::{MapLoading, MapLoadingStart},
+    },
+};
+
+pub struct LaunchPlugin;
+
+impl Plugin for LaunchPlugin {
+    fn build(&self, app: &mut App) {
+        if ENGINE_CONFIG.skips_menu() {
+            app.add_system(launch.run_if(in_state(EngineState::MainMenu)));
+        }
+    }
+}
+
+/// The map given on the command line, while it is loading.
+#[derive(Default)]
+enum LaunchState {
+    #[default]
+    Start,
+    Loading(Handle<MapMeta>),
+    Done,
+}
+
+/// Load the map given on the command line, and start the match or open the editor once it has
+/// loaded.
+///
+/// This only happens the first time the main menu is shown, so that leaving the match goes back to
+/// the menu like usual.
+fn launch(
+    mut commands: Commands,
+    mut state: Local<LaunchState>,
+    mut session_manager: SessionManager,
+    mut exit: EventWriter<AppExit>,
+    asset_server: Res<AssetServer>,
+    map_assets: Res<Assets<MapMeta>>,
+    core: Res<CoreMetaArc>,
+) {
+    let (path, is_editor) = match (&ENGINE_CONFIG.map, &ENGINE_CONFIG.editor) {
+        (Some(path), _) => (path, false),
+        (None, Some(path)) => (path, true),
+        (None, None) => return,
+    };
+
+    let handle = match &*state {
+        LaunchState::Start => {
+            info!(%path, "Loading map given on the command line");
+            *state = LaunchState::Loading(asset_server.load(path.as_str()));
+            return;
+        }
+        LaunchState::Loading(handle) => handle,
+        LaunchState::Done => return,
+    };
+
+    match asset_server.get_load_state(handle) {
+        LoadState::Loaded => (),
+        LoadState::Failed | LoadState::Unloaded => {
+            error!(
+                %path,
+                "Could not load the map given on the command line, check that the path is relative \
+                 to the asset directory"
+            );
+            exit.send(AppExit);
+            *state = LaunchState::Done;
+            return;
+        }
+        LoadState::NotLoaded | LoadState::Loading => return,
+    }
+    let map_meta = map_assets.get(handle).unwrap().clone();
+    *state = LaunchState::Done;
+
+    if is_editor {
+        info!(map = %map_meta.name, "Opening map in the editor");
+        session_manager.start_local(CoreSessionInfo {
+            meta: core.0.clone(),
+            map_meta,
+            player_info: default(),
+            mutators: default(),
+            seed: GlobalRng::SEED,
+        });
+        commands.insert_resource(NextState(Some(GameEditorState::Visible)));
+        commands.insert_resource(NextState(Some(EngineState::InGame)));
+        commands.insert_resource(NextState(Some(InGameState::Playing)));
+    } else {
+        info!(map = %map_meta.name, players = ENGINE_CONFIG.players, "Starting match");
+        // The first player is controlled locally, and the rest of the slots are filled with AI
+        let mut player_info = <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
+        for (i, info) in player_info
+            .iter_mut()
+            .take(ENGINE_CONFIG.players as usize)
+            .enumerate()
+        {
+            *info = Some(GameSessionPlayerInfo {
+                handle: core.players[0].clone(),
+                is_ai: i > 0,
+                ai_difficulty: default(),
+            });
+        }
+        commands.insert_resource(PlayerNames::default());
+        commands.insert_resource(MapLoading::new(
+            CoreSessionInfo {
+                meta: core.0.clone(),
+                map_meta,
+                player_info,
+                mutators: default(),
+                seed: rand::random(),
+            },
+            MapLoadingStart::Local { is_training: false },
+        ));
+    }
+}
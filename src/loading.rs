@@ -1,6 +1,5 @@
 use bevy::ecs::system::SystemParam;
 use bevy_egui::{egui, EguiContexts};
-use bevy_fluent::Locale;
 use leafwing_input_manager::{
     axislike::{AxisType, SingleAxis},
     prelude::InputMap,
@@ -42,6 +41,7 @@ fn core_assets_loaded(
     game_handle: Res<GameMetaHandle>,
     game_assets: Res<Assets<GameMeta>>,
     core_assets: Res<Assets<CoreMeta>>,
+    credits_assets: Res<Assets<CreditsMeta>>,
     player_assets: Res<Assets<PlayerMeta>>,
     atlas_assets: Res<Assets<TextureAtlas>>,
 ) -> bool {
@@ -53,6 +53,10 @@ fn core_assets_loaded(
     let Some(core) = core_assets.get(&game.core.inner) else {
         return false;
     };
+    // The credits asset ( needed for the credits logo )
+    if credits_assets.get(&game.credits.inner).is_none() {
+        return false;
+    }
 
     // Egui assets
     //
@@ -120,11 +124,14 @@ pub struct GameLoader<'w, 's> {
     game_handle: Res<'w, GameMetaHandle>,
     game_assets: ResMut<'w, Assets<GameMeta>>,
     core_assets: ResMut<'w, Assets<CoreMeta>>,
+    credits_assets: ResMut<'w, Assets<CreditsMeta>>,
     events: EventReader<'w, 's, AssetEvent<GameMeta>>,
     // active_scripts: ResMut<'w, ActiveScripts>,
     storage: ResMut<'w, Storage>,
     player_assets: ResMut<'w, Assets<PlayerMeta>>,
     texture_atlas_assets: Res<'w, Assets<TextureAtlas>>,
+    map_assets: Res<'w, Assets<MapMeta>>,
+    element_assets: Res<'w, Assets<ElementMeta>>,
     egui_ctx: EguiContexts<'w, 's>,
 }
 
@@ -147,6 +154,7 @@ impl<'w, 's> GameLoader<'w, 's> {
             game_handle,
             mut game_assets,
             mut core_assets,
+            mut credits_assets,
             mut egui_ctx,
             mut storage,
             ..
@@ -180,14 +188,14 @@ impl<'w, 's> GameLoader<'w, 's> {
             commands.insert_resource(EguiFontDefinitions(egui_fonts));
 
             // Spawn player input collectors.
-            let settings = storage.get(Settings::STORAGE_KEY);
-            let settings = settings.as_ref().unwrap_or(&game.default_settings);
+            let settings = Settings::get_stored_or_default(&game, &mut storage);
             for player in 0..MAX_PLAYERS {
                 commands.spawn((
                     Name::new(format!("Player Input Collector {player}")),
                     PlayerInputCollector(player),
                     InputManagerBundle {
-                        input_map: settings.player_controls.get_input_map(player),
+                        input_map: PlayerDevices::default()
+                            .input_map(&settings.player_controls, player),
                         ..default()
                     },
                 ));
@@ -198,11 +206,8 @@ impl<'w, 's> GameLoader<'w, 's> {
         }
 
         // Set the locale resource
-        let translations = &game.translations;
-        commands.insert_resource(
-            Locale::new(translations.detected_locale.clone())
-                .with_default(translations.default_locale.clone()),
-        );
+        let settings = Settings::get_stored_or_default(&game, &mut storage);
+        commands.insert_resource(game.translations.locale(settings.locale.as_ref()));
 
         let mut visuals = egui::Visuals::dark();
         visuals.widgets = game.ui_theme.widgets.get_egui_widget_style();
@@ -248,6 +253,28 @@ impl<'w, 's> GameLoader<'w, 's> {
             icon.egui_texture_id = egui_ctx.add_image(icon.image.inner.clone_weak());
         }
 
+        // Add the credits logo to egui context
+        if let Some(credits) = credits_assets.get_mut(&game.credits.inner) {
+            credits.logo.egui_texture_id =
+                egui_ctx.add_image(credits.logo.image.inner.clone_weak());
+        }
+
+        // Report problems with the core maps, so that broken map files are noticed on load.
+        for map_handle in core.stable_maps.iter().chain(&core.experimental_maps) {
+            let Some(map_meta) = self.map_assets.get(&map_handle.get_bevy_handle()) else {
+                warn!(path = ?map_handle.path, "Map could not be loaded");
+                continue;
+            };
+            let errors = crate::ui::map_validation::validate_map(
+                map_meta,
+                &self.element_assets,
+                &self.texture_atlas_assets,
+            );
+            for error in errors {
+                warn!(map = %map_meta.name, ?error, "Problem found in map");
+            }
+        }
+
         // Insert the game resource
         commands.insert_resource(game.clone());
         commands.insert_resource(CoreMetaArc(Arc::new(core.clone())));
@@ -335,7 +362,7 @@ impl<'w, 's> GameLoader<'w, 's> {
     }
 }
 
-fn menu_input_map() -> InputMap<MenuAction> {
+pub fn menu_input_map() -> InputMap<MenuAction> {
     InputMap::default()
         .set_gamepad(Gamepad::new(0))
         // Up
@@ -401,6 +428,15 @@ fn menu_input_map() -> InputMap<MenuAction> {
         // Pause
         .insert(KeyCode::Escape, MenuAction::Pause)
         .insert(GamepadButtonType::Start, MenuAction::Pause)
+        // Chat
+        .insert(KeyCode::Return, MenuAction::Chat)
+        .insert(KeyCode::T, MenuAction::Chat)
+        // Map editor undo/redo. The keyboard shortcuts are handled by the editor itself.
+        .insert(GamepadButtonType::LeftTrigger2, MenuAction::EditorUndo)
+        .insert(GamepadButtonType::RightTrigger2, MenuAction::EditorRedo)
+        // Map editor playtesting
+        .insert(KeyCode::F4, MenuAction::ToggleEditor)
+        // The screenshot key is bound from the settings, by `bind_screenshot_key()`.
         .build()
 }
 
@@ -21,12 +21,17 @@ use bevy_dylib;
 pub mod assets;
 pub mod audio;
 pub mod config;
+pub mod custom_players;
 pub mod debug;
 pub mod input;
+pub mod launch;
 pub mod loading;
 pub mod localization;
 pub mod metadata;
+pub mod particles;
 pub mod platform;
+pub mod presence;
+pub mod screenshot;
 pub mod session;
 pub mod ui;
 pub mod utils;
@@ -35,6 +40,8 @@ pub mod camera;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod networking;
 pub mod prelude;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod replay;
 pub use prelude::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, States, Default)]
@@ -104,16 +111,26 @@ pub fn main() {
         .add_plugin(JumpySessionPlugin)
         .add_plugin(JumpyUiPlugin)
         .add_plugin(JumpyAudioPlugin)
+        .add_plugin(particles::JumpyParticlesPlugin)
+        .add_plugin(screenshot::ScreenshotPlugin)
         .add_plugin(JumpyPlatformPlugin)
+        .add_plugin(custom_players::CustomPlayersPlugin)
         .add_plugin(JumpyLoadingPlugin)
         .add_plugin(JumpyAssetPlugin)
         .add_plugin(JumpyLocalizationPlugin)
-        .add_plugin(JumpyDebugPlugin);
+        .add_plugin(JumpyDebugPlugin)
+        .add_plugin(presence::PresencePlugin)
+        .add_plugin(launch::LaunchPlugin);
 
     #[cfg(not(target_arch = "wasm32"))]
-    app.add_plugin(networking::NetworkingPlugin);
+    app.add_plugin(networking::NetworkingPlugin)
+        .add_plugin(replay::JumpyReplayPlugin);
 
-    debug!(?engine_config, "Starting game");
+    info!(
+        args = ?std::env::args().collect::<Vec<_>>(),
+        ?engine_config,
+        "Starting game"
+    );
 
     // Get the game handle
     let asset_server = app.world.get_resource::<AssetServer>().unwrap();
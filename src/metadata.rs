@@ -2,12 +2,18 @@
 
 use crate::prelude::*;
 
+mod credits;
 mod localization;
+mod profiles;
 mod settings;
+mod stats;
 mod ui;
 
+pub use credits::*;
 pub use localization::*;
+pub use profiles::*;
 pub use settings::*;
+pub use stats::*;
 pub use ui::*;
 
 /// Resource containing the main [`Handle<GameMeta>`].
@@ -20,12 +26,17 @@ pub struct GameMetaHandle(pub Handle<GameMeta>);
 #[ulid = "01GPEMR6KZ4QZBNN8HBJZEX2JB"]
 pub struct GameMeta {
     pub core: AssetHandle<CoreMeta>,
+    pub credits: AssetHandle<CreditsMeta>,
     pub translations: localization::TranslationsMeta,
     pub ui_theme: ui::UIThemeMeta,
     pub main_menu: MainMenuMeta,
     #[asset(deserialize_only)]
     pub default_settings: settings::Settings,
     pub music: MusicMeta,
+    /// The ID of the Discord application that rich presence is shown for, when the game is built
+    /// with the `discord` feature.
+    #[serde(default)]
+    pub discord_app_id: Option<String>,
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
@@ -34,8 +45,16 @@ pub struct MusicMeta {
     pub title_screen: AssetHandle<AudioSource>,
     pub fight: Vec<AssetHandle<AudioSource>>,
     pub character_screen: AssetHandle<AudioSource>,
+    /// Played once, instead of looping, when a match ends.
     pub results_screen: AssetHandle<AudioSource>,
     pub credits: AssetHandle<AudioSource>,
+    /// How many seconds it takes to fade from one track to the next.
+    #[serde(default = "default_crossfade_duration")]
+    pub crossfade_duration: f32,
+}
+
+fn default_crossfade_duration() -> f32 {
+    0.5
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
@@ -45,6 +64,8 @@ pub struct MainMenuMeta {
     pub subtitle_font: FontMeta,
     pub background_image: ImageMeta,
     pub menu_width: f32,
+    /// How many seconds players have to vote for a map in online games.
+    pub map_vote_duration: f32,
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Serialize, Clone, Debug)]
@@ -59,9 +80,10 @@ pub struct ImageMeta {
     pub egui_texture_id: bevy_egui::egui::TextureId,
 }
 
-/// Helper trait for converting color meta to [`egui::Color32`].
+/// Helper trait for converting color meta to [`egui::Color32`] and Bevy [`Color`].
 pub trait ColorMetaExt {
     fn into_egui(self) -> egui::Color32;
+    fn into_bevy(self) -> Color;
 }
 
 impl ColorMetaExt for ColorMeta {
@@ -74,4 +96,9 @@ impl ColorMetaExt for ColorMeta {
             (a * 255.0) as u8,
         )
     }
+
+    fn into_bevy(self) -> Color {
+        let [r, g, b, a] = self.0.as_rgba_f32();
+        Color::rgba(r, g, b, a)
+    }
 }
@@ -0,0 +1,38 @@
+//! The credits for the game's assets, like its fonts and music.
+//!
+//! These are kept in a YAML asset instead of the code, so that artists can update their credits
+//! without recompiling the game.
+
+use crate::prelude::*;
+
+#[derive(BonesBevyAsset, TypeUlid, Deserialize, Clone, Debug)]
+#[asset_id = "credits"]
+#[serde(deny_unknown_fields)]
+#[ulid = "01H66CR3D1TS8W5NKQ7V2XBMJA"]
+pub struct CreditsMeta {
+    /// The logo shown at the top of the credits page.
+    pub logo: ImageMeta,
+    /// The asset attributions, grouped into sections like "Fonts" and "Music".
+    pub sections: Vec<AssetCreditsSectionMeta>,
+}
+
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AssetCreditsSectionMeta {
+    pub title: String,
+    pub entries: Vec<AssetCreditMeta>,
+}
+
+/// The attribution for an asset.
+#[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AssetCreditMeta {
+    /// The name of the asset, like the name of a font.
+    pub name: String,
+    /// The person or group that made the asset.
+    #[serde(default)]
+    pub author: Option<String>,
+    /// The license that the asset is used under.
+    #[serde(default)]
+    pub license: Option<String>,
+}
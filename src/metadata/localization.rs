@@ -1,6 +1,8 @@
-use bevy_fluent::BundleAsset;
+use bevy_fluent::{BundleAsset, Locale};
 use unic_langid::LanguageIdentifier;
 
+use crate::assets::EguiFont;
+
 use super::*;
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
@@ -16,4 +18,22 @@ pub struct TranslationsMeta {
     pub default_locale: LanguageIdentifier,
     /// The handles to the locale bundle assets
     pub locales: Vec<AssetHandle<BundleAsset>>,
+    /// Extra fonts, indexed by locale, that are used as a fallback for any characters that the UI
+    /// theme fonts don't have, such as for CJK scripts.
+    #[serde(default)]
+    pub fonts: HashMap<String, AssetHandle<EguiFont>>,
+}
+
+impl TranslationsMeta {
+    /// Get the [`Locale`] to use, given the locale selected in the settings, if any.
+    ///
+    /// Messages missing from the selected locale fall back to the default locale.
+    pub fn locale(&self, selected: Option<&LanguageIdentifier>) -> Locale {
+        Locale::new(
+            selected
+                .cloned()
+                .unwrap_or_else(|| self.detected_locale.clone()),
+        )
+        .with_default(self.default_locale.clone())
+    }
 }
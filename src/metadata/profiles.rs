@@ -0,0 +1,57 @@
+//! Player profiles, which give local players a display name and a preferred fish.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// The most characters a player's display name may have.
+pub const MAX_PLAYER_NAME_LEN: usize = 16;
+
+/// A local player's profile, stored in the [`Settings`].
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct PlayerProfile {
+    /// The name shown for the player, or empty to show the default name.
+    #[serde(default)]
+    pub name: String,
+    /// The fish that is picked for the player when they join.
+    #[serde(default)]
+    pub preferred_fish: Option<bones::Handle<PlayerMeta>>,
+}
+
+impl PlayerProfile {
+    /// Get the player's sanitized display name, or [`None`] if they haven't picked one.
+    pub fn display_name(&self) -> Option<String> {
+        let name = sanitize_player_name(&self.name);
+        (!name.is_empty()).then_some(name)
+    }
+}
+
+/// Clean up a player name so that it can be shown to other players.
+///
+/// Control characters are removed, since names are sent over the network, along with the
+/// characters used to pass arguments to localized messages, so that names can be shown in them.
+pub fn sanitize_player_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_control() && !matches!(c, '?' | '&' | '='))
+        .take(MAX_PLAYER_NAME_LEN)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sanitize_player_name_strips_control_characters() {
+        assert_eq!(sanitize_player_name("  Fishy\n\u{7}  "), "Fishy");
+        assert_eq!(sanitize_player_name("a=b&c?"), "abc");
+    }
+
+    #[test]
+    fn sanitize_player_name_limits_length() {
+        let name = sanitize_player_name(&"🐟".repeat(MAX_PLAYER_NAME_LEN * 2));
+        assert_eq!(name.chars().count(), MAX_PLAYER_NAME_LEN);
+    }
+}
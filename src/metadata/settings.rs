@@ -1,12 +1,16 @@
 use std::borrow::Cow;
 
-use bevy::prelude::Gamepad;
+use bevy::prelude::{error, KeyCode};
 use leafwing_input_manager::{axislike::VirtualDPad, prelude::InputMap, user_input::InputKind};
 use serde::{Deserialize, Serialize};
+use unic_langid::LanguageIdentifier;
 
-use crate::{input::PlayerAction, platform::Storage};
+use crate::{
+    input::{AssignedGamepad, PlayerAction},
+    platform::Storage,
+};
 
-use super::GameMeta;
+use super::{GameMeta, PlayerProfile};
 
 /// Global settings, stored and accessed through [`crate::platform::Storage`]
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -15,20 +19,249 @@ pub struct Settings {
     pub player_controls: PlayerControlMethods,
     /// The address of the matchmaking server to connect to for online games.
     pub matchmaking_server: String,
+    /// How many frames local inputs are delayed by in network games, to make up for network
+    /// latency. Every player uses the highest delay that any player in the match asks for.
+    #[serde(default = "default_input_delay")]
+    pub input_delay: u8,
+    /// Whether the input delay adapts to the latency jitter of the connection, using
+    /// `input_delay` as the minimum.
+    #[serde(default)]
+    pub adaptive_input_delay: bool,
+    /// Whether to show the arc that a held item will follow when it is thrown.
+    #[serde(default = "default_show_throw_preview")]
+    pub show_throw_preview: bool,
+    /// Whether the camera shakes for explosions, kills, and heavy landings.
+    #[serde(default = "default_camera_shake")]
+    pub camera_shake: bool,
+    /// Whether the game slows down and zooms in on the kill that ends a match.
+    ///
+    /// Network games always play the kill cam, so that every player stays in step.
+    #[serde(default = "default_kill_cam")]
+    pub kill_cam: bool,
+    /// Whether double-tapping left or right dashes, as well as pressing the dash button.
+    #[serde(default)]
+    pub double_tap_dash: bool,
+    /// The overall volume of the game, from `0.0` to `1.0`.
+    #[serde(default = "default_volume")]
+    pub master_volume: f32,
+    /// The volume of the music, from `0.0` to `1.0`.
+    #[serde(default = "default_volume")]
+    pub music_volume: f32,
+    /// The volume of the sound effects, from `0.0` to `1.0`.
+    #[serde(default = "default_volume")]
+    pub effects_volume: f32,
+    /// Whether all game audio is muted.
+    #[serde(default)]
+    pub mute_audio: bool,
+    /// Whether sound effects are panned and made quieter according to where they are relative to
+    /// the camera, instead of all being played centered at full volume.
+    #[serde(default = "default_positional_audio")]
+    pub positional_audio: bool,
+    /// The size of the game window, when not fullscreen.
+    #[serde(default = "default_window_resolution")]
+    pub window_resolution: [u32; 2],
+    /// Whether the game is borderless fullscreen instead of windowed.
+    #[serde(default)]
+    pub fullscreen: bool,
+    /// Whether vsync is enabled.
+    #[serde(default = "default_vsync")]
+    pub vsync: bool,
+    /// The user's UI scale, multiplied with the UI theme's scale.
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+    /// The language selected by the user, or `None` to use the system language.
+    #[serde(default)]
+    pub locale: Option<LanguageIdentifier>,
+    /// How local players' fish are marked, to make it easier to tell who is who.
+    #[serde(default)]
+    pub player_indicator: PlayerIndicator,
+    /// Whether to show each player's held item, ammo and lives or score during a match.
+    #[serde(default = "default_show_hud")]
+    pub show_hud: bool,
+    /// The profiles of the local players, by player slot.
+    #[serde(default)]
+    pub player_profiles: Vec<PlayerProfile>,
+    /// How many seconds of editing there are between autosaves of the map in the editor, or `0`
+    /// to turn autosaving off.
+    #[serde(default = "default_editor_autosave_interval")]
+    pub editor_autosave_interval: u32,
+    /// Whether to show what the player is doing in the game on their Discord profile.
+    #[serde(default = "default_discord_presence")]
+    pub discord_presence: bool,
+    /// The key that saves a screenshot of the game.
+    ///
+    /// The function keys are all used by other shortcuts, so this defaults to Print Screen.
+    #[serde(default = "default_screenshot_key")]
+    pub screenshot_key: KeyCode,
+}
+
+/// How local players' fish are marked in game.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerIndicator {
+    Off,
+    Outline,
+    Arrow,
+    #[default]
+    OutlineAndArrow,
+}
+
+impl PlayerIndicator {
+    /// All of the indicator options, in the order they are cycled through in the settings.
+    pub const ALL: [Self; 4] = [Self::Off, Self::Outline, Self::Arrow, Self::OutlineAndArrow];
+
+    pub fn shows_outline(self) -> bool {
+        matches!(self, Self::Outline | Self::OutlineAndArrow)
+    }
+
+    pub fn shows_arrow(self) -> bool {
+        matches!(self, Self::Arrow | Self::OutlineAndArrow)
+    }
+
+    /// The localization key for the name of the option.
+    pub fn localization_key(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Outline => "player-indicator-outline",
+            Self::Arrow => "player-indicator-arrow",
+            Self::OutlineAndArrow => "player-indicator-outline-and-arrow",
+        }
+    }
+}
+
+fn default_input_delay() -> u8 {
+    1
+}
+
+fn default_show_throw_preview() -> bool {
+    true
+}
+
+fn default_camera_shake() -> bool {
+    true
+}
+
+fn default_kill_cam() -> bool {
+    true
+}
+
+fn default_show_hud() -> bool {
+    true
+}
+
+fn default_positional_audio() -> bool {
+    true
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+fn default_window_resolution() -> [u32; 2] {
+    [1280, 720]
+}
+
+fn default_vsync() -> bool {
+    true
+}
+
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+fn default_discord_presence() -> bool {
+    true
+}
+
+fn default_screenshot_key() -> KeyCode {
+    KeyCode::Snapshot
+}
+
+fn default_editor_autosave_interval() -> u32 {
+    60
 }
 
 impl Settings {
     /// The key used to store the settings in the [`crate::platform::Storage`] resource.
     pub const STORAGE_KEY: &'static str = "settings";
 
+    /// The window resolutions that can be selected in the display settings.
+    pub const WINDOW_RESOLUTIONS: &'static [[u32; 2]] = &[
+        [1024, 576],
+        [1280, 720],
+        [1366, 768],
+        [1600, 900],
+        [1920, 1080],
+        [2560, 1440],
+        [3840, 2160],
+    ];
+
+    /// The minimum UI scale that can be selected in the display settings.
+    pub const MIN_UI_SCALE: f32 = 0.5;
+    /// The maximum UI scale that can be selected in the display settings.
+    pub const MAX_UI_SCALE: f32 = 2.0;
+
+    /// The editor autosave intervals that can be selected in the gameplay settings, in seconds.
+    pub const EDITOR_AUTOSAVE_INTERVALS: &'static [u32] = &[0, 30, 60, 120, 300];
+
     pub fn get_stored_or_default<'w>(
         game: &'w GameMeta,
         storage: &'w mut Storage,
     ) -> Cow<'w, Self> {
-        if let Some(settings) = storage.get::<Self>(Self::STORAGE_KEY) {
-            Cow::Owned(settings)
+        let Some(mut stored) = storage.get::<serde_yaml::Value>(Self::STORAGE_KEY) else {
+            return Cow::Borrowed(&game.default_settings);
+        };
+
+        // Fill in any settings that have been added since the settings were stored, such as the
+        // controls for new keyboard players, from the defaults.
+        let defaults =
+            serde_yaml::to_value(&game.default_settings).expect("Serialize default settings");
+        fill_missing_values(&mut stored, &defaults);
+
+        match serde_yaml::from_value(stored) {
+            Ok(settings) => Cow::Owned(settings),
+            Err(e) => {
+                error!("Error deserializing stored settings, using defaults: {e}");
+                Cow::Borrowed(&game.default_settings)
+            }
+        }
+    }
+
+    /// The factor to multiply the music volume by, taking into account the master volume and mute.
+    pub fn music_volume_factor(&self) -> f32 {
+        self.volume_factor(self.music_volume)
+    }
+
+    /// The factor to multiply sound effect volumes by, taking into account the master volume and
+    /// mute.
+    pub fn effects_volume_factor(&self) -> f32 {
+        self.volume_factor(self.effects_volume)
+    }
+
+    /// Get the profile of the local player in the given slot, if they have one.
+    pub fn player_profile(&self, slot: usize) -> Option<&PlayerProfile> {
+        self.player_profiles.get(slot)
+    }
+
+    fn volume_factor(&self, volume: f32) -> f32 {
+        if self.mute_audio {
+            0.0
+        } else {
+            self.master_volume.clamp(0.0, 1.0) * volume.clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Recursively add the keys from the `defaults` mapping that are missing from the `value` mapping.
+fn fill_missing_values(value: &mut serde_yaml::Value, defaults: &serde_yaml::Value) {
+    let (Some(map), Some(default_map)) = (value.as_mapping_mut(), defaults.as_mapping()) else {
+        return;
+    };
+    for (key, default) in default_map {
+        if let Some(value) = map.get_mut(key) {
+            fill_missing_values(value, default);
         } else {
-            Cow::Borrowed(&game.default_settings)
+            map.insert(key.clone(), default.clone());
         }
     }
 }
@@ -41,33 +274,55 @@ pub struct PlayerControlMethods {
     pub keyboard1: PlayerControls,
     /// Controls for keyboard player 2
     pub keyboard2: PlayerControls,
+    /// Controls for keyboard player 3
+    pub keyboard3: PlayerControls,
+    /// Controls for keyboard player 4
+    pub keyboard4: PlayerControls,
 }
 
 impl PlayerControlMethods {
-    /// Get the input map for the given player index
-    pub fn get_input_map(&self, player_idx: usize) -> InputMap<PlayerAction> {
+    /// Get the input map for a player using the given keyboard binding set and gamepad, if any.
+    pub fn get_input_map(
+        &self,
+        keyboard: Option<usize>,
+        gamepad: Option<AssignedGamepad>,
+    ) -> InputMap<PlayerAction> {
         let mut input_map = InputMap::default();
 
-        input_map.set_gamepad(Gamepad { id: player_idx });
-
         let mut add_controls = |ctrls: &PlayerControls| {
             input_map.insert(ctrls.movement.clone(), PlayerAction::Move);
             input_map.insert(ctrls.jump, PlayerAction::Jump);
             input_map.insert(ctrls.grab, PlayerAction::Grab);
             input_map.insert(ctrls.shoot, PlayerAction::Shoot);
             input_map.insert(ctrls.slide, PlayerAction::Slide);
+            input_map.insert(ctrls.emote, PlayerAction::Emote);
+            input_map.insert(ctrls.dash, PlayerAction::Dash);
         };
 
-        add_controls(&self.gamepad);
+        // Only listen to the player's own gamepad, since an input map without a gamepad listens
+        // to all of them.
+        if let Some(gamepad) = gamepad {
+            input_map.set_gamepad(gamepad.gamepad);
+            add_controls(&self.gamepad);
+        }
 
-        match player_idx {
-            0 => add_controls(&self.keyboard1),
-            1 => add_controls(&self.keyboard2),
-            _ => (),
+        if let Some(keyboard) = keyboard.and_then(|idx| self.keyboard(idx)) {
+            add_controls(keyboard);
         }
 
         input_map
     }
+
+    /// Get the keyboard binding set with the given index, if it exists.
+    pub fn keyboard(&self, keyboard_idx: usize) -> Option<&PlayerControls> {
+        match keyboard_idx {
+            0 => Some(&self.keyboard1),
+            1 => Some(&self.keyboard2),
+            2 => Some(&self.keyboard3),
+            3 => Some(&self.keyboard4),
+            _ => None,
+        }
+    }
 }
 
 /// Binds inputs to player actions
@@ -78,4 +333,25 @@ pub struct PlayerControls {
     pub grab: InputKind,
     pub shoot: InputKind,
     pub slide: InputKind,
+    pub emote: InputKind,
+    pub dash: InputKind,
+}
+
+impl PlayerControls {
+    /// Get mutable references to each of the inputs, in the order they are listed in the controls
+    /// settings.
+    pub fn inputs_mut(&mut self) -> [&mut InputKind; 10] {
+        [
+            &mut self.movement.up,
+            &mut self.movement.down,
+            &mut self.movement.left,
+            &mut self.movement.right,
+            &mut self.jump,
+            &mut self.grab,
+            &mut self.shoot,
+            &mut self.slide,
+            &mut self.emote,
+            &mut self.dash,
+        ]
+    }
 }
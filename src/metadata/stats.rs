@@ -0,0 +1,173 @@
+//! Lifetime statistics of the local players, accumulated over the matches they complete.
+
+use std::collections::BTreeMap;
+
+use jumpy_core::score::{MatchResult, MatchScore};
+use serde::{Deserialize, Serialize};
+
+use crate::platform::StorageItem;
+
+/// The lifetime statistics of each local player profile, stored in [`crate::platform::Storage`].
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PlayerStats {
+    /// The statistics of each player profile, by player slot, like
+    /// [`Settings::player_profiles`][super::Settings::player_profiles].
+    pub profiles: Vec<ProfileStats>,
+}
+
+impl StorageItem for PlayerStats {
+    const STORAGE_KEY: &'static str = "player_stats";
+}
+
+impl PlayerStats {
+    /// Get the statistics of the profile in the given player slot, if it has any.
+    pub fn profile(&self, slot: usize) -> Option<&ProfileStats> {
+        self.profiles.get(slot)
+    }
+
+    /// Add a match to the statistics of the profile in `slot`, which played the match as the
+    /// player with index `player_idx`, using the fish named `fish`.
+    ///
+    /// If the match doesn't have a result yet it counts as a loss, because the player left before
+    /// it was over.
+    pub fn record_match(&mut self, slot: usize, player_idx: usize, fish: &str, score: &MatchScore) {
+        if self.profiles.len() <= slot {
+            self.profiles.resize_with(slot + 1, Default::default);
+        }
+        let stats = &mut self.profiles[slot];
+
+        stats.matches_played += 1;
+        match score.result {
+            Some(MatchResult::Winner(winner)) if winner == player_idx => stats.wins += 1,
+            Some(MatchResult::Draw) => (),
+            _ => stats.losses += 1,
+        }
+
+        for kill in &score.kills {
+            if kill.victim == player_idx {
+                stats.deaths += 1;
+            }
+            if kill.killer == Some(player_idx) {
+                stats.kills += 1;
+                if let Some(weapon) = &kill.weapon {
+                    *stats.weapon_kills.entry(weapon.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        if !fish.is_empty() {
+            *stats.fish_played.entry(fish.to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// The lifetime statistics of a single player profile.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProfileStats {
+    /// The number of matches the player completed, or left during a network match.
+    pub matches_played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub kills: u32,
+    pub deaths: u32,
+    /// The number of kills made with each weapon, by element name.
+    pub weapon_kills: BTreeMap<String, u32>,
+    /// The number of matches played as each fish, by fish name.
+    pub fish_played: BTreeMap<String, u32>,
+}
+
+impl ProfileStats {
+    /// Get the name of the weapon the player has made the most kills with.
+    pub fn favorite_weapon(&self) -> Option<&str> {
+        most_counted(&self.weapon_kills)
+    }
+
+    /// Get the fish names and how many matches they were played in, starting with the most played.
+    pub fn fish_by_usage(&self) -> Vec<(&str, u32)> {
+        let mut fish = self
+            .fish_played
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect::<Vec<_>>();
+        // The sort is stable, so fish played equally often stay in alphabetical order
+        fish.sort_by(|a, b| b.1.cmp(&a.1));
+        fish
+    }
+}
+
+/// Get the key with the highest count, picking the first one alphabetically if there is a tie.
+fn most_counted(counts: &BTreeMap<String, u32>) -> Option<&str> {
+    counts
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(name, _)| name.as_str())
+}
+
+#[cfg(test)]
+mod test {
+    use jumpy_core::score::KillRecord;
+
+    use super::*;
+
+    fn kill(victim: usize, killer: Option<usize>, weapon: Option<&str>) -> KillRecord {
+        KillRecord {
+            victim,
+            killer,
+            weapon: weapon.map(String::from),
+        }
+    }
+
+    #[test]
+    fn record_match_counts_results_and_kills() {
+        let score = MatchScore {
+            result: Some(MatchResult::Winner(1)),
+            kills: vec![
+                kill(0, Some(1), Some("Musket")),
+                kill(2, Some(1), Some("Sword")),
+                kill(0, Some(1), Some("Musket")),
+                kill(1, None, None),
+                kill(0, None, None),
+            ],
+            ..Default::default()
+        };
+
+        let mut stats = PlayerStats::default();
+        stats.record_match(0, 0, "Fishy", &score);
+        stats.record_match(2, 1, "Sharky", &score);
+
+        let loser = stats.profile(0).unwrap();
+        assert_eq!((loser.wins, loser.losses), (0, 1));
+        assert_eq!((loser.kills, loser.deaths), (0, 3));
+
+        assert_eq!(stats.profile(1), Some(&ProfileStats::default()));
+
+        let winner = stats.profile(2).unwrap();
+        assert_eq!(winner.matches_played, 1);
+        assert_eq!((winner.wins, winner.losses), (1, 0));
+        assert_eq!((winner.kills, winner.deaths), (3, 1));
+        assert_eq!(winner.favorite_weapon(), Some("Musket"));
+        assert_eq!(winner.fish_by_usage(), vec![("Sharky", 1)]);
+    }
+
+    #[test]
+    fn unfinished_match_counts_as_loss() {
+        let mut stats = PlayerStats::default();
+        stats.record_match(0, 0, "Fishy", &MatchScore::default());
+        stats.record_match(
+            0,
+            0,
+            "Fishy",
+            &MatchScore {
+                result: Some(MatchResult::Draw),
+                ..Default::default()
+            },
+        );
+
+        let stats = stats.profile(0).unwrap();
+        assert_eq!(stats.matches_played, 2);
+        assert_eq!((stats.wins, stats.losses), (0, 1));
+        assert_eq!(stats.fish_played.get("Fishy"), Some(&2));
+    }
+}
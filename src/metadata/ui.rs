@@ -49,6 +49,16 @@ impl UiThemeEditorIcons {
 #[serde(deny_unknown_fields)]
 pub struct UiThemeColors {
     pub positive: ColorMeta,
+    pub negative: ColorMeta,
+    /// The color of each player, used for their indicators and in the HUD.
+    pub players: Vec<ColorMeta>,
+}
+
+impl UiThemeColors {
+    /// Get the color of the player with the given index.
+    pub fn player(&self, player_idx: usize) -> ColorMeta {
+        self.players[player_idx % self.players.len()]
+    }
 }
 
 #[derive(BonesBevyAssetLoad, Deserialize, Clone, Debug)]
@@ -1,26 +1,188 @@
 // #![doc = include_str!("./networking.md")]
 
+use std::sync::Mutex;
+
+use bevy::utils::Instant;
 use ggrs::P2PSession;
-use jumpy_core::input::PlayerControl;
+use jumpy_core::{
+    checksum::FrameChecksum,
+    input::{ConnectionStatus, PlayerControl, MAX_INPUT_DELAY},
+};
 use rand::Rng;
 
-use crate::prelude::*;
+use crate::{main_menu::MenuPage, prelude::*, presence::PresenceState};
 
 pub mod certs;
 
+pub use handshake::*;
+mod handshake;
+
 pub mod proto;
 use proto::*;
 
 pub use lan::*;
 mod lan;
 
+pub use lan_discovery::*;
+mod lan_discovery;
+
 pub use online::*;
 mod online;
 
+pub use resync::*;
+mod resync;
+
 pub struct NetworkingPlugin;
 
 impl Plugin for NetworkingPlugin {
-    fn build(&self, _app: &mut App) {}
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkDebugSettings>()
+            .init_resource::<NetworkDiagnostics>()
+            .init_resource::<ChatHistory>()
+            .add_system(stop_lan_beacon.run_if(resource_exists::<LanBeacon>()));
+    }
+}
+
+/// Stop broadcasting the [`LanBeacon`] once the match starts, or the host leaves the game.
+fn stop_lan_beacon(
+    mut commands: Commands,
+    engine_state: Res<State<EngineState>>,
+    menu_page: Res<MenuPage>,
+) {
+    if engine_state.0 == EngineState::InGame || matches!(*menu_page, MenuPage::Home) {
+        commands.remove_resource::<LanBeacon>();
+    }
+}
+
+/// How often, in network frames, players exchange [`FrameChecksum`]s to check for de-syncs.
+const CHECKSUM_INTERVAL: ggrs::Frame = 60;
+
+/// How long a remote player can go without responding before they are considered disconnected.
+const DISCONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How many confirmed frames of local checksums to keep around for comparing with remote players.
+const CHECKSUM_HISTORY: ggrs::Frame = 600;
+
+/// How long to wait for every remote player to finish loading the match before starting without
+/// the ones that haven't.
+const READY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Resource containing network debugging settings.
+#[derive(Resource, Default)]
+pub struct NetworkDebugSettings {
+    /// Exchange checksums every frame instead of every [`CHECKSUM_INTERVAL`] frames, to find the
+    /// exact frame that a de-sync happens on.
+    pub checksum_every_frame: bool,
+}
+
+/// How many of the most recent [`NetworkDiagnostics`] samples the adaptive input delay is based on.
+const ADAPTIVE_INPUT_DELAY_SAMPLES: usize = 10;
+
+/// How many seconds of samples to keep in the [`NetworkDiagnostics`] history.
+const DIAGNOSTICS_HISTORY: usize = 60;
+
+/// Resource containing network health statistics for the current network match, sampled once per
+/// second.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct NetworkDiagnostics {
+    /// The samples from the last [`DIAGNOSTICS_HISTORY`] seconds, oldest first.
+    pub samples: std::collections::VecDeque<NetworkDiagnosticsSample>,
+}
+
+impl NetworkDiagnostics {
+    /// Get the most recent sample.
+    pub fn latest(&self) -> Option<&NetworkDiagnosticsSample> {
+        self.samples.back()
+    }
+
+    fn push(&mut self, sample: NetworkDiagnosticsSample) {
+        if self.samples.len() >= DIAGNOSTICS_HISTORY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+}
+
+/// A single sample of [`NetworkDiagnostics`].
+#[derive(Clone, Debug, Default)]
+pub struct NetworkDiagnosticsSample {
+    /// The round-trip time to each remote player, in milliseconds.
+    pub ping: [Option<u32>; MAX_PLAYERS],
+    /// The number of frames that were rolled back and re-simulated in the last second.
+    pub rollback_frames: u32,
+    /// How many frames the simulation is ahead of the last frame with confirmed inputs from every
+    /// player.
+    pub predicted_frames: i32,
+    pub bytes_sent_per_second: u64,
+    pub bytes_received_per_second: u64,
+    /// The input delay, in frames, of the GGRS session.
+    pub input_delay: u8,
+}
+
+/// Resource inserted when our game state has diverged from a remote player's.
+#[derive(Resource, Clone, Debug)]
+pub struct DesyncDetected {
+    /// The frame that the checksums were compared on.
+    pub frame: ggrs::Frame,
+    /// The remote player whose checksum didn't match.
+    pub player: usize,
+    pub local_checksum: u64,
+    pub remote_checksum: u64,
+}
+
+/// How many unreliable messages are kept for [`NetworkSocket::recv_unreliable`] before new ones are
+/// dropped.
+const UNRELIABLE_MESSAGE_BUFFER: usize = 64;
+
+/// The maximum number of characters allowed in a chat message.
+pub const MAX_CHAT_MESSAGE_LEN: usize = 120;
+
+/// How many chat messages to keep in the [`ChatHistory`].
+const CHAT_HISTORY: usize = 50;
+
+/// Resource containing the chat messages sent during the current network match.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct ChatHistory {
+    /// The messages, oldest first.
+    pub messages: std::collections::VecDeque<ChatMessage>,
+}
+
+impl ChatHistory {
+    /// Add a message to the history, sanitizing it first.
+    ///
+    /// `time` is the elapsed time, in seconds, that the message was received at.
+    pub fn push(&mut self, player: usize, message: &str, time: f32) {
+        let text = sanitize_chat_message(message);
+        if text.trim().is_empty() {
+            return;
+        }
+        self.messages.push_back(ChatMessage { player, text, time });
+        while self.messages.len() > CHAT_HISTORY {
+            self.messages.pop_front();
+        }
+    }
+}
+
+/// A chat message in the [`ChatHistory`].
+#[derive(Clone, Debug)]
+pub struct ChatMessage {
+    /// The player that sent the message.
+    pub player: usize,
+    pub text: String,
+    /// The elapsed time, in seconds, that the message was received at.
+    pub time: f32,
+}
+
+/// Strip control characters from a chat message and limit it to [`MAX_CHAT_MESSAGE_LEN`]
+/// characters.
+///
+/// Messages from remote players can contain anything, so this must be done before displaying them.
+pub fn sanitize_chat_message(message: &str) -> String {
+    message
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_CHAT_MESSAGE_LEN)
+        .collect()
 }
 
 /// The [`ggrs::Config`] implementation used by Jumpy.
@@ -69,8 +231,76 @@ pub static NETWORK_ENDPOINT: Lazy<quinn::Endpoint> = Lazy::new(|| {
 });
 
 /// Resource containing the network socket while there is a connection to a LAN or online game.
-#[derive(Resource, Deref, DerefMut)]
-pub struct NetworkMatchSocket(pub Box<dyn NetworkSocket>);
+///
+/// Reliable messages are sent and received through this as [`ReliableMessage`]s, after a
+/// [`Handshake`] with the other players that makes sure they are playing a compatible version of
+/// the game.
+#[derive(Resource)]
+pub struct NetworkMatchSocket {
+    socket: Box<dyn NetworkSocket>,
+    handshake: Mutex<HandshakeProgress>,
+    /// The messages received while polling the handshake, which are kept until they are received
+    /// with [`NetworkMatchSocket::recv_messages`].
+    inbox: Mutex<Vec<(usize, ReliableMessage)>>,
+}
+
+impl std::ops::Deref for NetworkMatchSocket {
+    type Target = dyn NetworkSocket;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.socket
+    }
+}
+
+impl NetworkMatchSocket {
+    /// Create the socket for a match, and send our [`Handshake`] to the other players.
+    pub fn new(socket: Box<dyn NetworkSocket>) -> Self {
+        let local = Handshake::local();
+        socket.send_reliable(
+            SocketTarget::All,
+            &ReliableMessage::from(local.clone()).encode(),
+        );
+        Self {
+            socket,
+            handshake: Mutex::new(HandshakeProgress::new(local)),
+            inbox: default(),
+        }
+    }
+
+    /// Send a reliable message to the given [`SocketTarget`].
+    pub fn send_message(&self, target: SocketTarget, message: impl Into<ReliableMessage>) {
+        self.socket.send_reliable(target, &message.into().encode());
+    }
+
+    /// Receive the reliable messages sent by other players, other than their [`Handshake`]s. The
+    /// `usize` is the index of the player that sent the message.
+    pub fn recv_messages(&self) -> Vec<(usize, ReliableMessage)> {
+        self.poll();
+        std::mem::take(&mut *self.inbox.lock().unwrap())
+    }
+
+    /// Get the status of the [`Handshake`] with the other players.
+    pub fn handshake_status(&self) -> HandshakeStatus {
+        self.poll();
+        let player_is_local = self.socket.player_is_local();
+        self.handshake
+            .lock()
+            .unwrap()
+            .status((0..self.socket.player_count()).filter(|&i| !player_is_local[i]))
+    }
+
+    /// Receive the reliable messages from the socket, handling the handshakes and keeping the
+    /// rest in the inbox.
+    fn poll(&self) {
+        let mut handshake = self.handshake.lock().unwrap();
+        let mut inbox = self.inbox.lock().unwrap();
+        for (player, data) in self.socket.recv_reliable() {
+            if let Some(message) = handshake.receive(player, ReliableMessage::decode(&data)) {
+                inbox.push((player, message));
+            }
+        }
+    }
+}
 
 /// A boxed [`ggrs::NonBlockingSocket`] implementation.
 #[derive(Deref, DerefMut)]
@@ -88,13 +318,22 @@ impl ggrs::NonBlockingSocket<usize> for BoxedNonBlockingSocket {
 
 /// Trait implemented by network match sockets.
 pub trait NetworkSocket: Sync + Send {
-    /// Get a GGRS socket from this network socket.
-    fn ggrs_socket(&self) -> BoxedNonBlockingSocket;
+    /// Get a GGRS socket from this network socket, for the GGRS session with the given number.
+    ///
+    /// Messages for other sessions are dropped, so that a session that is restarted doesn't
+    /// receive the messages that were meant for the old one.
+    fn ggrs_socket(&self, session: u32) -> BoxedNonBlockingSocket;
     /// Send a reliable message to the given [`SocketTarget`].
     fn send_reliable(&self, target: SocketTarget, message: &[u8]);
     /// Receive reliable messages from other players. The `usize` is the index of the player that
     /// sent the message.
     fn recv_reliable(&self) -> Vec<(usize, Vec<u8>)>;
+    /// Send an unreliable message to the given [`SocketTarget`]. The message may be lost or arrive
+    /// out of order.
+    fn send_unreliable(&self, target: SocketTarget, message: &[u8]);
+    /// Receive unreliable messages from other players. The `usize` is the index of the player that
+    /// sent the message.
+    fn recv_unreliable(&self) -> Vec<(usize, Vec<u8>)>;
     /// Close the connection.
     fn close(&self);
     /// Get the player index of the local player.
@@ -103,6 +342,40 @@ pub trait NetworkSocket: Sync + Send {
     fn player_is_local(&self) -> [bool; MAX_PLAYERS];
     /// Get the player count for this network match.
     fn player_count(&self) -> usize;
+    /// Get whether or not we still have a connection to the given player.
+    fn player_is_connected(&self, player: usize) -> bool;
+    /// Get the total amount of data sent and received by this socket, over both the reliable and
+    /// unreliable channels.
+    fn traffic(&self) -> NetworkTraffic;
+}
+
+/// The total amount of data that has gone through a [`NetworkSocket`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetworkTraffic {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+impl NetworkTraffic {
+    /// Get the traffic for a QUIC connection.
+    pub fn of_connection(conn: &quinn::Connection) -> Self {
+        let stats = conn.stats();
+        Self {
+            bytes_sent: stats.udp_tx.bytes,
+            bytes_received: stats.udp_rx.bytes,
+        }
+    }
+}
+
+impl std::ops::Add for NetworkTraffic {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            bytes_sent: self.bytes_sent + rhs.bytes_sent,
+            bytes_received: self.bytes_received + rhs.bytes_received,
+        }
+    }
 }
 
 /// The target for a reliable network message.
@@ -114,54 +387,493 @@ pub enum SocketTarget {
 }
 
 /// [`SessionRunner`] implementation that uses [`ggrs`] for network play.
+///
+/// The GGRS session is started once every player has loaded the match, and is restarted whenever
+/// the players agree to change its input delay, or to let a disconnected player [`Rejoin`], with a
+/// [`Resync`].
 pub struct GgrsSessionRunner {
     pub last_player_input: PlayerControl,
     pub core: CoreSession,
-    pub session: P2PSession<GgrsConfig>,
+    /// The GGRS session, once every player is ready and the match has been started.
+    pub session: Option<P2PSession<GgrsConfig>>,
+    /// How many GGRS sessions have been started, which tells the messages of the current session
+    /// apart from the ones of earlier sessions.
+    pub session_count: u32,
+    /// The network frame that the current GGRS session started on.
+    pub frame_offset: ggrs::Frame,
     pub player_is_local: [bool; MAX_PLAYERS],
+    pub player_count: usize,
+    /// Which remote players have been disconnected from the session.
+    pub player_is_disconnected: [bool; MAX_PLAYERS],
     pub delta: f32,
     pub accumulator: f32,
+    /// The network frame that the core session is on.
+    pub frame: ggrs::Frame,
+    /// The last network frame that had confirmed inputs from every player the last time the
+    /// session was advanced.
+    pub confirmed_frame: ggrs::Frame,
+    /// Our local game state checksums, by frame.
+    pub checksums: HashMap<ggrs::Frame, u64>,
+    /// Checksums from remote players that haven't been compared with ours yet.
+    pub remote_checksums: Vec<(usize, ggrs::Frame, u64)>,
+    /// The last confirmed frame that we have sent our checksum for.
+    pub last_checksum_sent: ggrs::Frame,
+    /// The number of frames rolled back since the last [`NetworkDiagnostics`] sample.
+    pub rollback_frames: u32,
+    /// When the last [`NetworkDiagnostics`] sample was taken, and the socket traffic at that time.
+    pub last_diagnostics_sample: Option<(Instant, NetworkTraffic)>,
+    /// Which players have loaded the match and are ready to start it.
+    pub player_is_ready: [bool; MAX_PLAYERS],
+    /// When we started waiting for the other players to be ready, or [`None`] if we haven't yet
+    /// told them that we are ready.
+    pub waiting_for_players_since: Option<Instant>,
+    /// The input delay that each player asks for. The GGRS session uses the highest delay that
+    /// any player in it asks for.
+    pub player_input_delay: [u8; MAX_PLAYERS],
+    /// The input delay of the current GGRS session.
+    pub input_delay: u8,
+    /// The lowest input delay to ask for, if the delay we ask for adapts to the latency jitter of
+    /// our connection, or [`None`] to always ask for the same delay.
+    pub adaptive_input_delay: Option<u8>,
+    /// When the input delay of the session was last set.
+    pub input_delay_changed: Option<Instant>,
+    /// The inputs that each network frame was last simulated with, which are its confirmed inputs
+    /// once the frame is confirmed.
+    pub frame_inputs: std::collections::BTreeMap<ggrs::Frame, NetworkInputs>,
+    /// Snapshots of the game state at the start of every [`SNAPSHOT_INTERVAL`]th network frame,
+    /// which the GGRS session can be restarted from.
+    pub snapshots: std::collections::BTreeMap<ggrs::Frame, bones::World>,
+    /// The restart of the GGRS session that is in progress.
+    pub resync: Option<Resync>,
+    /// The frame of the last [`Resync`] that each player is ready for.
+    pub player_resync_frame: [Option<ggrs::Frame>; MAX_PLAYERS],
+    /// Our attempt to rejoin the match, after losing the connection to every other player.
+    pub rejoin: Option<Rejoin>,
 }
 
 /// The info required to create a [`GgrsSessionRunner`].
 pub struct GgrsSessionRunnerInfo {
-    pub socket: BoxedNonBlockingSocket,
     pub player_is_local: [bool; MAX_PLAYERS],
     pub player_count: usize,
+    /// The input delay, in frames, from the player's settings.
+    pub input_delay: u8,
+    /// Whether the input delay adapts to the connection, using `input_delay` as the minimum.
+    pub adaptive_input_delay: bool,
 }
 
 impl GgrsSessionRunner {
-    pub fn new(mut core: CoreSession, info: GgrsSessionRunnerInfo) -> Self
+    pub fn new(core: CoreSession, info: GgrsSessionRunnerInfo) -> Self
     where
         Self: Sized,
     {
-        core.time_step = 1.0 / (jumpy_core::FPS * NETWORK_FRAME_RATE_FACTOR);
+        let input_delay = info.input_delay.min(MAX_INPUT_DELAY);
+
+        Self {
+            last_player_input: PlayerControl::default(),
+            core,
+            session: None,
+            session_count: 0,
+            frame_offset: 0,
+            player_is_local: info.player_is_local,
+            player_count: info.player_count,
+            player_is_disconnected: default(),
+            accumulator: default(),
+            delta: default(),
+            frame: 0,
+            confirmed_frame: ggrs::NULL_FRAME,
+            checksums: default(),
+            remote_checksums: default(),
+            last_checksum_sent: ggrs::NULL_FRAME,
+            rollback_frames: 0,
+            last_diagnostics_sample: None,
+            player_is_ready: info.player_is_local,
+            waiting_for_players_since: None,
+            player_input_delay: [input_delay; MAX_PLAYERS],
+            input_delay,
+            adaptive_input_delay: info.adaptive_input_delay.then_some(input_delay),
+            input_delay_changed: None,
+            frame_inputs: default(),
+            snapshots: default(),
+            resync: None,
+            player_resync_frame: default(),
+            rejoin: None,
+        }
+    }
+
+    /// Whether we are still waiting for the other players to load the match before starting it, or
+    /// to let us rejoin it.
+    pub fn is_waiting_for_players(&self) -> bool {
+        self.session.is_none() || self.rejoin.is_some()
+    }
+
+    /// Get the index of the local player.
+    fn local_player_idx(&self) -> usize {
+        // We are the first local player
+        self.player_is_local
+            .iter()
+            .position(|is_local| *is_local)
+            .unwrap()
+    }
+
+    /// Tell the other players that we are ready, and start the match once they are all ready too.
+    ///
+    /// Players that still aren't ready after the [`READY_TIMEOUT`] are dropped from the match. The
+    /// rest play with the highest input delay that any of them asks for.
+    fn wait_for_players(&mut self, bevy_world: &mut World) -> Result<(), SessionError> {
+        let Some(socket) = bevy_world.get_resource::<NetworkMatchSocket>() else {
+            // The match can't be played without a connection to the other players
+            return Err(SessionError::Disconnected);
+        };
+
+        // We've finished loading by the time the session is advanced
+        let input_delay = self.player_input_delay[self.local_player_idx()];
+        let waiting_since = *self.waiting_for_players_since.get_or_insert_with(|| {
+            socket.send_message(SocketTarget::All, MatchMessage::Ready { input_delay });
+            Instant::now()
+        });
+
+        let not_ready = (0..self.player_count)
+            .filter(|&player| !self.player_is_ready[player])
+            .collect::<Vec<_>>();
+        if !not_ready.is_empty() && waiting_since.elapsed() <= READY_TIMEOUT {
+            return Ok(());
+        }
+
+        self.input_delay = (0..self.player_count)
+            .filter(|&player| self.player_is_ready[player])
+            .map(|player| self.player_input_delay[player])
+            .max()
+            .unwrap_or_default();
+        self.input_delay_changed = Some(Instant::now());
+        info!(input_delay = %self.input_delay, "All network players are ready, starting match");
+        self.start_session(socket, self.player_is_ready);
+
+        for player in not_ready {
+            warn!(%player, "Network player never became ready, dropping them from the match");
+            if let Some(mut presence) = bevy_world.get_resource_mut::<PresenceState>() {
+                presence.player_left();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start a new GGRS session on the current network frame, with the current input delay.
+    ///
+    /// The remote players that aren't in `players` are disconnected from the start.
+    fn start_session(&mut self, socket: &NetworkMatchSocket, players: [bool; MAX_PLAYERS]) {
         let mut builder = ggrs::SessionBuilder::new()
-            .with_num_players(info.player_count)
+            .with_num_players(self.player_count)
             .with_max_prediction_window(8)
-            .with_input_delay(1)
-            .with_fps((jumpy_core::FPS * NETWORK_FRAME_RATE_FACTOR) as usize)
+            .with_input_delay(self.input_delay as usize)
+            .with_disconnect_timeout(DISCONNECT_TIMEOUT)
+            .with_fps(jumpy_core::FPS as usize)
             .unwrap();
 
-        for i in 0..info.player_count {
-            if info.player_is_local[i] {
+        for i in 0..self.player_count {
+            if self.player_is_local[i] {
                 builder = builder.add_player(ggrs::PlayerType::Local, i).unwrap();
             } else {
                 builder = builder.add_player(ggrs::PlayerType::Remote(i), i).unwrap();
             }
         }
 
-        let session = builder.start_p2p_session(info.socket).unwrap();
+        self.session_count += 1;
+        let mut session = builder
+            .start_p2p_session(socket.ggrs_socket(self.session_count))
+            .unwrap();
+
+        let disconnected = self.player_is_disconnected.iter_mut().enumerate();
+        for (player, is_disconnected) in disconnected.take(self.player_count) {
+            *is_disconnected = !self.player_is_local[player] && !players[player];
+            if *is_disconnected {
+                if let Err(e) = session.disconnect_player(player) {
+                    warn!(%player, "Couldn't disconnect network player: {e}");
+                }
+            }
+        }
+
+        self.frame_offset = self.frame;
+        self.confirmed_frame = self.frame - 1;
+        self.session = Some(session);
+    }
 
-        Self {
-            last_player_input: PlayerControl::default(),
-            core,
-            session,
-            player_is_local: info.player_is_local,
-            accumulator: default(),
-            delta: default(),
+    /// Whether every remote player has been disconnected from the session.
+    fn all_remote_players_disconnected(&self) -> bool {
+        (0..self.player_count)
+            .filter(|&player| !self.player_is_local[player])
+            .all(|player| self.player_is_disconnected[player])
+    }
+
+    /// Add a sample to the [`NetworkDiagnostics`] once every second.
+    fn update_diagnostics(&mut self, bevy_world: &mut World) {
+        let Some(socket) = bevy_world.get_resource::<NetworkMatchSocket>() else {
+            return;
+        };
+        let Some(session) = &self.session else {
+            return;
+        };
+        let now = Instant::now();
+        let traffic = socket.traffic();
+
+        let Some((last_sample_time, last_traffic)) = self.last_diagnostics_sample else {
+            // Start with a fresh history for this match
+            bevy_world.resource_mut::<NetworkDiagnostics>().samples.clear();
+            self.last_diagnostics_sample = Some((now, traffic));
+            return;
+        };
+        let elapsed = (now - last_sample_time).as_secs_f64();
+        if elapsed < 1.0 {
+            return;
+        }
+
+        let ping = std::array::from_fn(|player| {
+            if self.player_is_local[player] {
+                return None;
+            }
+            session
+                .network_stats(player)
+                .ok()
+                .map(|stats| stats.ping as u32)
+        });
+        let per_second = |bytes: u64| (bytes as f64 / elapsed) as u64;
+        let sample = NetworkDiagnosticsSample {
+            ping,
+            rollback_frames: self.rollback_frames,
+            predicted_frames: self.frame - self.confirmed_frame,
+            bytes_sent_per_second: per_second(traffic.bytes_sent - last_traffic.bytes_sent),
+            bytes_received_per_second: per_second(
+                traffic.bytes_received - last_traffic.bytes_received,
+            ),
+            input_delay: self.input_delay,
+        };
+
+        self.rollback_frames = 0;
+        self.last_diagnostics_sample = Some((now, traffic));
+        let mut diagnostics = bevy_world.resource_mut::<NetworkDiagnostics>();
+        diagnostics.push(sample);
+
+        let Some(delay) = self
+            .adaptive_input_delay
+            .map(|min_delay| adaptive_input_delay(&diagnostics, min_delay))
+        else {
+            return;
+        };
+        let local_player_idx = self.local_player_idx();
+        if delay != self.player_input_delay[local_player_idx] {
+            info!(%delay, "Adapting input delay to network jitter");
+            self.player_input_delay[local_player_idx] = delay;
+            bevy_world.resource::<NetworkMatchSocket>().send_message(
+                SocketTarget::All,
+                MatchMessage::InputDelay { input_delay: delay },
+            );
+        }
+    }
+
+    /// Handle the reliable [`MatchMessage`]s sent to us by the other players.
+    fn receive_match_messages(&mut self, bevy_world: &mut World) {
+        let Some(socket) = bevy_world.get_resource::<NetworkMatchSocket>() else {
+            return;
+        };
+        let messages = socket.recv_messages();
+        let time = bevy_world.resource::<Time>().elapsed_seconds();
+
+        for (player, message) in messages {
+            let ReliableMessage::Match(message) = message else {
+                // Messages left over from the menus
+                continue;
+            };
+            match message {
+                MatchMessage::Checksum { frame, checksum } => {
+                    self.remote_checksums.push((player, frame, checksum))
+                }
+                MatchMessage::Chat { message } => bevy_world
+                    .resource_mut::<ChatHistory>()
+                    .push(player, &message, time),
+                MatchMessage::Ready { input_delay } => {
+                    self.player_is_ready[player] = true;
+                    self.player_input_delay[player] = input_delay.min(MAX_INPUT_DELAY);
+                }
+                MatchMessage::InputDelay { input_delay } => {
+                    self.player_input_delay[player] = input_delay.min(MAX_INPUT_DELAY);
+                }
+                MatchMessage::Resync {
+                    frame,
+                    input_delay,
+                    players,
+                    snapshot_frame,
+                } => {
+                    let mut snapshot_frames = [None; MAX_PLAYERS];
+                    snapshot_frames[self.local_player_idx()] = snapshot_frame;
+                    self.begin_resync(frame, input_delay, players, snapshot_frames);
+                }
+                MatchMessage::ResyncReady { frame } => {
+                    self.player_resync_frame[player] = Some(frame);
+                }
+                MatchMessage::Rejoin { snapshot_frames } => {
+                    self.accept_rejoin(player, &snapshot_frames, bevy_world)
+                }
+                MatchMessage::ResyncInputs { frame, inputs } => {
+                    self.receive_rejoin_inputs(frame, inputs)
+                }
+            }
         }
     }
+
+    /// Send the checksums of newly confirmed frames to the other players, and compare the
+    /// checksums we've received from them with our own.
+    fn exchange_checksums(&mut self, bevy_world: &mut World) {
+        let Some(socket) = bevy_world.get_resource::<NetworkMatchSocket>() else {
+            return;
+        };
+        let every_frame = bevy_world
+            .resource::<NetworkDebugSettings>()
+            .checksum_every_frame;
+        let confirmed_frame = self.confirmed_frame;
+
+        for frame in (self.last_checksum_sent + 1)..=confirmed_frame {
+            if !every_frame && frame % CHECKSUM_INTERVAL != 0 {
+                continue;
+            }
+            if let Some(&checksum) = self.checksums.get(&frame) {
+                let message = MatchMessage::Checksum { frame, checksum };
+                socket.send_message(SocketTarget::All, message);
+            }
+        }
+        self.last_checksum_sent = self.last_checksum_sent.max(confirmed_frame);
+
+        // Only compare checksums for frames that we have confirmed inputs for, because the
+        // checksums of predicted frames are expected to differ. Players who were disconnected
+        // carried on without us, so their checksums are expected to differ too.
+        let mut desync = None;
+        let checksums = &self.checksums;
+        let player_is_disconnected = self.player_is_disconnected;
+        self.remote_checksums
+            .retain(|&(player, frame, remote_checksum)| {
+                if player_is_disconnected[player] {
+                    return false;
+                }
+                if frame > confirmed_frame {
+                    return true;
+                }
+
+                match checksums.get(&frame) {
+                    Some(&local_checksum) if local_checksum != remote_checksum => {
+                        warn!(
+                            %frame,
+                            %local_checksum,
+                            %remote_checksum,
+                            %player,
+                            "Network de-sync detected: game state differs from remote player"
+                        );
+                        desync = Some(DesyncDetected {
+                            frame,
+                            player,
+                            local_checksum,
+                            remote_checksum,
+                        });
+                    }
+                    Some(_) => (),
+                    None => debug!(%frame, %player, "No local checksum for remote checksum"),
+                }
+
+                false
+            });
+        if let Some(desync) = desync {
+            bevy_world.insert_resource(desync);
+        }
+
+        let oldest_frame = confirmed_frame - CHECKSUM_HISTORY;
+        self.checksums.retain(|frame, _| *frame >= oldest_frame);
+    }
+
+    /// Simulate the current network frame with the players' network inputs.
+    ///
+    /// Players without inputs are marked as disconnected, and players who have inputs again before
+    /// they are removed from the match have rejoined it.
+    fn advance_core(&mut self, inputs: &NetworkInputs, bevy_world: &mut World) {
+        let player_count = self.player_count;
+        self.core.update_input(|player_inputs| {
+            for (player_idx, input) in inputs.iter().enumerate().take(player_count) {
+                let player = &mut player_inputs.players[player_idx];
+                match (input, player.connection) {
+                    (None, ConnectionStatus::Connected) => {
+                        player.connection = ConnectionStatus::Disconnected;
+                    }
+                    (Some(_), ConnectionStatus::Disconnected) => {
+                        player.connection = ConnectionStatus::Connected;
+                    }
+                    _ => (),
+                }
+                let control = input.as_ref().map(get_control).unwrap_or_default();
+                apply_control(&mut player.control, &control);
+            }
+        });
+        self.core.advance(bevy_world);
+
+        self.frame += 1;
+        let checksum = self.core.world.resource::<FrameChecksum>().borrow().0;
+        self.checksums.insert(self.frame, checksum);
+    }
+}
+
+/// Get the input delay, in frames, that covers the jitter in the one-way latency to the remote
+/// players over the recent [`NetworkDiagnostics`] samples, and is at least `min_delay`.
+fn adaptive_input_delay(diagnostics: &NetworkDiagnostics, min_delay: u8) -> u8 {
+    let frame_ms = 1000.0 / jumpy_core::FPS;
+    let jitter_ms = (0..MAX_PLAYERS)
+        .filter_map(|player| {
+            let pings = diagnostics
+                .samples
+                .iter()
+                .rev()
+                .take(ADAPTIVE_INPUT_DELAY_SAMPLES)
+                .filter_map(|sample| sample.ping[player]);
+            let min = pings.clone().min()?;
+            let max = pings.max()?;
+            // The one-way latency is half of the round trip
+            Some((max - min) as f32 / 2.0)
+        })
+        .fold(0.0, f32::max);
+
+    ((jitter_ms / frame_ms).ceil() as u8).clamp(min_delay.min(MAX_INPUT_DELAY), MAX_INPUT_DELAY)
+}
+
+/// Get the buttons held and the movement direction from a player's network input.
+fn get_control(input: &DensePlayerControl) -> PlayerControl {
+    PlayerControl {
+        move_direction: input.move_direction().0,
+        jump_pressed: input.jump_pressed(),
+        grab_pressed: input.grab_pressed(),
+        shoot_pressed: input.shoot_pressed(),
+        emote_pressed: input.emote_pressed(),
+        dash_pressed: input.dash_pressed(),
+        ..default()
+    }
+}
+
+/// Update a player's control with the buttons held in `input`, marking the ones that were just
+/// pressed.
+fn apply_control(control: &mut PlayerControl, input: &PlayerControl) {
+    control.jump_just_pressed = input.jump_pressed && !control.jump_pressed;
+    control.jump_pressed = input.jump_pressed;
+
+    control.grab_just_pressed = input.grab_pressed && !control.grab_pressed;
+    control.grab_pressed = input.grab_pressed;
+
+    control.shoot_just_pressed = input.shoot_pressed && !control.shoot_pressed;
+    control.shoot_pressed = input.shoot_pressed;
+
+    control.emote_just_pressed = input.emote_pressed && !control.emote_pressed;
+    control.emote_pressed = input.emote_pressed;
+
+    control.dash_just_pressed = input.dash_pressed && !control.dash_pressed;
+    control.dash_pressed = input.dash_pressed;
+
+    let was_moving = control.move_direction.length_squared() > f32::MIN_POSITIVE;
+    control.move_direction = input.move_direction;
+    let is_moving = control.move_direction.length_squared() > f32::MIN_POSITIVE;
+    control.just_moved = !was_moving && is_moving;
 }
 
 fn get_dense_input(control: &PlayerControl) -> DensePlayerControl {
@@ -170,6 +882,8 @@ fn get_dense_input(control: &PlayerControl) -> DensePlayerControl {
     dense_control.set_grab_pressed(control.grab_pressed);
     dense_control.set_slide_pressed(control.slide_pressed);
     dense_control.set_shoot_pressed(control.shoot_pressed);
+    dense_control.set_emote_pressed(control.emote_pressed);
+    dense_control.set_dash_pressed(control.dash_pressed);
     dense_control.set_move_direction(DenseMoveDirection(control.move_direction));
     dense_control
 }
@@ -191,14 +905,32 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
     }
 
     fn advance(&mut self, bevy_world: &mut World) -> Result<(), SessionError> {
-        const STEP: f32 = 1.0 / (jumpy_core::FPS * NETWORK_FRAME_RATE_FACTOR);
+        const STEP: f32 = 1.0 / jumpy_core::FPS;
         let delta = self.delta;
-        let local_player_idx = self.network_player_idx().unwrap();
+        let local_player_idx = self.local_player_idx();
 
         self.accumulator += delta;
+        self.receive_match_messages(bevy_world);
+
+        // Don't start simulating until everybody has loaded the match, so that nobody starts behind
+        if self.session.is_none() {
+            self.wait_for_players(bevy_world)?;
+            if self.session.is_none() {
+                self.accumulator = 0.0;
+                return Ok(());
+            }
+
+            // Nobody is left to play with if every remote player timed out
+            if self.all_remote_players_disconnected() {
+                return Err(SessionError::Disconnected);
+            }
+        }
+
+        self.update_resync(bevy_world);
 
         let mut skip_frames = 0;
-        for event in self.session.events() {
+        let events = self.session.as_mut().unwrap().events().collect::<Vec<_>>();
+        for event in events {
             match event {
                 ggrs::GGRSEvent::Synchronizing { addr, total, count } => {
                     info!(player=%addr, %total, progress=%count, "Syncing network player");
@@ -206,7 +938,19 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
                 ggrs::GGRSEvent::Synchronized { addr } => {
                     info!(player=%addr, "Syncrhonized network client");
                 }
-                ggrs::GGRSEvent::Disconnected { .. } => return Err(SessionError::Disconnected),
+                ggrs::GGRSEvent::Disconnected { addr } => {
+                    warn!(player=%addr, "Network player disconnected");
+                    self.player_is_disconnected[addr] = true;
+                    if let Some(mut presence) = bevy_world.get_resource_mut::<PresenceState>() {
+                        presence.player_left();
+                    }
+
+                    // Try to get back into the match after losing the connection to everybody, unless
+                    // we are the one that the others would rejoin
+                    if self.all_remote_players_disconnected() && self.should_rejoin() {
+                        self.begin_rejoin();
+                    }
+                }
                 ggrs::GGRSEvent::NetworkInterrupted { addr, .. } => {
                     info!(player=%addr, "Network player interrupted");
                 }
@@ -232,66 +976,79 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
             }
         }
 
-        loop {
-            self.session
+        // Keep the match going as long as there is somebody left who may rejoin it
+        self.update_rejoin(bevy_world)?;
+        if self.rejoin.is_none() && self.all_remote_players_removed() {
+            return Err(SessionError::Disconnected);
+        }
+
+        // Hold the game on the frame of a resync until the other players have reached it too
+        if self.is_waiting_for_resync() {
+            self.session.as_mut().unwrap().poll_remote_clients();
+            self.accumulator = 0.0;
+        }
+
+        let mut steps = 0;
+        while !self.is_waiting_for_resync() {
+            let session = self.session.as_mut().unwrap();
+            session
                 .add_local_input(local_player_idx, get_dense_input(&self.last_player_input))
                 .unwrap();
+            if self.accumulator >= STEP && steps >= MAX_FRAME_STEPS {
+                // Give up on catching up after a long hitch, instead of falling further behind by
+                // taking longer and longer to simulate the frames. The other players will wait for
+                // us to catch up.
+                warn!("Frame took too long: couldn't keep up with fixed update.");
+                self.accumulator %= STEP;
+            }
             if self.accumulator >= STEP {
                 self.accumulator -= STEP;
+                steps += 1;
 
                 if skip_frames > 0 {
                     skip_frames = skip_frames.saturating_sub(1);
                     continue;
                 }
 
-                match self.session.advance_frame() {
+                match session.advance_frame() {
                     Ok(requests) => {
+                        let confirmed_frame = session.confirmed_frame() + self.frame_offset;
                         for request in requests {
                             match request {
                                 ggrs::GGRSRequest::SaveGameState { cell, frame } => {
-                                    cell.save(frame, Some(self.core.world.clone()), None)
+                                    self.frame = frame + self.frame_offset;
+                                    let snapshot = self.core.snapshot();
+                                    if self.frame % SNAPSHOT_INTERVAL == 0 {
+                                        self.snapshots.insert(self.frame, snapshot.clone());
+                                    }
+                                    cell.save(frame, Some(snapshot), None)
                                 }
-                                ggrs::GGRSRequest::LoadGameState { cell, .. } => {
-                                    let world = cell.load().unwrap_or_default();
-                                    self.core.world = world;
+                                ggrs::GGRSRequest::LoadGameState { cell, frame } => {
+                                    let frame = frame + self.frame_offset;
+                                    self.rollback_frames += (self.frame - frame).max(0) as u32;
+                                    self.frame = frame;
+                                    let mut world = cell.load().unwrap_or_default();
+                                    self.core.restore(&mut world);
                                 }
                                 ggrs::GGRSRequest::AdvanceFrame {
                                     inputs: network_inputs,
                                 } => {
-                                    self.core.update_input(|inputs| {
-                                        for (player_idx, (input, _status)) in
-                                            network_inputs.into_iter().enumerate()
-                                        {
-                                            let control = &mut inputs.players[player_idx].control;
-
-                                            let jump_pressed = input.jump_pressed();
-                                            control.jump_just_pressed =
-                                                jump_pressed && !control.jump_pressed;
-                                            control.jump_pressed = jump_pressed;
-
-                                            let grab_pressed = input.grab_pressed();
-                                            control.grab_just_pressed =
-                                                grab_pressed && !control.grab_pressed;
-                                            control.grab_pressed = grab_pressed;
-
-                                            let shoot_pressed = input.shoot_pressed();
-                                            control.shoot_just_pressed =
-                                                shoot_pressed && !control.shoot_pressed;
-                                            control.shoot_pressed = shoot_pressed;
-
-                                            let was_moving =
-                                                control.move_direction.length_squared()
-                                                    > f32::MIN_POSITIVE;
-                                            control.move_direction = input.move_direction().0;
-                                            let is_moving = control.move_direction.length_squared()
-                                                > f32::MIN_POSITIVE;
-                                            control.just_moved = !was_moving && is_moving;
-                                        }
+                                    let inputs: NetworkInputs = std::array::from_fn(|player_idx| {
+                                        network_inputs.get(player_idx).and_then(
+                                            |(input, status)| {
+                                                (*status != ggrs::InputStatus::Disconnected)
+                                                    .then_some(*input)
+                                            },
+                                        )
                                     });
-                                    self.core.advance(bevy_world);
+                                    self.frame_inputs.insert(self.frame, inputs);
+                                    self.advance_core(&inputs, bevy_world);
                                 }
                             }
                         }
+                        // Without any remote players, the local inputs confirm frames ahead of time
+                        self.confirmed_frame = confirmed_frame.min(self.frame - 1);
+                        self.trim_history();
                     }
                     Err(e) => match e {
                         ggrs::GGRSError::NotSynchronized => {
@@ -303,11 +1060,18 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
                         e => error!("Network protocol error: {e}"),
                     },
                 }
+
+                // Stop once we have the frames we need to resync
+                self.update_resync(bevy_world);
             } else {
                 break;
             }
         }
 
+        self.exchange_checksums(bevy_world);
+        self.update_diagnostics(bevy_world);
+        self.update_input_delay(bevy_world);
+
         Ok(())
     }
 
@@ -316,13 +1080,48 @@ impl crate::session::SessionRunner for GgrsSessionRunner {
         ShouldRun::Yes
     }
 
+    fn interpolation_alpha(&self) -> f32 {
+        (self.accumulator * jumpy_core::FPS).min(1.0)
+    }
+
     fn network_player_idx(&mut self) -> Option<usize> {
-        // We are the first local player
-        for i in 0..MAX_PLAYERS {
-            if self.player_is_local[i] {
-                return Some(i);
-            }
+        Some(self.local_player_idx())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics(pings: &[u32]) -> NetworkDiagnostics {
+        let mut diagnostics = NetworkDiagnostics::default();
+        for &ping in pings {
+            diagnostics.push(NetworkDiagnosticsSample {
+                ping: [None, Some(ping), None, None],
+                ..default()
+            });
         }
-        unreachable!();
+        diagnostics
+    }
+
+    #[test]
+    fn adaptive_input_delay_covers_jitter() {
+        // A steady connection only needs the minimum delay, no matter how high its ping is
+        assert_eq!(adaptive_input_delay(&diagnostics(&[200; 10]), 1), 1);
+        assert_eq!(adaptive_input_delay(&diagnostics(&[]), 2), 2);
+
+        // 60ms of round trip jitter is 30ms one way, which is two frames
+        let delay = adaptive_input_delay(&diagnostics(&[40, 100, 60, 40]), 0);
+        assert_eq!(delay, 2);
+
+        // Only recent samples count
+        let mut pings = vec![40, 500];
+        pings.extend([40; ADAPTIVE_INPUT_DELAY_SAMPLES]);
+        assert_eq!(adaptive_input_delay(&diagnostics(&pings), 0), 0);
+
+        assert_eq!(
+            adaptive_input_delay(&diagnostics(&[0, 1000]), 0),
+            MAX_INPUT_DELAY
+        );
     }
 }
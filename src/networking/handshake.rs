@@ -0,0 +1,330 @@
+//! The handshake that players exchange as soon as they are connected, to make sure that everybody
+//! is playing a compatible version of the game before they get to player select.
+
+use std::{
+    hash::Hasher,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::utils::Instant;
+use jumpy_core::utils::FnvHasher;
+
+use super::*;
+
+/// How long to wait for the handshakes of the other players before giving up on the match.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The version of the game, shown to players when their versions don't match.
+const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The hash of the game data that affects gameplay, so that players with modified balance data
+/// can't play together.
+///
+/// This is the `.core.yaml` files and every `.yaml` file in the asset sub-folders, which are the
+/// elements, maps, and players that the core metadata loads.
+static CORE_META_HASH: Lazy<u64> = Lazy::new(|| {
+    let asset_dir = bevy::asset::FileAssetIo::get_base_path().join(
+        ENGINE_CONFIG
+            .asset_dir
+            .clone()
+            .unwrap_or_else(|| "assets".into()),
+    );
+    let mut files = Vec::new();
+    collect_core_files(&asset_dir, &asset_dir, &mut files);
+    // Sort the files so that the hash is the same on every computer
+    files.sort();
+
+    // The hash must be the same whatever Rust version built the game
+    let mut hasher = FnvHasher::default();
+    for (name, path) in &files {
+        match std::fs::read_to_string(path) {
+            Ok(data) => {
+                hasher.write(name.as_bytes());
+                hasher.write(normalize_yaml(&data).as_bytes());
+            }
+            Err(e) => warn!(file = %path.display(), "Could not read core asset file: {e}"),
+        }
+    }
+    hasher.finish()
+});
+
+/// Get the data in a YAML file without its formatting, so that it hashes the same no matter the
+/// line endings that the file was checked out with, or its comments and indentation.
+///
+/// Files that can't be parsed are only normalized to `\n` line endings.
+fn normalize_yaml(yaml: &str) -> String {
+    serde_yaml::from_str::<serde_yaml::Value>(yaml)
+        .and_then(|value| serde_yaml::to_string(&value))
+        .unwrap_or_else(|_| yaml.replace("\r\n", "\n"))
+}
+
+/// Collect the core files in `dir`, with their paths relative to the asset directory.
+fn collect_core_files(asset_dir: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let name = path
+            .strip_prefix(asset_dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if path.is_dir() {
+            collect_core_files(asset_dir, &path, files);
+        } else if (dir != asset_dir && name.ends_with(".yaml")) || name.ends_with(".core.yaml") {
+            files.push((name, path));
+        }
+    }
+}
+
+/// The first message sent to every other player once connected.
+///
+/// The encoding of this must never change, so that players can tell which version of the game the
+/// other players have, even when they can't read any of their other messages.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Handshake {
+    /// The [`NETWORK_PROTOCOL_VERSION`] of the player.
+    pub protocol_version: u32,
+    /// The version of the game the player has, shown when it isn't compatible with ours.
+    pub game_version: String,
+    /// The hash of the player's core game data.
+    pub core_meta_hash: u64,
+}
+
+impl Handshake {
+    /// Get the handshake for our own version of the game.
+    pub fn local() -> Self {
+        Self {
+            protocol_version: NETWORK_PROTOCOL_VERSION,
+            game_version: GAME_VERSION.into(),
+            core_meta_hash: *CORE_META_HASH,
+        }
+    }
+
+    /// Check that the handshake of another player is compatible with ours.
+    pub fn check(&self, player: usize, remote: &Handshake) -> Result<(), HandshakeError> {
+        if remote.protocol_version != self.protocol_version {
+            Err(HandshakeError::Version {
+                player,
+                local: self.game_version.clone(),
+                remote: remote.game_version.clone(),
+            })
+        } else if remote.core_meta_hash != self.core_meta_hash {
+            Err(HandshakeError::GameData { player })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The reason that a handshake failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeError {
+    /// The player has a version of the game that isn't compatible with ours.
+    Version {
+        player: usize,
+        local: String,
+        remote: String,
+    },
+    /// The player has the same version of the game, but their game data has been modified.
+    GameData { player: usize },
+    /// The player sent a message that couldn't be read instead of their handshake, which means
+    /// that they have a much older or newer version of the game.
+    Invalid { player: usize },
+    /// Not every player sent their handshake within the [`HANDSHAKE_TIMEOUT`].
+    Timeout,
+}
+
+impl HandshakeError {
+    /// Get the localization query for the error message.
+    pub fn localization_query(&self) -> String {
+        match self {
+            HandshakeError::Version {
+                player,
+                local,
+                remote,
+            } => format!(
+                "handshake-version-mismatch?player={}&local={local}&remote={remote}",
+                player + 1
+            ),
+            HandshakeError::GameData { player } => {
+                format!("handshake-game-data-mismatch?player={}", player + 1)
+            }
+            HandshakeError::Invalid { player } => {
+                format!("handshake-invalid?player={}", player + 1)
+            }
+            HandshakeError::Timeout => "handshake-timeout".into(),
+        }
+    }
+}
+
+/// The progress of the handshake with the other players.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeStatus {
+    Waiting,
+    Complete,
+    Failed(HandshakeError),
+}
+
+/// Keeps track of the handshakes received from the other players.
+pub(super) struct HandshakeProgress {
+    local: Handshake,
+    started: Instant,
+    received: [bool; MAX_PLAYERS],
+    error: Option<HandshakeError>,
+}
+
+impl HandshakeProgress {
+    pub fn new(local: Handshake) -> Self {
+        Self {
+            local,
+            started: Instant::now(),
+            received: default(),
+            error: None,
+        }
+    }
+
+    /// Handle a reliable message from a player, returning the message if it is meant for the rest
+    /// of the game.
+    pub fn receive(
+        &mut self,
+        player: usize,
+        message: Result<ReliableMessage, MessageError>,
+    ) -> Option<ReliableMessage> {
+        match message {
+            Ok(ReliableMessage::Handshake(handshake)) => {
+                if let Err(e) = self.local.check(player, &handshake) {
+                    warn!(%player, ?handshake, "Player's version of the game isn't compatible");
+                    self.error.get_or_insert(e);
+                }
+                self.received[player] = true;
+                None
+            }
+            Ok(message) => Some(message),
+            // Messages we can't read are an error until the handshake tells us why
+            Err(e) if !self.received[player] => {
+                warn!(%player, "Received invalid message before handshake: {e}");
+                self.error.get_or_insert(HandshakeError::Invalid { player });
+                None
+            }
+            Err(e) => {
+                warn!(%player, "Ignoring network message that was not understood: {e}");
+                None
+            }
+        }
+    }
+
+    /// Get the status of the handshake with the given remote players.
+    pub fn status(&self, remote_players: impl IntoIterator<Item = usize>) -> HandshakeStatus {
+        if let Some(error) = &self.error {
+            return HandshakeStatus::Failed(error.clone());
+        }
+        if remote_players
+            .into_iter()
+            .all(|player| self.received[player])
+        {
+            HandshakeStatus::Complete
+        } else if self.started.elapsed() > HANDSHAKE_TIMEOUT {
+            HandshakeStatus::Failed(HandshakeError::Timeout)
+        } else {
+            HandshakeStatus::Waiting
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_data_hash_ignores_formatting() {
+        let yaml = "# Fish physics\nspeed: 2.5\njump:\n  height: 10\n";
+        let crlf = yaml.replace('\n', "\r\n");
+        let reformatted = "speed:   2.5\njump:\n    height: 10 # pixels\n";
+        assert_eq!(normalize_yaml(yaml), normalize_yaml(&crlf));
+        assert_eq!(normalize_yaml(yaml), normalize_yaml(reformatted));
+        assert_ne!(
+            normalize_yaml(yaml),
+            normalize_yaml("speed: 3\njump:\n  height: 10\n")
+        );
+    }
+
+    fn handshake() -> Handshake {
+        Handshake {
+            protocol_version: NETWORK_PROTOCOL_VERSION,
+            game_version: "1.0.0".into(),
+            core_meta_hash: 42,
+        }
+    }
+
+    #[test]
+    fn compatible_handshakes_complete() {
+        let mut progress = HandshakeProgress::new(handshake());
+        assert_eq!(progress.status([1, 2]), HandshakeStatus::Waiting);
+
+        for player in [1, 2] {
+            let message = ReliableMessage::Handshake(handshake()).encode();
+            assert!(progress
+                .receive(player, ReliableMessage::decode(&message))
+                .is_none());
+        }
+        assert_eq!(progress.status([1, 2]), HandshakeStatus::Complete);
+    }
+
+    #[test]
+    fn mismatched_handshakes_fail() {
+        let mut progress = HandshakeProgress::new(handshake());
+        let remote = Handshake {
+            protocol_version: NETWORK_PROTOCOL_VERSION + 1,
+            game_version: "2.0.0".into(),
+            ..handshake()
+        };
+        progress.receive(1, Ok(ReliableMessage::Handshake(remote)));
+        assert_eq!(
+            progress.status([1]),
+            HandshakeStatus::Failed(HandshakeError::Version {
+                player: 1,
+                local: "1.0.0".into(),
+                remote: "2.0.0".into(),
+            })
+        );
+
+        let mut progress = HandshakeProgress::new(handshake());
+        let remote = Handshake {
+            core_meta_hash: 7,
+            ..handshake()
+        };
+        progress.receive(1, Ok(ReliableMessage::Handshake(remote)));
+        assert_eq!(
+            progress.status([1]),
+            HandshakeStatus::Failed(HandshakeError::GameData { player: 1 })
+        );
+    }
+
+    #[test]
+    fn garbage_during_handshake_is_rejected() {
+        for garbage in [&[][..], &[0xff; 16], b"hello world", &[0, 1, 2, 3]] {
+            let mut progress = HandshakeProgress::new(handshake());
+            progress.receive(2, ReliableMessage::decode(garbage));
+            assert_eq!(
+                progress.status([2]),
+                HandshakeStatus::Failed(HandshakeError::Invalid { player: 2 }),
+                "{garbage:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn garbage_after_handshake_is_ignored() {
+        let mut progress = HandshakeProgress::new(handshake());
+        progress.receive(1, Ok(ReliableMessage::Handshake(handshake())));
+        assert!(progress
+            .receive(1, ReliableMessage::decode(b"hello world"))
+            .is_none());
+        assert_eq!(progress.status([1]), HandshakeStatus::Complete);
+    }
+}
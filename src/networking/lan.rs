@@ -6,8 +6,6 @@ use futures_lite::{future, FutureExt};
 
 use super::*;
 
-pub const NETWORK_FRAME_RATE_FACTOR: f32 = 0.9;
-
 /// Channel used to do matchmaking over LAN.
 ///
 /// Spawns a task to handle the actual matchmaking.
@@ -26,6 +24,8 @@ async fn lan_matchmaker(
     #[derive(Serialize, Deserialize)]
     enum MatchmakerNetMsg {
         MatchReady {
+            /// The [`NETWORK_PROTOCOL_VERSION`] of the host.
+            protocol_version: u32,
             /// The peers they have for the match, with the index in the array being the player index of the peer.
             peers: [Option<SocketAddrV4>; MAX_PLAYERS],
             /// The player index of the player getting the message.
@@ -117,7 +117,8 @@ async fn lan_matchmaker(
 
                             let mut uni = conn.open_uni().await.unwrap();
                             uni.write_all(
-                                &postcard::to_vec::<_, 20>(&MatchmakerNetMsg::MatchReady {
+                                &postcard::to_vec::<_, 32>(&MatchmakerNetMsg::MatchReady {
+                                    protocol_version: NETWORK_PROTOCOL_VERSION,
                                     player_idx: i + 1,
                                     peers,
                                     player_count,
@@ -168,22 +169,45 @@ async fn lan_matchmaker(
 
             // Join a hosted match
             LanMatchmakerRequest::JoinServer { ip, port } => {
-                let conn = NETWORK_ENDPOINT
-                    .connect((ip, port).into(), "jumpy-host")
-                    .unwrap()
-                    .await
-                    .expect("Could not connect to server");
+                let conn = match NETWORK_ENDPOINT.connect((ip, port).into(), "jumpy-host") {
+                    Ok(connecting) => connecting.await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let conn = match conn {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!(%ip, %port, "Could not connect to LAN game: {e}");
+                        matchmaker_channel
+                            .try_send(LanMatchmakerResponse::JoinFailed)
+                            .ok();
+                        continue;
+                    }
+                };
 
                 // Wait for match to start
                 let mut uni = conn.accept_uni().await.unwrap();
-                let bytes = uni.read_to_end(20).await.unwrap();
+                let bytes = uni.read_to_end(32).await.unwrap();
                 let message: MatchmakerNetMsg = postcard::from_bytes(&bytes).unwrap();
 
                 match message {
+                    MatchmakerNetMsg::MatchReady {
+                        protocol_version, ..
+                    } if protocol_version != NETWORK_PROTOCOL_VERSION => {
+                        error!(
+                            %protocol_version,
+                            expected=%NETWORK_PROTOCOL_VERSION,
+                            "Cannot join LAN game hosted with an incompatible version of the game"
+                        );
+                        conn.close(0u32.into(), b"Incompatible protocol version");
+                        matchmaker_channel
+                            .try_send(LanMatchmakerResponse::JoinFailed)
+                            .ok();
+                    }
                     MatchmakerNetMsg::MatchReady {
                         peers: peer_addrs,
                         player_idx,
                         player_count,
+                        ..
                     } => {
                         info!(%player_count, %player_idx, ?peer_addrs, "Matchmaking finished");
                         let mut peer_connections = std::array::from_fn(|_| None);
@@ -265,6 +289,8 @@ pub enum LanMatchmakerRequest {
 pub enum LanMatchmakerResponse {
     ServerStarted,
     PlayerCount(usize),
+    /// The game we tried to join couldn't be reached, or was hosted with an incompatible version.
+    JoinFailed,
     GameStarting {
         lan_socket: LanSocket,
         player_idx: usize,
@@ -275,8 +301,13 @@ pub enum LanMatchmakerResponse {
 #[derive(Debug, Clone)]
 pub struct LanSocket {
     pub connections: [Option<quinn::Connection>; MAX_PLAYERS],
-    pub ggrs_receiver: async_channel::Receiver<(usize, ggrs::Message)>,
+    /// The GGRS messages received from each player, with the number of the GGRS session that they
+    /// belong to.
+    pub ggrs_receiver: async_channel::Receiver<(usize, u32, ggrs::Message)>,
+    /// The number of the GGRS session that this socket sends and receives messages for.
+    pub ggrs_session: u32,
     pub reliable_receiver: async_channel::Receiver<(usize, Vec<u8>)>,
+    pub unreliable_receiver: async_channel::Receiver<(usize, Vec<u8>)>,
     pub player_idx: usize,
     pub player_count: usize,
 }
@@ -285,6 +316,8 @@ impl LanSocket {
     pub fn new(player_idx: usize, connections: [Option<quinn::Connection>; MAX_PLAYERS]) -> Self {
         let (ggrs_sender, ggrs_receiver) = async_channel::unbounded();
         let (reliable_sender, reliable_receiver) = async_channel::unbounded();
+        let (unreliable_sender, unreliable_receiver) =
+            async_channel::bounded(UNRELIABLE_MESSAGE_BUFFER);
 
         let pool = bevy::tasks::IoTaskPool::get();
 
@@ -293,6 +326,7 @@ impl LanSocket {
         for i in 0..MAX_PLAYERS {
             if let Some(conn) = connections[i].clone() {
                 let ggrs_sender = ggrs_sender.clone();
+                let unreliable_sender = unreliable_sender.clone();
 
                 // Unreliable message receiver
                 let conn_ = conn.clone();
@@ -318,7 +352,7 @@ impl LanSocket {
                             }
                             either::Either::Right(datagram_result) => match datagram_result {
                                 Ok(data) => {
-                                    let message: ggrs::Message = postcard::from_bytes(&data)
+                                    let datagram: Datagram = postcard::from_bytes(&data)
                                         .expect("Could not deserialize net message");
 
                                     // Debugging code to introduce artificial latency
@@ -332,7 +366,17 @@ impl LanSocket {
                                         )
                                         .await;
                                     }
-                                    if ggrs_sender.send((i, message)).await.is_err() {
+                                    let receiver_closed = match datagram {
+                                        Datagram::Ggrs { session, message } => {
+                                            ggrs_sender.send((i, session, message)).await.is_err()
+                                        }
+                                        // Drop unreliable messages if nobody is receiving them
+                                        Datagram::Unreliable(message) => matches!(
+                                            unreliable_sender.try_send((i, message)),
+                                            Err(async_channel::TrySendError::Closed(_))
+                                        ),
+                                    };
+                                    if receiver_closed {
                                         break;
                                     }
                                 }
@@ -401,7 +445,9 @@ impl LanSocket {
             player_count: connections.iter().flatten().count() + 1,
             connections,
             ggrs_receiver,
+            ggrs_session: 0,
             reliable_receiver,
+            unreliable_receiver,
         }
     }
 }
@@ -411,15 +457,22 @@ impl ggrs::NonBlockingSocket<usize> for LanSocket {
         let conn = self.connections[*addr].as_ref().unwrap();
 
         // TODO: determine a reasonable size for this buffer.
-        let msg_bytes = postcard::to_allocvec(msg).unwrap();
+        let msg_bytes = postcard::to_allocvec(&Datagram::Ggrs {
+            session: self.ggrs_session,
+            message: msg.clone(),
+        })
+        .unwrap();
         conn.send_datagram(Bytes::copy_from_slice(&msg_bytes[..]))
             .ok();
     }
 
     fn receive_all_messages(&mut self) -> Vec<(usize, ggrs::Message)> {
         let mut messages = Vec::new();
-        while let Ok(message) = self.ggrs_receiver.try_recv() {
-            messages.push(message);
+        while let Ok((player, session, message)) = self.ggrs_receiver.try_recv() {
+            // Drop the messages left over from earlier sessions
+            if session == self.ggrs_session {
+                messages.push((player, message));
+            }
         }
         messages
     }
@@ -467,8 +520,36 @@ impl NetworkSocket for LanSocket {
         messages
     }
 
-    fn ggrs_socket(&self) -> BoxedNonBlockingSocket {
-        BoxedNonBlockingSocket(Box::new(self.clone()))
+    fn send_unreliable(&self, target: SocketTarget, message: &[u8]) {
+        let datagram = postcard::to_allocvec(&Datagram::Unreliable(message.to_vec())).unwrap();
+        let datagram = Bytes::from(datagram);
+
+        match target {
+            SocketTarget::Player(i) => {
+                let conn = self.connections[i].as_ref().unwrap();
+                conn.send_datagram(datagram).ok();
+            }
+            SocketTarget::All => {
+                for conn in self.connections.iter().flatten() {
+                    conn.send_datagram(datagram.clone()).ok();
+                }
+            }
+        }
+    }
+
+    fn recv_unreliable(&self) -> Vec<(usize, Vec<u8>)> {
+        let mut messages = Vec::new();
+        while let Ok(message) = self.unreliable_receiver.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn ggrs_socket(&self, session: u32) -> BoxedNonBlockingSocket {
+        BoxedNonBlockingSocket(Box::new(Self {
+            ggrs_session: session,
+            ..self.clone()
+        }))
     }
 
     fn close(&self) {
@@ -488,4 +569,19 @@ impl NetworkSocket for LanSocket {
     fn player_is_local(&self) -> [bool; MAX_PLAYERS] {
         std::array::from_fn(|i| self.connections[i].is_none() && i < self.player_count)
     }
+
+    fn player_is_connected(&self, player: usize) -> bool {
+        self.connections[player]
+            .as_ref()
+            .map(|conn| conn.close_reason().is_none())
+            .unwrap_or(true)
+    }
+
+    fn traffic(&self) -> NetworkTraffic {
+        self.connections
+            .iter()
+            .flatten()
+            .map(NetworkTraffic::of_connection)
+            .fold(default(), |total, traffic| total + traffic)
+    }
 }
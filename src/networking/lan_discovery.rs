@@ -0,0 +1,324 @@
+//! Discovery of LAN games, using a UDP beacon that hosts broadcast on the local network.
+//!
+//! The beacon starts with a [`BeaconHeader`] that every version of the game can read, so that
+//! games hosted with an incompatible version still show up, with the version they need, instead of
+//! silently missing from the list.
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    sync::{Mutex, Weak},
+    time::Duration,
+};
+
+use bevy::utils::Instant;
+
+use super::*;
+
+/// The UDP port that beacons are broadcast to.
+///
+/// This is just outside of the range that the [`NETWORK_ENDPOINT`] picks its port from.
+pub const LAN_BEACON_PORT: u16 = 11001;
+
+/// How often the host broadcasts its beacon.
+pub const BEACON_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a host is listed after its last beacon was received.
+pub const BEACON_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// The largest beacon that will be received. Longer beacons are dropped.
+const MAX_BEACON_SIZE: usize = 512;
+
+/// The bytes that every beacon starts with, to ignore other traffic on the port.
+const BEACON_MAGIC: [u8; 4] = *b"JMPY";
+
+/// The version of the game, shown to players that can't join a game because of a version mismatch.
+const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The start of the beacon, that must stay the same across protocol versions.
+#[derive(Serialize, Deserialize)]
+struct BeaconHeader {
+    magic: [u8; 4],
+    protocol_version: u32,
+    game_version: String,
+    host_name: String,
+}
+
+/// Information about a hosted LAN game, that is broadcast in its beacon.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LanHostInfo {
+    #[serde(skip)]
+    pub host_name: String,
+    /// The port of the host's [`NETWORK_ENDPOINT`].
+    pub port: u16,
+    /// The number of players in the game, including the host.
+    pub player_count: usize,
+    pub max_players: usize,
+    /// The name of the map the host selected, if they have selected one yet.
+    pub map: Option<String>,
+}
+
+impl LanHostInfo {
+    /// Encode the beacon that is broadcast for this game.
+    pub fn to_beacon(&self) -> Vec<u8> {
+        let mut bytes = postcard::to_allocvec(&BeaconHeader {
+            magic: BEACON_MAGIC,
+            protocol_version: NETWORK_PROTOCOL_VERSION,
+            game_version: GAME_VERSION.into(),
+            host_name: self.host_name.clone(),
+        })
+        .unwrap();
+        bytes.extend(postcard::to_allocvec(self).unwrap());
+        bytes
+    }
+}
+
+/// A LAN game that was found by a [`LanBrowser`].
+#[derive(Clone, Debug)]
+pub struct DiscoveredLanGame {
+    /// The address that the beacon was sent from.
+    pub addr: SocketAddr,
+    pub protocol_version: u32,
+    pub game_version: String,
+    pub host_name: String,
+    /// The rest of the game's info, which is only known if it was hosted with a compatible version
+    /// of the game.
+    pub info: Option<LanHostInfo>,
+    pub last_seen: Instant,
+}
+
+impl DiscoveredLanGame {
+    /// Decode a beacon received from `addr`, returning `None` if it isn't a valid beacon.
+    pub fn from_beacon(addr: SocketAddr, bytes: &[u8], now: Instant) -> Option<Self> {
+        let (header, rest) = postcard::take_from_bytes::<BeaconHeader>(bytes).ok()?;
+        if header.magic != BEACON_MAGIC {
+            return None;
+        }
+        let info = if header.protocol_version == NETWORK_PROTOCOL_VERSION {
+            let mut info = postcard::from_bytes::<LanHostInfo>(rest).ok()?;
+            info.host_name = header.host_name.clone();
+            Some(info)
+        } else {
+            None
+        };
+
+        Some(Self {
+            addr,
+            protocol_version: header.protocol_version,
+            game_version: header.game_version,
+            host_name: header.host_name,
+            info,
+            last_seen: now,
+        })
+    }
+
+    /// Whether the game was hosted with a version of the game that we can play with.
+    pub fn is_compatible(&self) -> bool {
+        self.protocol_version == NETWORK_PROTOCOL_VERSION && self.info.is_some()
+    }
+
+    /// Get the address to join the game at, if it can be joined.
+    pub fn join_addr(&self) -> Option<SocketAddrV4> {
+        let info = self.info.as_ref().filter(|_| self.is_compatible())?;
+        let SocketAddr::V4(addr) = self.addr else {
+            return None;
+        };
+        (info.player_count < info.max_players).then(|| SocketAddrV4::new(*addr.ip(), info.port))
+    }
+}
+
+/// Resource that broadcasts the beacon of the LAN game being hosted, for as long as it exists.
+#[derive(Resource)]
+pub struct LanBeacon {
+    info: LanHostInfo,
+    beacon: Arc<Mutex<Vec<u8>>>,
+}
+
+impl LanBeacon {
+    /// Start broadcasting the beacon for a game.
+    pub fn start(info: LanHostInfo) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+
+        let beacon = Arc::new(Mutex::new(info.to_beacon()));
+        let weak_beacon = Arc::downgrade(&beacon);
+        std::thread::spawn(move || broadcast_beacon(socket, weak_beacon));
+        info!(?info, "Started LAN beacon");
+
+        Ok(Self { info, beacon })
+    }
+
+    /// Update the info that is broadcast, starting with the next beacon.
+    pub fn update(&mut self, f: impl FnOnce(&mut LanHostInfo)) {
+        f(&mut self.info);
+        *self.beacon.lock().unwrap() = self.info.to_beacon();
+    }
+}
+
+/// Broadcast the beacon every [`BEACON_INTERVAL`], until the [`LanBeacon`] is dropped.
+fn broadcast_beacon(socket: UdpSocket, beacon: Weak<Mutex<Vec<u8>>>) {
+    let mut warned = false;
+    while let Some(beacon) = beacon.upgrade() {
+        let bytes = beacon.lock().unwrap().clone();
+        drop(beacon);
+
+        if let Err(e) = socket.send_to(&bytes, (Ipv4Addr::BROADCAST, LAN_BEACON_PORT)) {
+            // Broadcasting is often blocked, so only warn once instead of every second
+            if !warned {
+                warn!("Could not broadcast LAN beacon, players will have to join by address: {e}");
+                warned = true;
+            }
+        }
+        std::thread::sleep(BEACON_INTERVAL);
+    }
+    info!("Stopped LAN beacon");
+}
+
+/// Lists the LAN games that are being hosted, from the beacons received on the [`LAN_BEACON_PORT`].
+pub struct LanBrowser {
+    receiver: async_channel::Receiver<DiscoveredLanGame>,
+    games: Vec<DiscoveredLanGame>,
+}
+
+impl LanBrowser {
+    /// Start listening for beacons.
+    ///
+    /// This fails if the beacon port is already in use, such as by another copy of the game
+    /// running on the same computer.
+    pub fn start() -> std::io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, LAN_BEACON_PORT))?;
+        // Time out now and then to notice when the browser has been dropped
+        socket.set_read_timeout(Some(BEACON_INTERVAL))?;
+
+        let (sender, receiver) = async_channel::unbounded();
+        std::thread::spawn(move || {
+            let mut buf = [0; MAX_BEACON_SIZE];
+            while !sender.is_closed() {
+                let Ok((len, addr)) = socket.recv_from(&mut buf) else {
+                    continue;
+                };
+                if let Some(game) =
+                    DiscoveredLanGame::from_beacon(addr, &buf[..len], Instant::now())
+                {
+                    sender.try_send(game).ok();
+                }
+            }
+        });
+
+        Ok(Self {
+            receiver,
+            games: default(),
+        })
+    }
+
+    /// Add the games from the beacons received since the last update, and remove the games that
+    /// haven't sent a beacon for the [`BEACON_TIMEOUT`].
+    pub fn update(&mut self) {
+        while let Ok(game) = self.receiver.try_recv() {
+            self.add_game(game);
+        }
+        self.remove_stale_games(Instant::now());
+    }
+
+    /// The LAN games that are currently being hosted, ordered by host name.
+    pub fn games(&self) -> &[DiscoveredLanGame] {
+        &self.games
+    }
+
+    fn add_game(&mut self, game: DiscoveredLanGame) {
+        match self.games.iter_mut().find(|x| x.addr == game.addr) {
+            Some(existing) => *existing = game,
+            None => {
+                self.games.push(game);
+                self.games.sort_by(|a, b| a.host_name.cmp(&b.host_name));
+            }
+        }
+    }
+
+    fn remove_stale_games(&mut self, now: Instant) {
+        self.games
+            .retain(|game| now.duration_since(game.last_seen) < BEACON_TIMEOUT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host_info() -> LanHostInfo {
+        LanHostInfo {
+            host_name: "Fish Fight".into(),
+            port: 10_500,
+            player_count: 1,
+            max_players: 2,
+            map: Some("Level 1".into()),
+        }
+    }
+
+    fn addr() -> SocketAddr {
+        (Ipv4Addr::new(192, 168, 1, 7), 50_000).into()
+    }
+
+    #[test]
+    fn beacon_round_trips() {
+        let info = host_info();
+        let game =
+            DiscoveredLanGame::from_beacon(addr(), &info.to_beacon(), Instant::now()).unwrap();
+        assert!(game.is_compatible());
+        assert_eq!(game.host_name, info.host_name);
+        assert_eq!(game.info.as_ref(), Some(&info));
+        assert_eq!(
+            game.join_addr(),
+            Some(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 7), 10_500))
+        );
+    }
+
+    #[test]
+    fn incompatible_and_full_games_cannot_be_joined() {
+        let mut bytes = postcard::to_allocvec(&BeaconHeader {
+            magic: BEACON_MAGIC,
+            protocol_version: NETWORK_PROTOCOL_VERSION + 1,
+            game_version: "99.0.0".into(),
+            host_name: "Future Fish".into(),
+        })
+        .unwrap();
+        bytes.extend([1, 2, 3]);
+        let game = DiscoveredLanGame::from_beacon(addr(), &bytes, Instant::now()).unwrap();
+        assert!(!game.is_compatible());
+        assert_eq!(game.game_version, "99.0.0");
+        assert_eq!(game.join_addr(), None);
+
+        let info = LanHostInfo {
+            player_count: 2,
+            ..host_info()
+        };
+        let game =
+            DiscoveredLanGame::from_beacon(addr(), &info.to_beacon(), Instant::now()).unwrap();
+        assert!(game.is_compatible());
+        assert_eq!(game.join_addr(), None);
+    }
+
+    #[test]
+    fn other_traffic_is_ignored() {
+        assert!(DiscoveredLanGame::from_beacon(addr(), b"", Instant::now()).is_none());
+        assert!(DiscoveredLanGame::from_beacon(addr(), b"hello world", Instant::now()).is_none());
+    }
+
+    #[test]
+    fn stale_games_expire() {
+        let start = Instant::now();
+        let (_sender, receiver) = async_channel::unbounded();
+        let mut browser = LanBrowser {
+            receiver,
+            games: default(),
+        };
+        let beacon = host_info().to_beacon();
+        browser.add_game(DiscoveredLanGame::from_beacon(addr(), &beacon, start).unwrap());
+        browser.add_game(DiscoveredLanGame::from_beacon(addr(), &beacon, start).unwrap());
+        assert_eq!(browser.games().len(), 1);
+
+        browser.remove_stale_games(start + BEACON_TIMEOUT / 2);
+        assert_eq!(browser.games().len(), 1);
+        browser.remove_stale_games(start + BEACON_TIMEOUT);
+        assert!(browser.games().is_empty());
+    }
+}
@@ -8,7 +8,10 @@ use quinn::Connection;
 
 use crate::prelude::*;
 
-use super::{NetworkSocket, NETWORK_ENDPOINT};
+use super::{
+    proto::{Datagram, NETWORK_PROTOCOL_VERSION},
+    NetworkSocket, NETWORK_ENDPOINT,
+};
 
 pub static ONLINE_MATCHMAKER: Lazy<OnlineMatchmaker> = Lazy::new(|| {
     let (client, server) = bi_channel();
@@ -62,7 +65,9 @@ async fn online_matchmaker(
 
                 let message = MatchmakerRequest::RequestMatch(MatchInfo {
                     client_count: player_count.try_into().unwrap(),
-                    match_data: b"jumpy_default_game".to_vec(),
+                    // Only match with players that use the same network protocol
+                    match_data: format!("jumpy_default_game_v{NETWORK_PROTOCOL_VERSION}")
+                        .into_bytes(),
                 });
                 info!(request=?message, "Sending match request");
                 let message = postcard::to_allocvec(&message).unwrap();
@@ -177,8 +182,13 @@ fn resolve_addr_blocking(addr: &str) -> anyhow::Result<SocketAddr> {
 #[derive(Debug, Clone)]
 pub struct OnlineSocket {
     pub conn: Connection,
-    pub ggrs_receiver: async_channel::Receiver<(usize, ggrs::Message)>,
+    /// The GGRS messages received from each player, with the number of the GGRS session that they
+    /// belong to.
+    pub ggrs_receiver: async_channel::Receiver<(usize, u32, ggrs::Message)>,
+    /// The number of the GGRS session that this socket sends and receives messages for.
+    pub ggrs_session: u32,
     pub reliable_receiver: async_channel::Receiver<(usize, Vec<u8>)>,
+    pub unreliable_receiver: async_channel::Receiver<(usize, Vec<u8>)>,
     pub player_idx: usize,
     pub player_count: usize,
 }
@@ -187,6 +197,8 @@ impl OnlineSocket {
     pub fn new(player_idx: usize, player_count: usize, conn: Connection) -> Self {
         let (ggrs_sender, ggrs_receiver) = async_channel::unbounded();
         let (reliable_sender, reliable_receiver) = async_channel::unbounded();
+        let (unreliable_sender, unreliable_receiver) =
+            async_channel::bounded(networking::UNRELIABLE_MESSAGE_BUFFER);
 
         let task_pool = IoTaskPool::get();
 
@@ -210,10 +222,20 @@ impl OnlineSocket {
                                 let message: bones_matchmaker_proto::RecvProxyMessage =
                                     postcard::from_bytes(&data)
                                         .expect("Could not deserialize net message");
-                                let player = message.from_client;
-                                let message = postcard::from_bytes(&message.message).unwrap();
-
-                                if ggrs_sender.send((player as _, message)).await.is_err() {
+                                let player = message.from_client as usize;
+                                let datagram = postcard::from_bytes(&message.message).unwrap();
+
+                                let receiver_closed = match datagram {
+                                    Datagram::Ggrs { session, message } => {
+                                        ggrs_sender.send((player, session, message)).await.is_err()
+                                    }
+                                    // Drop unreliable messages if nobody is receiving them
+                                    Datagram::Unreliable(message) => matches!(
+                                        unreliable_sender.try_send((player, message)),
+                                        Err(async_channel::TrySendError::Closed(_))
+                                    ),
+                                };
+                                if receiver_closed {
                                     break;
                                 }
                             }
@@ -268,7 +290,9 @@ impl OnlineSocket {
         Self {
             conn,
             ggrs_receiver,
+            ggrs_session: 0,
             reliable_receiver,
+            unreliable_receiver,
             player_idx,
             player_count,
         }
@@ -276,8 +300,11 @@ impl OnlineSocket {
 }
 
 impl NetworkSocket for OnlineSocket {
-    fn ggrs_socket(&self) -> networking::BoxedNonBlockingSocket {
-        networking::BoxedNonBlockingSocket(Box::new(self.clone()))
+    fn ggrs_socket(&self, session: u32) -> networking::BoxedNonBlockingSocket {
+        networking::BoxedNonBlockingSocket(Box::new(Self {
+            ggrs_session: session,
+            ..self.clone()
+        }))
     }
 
     fn send_reliable(&self, target: networking::SocketTarget, message: &[u8]) {
@@ -314,6 +341,31 @@ impl NetworkSocket for OnlineSocket {
         messages
     }
 
+    fn send_unreliable(&self, target: networking::SocketTarget, message: &[u8]) {
+        let target_client = match target {
+            networking::SocketTarget::Player(player) => {
+                bones_matchmaker_proto::TargetClient::One(player as _)
+            }
+            networking::SocketTarget::All => bones_matchmaker_proto::TargetClient::All,
+        };
+        let message = bones_matchmaker_proto::SendProxyMessage {
+            target_client,
+            message: postcard::to_allocvec(&Datagram::Unreliable(message.to_vec())).unwrap(),
+        };
+        let msg_bytes = postcard::to_allocvec(&message).unwrap();
+        self.conn
+            .send_datagram(Bytes::copy_from_slice(&msg_bytes[..]))
+            .ok();
+    }
+
+    fn recv_unreliable(&self) -> Vec<(usize, Vec<u8>)> {
+        let mut messages = Vec::new();
+        while let Ok(message) = self.unreliable_receiver.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
     fn close(&self) {
         self.conn.close(0u8.into(), &[]);
     }
@@ -329,13 +381,27 @@ impl NetworkSocket for OnlineSocket {
     fn player_count(&self) -> usize {
         self.player_count
     }
+
+    fn player_is_connected(&self, _player: usize) -> bool {
+        // All messages go through the matchmaking server, so we only know whether or not we are
+        // still connected to it.
+        self.conn.close_reason().is_none()
+    }
+
+    fn traffic(&self) -> networking::NetworkTraffic {
+        networking::NetworkTraffic::of_connection(&self.conn)
+    }
 }
 
 impl ggrs::NonBlockingSocket<usize> for OnlineSocket {
     fn send_to(&mut self, msg: &ggrs::Message, addr: &usize) {
         let message = bones_matchmaker_proto::SendProxyMessage {
             target_client: bones_matchmaker_proto::TargetClient::One(*addr as u8),
-            message: postcard::to_allocvec(msg).unwrap(),
+            message: postcard::to_allocvec(&Datagram::Ggrs {
+                session: self.ggrs_session,
+                message: msg.clone(),
+            })
+            .unwrap(),
         };
         let msg_bytes = postcard::to_allocvec(&message).unwrap();
         self.conn
@@ -345,8 +411,11 @@ impl ggrs::NonBlockingSocket<usize> for OnlineSocket {
 
     fn receive_all_messages(&mut self) -> Vec<(usize, ggrs::Message)> {
         let mut messages = Vec::new();
-        while let Ok(message) = self.ggrs_receiver.try_recv() {
-            messages.push(message);
+        while let Ok((player, session, message)) = self.ggrs_receiver.try_recv() {
+            // Drop the messages left over from earlier sessions
+            if session == self.ggrs_session {
+                messages.push((player, message));
+            }
         }
         messages
     }
@@ -2,21 +2,42 @@
 
 use numquant::{IntRange, Quantized};
 
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    ui::{
+        editor::EditorTool,
+        main_menu::{map_select::MapSelectMessage, player_select::PlayerSelectMessage},
+    },
+};
+
+use super::Handshake;
+
+/// The version of the network protocol.
+///
+/// This must be bumped whenever the encoding of the messages sent between players changes, such as
+/// [`DensePlayerControl`], or the simulation changes in a way that desyncs older versions, like its
+/// frame rate, so that players with incompatible versions of the game are never put into the same
+/// match.
+pub const NETWORK_PROTOCOL_VERSION: u32 = 10;
 
 bitfield::bitfield! {
-    /// A player's controller inputs densely packed into a single u16.
+    /// A player's controller inputs densely packed into a single u32.
     ///
     /// This is used when sending player inputs across the network.
-    #[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Eq, Reflect)]
+    #[derive(
+        bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, PartialEq, Eq, Reflect, Serialize,
+        Deserialize,
+    )]
     #[repr(transparent)]
-    pub struct DensePlayerControl(u16);
+    pub struct DensePlayerControl(u32);
     impl Debug;
     pub jump_pressed, set_jump_pressed: 0;
     pub shoot_pressed, set_shoot_pressed: 1;
     pub grab_pressed, set_grab_pressed: 2;
     pub slide_pressed, set_slide_pressed: 3;
-    pub from into DenseMoveDirection, move_direction, set_move_direction: 15, 4;
+    pub emote_pressed, set_emote_pressed: 4;
+    pub u16, from into DenseMoveDirection, move_direction, set_move_direction: 16, 5;
+    pub dash_pressed, set_dash_pressed: 17;
 }
 
 impl Default for DensePlayerControl {
@@ -27,6 +48,10 @@ impl Default for DensePlayerControl {
     }
 }
 
+/// The network inputs of every player for one frame, with [`None`] for the players that were
+/// disconnected.
+pub type NetworkInputs = [Option<DensePlayerControl>; MAX_PLAYERS];
+
 /// A newtype around [`Vec2`] that implements [`From<u16>`] and [`Into<u16>`] as a way to compress
 /// user stick input for use in [`DensePlayerControl`].
 #[derive(Debug, Deref, DerefMut, Default)]
@@ -67,3 +92,212 @@ impl From<DenseMoveDirection> for u16 {
         x_bits | (y_bits << 6)
     }
 }
+
+/// The envelope of every reliable network message sent between players.
+///
+/// Messages are encoded with the [`NETWORK_PROTOCOL_VERSION`] of the sender in front of them, so
+/// that messages from players with another version of the game are rejected instead of being
+/// misread.
+#[derive(Serialize, Deserialize)]
+pub enum ReliableMessage {
+    /// The [`Handshake`] that is sent first, after connecting to the other players.
+    ///
+    /// This must stay the first variant, so that it can be read by every version of the game.
+    Handshake(Handshake),
+    PlayerSelect(PlayerSelectMessage),
+    MapSelect(MapSelectMessage),
+    Match(MatchMessage),
+}
+
+impl ReliableMessage {
+    /// Encode the message, prefixed with our [`NETWORK_PROTOCOL_VERSION`].
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = postcard::to_allocvec(&NETWORK_PROTOCOL_VERSION).unwrap();
+        bytes.extend(postcard::to_allocvec(self).unwrap());
+        bytes
+    }
+
+    /// Decode a message sent by another player.
+    ///
+    /// Only [`ReliableMessage::Handshake`]s are decoded from players with another protocol
+    /// version.
+    pub fn decode(bytes: &[u8]) -> Result<Self, MessageError> {
+        let (version, rest) = postcard::take_from_bytes::<u32>(bytes)?;
+        let message = postcard::from_bytes::<Self>(rest);
+        if version == NETWORK_PROTOCOL_VERSION {
+            Ok(message?)
+        } else {
+            match message {
+                Ok(message @ ReliableMessage::Handshake(_)) => Ok(message),
+                _ => Err(MessageError::ProtocolVersion(version)),
+            }
+        }
+    }
+}
+
+impl From<Handshake> for ReliableMessage {
+    fn from(message: Handshake) -> Self {
+        Self::Handshake(message)
+    }
+}
+
+impl From<PlayerSelectMessage> for ReliableMessage {
+    fn from(message: PlayerSelectMessage) -> Self {
+        Self::PlayerSelect(message)
+    }
+}
+
+impl From<MapSelectMessage> for ReliableMessage {
+    fn from(message: MapSelectMessage) -> Self {
+        Self::MapSelect(message)
+    }
+}
+
+impl From<MatchMessage> for ReliableMessage {
+    fn from(message: MatchMessage) -> Self {
+        Self::Match(message)
+    }
+}
+
+/// An error decoding a [`ReliableMessage`].
+#[derive(thiserror::Error, Debug)]
+pub enum MessageError {
+    #[error("malformed message: {0}")]
+    Malformed(#[from] postcard::Error),
+    #[error("message from network protocol version {0}")]
+    ProtocolVersion(u32),
+}
+
+/// A reliable network message sent between players during a match.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum MatchMessage {
+    /// The [`FrameChecksum`][jumpy_core::checksum::FrameChecksum] of the game state at the start
+    /// of a confirmed frame.
+    Checksum { frame: ggrs::Frame, checksum: u64 },
+    /// A chat message typed by the sending player.
+    Chat { message: String },
+    /// The sending player has loaded the match and is ready to start it.
+    Ready {
+        /// The input delay, in frames, that the player asks for.
+        input_delay: u8,
+    },
+    /// The input delay that the sending player asks for has changed.
+    InputDelay { input_delay: u8 },
+    /// Restart the GGRS session at the start of a network frame, which is how the input delay is
+    /// changed in the middle of a match.
+    Resync {
+        /// The network frame that the new session starts on.
+        frame: ggrs::Frame,
+        /// The input delay of the new session.
+        input_delay: u8,
+        /// Which players are in the new session.
+        players: [bool; MAX_PLAYERS],
+        /// The snapshot that the receiving player replays the confirmed inputs from to catch up to
+        /// `frame`, when they are rejoining the match. The inputs are sent in
+        /// [`MatchMessage::ResyncInputs`]. [`None`] for the players that are already in the session.
+        snapshot_frame: Option<ggrs::Frame>,
+    },
+    /// The sending player has reached the frame of a [`MatchMessage::Resync`], and is ready to
+    /// start the new session.
+    ResyncReady { frame: ggrs::Frame },
+    /// The sending player lost their connection to the session and asks to be let back in.
+    Rejoin {
+        /// The network frames of the snapshots that the player can catch up from.
+        snapshot_frames: Vec<ggrs::Frame>,
+    },
+    /// Confirmed inputs for a rejoining player to replay, starting at `frame`.
+    ResyncInputs {
+        frame: ggrs::Frame,
+        inputs: Vec<NetworkInputs>,
+    },
+}
+
+/// An unreliable network message, sent as a QUIC datagram.
+#[derive(Serialize, Deserialize)]
+pub enum Datagram {
+    /// A message used by [`ggrs`] to synchronize the game.
+    Ggrs {
+        /// The number of the GGRS session that the message belongs to, which goes up every time
+        /// the session is restarted, so that the messages of old sessions can be dropped.
+        session: u32,
+        message: ggrs::Message,
+    },
+    /// An encoded [`UnreliableMessage`].
+    Unreliable(Vec<u8>),
+}
+
+/// An unreliable network message sent between players.
+///
+/// These may be lost or arrive out of order, so they should only be used for state that is sent
+/// again regularly.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum UnreliableMessage {
+    /// What the sending player is doing in the map editor.
+    EditorPresence(EditorPresence),
+}
+
+/// What a player is doing in the map editor, shown to the other players editing the map with them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditorPresence {
+    /// The tile under the player's cursor, if their cursor is over the map.
+    pub cursor_tile: Option<UVec2>,
+    pub tool: EditorTool,
+    /// The index of the layer the player has selected.
+    pub layer: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reliable_messages_round_trip() {
+        let message = ReliableMessage::from(MatchMessage::Chat {
+            message: "Hello".into(),
+        });
+        let ReliableMessage::Match(MatchMessage::Chat { message }) =
+            ReliableMessage::decode(&message.encode()).unwrap()
+        else {
+            panic!("decoded the wrong message");
+        };
+        assert_eq!(message, "Hello");
+    }
+
+    #[test]
+    fn other_protocol_versions_only_decode_handshakes() {
+        let handshake = Handshake {
+            protocol_version: NETWORK_PROTOCOL_VERSION + 1,
+            game_version: "99.0.0".into(),
+            core_meta_hash: 0,
+        };
+        let mut bytes = postcard::to_allocvec(&(NETWORK_PROTOCOL_VERSION + 1)).unwrap();
+        bytes
+            .extend(postcard::to_allocvec(&ReliableMessage::Handshake(handshake.clone())).unwrap());
+        assert!(matches!(
+            ReliableMessage::decode(&bytes),
+            Ok(ReliableMessage::Handshake(x)) if x == handshake
+        ));
+
+        let mut bytes = postcard::to_allocvec(&(NETWORK_PROTOCOL_VERSION + 1)).unwrap();
+        bytes.extend(
+            postcard::to_allocvec(&ReliableMessage::Match(MatchMessage::Ready {
+                input_delay: 1,
+            }))
+            .unwrap(),
+        );
+        assert!(matches!(
+            ReliableMessage::decode(&bytes),
+            Err(MessageError::ProtocolVersion(v)) if v == NETWORK_PROTOCOL_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn garbage_is_malformed() {
+        for garbage in [&[][..], &[0xff; 16], &[NETWORK_PROTOCOL_VERSION as u8, 200]] {
+            assert!(matches!(
+                ReliableMessage::decode(garbage),
+                Err(MessageError::Malformed(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,605 @@
+//! Restarting the GGRS session in the middle of a match.
+//!
+//! GGRS only lets the input delay be set when a session is built, so to change it, every player
+//! restarts their session on the same network frame. The coordinator, which is the connected player
+//! with the lowest index, sends a [`MatchMessage::Resync`] with the frame and the new input delay.
+//! The frame is always one that every player keeps a snapshot of, so once a player has confirmed
+//! the inputs of all the frames before it, they go back to the snapshot, tell the others with a
+//! [`MatchMessage::ResyncReady`], and wait for everybody else to do the same before starting the
+//! new session from there.
+//!
+//! Any frames that were simulated past the resync frame are thrown away, so the game may jump back
+//! by a few frames, and the local inputs are dropped for the first frames of the new session, while
+//! its input delay fills up.
+//!
+//! The same restart lets a player who lost their connection rejoin the match, as long as they
+//! haven't been removed from it yet. A player who loses the connection to every other player stops
+//! and keeps asking to be let back in with a [`MatchMessage::Rejoin`], listing the snapshots they
+//! have from before they lost the connection. The coordinator picks one of them that it still has
+//! the inputs after, and starts a resync with the rejoining player in it. Once the coordinator has
+//! reached the resync frame, it sends the rejoining player the confirmed inputs from the snapshot up
+//! to the resync frame in [`MatchMessage::ResyncInputs`], which they replay to catch up before
+//! joining the new session. The player with the lowest index that is still in the match never tries
+//! to rejoin, and keeps playing instead, so that there is always somebody to rejoin.
+//!
+//! Rejoining only works while the connection between the players is still up, like after a network
+//! stall long enough for GGRS to disconnect the player. A player who loses their connection to the
+//! match entirely can't get back in.
+
+use std::collections::BTreeMap;
+
+use jumpy_core::input::PlayerInputs;
+
+use super::*;
+
+/// How many network frames apart the snapshots that the session can be restarted from are.
+pub const SNAPSHOT_INTERVAL: ggrs::Frame = 60;
+
+/// How many confirmed snapshots to keep.
+const SNAPSHOT_HISTORY: usize = 4;
+
+/// The shortest time between two changes of the input delay.
+const INPUT_DELAY_CHANGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long to wait for the other players to be ready for a resync before leaving them out of the
+/// new session.
+///
+/// This is long enough for a rejoining player to receive and replay the inputs that they missed.
+const RESYNC_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How often a player who lost their connection asks the others to let them rejoin.
+const REJOIN_REQUEST_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How many frames of inputs are sent in each [`MatchMessage::ResyncInputs`], which keeps the
+/// messages well under the size limit of reliable messages.
+const RESYNC_INPUTS_CHUNK: usize = 128;
+
+/// A restart of the GGRS session that the players have agreed on.
+#[derive(Clone, Debug)]
+pub struct Resync {
+    /// The network frame that the new session starts on.
+    pub frame: ggrs::Frame,
+    /// The input delay of the new session.
+    pub input_delay: u8,
+    /// Which players are in the new session.
+    pub players: [bool; MAX_PLAYERS],
+    /// The snapshot that each rejoining player catches up from. Only the coordinator and the
+    /// rejoining player know this.
+    pub snapshot_frames: [Option<ggrs::Frame>; MAX_PLAYERS],
+    /// The players in the new session that were disconnected from our old session when the resync
+    /// started.
+    pub rejoining: [bool; MAX_PLAYERS],
+    /// When we found out about the resync.
+    pub started: Instant,
+}
+
+/// An attempt to rejoin the match after losing the connection to every other player.
+#[derive(Clone, Debug)]
+pub struct Rejoin {
+    /// When we lost the connection.
+    pub started: Instant,
+    /// When we last asked the other players to let us back in.
+    pub last_request: Option<Instant>,
+    /// The network frames of the snapshots from before we lost the connection, which we can catch
+    /// up from.
+    pub snapshot_frames: Vec<ggrs::Frame>,
+    /// The confirmed inputs that were sent to us to replay, by network frame.
+    pub inputs: BTreeMap<ggrs::Frame, NetworkInputs>,
+}
+
+impl GgrsSessionRunner {
+    /// Get which players are in the current GGRS session and still connected.
+    fn connected_players(&self) -> [bool; MAX_PLAYERS] {
+        std::array::from_fn(|player| {
+            player < self.player_count
+                && (self.player_is_local[player] || !self.player_is_disconnected[player])
+        })
+    }
+
+    /// Whether we are the connected player with the lowest index, who decides when to resync.
+    fn is_coordinator(&self) -> bool {
+        self.connected_players().iter().position(|x| *x) == Some(self.local_player_idx())
+    }
+
+    /// Get the highest input delay that any of the given players asks for.
+    fn wanted_input_delay(&self, players: [bool; MAX_PLAYERS]) -> u8 {
+        (0..self.player_count)
+            .filter(|&player| players[player])
+            .map(|player| self.player_input_delay[player])
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Get the first snapshot frame after the current frame, which every player will keep a
+    /// snapshot of, even if they are already past it.
+    fn next_snapshot_frame(&self) -> ggrs::Frame {
+        (self.frame / SNAPSHOT_INTERVAL + 1) * SNAPSHOT_INTERVAL
+    }
+
+    /// Get the connection status that the match has for a player.
+    fn connection_status(&self, player: usize) -> ConnectionStatus {
+        self.core.world.resource::<PlayerInputs>().borrow().players[player].connection
+    }
+
+    /// Get the network frame that a player's inputs have been missing since, or [`None`] if we
+    /// have their inputs for the last frame.
+    fn disconnect_frame(&self, player: usize) -> Option<ggrs::Frame> {
+        let mut disconnect_frame = None;
+        for (&frame, inputs) in self.frame_inputs.iter().rev() {
+            if inputs[player].is_some() {
+                break;
+            }
+            disconnect_frame = Some(frame);
+        }
+        disconnect_frame
+    }
+
+    /// Whether the game is held on the current frame, because we have reached the frame of a
+    /// resync and are waiting for the other players to reach it too, or because we are waiting to
+    /// rejoin the match.
+    pub(super) fn is_waiting_for_resync(&self) -> bool {
+        self.rejoin.is_some()
+            || self.resync.as_ref().map_or(false, |resync| {
+                self.player_resync_frame[self.local_player_idx()] == Some(resync.frame)
+            })
+    }
+
+    /// Whether we should try to rejoin the other players once we have lost the connection to all of
+    /// them.
+    ///
+    /// The player with the lowest index that is still in the match keeps playing instead, so that
+    /// there is somebody to rejoin.
+    pub(super) fn should_rejoin(&self) -> bool {
+        (0..self.local_player_idx()).any(|player| {
+            !self.player_is_local[player]
+                && self.connection_status(player) != ConnectionStatus::Removed
+        })
+    }
+
+    /// Whether every remote player has been removed from the match, after being disconnected for
+    /// longer than the grace period.
+    pub(super) fn all_remote_players_removed(&self) -> bool {
+        (0..self.player_count)
+            .filter(|&player| !self.player_is_local[player])
+            .all(|player| self.connection_status(player) == ConnectionStatus::Removed)
+    }
+
+    /// Start a resync sent to us by the coordinator.
+    ///
+    /// `snapshot_frames` has the snapshot that each rejoining player catches up from, if we know
+    /// it.
+    pub(super) fn begin_resync(
+        &mut self,
+        frame: ggrs::Frame,
+        input_delay: u8,
+        players: [bool; MAX_PLAYERS],
+        snapshot_frames: [Option<ggrs::Frame>; MAX_PLAYERS],
+    ) {
+        if let Some(resync) = &self.resync {
+            debug!(%frame, current=%resync.frame, "Ignoring resync while another one is running");
+            return;
+        }
+        let local_player_idx = self.local_player_idx();
+        if frame <= self.frame_offset || !players[local_player_idx] {
+            debug!(%frame, "Ignoring resync that we aren't part of");
+            return;
+        }
+        // While we are rejoining, we can only catch up from one of our snapshots
+        let snapshot_frame = snapshot_frames[local_player_idx];
+        if self.rejoin.is_some() != snapshot_frame.is_some() {
+            debug!(%frame, "Ignoring resync that doesn't match whether we are rejoining");
+            return;
+        }
+        if let Some(snapshot_frame) = snapshot_frame {
+            if !self.snapshots.contains_key(&snapshot_frame) || snapshot_frame >= frame {
+                warn!(%frame, %snapshot_frame, "Can't rejoin from a snapshot that we don't have");
+                return;
+            }
+        }
+
+        info!(%frame, %input_delay, "Restarting the network session");
+        self.resync = Some(Resync {
+            frame,
+            input_delay: input_delay.min(MAX_INPUT_DELAY),
+            players,
+            snapshot_frames,
+            rejoining: std::array::from_fn(|player| {
+                players[player]
+                    && player < self.player_count
+                    && !self.player_is_local[player]
+                    && self.player_is_disconnected[player]
+            }),
+            started: Instant::now(),
+        });
+    }
+
+    /// Go back to the frame of the resync once all of the frames before it are confirmed, and
+    /// start the new session once every player in it has done the same.
+    pub(super) fn update_resync(&mut self, bevy_world: &mut World) {
+        let Some(resync) = self.resync.clone() else {
+            return;
+        };
+        if !bevy_world.contains_resource::<NetworkMatchSocket>() {
+            return;
+        }
+        let local_player_idx = self.local_player_idx();
+
+        if self.player_resync_frame[local_player_idx] != Some(resync.frame) {
+            if let Some(snapshot_frame) = resync.snapshot_frames[local_player_idx] {
+                if !self.replay_rejoin_inputs(snapshot_frame, resync.frame, bevy_world) {
+                    return;
+                }
+            } else {
+                // Keep playing until we have the confirmed inputs for every frame before the resync
+                if self.confirmed_frame < resync.frame - 1 {
+                    return;
+                }
+                let Some(snapshot) = self.snapshots.get(&resync.frame) else {
+                    error!(frame=%resync.frame, "No snapshot to resync from, keeping the old session");
+                    self.resync = None;
+                    return;
+                };
+
+                self.rollback_frames += (self.frame - resync.frame).max(0) as u32;
+                self.core.restore(&mut snapshot.clone());
+                self.frame = resync.frame;
+            }
+
+            self.confirmed_frame = resync.frame - 1;
+            self.last_checksum_sent = self.last_checksum_sent.min(resync.frame - 1);
+            self.checksums.retain(|frame, _| *frame <= resync.frame);
+            self.snapshots.retain(|frame, _| *frame <= resync.frame);
+            self.frame_inputs.retain(|frame, _| *frame < resync.frame);
+
+            self.player_resync_frame[local_player_idx] = Some(resync.frame);
+            let socket = bevy_world.resource::<NetworkMatchSocket>();
+            socket.send_message(
+                SocketTarget::All,
+                MatchMessage::ResyncReady {
+                    frame: resync.frame,
+                },
+            );
+            for (player, snapshot_frame) in resync.snapshot_frames.iter().enumerate() {
+                match snapshot_frame {
+                    Some(snapshot_frame) if player != local_player_idx => {
+                        self.send_rejoin_inputs(socket, player, *snapshot_frame, &resync)
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        // Players that drop out of the old session while we wait aren't waited for
+        let mut players = resync.players;
+        let waiting = (0..self.player_count)
+            .filter(|&player| {
+                players[player]
+                    && !self.player_is_local[player]
+                    && (!self.player_is_disconnected[player] || resync.rejoining[player])
+                    && self.player_resync_frame[player] != Some(resync.frame)
+            })
+            .collect::<Vec<_>>();
+        if !waiting.is_empty() && resync.started.elapsed() <= RESYNC_TIMEOUT {
+            return;
+        }
+        for player in waiting {
+            warn!(%player, "Network player never got ready to resync, leaving them out");
+            players[player] = false;
+        }
+        for (player, in_session) in players.iter_mut().enumerate() {
+            *in_session &= self.player_is_local[player]
+                || !self.player_is_disconnected[player]
+                || resync.rejoining[player];
+        }
+
+        // Don't carry on alone if the players we were rejoining never got ready, ask again instead
+        let has_remote_players =
+            (0..self.player_count).any(|player| players[player] && !self.player_is_local[player]);
+        if let Some(rejoin) = self.rejoin.as_mut().filter(|_| !has_remote_players) {
+            warn!(frame=%resync.frame, "Couldn't rejoin the match, trying again");
+            rejoin.last_request = None;
+            rejoin.snapshot_frames = self.snapshots.keys().copied().collect();
+            self.resync = None;
+            return;
+        }
+
+        info!(frame=%resync.frame, input_delay=%resync.input_delay, "Starting new network session");
+        if let Some(mut presence) = bevy_world.get_resource_mut::<PresenceState>() {
+            for (in_session, rejoining) in players.iter().zip(resync.rejoining) {
+                if *in_session && rejoining {
+                    presence.player_joined();
+                }
+            }
+        }
+        self.input_delay = resync.input_delay;
+        self.input_delay_changed = Some(Instant::now());
+        self.resync = None;
+        self.rejoin = None;
+        self.start_session(bevy_world.resource::<NetworkMatchSocket>(), players);
+    }
+
+    /// Resync with the highest input delay that the connected players ask for, if it has changed
+    /// and we are the coordinator.
+    pub(super) fn update_input_delay(&mut self, bevy_world: &mut World) {
+        if self.session.is_none()
+            || self.resync.is_some()
+            || self.rejoin.is_some()
+            || !self.is_coordinator()
+        {
+            return;
+        }
+        if self.input_delay_changed.map_or(false, |changed| {
+            changed.elapsed() < INPUT_DELAY_CHANGE_INTERVAL
+        }) {
+            return;
+        }
+        let Some(socket) = bevy_world.get_resource::<NetworkMatchSocket>() else {
+            return;
+        };
+
+        let players = self.connected_players();
+        let input_delay = self.wanted_input_delay(players);
+        if input_delay == self.input_delay {
+            return;
+        }
+
+        let frame = self.next_snapshot_frame();
+        socket.send_message(
+            SocketTarget::All,
+            MatchMessage::Resync {
+                frame,
+                input_delay,
+                players,
+                snapshot_frame: None,
+            },
+        );
+        self.begin_resync(frame, input_delay, players, [None; MAX_PLAYERS]);
+    }
+
+    /// Stop playing and start asking the other players to let us rejoin, after losing the
+    /// connection to all of them.
+    pub(super) fn begin_rejoin(&mut self) {
+        if self.rejoin.is_some() {
+            return;
+        }
+
+        // Only the snapshots from before the last player stopped sending inputs have their inputs
+        let disconnect_frame = (0..self.player_count)
+            .filter(|&player| !self.player_is_local[player])
+            .filter_map(|player| self.disconnect_frame(player))
+            .max()
+            .unwrap_or(self.frame);
+        let snapshot_frames = self
+            .snapshots
+            .range(..=disconnect_frame)
+            .map(|(frame, _)| *frame)
+            .collect();
+
+        warn!("Lost the connection to every other player, trying to rejoin the match");
+        self.resync = None;
+        self.rejoin = Some(Rejoin {
+            started: Instant::now(),
+            last_request: None,
+            snapshot_frames,
+            inputs: default(),
+        });
+    }
+
+    /// Keep asking the other players to let us rejoin, giving up once the grace period for
+    /// disconnected players is over.
+    pub(super) fn update_rejoin(&mut self, bevy_world: &mut World) -> Result<(), SessionError> {
+        let grace_period = self.core.info.meta.config.disconnect_grace_period;
+        let Some(rejoin) = &mut self.rejoin else {
+            return Ok(());
+        };
+        let Some(socket) = bevy_world.get_resource::<NetworkMatchSocket>() else {
+            return Err(SessionError::Disconnected);
+        };
+        if rejoin.started.elapsed() > grace_period {
+            warn!("Couldn't rejoin the network match before being removed from it");
+            return Err(SessionError::Disconnected);
+        }
+
+        let request_due = rejoin.last_request.map_or(true, |last_request| {
+            last_request.elapsed() >= REJOIN_REQUEST_INTERVAL
+        });
+        if self.resync.is_none() && request_due {
+            rejoin.last_request = Some(Instant::now());
+            socket.send_message(
+                SocketTarget::All,
+                MatchMessage::Rejoin {
+                    snapshot_frames: rejoin.snapshot_frames.clone(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Let a player who lost their connection back into the match, if we are the coordinator and
+    /// they haven't been removed from the match yet.
+    pub(super) fn accept_rejoin(
+        &mut self,
+        player: usize,
+        snapshot_frames: &[ggrs::Frame],
+        bevy_world: &mut World,
+    ) {
+        if self.session.is_none() || self.resync.is_some() || self.rejoin.is_some() {
+            return;
+        }
+        if player >= self.player_count
+            || !self.player_is_disconnected[player]
+            || !self.is_coordinator()
+        {
+            return;
+        }
+        if self.connection_status(player) != ConnectionStatus::Disconnected {
+            debug!(%player, "Ignoring rejoin from a player who isn't in the match anymore");
+            return;
+        }
+        let Some(socket) = bevy_world.get_resource::<NetworkMatchSocket>() else {
+            return;
+        };
+
+        // The player catches up from their latest snapshot from before they were disconnected,
+        // which we must still have the inputs after
+        let Some(disconnect_frame) = self.disconnect_frame(player) else {
+            return;
+        };
+        let oldest_frame = self
+            .frame_inputs
+            .keys()
+            .next()
+            .copied()
+            .unwrap_or(self.frame);
+        let Some(snapshot_frame) = snapshot_frames
+            .iter()
+            .copied()
+            .filter(|frame| (oldest_frame..=disconnect_frame).contains(frame))
+            .max()
+        else {
+            warn!(%player, "Network player can't rejoin, none of their snapshots are recent enough");
+            return;
+        };
+
+        let mut players = self.connected_players();
+        players[player] = true;
+        let input_delay = self.wanted_input_delay(players);
+        let frame = self.next_snapshot_frame();
+        info!(%player, %snapshot_frame, %frame, "Letting network player rejoin the match");
+
+        // The rejoining player is told about the resync once we have the inputs they need
+        let local_player_idx = self.local_player_idx();
+        for other_player in (0..self.player_count)
+            .filter(|&other| players[other] && other != player && other != local_player_idx)
+        {
+            socket.send_message(
+                SocketTarget::Player(other_player),
+                MatchMessage::Resync {
+                    frame,
+                    input_delay,
+                    players,
+                    snapshot_frame: None,
+                },
+            );
+        }
+        let mut snapshot_frames = [None; MAX_PLAYERS];
+        snapshot_frames[player] = Some(snapshot_frame);
+        self.begin_resync(frame, input_delay, players, snapshot_frames);
+    }
+
+    /// Send a rejoining player the resync, and the confirmed inputs from their snapshot up to the
+    /// resync frame.
+    fn send_rejoin_inputs(
+        &self,
+        socket: &NetworkMatchSocket,
+        player: usize,
+        snapshot_frame: ggrs::Frame,
+        resync: &Resync,
+    ) {
+        socket.send_message(
+            SocketTarget::Player(player),
+            MatchMessage::Resync {
+                frame: resync.frame,
+                input_delay: resync.input_delay,
+                players: resync.players,
+                snapshot_frame: Some(snapshot_frame),
+            },
+        );
+
+        let inputs = self
+            .frame_inputs
+            .range(snapshot_frame..resync.frame)
+            .map(|(_, inputs)| *inputs)
+            .collect::<Vec<_>>();
+        for (i, chunk) in inputs.chunks(RESYNC_INPUTS_CHUNK).enumerate() {
+            socket.send_message(
+                SocketTarget::Player(player),
+                MatchMessage::ResyncInputs {
+                    frame: snapshot_frame + (i * RESYNC_INPUTS_CHUNK) as ggrs::Frame,
+                    inputs: chunk.to_vec(),
+                },
+            );
+        }
+    }
+
+    /// Keep the confirmed inputs sent to us while we are rejoining.
+    pub(super) fn receive_rejoin_inputs(&mut self, frame: ggrs::Frame, inputs: Vec<NetworkInputs>) {
+        let Some(rejoin) = &mut self.rejoin else {
+            return;
+        };
+        for (i, inputs) in inputs.into_iter().enumerate() {
+            rejoin.inputs.insert(frame + i as ggrs::Frame, inputs);
+        }
+    }
+
+    /// Catch up to the resync `frame` by replaying the confirmed inputs sent to us from the
+    /// snapshot at `snapshot_frame`, once we have all of them.
+    ///
+    /// Returns whether we have caught up.
+    fn replay_rejoin_inputs(
+        &mut self,
+        snapshot_frame: ggrs::Frame,
+        frame: ggrs::Frame,
+        bevy_world: &mut World,
+    ) -> bool {
+        let Some(rejoin) = &mut self.rejoin else {
+            return false;
+        };
+        if !(snapshot_frame..frame).all(|frame| rejoin.inputs.contains_key(&frame)) {
+            return false;
+        }
+        let inputs = std::mem::take(&mut rejoin.inputs);
+        let Some(mut snapshot) = self.snapshots.get(&snapshot_frame).cloned() else {
+            return false;
+        };
+
+        info!(from=%snapshot_frame, to=%frame, "Replaying missed frames to rejoin the match");
+        self.core.restore(&mut snapshot);
+        self.frame = snapshot_frame;
+        self.snapshots.retain(|frame, _| *frame <= snapshot_frame);
+        self.frame_inputs.retain(|frame, _| *frame < snapshot_frame);
+        for (&input_frame, inputs) in inputs.range(snapshot_frame..frame) {
+            if input_frame % SNAPSHOT_INTERVAL == 0 {
+                self.snapshots.insert(input_frame, self.core.snapshot());
+            }
+            self.frame_inputs.insert(input_frame, *inputs);
+            self.advance_core(inputs, bevy_world);
+        }
+
+        true
+    }
+
+    /// Forget the snapshots that are too old to resync from, and the inputs that are too old for a
+    /// disconnected player to catch up with before the grace period is over.
+    pub(super) fn trim_history(&mut self) {
+        let Some(&oldest_frame) = self
+            .snapshots
+            .range(..=self.confirmed_frame + 1)
+            .rev()
+            .nth(SNAPSHOT_HISTORY - 1)
+            .map(|(frame, _)| frame)
+        else {
+            return;
+        };
+        self.snapshots = self.snapshots.split_off(&oldest_frame);
+
+        // A rejoining player may catch up from a snapshot up to the snapshot history before they
+        // were disconnected, and the inputs of the players rejoining in a resync are still needed
+        let grace_period = self.core.info.meta.config.disconnect_grace_period;
+        let grace_frames = (grace_period.as_secs_f32() * jumpy_core::FPS) as ggrs::Frame;
+        let history_frames = SNAPSHOT_HISTORY as ggrs::Frame * SNAPSHOT_INTERVAL;
+        let rejoin_snapshot_frames = self
+            .resync
+            .iter()
+            .flat_map(|resync| resync.snapshot_frames.into_iter().flatten());
+        let oldest_input = rejoin_snapshot_frames
+            .chain([
+                oldest_frame,
+                self.confirmed_frame - grace_frames - history_frames,
+            ])
+            .min()
+            .unwrap();
+        self.frame_inputs = self.frame_inputs.split_off(&oldest_input);
+    }
+}
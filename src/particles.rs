@@ -0,0 +1,212 @@
+//! Cosmetic particle effects that are played outside of the game session.
+//!
+//! Unlike the particles in [`jumpy_core::particles`], these are plain Bevy entities that aren't
+//! part of the simulation, so they are cheaper and can't affect rollback, but they aren't rolled
+//! back either. They are only used for effects that don't matter to the game, like landing dust.
+
+use jumpy_core::{physics::KinematicBody, player::PlayerIdx};
+use rand::{thread_rng, Rng};
+
+use crate::prelude::*;
+
+pub struct JumpyParticlesPlugin;
+
+impl Plugin for JumpyParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                spawn_landing_dust,
+                update_cosmetic_emitters,
+                update_cosmetic_particles,
+            )
+                .chain()
+                .distributive_run_if(in_state(EngineState::InGame))
+                .distributive_run_if(in_state(InGameState::Playing))
+                .distributive_run_if(resource_exists::<Session>()),
+        )
+        .add_system(clear_cosmetic_particles.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// Component for a Bevy entity that spawns the particles of a cosmetic particle effect.
+#[derive(Component, Clone, Debug)]
+pub struct CosmeticEmitter {
+    pub meta: Handle<ParticleEffectMeta>,
+    pub flip_x: bool,
+    /// How long the emitter has been running for, in seconds.
+    pub age: f32,
+    /// The fraction of a particle left over from the last frame.
+    pub remainder: f32,
+    /// The number of frames that the emitter has spawned particles for.
+    pub frame: u32,
+}
+
+impl CosmeticEmitter {
+    pub fn new(meta: Handle<ParticleEffectMeta>, flip_x: bool) -> Self {
+        Self {
+            meta,
+            flip_x,
+            age: 0.0,
+            remainder: 0.0,
+            frame: 0,
+        }
+    }
+}
+
+/// Component for a particle spawned by a [`CosmeticEmitter`].
+#[derive(Component, Clone, Debug)]
+pub struct CosmeticParticle {
+    pub meta: Handle<ParticleEffectMeta>,
+    pub emitter: Entity,
+    /// The velocity of the particle, in pixels per game frame.
+    pub velocity: Vec2,
+    pub age: f32,
+}
+
+/// Play the landing dust effect under each player that has just landed on the ground.
+fn spawn_landing_dust(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    core_meta: Res<CoreMetaArc>,
+    mut was_on_ground: Local<[bool; MAX_PLAYERS]>,
+) {
+    let Some(landing_dust) = &core_meta.landing_dust else {
+        return;
+    };
+
+    let players = session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             player_indexes: bones::Comp<PlayerIdx>,
+             bodies: bones::Comp<KinematicBody>,
+             transforms: bones::Comp<bones::Transform>| {
+                let players = entities
+                    .iter_with((&player_indexes, &bodies, &transforms))
+                    .map(|(_ent, (player_idx, body, transform))| {
+                        let feet = body.bounding_box(*transform).min.y;
+                        let position = Vec3::new(transform.translation.x, feet, 1.0);
+                        (player_idx.0, body.is_on_ground, position)
+                    })
+                    .collect::<Vec<_>>();
+                Ok(players)
+            },
+        )
+        .unwrap();
+
+    // The session may run any number of frames between our updates, so we keep track of the
+    // ground state ourselves instead of relying on the body's `was_on_ground`.
+    for (player_idx, is_on_ground, position) in players {
+        if is_on_ground && !was_on_ground[player_idx] {
+            commands.spawn((
+                CosmeticEmitter::new(landing_dust.get_bevy_handle(), false),
+                TransformBundle::from_transform(Transform::from_translation(position)),
+                Name::new("Landing Dust"),
+            ));
+        }
+        was_on_ground[player_idx] = is_on_ground;
+    }
+}
+
+/// Spawn the particles of each cosmetic emitter, despawning the emitter once its particles are
+/// gone.
+fn update_cosmetic_emitters(
+    mut commands: Commands,
+    time: Res<Time>,
+    particle_assets: Res<Assets<ParticleEffectMeta>>,
+    mut emitters: Query<(Entity, &mut CosmeticEmitter, &Transform)>,
+    particles: Query<&CosmeticParticle>,
+) {
+    let mut rng = thread_rng();
+    for (emitter_ent, mut emitter, transform) in &mut emitters {
+        let Some(effect) = particle_assets.get(&emitter.meta) else {
+            continue;
+        };
+
+        emitter.age += time.delta_seconds();
+        if emitter.age >= effect.total_duration() {
+            commands.entity(emitter_ent).despawn();
+            continue;
+        }
+
+        // Spawn the particles for each game frame that has passed
+        let alive = particles
+            .iter()
+            .filter(|x| x.emitter == emitter_ent)
+            .count();
+        let mut count = 0;
+        while (emitter.frame as f32) < emitter.age * jumpy_core::FPS {
+            let emitter = &mut *emitter;
+            count += effect.spawn_count(emitter.frame, &mut emitter.remainder);
+            emitter.frame += 1;
+        }
+        let count = count.min(effect.max_particles.saturating_sub(alive));
+
+        for _ in 0..count {
+            let velocity = effect.particle_velocity(
+                rng.gen_range(-1.0..=1.0),
+                rng.gen_range(0.0..=1.0),
+                emitter.flip_x,
+            );
+            commands.spawn((
+                CosmeticParticle {
+                    meta: emitter.meta.clone(),
+                    emitter: emitter_ent,
+                    velocity,
+                    age: 0.0,
+                },
+                SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        index: effect.first_frame,
+                        flip_x: emitter.flip_x,
+                        ..default()
+                    },
+                    texture_atlas: effect.atlas.get_bevy_handle_untyped().typed(),
+                    transform: Transform::from_translation(transform.translation),
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+/// Move and animate cosmetic particles, despawning them at the end of their lifetime.
+fn update_cosmetic_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    particle_assets: Res<Assets<ParticleEffectMeta>>,
+    mut particles: Query<(
+        Entity,
+        &mut CosmeticParticle,
+        &mut Transform,
+        &mut TextureAtlasSprite,
+    )>,
+) {
+    // Particle velocities are in pixels per game frame
+    let frames = time.delta_seconds() * jumpy_core::FPS;
+    for (ent, mut particle, mut transform, mut sprite) in &mut particles {
+        let Some(effect) = particle_assets.get(&particle.meta) else {
+            commands.entity(ent).despawn();
+            continue;
+        };
+
+        particle.age += time.delta_seconds();
+        if particle.age >= effect.lifetime {
+            commands.entity(ent).despawn();
+            continue;
+        }
+
+        particle.velocity.y -= effect.gravity * frames;
+        transform.translation += (particle.velocity * frames).extend(0.0);
+        sprite.index = effect.particle_frame(particle.age);
+    }
+}
+
+fn clear_cosmetic_particles(
+    mut commands: Commands,
+    entities: Query<Entity, Or<(With<CosmeticEmitter>, With<CosmeticParticle>)>>,
+) {
+    for ent in &entities {
+        commands.entity(ent).despawn();
+    }
+}
@@ -17,7 +17,68 @@ pub struct JumpyPlatformPlugin;
 impl Plugin for JumpyPlatformPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Storage>()
-            .add_system(load_storage.run_if(in_state(EngineState::LoadingPlatformStorage)));
+            .init_resource::<StorageItemMigrations>()
+            .init_resource::<StorageWarnings>()
+            .add_system(load_storage.run_if(in_state(EngineState::LoadingPlatformStorage)))
+            .add_system(receive_storage_warnings);
+    }
+}
+
+/// Extension trait for registering [`StorageItem`]s with the app.
+pub trait StorageAppExt {
+    /// Register a [`StorageItem`], so that its [`migrate`][StorageItem::migrate] hook is run when
+    /// the [`Storage`] is loaded.
+    fn register_storage_item<T: StorageItem>(&mut self) -> &mut Self;
+}
+
+impl StorageAppExt for App {
+    fn register_storage_item<T: StorageItem>(&mut self) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(StorageItemMigrations::default)
+            .0
+            .insert(T::STORAGE_KEY, T::migrate);
+        self
+    }
+}
+
+/// Get the directory that the game keeps its persistent data in.
+///
+/// This is the platform's data directory for the game, unless the [`DATA_DIR_ENV_VAR`] environment
+/// variable is set, which can be used for portable installs.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn data_dir() -> std::path::PathBuf {
+    if let Some(dir) = std::env::var_os(DATA_DIR_ENV_VAR).filter(|dir| !dir.is_empty()) {
+        return dir.into();
+    }
+
+    directories::ProjectDirs::from("org", "FishFolk", "Jumpy")
+        .expect("Identify system data dir path")
+        .data_dir()
+        .to_path_buf()
+}
+
+/// A problem with the [`Storage`] that the player should know about, but that doesn't stop the
+/// game from running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageWarning {
+    /// The storage couldn't be read, and was loaded from the backup of the last save before it.
+    RecoveredFromBackup,
+    /// The storage couldn't be read, and there was no backup to recover, so it was reset.
+    Reset,
+    /// The storage couldn't be saved.
+    SaveFailed(String),
+    /// The storage couldn't be saved, because the browser's storage quota is used up.
+    QuotaExceeded,
+}
+
+/// The [`StorageWarning`]s that haven't been shown to the player yet, oldest first.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct StorageWarnings(pub Vec<StorageWarning>);
+
+/// Collect the warnings reported by the storage backend into [`StorageWarnings`].
+fn receive_storage_warnings(storage: Res<Storage>, mut warnings: ResMut<StorageWarnings>) {
+    while let Ok(warning) = storage.warning_receiver.try_recv() {
+        warnings.push(warning);
     }
 }
 
@@ -29,12 +90,14 @@ pub fn load_storage(
     mut started: Local<bool>,
     mut commands: Commands,
     mut storage: ResMut<Storage>,
+    item_migrations: Res<StorageItemMigrations>,
 ) {
     // If we haven't started loading
     if !*started {
         debug!("Start loading platform storage");
         // Start loading
         *started = true;
+        storage.item_migrations = item_migrations.clone();
         storage.load();
 
     // If storage has finished loading
@@ -45,9 +108,82 @@ pub fn load_storage(
     }
 }
 
+/// A type that is kept in [`Storage`] under its own key.
+pub trait StorageItem: Serialize + DeserializeOwned + Default {
+    /// The key the item is stored under.
+    const STORAGE_KEY: &'static str;
+
+    /// Update the item's stored value from an older [`STORAGE_VERSION`].
+    ///
+    /// This runs after the migrations of the whole storage, for items that have been registered
+    /// with [`StorageAppExt::register_storage_item`], and only if the item is in storage.
+    fn migrate(value: serde_yaml::Value, _from: u32) -> serde_yaml::Value {
+        value
+    }
+}
+
 /// The type of the inner data in [`Storage`]
 type StorageData = HashMap<String, serde_yaml::Value>;
 
+/// The version of the format of the data in [`Storage`], which is saved along with the data.
+///
+/// Whenever stored data changes in a way that older data would no longer load, bump this version
+/// and add a migration for the old data to [`MIGRATIONS`].
+pub const STORAGE_VERSION: u32 = 1;
+
+/// The key that the [`STORAGE_VERSION`] is saved under, next to the stored items.
+const STORAGE_VERSION_KEY: &str = "storage_version";
+
+/// The migrations of the storage data, where the migration at index `n` updates data from
+/// version `n` to version `n + 1`.
+const MIGRATIONS: [fn(StorageData) -> StorageData; STORAGE_VERSION as usize] = [migrate_v0];
+
+/// Version `0` is storage saved before it was versioned, which only differs in that it has no
+/// version key.
+fn migrate_v0(data: StorageData) -> StorageData {
+    data
+}
+
+/// The [`StorageItem::migrate`] hooks of the registered storage items, by storage key.
+#[derive(Resource, Clone, Default)]
+pub struct StorageItemMigrations(
+    HashMap<&'static str, fn(serde_yaml::Value, u32) -> serde_yaml::Value>,
+);
+
+/// Update storage data saved with an older [`STORAGE_VERSION`] to the current version.
+///
+/// Data from a newer version than the current one can't be migrated, and is returned unchanged.
+pub fn migrate(
+    from_version: u32,
+    mut data: StorageData,
+    item_migrations: &StorageItemMigrations,
+) -> StorageData {
+    if from_version >= STORAGE_VERSION {
+        return data;
+    }
+
+    for migration in &MIGRATIONS[from_version as usize..] {
+        data = migration(data);
+    }
+    for (key, migration) in &item_migrations.0 {
+        if let Some(value) = data.remove(*key) {
+            data.insert(key.to_string(), migration(value, from_version));
+        }
+    }
+
+    data
+}
+
+/// Take the [`STORAGE_VERSION`] that storage data was saved with out of the data.
+///
+/// Data saved before storage was versioned is version `0`.
+fn take_storage_version(data: &mut StorageData) -> u32 {
+    data.remove(STORAGE_VERSION_KEY)
+        .and_then(|version| version.as_u64())
+        .map(|version| version as u32)
+        .unwrap_or(0)
+}
+
 /// Resource for accessing platform specific persistent storage apis through a simple interface.
 #[derive(Resource)]
 pub struct Storage {
@@ -57,16 +193,27 @@ pub struct Storage {
     data_receiver: Option<Receiver<StorageData>>,
     /// The sender we use to send storage requests to the storage backend
     backend_sender: Sender<StorageRequest>,
+    /// The receiver for the warnings reported by the storage backend.
+    warning_receiver: Receiver<StorageWarning>,
+    /// The migration hooks of the registered storage items, run when the data is loaded.
+    item_migrations: StorageItemMigrations,
+    /// Whether the data was saved by a newer version of the game, in which case it is never saved,
+    /// so that the newer data isn't lost.
+    read_only: bool,
 }
 
 impl FromWorld for Storage {
     fn from_world(_: &mut World) -> Self {
-        let backend_sender = backend::init_storage();
+        let (warning_sender, warning_receiver) = async_channel::unbounded();
+        let backend_sender = backend::init_storage(warning_sender);
 
         Self {
             data: None,
             data_receiver: None,
             backend_sender,
+            warning_receiver,
+            item_migrations: default(),
+            read_only: false,
         }
     }
 }
@@ -86,7 +233,21 @@ impl Storage {
         // If we are waiting on a data load response
         if let Some(receiver) = &mut self.data_receiver {
             // If the data has been loaded
-            if let Ok(data) = receiver.try_recv() {
+            if let Ok(mut data) = receiver.try_recv() {
+                let version = take_storage_version(&mut data);
+                if version > STORAGE_VERSION {
+                    warn!(
+                        version,
+                        current_version = STORAGE_VERSION,
+                        "Storage was saved by a newer version of the game. It will be used as is, \
+                        but changes will not be saved, so that the newer data isn't lost."
+                    );
+                    self.read_only = true;
+                } else if version < STORAGE_VERSION {
+                    info!(from = version, to = STORAGE_VERSION, "Migrating storage");
+                    data = migrate(version, data, &self.item_migrations);
+                }
+
                 // Set the local data and clear the load receiver
                 self.data = Some(data);
                 self.data_receiver = None;
@@ -211,6 +372,116 @@ impl Storage {
         self.try_set(key, value).expect("Set value in storage")
     }
 
+    /// Get a [`StorageItem`] from the in-memory storage cache, or its default value if it hasn't
+    /// been stored or can't be deserialized.
+    #[track_caller]
+    pub fn get_item<T: StorageItem>(&mut self) -> T {
+        self.get(T::STORAGE_KEY).unwrap_or_default()
+    }
+
+    /// Set a [`StorageItem`] in the in-memory storage cache.
+    ///
+    /// Changes will not be persisted until [`Self::save()`] is called.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if storage has not been loaded yet or if there is a serialization error.
+    #[track_caller]
+    pub fn set_item<T: StorageItem>(&mut self, item: &T) {
+        self.set(T::STORAGE_KEY, item)
+    }
+
+    /// Remove a value from the in-memory storage cache, returning it if it was set.
+    ///
+    /// Changes will not be persisted until [`Self::save()`] is called.
+    pub fn try_remove<T>(&mut self, key: &str) -> Result<Option<T>, StorageError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        self.check_pending_data_load();
+
+        if let Some(data) = &mut self.data {
+            if let Some(value) = data.remove(key) {
+                Ok(Some(serde_yaml::from_value(value)?))
+            } else {
+                Ok(None)
+            }
+        } else {
+            Err(StorageError::NotLoaded)
+        }
+    }
+
+    /// Remove a value from the in-memory storage cache, returning it if it was set.
+    ///
+    /// Changes will not be persisted until [`Self::save()`] is called.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if storage has not been loaded yet. If the removed value can't be
+    /// deserialized it is still removed, but `None` is returned.
+    #[track_caller]
+    pub fn remove<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        match self.try_remove(key) {
+            Ok(data) => data,
+            Err(StorageError::SerializationError(e)) => {
+                error!("Error deserializing removed storage key: {}", e);
+                None
+            }
+            Err(e) => panic!("Remove value from storage: {e}"),
+        }
+    }
+
+    /// Remove a [`StorageItem`] from the in-memory storage cache, returning it if it was set.
+    ///
+    /// Changes will not be persisted until [`Self::save()`] is called.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if storage has not been loaded yet.
+    #[track_caller]
+    pub fn remove_item<T: StorageItem>(&mut self) -> Option<T> {
+        self.remove(T::STORAGE_KEY)
+    }
+
+    /// Remove all values from the in-memory storage cache.
+    ///
+    /// Changes will not be persisted until [`Self::save()`] is called.
+    pub fn try_clear(&mut self) -> Result<(), StorageError> {
+        self.check_pending_data_load();
+
+        if let Some(data) = &mut self.data {
+            data.clear();
+
+            Ok(())
+        } else {
+            Err(StorageError::NotLoaded)
+        }
+    }
+
+    /// Remove all values from the in-memory storage cache.
+    ///
+    /// Changes will not be persisted until [`Self::save()`] is called.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if storage has not been loaded yet.
+    #[track_caller]
+    pub fn clear(&mut self) {
+        self.try_clear().expect("Clear storage")
+    }
+
+    /// Get a view of the storage whose keys are all prefixed with `prefix`, so that different
+    /// owners, like player profiles or maps, can store values under the same keys.
+    pub fn scoped(&mut self, prefix: &str) -> ScopedStorage<'_> {
+        ScopedStorage {
+            prefix: format!("{prefix}{SCOPE_SEPARATOR}"),
+            storage: self,
+        }
+    }
+
     /// Saves the in-memory storage cache to persistent storage.
     ///
     /// This operation is asynchronous and returns a [`SaveTask`] that can be used to check when the
@@ -221,9 +492,18 @@ impl Storage {
         if let Some(data) = &self.data {
             let (result_sender, result_receiver) = async_channel::unbounded();
 
+            if self.read_only {
+                warn!("Not saving storage, because it was saved by a newer version of the game");
+                result_sender.try_send(()).ok();
+                return Ok(SaveTask(result_receiver));
+            }
+
+            let mut data = data.clone();
+            data.insert(STORAGE_VERSION_KEY.into(), STORAGE_VERSION.into());
+
             self.backend_sender
                 .try_send(StorageRequest::Save {
-                    data: data.clone(),
+                    data,
                     result_sender,
                 })
                 .map_err(|_| StorageError::BackendLost)?;
@@ -249,6 +529,132 @@ impl Storage {
     }
 }
 
+/// The separator between the prefix of a [`ScopedStorage`] and the keys in it.
+const SCOPE_SEPARATOR: char = '/';
+
+/// A view of [`Storage`] that prefixes all of the keys that it accesses, returned by
+/// [`Storage::scoped()`].
+pub struct ScopedStorage<'a> {
+    storage: &'a mut Storage,
+    /// The prefix of the scope, including the trailing separator.
+    prefix: String,
+}
+
+impl<'a> ScopedStorage<'a> {
+    /// Get the key in the underlying storage for a key in this scope.
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+
+    /// Try to get a value in this scope from the in-memory storage cache.
+    pub fn try_get<T>(&mut self, key: &str) -> Result<Option<T>, StorageError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let key = self.key(key);
+        self.storage.try_get(&key)
+    }
+
+    /// Get a value in this scope from the in-memory storage cache. See [`Storage::get()`].
+    #[track_caller]
+    pub fn get<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let key = self.key(key);
+        self.storage.get(&key)
+    }
+
+    /// Set a value in this scope in the in-memory storage cache.
+    pub fn try_set<T>(&mut self, key: &str, value: &T) -> Result<(), StorageError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let key = self.key(key);
+        self.storage.try_set(&key, value)
+    }
+
+    /// Set a value in this scope in the in-memory storage cache. See [`Storage::set()`].
+    #[track_caller]
+    pub fn set<T>(&mut self, key: &str, value: &T)
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let key = self.key(key);
+        self.storage.set(&key, value)
+    }
+
+    /// Remove a value in this scope from the in-memory storage cache.
+    pub fn try_remove<T>(&mut self, key: &str) -> Result<Option<T>, StorageError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let key = self.key(key);
+        self.storage.try_remove(&key)
+    }
+
+    /// Remove a value in this scope from the in-memory storage cache. See [`Storage::remove()`].
+    #[track_caller]
+    pub fn remove<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let key = self.key(key);
+        self.storage.remove(&key)
+    }
+
+    /// Get a [`StorageItem`] in this scope, or its default value. See [`Storage::get_item()`].
+    #[track_caller]
+    pub fn get_item<T: StorageItem>(&mut self) -> T {
+        self.get(T::STORAGE_KEY).unwrap_or_default()
+    }
+
+    /// Set a [`StorageItem`] in this scope. See [`Storage::set_item()`].
+    #[track_caller]
+    pub fn set_item<T: StorageItem>(&mut self, item: &T) {
+        self.set(T::STORAGE_KEY, item)
+    }
+
+    /// Remove a [`StorageItem`] in this scope. See [`Storage::remove_item()`].
+    #[track_caller]
+    pub fn remove_item<T: StorageItem>(&mut self) -> Option<T> {
+        self.remove(T::STORAGE_KEY)
+    }
+
+    /// Remove all of the values in this scope, including those of nested scopes, from the
+    /// in-memory storage cache.
+    pub fn try_clear(&mut self) -> Result<(), StorageError> {
+        self.storage.check_pending_data_load();
+
+        if let Some(data) = &mut self.storage.data {
+            data.retain(|key, _| !key.starts_with(&self.prefix));
+
+            Ok(())
+        } else {
+            Err(StorageError::NotLoaded)
+        }
+    }
+
+    /// Remove all of the values in this scope, including those of nested scopes, from the
+    /// in-memory storage cache.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if storage has not been loaded yet.
+    #[track_caller]
+    pub fn clear(&mut self) {
+        self.try_clear().expect("Clear storage scope")
+    }
+
+    /// Get a view of the storage nested in this scope.
+    pub fn scoped(&mut self, prefix: &str) -> ScopedStorage<'_> {
+        ScopedStorage {
+            prefix: format!("{}{prefix}{SCOPE_SEPARATOR}", self.prefix),
+            storage: self.storage,
+        }
+    }
+}
+
 /// [`Storage::save()`] task handle that can be used to check whether or not saving has been
 /// completed.
 pub struct SaveTask(Receiver<()>);
@@ -276,19 +682,19 @@ enum StorageRequest {
 mod native {
     use std::{
         fs,
-        io::{Read, Write},
+        io::{self, Write},
+        path::{Path, PathBuf},
     };
 
     use async_channel::Sender;
     use bevy::{
-        prelude::{error, trace},
+        prelude::{error, trace, warn},
         tasks::IoTaskPool,
-        utils::HashMap,
     };
 
-    use super::StorageRequest;
+    use super::{StorageData, StorageRequest, StorageWarning};
 
-    pub(super) fn init_storage() -> Sender<StorageRequest> {
+    pub(super) fn init_storage(warning_sender: Sender<StorageWarning>) -> Sender<StorageRequest> {
         trace!("Initialize platform storage backend");
         let io_task_pool = IoTaskPool::get();
 
@@ -296,40 +702,44 @@ mod native {
         let (sender, receiver) = async_channel::unbounded();
 
         // Identify project storage file path
-        let project_dirs = directories::ProjectDirs::from("org", "FishFolk", "Jumpy")
-            .expect("Identify system data dir path");
-        let file_path = project_dirs.data_dir().join("storage.yml");
+        let file_path = super::data_dir().join("storage.yml");
+        let backup_path = file_path.with_extension("yml.bak");
 
         trace!(?file_path, "Platform storage filepath");
 
         // Spawn an async task that will read and write to the filesystem
         io_task_pool
             .spawn(async move {
+                // Whether the storage file is known to be broken, in which case it must not replace
+                // the backup on the next save.
+                let mut file_broken = false;
+
                 while let Ok(request) = receiver.recv().await {
                     match request {
                         StorageRequest::Load { result_sender } => {
-                            let data = if file_path.exists() {
-                                let mut file = fs::OpenOptions::new()
-                                    .read(true)
-                                    .open(&file_path)
-                                    .expect("Open storage file");
-
-                                let mut contents = Vec::new();
-                                file.read_to_end(&mut contents).expect("Read storage file");
-
-                                match serde_yaml::from_slice(&contents) {
-                                    Ok(data) => data,
-                                    Err(e) => {
-                                        error!(
-                                            "Error deserializing storage file. Ignoring existing \
-                                            data and overwriting on next attempt to save: {}",
-                                            e
-                                        );
-                                        Default::default()
+                            let data = match read_storage_file(&file_path) {
+                                Ok(data) => data.unwrap_or_default(),
+                                Err(e) => {
+                                    file_broken = true;
+                                    error!("Error reading storage file: {e}");
+
+                                    match read_storage_file(&backup_path) {
+                                        Ok(Some(data)) => {
+                                            warn!("Recovered storage from backup");
+                                            warning_sender
+                                                .try_send(StorageWarning::RecoveredFromBackup)
+                                                .ok();
+                                            data
+                                        }
+                                        backup => {
+                                            if let Err(e) = backup {
+                                                error!("Error reading storage backup: {e}");
+                                            }
+                                            warning_sender.try_send(StorageWarning::Reset).ok();
+                                            Default::default()
+                                        }
                                     }
                                 }
-                            } else {
-                                HashMap::new()
                             };
 
                             result_sender.try_send(data).ok();
@@ -338,18 +748,15 @@ mod native {
                             data,
                             result_sender,
                         } => {
-                            let data = serde_yaml::to_string(&data).expect("Serialize data");
-                            if let Some(parent) = file_path.parent() {
-                                std::fs::create_dir_all(parent).expect("Create storage dir");
+                            match write_storage_file(&file_path, &backup_path, &data, file_broken) {
+                                Ok(()) => file_broken = false,
+                                Err(e) => {
+                                    error!("Error saving storage: {e}");
+                                    warning_sender
+                                        .try_send(StorageWarning::SaveFailed(e.to_string()))
+                                        .ok();
+                                }
                             }
-                            let mut file = fs::OpenOptions::new()
-                                .create(true)
-                                .write(true)
-                                .truncate(true)
-                                .open(&file_path)
-                                .expect("Open storage file");
-
-                            file.write_all(data.as_bytes()).expect("Write storage file");
 
                             result_sender.try_send(()).ok();
                         }
@@ -360,6 +767,80 @@ mod native {
 
         sender
     }
+
+    /// Read and deserialize a storage file, returning `None` if it doesn't exist.
+    fn read_storage_file(path: &Path) -> io::Result<Option<StorageData>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read(path)?;
+        serde_yaml::from_slice(&contents)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Atomically replace the storage file with `data`, keeping the previous file as a backup.
+    ///
+    /// The data is written to a temporary file in the same directory and synced to disk before it
+    /// is renamed over the storage file, so that the storage file is always either the old or the
+    /// new data, even if the game crashes while saving.
+    fn write_storage_file(
+        path: &Path,
+        backup_path: &Path,
+        data: &StorageData,
+        keep_backup: bool,
+    ) -> io::Result<()> {
+        let data = serde_yaml::to_string(data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = PathBuf::from(format!("{}.tmp", path.display()));
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(data.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+
+        if path.exists() && !keep_backup {
+            fs::copy(path, backup_path)?;
+        }
+        fs::rename(&temp_path, path)
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn save_keeps_backup_and_load_recovers_it() {
+            let dir = std::env::temp_dir().join(format!("jumpy-storage-{}", std::process::id()));
+            let path = dir.join("storage.yml");
+            let backup_path = dir.join("storage.yml.bak");
+
+            let mut first = StorageData::default();
+            first.insert("value".into(), 1.into());
+            let mut second = StorageData::default();
+            second.insert("value".into(), 2.into());
+
+            write_storage_file(&path, &backup_path, &first, false).unwrap();
+            write_storage_file(&path, &backup_path, &second, false).unwrap();
+            assert_eq!(read_storage_file(&path).unwrap(), Some(second));
+            assert_eq!(
+                read_storage_file(&backup_path).unwrap(),
+                Some(first.clone())
+            );
+
+            // A broken storage file doesn't load, and doesn't replace the backup when saving
+            fs::write(&path, "value: [").unwrap();
+            assert!(read_storage_file(&path).is_err());
+            write_storage_file(&path, &backup_path, &StorageData::default(), true).unwrap();
+            assert_eq!(read_storage_file(&backup_path).unwrap(), Some(first));
+
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
 }
 
 /// WASM platform support
@@ -367,13 +848,14 @@ mod native {
 mod wasm {
     use async_channel::Sender;
     use bevy::{prelude::*, tasks::IoTaskPool};
+    use wasm_bindgen::JsCast;
 
-    use super::StorageRequest;
+    use super::{StorageRequest, StorageWarning};
 
     const BROWSER_LOCAL_STORAGE_KEY: &str = "jumpy-platform-storage";
 
     /// Initialize storage backend
-    pub(super) fn init_storage() -> Sender<StorageRequest> {
+    pub(super) fn init_storage(warning_sender: Sender<StorageWarning>) -> Sender<StorageRequest> {
         trace!("Initialize platform storage backend");
         let io_task_pool = IoTaskPool::get();
 
@@ -399,6 +881,7 @@ mod wasm {
                                         and overwriting on next attempt to set data in storage: {}",
                                         e
                                     );
+                                    warning_sender.try_send(StorageWarning::Reset).ok();
 
                                     None
                                 }
@@ -413,9 +896,16 @@ mod wasm {
                     } => {
                         let data = serde_yaml::to_string(&data).expect("Serialize platform data");
 
-                        local_storage
-                            .set_item(BROWSER_LOCAL_STORAGE_KEY, &data)
-                            .unwrap();
+                        if let Err(e) = local_storage.set_item(BROWSER_LOCAL_STORAGE_KEY, &data) {
+                            let warning = match e.dyn_ref::<web_sys::DomException>() {
+                                Some(e) if e.name() == "QuotaExceededError" => {
+                                    StorageWarning::QuotaExceeded
+                                }
+                                _ => StorageWarning::SaveFailed(format!("{e:?}")),
+                            };
+                            error!("Error saving storage: {warning:?}");
+                            warning_sender.try_send(warning).ok();
+                        }
 
                         result_sender.try_send(()).ok();
                     }
@@ -426,3 +916,178 @@ mod wasm {
         sender
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Serialize, serde::Deserialize, Default)]
+    #[serde(default)]
+    struct RenamedField {
+        new_name: u32,
+    }
+
+    impl StorageItem for RenamedField {
+        const STORAGE_KEY: &'static str = "renamed_field";
+
+        fn migrate(mut value: serde_yaml::Value, from: u32) -> serde_yaml::Value {
+            if from == 0 {
+                if let Some(mapping) = value.as_mapping_mut() {
+                    if let Some(old) = mapping.remove("old_name") {
+                        mapping.insert("new_name".into(), old);
+                    }
+                }
+            }
+            value
+        }
+    }
+
+    /// Make storage without a backend, that will load `data`, and the receiver for the requests
+    /// that would have been sent to the backend.
+    fn storage_loading(data: StorageData) -> (Storage, Receiver<StorageRequest>) {
+        let (backend_sender, backend_receiver) = async_channel::unbounded();
+        let (data_sender, data_receiver) = async_channel::unbounded();
+        data_sender.try_send(data).unwrap();
+
+        let mut item_migrations = StorageItemMigrations::default();
+        item_migrations
+            .0
+            .insert(RenamedField::STORAGE_KEY, RenamedField::migrate);
+
+        let storage = Storage {
+            data: None,
+            data_receiver: Some(data_receiver),
+            backend_sender,
+            warning_receiver: async_channel::unbounded().1,
+            item_migrations,
+            read_only: false,
+        };
+        (storage, backend_receiver)
+    }
+
+    fn parse(yaml: &str) -> StorageData {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn unversioned_storage_is_migrated() {
+        let (mut storage, backend) = storage_loading(parse(
+            "settings:\n  main_volume: 0.5\nrenamed_field:\n  old_name: 7",
+        ));
+
+        assert!(storage.is_loaded());
+        assert_eq!(storage.get_item::<RenamedField>().new_name, 7);
+        assert_eq!(
+            storage.get::<serde_yaml::Value>("settings"),
+            Some(serde_yaml::from_str("main_volume: 0.5").unwrap())
+        );
+
+        storage.try_save().unwrap();
+        match backend.try_recv().unwrap() {
+            StorageRequest::Save { data, .. } => {
+                assert_eq!(
+                    data.get(STORAGE_VERSION_KEY),
+                    Some(&serde_yaml::Value::from(STORAGE_VERSION))
+                );
+            }
+            _ => panic!("Expected a save request"),
+        }
+    }
+
+    #[test]
+    fn current_version_is_not_migrated() {
+        let (mut storage, _backend) = storage_loading(parse(&format!(
+            "{STORAGE_VERSION_KEY}: {STORAGE_VERSION}\nrenamed_field:\n  old_name: 7"
+        )));
+
+        assert!(storage.is_loaded());
+        assert_eq!(storage.get_item::<RenamedField>().new_name, 0);
+        assert!(storage
+            .get::<serde_yaml::Value>(STORAGE_VERSION_KEY)
+            .is_none());
+    }
+
+    #[test]
+    fn future_version_is_read_only() {
+        let future_version = STORAGE_VERSION + 1;
+        let (mut storage, backend) = storage_loading(parse(&format!(
+            "{STORAGE_VERSION_KEY}: {future_version}\nrenamed_field:\n  new_name: 3"
+        )));
+
+        assert!(storage.is_loaded());
+        assert_eq!(storage.get_item::<RenamedField>().new_name, 3);
+
+        storage.set_item(&RenamedField { new_name: 4 });
+        storage.try_save().unwrap();
+        assert!(backend.try_recv().is_err());
+    }
+
+    #[test]
+    fn scoped_storage_round_trips_alongside_unscoped_keys() {
+        let (mut storage, _backend) = storage_loading(StorageData::default());
+        assert!(storage.is_loaded());
+
+        storage.set("volume", &1);
+        storage.scoped("profile-0").set("volume", &2);
+        storage.scoped("profile-1").set("volume", &3);
+        storage
+            .scoped("profile-1")
+            .set_item(&RenamedField { new_name: 4 });
+        storage.scoped("profile-1").scoped("map").set("volume", &5);
+
+        assert_eq!(storage.get("volume"), Some(1));
+        assert_eq!(storage.scoped("profile-0").get("volume"), Some(2));
+        assert_eq!(storage.scoped("profile-1").get("volume"), Some(3));
+        assert_eq!(storage.get::<u32>("profile-1/volume"), Some(3));
+        assert_eq!(
+            storage
+                .scoped("profile-1")
+                .get_item::<RenamedField>()
+                .new_name,
+            4
+        );
+        assert_eq!(storage.get_item::<RenamedField>().new_name, 0);
+
+        assert_eq!(storage.scoped("profile-0").remove("volume"), Some(2));
+        assert_eq!(storage.scoped("profile-0").get::<u32>("volume"), None);
+        assert_eq!(storage.get("volume"), Some(1));
+
+        storage.scoped("profile-1").clear();
+        assert_eq!(storage.scoped("profile-1").get::<u32>("volume"), None);
+        assert_eq!(
+            storage
+                .scoped("profile-1")
+                .scoped("map")
+                .get::<u32>("volume"),
+            None
+        );
+        assert_eq!(storage.get("volume"), Some(1));
+    }
+
+    #[test]
+    fn removed_keys_are_not_saved() {
+        let (mut storage, backend) =
+            storage_loading(parse("kept: 1\nremoved: 2\nrenamed_field:\n  new_name: 3"));
+        assert!(storage.is_loaded());
+
+        assert_eq!(storage.remove("removed"), Some(2));
+        assert_eq!(storage.remove::<u32>("removed"), None);
+        assert_eq!(
+            storage
+                .remove_item::<RenamedField>()
+                .map(|item| item.new_name),
+            Some(3)
+        );
+
+        storage.try_save().unwrap();
+        let StorageRequest::Save { data, .. } = backend.try_recv().unwrap() else {
+            panic!("Expected a save request");
+        };
+        let mut keys = data.keys().map(String::as_str).collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, ["kept", STORAGE_VERSION_KEY]);
+
+        storage.clear();
+        assert_eq!(storage.get::<u32>("kept"), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! Rich presence, which shows the player's friends what they are doing in the game.
+//!
+//! The [`PresenceState`] is kept up to date by the menu, session and networking code on every
+//! platform, and is published to the local Discord client when the game is built with the
+//! `discord` feature.
+
+use crate::prelude::*;
+
+#[cfg(all(feature = "discord", not(target_arch = "wasm32")))]
+mod discord;
+
+pub struct PresencePlugin;
+
+impl Plugin for PresencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PresenceState>();
+
+        #[cfg(all(feature = "discord", not(target_arch = "wasm32")))]
+        app.add_plugin(discord::DiscordPresencePlugin);
+    }
+}
+
+/// Resource containing what the player is currently doing, for rich presence.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PresenceState {
+    #[default]
+    InMenu,
+    LocalMatch {
+        /// The name of the map being played.
+        map: String,
+        players: usize,
+    },
+    OnlineMatch {
+        /// The number of players still connected to the match.
+        players: usize,
+        max_players: usize,
+    },
+}
+
+impl PresenceState {
+    /// Update the player count of an online match after a player has left it.
+    pub fn player_left(&mut self) {
+        if let Self::OnlineMatch { players, .. } = self {
+            *players = players.saturating_sub(1);
+        }
+    }
+
+    /// Update the player count of an online match after a player has rejoined it.
+    pub fn player_joined(&mut self) {
+        if let Self::OnlineMatch {
+            players,
+            max_players,
+        } = self
+        {
+            *players = (*players + 1).min(*max_players);
+        }
+    }
+
+    /// Whether `other` is a change to the same activity, like a player leaving the match, so that
+    /// the elapsed time keeps counting instead of starting over.
+    pub fn is_same_activity(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::OnlineMatch { .. }, Self::OnlineMatch { .. }) => true,
+            _ => self == other,
+        }
+    }
+
+    /// Get the line describing the activity.
+    pub fn details(&self) -> String {
+        match self {
+            Self::InMenu => "In menu".into(),
+            Self::LocalMatch { map, players } => {
+                let plural = if *players == 1 { "" } else { "s" };
+                format!("Local match on {map} ({players} player{plural})")
+            }
+            Self::OnlineMatch {
+                players,
+                max_players,
+            } => format!("Online match {players}/{max_players} players"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_leaving_is_same_online_match() {
+        let mut presence = PresenceState::OnlineMatch {
+            players: 3,
+            max_players: 4,
+        };
+        let before = presence.clone();
+        presence.player_left();
+        assert_eq!(presence.details(), "Online match 2/4 players");
+        assert!(before.is_same_activity(&presence));
+        assert!(!presence.is_same_activity(&PresenceState::InMenu));
+    }
+
+    #[test]
+    fn rejoining_players_count_up_to_the_max() {
+        let mut presence = PresenceState::OnlineMatch {
+            players: 3,
+            max_players: 4,
+        };
+        presence.player_joined();
+        presence.player_joined();
+        assert_eq!(presence.details(), "Online match 4/4 players");
+    }
+
+    #[test]
+    fn local_match_details_name_the_map() {
+        let presence = PresenceState::LocalMatch {
+            map: "Level 1".into(),
+            players: 3,
+        };
+        assert_eq!(presence.details(), "Local match on Level 1 (3 players)");
+    }
+}
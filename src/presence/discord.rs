@@ -0,0 +1,140 @@
+//! Publishes the [`PresenceState`] to the local Discord client.
+//!
+//! Talking to Discord blocks, so it is done on its own thread, which the presence is sent to
+//! whenever it changes. If Discord isn't running, or the connection to it is lost, the thread logs
+//! it once and stops, and presence updates are dropped from then on.
+
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_channel::{Receiver, Sender};
+use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
+
+use super::PresenceState;
+use crate::prelude::*;
+
+/// How long to wait between activity updates. Discord only accepts 5 updates every 20 seconds.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(4);
+
+pub struct DiscordPresencePlugin;
+
+impl Plugin for DiscordPresencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(send_presence.run_if(resource_exists::<GameMeta>()));
+    }
+}
+
+/// A presence update sent to the Discord thread, or `None` to clear the presence.
+type PresenceUpdate = Option<PresenceState>;
+
+/// Send the presence to the Discord thread when it changes, or clear it when it is turned off in
+/// the settings.
+fn send_presence(
+    mut sender: Local<Option<Sender<PresenceUpdate>>>,
+    mut last_update: Local<Option<PresenceUpdate>>,
+    presence: Res<PresenceState>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    let enabled = Settings::get_stored_or_default(&game, &mut storage).discord_presence;
+    let update = enabled.then(|| presence.clone());
+    if last_update.as_ref() == Some(&update) {
+        return;
+    }
+
+    // Don't connect to Discord until the presence has been enabled
+    if sender.is_none() && update.is_none() {
+        *last_update = Some(update);
+        return;
+    }
+
+    let sender = sender.get_or_insert_with(|| start_discord_thread(&game));
+    // Sending fails once the thread has stopped, which it has already logged
+    sender.try_send(update.clone()).ok();
+    *last_update = Some(update);
+}
+
+/// Start the thread that publishes the presence to Discord, returning the sender for the updates.
+fn start_discord_thread(game: &GameMeta) -> Sender<PresenceUpdate> {
+    let (sender, receiver) = async_channel::unbounded();
+
+    let Some(app_id) = game.discord_app_id.clone() else {
+        info!("No Discord application ID is configured, Discord rich presence is disabled");
+        receiver.close();
+        return sender;
+    };
+
+    if let Err(e) = thread::Builder::new()
+        .name("discord-presence".into())
+        .spawn(move || run_discord_thread(&app_id, receiver))
+    {
+        warn!("Couldn't start the Discord rich presence thread: {e}");
+    }
+
+    sender
+}
+
+fn run_discord_thread(app_id: &str, receiver: Receiver<PresenceUpdate>) {
+    let client = DiscordIpcClient::new(app_id).and_then(|mut client| {
+        client.connect()?;
+        Ok(client)
+    });
+    let mut client = match client {
+        Ok(client) => client,
+        Err(e) => {
+            info!("Discord is not available, Discord rich presence is disabled: {e}");
+            return;
+        }
+    };
+    info!("Connected to Discord for rich presence");
+
+    let mut shown: PresenceUpdate = None;
+    let mut started = unix_time();
+    while let Ok(mut update) = futures_lite::future::block_on(receiver.recv()) {
+        // Skip the updates that were sent while we were waiting, and only send the latest one
+        while let Ok(newer) = receiver.try_recv() {
+            update = newer;
+        }
+        if update == shown {
+            continue;
+        }
+
+        let result = match &update {
+            Some(presence) => {
+                let is_same_activity = shown
+                    .as_ref()
+                    .map_or(false, |shown| shown.is_same_activity(presence));
+                if !is_same_activity {
+                    started = unix_time();
+                }
+
+                let details = presence.details();
+                client.set_activity(
+                    activity::Activity::new()
+                        .details(&details)
+                        .timestamps(activity::Timestamps::new().start(started)),
+                )
+            }
+            None => client.clear_activity(),
+        };
+        if let Err(e) = result {
+            info!("Lost the connection to Discord, Discord rich presence is disabled: {e}");
+            return;
+        }
+        shown = update;
+
+        thread::sleep(UPDATE_INTERVAL);
+    }
+
+    client.close().ok();
+}
+
+/// Get the current time in seconds since the Unix epoch, which is how Discord takes timestamps.
+fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,286 @@
+//! Saving, loading, and playing back recorded match replays.
+
+use std::path::{Path, PathBuf};
+
+use bevy::tasks::IoTaskPool;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use jumpy_core::{
+    input::PlayerControl,
+    replay::{ReplayData, ReplayPlayback, REPLAY_FILE_EXTENSION},
+};
+
+use crate::{
+    prelude::*,
+    ui::widgets::{bordered_button::BorderedButton, bordered_frame::BorderedFrame, EguiUiExt},
+};
+
+pub struct JumpyReplayPlugin;
+
+impl Plugin for JumpyReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (save_replay_hotkey, replay_playback_overlay)
+                .distributive_run_if(in_state(EngineState::InGame))
+                .distributive_run_if(in_state(InGameState::Playing))
+                .distributive_run_if(in_state(GameEditorState::Hidden))
+                .distributive_run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Session runner that plays back a replay instead of reading local input.
+///
+/// Playback can be paused, stepped one frame at a time, and sped up, by changing how many times the
+/// session is advanced per Bevy frame.
+pub struct ReplaySessionRunner {
+    pub local: LocalSessionRunner,
+    /// Whether or not playback is paused.
+    pub paused: bool,
+    /// Whether or not playback runs at double speed.
+    pub fast_forward: bool,
+    /// Whether to advance a single frame while paused.
+    step_requested: bool,
+}
+
+impl ReplaySessionRunner {
+    pub fn new(core: CoreSession) -> Self {
+        Self {
+            local: LocalSessionRunner::new(core),
+            paused: false,
+            fast_forward: false,
+            step_requested: false,
+        }
+    }
+
+    /// Advance a single frame the next time the session runs, if playback is paused.
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    /// Whether or not all of the recorded inputs have been played back.
+    pub fn is_finished(&self) -> bool {
+        self.local
+            .core
+            .world
+            .resource::<ReplayPlayback>()
+            .borrow()
+            .is_finished()
+    }
+}
+
+impl SessionRunner for ReplaySessionRunner {
+    fn core_session(&mut self) -> &mut CoreSession {
+        &mut self.local.core
+    }
+
+    fn restart(&mut self) {
+        self.local.core.restart();
+    }
+
+    fn set_player_input(&mut self, _player_idx: usize, _control: PlayerControl) {
+        // Player inputs come from the replay, not from local input devices.
+    }
+
+    fn advance(&mut self, bevy_world: &mut World) -> Result<(), SessionError> {
+        self.local.advance(bevy_world)
+    }
+
+    fn run_criteria(&mut self, time: &Time) -> ShouldRun {
+        if self.is_finished() {
+            ShouldRun::No
+        } else if self.paused {
+            if std::mem::take(&mut self.step_requested) {
+                ShouldRun::Yes
+            } else {
+                ShouldRun::No
+            }
+        } else {
+            let speed = if self.fast_forward { 2.0 } else { 1.0 };
+            self.local
+                .run_criteria_for_delta(time.delta_seconds_f64() * speed)
+        }
+    }
+
+    fn interpolation_alpha(&self) -> f32 {
+        // Frames stepped through while paused are shown as they are
+        if self.paused || self.is_finished() {
+            1.0
+        } else {
+            self.local.interpolation_alpha()
+        }
+    }
+
+    fn network_player_idx(&mut self) -> Option<usize> {
+        None
+    }
+}
+
+/// Get the directory that replays are saved to.
+pub fn replay_dir() -> PathBuf {
+    crate::platform::data_dir().join("replays")
+}
+
+/// List the replay files in the [`replay_dir()`], newest first.
+pub fn replay_files() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(replay_dir()) else {
+        return Vec::new();
+    };
+
+    let mut files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|x| x.to_str()) == Some(REPLAY_FILE_EXTENSION))
+        .collect::<Vec<_>>();
+    // Replay file names start with a timestamp, so they sort by the time they were recorded.
+    files.sort();
+    files.reverse();
+
+    files
+}
+
+/// Load a replay from a file.
+pub fn load_replay(path: &Path) -> anyhow::Result<ReplayData> {
+    let bytes = std::fs::read(path).context("Read replay file")?;
+    let replay = ReplayData::from_bytes(&bytes)?;
+
+    Ok(replay)
+}
+
+/// Save the replay recorded so far in the given session to a timestamped file in the
+/// [`replay_dir()`].
+///
+/// The file is written in the background. Sessions that haven't recorded any frames are skipped.
+pub fn save_replay(core: &CoreSession) {
+    let data = core.replay_data();
+    if data.frames.is_empty() {
+        return;
+    }
+
+    IoTaskPool::get()
+        .spawn(async move {
+            if let Err(e) = write_replay(&data) {
+                error!("Could not save replay: {e}");
+            }
+        })
+        .detach();
+}
+
+fn write_replay(data: &ReplayData) -> std::io::Result<()> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let dir = replay_dir();
+    let file_path = dir.join(format!("replay-{timestamp}.{REPLAY_FILE_EXTENSION}"));
+
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(&file_path, data.to_bytes())?;
+    info!(?file_path, frames = data.frames.len(), "Saved replay");
+
+    Ok(())
+}
+
+/// Save the replay of the current match when F6 is pressed.
+fn save_replay_hotkey(keyboard_input: Res<Input<KeyCode>>, mut session: ResMut<Session>) {
+    if keyboard_input.just_pressed(KeyCode::F6) {
+        save_replay(session.core_session());
+    }
+}
+
+/// Render the replay playback controls, and the "Replay finished" message once the recorded inputs
+/// run out.
+fn replay_playback_overlay(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    mut contexts: EguiContexts,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let Some(replay) = session.downcast_mut::<ReplaySessionRunner>() else {
+        return;
+    };
+    let ui_theme = &game.ui_theme;
+    let ctx = contexts.ctx_mut();
+
+    if replay.is_finished() {
+        egui::Area::new("replay_finished")
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                BorderedFrame::new(&ui_theme.panel.border)
+                    .padding(ui_theme.panel.padding.into())
+                    .show(ui, |ui| {
+                        ui.vertical_centered(|ui| {
+                            let heading_font = ui_theme
+                                .font_styles
+                                .heading
+                                .colored(ui_theme.panel.font_color);
+                            ui.themed_label(&heading_font, &localization.get("replay-finished"));
+
+                            ui.add_space(heading_font.size / 2.0);
+
+                            if BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &localization.get("restart"),
+                            )
+                            .show(ui)
+                            .clicked()
+                            {
+                                replay.restart();
+                            }
+
+                            if BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &localization.get("main-menu"),
+                            )
+                            .show(ui)
+                            .clicked()
+                            {
+                                commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+                            }
+                        });
+                    });
+            });
+        return;
+    }
+
+    let font = &ui_theme.font_styles.normal;
+    egui::Area::new("replay_controls")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -font.size))
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let pause_label = if replay.paused {
+                    localization.get("replay-resume")
+                } else {
+                    localization.get("replay-pause")
+                };
+                if BorderedButton::themed(&ui_theme.button_styles.small, &pause_label)
+                    .show(ui)
+                    .clicked()
+                {
+                    replay.paused = !replay.paused;
+                }
+
+                ui.scope(|ui| {
+                    ui.set_enabled(replay.paused);
+                    if BorderedButton::themed(
+                        &ui_theme.button_styles.small,
+                        &localization.get("replay-step"),
+                    )
+                    .show(ui)
+                    .clicked()
+                    {
+                        replay.step();
+                    }
+                });
+
+                let speed_label = if replay.fast_forward { "2x" } else { "1x" };
+                if BorderedButton::themed(&ui_theme.button_styles.small, speed_label)
+                    .show(ui)
+                    .clicked()
+                {
+                    replay.fast_forward = !replay.fast_forward;
+                }
+            });
+        });
+}
@@ -0,0 +1,591 @@
+//! Screenshot capture.
+//!
+//! When the [`MenuAction::Screenshot`] key is pressed, the game cameras are cloned for a couple of
+//! frames to render the game into an offscreen image, which is copied to a buffer by a node in the
+//! render graph. The buffer is mapped asynchronously, so that reading it back never blocks the GPU,
+//! and the pixels are sent back to the main world to be encoded and saved in the background.
+//!
+//! Screenshots are saved to the `screenshots` folder in the platform data directory, or downloaded
+//! by the browser on web.
+
+use std::num::NonZeroU32;
+
+use async_channel::{Receiver, Sender};
+use bevy::{
+    ecs::schedule::common_conditions::not,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssets,
+        render_graph::{self, RenderGraph},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice},
+        view::RenderLayers,
+        Extract, ExtractSchedule, RenderApp, RenderSet,
+    },
+    tasks::IoTaskPool,
+    window::{PrimaryWindow, WindowRef},
+};
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use image::ImageEncoder;
+
+use crate::{
+    prelude::*,
+    ui::{
+        photo_mode::PhotoMode,
+        widgets::{bordered_frame::BorderedFrame, EguiUiExt},
+    },
+};
+
+/// How many frames the screenshot cameras render before the image is captured, so that the image
+/// has been prepared on the GPU by the time it is copied.
+const CAPTURE_FRAME: u32 = 1;
+
+/// How long the toast confirming a screenshot stays on screen.
+const TOAST_DURATION: f32 = 5.0;
+
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        let (frame_sender, frame_receiver) = async_channel::unbounded();
+        let (saved_sender, saved_receiver) = async_channel::unbounded();
+
+        app.init_resource::<ScreenshotCapture>()
+            .init_resource::<ScreenshotToast>()
+            .insert_resource(ScreenshotChannels {
+                frame_receiver,
+                saved_sender,
+                saved_receiver,
+            })
+            .add_systems((
+                apply_screenshot_key.run_if(resource_added::<GameMeta>()),
+                capture_screenshot.run_if(resource_exists::<GameMeta>()),
+                save_screenshots,
+                screenshot_toast
+                    .run_if(resource_exists::<GameMeta>())
+                    .run_if(not(resource_exists::<PhotoMode>())),
+            ));
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else { return };
+        render_app
+            .insert_resource(RenderScreenshots {
+                frame_sender,
+                requested: None,
+                copying: None,
+                mapping: Vec::new(),
+            })
+            .add_system(extract_screenshot.in_schedule(ExtractSchedule))
+            .add_system(prepare_screenshot_buffer.in_set(RenderSet::Prepare))
+            .add_system(read_screenshot_buffers.in_set(RenderSet::Cleanup));
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(ScreenshotNode::NAME, ScreenshotNode);
+        render_graph.add_node_edge(
+            bevy::render::main_graph::node::CAMERA_DRIVER,
+            ScreenshotNode::NAME,
+        );
+    }
+}
+
+/// Marker component for the cameras that render a screenshot.
+#[derive(Component)]
+pub struct ScreenshotCamera;
+
+/// The screenshot that is being rendered, if any.
+#[derive(Resource, Default)]
+struct ScreenshotCapture(Option<PendingScreenshot>);
+
+struct PendingScreenshot {
+    /// The image the screenshot cameras render to.
+    image: Handle<Image>,
+    /// The screenshot cameras.
+    cameras: Vec<Entity>,
+    /// How many frames the screenshot cameras have rendered.
+    frames: u32,
+}
+
+/// The pixels of a captured screenshot, sent from the render world.
+struct CapturedFrame {
+    width: u32,
+    height: u32,
+    /// The sRGB pixels, with 4 bytes per pixel and no padding between rows.
+    pixels: Vec<u8>,
+}
+
+#[derive(Resource)]
+struct ScreenshotChannels {
+    frame_receiver: Receiver<CapturedFrame>,
+    saved_sender: Sender<Result<String, String>>,
+    saved_receiver: Receiver<Result<String, String>>,
+}
+
+/// The message confirming the last screenshot, and when it was first shown.
+#[derive(Resource, Default)]
+struct ScreenshotToast(Option<(String, Option<f32>)>);
+
+/// Bind the screenshot key from the settings once the game is loaded.
+///
+/// The settings menu binds it again when the settings are saved.
+fn apply_screenshot_key(
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    mut menu_input_maps: Query<&mut InputMap<MenuAction>>,
+) {
+    let settings = Settings::get_stored_or_default(&game, &mut storage);
+    for mut input_map in &mut menu_input_maps {
+        bind_screenshot_key(&mut input_map, settings.screenshot_key);
+    }
+}
+
+/// Start rendering a screenshot when the screenshot key is pressed, and clean up the screenshot
+/// cameras once it has been captured.
+fn capture_screenshot(
+    mut commands: Commands,
+    mut capture: ResMut<ScreenshotCapture>,
+    mut images: ResMut<Assets<Image>>,
+    menu_input: Query<&ActionState<MenuAction>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<
+        (
+            &Camera,
+            &Camera2d,
+            &Transform,
+            &OrthographicProjection,
+            Option<&RenderLayers>,
+        ),
+        Without<ScreenshotCamera>,
+    >,
+) {
+    if let Some(pending) = &mut capture.0 {
+        pending.frames += 1;
+        // The buffer copy has been requested in the last frame, so the cameras aren't needed
+        // anymore.
+        if pending.frames > CAPTURE_FRAME {
+            for camera in &pending.cameras {
+                commands.entity(*camera).despawn();
+            }
+            capture.0 = None;
+        }
+        return;
+    }
+
+    let Ok(menu_input) = menu_input.get_single() else { return };
+    if !menu_input.just_pressed(MenuAction::Screenshot) {
+        return;
+    }
+    let Ok(window) = windows.get_single() else { return };
+    let size = Extent3d {
+        width: window.physical_width(),
+        height: window.physical_height(),
+        depth_or_array_layers: 1,
+    };
+    if size.width == 0 || size.height == 0 {
+        return;
+    }
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("screenshot"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    let image = images.add(image);
+
+    // Render the same view as every camera that renders to the window
+    let cameras = cameras
+        .iter()
+        .filter(|(camera, ..)| {
+            camera.is_active && matches!(camera.target, RenderTarget::Window(WindowRef::Primary))
+        })
+        .map(
+            |(camera, camera_2d, transform, projection, render_layers)| {
+                let mut entity = commands.spawn((
+                    Name::new("Screenshot Camera"),
+                    ScreenshotCamera,
+                    Camera2dBundle {
+                        camera: Camera {
+                            target: RenderTarget::Image(image.clone()),
+                            ..camera.clone()
+                        },
+                        camera_2d: camera_2d.clone(),
+                        transform: *transform,
+                        projection: projection.clone(),
+                        ..default()
+                    },
+                ));
+                if let Some(render_layers) = render_layers {
+                    entity.insert(*render_layers);
+                }
+                entity.id()
+            },
+        )
+        .collect();
+
+    capture.0 = Some(PendingScreenshot {
+        image,
+        cameras,
+        frames: 0,
+    });
+}
+
+/// Encode and save the screenshots captured by the render world in the background.
+fn save_screenshots(
+    channels: Res<ScreenshotChannels>,
+    mut toast: ResMut<ScreenshotToast>,
+    localization: Res<Localization>,
+) {
+    while let Ok(frame) = channels.frame_receiver.try_recv() {
+        let saved_sender = channels.saved_sender.clone();
+        let file_name = format!("screenshot-{}", timestamp(unix_time()));
+        IoTaskPool::get()
+            .spawn(async move {
+                let result = encode_png(&frame)
+                    .map_err(|e| e.to_string())
+                    .and_then(|png| backend::save_screenshot(&file_name, &png));
+                saved_sender.try_send(result).ok();
+            })
+            .detach();
+    }
+
+    while let Ok(result) = channels.saved_receiver.try_recv() {
+        let message = match result {
+            Ok(path) => {
+                info!(%path, "Saved screenshot");
+                format!("{}\n{path}", localization.get("screenshot-saved"))
+            }
+            Err(e) => {
+                error!("Error saving screenshot: {e}");
+                format!("{}\n{e}", localization.get("screenshot-failed"))
+            }
+        };
+        toast.0 = Some((message, None));
+    }
+}
+
+/// Show a toast with the path that the last screenshot was saved to.
+fn screenshot_toast(
+    mut toast: ResMut<ScreenshotToast>,
+    time: Res<Time>,
+    game: Res<GameMeta>,
+    mut contexts: EguiContexts,
+) {
+    let Some((message, shown_at)) = &mut toast.0 else { return };
+    let shown_at = *shown_at.get_or_insert(time.elapsed_seconds());
+    if time.elapsed_seconds() - shown_at > TOAST_DURATION {
+        toast.0 = None;
+        return;
+    }
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("screenshot_toast")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(font.size, -font.size))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&font, message);
+                });
+        });
+}
+
+/// Encode the pixels of a captured frame as a PNG image.
+fn encode_png(frame: &CapturedFrame) -> image::ImageResult<Vec<u8>> {
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png).write_image(
+        &frame.pixels,
+        frame.width,
+        frame.height,
+        image::ColorType::Rgba8,
+    )?;
+    Ok(png)
+}
+
+/// Format a Unix time as a UTC date and time that can be used in file names and sorts in order,
+/// like `2023-11-14_22-13-20`.
+fn timestamp(unix_time: u64) -> String {
+    let (days, seconds) = (unix_time / 86400, unix_time % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Get the year, month and day of a number of days since the Unix epoch.
+///
+/// This is the `civil_from_days` algorithm from
+/// <http://howardhinnant.github.io/date_algorithms.html>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// The render world's side of the screenshot capture.
+#[derive(Resource)]
+struct RenderScreenshots {
+    /// Sends the captured frames to the main world.
+    frame_sender: Sender<CapturedFrame>,
+    /// The image to capture this frame.
+    requested: Option<Handle<Image>>,
+    /// The buffer that the image is copied to this frame.
+    copying: Option<ScreenshotBuffer>,
+    /// The buffers that are being mapped to be read, with the receiver for the mapping result.
+    mapping: Vec<(ScreenshotBuffer, Receiver<bool>)>,
+}
+
+struct ScreenshotBuffer {
+    texture: Texture,
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    /// The size of a row in the buffer, which is padded to the alignment required for copies.
+    padded_bytes_per_row: usize,
+}
+
+/// Get the screenshot image to capture this frame from the main world.
+fn extract_screenshot(
+    mut screenshots: ResMut<RenderScreenshots>,
+    capture: Extract<Res<ScreenshotCapture>>,
+) {
+    screenshots.requested = capture
+        .0
+        .as_ref()
+        .filter(|pending| pending.frames == CAPTURE_FRAME)
+        .map(|pending| pending.image.clone());
+}
+
+/// Create the buffer that the screenshot image is copied to.
+fn prepare_screenshot_buffer(
+    mut screenshots: ResMut<RenderScreenshots>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+) {
+    let Some(image) = screenshots.requested.take() else { return };
+    let Some(gpu_image) = images.get(&image) else {
+        warn!("Screenshot image wasn't ready to be captured");
+        return;
+    };
+
+    let width = gpu_image.size.x as u32;
+    let height = gpu_image.size.y as u32;
+    let padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(width as usize * 4);
+    let buffer = render_device.create_buffer(&BufferDescriptor {
+        label: Some("screenshot_buffer"),
+        size: (padded_bytes_per_row * height as usize) as u64,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    screenshots.copying = Some(ScreenshotBuffer {
+        texture: gpu_image.texture.clone(),
+        buffer,
+        width,
+        height,
+        padded_bytes_per_row,
+    });
+}
+
+/// Render graph node that copies the screenshot image to its buffer, after the cameras have
+/// rendered.
+struct ScreenshotNode;
+
+impl ScreenshotNode {
+    const NAME: &str = "screenshot";
+}
+
+impl render_graph::Node for ScreenshotNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(copy) = &world.resource::<RenderScreenshots>().copying else { return Ok(()) };
+
+        render_context.command_encoder().copy_texture_to_buffer(
+            copy.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &copy.buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(copy.padded_bytes_per_row as u32),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: copy.width,
+                height: copy.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+/// Start mapping the buffer copied this frame, and send the buffers that have finished mapping to
+/// the main world.
+///
+/// The device is only polled, never waited on, so reading a screenshot back doesn't stall
+/// rendering.
+fn read_screenshot_buffers(
+    mut screenshots: ResMut<RenderScreenshots>,
+    render_device: Res<RenderDevice>,
+) {
+    if let Some(copy) = screenshots.copying.take() {
+        let (mapped_sender, mapped_receiver) = async_channel::bounded(1);
+        copy.buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                if let Err(e) = &result {
+                    error!("Error mapping screenshot buffer: {e}");
+                }
+                mapped_sender.try_send(result.is_ok()).ok();
+            });
+        screenshots.mapping.push((copy, mapped_receiver));
+    }
+    if screenshots.mapping.is_empty() {
+        return;
+    }
+
+    render_device.poll(Maintain::Poll);
+
+    let frame_sender = screenshots.frame_sender.clone();
+    screenshots
+        .mapping
+        .retain(|(copy, mapped_receiver)| match mapped_receiver.try_recv() {
+            Ok(true) => {
+                let row_bytes = copy.width as usize * 4;
+                let mut pixels = Vec::with_capacity(row_bytes * copy.height as usize);
+                {
+                    let data = copy.buffer.slice(..).get_mapped_range();
+                    for row in data.chunks(copy.padded_bytes_per_row) {
+                        pixels.extend_from_slice(&row[..row_bytes]);
+                    }
+                }
+                copy.buffer.unmap();
+
+                frame_sender
+                    .try_send(CapturedFrame {
+                        width: copy.width,
+                        height: copy.height,
+                        pixels,
+                    })
+                    .ok();
+                false
+            }
+            Ok(false) => false,
+            Err(_) => true,
+        });
+}
+
+/// Screenshots are saved in the data directory.
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::{fs, io::Write};
+
+    /// Save a screenshot, returning the path it was saved to.
+    ///
+    /// If there already is a screenshot with the same name, a number is added to the name.
+    pub(super) fn save_screenshot(file_name: &str, png: &[u8]) -> Result<String, String> {
+        let dir = crate::platform::data_dir().join("screenshots");
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+        for n in 0.. {
+            let path = if n == 0 {
+                dir.join(format!("{file_name}.png"))
+            } else {
+                dir.join(format!("{file_name}-{n}.png"))
+            };
+            // Only create new files, so that screenshots are never overwritten, even if two of them
+            // are saved at the same time.
+            let mut file = match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.to_string()),
+            };
+            file.write_all(png).map_err(|e| e.to_string())?;
+
+            return Ok(path.display().to_string());
+        }
+
+        unreachable!()
+    }
+}
+
+/// Screenshots are downloaded by the browser.
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use wasm_bindgen::JsCast;
+
+    /// Have the browser download a screenshot, returning the file name.
+    pub(super) fn save_screenshot(file_name: &str, png: &[u8]) -> Result<String, String> {
+        let file_name = format!("{file_name}.png");
+        download(&file_name, png).map_err(|e| format!("{e:?}"))?;
+        Ok(file_name)
+    }
+
+    fn download(file_name: &str, contents: &[u8]) -> Result<(), wasm_bindgen::JsValue> {
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&js_sys::Array::of1(
+            &js_sys::Uint8Array::from(contents),
+        ))?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+        let document = web_sys::window().unwrap().document().unwrap();
+        let link: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+        link.set_href(&url);
+        link.set_download(file_name);
+        link.click();
+
+        web_sys::Url::revoke_object_url(&url)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        assert_eq!(timestamp(0), "1970-01-01_00-00-00");
+        assert_eq!(timestamp(1700000000), "2023-11-14_22-13-20");
+        assert_eq!(timestamp(951868799), "2000-02-29_23-59-59");
+        assert_eq!(timestamp(4102444800), "2100-01-01_00-00-00");
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::utils::Instant;
+use bevy::ecs::schedule::common_conditions::not;
 use downcast_rs::{impl_downcast, Downcast};
 use jumpy_core::input::PlayerControl;
 
@@ -19,6 +19,8 @@ impl Plugin for JumpySessionPlugin {
         let mut session_schedule = Schedule::new();
         session_schedule.add_systems((
             ensure_2_players,
+            apply_camera_shake_setting,
+            apply_kill_cam_setting,
             collect_local_input.pipe(update_game),
             play_sounds,
         ));
@@ -29,20 +31,30 @@ impl Plugin for JumpySessionPlugin {
             .configure_set(
                 SessionStage::Update
                     .before(CoreSet::Update)
-                    .run_if(in_state(InGameState::Playing))
+                    .run_if(not(resource_exists::<Paused>()))
                     .run_if(in_state(EngineState::InGame))
                     .run_if(resource_exists::<Session>()),
             )
             .add_system(move |world: &mut World| {
+                // The pause menu only stops the session from advancing in local games, by
+                // inserting the `Paused` resource. Since a pause can only start between session
+                // updates, a frame is never interrupted half-way, so no game events are lost.
                 let in_correct_state = {
                     world.resource::<State<EngineState>>().0 == EngineState::InGame
-                        && world.resource::<State<InGameState>>().0 == InGameState::Playing
+                        && !world.contains_resource::<Paused>()
                 };
 
                 if !in_correct_state || !world.contains_resource::<Session>() {
                     return;
                 }
 
+                // Put back the transforms that were interpolated for the last render, before the
+                // session is advanced or saved.
+                world
+                    .resource_mut::<Session>()
+                    .core_session()
+                    .restore_transforms();
+
                 loop {
                     let should_run =
                         world.resource_scope(|world: &mut World, mut session: Mut<Session>| {
@@ -60,7 +72,19 @@ impl Plugin for JumpySessionPlugin {
                         }
                     }
                 }
+
+                // Show the session between its last two frames, according to how much time has
+                // passed since the last one, so that it moves smoothly at any frame rate.
+                if let Some(mut session) = world.get_resource_mut::<Session>() {
+                    let alpha = session.interpolation_alpha();
+                    session.core_session().interpolate_transforms(alpha);
+                }
             });
+
+        // Reload modified element metadata into the running session
+        if ENGINE_CONFIG.hot_reload {
+            app.add_system(reload_modified_elements.run_if(resource_exists::<Session>()));
+        }
     }
 }
 
@@ -68,6 +92,10 @@ impl Plugin for JumpySessionPlugin {
 #[derive(Resource, Deref, DerefMut)]
 pub struct Session(pub Box<dyn SessionRunner>);
 
+/// While this resource exists the game session will not be advanced.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct Paused;
+
 pub trait SessionRunner: Sync + Send + Downcast {
     fn core_session(&mut self) -> &mut CoreSession;
     fn world(&mut self) -> &mut bones::World {
@@ -81,6 +109,11 @@ pub trait SessionRunner: Sync + Send + Downcast {
     fn set_player_input(&mut self, player_idx: usize, control: PlayerControl);
     fn advance(&mut self, bevy_world: &mut World) -> Result<(), SessionError>;
     fn run_criteria(&mut self, time: &Time) -> ShouldRun;
+    /// How far the time that hasn't been simulated yet is towards the next frame, from `0.0` to
+    /// `1.0`, which is how far between the last two frames the session is shown.
+    fn interpolation_alpha(&self) -> f32 {
+        1.0
+    }
     /// Returns the player index of the player if we are in a network game.
     ///
     /// In a network game, we currently only allow for one local player, so this allows the session
@@ -96,23 +129,93 @@ pub enum SessionError {
     Disconnected,
 }
 
+/// The most frames that a session may be advanced in one Bevy frame, while catching up after the
+/// game was slowed down.
+///
+/// Once this many frames were simulated, the rest of the time is dropped, so that a long hitch
+/// doesn't make the following frames take longer and longer to catch up.
+pub const MAX_FRAME_STEPS: u32 = 8;
+
+/// Fixed-update timing, which accumulates real time and turns it into frames of exactly `1 /`
+/// [`FPS`][jumpy_core::FPS] seconds.
+///
+/// Slow Bevy frames may advance the session several times, and fast ones may not advance it at
+/// all.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FixedTimestep {
+    /// The time that hasn't been simulated yet, in seconds.
+    pub accumulator: f64,
+    /// The number of frames advanced in the current Bevy frame, or [`None`] before the first check
+    /// of the frame.
+    frame_steps: Option<u32>,
+}
+
+impl FixedTimestep {
+    /// The length of a frame, in seconds.
+    pub const STEP: f64 = 1.0 / jumpy_core::FPS as f64;
+
+    /// Check whether to advance another frame, given the amount of time that has elapsed since the
+    /// last Bevy frame.
+    ///
+    /// This is checked in a loop every Bevy frame, until it returns [`ShouldRun::No`].
+    pub fn check(&mut self, delta: f64) -> ShouldRun {
+        if self.frame_steps.is_none() {
+            self.accumulator += delta;
+        }
+        let steps = self.frame_steps.get_or_insert(0);
+
+        if self.accumulator < Self::STEP {
+            self.frame_steps = None;
+            ShouldRun::No
+        } else if *steps >= MAX_FRAME_STEPS {
+            warn!("Frame took too long: couldn't keep up with fixed update.");
+            // Keep the part of a frame that is left, so the session is still shown between frames
+            self.accumulator %= Self::STEP;
+            self.frame_steps = None;
+            ShouldRun::No
+        } else {
+            *steps += 1;
+            self.accumulator -= Self::STEP;
+            ShouldRun::YesAndCheckAgain
+        }
+    }
+
+    /// How far the time that hasn't been simulated yet is towards the next frame.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / Self::STEP).min(1.0) as f32
+    }
+}
+
 pub struct LocalSessionRunner {
     pub core: CoreSession,
-    pub accumulator: f64,
-    pub loop_start: Option<Instant>,
+    pub timestep: FixedTimestep,
+    /// How fast the game runs compared to real time, used to slow the game down in training mode.
+    pub time_scale: f64,
+    /// Whether the game is frozen, only advancing when frames are stepped through with
+    /// [`step_frames`][Self::step_frames].
+    pub frozen: bool,
+    /// How many frames to advance while the game is frozen, one per Bevy frame.
+    pub step_frames: u32,
 }
 
 impl LocalSessionRunner {
-    fn new(core: CoreSession) -> Self
+    pub fn new(core: CoreSession) -> Self
     where
         Self: Sized,
     {
         LocalSessionRunner {
             core,
-            accumulator: default(),
-            loop_start: default(),
+            timestep: default(),
+            time_scale: 1.0,
+            frozen: false,
+            step_frames: 0,
         }
     }
+
+    /// Fixed-update run criteria, given the amount of time that has elapsed since the last check.
+    pub fn run_criteria_for_delta(&mut self, delta: f64) -> ShouldRun {
+        self.timestep.check(delta)
+    }
 }
 
 /// Indicates whether or not a session advance should be run.
@@ -144,29 +247,22 @@ impl SessionRunner for LocalSessionRunner {
         Ok(())
     }
     fn run_criteria(&mut self, time: &Time) -> ShouldRun {
-        const STEP: f64 = 1.0 / jumpy_core::FPS as f64;
-        let delta = time.delta_seconds_f64();
-        if self.loop_start.is_none() {
-            self.accumulator += delta;
-        }
-
-        if self.accumulator >= STEP {
-            let start = self.loop_start.get_or_insert_with(Instant::now);
-
-            let loop_too_long = (Instant::now() - *start).as_secs_f64() > STEP;
-
-            if loop_too_long {
-                warn!("Frame took too long: couldn't keep up with fixed update.");
-                self.accumulator = 0.0;
-                self.loop_start = None;
-                ShouldRun::No
+        if self.frozen {
+            return if self.step_frames > 0 {
+                self.step_frames -= 1;
+                ShouldRun::Yes
             } else {
-                self.accumulator -= STEP;
-                ShouldRun::YesAndCheckAgain
-            }
+                ShouldRun::No
+            };
+        }
+        self.run_criteria_for_delta(time.delta_seconds_f64() * self.time_scale)
+    }
+    fn interpolation_alpha(&self) -> f32 {
+        // Frames stepped through while frozen are shown as they are
+        if self.frozen {
+            1.0
         } else {
-            self.loop_start = None;
-            ShouldRun::No
+            self.timestep.alpha()
         }
     }
     fn network_player_idx(&mut self) -> Option<usize> {
@@ -192,7 +288,11 @@ pub struct SessionManager<'w, 's> {
 
 impl<'w, 's> SessionManager<'w, 's> {
     /// Start a game session
-    pub fn start_local(&mut self, info: CoreSessionInfo) {
+    pub fn start_local(&mut self, mut info: CoreSessionInfo) {
+        // Local sessions can be made reproducible with the `--seed` flag
+        if let Some(seed) = ENGINE_CONFIG.seed {
+            info.seed = seed;
+        }
         let session = Session(Box::new(LocalSessionRunner::new(CoreSession::new(info))));
         self.commands.insert_resource(session);
         self.menu_camera.for_each_mut(|mut x| x.is_active = false);
@@ -216,15 +316,40 @@ impl<'w, 's> SessionManager<'w, 's> {
             .insert_resource(NextState(Some(EngineState::InGame)));
     }
 
+    /// Start a session that plays back a recorded replay
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_replay(&mut self, replay: jumpy_core::replay::ReplayData) {
+        let core = CoreSession::new_replay(self.core_meta_arc.0.clone(), replay);
+        let session = Session(Box::new(crate::replay::ReplaySessionRunner::new(core)));
+        self.commands.insert_resource(session);
+        self.menu_camera.for_each_mut(|mut x| x.is_active = false);
+    }
+
     /// Restart a game session without changing the settings
     pub fn restart(&mut self) {
         if let Some(session) = self.session.as_mut() {
+            #[cfg(not(target_arch = "wasm32"))]
+            crate::replay::save_replay(session.core_session());
+            session.restart();
+        }
+    }
+
+    /// Restart a game session on a different map, keeping the rest of the settings
+    pub fn restart_with_map(&mut self, map_meta: MapMeta) {
+        if let Some(session) = self.session.as_mut() {
+            #[cfg(not(target_arch = "wasm32"))]
+            crate::replay::save_replay(session.core_session());
+            session.core_session().info.map_meta = map_meta;
             session.restart();
         }
     }
 
     /// Stop a game session
     pub fn stop(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(session) = self.session.as_mut() {
+            crate::replay::save_replay(session.core_session());
+        }
         self.commands.remove_resource::<Session>();
         self.menu_camera.for_each_mut(|mut x| x.is_active = true);
     }
@@ -249,13 +374,67 @@ fn ensure_2_players(mut session: ResMut<Session>, core_meta: Res<CoreMetaArc>) {
     }
 }
 
+/// How many seconds a player has to tap a direction again for it to count as a double tap.
+const DOUBLE_TAP_WINDOW: f32 = 0.25;
+
+/// Keeps track of a local player tapping left and right, to dash when they double tap.
+#[derive(Default, Clone, Copy)]
+struct DoubleTapDash {
+    /// The horizontal direction being held, or `0` if neither left nor right is held.
+    held: i8,
+    /// The direction of the last tap, and the time that it was let go of.
+    last_tap: Option<(i8, f32)>,
+    /// Whether the direction being held is the second tap of a double tap, which holds the dash
+    /// button until it is let go of.
+    dashing: bool,
+}
+
+impl DoubleTapDash {
+    /// Update the held direction, returning whether the dash button should be held.
+    fn update(&mut self, move_x: f32, now: f32) -> bool {
+        let direction = if move_x > 0.5 {
+            1
+        } else if move_x < -0.5 {
+            -1
+        } else {
+            0
+        };
+
+        if direction != self.held {
+            if self.held != 0 {
+                self.last_tap = Some((self.held, now));
+            }
+            self.dashing = direction != 0
+                && matches!(
+                    self.last_tap,
+                    Some((tapped, time)) if tapped == direction && now - time <= DOUBLE_TAP_WINDOW
+                );
+            if self.dashing {
+                self.last_tap = None;
+            }
+            self.held = direction;
+        }
+
+        self.dashing
+    }
+}
+
 /// Update the input to the game session.
 fn collect_local_input(
     mut session: ResMut<Session>,
     player_input_collectors: Query<(&PlayerInputCollector, &ActionState<PlayerAction>)>,
     mut current_editor_input: ResMut<CurrentEditorInput>,
+    disable_menu_input: Res<DisableMenuInput>,
+    in_game_state: Res<State<InGameState>>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    time: Res<Time>,
+    mut double_taps: Local<[DoubleTapDash; MAX_PLAYERS]>,
 ) {
     let network_player_idx = session.network_player_idx();
+    let double_tap_dash = Settings::get_stored_or_default(&game, &mut storage).double_tap_dash;
+    // Online games keep running while the pause menu is open
+    let is_paused = in_game_state.0 == InGameState::Paused;
 
     if let Some(local_session) = session.downcast_mut::<LocalSessionRunner>() {
         // TODO: Handle editor input for non-local sessions.
@@ -270,7 +449,16 @@ fn collect_local_input(
             continue;
         }
 
-        let mut control = session.0.get_player_input(player_idx.0);
+        let player_idx = network_player_idx.unwrap_or(player_idx.0);
+
+        // Don't control the player while typing in a text box, such as the chat, or while using
+        // the pause menu.
+        if **disable_menu_input || is_paused {
+            session.set_player_input(player_idx, default());
+            continue;
+        }
+
+        let mut control = session.0.get_player_input(player_idx);
 
         let jump_pressed = action_state.pressed(PlayerAction::Jump);
         control.jump_just_pressed = jump_pressed && !control.jump_pressed;
@@ -284,12 +472,23 @@ fn collect_local_input(
         control.shoot_just_pressed = shoot_pressed && !control.shoot_pressed;
         control.shoot_pressed = shoot_pressed;
 
+        let emote_pressed = action_state.pressed(PlayerAction::Emote);
+        control.emote_just_pressed = emote_pressed && !control.emote_pressed;
+        control.emote_pressed = emote_pressed;
+
         let was_moving = control.move_direction.length_squared() > f32::MIN_POSITIVE;
         control.move_direction = action_state.axis_pair(PlayerAction::Move).unwrap().xy();
         let is_moving = control.move_direction.length_squared() > f32::MIN_POSITIVE;
         control.just_moved = !was_moving && is_moving;
 
-        session.set_player_input(network_player_idx.unwrap_or(player_idx.0), control);
+        let double_tapped =
+            double_taps[player_idx].update(control.move_direction.x, time.elapsed_seconds());
+        let dash_pressed =
+            action_state.pressed(PlayerAction::Dash) || (double_tap_dash && double_tapped);
+        control.dash_just_pressed = dash_pressed && !control.dash_pressed;
+        control.dash_pressed = dash_pressed;
+
+        session.set_player_input(player_idx, control);
     }
 }
 
@@ -320,27 +519,153 @@ fn update_game(world: &mut World) {
     }
 }
 
+/// Forward the element metadata assets that were modified on disk to the game session, so that the
+/// elements in the running game are updated.
+///
+/// This only reloads elements in local games, since network games and replays have to play out
+/// the same way for everybody.
+fn reload_modified_elements(
+    mut session: ResMut<Session>,
+    mut element_events: EventReader<AssetEvent<ElementMeta>>,
+) {
+    let Some(local_session) = session.downcast_mut::<LocalSessionRunner>() else {
+        element_events.clear();
+        return;
+    };
+    for event in element_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            local_session.core.reload_element(handle.id());
+        }
+    }
+}
+
+/// Turn the game session's camera shake on or off, according to the local player's settings.
+fn apply_camera_shake_setting(
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    let camera_shake = Settings::get_stored_or_default(&game, &mut storage).camera_shake;
+    session
+        .world()
+        .run_initialized_system(
+            move |mut camera_trauma: bones::ResMut<jumpy_core::camera::CameraTrauma>| {
+                camera_trauma.shake_enabled = camera_shake;
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+/// Turn the game session's kill cam on or off, according to the local player's settings.
+///
+/// The kill cam slows down the game, so network games always play it, to keep every player on the
+/// same frame.
+fn apply_kill_cam_setting(
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+) {
+    let kill_cam = session.network_player_idx().is_some()
+        || Settings::get_stored_or_default(&game, &mut storage).kill_cam;
+    session
+        .world()
+        .run_initialized_system(
+            move |mut kill_cam_res: bones::ResMut<jumpy_core::camera::KillCam>| {
+                kill_cam_res.enabled = kill_cam;
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
 /// Play sounds from the game session.
-fn play_sounds(audio: Res<AudioChannel<EffectsChannel>>, mut session: ResMut<Session>) {
-    // Get the sound queue out of the world
-    let queue = session
+///
+/// Sounds that come from a position in the world are panned and attenuated according to where they
+/// are relative to the camera's view, unless positional audio is disabled in the settings.
+fn play_sounds(
+    audio: Res<AudioChannel<EffectsChannel>>,
+    effects_volume: Res<EffectsVolume>,
+    positional_audio: Res<PositionalAudio>,
+    mut session: ResMut<Session>,
+) {
+    // Get the sound queue and the camera's view out of the world
+    let (queue, view) = session
         .world()
-        .run_initialized_system(move |mut audio_events: bones::ResMut<bones::AudioEvents>| {
-            Ok(audio_events.queue.drain(..).collect::<Vec<_>>())
-        })
+        .run_initialized_system(
+            move |mut sound_events: bones::ResMut<jumpy_core::audio::SoundEvents>,
+                  entities: bones::Res<bones::Entities>,
+                  cameras: bones::Comp<bones::Camera>,
+                  transforms: bones::Comp<bones::Transform>,
+                  window: bones::Res<bones::Window>| {
+                // The view follows the camera's zoom, which is set through its height
+                let view = entities.iter_with((&cameras, &transforms)).next().map(
+                    |(_ent, (camera, transform))| {
+                        let viewport_size = camera
+                            .viewport
+                            .map(|x| x.size.as_vec2())
+                            .unwrap_or(window.size);
+                        let aspect = viewport_size.x / viewport_size.y.max(1.0);
+                        let view_size = Vec2::new(camera.height * aspect, camera.height);
+                        (transform.translation.truncate(), view_size)
+                    },
+                );
+                Ok((sound_events.queue.drain(..).collect::<Vec<_>>(), view))
+            },
+        )
         .unwrap();
 
     // Play all the sounds in the queue
     for event in queue {
-        match event {
-            bones::AudioEvent::PlaySound {
-                sound_source,
-                volume,
-            } => {
-                audio
-                    .play(sound_source.get_bevy_handle_untyped().typed())
-                    .with_volume(volume);
+        let (panning, volume) = match (event.position, view) {
+            (Some(position), Some((view_center, view_size))) if **positional_audio => {
+                let Some(positional) = positional_sound(view_center, view_size, position) else {
+                    continue;
+                };
+                positional
             }
+            _ => (0.5, 1.0),
+        };
+        audio
+            .play(event.sound_source.get_bevy_handle_untyped().typed())
+            .with_volume(event.volume * volume * **effects_volume)
+            .with_panning(panning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Count the frames that are advanced in a Bevy frame that took `delta` seconds.
+    fn frames_advanced(timestep: &mut FixedTimestep, delta: f64) -> u32 {
+        let mut frames = 0;
+        while timestep.check(delta) != ShouldRun::No {
+            frames += 1;
         }
+        frames
+    }
+
+    #[test]
+    fn fixed_timestep_follows_real_time() {
+        let mut timestep = FixedTimestep::default();
+
+        // A 144 Hz display only advances on some of its frames
+        let frames = (0..144)
+            .map(|_| frames_advanced(&mut timestep, 1.0 / 144.0))
+            .sum::<u32>();
+        assert!((59..=60).contains(&frames));
+        assert!(timestep.alpha() < 1.0);
+
+        // A slow frame advances several times
+        assert_eq!(frames_advanced(&mut timestep, FixedTimestep::STEP * 3.0), 3);
+    }
+
+    #[test]
+    fn fixed_timestep_limits_catching_up() {
+        let mut timestep = FixedTimestep::default();
+        assert_eq!(frames_advanced(&mut timestep, 2.0), MAX_FRAME_STEPS);
+        assert!(timestep.accumulator < FixedTimestep::STEP);
+        assert_eq!(frames_advanced(&mut timestep, 0.0), 0);
     }
 }
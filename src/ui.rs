@@ -1,20 +1,42 @@
 use bevy::{
     ecs::system::SystemState,
-    window::{PrimaryWindow, WindowMode},
+    window::{PresentMode, PrimaryWindow, WindowMode},
 };
 use bevy_egui::EguiContexts;
 
 use crate::prelude::*;
 
-pub use self::ui_input::MenuAction;
+pub use self::ui_input::{
+    bind_screenshot_key, is_reserved_screenshot_key, MenuAction, MenuNavigator, NavDirection,
+};
 
 pub mod ui_input;
 pub mod widgets;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod chat;
+pub mod collision_debug;
+pub mod countdown;
 pub mod debug_tools;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod desync_toast;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod disconnected_players;
 pub mod editor;
+pub mod hud;
 pub mod main_menu;
+pub mod map_loading;
+pub mod map_thumbnails;
+pub mod map_validation;
 pub mod pause_menu;
+pub mod photo_mode;
+pub mod player_indicators;
+pub mod spectating;
+pub mod stocks;
+pub mod storage_warnings;
+pub mod throw_preview;
+pub mod training;
+pub mod tutorial;
 
 pub struct JumpyUiPlugin;
 
@@ -23,11 +45,26 @@ impl Plugin for JumpyUiPlugin {
         app.add_plugin(bevy_egui::EguiPlugin)
             .add_plugin(ui_input::UiInputPlugin)
             .add_plugin(main_menu::MainMenuPlugin)
+            .add_plugin(map_loading::MapLoadingPlugin)
+            .add_plugin(map_thumbnails::MapThumbnailsPlugin)
             .add_plugin(editor::EditorPlugin)
             .add_plugin(debug_tools::DebugToolsPlugin)
+            .add_plugin(collision_debug::CollisionDebugPlugin)
+            .add_plugin(hud::HudPlugin)
+            .add_plugin(countdown::CountdownPlugin)
             .add_plugin(pause_menu::PausePlugin)
+            .add_plugin(photo_mode::PhotoModePlugin)
+            .add_plugin(player_indicators::PlayerIndicatorsPlugin)
+            .add_plugin(spectating::SpectatingPlugin)
+            .add_plugin(stocks::StocksPlugin)
+            .add_plugin(storage_warnings::StorageWarningsPlugin)
+            .add_plugin(throw_preview::ThrowPreviewPlugin)
+            .add_plugin(training::TrainingPlugin)
+            .add_plugin(tutorial::TutorialPlugin)
             .init_resource::<WidgetAdjacencies>()
             .init_resource::<DisableMenuInput>()
+            .init_resource::<UiScaleSetting>()
+            .add_system(load_display_settings.in_schedule(OnExit(EngineState::LoadingGameData)))
             .add_system(
                 handle_menu_input
                     .run_if(resource_exists::<GameMeta>())
@@ -38,6 +75,11 @@ impl Plugin for JumpyUiPlugin {
             )
             .add_system(update_egui_fonts)
             .add_system(update_ui_scale.run_if(resource_exists::<GameMeta>()));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugin(chat::ChatPlugin)
+            .add_plugin(desync_toast::DesyncToastPlugin)
+            .add_plugin(disconnected_players::DisconnectedPlayersPlugin);
     }
 }
 
@@ -120,6 +162,9 @@ pub struct WidgetAdjacencies {
     pub map: HashMap<egui::Id, WidgetAdjacency>,
     /// These widgets will have the focus change when pressing directional inputs
     pub text_boxes: HashSet<egui::Id>,
+    /// These widgets will have their value adjusted instead of the focus changed when pressing left
+    /// or right.
+    pub sliders: HashSet<egui::Id>,
 }
 
 /// The list of widgets in each direction from another widget
@@ -176,7 +221,66 @@ impl<'a> WidgetAdjacencyEntry<'a> {
 #[derive(Resource, Default, Deref, DerefMut)]
 pub struct DisableMenuInput(pub bool);
 
+/// The UI scale chosen in the display settings, multiplied with the UI theme's scale.
+#[derive(Resource, Deref, DerefMut)]
+pub struct UiScaleSetting(pub f32);
+
+impl Default for UiScaleSetting {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Apply the display settings to the window and the UI scale.
+pub fn apply_display_settings(
+    settings: &Settings,
+    window: &mut Window,
+    ui_scale: &mut UiScaleSetting,
+) {
+    // On the web the canvas is sized to fit its parent element instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let [width, height] = settings.window_resolution;
+        window.resolution.set(width as f32, height as f32);
+    }
+    window.mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    window.present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+    **ui_scale = settings
+        .ui_scale
+        .clamp(Settings::MIN_UI_SCALE, Settings::MAX_UI_SCALE);
+}
+
+/// Apply the stored display settings once the game has loaded, before the menu is shown.
+fn load_display_settings(
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScaleSetting>,
+) {
+    let mut settings = Settings::get_stored_or_default(&game, &mut storage);
+    // The window mode given on the command line takes precedence, without changing the setting
+    if ENGINE_CONFIG.fullscreen {
+        settings.fullscreen = true;
+    } else if ENGINE_CONFIG.windowed {
+        settings.fullscreen = false;
+    }
+    if let Ok(mut window) = windows.get_single_mut() {
+        apply_display_settings(&settings, &mut window, &mut ui_scale);
+    }
+}
+
 fn handle_menu_input(
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    mut modified_settings: ResMut<main_menu::settings::ModifiedSettings>,
     disable_menu_input: Res<DisableMenuInput>,
     mut windows: Query<&mut Window, With<PrimaryWindow>>,
     input: Query<&ActionState<MenuAction>>,
@@ -195,13 +299,29 @@ fn handle_menu_input(
                 WindowMode::BorderlessFullscreen => WindowMode::Windowed,
                 _ => WindowMode::BorderlessFullscreen,
             };
+
+            // Keep the display settings in sync with the hotkey
+            let fullscreen = window.mode == WindowMode::BorderlessFullscreen;
+            let mut settings = Settings::get_stored_or_default(&game, &mut storage).into_owned();
+            settings.fullscreen = fullscreen;
+            storage.set(Settings::STORAGE_KEY, &settings);
+            storage.save();
+            if let Some(settings) = &mut **modified_settings {
+                settings.fullscreen = fullscreen;
+            }
         }
     }
 
     if let Ok(mut inputs) = egui_inputs.get_single_mut() {
         if **disable_menu_input {
+            // Text boxes still need keys like backspace and enter to be usable.
+            let is_text_box = egui_ctx
+                .ctx_mut()
+                .memory(|memory| memory.focus())
+                .map(|id| adjacencies.text_boxes.contains(&id))
+                .unwrap_or(false);
             inputs.events.retain(|event| match event {
-                egui::Event::Key { key, .. } => key == &egui::Key::Escape,
+                egui::Event::Key { key, .. } => is_text_box || key == &egui::Key::Escape,
                 _ => true,
             });
             return;
@@ -226,6 +346,30 @@ fn handle_menu_input(
             });
         }
 
+        // Left and right adjust the value of focused sliders instead of moving the focus. Egui
+        // already gets the arrow keys from the keyboard, so we only need to forward the other
+        // inputs, such as the gamepad.
+        let is_slider = egui_ctx
+            .ctx_mut()
+            .memory(|memory| memory.focus())
+            .map(|id| adjacencies.sliders.contains(&id))
+            .unwrap_or(false);
+        if is_slider {
+            for (action, key, key_code) in [
+                (MenuAction::Left, egui::Key::ArrowLeft, KeyCode::Left),
+                (MenuAction::Right, egui::Key::ArrowRight, KeyCode::Right),
+            ] {
+                if input.just_pressed(action) && !keyboard.pressed(key_code) {
+                    inputs.events.push(egui::Event::Key {
+                        key,
+                        pressed: true,
+                        repeat: false,
+                        modifiers: egui::Modifiers::NONE,
+                    });
+                }
+            }
+        }
+
         // Helper to fall back on using tab order instead of adjacency map to determine next focused
         // widget.
         let mut tab_fallback = || {
@@ -272,6 +416,10 @@ fn handle_menu_input(
                         } else {
                             tab_fallback()
                         }
+                    } else if is_slider
+                        && (input.pressed(MenuAction::Left) || input.pressed(MenuAction::Right))
+                    {
+                        // The slider handles left and right itself
                     } else if input.just_pressed(MenuAction::Left) {
                         if let Some(adjacent) = adjacency.left {
                             memory.request_focus(adjacent);
@@ -299,10 +447,14 @@ pub struct EguiFontDefinitions(pub egui::FontDefinitions);
 
 /// Watches for asset events for [`EguiFont`] assets and updates the corresponding fonts from the
 /// [`GameMeta`], inserting the font data into the egui context.
+///
+/// Also adds the selected locale's font, if it has one, as a fallback for all of the game fonts.
 fn update_egui_fonts(
     mut font_queue: Local<Vec<Handle<EguiFont>>>,
+    mut applied_locale: Local<Option<unic_langid::LanguageIdentifier>>,
     egui_font_definitions: Option<ResMut<EguiFontDefinitions>>,
     game: Option<Res<GameMeta>>,
+    locale: Res<bevy_fluent::Locale>,
     mut events: EventReader<AssetEvent<EguiFont>>,
     assets: Res<Assets<EguiFont>>,
     mut contexts: EguiContexts,
@@ -316,7 +468,21 @@ fn update_egui_fonts(
 
     // Update queued fonts if the game is ready
     if let Some((game, mut egui_font_definitions)) = game.zip(egui_font_definitions) {
+        // Update the locale font if the locale changed
+        let mut update_locale_font = applied_locale.as_ref() != Some(&locale.requested);
+
         for handle in font_queue.drain(..) {
+            // Update the locale font if this is one of the locale fonts
+            if game
+                .translations
+                .fonts
+                .values()
+                .any(|font_handle| font_handle.inner == handle)
+            {
+                update_locale_font = true;
+                continue;
+            }
+
             // Get the game font name associated to this handle
             let name = game
                 .ui_theme
@@ -340,48 +506,83 @@ fn update_egui_fonts(
                         .font_data
                         .insert(font_name.clone(), font.0.clone());
 
+                    // The game font goes before the locale fallback font
                     egui_font_definitions
                         .families
                         .get_mut(&egui::FontFamily::Name(font_name.clone().into()))
                         .unwrap()
-                        .push(font_name);
+                        .insert(0, font_name);
 
                     ctx.set_fonts(egui_font_definitions.clone());
                 }
             }
         }
+
+        if update_locale_font {
+            const LOCALE_FONT_NAME: &str = "locale-fallback";
+
+            // Remove the previous locale's font
+            egui_font_definitions.font_data.remove(LOCALE_FONT_NAME);
+            for family in egui_font_definitions.families.values_mut() {
+                family.retain(|font_name| font_name != LOCALE_FONT_NAME);
+            }
+
+            // Add the new locale's font as a fallback for every game font
+            let font = game
+                .translations
+                .fonts
+                .get(&locale.requested.to_string())
+                .and_then(|handle| assets.get(&handle.inner));
+            if let Some(font) = font {
+                egui_font_definitions
+                    .font_data
+                    .insert(LOCALE_FONT_NAME.into(), font.0.clone());
+                for font_name in game.ui_theme.font_families.keys() {
+                    egui_font_definitions
+                        .families
+                        .get_mut(&egui::FontFamily::Name(font_name.clone().into()))
+                        .unwrap()
+                        .push(LOCALE_FONT_NAME.into());
+                }
+            }
+
+            contexts.ctx_mut().set_fonts(egui_font_definitions.clone());
+            *applied_locale = Some(locale.requested.clone());
+        }
     }
 }
 
 fn update_ui_scale(
     game_meta: Res<GameMeta>,
+    ui_scale_setting: Res<UiScaleSetting>,
     mut egui_settings: ResMut<bevy_egui::EguiSettings>,
     windows: Query<&Window, With<PrimaryWindow>>,
     projection: Query<&OrthographicProjection, With<MenuCamera>>,
 ) {
     if let Ok(window) = windows.get_single() {
         if let Ok(projection) = projection.get_single() {
-            match projection.scaling_mode {
+            let scale = match projection.scaling_mode {
                 bevy::render::camera::ScalingMode::FixedVertical(height) => {
                     let window_height = window.height();
-                    let scale = window_height / height;
-                    egui_settings.scale_factor = (scale * game_meta.ui_theme.scale) as f64;
+                    Some(window_height / height)
                 }
                 bevy::render::camera::ScalingMode::FixedHorizontal(width) => {
                     let window_width = window.width();
-                    let scale = window_width / width;
-                    egui_settings.scale_factor = (scale * game_meta.ui_theme.scale) as f64;
+                    Some(window_width / width)
                 }
                 bevy::render::camera::ScalingMode::Fixed { width, height } => {
                     let window_width = window.width();
                     let window_height = window.height();
                     let scale = window_width / width;
-                    let scale = scale.min(window_height / height);
-                    egui_settings.scale_factor = (scale * game_meta.ui_theme.scale) as f64;
+                    Some(scale.min(window_height / height))
                 }
-                bevy::render::camera::ScalingMode::AutoMin { .. } => (),
-                bevy::render::camera::ScalingMode::AutoMax { .. } => (),
-                bevy::render::camera::ScalingMode::WindowSize(..) => (),
+                bevy::render::camera::ScalingMode::AutoMin { .. } => None,
+                bevy::render::camera::ScalingMode::AutoMax { .. } => None,
+                bevy::render::camera::ScalingMode::WindowSize(..) => None,
+            };
+            if let Some(scale) = scale {
+                egui_settings.scale_factor =
+                    (scale * game_meta.ui_theme.scale * **ui_scale_setting) as f64;
             }
         }
     }
@@ -0,0 +1,152 @@
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+use crate::{
+    networking::{
+        proto::MatchMessage, sanitize_chat_message, ChatHistory, NetworkMatchSocket, SocketTarget,
+        MAX_CHAT_MESSAGE_LEN,
+    },
+    prelude::*,
+};
+
+use super::{
+    main_menu::player_select::PlayerNames,
+    widgets::{bordered_frame::BorderedFrame, EguiUiExt},
+};
+
+/// How many of the most recent chat messages are shown on screen.
+const VISIBLE_MESSAGES: usize = 6;
+
+/// How long, in seconds, chat messages stay on screen while the chat box is closed.
+const MESSAGE_DURATION: f32 = 10.0;
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(clear_chat_history.run_if(resource_added::<NetworkMatchSocket>()))
+            .add_system(
+                chat_widget
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            );
+    }
+}
+
+/// Local state for the chat widget.
+#[derive(Default)]
+struct ChatState {
+    is_open: bool,
+    just_opened: bool,
+    text: String,
+}
+
+/// Start every network match with an empty chat.
+fn clear_chat_history(mut history: ResMut<ChatHistory>) {
+    history.messages.clear();
+}
+
+/// Renders the chat messages and the chat box for typing new ones.
+#[allow(clippy::too_many_arguments)]
+fn chat_widget(
+    mut state: Local<ChatState>,
+    mut history: ResMut<ChatHistory>,
+    mut menu_input: Query<&mut ActionState<MenuAction>>,
+    mut disable_menu_input: ResMut<DisableMenuInput>,
+    mut adjacencies: ResMut<WidgetAdjacencies>,
+    keyboard: Res<Input<KeyCode>>,
+    socket: Res<NetworkMatchSocket>,
+    time: Res<Time>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    player_names: Res<PlayerNames>,
+    mut contexts: EguiContexts,
+) {
+    let mut menu_input = menu_input.single_mut();
+    let now = time.elapsed_seconds();
+
+    if state.is_open {
+        // Don't let escape open the pause menu while it's closing the chat box
+        menu_input.consume(MenuAction::Pause);
+
+        if keyboard.just_pressed(KeyCode::Return) {
+            let message = sanitize_chat_message(&state.text);
+            if !message.trim().is_empty() {
+                let player = socket.player_idx();
+                socket.send_message(
+                    SocketTarget::All,
+                    MatchMessage::Chat {
+                        message: message.clone(),
+                    },
+                );
+                history.push(player, &message, now);
+            }
+            state.text.clear();
+            state.is_open = false;
+        } else if keyboard.just_pressed(KeyCode::Escape) {
+            state.text.clear();
+            state.is_open = false;
+        }
+    } else if menu_input.just_pressed(MenuAction::Chat) {
+        state.is_open = true;
+        state.just_opened = true;
+    }
+    **disable_menu_input = state.is_open;
+
+    let messages = history
+        .messages
+        .iter()
+        .rev()
+        .take(VISIBLE_MESSAGES)
+        .filter(|message| state.is_open || now - message.time < MESSAGE_DURATION)
+        .collect::<Vec<_>>();
+    if messages.is_empty() && !state.is_open {
+        return;
+    }
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("chat")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(font.size, -font.size))
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.set_max_width(font.size * 25.0);
+
+                    for message in messages.into_iter().rev() {
+                        let sender = match player_names.get(message.player) {
+                            Some(name) => name.to_string(),
+                            None => {
+                                let player = message.player + 1;
+                                localization.get(&format!("chat-sender?player={player}"))
+                            }
+                        };
+                        ui.themed_label(&font, &format!("{sender}: {}", message.text));
+                    }
+
+                    if state.is_open {
+                        let hint = localization.get("chat-hint");
+                        let text_box = ui.add(
+                            egui::TextEdit::singleline(&mut state.text)
+                                .font(font.clone())
+                                .char_limit(MAX_CHAT_MESSAGE_LEN)
+                                .hint_text(hint)
+                                .desired_width(ui.available_width()),
+                        );
+                        adjacencies.text_boxes.insert(text_box.id);
+
+                        // Focus the text box on the frame after the chat opens, so that the key
+                        // that opened it isn't typed into it.
+                        if state.just_opened {
+                            state.just_opened = false;
+                            text_box.request_focus();
+                        }
+                    }
+                });
+        });
+}
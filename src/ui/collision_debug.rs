@@ -0,0 +1,288 @@
+use bevy::window::PrimaryWindow;
+use bones_bevy_renderer::BevyBonesEntity;
+use jumpy_core::{
+    bullet::Bullet,
+    damage::DamageRegion,
+    physics::{
+        collisions::{Actor, Collider},
+        ColliderShape, KinematicBody, TileCollisionKind, GROUND_CHECK_OFFSET,
+    },
+};
+
+use crate::prelude::*;
+
+/// The width of the collision shape outlines, in pixels on the screen.
+const LINE_WIDTH: f32 = 1.5;
+/// The number of line segments used to draw circle colliders.
+const CIRCLE_SEGMENTS: usize = 16;
+/// The z position of the collision shapes, in front of everything in the map.
+const DEBUG_Z: f32 = 950.0;
+
+const SOLID_TILE_COLOR: Color = Color::rgba(0.8, 0.8, 0.8, 0.35);
+const BREAKABLE_TILE_COLOR: Color = Color::rgba(0.8, 0.37, 0.06, 0.35);
+const JUMP_THROUGH_TILE_COLOR: Color = Color::rgba(1.0, 0.84, 0.0, 0.35);
+const ACTOR_COLOR: Color = Color::rgb(0.15, 0.75, 0.27);
+const ACTOR_TILE_COLOR: Color = Color::rgba(0.15, 0.75, 0.27, 0.3);
+const DAMAGE_REGION_COLOR: Color = Color::RED;
+const BULLET_COLOR: Color = Color::rgb(1.0, 0.0, 1.0);
+const ON_GROUND_COLOR: Color = Color::WHITE;
+const IN_AIR_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.4);
+
+pub struct CollisionDebugPlugin;
+
+impl Plugin for CollisionDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowCollisionShapes>()
+            .add_system(
+                render_collision_shapes
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(clear_collision_shapes.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// Resource containing whether the collision shapes in the game are drawn, which is toggled in the
+/// debug tools.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ShowCollisionShapes(pub bool);
+
+/// Marker component for the sprites that make up the collision shapes.
+#[derive(Component)]
+pub struct CollisionDebugSprite;
+
+/// The collision shapes read out of the game session.
+#[derive(Default)]
+struct CollisionShapes {
+    /// The filled rectangles, with their center, size and color.
+    fills: Vec<(Vec2, Vec2, Color)>,
+    /// The lines making up the outlines, with their points and color.
+    lines: Vec<(Vec<Vec2>, Color)>,
+}
+
+/// Get the points making up the outline of a collider shape.
+fn shape_outline(shape: ColliderShape, position: Vec2, rotation: f32) -> Vec<Vec2> {
+    let angle = Vec2::from_angle(rotation);
+    match shape {
+        ColliderShape::Circle { diameter } => (0..=CIRCLE_SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                position + Vec2::from_angle(angle) * diameter / 2.0
+            })
+            .collect(),
+        ColliderShape::Rectangle { size } => {
+            let half = size / 2.0;
+            [
+                vec2(-half.x, half.y),
+                half,
+                vec2(half.x, -half.y),
+                -half,
+                vec2(-half.x, half.y),
+            ]
+            .into_iter()
+            .map(|corner| position + angle.rotate(corner))
+            .collect()
+        }
+    }
+}
+
+/// Draw the colliders of the tiles, actors, bullets and damage regions in the game, along with the
+/// tiles each actor overlaps and the probe used to check whether they are on the ground.
+///
+/// This only reads the game session, and draws the shapes with Bevy sprites outside of it, so it
+/// doesn't change the simulation and can't affect rollback.
+fn render_collision_shapes(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    show: Res<ShowCollisionShapes>,
+    sprites: Query<Entity, With<CollisionDebugSprite>>,
+    cameras: Query<&OrthographicProjection, (With<Camera>, With<BevyBonesEntity>)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+) {
+    for sprite in &sprites {
+        commands.entity(sprite).despawn();
+    }
+
+    if !**show {
+        return;
+    }
+
+    // Keep the lines the same width on screen, no matter how far the camera is zoomed out
+    let (Ok(projection), Ok(window)) = (cameras.get_single(), windows.get_single()) else {
+        return;
+    };
+    let line_width = LINE_WIDTH * projection.area.height() / window.height().max(1.0);
+
+    let shapes = session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             tile_layers: bones::Comp<bones::TileLayer>,
+             tile_collision_kinds: bones::Comp<TileCollisionKind>,
+             actors: bones::Comp<Actor>,
+             colliders: bones::Comp<Collider>,
+             bullets: bones::Comp<Bullet>,
+             bodies: bones::Comp<KinematicBody>,
+             damage_regions: bones::Comp<DamageRegion>,
+             transforms: bones::Comp<bones::Transform>| {
+                let mut shapes = CollisionShapes::default();
+                let rotation_of =
+                    |transform: &bones::Transform| transform.rotation.to_euler(EulerRot::XYZ).2;
+
+                // The tiles, and the tiles that each actor is overlapping
+                let actor_bounds = entities
+                    .iter_with((&actors, &colliders, &transforms))
+                    .filter(|(_, (_, collider, _))| !collider.disabled)
+                    .map(|(_, (_, collider, transform))| {
+                        let outline = shape_outline(
+                            collider.shape,
+                            transform.translation.truncate(),
+                            rotation_of(transform),
+                        );
+                        let min = outline.iter().copied().reduce(Vec2::min).unwrap();
+                        let max = outline.iter().copied().reduce(Vec2::max).unwrap();
+                        (min, max)
+                    })
+                    .collect::<Vec<_>>();
+                for (_, layer) in entities.iter_with(&tile_layers) {
+                    for x in 0..layer.grid_size.x {
+                        for y in 0..layer.grid_size.y {
+                            let Some(tile) = layer.get(uvec2(x, y)) else {
+                                continue;
+                            };
+                            let color = match tile_collision_kinds.get(tile) {
+                                Some(TileCollisionKind::Solid) => SOLID_TILE_COLOR,
+                                Some(TileCollisionKind::Breakable) => BREAKABLE_TILE_COLOR,
+                                Some(TileCollisionKind::JumpThrough) => JUMP_THROUGH_TILE_COLOR,
+                                Some(TileCollisionKind::Empty) | None => continue,
+                            };
+                            let center = (vec2(x as f32, y as f32) + 0.5) * layer.tile_size;
+                            shapes.fills.push((center, layer.tile_size, color));
+                        }
+                    }
+                }
+
+                // All of the map layers share the same grid
+                if let Some((_, layer)) = entities.iter_with(&tile_layers).next() {
+                    let last_cell = layer.grid_size.as_ivec2() - 1;
+                    for (min, max) in &actor_bounds {
+                        let min_cell = (*min / layer.tile_size).floor().as_ivec2().max(IVec2::ZERO);
+                        let max_cell = (*max / layer.tile_size).floor().as_ivec2().min(last_cell);
+                        for x in min_cell.x..=max_cell.x {
+                            for y in min_cell.y..=max_cell.y {
+                                let center = (vec2(x as f32, y as f32) + 0.5) * layer.tile_size;
+                                shapes
+                                    .fills
+                                    .push((center, layer.tile_size, ACTOR_TILE_COLOR));
+                            }
+                        }
+                    }
+                }
+
+                // The actors and bullets
+                for (ent, (_, collider, transform)) in
+                    entities.iter_with((&actors, &colliders, &transforms))
+                {
+                    if collider.disabled {
+                        continue;
+                    }
+                    let color = if bullets.contains(ent) {
+                        BULLET_COLOR
+                    } else {
+                        ACTOR_COLOR
+                    };
+                    shapes.lines.push((
+                        shape_outline(
+                            collider.shape,
+                            transform.translation.truncate(),
+                            rotation_of(transform),
+                        ),
+                        color,
+                    ));
+                }
+
+                // The ground check done in `update_kinematic_bodies`, drawn along the bottom of the
+                // body.
+                for (_, (body, transform)) in entities.iter_with((&bodies, &transforms)) {
+                    if body.is_deactivated {
+                        continue;
+                    }
+                    let mut position = transform.translation.truncate();
+                    position.y -= GROUND_CHECK_OFFSET;
+                    let outline = shape_outline(body.shape, position, rotation_of(transform));
+                    let min = outline.iter().copied().reduce(Vec2::min).unwrap();
+                    let max = outline.iter().copied().reduce(Vec2::max).unwrap();
+                    let color = if body.is_on_ground {
+                        ON_GROUND_COLOR
+                    } else {
+                        IN_AIR_COLOR
+                    };
+                    shapes
+                        .lines
+                        .push((vec![vec2(min.x, min.y), vec2(max.x, min.y)], color));
+                }
+
+                // The damage regions, which don't rotate with their entities
+                for (_, (region, transform)) in entities.iter_with((&damage_regions, &transforms)) {
+                    shapes.lines.push((
+                        shape_outline(
+                            ColliderShape::Rectangle { size: region.size },
+                            transform.translation.truncate(),
+                            0.0,
+                        ),
+                        DAMAGE_REGION_COLOR,
+                    ));
+                }
+
+                Ok(shapes)
+            },
+        )
+        .unwrap();
+
+    for (center, size, color) in shapes.fills {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(center.extend(DEBUG_Z)),
+                ..default()
+            },
+            CollisionDebugSprite,
+        ));
+    }
+
+    // Draw each line segment as a thin sprite stretched between its points
+    for (points, color) in shapes.lines {
+        for segment in points.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let delta = end - start;
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(vec2(delta.length() + line_width, line_width)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(
+                        ((start + end) / 2.0).extend(DEBUG_Z + 1.0),
+                    )
+                    .with_rotation(Quat::from_rotation_z(delta.y.atan2(delta.x))),
+                    ..default()
+                },
+                CollisionDebugSprite,
+            ));
+        }
+    }
+}
+
+fn clear_collision_shapes(
+    mut commands: Commands,
+    sprites: Query<Entity, With<CollisionDebugSprite>>,
+) {
+    for sprite in &sprites {
+        commands.entity(sprite).despawn();
+    }
+}
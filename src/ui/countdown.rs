@@ -0,0 +1,144 @@
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use jumpy_core::{match_state::MatchState, overtime::SuddenDeath, score::MatchScore};
+
+use crate::prelude::*;
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+/// How long "GO!" stays on screen after the countdown finishes.
+const GO_DURATION: f32 = 1.0;
+
+/// How long sudden death is announced for when it starts.
+const SUDDEN_DEATH_ANNOUNCEMENT_DURATION: f32 = 2.0;
+
+pub struct CountdownPlugin;
+
+impl Plugin for CountdownPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            countdown_overlay
+                .run_if(in_state(EngineState::InGame))
+                .run_if(in_state(GameEditorState::Hidden))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Whether a network session is still waiting for the other players to load the match.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_waiting_for_players(session: &Session) -> bool {
+    session
+        .downcast_ref::<crate::networking::GgrsSessionRunner>()
+        .map(|session| session.is_waiting_for_players())
+        .unwrap_or(false)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn is_waiting_for_players(_session: &Session) -> bool {
+    false
+}
+
+/// Shows the countdown to the start of the match, the time left in timed matches, and announces
+/// sudden death.
+fn countdown_overlay(
+    mut contexts: EguiContexts,
+    mut session_manager: SessionManager,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let Some(session) = session_manager.session.as_mut() else {
+        return;
+    };
+    let ui_theme = &game.ui_theme;
+
+    if is_waiting_for_players(session) {
+        show_announcement(
+            &mut contexts,
+            &game,
+            &localization.get("waiting-for-players"),
+        );
+        return;
+    }
+
+    let time_step = session.core_session().time_step;
+    let time_limit = session.core_session().info.meta.config.time_limit;
+    let (match_state, sudden_death, match_over) = session
+        .world()
+        .run_initialized_system(
+            |match_state: bones::Res<MatchState>,
+             sudden_death: bones::Res<SuddenDeath>,
+             score: bones::Res<MatchScore>| {
+                Ok((
+                    *match_state,
+                    sudden_death.active.then_some(sudden_death.frames_elapsed),
+                    score.result.is_some(),
+                ))
+            },
+        )
+        .unwrap();
+
+    // Show the time left in timed matches
+    if let (Some(time_limit), MatchState::Playing { frames_played }, None, false) =
+        (time_limit, match_state, sudden_death, match_over)
+    {
+        let seconds_left = (time_limit.as_secs_f32() - frames_played as f32 * time_step)
+            .max(0.0)
+            .ceil() as u32;
+        let font = ui_theme
+            .font_styles
+            .bigger
+            .colored(ui_theme.panel.font_color);
+        egui::Area::new("round_timer")
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, font.size))
+            .interactable(false)
+            .show(contexts.ctx_mut(), |ui| {
+                BorderedFrame::new(&ui_theme.panel.border)
+                    .padding(ui_theme.panel.padding.into())
+                    .show(ui, |ui| {
+                        ui.themed_label(
+                            &font,
+                            &format!("{}:{:02}", seconds_left / 60, seconds_left % 60),
+                        );
+                    });
+            });
+    }
+
+    let text = match (match_state, sudden_death) {
+        (MatchState::Starting { countdown_frames }, _) => {
+            format!("{}", (countdown_frames as f32 * time_step).ceil())
+        }
+        (_, Some(frames_elapsed))
+            if frames_elapsed as f32 * time_step < SUDDEN_DEATH_ANNOUNCEMENT_DURATION =>
+        {
+            localization.get("sudden-death")
+        }
+        (MatchState::Playing { frames_played }, None)
+            if frames_played as f32 * time_step < GO_DURATION =>
+        {
+            localization.get("match-countdown-go")
+        }
+        _ => return,
+    };
+    show_announcement(&mut contexts, &game, &text);
+}
+
+/// Show a big announcement in the middle of the screen.
+fn show_announcement(contexts: &mut EguiContexts, game: &GameMeta, text: &str) {
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .heading
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("countdown_overlay")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&font, text);
+                });
+        });
+}
@@ -1,25 +1,38 @@
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::IoTaskPool;
 use bevy::{
     diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
     prelude::*,
 };
 use bevy_egui::*;
 use bevy_fluent::Localization;
+use jumpy_core::{
+    inspector::{ComponentInspector, InspectorFields, InspectorValue},
+    physics::collisions::RapierContext,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::networking::{NetworkDebugSettings, NetworkDiagnostics, NetworkDiagnosticsSample};
+use crate::{prelude::*, session::LocalSessionRunner};
 
-use crate::prelude::*;
+use super::collision_debug::ShowCollisionShapes;
 
 pub struct DebugToolsPlugin;
 
 impl Plugin for DebugToolsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(FrameTimeDiagnosticsPlugin)
-            // .init_resource::<ShowNetworkVisualizer>()
             .init_resource::<BonesSnapshot>()
             .init_resource::<CoreDebugSettings>()
             .init_resource::<ShowDebugWindows>()
             .add_system(sync_core_debug_settings)
             .add_system(debug_tools_window)
             .add_system(frame_diagnostic_window)
-            .add_system(profiler_window);
+            .add_system(profiler_window)
+            .add_system(core_inspector_window);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_system(network_diagnostics_window);
     }
 }
 
@@ -40,6 +53,8 @@ fn sync_core_debug_settings(session: Option<ResMut<Session>>, settings: Res<Core
 struct ShowDebugWindows {
     pub frame_time_diagnostics: bool,
     pub profiler: bool,
+    pub network_diagnostics: bool,
+    pub core_inspector: bool,
 }
 
 /// Resource containing the bones snapshot.
@@ -49,6 +64,7 @@ struct BonesSnapshot(Option<bones::World>);
 /// System that renders the debug tools window which can be toggled by pressing F12
 fn debug_tools_window(
     mut core_debug_settings: ResMut<CoreDebugSettings>,
+    mut show_collision_shapes: ResMut<ShowCollisionShapes>,
     mut visible: Local<bool>,
     mut show_debug_windows: ResMut<ShowDebugWindows>,
     localization: Res<Localization>,
@@ -56,6 +72,7 @@ fn debug_tools_window(
     mut show_inspector: ResMut<WorldInspectorEnabled>,
     mut bones_world_snapshot: ResMut<BonesSnapshot>,
     mut session: Option<ResMut<Session>>,
+    #[cfg(not(target_arch = "wasm32"))] mut network_debug_settings: ResMut<NetworkDebugSettings>,
     mut egui_ctxs: EguiContexts,
 ) {
     // Toggle debug window visibility
@@ -65,8 +82,7 @@ fn debug_tools_window(
 
     // Shortcut to toggle collision shapes without having to use the menu
     if input.just_pressed(KeyCode::F10) {
-        core_debug_settings.show_damage_regions = !core_debug_settings.show_damage_regions;
-        core_debug_settings.show_kinematic_colliders = core_debug_settings.show_damage_regions;
+        **show_collision_shapes = !**show_collision_shapes;
     }
 
     // Shortcut to toggle the inspector without having to use the menu
@@ -79,14 +95,34 @@ fn debug_tools_window(
         show_debug_windows.frame_time_diagnostics = !show_debug_windows.frame_time_diagnostics;
     }
 
+    // Shortcut to toggle network diagnostics
+    #[cfg(not(target_arch = "wasm32"))]
     if input.just_pressed(KeyCode::F7) {
-        show_debug_windows.profiler = !show_debug_windows.profiler;
+        show_debug_windows.network_diagnostics = !show_debug_windows.network_diagnostics;
     }
 
-    // // Shortcut to toggle network visualizers
-    // if input.just_pressed(KeyCode::F7) {
-    //     **show_network_visualizer = !**show_network_visualizer;
-    // }
+    // Shortcuts to advance the game while stepping through frames
+    if let Some(runner) = session
+        .as_mut()
+        .and_then(|session| session.downcast_mut::<LocalSessionRunner>())
+        .filter(|runner| runner.frozen)
+    {
+        if input.just_pressed(KeyCode::F1) {
+            runner.step_frames += 1;
+        }
+        if input.just_pressed(KeyCode::F2) {
+            runner.step_frames += 10;
+        }
+    }
+
+    // Shortcut to toggle the core world inspector
+    if input.just_pressed(KeyCode::F3) {
+        show_debug_windows.core_inspector = !show_debug_windows.core_inspector;
+    }
+
+    if input.just_pressed(KeyCode::F5) {
+        show_debug_windows.profiler = !show_debug_windows.profiler;
+    }
 
     // Display debug tool window
     egui::Window::new(localization.get("debug-tools"))
@@ -96,12 +132,20 @@ fn debug_tools_window(
         .show(egui_ctxs.ctx_mut(), |ui| {
             // Show collision shapes
             ui.checkbox(
-                &mut core_debug_settings.show_kinematic_colliders,
-                format!("{} ( F10 )", localization.get("show-kinematic-colliders")),
+                &mut show_collision_shapes.0,
+                format!("{} ( F10 )", localization.get("show-collision-shapes")),
             );
             ui.checkbox(
-                &mut core_debug_settings.show_damage_regions,
-                format!("{} ( F10 )", localization.get("show-damage-regions")),
+                &mut core_debug_settings.show_emote_regions,
+                localization.get("show-emote-regions"),
+            );
+            ui.checkbox(
+                &mut core_debug_settings.show_force_regions,
+                localization.get("show-force-regions"),
+            );
+            ui.checkbox(
+                &mut core_debug_settings.show_camera_framing,
+                localization.get("show-camera-framing"),
             );
             ui.checkbox(
                 &mut core_debug_settings.show_pathfinding_lines,
@@ -114,6 +158,12 @@ fn debug_tools_window(
                 format!("{} ( F9 )", localization.get("show-world-inspector")),
             );
 
+            // Show core world inspector
+            ui.checkbox(
+                &mut show_debug_windows.core_inspector,
+                format!("{} ( F3 )", localization.get("show-core-inspector")),
+            );
+
             // Show frame time diagnostics
             ui.checkbox(
                 &mut show_debug_windows.frame_time_diagnostics,
@@ -122,8 +172,22 @@ fn debug_tools_window(
 
             // Show profiler
             ui.checkbox(
-                &mut show_debug_windows.frame_time_diagnostics,
-                format!("{} ( F7 )", localization.get("show-profiler")),
+                &mut show_debug_windows.profiler,
+                format!("{} ( F5 )", localization.get("show-profiler")),
+            );
+
+            // Show network diagnostics
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.checkbox(
+                &mut show_debug_windows.network_diagnostics,
+                format!("{} ( F7 )", localization.get("show-network-diagnostics")),
+            );
+
+            // Exchange network checksums every frame
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.checkbox(
+                &mut network_debug_settings.checksum_every_frame,
+                localization.get("checksum-every-frame"),
             );
 
             // Snapshot/Restore buttons
@@ -153,14 +217,83 @@ fn debug_tools_window(
                 });
             });
 
-            // Show network visualizer
-            // ui.checkbox(
-            //     &mut show_network_visualizer,
-            //     format!("{} ( F7 )", localization.get("show-network-visualizer")),
-            // );
+            // Frame stepping, which would break network games, and world dumps
+            ui.add_space(2.0);
+            ui.heading(localization.get("frame-stepping"));
+            ui.horizontal(|ui| {
+                let mut runner = session
+                    .as_mut()
+                    .and_then(|session| session.downcast_mut::<LocalSessionRunner>());
+                ui.set_enabled(runner.is_some());
+
+                let mut frozen = runner.as_ref().map(|x| x.frozen).unwrap_or_default();
+                if ui
+                    .checkbox(&mut frozen, localization.get("step-frames"))
+                    .changed()
+                {
+                    if let Some(runner) = &mut runner {
+                        runner.frozen = frozen;
+                    }
+                }
+                ui.add_enabled_ui(frozen, |ui| {
+                    for (frames, label, key) in
+                        [(1, "advance-frame", "F1"), (10, "advance-10-frames", "F2")]
+                    {
+                        let label = format!("{} ( {key} )", localization.get(label));
+                        if ui.button(label).clicked() {
+                            if let Some(runner) = &mut runner {
+                                runner.step_frames += frames;
+                            }
+                        }
+                    }
+                });
+            });
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui
+                .add_enabled(
+                    session.is_some(),
+                    egui::Button::new(localization.get("dump-world")),
+                )
+                .clicked()
+            {
+                if let Some(session) = &mut session {
+                    save_world_dump(session.core_session());
+                }
+            }
         });
 }
 
+/// Save a dump of the game state to a file in the `world-dumps` folder of the data directory,
+/// named after the frame it was taken on.
+///
+/// See [`jumpy_core::debug::dump_world()`].
+#[cfg(not(target_arch = "wasm32"))]
+fn save_world_dump(core: &CoreSession) {
+    let dump = core.dump_world();
+    let frame = **core
+        .world
+        .resource::<jumpy_core::session::SessionFrame>()
+        .borrow();
+
+    IoTaskPool::get()
+        .spawn(async move {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let dir = crate::platform::data_dir().join("world-dumps");
+            let file_path = dir.join(format!("world-{frame:08}-{timestamp}.txt"));
+
+            let result = std::fs::create_dir_all(&dir)
+                .and_then(|_| std::fs::write(&file_path, dump.as_bytes()));
+            match result {
+                Ok(()) => info!(?file_path, "Saved world dump"),
+                Err(e) => error!("Could not save world dump: {e}"),
+            }
+        })
+        .detach();
+}
+
 struct FrameDiagState {
     min_fps: f64,
     max_fps: f64,
@@ -185,8 +318,16 @@ fn frame_diagnostic_window(
     diagnostics: Res<Diagnostics>,
     localization: Res<Localization>,
     mut egui_ctx: EguiContexts,
+    mut session: Option<ResMut<Session>>,
 ) {
     if show.frame_time_diagnostics {
+        let synced_colliders = session.as_mut().map(|session| {
+            session
+                .world()
+                .run_initialized_system(|ctx: bones::Res<RapierContext>| Ok(ctx.synced_colliders))
+                .unwrap()
+        });
+
         egui::Window::new(&localization.get("frame-diagnostics"))
             .id(egui::Id::new("frame_diagnostics"))
             .default_width(500.0)
@@ -236,6 +377,12 @@ fn frame_diagnostic_window(
                     avg = frame_time.average().unwrap() * 1000.0,
                     max = state.max_frame_time * 1000.0,
                 ));
+                if let Some(synced_colliders) = synced_colliders {
+                    ui.monospace(&format!(
+                        "{label:20}: {synced_colliders:4}",
+                        label = localization.get("synced-colliders"),
+                    ));
+                }
             });
     }
 }
@@ -255,3 +402,266 @@ fn profiler_window(
             });
     }
 }
+
+/// Shows the entities in the game session and their components, which may be edited in local games.
+fn core_inspector_window(
+    mut search: Local<String>,
+    mut show: ResMut<ShowDebugWindows>,
+    mut session: Option<ResMut<Session>>,
+    localization: Res<Localization>,
+    mut egui_ctx: EguiContexts,
+) {
+    if !show.core_inspector {
+        return;
+    }
+
+    egui::Window::new(&localization.get("core-inspector"))
+        .id(egui::Id::new("core_inspector"))
+        .default_size(egui::vec2(400.0, 400.0))
+        .open(&mut show.core_inspector)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            let Some(session) = session.as_mut() else {
+                ui.monospace(&localization.get("not-available"));
+                return;
+            };
+
+            // Editing the game in network games would make it de-sync, and replays must play out
+            // the way they were recorded.
+            let editable = session.downcast_ref::<LocalSessionRunner>().is_some();
+            if !editable {
+                ui.label(localization.get("core-inspector-read-only"));
+            }
+            ui.horizontal(|ui| {
+                ui.label(localization.get("search"));
+                ui.text_edit_singleline(&mut *search);
+            });
+            ui.separator();
+
+            let inspectors = session.core_session().inspectors.clone();
+            let world = session.world();
+            let entities = world
+                .run_initialized_system(|entities: bones::Res<bones::Entities>| {
+                    Ok(entities
+                        .iter_with_bitset(entities.bitset())
+                        .collect::<Vec<_>>())
+                })
+                .unwrap();
+            let world = &*world;
+            let search = search.to_lowercase();
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for entity in entities {
+                    let components = inspectors
+                        .iter()
+                        .filter(|inspector| inspector.has(world, entity))
+                        .collect::<Vec<_>>();
+                    if components.is_empty() {
+                        continue;
+                    }
+                    let title = format!("{entity:?}");
+                    let matches_search = title.to_lowercase().contains(&search)
+                        || components
+                            .iter()
+                            .any(|inspector| inspector.name.to_lowercase().contains(&search));
+                    if !matches_search {
+                        continue;
+                    }
+
+                    egui::CollapsingHeader::new(&title).show(ui, |ui| {
+                        for inspector in components {
+                            ui.strong(inspector.name);
+                            core_component_ui(ui, world, entity, inspector, editable);
+                        }
+                    });
+                }
+                ui.allocate_space(ui.available_size());
+            });
+        });
+}
+
+/// Shows the fields of a component in the core world inspector, and writes back any edits made to
+/// them.
+fn core_component_ui(
+    ui: &mut egui::Ui,
+    world: &bones::World,
+    entity: bones::Entity,
+    inspector: &ComponentInspector,
+    editable: bool,
+) {
+    let mut fields = InspectorFields::default();
+    inspector.inspect(world, entity, &mut fields);
+
+    egui::Grid::new((format!("{entity:?}"), inspector.name))
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (i, field) in fields.fields.into_iter().enumerate() {
+                ui.label(field.name);
+                let mut value = field.value.clone();
+                ui.add_enabled_ui(editable && field.editable, |ui| match &mut value {
+                    InspectorValue::Text(text) => {
+                        ui.monospace(&*text);
+                    }
+                    InspectorValue::Bool(value) => {
+                        ui.checkbox(value, "");
+                    }
+                    InspectorValue::Number(value) => {
+                        ui.add(egui::DragValue::new(value).speed(0.1));
+                    }
+                    InspectorValue::Vec2(value) => {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut value.x).speed(0.1));
+                            ui.add(egui::DragValue::new(&mut value.y).speed(0.1));
+                        });
+                    }
+                });
+                if value != field.value {
+                    inspector.inspect(world, entity, &mut InspectorFields::with_edit(i, value));
+                }
+                ui.end_row();
+            }
+        });
+}
+
+/// How many seconds a change in the input delay is pointed out in the network diagnostics for.
+#[cfg(not(target_arch = "wasm32"))]
+const INPUT_DELAY_CHANGE_SAMPLES: usize = 5;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn network_diagnostics_window(
+    mut show: ResMut<ShowDebugWindows>,
+    session: Option<ResMut<Session>>,
+    diagnostics: Res<NetworkDiagnostics>,
+    localization: Res<Localization>,
+    mut egui_ctx: EguiContexts,
+) {
+    use egui::plot::{Legend, Line, Plot, PlotPoints};
+
+    if !show.network_diagnostics {
+        return;
+    }
+
+    let is_network_game = session
+        .map(|mut session| session.network_player_idx().is_some())
+        .unwrap_or(false);
+    let latest = diagnostics.latest().filter(|_| is_network_game);
+
+    egui::Window::new(&localization.get("network-diagnostics"))
+        .id(egui::Id::new("network_diagnostics"))
+        .default_width(500.0)
+        .open(&mut show.network_diagnostics)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            let not_available = localization.get("not-available");
+            let Some(latest) = latest else {
+                ui.monospace(&not_available);
+                return;
+            };
+
+            for (player, ping) in latest.ping.iter().enumerate() {
+                if let Some(ping) = ping {
+                    ui.monospace(&format!(
+                        "{label:20}: {ping:4}ms",
+                        label = localization.get(&format!("player-ping?player={}", player + 1)),
+                    ));
+                }
+            }
+            ui.monospace(&format!(
+                "{label:20}: {frames:4}",
+                label = localization.get("rollback-frames"),
+                frames = latest.rollback_frames,
+            ));
+            ui.monospace(&format!(
+                "{label:20}: {frames:4}",
+                label = localization.get("predicted-frames"),
+                frames = latest.predicted_frames,
+            ));
+            // Show which way the input delay last changed, for a few seconds after it changes
+            let delay_change = diagnostics
+                .samples
+                .iter()
+                .rev()
+                .take(INPUT_DELAY_CHANGE_SAMPLES + 1)
+                .map(|sample| sample.input_delay)
+                .last()
+                .map_or(std::cmp::Ordering::Equal, |previous| {
+                    latest.input_delay.cmp(&previous)
+                });
+            ui.monospace(&format!(
+                "{label:20}: {frames:4} {change}",
+                label = localization.get("input-delay-frames"),
+                frames = latest.input_delay,
+                change = match delay_change {
+                    std::cmp::Ordering::Less => "(-)",
+                    std::cmp::Ordering::Equal => "",
+                    std::cmp::Ordering::Greater => "(+)",
+                },
+            ));
+            ui.monospace(&format!(
+                "{label:20}: {kb:6.1}KB/s",
+                label = localization.get("bytes-sent"),
+                kb = latest.bytes_sent_per_second as f64 / 1000.0,
+            ));
+            ui.monospace(&format!(
+                "{label:20}: {kb:6.1}KB/s",
+                label = localization.get("bytes-received"),
+                kb = latest.bytes_received_per_second as f64 / 1000.0,
+            ));
+
+            // Plot the history of each statistic, one point per second
+            let line = |name: String, value: fn(&NetworkDiagnosticsSample) -> Option<f64>| {
+                let points: PlotPoints = diagnostics
+                    .samples
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, sample)| value(sample).map(|y| [i as f64, y]))
+                    .collect();
+                Line::new(points).name(name)
+            };
+
+            ui.add_space(2.0);
+            Plot::new("network_latency_plot")
+                .height(150.0)
+                .legend(Legend::default())
+                .include_y(0.0)
+                .show(ui, |plot| {
+                    for player in 0..MAX_PLAYERS {
+                        if latest.ping[player].is_none() {
+                            continue;
+                        }
+                        let points: PlotPoints = diagnostics
+                            .samples
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(i, sample)| {
+                                sample.ping[player].map(|ping| [i as f64, ping as f64])
+                            })
+                            .collect();
+                        plot.line(
+                            Line::new(points).name(
+                                localization.get(&format!("player-ping?player={}", player + 1)),
+                            ),
+                        );
+                    }
+                    plot.line(line(localization.get("rollback-frames"), |sample| {
+                        Some(sample.rollback_frames as f64)
+                    }));
+                    plot.line(line(localization.get("predicted-frames"), |sample| {
+                        Some(sample.predicted_frames as f64)
+                    }));
+                    plot.line(line(localization.get("input-delay-frames"), |sample| {
+                        Some(sample.input_delay as f64)
+                    }));
+                });
+            Plot::new("network_traffic_plot")
+                .height(150.0)
+                .legend(Legend::default())
+                .include_y(0.0)
+                .show(ui, |plot| {
+                    plot.line(line(localization.get("bytes-sent"), |sample| {
+                        Some(sample.bytes_sent_per_second as f64 / 1000.0)
+                    }));
+                    plot.line(line(localization.get("bytes-received"), |sample| {
+                        Some(sample.bytes_received_per_second as f64 / 1000.0)
+                    }));
+                });
+        });
+}
@@ -0,0 +1,59 @@
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+use crate::{networking::DesyncDetected, prelude::*};
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+/// How long the de-sync toast stays on screen.
+const TOAST_DURATION: f32 = 5.0;
+
+pub struct DesyncToastPlugin;
+
+impl Plugin for DesyncToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(desync_toast.run_if(resource_exists::<DesyncDetected>()));
+    }
+}
+
+/// Briefly shows a warning whenever a network de-sync is detected.
+fn desync_toast(
+    mut commands: Commands,
+    mut shown_at: Local<f32>,
+    desync: Res<DesyncDetected>,
+    time: Res<Time>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    mut contexts: EguiContexts,
+) {
+    if desync.is_changed() {
+        *shown_at = time.elapsed_seconds();
+    }
+    if time.elapsed_seconds() - *shown_at > TOAST_DURATION {
+        commands.remove_resource::<DesyncDetected>();
+        return;
+    }
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+    let frame = desync.frame;
+    let player = desync.player + 1;
+
+    egui::Area::new("desync_toast")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-font.size, font.size))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(
+                        &font,
+                        &localization
+                            .get(&format!("desync-detected?frame={frame}&player={player}")),
+                    );
+                });
+        });
+}
@@ -0,0 +1,75 @@
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use jumpy_core::input::{ConnectionStatus, PlayerInputs};
+
+use crate::prelude::*;
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+pub struct DisconnectedPlayersPlugin;
+
+impl Plugin for DisconnectedPlayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            disconnected_players_overlay
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Lists the network players that have lost their connection during the match.
+fn disconnected_players_overlay(
+    mut session: ResMut<Session>,
+    mut contexts: EguiContexts,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    if session.network_player_idx().is_none() {
+        return;
+    }
+
+    let disconnected = session
+        .world()
+        .run_initialized_system(|player_inputs: bones::Res<PlayerInputs>| {
+            Ok(player_inputs
+                .players
+                .iter()
+                .enumerate()
+                .filter(|(_, input)| input.connection != ConnectionStatus::Connected)
+                .map(|(idx, input)| (idx, input.connection))
+                .collect::<Vec<_>>())
+        })
+        .unwrap();
+
+    if disconnected.is_empty() {
+        return;
+    }
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("disconnected_players")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(font.size, font.size))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    for (player_idx, connection) in disconnected {
+                        let player = player_idx + 1;
+                        let key = match connection {
+                            ConnectionStatus::Removed => "player-left",
+                            _ => "player-disconnected",
+                        };
+                        ui.themed_label(
+                            &font,
+                            &localization.get(&format!("{key}?player={player}")),
+                        );
+                    }
+                });
+        });
+}
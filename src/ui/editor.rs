@@ -1,25 +1,67 @@
-use super::{widget, widgets::bordered_button::BorderedButton, WidgetSystem};
-use crate::prelude::*;
-use bevy::{ecs::system::SystemParam, math::Vec3Swizzles, window::PrimaryWindow};
+use super::{
+    map_thumbnails::{MapThumbnails, ThumbnailKey},
+    map_validation::{map_problem_message, validate_map},
+    widget,
+    widgets::bordered_button::BorderedButton,
+    WidgetAdjacencies, WidgetSystem,
+};
+use crate::{networking::NetworkMatchSocket, prelude::*};
+use bevy::{
+    ecs::system::SystemParam, math::Vec3Swizzles, tasks::IoTaskPool, window::PrimaryWindow,
+};
 use bevy_egui::*;
 use bevy_fluent::Localization;
 use bones_bevy_renderer::BevyBonesEntity;
 use jumpy_core::{
+    editor::EditorHistory,
+    elements::item_spawner,
     input::{ElementLayer, TileLayer},
-    physics::TileCollisionKind,
+    physics::{TileCollisionKind, TileSurface},
+    random::GlobalRng,
 };
 use std::marker::PhantomData;
 
+mod autosave;
+mod layers;
+mod presence;
+use autosave::*;
+use layers::*;
+use presence::*;
+
 pub struct EditorPlugin;
 
 impl Plugin for EditorPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<EditorState>()
+            .init_resource::<MapImportChannel>()
+            .init_resource::<EditorMapExport>()
+            .init_resource::<RemoteEditorPresence>()
+            .init_resource::<EditorAutosaves>()
+            .register_storage_item::<EditorMapSaveTimes>()
             .add_system(
                 editor_ui_system
                     .run_if(in_state(EngineState::InGame))
                     .run_if(in_state(GameEditorState::Visible)),
             )
+            .add_system(
+                exchange_editor_presence
+                    .after(editor_ui_system)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(in_state(GameEditorState::Visible))
+                    .run_if(resource_exists::<NetworkMatchSocket>()),
+            )
+            .add_system(
+                update_autosaves
+                    .after(editor_ui_system)
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(in_state(GameEditorState::Visible))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(load_autosaves.in_schedule(OnEnter(GameEditorState::Visible)))
+            .add_system(reset_autosaves.in_schedule(OnExit(EngineState::InGame)))
+            .add_system(toggle_playtest.run_if(in_state(EngineState::InGame)))
+            .add_system(end_playtest.in_schedule(OnEnter(GameEditorState::Visible)))
+            .add_system(discard_playtest.in_schedule(OnExit(EngineState::InGame)))
             .add_system(cleanup_editor.in_schedule(OnExit(GameEditorState::Visible)));
     }
 }
@@ -37,9 +79,12 @@ struct EditorState {
     pub current_layer_idx: usize,
     pub current_tilemap_tile: usize,
     pub current_collision: TileCollisionKind,
+    /// The surface given to tiles placed with the tile tool.
+    pub current_surface: TileSurface,
     pub current_tool: EditorTool,
     pub camera: EditorCameraPos,
-    // pub hidden_layers: HashSet<usize>,
+    /// The map element that is selected in the element inspector.
+    pub selected_element: Option<bones::Entity>,
 }
 
 impl Default for EditorState {
@@ -49,8 +94,10 @@ impl Default for EditorState {
             current_layer_idx: Default::default(),
             current_tilemap_tile: Default::default(),
             current_collision: TileCollisionKind::Solid,
+            current_surface: default(),
             current_tool: Default::default(),
             camera: Default::default(),
+            selected_element: None,
         }
     }
 }
@@ -60,12 +107,94 @@ pub struct UserMapStorage(pub HashMap<String, MapMeta>);
 
 impl UserMapStorage {
     pub const STORAGE_KEY: &str = "user_maps";
+    /// The storage key for maps that were imported from files, which are kept apart from the maps
+    /// made in the editor.
+    pub const CUSTOM_STORAGE_KEY: &str = "custom_maps";
+}
+
+/// The grid that elements snap to when they are placed or dragged in the editor, saved in
+/// [`Storage`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SnapMode {
+    #[default]
+    Off,
+    HalfTile,
+    FullTile,
+    /// Snap to a grid with cells of the given number of pixels.
+    Custom(u32),
+}
+
+impl SnapMode {
+    pub const STORAGE_KEY: &str = "editor_snap_mode";
+
+    /// Get the size of the grid cells for a map with the given tile size, or [`None`] if snapping
+    /// is off.
+    pub fn grid_size(&self, tile_size: Vec2) -> Option<Vec2> {
+        match self {
+            SnapMode::Off => None,
+            SnapMode::HalfTile => Some(tile_size / 2.0),
+            SnapMode::FullTile => Some(tile_size),
+            SnapMode::Custom(pixels) => Some(Vec2::splat((*pixels).max(1) as f32)),
+        }
+    }
+
+    /// Snap the position of an element so that the bottom center of its editor grab box lies on
+    /// the snapping grid.
+    pub fn snap_element(
+        &self,
+        pos: Vec2,
+        editor_meta: &ElementEditorMeta,
+        tile_size: Vec2,
+    ) -> Vec2 {
+        let Some(grid_size) = self.grid_size(tile_size) else {
+            return pos;
+        };
+        let bottom_center_offset =
+            -editor_meta.grab_offset + Vec2::new(0.0, editor_meta.grab_size.y / 2.0);
+        let bottom_center = pos - bottom_center_offset;
+        let snapped_bottom_center = (bottom_center / grid_size).round() * grid_size;
+
+        snapped_bottom_center + bottom_center_offset
+    }
+}
+
+/// Channel that the contents of map files picked for import are sent through, since the file
+/// dialog runs in the background.
+#[derive(Resource)]
+struct MapImportChannel {
+    sender: async_channel::Sender<Vec<u8>>,
+    receiver: async_channel::Receiver<Vec<u8>>,
+}
+
+impl Default for MapImportChannel {
+    fn default() -> Self {
+        let (sender, receiver) = async_channel::unbounded();
+        Self { sender, receiver }
+    }
+}
+
+/// The outcome of importing a map file, shown until the player closes it.
+enum MapImportResult {
+    /// The map was imported under the given name, possibly with some warnings.
+    Imported {
+        name: String,
+        map_meta: MapMeta,
+        warnings: Vec<MapValidationError>,
+    },
+    /// The map has errors that stop it from being played, so it wasn't imported.
+    Invalid {
+        map_meta: MapMeta,
+        errors: Vec<MapValidationError>,
+    },
+    /// The file couldn't be read as a map.
+    Unreadable(String),
 }
 
 fn tile_collision_color(collision: TileCollisionKind) -> egui::Color32 {
     match collision {
         TileCollisionKind::Solid => egui::Color32::LIGHT_GRAY.linear_multiply(0.68),
         TileCollisionKind::JumpThrough => egui::Color32::GOLD,
+        TileCollisionKind::Breakable => egui::Color32::from_rgb(205, 94, 15),
         _ => egui::Color32::BLACK,
     }
 }
@@ -89,8 +218,20 @@ impl Default for EditorCameraPos {
 #[derive(Resource, Default, Deref, DerefMut)]
 struct EditorMapExport(Option<MapMeta>);
 
-#[derive(Default, PartialEq, Eq)]
-enum EditorTool {
+/// The current state of the element selected in the editor, if there is one.
+#[derive(Resource, Default, Deref, DerefMut)]
+struct EditorSelectedElement(Option<SelectedElement>);
+
+struct SelectedElement {
+    entity: bones::Entity,
+    handle: Handle<ElementMeta>,
+    pos: Vec2,
+    properties: ElementProperties,
+    waypoints: ElementWaypoints,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditorTool {
     #[default]
     Element,
     Tile,
@@ -107,6 +248,72 @@ impl EditorTool {
     }
 }
 
+/// Resource that exists while a map is being playtested from the editor.
+///
+/// The rest of the editor state, such as the camera position and the current layer and tool, is
+/// left alone during the playtest, so only the game world needs to be restored afterwards.
+#[derive(Resource)]
+struct EditorPlaytest {
+    /// The game world from before the playtest started, including any unsaved edits.
+    snapshot: bones::World,
+}
+
+/// Snapshot the game world and hide the editor, to playtest the map with a single player spawned
+/// near `pos`.
+fn start_playtest(commands: &mut Commands, session: &mut Session, pos: Vec2) {
+    let core = session.core_session();
+    commands.insert_resource(EditorPlaytest {
+        snapshot: core.snapshot(),
+    });
+    jumpy_core::editor::start_playtest(&core.world, pos);
+    commands.insert_resource(NextState(Some(GameEditorState::Hidden)));
+}
+
+/// Start a playtest when the editor toggle is pressed in the editor, and go back to the editor when
+/// it is pressed during a playtest.
+fn toggle_playtest(
+    mut commands: Commands,
+    mut session: Option<ResMut<Session>>,
+    state: Res<EditorState>,
+    editor_state: Res<State<GameEditorState>>,
+    playtest: Option<Res<EditorPlaytest>>,
+    menu_input: Query<&ActionState<MenuAction>>,
+) {
+    if !menu_input.single().just_pressed(MenuAction::ToggleEditor) {
+        return;
+    }
+
+    match editor_state.0 {
+        GameEditorState::Visible => {
+            let Some(session) = session.as_mut() else { return };
+            if session.downcast_ref::<LocalSessionRunner>().is_some() {
+                start_playtest(&mut commands, session, state.camera.pos);
+            }
+        }
+        GameEditorState::Hidden => {
+            if playtest.is_some() {
+                commands.insert_resource(NextState(Some(GameEditorState::Visible)));
+            }
+        }
+    }
+}
+
+/// Restore the game world from before the playtest when the editor is opened again.
+fn end_playtest(world: &mut World) {
+    let Some(mut playtest) = world.remove_resource::<EditorPlaytest>() else {
+        return;
+    };
+    if let Some(mut session) = world.get_resource_mut::<Session>() {
+        // Everything spawned during the playtest is thrown away with the playtest world.
+        session.core_session().restore(&mut playtest.snapshot);
+    }
+}
+
+/// Forget about the playtest if the game is left in the middle of it.
+fn discard_playtest(mut commands: Commands) {
+    commands.remove_resource::<EditorPlaytest>();
+}
+
 /// Resource that maps the map tileset paths to their egui textures.
 #[derive(Resource)]
 pub struct MapTilesetEguiTextures(pub HashMap<bones::AssetPath, MapTilesetEguiTextureinfo>);
@@ -134,6 +341,9 @@ pub fn cleanup_editor(session: Option<ResMut<Session>>) {
             .get::<jumpy_core::camera::CameraState>();
         let mut camera_states = camera_states.borrow_mut();
         camera_states.iter_mut().next().unwrap().disable_controller = false;
+
+        // Show the layers that were hidden in the editor
+        jumpy_core::editor::set_hidden_map_layers(session.world(), &[]);
     }
 }
 
@@ -191,6 +401,46 @@ pub fn editor_ui_system(world: &mut World) {
             .map(|mut sess| sess.core_session().export_map())
     };
     world.insert_resource(EditorMapExport(map_meta));
+    apply_hidden_layers(world);
+
+    // Get the selected element from the world, deselecting it if it no longer exists
+    let selected_element = {
+        let selected = world.resource::<EditorState>().selected_element;
+        let session = world.get_resource_mut::<Session>();
+        selected.zip(session).and_then(|(selected, mut session)| {
+            session
+                .world()
+                .run_initialized_system(
+                    move |entities: bones::Res<bones::Entities>,
+                          transforms: bones::Comp<bones::Transform>,
+                          element_handles: bones::Comp<jumpy_core::elements::ElementHandle>,
+                          element_properties: bones::Comp<ElementProperties>,
+                          element_waypoints: bones::Comp<ElementWaypoints>| {
+                        Ok(entities
+                            .iter_with((&element_handles, &transforms))
+                            .find(|(entity, _)| *entity == selected)
+                            .map(|(entity, (handle, transform))| SelectedElement {
+                                entity,
+                                handle: handle.get_bevy_handle(),
+                                pos: transform.translation.truncate(),
+                                properties: element_properties
+                                    .get(entity)
+                                    .cloned()
+                                    .unwrap_or_default(),
+                                waypoints: element_waypoints
+                                    .get(entity)
+                                    .cloned()
+                                    .unwrap_or_default(),
+                            }))
+                    },
+                )
+                .unwrap()
+        })
+    };
+    if selected_element.is_none() {
+        world.resource_mut::<EditorState>().selected_element = None;
+    }
+    world.insert_resource(EditorSelectedElement(selected_element));
 
     let mut state = world.resource_mut::<EditorState>();
     state.cursor.current_pos = cursor_pos;
@@ -223,6 +473,7 @@ pub fn editor_ui_system(world: &mut World) {
         .frame(egui::Frame::none())
         .show(egui_ctx, |ui| {
             widget::<EditorCentralPanel>(world, ui, "editor-central-panel".into(), ());
+            widget::<AutosaveRecoveryDialog>(world, ui, "autosave-recovery-dialog".into(), ());
         });
 }
 
@@ -251,6 +502,15 @@ struct EditorTopBar<'w, 's> {
     clipboard: ResMut<'w, bevy_egui::EguiClipboard>,
     map_export: Res<'w, EditorMapExport>,
     storage: ResMut<'w, Storage>,
+    thumbnails: ResMut<'w, MapThumbnails>,
+    autosaves: ResMut<'w, EditorAutosaves>,
+    editor_input: ResMut<'w, CurrentEditorInput>,
+    menu_input: Query<'w, 's, &'static ActionState<MenuAction>>,
+    map_assets: Res<'w, Assets<MapMeta>>,
+    element_assets: Res<'w, Assets<ElementMeta>>,
+    atlas_assets: Res<'w, Assets<TextureAtlas>>,
+    map_import_channel: Res<'w, MapImportChannel>,
+    map_import_result: Local<'s, Option<MapImportResult>>,
 }
 
 impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
@@ -265,7 +525,9 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
     ) {
         let mut params: EditorTopBar = state.get_mut(world);
 
+        receive_map_imports(&mut params);
         map_export_window(ui, &mut params);
+        map_import_window(ui, &mut params);
 
         ui.horizontal_centered(|ui| {
             ui.label(&params.localization.get("map-editor"));
@@ -308,6 +570,9 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
                 }
             }
 
+            ui.separator();
+            snap_mode_select(ui, &mut params);
+
             ui.add_space(ui.spacing().icon_spacing);
 
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -316,6 +581,9 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
                         .commands
                         .insert_resource(NextState(Some(EngineState::MainMenu)));
                 }
+                if ui.button(&params.localization.get("import-map")).clicked() {
+                    pick_map_file(params.map_import_channel.sender.clone());
+                }
 
                 ui.scope(|ui| {
                     ui.set_enabled(params.session_manager.session.is_some());
@@ -324,6 +592,24 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
                             .commands
                             .insert_resource(NextState(Some(GameEditorState::Hidden)));
                     }
+                    let is_local = params
+                        .session_manager
+                        .session
+                        .as_ref()
+                        .map(|session| session.downcast_ref::<LocalSessionRunner>().is_some())
+                        .unwrap_or_default();
+                    let playtest_button = ui
+                        .add_enabled(
+                            is_local,
+                            egui::Button::new(params.localization.get("playtest")),
+                        )
+                        .on_hover_text(params.localization.get("playtest-hint"));
+                    if playtest_button.clicked() {
+                        let pos = params.state.camera.pos;
+                        if let Some(session) = params.session_manager.session.as_mut() {
+                            start_playtest(&mut params.commands, session, pos);
+                        }
+                    }
                     if ui.button(&params.localization.get("export")).clicked() {
                         *params.show_map_export_window = true;
                     }
@@ -338,6 +624,8 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
                             meta: params.core_meta.0.clone(),
                             map_meta: params.map_export.0.as_ref().unwrap().clone(),
                             player_info: default(),
+                            mutators: default(),
+                            seed: GlobalRng::SEED,
                         });
                         params
                             .commands
@@ -354,15 +642,116 @@ impl<'w, 's> WidgetSystem for EditorTopBar<'w, 's> {
                                 .unwrap_or_default();
                             user_maps.insert(map.name.clone(), map.clone());
                             params.storage.set(UserMapStorage::STORAGE_KEY, &user_maps);
+                            let edits = params
+                                .session_manager
+                                .session
+                                .as_mut()
+                                .map(|session| {
+                                    session.world().resource::<EditorHistory>().borrow().edits()
+                                })
+                                .unwrap_or_default();
+                            params
+                                .autosaves
+                                .map_saved(&mut params.storage, &map.name, edits);
                             params.storage.save();
+                            params.thumbnails.regenerate(
+                                ThumbnailKey::user(UserMapStorage::STORAGE_KEY, map),
+                                map,
+                            );
                         }
                     }
+
+                    let (can_undo, can_redo) = params
+                        .session_manager
+                        .session
+                        .as_mut()
+                        .map(|session| {
+                            let history = session.world().resource::<EditorHistory>();
+                            let history = history.borrow();
+                            (history.can_undo(), history.can_redo())
+                        })
+                        .unwrap_or_default();
+                    // Leave Ctrl+Z to text boxes while one is being edited
+                    let (undo_key, redo_key) = if ui.ctx().wants_keyboard_input() {
+                        (false, false)
+                    } else {
+                        ui.input(|i| {
+                            let undo_redo = i.modifiers.command && i.key_pressed(egui::Key::Z);
+                            (
+                                undo_redo && !i.modifiers.shift,
+                                undo_redo && i.modifiers.shift,
+                            )
+                        })
+                    };
+                    let menu_input = params.menu_input.single();
+
+                    let redo_button = ui
+                        .add_enabled(can_redo, egui::Button::new(params.localization.get("redo")));
+                    if can_redo
+                        && (redo_button.clicked()
+                            || redo_key
+                            || menu_input.just_pressed(MenuAction::EditorRedo))
+                    {
+                        **params.editor_input = Some(EditorInput::Redo);
+                    }
+                    let undo_button = ui
+                        .add_enabled(can_undo, egui::Button::new(params.localization.get("undo")));
+                    if can_undo
+                        && (undo_button.clicked()
+                            || undo_key
+                            || menu_input.just_pressed(MenuAction::EditorUndo))
+                    {
+                        **params.editor_input = Some(EditorInput::Undo);
+                    }
                 });
             });
         });
     }
 }
 
+fn snap_mode_select(ui: &mut egui::Ui, params: &mut EditorTopBar) {
+    let mut snap_mode = params
+        .storage
+        .get::<SnapMode>(SnapMode::STORAGE_KEY)
+        .unwrap_or_default();
+    let previous_snap_mode = snap_mode;
+    let snap_mode_name = |snap_mode: SnapMode| match snap_mode {
+        SnapMode::Off => params.localization.get("snap-off"),
+        SnapMode::HalfTile => params.localization.get("snap-half-tile"),
+        SnapMode::FullTile => params.localization.get("snap-full-tile"),
+        SnapMode::Custom(_) => params.localization.get("snap-custom"),
+    };
+
+    ui.label(&params.localization.get("snap"));
+    egui::ComboBox::new("snap-mode-select", "")
+        .selected_text(snap_mode_name(snap_mode))
+        .show_ui(ui, |ui| {
+            for choice in [SnapMode::Off, SnapMode::HalfTile, SnapMode::FullTile] {
+                ui.selectable_value(&mut snap_mode, choice, snap_mode_name(choice));
+            }
+            let is_custom = matches!(snap_mode, SnapMode::Custom(_));
+            if ui
+                .selectable_label(is_custom, snap_mode_name(SnapMode::Custom(0)))
+                .clicked()
+                && !is_custom
+            {
+                snap_mode = SnapMode::Custom(8);
+            }
+        });
+    if let SnapMode::Custom(pixels) = &mut snap_mode {
+        ui.add(
+            egui::DragValue::new(pixels)
+                .clamp_range(1..=256)
+                .suffix("px"),
+        );
+    }
+
+    if snap_mode != previous_snap_mode {
+        params.storage.set(SnapMode::STORAGE_KEY, &snap_mode);
+        params.storage.save();
+    }
+}
+
 fn map_export_window(ui: &mut egui::Ui, params: &mut EditorTopBar) {
     if !*params.show_map_export_window {
         return;
@@ -405,6 +794,17 @@ fn map_export_window(ui: &mut egui::Ui, params: &mut EditorTopBar) {
                         *params.show_map_export_window = false;
                     }
 
+                    if BorderedButton::themed(
+                        &params.game.ui_theme.button_styles.small,
+                        &params.localization.get("export-map"),
+                    )
+                    .focus_on_hover(false)
+                    .show(ui)
+                    .clicked()
+                    {
+                        save_map_file(&map_meta.name, export.clone());
+                    }
+
                     if BorderedButton::themed(
                         &params.game.ui_theme.button_styles.small,
                         &params.localization.get("copy-to-clipboard"),
@@ -435,6 +835,191 @@ fn map_export_window(ui: &mut egui::Ui, params: &mut EditorTopBar) {
     );
 }
 
+/// Save an exported map to a file picked by the player, or download it when running on the web.
+fn save_map_file(name: &str, export: String) {
+    let file_name = format!("{name}.map.yaml");
+
+    #[cfg(not(target_arch = "wasm32"))]
+    IoTaskPool::get()
+        .spawn(async move {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("Map", &["yaml"])
+                .set_file_name(&file_name)
+                .save_file()
+                .await else { return };
+            if let Err(e) = std::fs::write(file.path(), export) {
+                error!("Could not export map: {e}");
+            }
+        })
+        .detach();
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        if let Err(e) = download_file(&file_name, &export) {
+            error!("Could not export map: {e:?}");
+        }
+    }
+}
+
+/// Have the browser download a file with the given contents.
+#[cfg(target_arch = "wasm32")]
+fn download_file(file_name: &str, contents: &str) -> Result<(), wasm_bindgen::JsValue> {
+    use wasm_bindgen::JsCast;
+
+    let blob = web_sys::Blob::new_with_str_sequence(&js_sys::Array::of1(&contents.into()))?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window().unwrap().document().unwrap();
+    let link: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+
+    web_sys::Url::revoke_object_url(&url)
+}
+
+/// Let the player pick a map file to import, and send its contents to the [`MapImportChannel`].
+fn pick_map_file(sender: async_channel::Sender<Vec<u8>>) {
+    IoTaskPool::get()
+        .spawn(async move {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("Map", &["yaml"])
+                .pick_file()
+                .await else { return };
+            sender.send(file.read().await).await.ok();
+        })
+        .detach();
+}
+
+/// Import any map files that the player has picked, saving them to the custom maps in [`Storage`].
+///
+/// Maps with errors are not imported, and maps with the same name as a built-in or previously
+/// imported map are renamed.
+fn receive_map_imports(params: &mut EditorTopBar) {
+    while let Ok(contents) = params.map_import_channel.receiver.try_recv() {
+        let mut map_meta: MapMeta = match serde_yaml::from_slice(&contents) {
+            Ok(map_meta) => map_meta,
+            Err(e) => {
+                warn!("Could not read imported map: {e}");
+                *params.map_import_result = Some(MapImportResult::Unreadable(e.to_string()));
+                continue;
+            }
+        };
+
+        let errors = validate_map(&map_meta, &params.element_assets, &params.atlas_assets);
+        if errors.iter().any(|error| error.is_fatal()) {
+            warn!(map = %map_meta.name, ?errors, "Imported map has errors");
+            *params.map_import_result = Some(MapImportResult::Invalid { map_meta, errors });
+            continue;
+        }
+
+        let mut custom_maps: UserMapStorage = params
+            .storage
+            .get(UserMapStorage::CUSTOM_STORAGE_KEY)
+            .unwrap_or_default();
+        let builtin_names = params
+            .core_meta
+            .stable_maps
+            .iter()
+            .chain(&params.core_meta.experimental_maps)
+            .filter_map(|handle| params.map_assets.get(&handle.get_bevy_handle()))
+            .map(|map_meta| map_meta.name.as_str())
+            .collect::<HashSet<_>>();
+        let name = unique_map_name(&map_meta.name, |name| {
+            builtin_names.contains(name) || custom_maps.contains_key(name)
+        });
+
+        info!(%name, "Imported map");
+        map_meta.name = name.clone();
+        custom_maps.insert(name.clone(), map_meta.clone());
+        params
+            .storage
+            .set(UserMapStorage::CUSTOM_STORAGE_KEY, &custom_maps);
+        params.storage.save();
+
+        *params.map_import_result = Some(MapImportResult::Imported {
+            name,
+            map_meta,
+            warnings: errors,
+        });
+    }
+}
+
+/// Get a name that isn't taken, by adding a number to the end of `name` if it is.
+fn unique_map_name(name: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|i| format!("{name} ({i})"))
+        .find(|name| !is_taken(name))
+        .unwrap()
+}
+
+fn map_import_window(ui: &mut egui::Ui, params: &mut EditorTopBar) {
+    let Some(result) = params.map_import_result.as_ref() else {
+        return;
+    };
+
+    let mut close = false;
+    overlay_window(
+        ui,
+        "import-map-window",
+        &params.localization.get("import-map"),
+        params.game.main_menu.menu_width,
+        |ui| {
+            let (message, map_meta, problems) = match result {
+                MapImportResult::Imported {
+                    name,
+                    map_meta,
+                    warnings,
+                } => (
+                    format!("{}: {name}", params.localization.get("map-imported")),
+                    Some(map_meta),
+                    warnings.as_slice(),
+                ),
+                MapImportResult::Invalid { map_meta, errors } => (
+                    params.localization.get("map-import-invalid"),
+                    Some(map_meta),
+                    errors.as_slice(),
+                ),
+                MapImportResult::Unreadable(error) => (
+                    format!(
+                        "{}: {error}",
+                        params.localization.get("map-import-unreadable")
+                    ),
+                    None,
+                    &[][..],
+                ),
+            };
+
+            ui.label(message);
+            if let Some(map_meta) = map_meta {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for error in problems {
+                        ui.label(map_problem_message(&params.localization, map_meta, error));
+                    }
+                });
+            }
+
+            ui.add_space(ui.spacing().icon_width);
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                close = BorderedButton::themed(
+                    &params.game.ui_theme.button_styles.small,
+                    &params.localization.get("close"),
+                )
+                .focus_on_hover(false)
+                .show(ui)
+                .clicked();
+            });
+        },
+    );
+
+    if close {
+        *params.map_import_result = None;
+    }
+}
+
 #[derive(SystemParam)]
 struct EditorLeftToolbar<'w, 's> {
     game: Res<'w, GameMeta>,
@@ -496,6 +1081,19 @@ struct EditorRightToolbar<'w, 's> {
     editor_input: ResMut<'w, CurrentEditorInput>,
     map_export: Res<'w, EditorMapExport>,
     tilesets: Res<'w, MapTilesetEguiTextures>,
+    map_assets: Res<'w, Assets<MapMeta>>,
+    element_assets: Res<'w, Assets<ElementMeta>>,
+    atlas_assets: Res<'w, Assets<TextureAtlas>>,
+    selected_element: Res<'w, EditorSelectedElement>,
+    remote_presence: Res<'w, RemoteEditorPresence>,
+    time: Res<'w, Time>,
+    storage: ResMut<'w, Storage>,
+    adjacencies: ResMut<'w, WidgetAdjacencies>,
+    /// The layer that is being dragged to a new position in the layer list.
+    dragged_layer: Local<'s, Option<usize>>,
+    layer_to_delete: Local<'s, Option<usize>>,
+    show_background_picker: Local<'s, bool>,
+    background_to_delete: Local<'s, Option<usize>>,
 }
 
 impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
@@ -510,6 +1108,9 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
     ) {
         let mut params: EditorRightToolbar = state.get_mut(world);
         layer_create_dialog(ui, &mut params);
+        layer_delete_dialog(ui, &mut params);
+        background_picker_dialog(ui, &mut params);
+        background_delete_dialog(ui, &mut params);
 
         let map_meta = params.map_export.0.as_ref();
 
@@ -642,6 +1243,10 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
         ui.separator();
 
         if let Some(map) = map_meta {
+            let mut layer_prefs = EditorLayerPrefs::load(&mut params.storage, &map.name);
+            let mut layer_prefs_changed = false;
+            let mut row_rects = Vec::with_capacity(map.layers.len());
+
             let row_height = ui.spacing().interact_size.y * 1.4;
             ui.push_id("layers", |ui| {
                 let width = ui.available_width() - ui.spacing().item_spacing.x * 4.0;
@@ -654,7 +1259,9 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                         ui.add_space(ui.spacing().item_spacing.x);
 
                         let row_rect = ui.max_rect();
-                        let mut response = ui.allocate_rect(row_rect, egui::Sense::click());
+                        row_rects.push(row_rect);
+                        let mut response =
+                            ui.allocate_rect(row_rect, egui::Sense::click_and_drag());
 
                         response = response.context_menu(|ui| {
                             params.state.current_layer_idx = i;
@@ -662,12 +1269,25 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                                 .button(&format!("🗑 {}", params.localization.get("delete-layer")))
                                 .clicked()
                             {
-                                **params.editor_input =
-                                    Some(EditorInput::DeleteLayer { layer: i as u8 });
+                                // Ask before deleting a layer that has something on it
+                                if layer.tiles.is_empty() && layer.elements.is_empty() {
+                                    delete_layer(
+                                        &mut params.state,
+                                        &mut params.editor_input,
+                                        i,
+                                        map.layers.len(),
+                                    );
+                                } else {
+                                    *params.layer_to_delete = Some(i);
+                                }
                                 ui.close_menu();
                             }
                         });
 
+                        if response.drag_started() {
+                            *params.dragged_layer = Some(i);
+                        }
+
                         let hovered = response.hovered();
                         let active = hovered && response.is_pointer_button_down_on();
                         let highlighted = hovered || params.state.current_layer_idx == i;
@@ -691,7 +1311,7 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
 
                         ui.allocate_ui_at_rect(row_rect.expand2(egui::vec2(-4.0, 0.0)), |ui| {
                             ui.vertical(|ui| {
-                                ui.set_width(width * 0.8);
+                                ui.set_width(width * 0.6);
                                 ui.add_space(ui.spacing().interact_size.y * 0.2);
 
                                 #[derive(Clone)]
@@ -704,6 +1324,7 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                                 if let Some(mut data) = edit_data {
                                     let output =
                                         egui::TextEdit::singleline(&mut data.name).show(ui);
+                                    params.adjacencies.text_boxes.insert(output.response.id);
                                     output.response.request_focus();
                                     if output.cursor_range.is_none() {
                                         use egui::text::{CCursor, CCursorRange};
@@ -720,6 +1341,8 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                                     }
                                     if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
                                         ui.data_mut(|d| d.remove::<EditingLayerName>(response.id));
+                                        layer_prefs.rename(&layer.id, &data.name);
+                                        layer_prefs_changed = true;
                                         **params.editor_input = Some(EditorInput::RenameLayer {
                                             layer: i as u8,
                                             name: data.name,
@@ -732,7 +1355,16 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                                         ui.data_mut(|d| d.insert_temp(response.id, data));
                                     }
                                 } else {
-                                    ui.label(&layer.id);
+                                    ui.horizontal(|ui| {
+                                        ui.label(&layer.id);
+                                        layer_editors(
+                                            ui,
+                                            &params.remote_presence,
+                                            &params.localization,
+                                            params.time.elapsed_seconds(),
+                                            i,
+                                        );
+                                    });
                                     if response.double_clicked() {
                                         ui.data_mut(|d| {
                                             d.insert_temp(
@@ -746,6 +1378,26 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                                 }
                             });
 
+                            // Visibility and lock toggles
+                            let is_hidden = layer_prefs.is_hidden(layer);
+                            if ui
+                                .selectable_label(!is_hidden, "👁")
+                                .on_hover_text(params.localization.get("toggle-visibility"))
+                                .clicked()
+                            {
+                                toggle_layer_pref(&mut layer_prefs.hidden, &layer.id);
+                                layer_prefs_changed = true;
+                            }
+                            let is_locked = layer_prefs.is_locked(layer);
+                            if ui
+                                .selectable_label(is_locked, "🔒")
+                                .on_hover_text(params.localization.get("toggle-lock"))
+                                .clicked()
+                            {
+                                toggle_layer_pref(&mut layer_prefs.locked, &layer.id);
+                                layer_prefs_changed = true;
+                            }
+
                             ui.scope(|ui| {
                                 ui.set_enabled(i > 0);
                                 // Up button
@@ -786,27 +1438,51 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                                 }
                             });
                         });
-
-                        // ui.vertical_centered(|ui| {
-                        //     ui.set_width(width * 0.1);
-                        //     ui.add_space(ui.spacing().interact_size.y * 0.2);
-                        //     let is_visible = !params.state.hidden_layers.contains(&i);
-                        //     if ui
-                        //         .selectable_label(is_visible, "👁")
-                        //         .on_hover_text(params.localization.get("toggle-visibility"))
-                        //         .clicked()
-                        //     {
-                        //         if is_visible {
-                        //             params.state.hidden_layers.insert(i);
-                        //         } else {
-                        //             params.state.hidden_layers.remove(&i);
-                        //         }
-                        //     };
-                        // });
                     });
                 }
             });
+
+            // Drop a dragged layer in front of the row under the cursor
+            if let Some(from) = *params.dragged_layer {
+                let pointer = ui.input(|i| i.pointer.interact_pos());
+                let to = pointer
+                    .map(|pos| {
+                        row_rects
+                            .iter()
+                            .position(|rect| pos.y < rect.bottom())
+                            .unwrap_or(row_rects.len().saturating_sub(1))
+                    })
+                    .unwrap_or(from);
+
+                if let Some(rect) = row_rects.get(to).filter(|_| to != from) {
+                    let y = if to > from { rect.bottom() } else { rect.top() };
+                    ui.painter().hline(
+                        rect.left()..=rect.right(),
+                        y,
+                        ui.visuals().widgets.active.fg_stroke,
+                    );
+                }
+
+                if ui.input(|i| i.pointer.any_released()) {
+                    *params.dragged_layer = None;
+                    if to != from {
+                        params.state.current_layer_idx =
+                            reordered_layer_idx(params.state.current_layer_idx, from, to);
+                        **params.editor_input = Some(EditorInput::ReorderLayer {
+                            layer: from as u8,
+                            to: to as u8,
+                        });
+                    }
+                }
+            }
+
+            if layer_prefs_changed {
+                layer_prefs.save(&mut params.storage, &map.name);
+            }
         }
+        element_inspector(ui, &mut params);
+        backgrounds_section(ui, &mut params);
+        problems_section(ui, &params);
 
         // Collision section
         if params.state.current_tool == EditorTool::Collision
@@ -824,6 +1500,10 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
                     TileCollisionKind::JumpThrough,
                     params.localization.get("jump-through"),
                 ),
+                (
+                    TileCollisionKind::Breakable,
+                    params.localization.get("breakable"),
+                ),
                 (TileCollisionKind::Empty, params.localization.get("empty")),
             ] {
                 let color = tile_collision_color(collision);
@@ -839,6 +1519,29 @@ impl<'w, 's> WidgetSystem for EditorRightToolbar<'w, 's> {
             }
         }
 
+        // Surface section
+        if params.state.current_tool == EditorTool::Tile {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(&params.localization.get("surface"));
+            });
+            ui.separator();
+
+            let surface = &mut params.state.current_surface;
+            ui.horizontal(|ui| {
+                ui.label(&params.localization.get("surface-friction"));
+                ui.add(
+                    egui::DragValue::new(&mut surface.friction)
+                        .clamp_range(0.0..=2.0)
+                        .speed(0.01),
+                );
+            });
+            ui.checkbox(
+                &mut surface.sticky,
+                &params.localization.get("surface-sticky"),
+            );
+        }
+
         // Tilemap section
         if params.state.current_tool == EditorTool::Tile {
             if let Some(map_meta) = map_meta {
@@ -1023,45 +1726,604 @@ fn layer_create_dialog(ui: &mut egui::Ui, params: &mut EditorRightToolbar) {
     );
 }
 
-#[derive(SystemParam)]
-struct EditorCentralPanel<'w, 's> {
-    show_map_create: Local<'s, bool>,
-    show_map_open: Local<'s, bool>,
-    map_create_info: Local<'s, MapCreateInfo>,
-    game: Res<'w, GameMeta>,
-    core_meta: Res<'w, CoreMetaArc>,
-    state: ResMut<'w, EditorState>,
-    map_assets: Res<'w, Assets<MapMeta>>,
-    element_assets: Res<'w, Assets<ElementMeta>>,
-    localization: Res<'w, Localization>,
-    session_manager: SessionManager<'w, 's>,
-    editor_input: ResMut<'w, CurrentEditorInput>,
-    camera: CameraQuery<'w, 's>,
-    map: Res<'w, EditorMapExport>,
-    storage: ResMut<'w, Storage>,
-}
-
-struct MapCreateInfo {
-    name: String,
-    map_width: u32,
-    map_height: u32,
-}
-
-impl Default for MapCreateInfo {
-    fn default() -> Self {
-        Self {
-            name: default(),
-            map_width: 27,
-            map_height: 21,
-        }
-    }
-}
+/// Asks for confirmation before deleting a layer that has tiles or elements on it, warning about
+/// any player spawners that would be deleted with it.
+fn layer_delete_dialog(ui: &mut egui::Ui, params: &mut EditorRightToolbar) {
+    let Some(layer_idx) = *params.layer_to_delete else {
+        return;
+    };
+    let Some(map) = params.map_export.0.as_ref() else {
+        *params.layer_to_delete = None;
+        return;
+    };
+    let Some(layer) = map.layers.get(layer_idx) else {
+        *params.layer_to_delete = None;
+        return;
+    };
+    let space = ui.spacing().icon_width;
 
-impl MapCreateInfo {
-    fn is_valid(&self) -> bool {
-        !self.name.is_empty() && self.map_width > 10 && self.map_height > 10
-    }
-}
+    let spawner_count = |layer: &MapLayerMeta| {
+        layer
+            .elements
+            .iter()
+            .filter_map(|spawn| params.element_assets.get(&spawn.element.get_bevy_handle()))
+            .filter(|meta| matches!(meta.builtin, BuiltinElementKind::PlayerSpawner))
+            .count()
+    };
+    let spawners = spawner_count(layer);
+    let total_spawners = map.layers.iter().map(spawner_count).sum::<usize>();
+
+    overlay_window(
+        ui,
+        "layer-delete-window",
+        &params.localization.get("delete-layer"),
+        params.game.main_menu.menu_width,
+        |ui| {
+            ui.label(&layer.id);
+            ui.label(&params.localization.get(&format!(
+                "delete-layer-contents?tiles={}&elements={}",
+                layer.tiles.len(),
+                layer.elements.len()
+            )));
+
+            if spawners > 0 {
+                ui.add_space(space / 2.0);
+                let warning = if spawners == total_spawners {
+                    params.localization.get("delete-layer-all-spawners-warning")
+                } else {
+                    params.localization.get(&format!(
+                        "delete-layer-spawners-warning?spawners={spawners}&total={total_spawners}"
+                    ))
+                };
+                ui.label(
+                    egui::RichText::new(warning)
+                        .color(ui.visuals().error_fg_color)
+                        .strong(),
+                );
+            }
+
+            ui.add_space(space);
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                if BorderedButton::themed(
+                    &params.game.ui_theme.button_styles.small,
+                    &params.localization.get("delete"),
+                )
+                .focus_on_hover(false)
+                .show(ui)
+                .clicked()
+                {
+                    *params.layer_to_delete = None;
+                    delete_layer(
+                        &mut params.state,
+                        &mut params.editor_input,
+                        layer_idx,
+                        map.layers.len(),
+                    );
+                }
+
+                ui.add_space(space);
+
+                if BorderedButton::themed(
+                    &params.game.ui_theme.button_styles.small,
+                    &params.localization.get("cancel"),
+                )
+                .focus_on_hover(false)
+                .show(ui)
+                .clicked()
+                {
+                    *params.layer_to_delete = None;
+                }
+            });
+        },
+    );
+}
+
+/// Delete a layer, keeping the current layer selected, or selecting the one next to it if it is
+/// the one being deleted.
+fn delete_layer(
+    state: &mut EditorState,
+    editor_input: &mut CurrentEditorInput,
+    layer: usize,
+    layer_count: usize,
+) {
+    state.current_layer_idx = if state.current_layer_idx > layer {
+        state.current_layer_idx - 1
+    } else {
+        state.current_layer_idx.min(layer_count.saturating_sub(2))
+    };
+    **editor_input = Some(EditorInput::DeleteLayer { layer: layer as u8 });
+}
+
+/// Add a layer to a set of hidden or locked layers, or remove it if it's already in it.
+fn toggle_layer_pref(layers: &mut HashSet<String>, layer: &str) {
+    if !layers.remove(layer) {
+        layers.insert(layer.to_string());
+    }
+}
+
+/// Get the file name of an image to show in the editor.
+fn image_name(image: &bones::Handle<Image>) -> String {
+    image
+        .path
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Get a parallax layer for each of the distinct background images used by the loaded maps.
+///
+/// These are used as the starting point for new background layers, since they come with the image
+/// size already filled in.
+fn background_image_choices(map_assets: &Assets<MapMeta>) -> Vec<ParallaxLayerMeta> {
+    let mut choices = HashMap::<bones::AssetPath, ParallaxLayerMeta>::new();
+    for (_, map) in map_assets.iter() {
+        for layer in &map.background.layers {
+            choices
+                .entry(layer.image.path.clone())
+                .or_insert_with(|| layer.clone());
+        }
+    }
+    let mut choices = choices.into_values().collect::<Vec<_>>();
+    choices.sort_by_key(|layer| layer.image.path.path.clone());
+
+    choices
+}
+
+/// Shows the selected element's name, position, and properties, and lets them be changed.
+fn element_inspector(ui: &mut egui::Ui, params: &mut EditorRightToolbar) {
+    let Some(selected) = params.selected_element.0.as_ref() else {
+        return;
+    };
+    let Some(element_meta) = params.element_assets.get(&selected.handle) else {
+        return;
+    };
+    let entity = selected.entity;
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label(&params.localization.get("element-inspector"));
+
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui
+                .button("✖")
+                .on_hover_text(params.localization.get("deselect"))
+                .clicked()
+            {
+                params.state.selected_element = None;
+            }
+        });
+    });
+    ui.separator();
+
+    let mut input = None;
+    egui::Grid::new("element-inspector")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label(&params.localization.get("element"));
+            ui.label(&element_meta.name);
+            ui.end_row();
+
+            ui.label(&params.localization.get("position"));
+            ui.horizontal(|ui| {
+                let mut pos = selected.pos;
+                let x_changed = ui.add(egui::DragValue::new(&mut pos.x)).changed();
+                let y_changed = ui.add(egui::DragValue::new(&mut pos.y)).changed();
+                if x_changed || y_changed {
+                    input = Some(EditorInput::MoveEntity { entity, pos });
+                }
+            });
+            ui.end_row();
+
+            for property in &element_meta.editor.properties {
+                let overridden = selected.properties.get(&property.key).copied();
+                let mut value = overridden.unwrap_or(property.default);
+
+                ui.label(&property.name);
+                ui.horizontal(|ui| {
+                    let changed = match &mut value {
+                        ElementPropertyValue::Bool(value) => ui.checkbox(value, "").changed(),
+                        ElementPropertyValue::Number(value) => {
+                            ui.add(egui::DragValue::new(value).speed(0.1)).changed()
+                        }
+                    };
+                    if changed {
+                        input = Some(EditorInput::SetElementProperty {
+                            entity,
+                            key: property.key.clone(),
+                            value: Some(value),
+                        });
+                    }
+
+                    if overridden.is_some()
+                        && ui
+                            .button("⟲")
+                            .on_hover_text(params.localization.get("reset-to-default"))
+                            .clicked()
+                    {
+                        input = Some(EditorInput::SetElementProperty {
+                            entity,
+                            key: property.key.clone(),
+                            value: None,
+                        });
+                    }
+                });
+                ui.end_row();
+            }
+
+            if matches!(element_meta.builtin, BuiltinElementKind::MovingPlatform(_)) {
+                ui.label(&params.localization.get("waypoints"));
+                ui.horizontal(|ui| {
+                    ui.label(selected.waypoints.len().to_string());
+                    if ui
+                        .button("➕")
+                        .on_hover_text(params.localization.get("add-waypoint"))
+                        .clicked()
+                    {
+                        // Put the new waypoint a little past the end of the path
+                        let last = selected.waypoints.last().copied().unwrap_or_default();
+                        let mut waypoints = selected.waypoints.clone();
+                        waypoints.push(last + NEW_WAYPOINT_OFFSET);
+                        input = Some(EditorInput::SetElementWaypoints { entity, waypoints });
+                    }
+                });
+                ui.end_row();
+            }
+
+            // Let the weight of each item in a spawner's or a crate's table be changed for this
+            // element
+            let item_table = match &element_meta.builtin {
+                BuiltinElementKind::ItemSpawner(meta) => meta.items.as_slice(),
+                BuiltinElementKind::BreakableCrate(meta) => meta.loot.as_slice(),
+                _ => &[],
+            };
+            for (i, entry) in item_table.iter().enumerate() {
+                let key = item_spawner::weight_property_key(i);
+                let overridden = selected
+                    .properties
+                    .get(&key)
+                    .and_then(|value| value.as_number());
+                let mut weight = overridden.unwrap_or(entry.weight);
+                let item_name = params
+                    .element_assets
+                    .get(&entry.element.get_bevy_handle())
+                    .map(|item| item.name.as_str())
+                    .unwrap_or_default();
+
+                ui.label(format!(
+                    "{}: {item_name}",
+                    params.localization.get("spawn-weight")
+                ));
+                ui.horizontal(|ui| {
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut weight)
+                                .speed(0.1)
+                                .clamp_range(0.0..=f32::MAX),
+                        )
+                        .changed()
+                    {
+                        input = Some(EditorInput::SetElementProperty {
+                            entity,
+                            key: key.clone(),
+                            value: Some(ElementPropertyValue::Number(weight)),
+                        });
+                    }
+
+                    if overridden.is_some()
+                        && ui
+                            .button("⟲")
+                            .on_hover_text(params.localization.get("reset-to-default"))
+                            .clicked()
+                    {
+                        input = Some(EditorInput::SetElementProperty {
+                            entity,
+                            key,
+                            value: None,
+                        });
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+    if let Some(input) = input {
+        **params.editor_input = Some(input);
+    }
+}
+
+/// How far from the end of an element's path a waypoint added in the inspector is placed.
+const NEW_WAYPOINT_OFFSET: Vec2 = Vec2::new(64.0, 0.0);
+
+/// The size of the markers that an element's waypoints are dragged around with.
+const WAYPOINT_MARKER_SIZE: f32 = 10.0;
+
+/// Lists any problems with the map being edited, so they can be fixed before the map is played.
+fn problems_section(ui: &mut egui::Ui, params: &EditorRightToolbar) {
+    let Some(map) = params.map_export.0.as_ref() else {
+        return;
+    };
+    let errors = validate_map(map, &params.element_assets, &params.atlas_assets);
+    if errors.is_empty() {
+        return;
+    }
+
+    ui.separator();
+    ui.label(&params.localization.get("map-problems"));
+    ui.separator();
+
+    for error in &errors {
+        let color = if error.is_fatal() {
+            ui.visuals().error_fg_color
+        } else {
+            ui.visuals().warn_fg_color
+        };
+        ui.colored_label(color, map_problem_message(&params.localization, map, error));
+    }
+}
+
+fn backgrounds_section(ui: &mut egui::Ui, params: &mut EditorRightToolbar) {
+    let Some(map) = params.map_export.0.as_ref() else {
+        return;
+    };
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label(&params.localization.get("backgrounds"));
+
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui
+                .button("➕")
+                .on_hover_text(params.localization.get("add-background"))
+                .clicked()
+            {
+                *params.show_background_picker = true;
+            }
+        });
+    });
+    ui.separator();
+
+    let choices = background_image_choices(&params.map_assets);
+    let layer_count = map.background.layers.len();
+    for (i, layer) in map.background.layers.iter().enumerate() {
+        egui::CollapsingHeader::new(image_name(&layer.image))
+            .id_source(("background-layer", i))
+            .show(ui, |ui| {
+                let mut meta = layer.clone();
+                let mut changed = false;
+
+                egui::Grid::new(("background-layer-settings", i))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label(&params.localization.get("background-image"));
+                        egui::ComboBox::new(("background-image-select", i), "")
+                            .selected_text(image_name(&meta.image))
+                            .show_ui(ui, |ui| {
+                                for choice in &choices {
+                                    if ui
+                                        .selectable_label(
+                                            choice.image.path == meta.image.path,
+                                            image_name(&choice.image),
+                                        )
+                                        .clicked()
+                                    {
+                                        meta.image = choice.image.clone();
+                                        meta.size = choice.size;
+                                        changed = true;
+                                    }
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label(&params.localization.get("background-depth"));
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut meta.depth).speed(0.05))
+                            .changed();
+                        ui.end_row();
+
+                        ui.label(&params.localization.get("background-scale"));
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut meta.scale)
+                                    .speed(0.05)
+                                    .clamp_range(0.01..=f32::MAX),
+                            )
+                            .changed();
+                        ui.end_row();
+
+                        ui.label(&params.localization.get("background-offset"));
+                        ui.horizontal(|ui| {
+                            changed |= ui.add(egui::DragValue::new(&mut meta.offset.x)).changed();
+                            changed |= ui.add(egui::DragValue::new(&mut meta.offset.y)).changed();
+                        });
+                        ui.end_row();
+
+                        ui.label(&params.localization.get("background-tiling"));
+                        changed |= ui.checkbox(&mut meta.tiling, "").changed();
+                        ui.end_row();
+                    });
+
+                if changed {
+                    **params.editor_input = Some(EditorInput::UpdateBackgroundLayer {
+                        layer: i as u8,
+                        meta,
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(i > 0, egui::Button::new("⏶"))
+                        .on_hover_text(params.localization.get("move-back"))
+                        .clicked()
+                    {
+                        **params.editor_input = Some(EditorInput::MoveBackgroundLayer {
+                            layer: i as u8,
+                            down: false,
+                        });
+                    }
+                    if ui
+                        .add_enabled(i + 1 < layer_count, egui::Button::new("⏷"))
+                        .on_hover_text(params.localization.get("move-forward"))
+                        .clicked()
+                    {
+                        **params.editor_input = Some(EditorInput::MoveBackgroundLayer {
+                            layer: i as u8,
+                            down: true,
+                        });
+                    }
+                    if ui
+                        .button(&format!("🗑 {}", params.localization.get("delete")))
+                        .clicked()
+                    {
+                        *params.background_to_delete = Some(i);
+                    }
+                });
+            });
+    }
+}
+
+fn background_picker_dialog(ui: &mut egui::Ui, params: &mut EditorRightToolbar) {
+    if !*params.show_background_picker {
+        return;
+    }
+
+    let choices = background_image_choices(&params.map_assets);
+    overlay_window(
+        ui,
+        "background-picker-window",
+        &params.localization.get("add-background"),
+        params.game.main_menu.menu_width,
+        |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(params.game.main_menu.menu_width)
+                .show(ui, |ui| {
+                    if choices.is_empty() {
+                        ui.label(&params.localization.get("no-background-images"));
+                    }
+                    for choice in choices {
+                        if ui.button(image_name(&choice.image)).clicked() {
+                            *params.show_background_picker = false;
+                            **params.editor_input = Some(EditorInput::CreateBackgroundLayer {
+                                meta: ParallaxLayerMeta {
+                                    depth: 1.0,
+                                    offset: Vec2::ZERO,
+                                    tiling: true,
+                                    ..choice
+                                },
+                            });
+                        }
+                    }
+                });
+
+            ui.add_space(ui.spacing().icon_width);
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                if BorderedButton::themed(
+                    &params.game.ui_theme.button_styles.small,
+                    &params.localization.get("cancel"),
+                )
+                .focus_on_hover(false)
+                .show(ui)
+                .clicked()
+                {
+                    *params.show_background_picker = false;
+                }
+            });
+        },
+    );
+}
+
+/// Asks for confirmation before deleting a background layer, because it can't be undone.
+fn background_delete_dialog(ui: &mut egui::Ui, params: &mut EditorRightToolbar) {
+    let Some(layer) = *params.background_to_delete else {
+        return;
+    };
+    let space = ui.spacing().icon_width;
+
+    overlay_window(
+        ui,
+        "background-delete-window",
+        &params.localization.get("delete-background"),
+        params.game.main_menu.menu_width,
+        |ui| {
+            ui.label(&params.localization.get("delete-background-warning"));
+
+            ui.add_space(space);
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                if BorderedButton::themed(
+                    &params.game.ui_theme.button_styles.small,
+                    &params.localization.get("delete"),
+                )
+                .focus_on_hover(false)
+                .show(ui)
+                .clicked()
+                {
+                    *params.background_to_delete = None;
+                    **params.editor_input =
+                        Some(EditorInput::DeleteBackgroundLayer { layer: layer as u8 });
+                }
+
+                ui.add_space(space);
+
+                if BorderedButton::themed(
+                    &params.game.ui_theme.button_styles.small,
+                    &params.localization.get("cancel"),
+                )
+                .focus_on_hover(false)
+                .show(ui)
+                .clicked()
+                {
+                    *params.background_to_delete = None;
+                }
+            });
+        },
+    );
+}
+
+#[derive(SystemParam)]
+struct EditorCentralPanel<'w, 's> {
+    show_map_create: Local<'s, bool>,
+    show_map_open: Local<'s, bool>,
+    map_create_info: Local<'s, MapCreateInfo>,
+    game: Res<'w, GameMeta>,
+    core_meta: Res<'w, CoreMetaArc>,
+    state: ResMut<'w, EditorState>,
+    map_assets: Res<'w, Assets<MapMeta>>,
+    element_assets: Res<'w, Assets<ElementMeta>>,
+    localization: Res<'w, Localization>,
+    session_manager: SessionManager<'w, 's>,
+    editor_input: ResMut<'w, CurrentEditorInput>,
+    camera: CameraQuery<'w, 's>,
+    map: Res<'w, EditorMapExport>,
+    storage: ResMut<'w, Storage>,
+    selected_element: Res<'w, EditorSelectedElement>,
+    remote_presence: Res<'w, RemoteEditorPresence>,
+    time: Res<'w, Time>,
+}
+
+struct MapCreateInfo {
+    name: String,
+    map_width: u32,
+    map_height: u32,
+}
+
+impl Default for MapCreateInfo {
+    fn default() -> Self {
+        Self {
+            name: default(),
+            map_width: 27,
+            map_height: 21,
+        }
+    }
+}
+
+impl MapCreateInfo {
+    fn is_valid(&self) -> bool {
+        !self.name.is_empty() && self.map_width > 10 && self.map_height > 10
+    }
+}
 
 impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
     type Args = ();
@@ -1085,6 +2347,18 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
             let Ok((camera, camera_transform, _)) = params.camera.get_single() else { return };
             let Some(map) = params.map.0.as_ref() else { return; };
 
+            // Locked layers can't be painted on, and their elements can't be selected
+            let layer_locked = map
+                .layers
+                .get(params.state.current_layer_idx)
+                .map(|layer| {
+                    EditorLayerPrefs::load(&mut params.storage, &map.name).is_locked(layer)
+                })
+                .unwrap_or_default();
+            if layer_locked {
+                params.state.selected_element = None;
+            }
+
             let core_meta = session.world().resource::<CoreMetaArc>();
             let core_meta = core_meta.borrow();
 
@@ -1173,6 +2447,53 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
             let screen_rect = ui.input(|i| i.screen_rect);
             let window_size = screen_rect.size();
 
+            // Draw the map boundary, at the kill margin of the edges that kill and at the edge of
+            // the map for the ones that wrap or wall things in.
+            {
+                let map_size = map.grid_size.as_vec2() * map.tile_size;
+                let boundary = map.boundary;
+                let edge_offset = |edge| match boundary.edge(edge) {
+                    MapEdgeBehavior::Kill | MapEdgeBehavior::Open => boundary.kill_margin,
+                    MapEdgeBehavior::Wrap | MapEdgeBehavior::Wall => 0.0,
+                };
+                let min = vec2(-edge_offset(MapEdge::Left), -edge_offset(MapEdge::Bottom));
+                let max = map_size + vec2(edge_offset(MapEdge::Right), edge_offset(MapEdge::Top));
+                let to_screen = |point: Vec2| {
+                    let ndc =
+                        camera.world_to_ndc(&(*camera_transform).into(), point.extend(0.0))?;
+                    let ndc = (ndc + 1.0) / 2.0;
+                    Some(egui::pos2(
+                        window_size.x * ndc.x,
+                        window_size.y - window_size.y * ndc.y,
+                    ))
+                };
+                let sides = [
+                    (MapEdge::Left, vec2(min.x, min.y), vec2(min.x, max.y)),
+                    (MapEdge::Right, vec2(max.x, min.y), vec2(max.x, max.y)),
+                    (MapEdge::Bottom, vec2(min.x, min.y), vec2(max.x, min.y)),
+                    (MapEdge::Top, vec2(min.x, max.y), vec2(max.x, max.y)),
+                ];
+                let mut painter = ui.painter_at(screen_rect);
+                painter.set_clip_rect(map_response_rect);
+                for (edge, start, end) in sides {
+                    let (Some(start), Some(end)) = (to_screen(start), to_screen(end)) else {
+                        continue;
+                    };
+                    let color = match boundary.edge(edge) {
+                        MapEdgeBehavior::Kill => egui::Color32::RED,
+                        MapEdgeBehavior::Wrap => egui::Color32::LIGHT_BLUE,
+                        MapEdgeBehavior::Wall => egui::Color32::WHITE,
+                        MapEdgeBehavior::Open => egui::Color32::DARK_GRAY,
+                    };
+                    painter.extend(egui::Shape::dashed_line(
+                        &[start, end],
+                        (1.0, color),
+                        8.0,
+                        6.0,
+                    ));
+                }
+            }
+
             // Map element tool
             if params.state.current_tool == EditorTool::Element {
                 // Collect map element list
@@ -1201,45 +2522,54 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                     .collect::<Vec<_>>();
                 element_categories.sort_by(|a, b| a.0.cmp(&b.0));
 
-                // Element context menu
-                map_response.context_menu(|ui| {
-                    if ui.input(|i| i.pointer.secondary_clicked()) {
-                        params.state.cursor.context_click_pos = params.state.cursor.current_pos;
-                    }
-                    ui.menu_button(
-                        &format!("➕ {}", params.localization.get("add-element")),
-                        |ui| {
-                            for (category, elements) in element_categories {
-                                ui.menu_button(&category, |ui| {
-                                    for (handle, element) in elements {
-                                        if ui.button(&element.name).clicked() {
-                                            **params.editor_input =
-                                                Some(EditorInput::SpawnElement {
-                                                    handle,
-                                                    translation: params
-                                                        .state
-                                                        .cursor
-                                                        .context_click_pos
-                                                        .unwrap(),
-                                                    layer: params
-                                                        .state
-                                                        .current_layer_idx
-                                                        .try_into()
-                                                        .unwrap(),
-                                                });
-                                            ui.close_menu();
-                                            params.state.cursor.context_click_pos = None;
+                let snap_mode = params
+                    .storage
+                    .get::<SnapMode>(SnapMode::STORAGE_KEY)
+                    .unwrap_or_default();
+                let tile_size = params.map.0.as_ref().unwrap().tile_size;
+
+                // Element context menu, for adding elements to unlocked layers
+                if !layer_locked {
+                    map_response.context_menu(|ui| {
+                        if ui.input(|i| i.pointer.secondary_clicked()) {
+                            params.state.cursor.context_click_pos = params.state.cursor.current_pos;
+                        }
+                        ui.menu_button(
+                            &format!("➕ {}", params.localization.get("add-element")),
+                            |ui| {
+                                for (category, elements) in element_categories {
+                                    ui.menu_button(&category, |ui| {
+                                        for (handle, element) in elements {
+                                            if ui.button(&element.name).clicked() {
+                                                let translation = snap_mode.snap_element(
+                                                    params.state.cursor.context_click_pos.unwrap(),
+                                                    &element.editor,
+                                                    tile_size,
+                                                );
+                                                **params.editor_input =
+                                                    Some(EditorInput::SpawnElement {
+                                                        handle,
+                                                        translation,
+                                                        layer: params
+                                                            .state
+                                                            .current_layer_idx
+                                                            .try_into()
+                                                            .unwrap(),
+                                                    });
+                                                ui.close_menu();
+                                                params.state.cursor.context_click_pos = None;
+                                            }
                                         }
-                                    }
-                                });
-                            }
-                        },
-                    );
-                });
+                                    });
+                                }
+                            },
+                        );
+                    });
+                }
 
                 // Selectable element rendering and handling
                 for (entity, handle, translation, layer_idx) in elements {
-                    if layer_idx != params.state.current_layer_idx {
+                    if layer_idx != params.state.current_layer_idx || layer_locked {
                         continue;
                     }
 
@@ -1260,6 +2590,7 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                         pos + egui::vec2(grab_offset.x, -grab_offset.y) / ppp,
                         egui::vec2(grab_size.x, grab_size.y) / ppp,
                     );
+                    let is_selected = params.state.selected_element == Some(entity);
                     let mut color_override = None;
                     let response = ui
                         .allocate_rect(rect, egui::Sense::click_and_drag())
@@ -1274,6 +2605,10 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                             }
                         });
 
+                    if response.clicked() {
+                        params.state.selected_element = Some(entity);
+                    }
+
                     #[derive(Clone)]
                     struct ElementDrag {
                         offset: Vec2,
@@ -1297,7 +2632,6 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                         if grab_size.x % 2.0 != 0.0 { 0.5 } else { 0.0 },
                         if grab_size.y % 2.0 != 0.0 { 0.5 } else { 0.0 },
                     );
-                    let snap_to_grid = ui.input(|i| i.modifiers.shift_only());
                     let ctrl_modifier = ui.input(|i| i.modifiers.command);
 
                     let default_color = if response.dragged_by(egui::PointerButton::Primary)
@@ -1310,17 +2644,10 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                         let new_pos =
                             params.state.cursor.current_pos.unwrap() - element_drag.offset;
 
-                        let new_pos = if snap_to_grid {
-                            let bottom_center_offset =
-                                -grab_offset + Vec2::new(0.0, grab_size.y / 2.0);
-                            let bottom_center = new_pos - bottom_center_offset;
-
-                            let increment = params.map.0.as_ref().unwrap().tile_size / 4.0;
-                            let snapped_bottom_center =
-                                (bottom_center / increment).floor() * increment;
-                            snapped_bottom_center + bottom_center_offset
-                        } else {
+                        let new_pos = if snap_mode == SnapMode::Off {
                             new_pos.floor() + half_pixel_offset
+                        } else {
+                            snap_mode.snap_element(new_pos, &element_meta.editor, tile_size)
                         };
 
                         **params.editor_input = Some(EditorInput::MoveEntity {
@@ -1331,7 +2658,11 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                         egui::Color32::GREEN
                     } else {
                         response.on_hover_cursor(egui::CursorIcon::PointingHand);
-                        egui::Color32::LIGHT_GRAY
+                        if is_selected {
+                            egui::Color32::GOLD
+                        } else {
+                            egui::Color32::LIGHT_GRAY
+                        }
                     };
                     let mut painter = ui.painter_at(screen_rect);
                     let color = color_override.unwrap_or(default_color);
@@ -1345,13 +2676,144 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                             color,
                         );
                     }
-                    painter.rect_stroke(rect, 2.0, (1.0, color));
+                    let stroke_width = if is_selected { 2.0 } else { 1.0 };
+                    painter.rect_stroke(rect, 2.0, (stroke_width, color));
+                }
+
+                // Draw the path of the selected element, with markers to drag its waypoints
+                if let Some(selected) = &**params.selected_element {
+                    let to_screen = |point: Vec2| {
+                        let ndc =
+                            camera.world_to_ndc(&(*camera_transform).into(), point.extend(0.0))?;
+                        let ndc = (ndc + 1.0) / 2.0;
+                        Some(egui::pos2(
+                            window_size.x * ndc.x,
+                            window_size.y - window_size.y * ndc.y,
+                        ))
+                    };
+                    let mut painter = ui.painter_at(screen_rect);
+                    painter.set_clip_rect(map_response_rect);
+
+                    let mut last_point = to_screen(selected.pos);
+                    for (i, waypoint) in selected.waypoints.iter().enumerate() {
+                        let Some(point) = to_screen(selected.pos + *waypoint) else {
+                            continue;
+                        };
+                        if let Some(last_point) = last_point {
+                            painter.line_segment([last_point, point], (1.0, egui::Color32::GOLD));
+                        }
+                        last_point = Some(point);
+
+                        let rect = egui::Rect::from_center_size(
+                            point,
+                            egui::Vec2::splat(WAYPOINT_MARKER_SIZE),
+                        );
+                        let response = ui
+                            .allocate_rect(rect, egui::Sense::click_and_drag())
+                            .context_menu(|ui| {
+                                if ui
+                                    .button(&format!(
+                                        "🗑 {}",
+                                        params.localization.get("delete-waypoint")
+                                    ))
+                                    .clicked()
+                                {
+                                    ui.close_menu();
+                                    let mut waypoints = selected.waypoints.clone();
+                                    waypoints.remove(i);
+                                    **params.editor_input =
+                                        Some(EditorInput::SetElementWaypoints {
+                                            entity: selected.entity,
+                                            waypoints,
+                                        });
+                                }
+                            });
+
+                        let color = if response.dragged_by(egui::PointerButton::Primary) {
+                            if let Some(cursor_pos) = params.state.cursor.current_pos {
+                                // Waypoints snap relative to the element, so that the platform
+                                // stays lined up with the grid the same way as the element.
+                                let offset = cursor_pos - selected.pos;
+                                let offset = match snap_mode.grid_size(tile_size) {
+                                    Some(grid_size) => (offset / grid_size).round() * grid_size,
+                                    None => offset.round(),
+                                };
+                                let mut waypoints = selected.waypoints.clone();
+                                waypoints[i] = offset;
+                                **params.editor_input = Some(EditorInput::SetElementWaypoints {
+                                    entity: selected.entity,
+                                    waypoints,
+                                });
+                            }
+                            egui::Color32::GREEN
+                        } else {
+                            egui::Color32::GOLD
+                        };
+                        response.on_hover_cursor(egui::CursorIcon::Grab);
+                        painter.circle_filled(point, WAYPOINT_MARKER_SIZE / 2.0, color);
+                    }
+                }
+
+                if ui.input(|i| i.key_pressed(egui::Key::Escape))
+                    && !ui.ctx().wants_keyboard_input()
+                {
+                    params.state.selected_element = None;
+                }
+
+                // Nudge the selected element with the arrow keys
+                if let Some(selected) = &**params.selected_element {
+                    if !ui.ctx().wants_keyboard_input() {
+                        let (delta, arrow_held) = ui.input(|i| {
+                            let step = if i.modifiers.shift {
+                                tile_size
+                            } else {
+                                Vec2::ONE
+                            };
+                            let mut delta = Vec2::ZERO;
+                            for event in &i.events {
+                                if let egui::Event::Key {
+                                    key, pressed: true, ..
+                                } = event
+                                {
+                                    match key {
+                                        egui::Key::ArrowLeft => delta.x -= step.x,
+                                        egui::Key::ArrowRight => delta.x += step.x,
+                                        egui::Key::ArrowUp => delta.y += step.y,
+                                        egui::Key::ArrowDown => delta.y -= step.y,
+                                        _ => (),
+                                    }
+                                }
+                            }
+                            let arrow_held = [
+                                egui::Key::ArrowLeft,
+                                egui::Key::ArrowRight,
+                                egui::Key::ArrowUp,
+                                egui::Key::ArrowDown,
+                            ]
+                            .into_iter()
+                            .any(|key| i.key_down(key));
+
+                            (delta, arrow_held)
+                        });
+
+                        // Keep sending the move in between key repeats so that holding an arrow
+                        // key is undone in one step.
+                        if arrow_held || delta != Vec2::ZERO {
+                            **params.editor_input = Some(EditorInput::MoveEntity {
+                                entity: selected.entity,
+                                pos: selected.pos + delta,
+                            });
+                        }
+                    }
                 }
 
             // Tile tool
             } else if params.state.current_tool == EditorTool::Tile {
                 #[allow(clippy::unnecessary_operation)] // false alarm
                 'tile_tool: {
+                    if layer_locked {
+                        break 'tile_tool;
+                    }
                     if let Some(cursor_pos) = params.state.cursor.current_pos {
                         if cursor_pos.x < 0.0
                             || cursor_pos.y < 0.0
@@ -1396,6 +2858,7 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                                 pos: tile_xy,
                                 tilemap_tile_idx: Some(params.state.current_tilemap_tile),
                                 collision: params.state.current_collision,
+                                surface: params.state.current_surface,
                             });
                         } else if response.dragged_by(egui::PointerButton::Secondary) {
                             **params.editor_input = Some(EditorInput::SetTile {
@@ -1403,6 +2866,7 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                                 pos: tile_xy,
                                 tilemap_tile_idx: None,
                                 collision: params.state.current_collision,
+                                surface: default(),
                             });
                         }
                     }
@@ -1447,6 +2911,7 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                             .map(|pos| rect.contains(pos))
                             .unwrap_or(false)
                     }) && !ui.input(|i| i.modifiers.command)
+                        && !layer_locked
                     {
                         if ui.input(|i| i.pointer.primary_down()) {
                             **params.editor_input = Some(EditorInput::SetTile {
@@ -1454,6 +2919,7 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                                 pos: tile_xy,
                                 tilemap_tile_idx: Some(tile.idx as usize),
                                 collision: params.state.current_collision,
+                                surface: tile.surface,
                             });
                         } else if ui.input(|i| i.pointer.secondary_down()) {
                             **params.editor_input = Some(EditorInput::SetTile {
@@ -1461,12 +2927,33 @@ impl<'w, 's> WidgetSystem for EditorCentralPanel<'w, 's> {
                                 pos: tile_xy,
                                 tilemap_tile_idx: Some(tile.idx as usize),
                                 collision: TileCollisionKind::Empty,
+                                surface: tile.surface,
                             });
                         }
                     }
                 }
             };
 
+            remote_cursors(
+                ui,
+                &params.remote_presence,
+                &params.localization,
+                params.time.elapsed_seconds(),
+                map_response_rect,
+                |tile_xy| {
+                    let tile_pos = tile_xy.as_vec2() * map.tile_size;
+                    let ndc =
+                        camera.world_to_ndc(&(*camera_transform).into(), tile_pos.extend(0.0))?;
+                    let ndc = (ndc + 1.0) / 2.0;
+                    let bottom_left =
+                        egui::pos2(window_size.x * ndc.x, window_size.y - window_size.y * ndc.y);
+                    let size = egui::vec2(map.tile_size.x, map.tile_size.y) / ppp;
+                    let top_right = egui::pos2(bottom_left.x + size.x, bottom_left.y - size.y);
+
+                    Some(egui::Rect::from_two_pos(bottom_left, top_right))
+                },
+            );
+
         // If there is no current map
         } else {
             ui.add_space(ui.available_height() / 2.0);
@@ -1536,51 +3023,64 @@ fn map_open_dialog(ui: &mut egui::Ui, params: &mut EditorCentralPanel) {
                                 meta: params.core_meta.0.clone(),
                                 map_meta: (*map_meta).clone(),
                                 player_info: default(),
+                                mutators: default(),
+                                seed: GlobalRng::SEED,
                             });
                             *params.show_map_open = false;
                         }
                     }
 
-                    let user_maps: Option<UserMapStorage> =
-                        params.storage.get(UserMapStorage::STORAGE_KEY);
-                    ui.heading(params.localization.get("user-maps"));
-                    if let Some(mut user_maps) = user_maps {
-                        let mut maps = user_maps.0.clone().into_iter().collect::<Vec<_>>();
-                        maps.sort_by(|a, b| a.0.cmp(&b.0));
+                    for (title, storage_key) in [
+                        ("user-maps", UserMapStorage::STORAGE_KEY),
+                        ("custom-maps", UserMapStorage::CUSTOM_STORAGE_KEY),
+                    ] {
+                        let user_maps: Option<UserMapStorage> = params.storage.get(storage_key);
+                        ui.heading(params.localization.get(title));
+                        if let Some(mut user_maps) = user_maps {
+                            let mut maps = user_maps.0.clone().into_iter().collect::<Vec<_>>();
+                            maps.sort_by(|a, b| a.0.cmp(&b.0));
+
+                            if maps.is_empty() {
+                                ui.label(params.localization.get("none"));
+                            }
 
-                        if maps.is_empty() {
+                            for (name, map_meta) in maps {
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Min),
+                                    |ui| {
+                                        if ui
+                                            .button("🗑")
+                                            .on_hover_text(params.localization.get("delete"))
+                                            .clicked()
+                                        {
+                                            user_maps.remove(&name);
+                                            params.storage.set(storage_key, &user_maps);
+                                            params.storage.save();
+                                        }
+                                        if ui
+                                            .add(
+                                                egui::Button::new(&name).min_size(egui::vec2(
+                                                    ui.available_width(),
+                                                    0.0,
+                                                )),
+                                            )
+                                            .clicked()
+                                        {
+                                            params.session_manager.start_local(CoreSessionInfo {
+                                                meta: params.core_meta.0.clone(),
+                                                map_meta: map_meta.clone(),
+                                                player_info: default(),
+                                                mutators: default(),
+                                                seed: GlobalRng::SEED,
+                                            });
+                                            *params.show_map_open = false;
+                                        };
+                                    },
+                                );
+                            }
+                        } else {
                             ui.label(params.localization.get("none"));
                         }
-
-                        for (name, map_meta) in maps {
-                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
-                                if ui
-                                    .button("🗑")
-                                    .on_hover_text(params.localization.get("delete"))
-                                    .clicked()
-                                {
-                                    user_maps.remove(&name);
-                                    params.storage.set(UserMapStorage::STORAGE_KEY, &user_maps);
-                                    params.storage.save();
-                                }
-                                if ui
-                                    .add(
-                                        egui::Button::new(&name)
-                                            .min_size(egui::vec2(ui.available_width(), 0.0)),
-                                    )
-                                    .clicked()
-                                {
-                                    params.session_manager.start_local(CoreSessionInfo {
-                                        meta: params.core_meta.0.clone(),
-                                        map_meta: map_meta.clone(),
-                                        player_info: default(),
-                                    });
-                                    *params.show_map_open = false;
-                                };
-                            });
-                        }
-                    } else {
-                        ui.label(params.localization.get("none"));
                     }
                 });
             });
@@ -0,0 +1,462 @@
+//! Crash-safe autosaves of the map being edited.
+//!
+//! While a map is being edited, it is saved to the oldest of a few rotating autosave slots every
+//! [`Settings::editor_autosave_interval`] seconds, as long as it has changed. When the editor is
+//! opened, the autosaves that are newer than the last time their map was saved are offered for
+//! recovery.
+
+use async_channel::{Receiver, Sender};
+use bevy::{ecs::system::SystemState, tasks::IoTaskPool};
+use bevy_egui::egui;
+use bevy_fluent::Localization;
+use jumpy_core::{editor::EditorHistory, random::GlobalRng};
+
+use crate::{
+    prelude::*,
+    ui::{widgets::bordered_button::BorderedButton, WidgetId, WidgetSystem},
+};
+
+use super::{overlay_window, EditorMapExport, EditorPlaytest};
+
+/// The number of autosaves that are kept, overwriting the oldest one.
+pub const AUTOSAVE_SLOTS: usize = 3;
+
+/// A map saved to an autosave slot.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EditorAutosave {
+    /// When the map was autosaved, in seconds since the Unix epoch.
+    pub saved_at: u64,
+    pub map: MapMeta,
+}
+
+/// The time that each map was last saved from the editor, by map name, in seconds since the Unix
+/// epoch.
+#[derive(Serialize, Deserialize, Clone, Default, Deref, DerefMut)]
+pub struct EditorMapSaveTimes(pub HashMap<String, u64>);
+
+impl StorageItem for EditorMapSaveTimes {
+    const STORAGE_KEY: &'static str = "editor_map_save_times";
+}
+
+/// An autosave slot that has a map in it.
+struct AutosaveSlot {
+    map_name: String,
+    saved_at: u64,
+}
+
+/// Resource keeping track of the editor autosaves.
+#[derive(Resource)]
+pub struct EditorAutosaves {
+    /// Seconds of editing since the last autosave.
+    elapsed: f32,
+    /// The number of edits the map had when it was last saved or autosaved.
+    saved_edits: u64,
+    /// The contents of the autosave slots, once they have been read.
+    slots: Option<[Option<AutosaveSlot>; AUTOSAVE_SLOTS]>,
+    /// Whether the autosaves have been checked for recovery since the editor was opened.
+    checked: bool,
+    /// The autosaves that may be recovered, by slot, newest first.
+    recoverable: Vec<(usize, EditorAutosave)>,
+    load_sender: Sender<Vec<(usize, EditorAutosave)>>,
+    load_receiver: Receiver<Vec<(usize, EditorAutosave)>>,
+}
+
+impl Default for EditorAutosaves {
+    fn default() -> Self {
+        let (load_sender, load_receiver) = async_channel::unbounded();
+        Self {
+            elapsed: 0.0,
+            saved_edits: 0,
+            slots: None,
+            checked: false,
+            recoverable: Vec::new(),
+            load_sender,
+            load_receiver,
+        }
+    }
+}
+
+impl EditorAutosaves {
+    /// Record that the map was saved with the given number of edits, so that it isn't autosaved
+    /// until it is changed again, and remove its autosaves, which are now out of date.
+    pub fn map_saved(&mut self, storage: &mut Storage, map_name: &str, edits: u64) {
+        self.elapsed = 0.0;
+        self.saved_edits = edits;
+
+        let mut save_times = storage.get_item::<EditorMapSaveTimes>();
+        save_times.insert(map_name.to_string(), unix_time());
+        storage.set_item(&save_times);
+
+        if let Some(slots) = &mut self.slots {
+            for (idx, slot) in slots.iter_mut().enumerate() {
+                if slot.as_ref().map(|slot| slot.map_name.as_str()) == Some(map_name) {
+                    *slot = None;
+                    delete_slot(idx);
+                }
+            }
+        }
+        self.recoverable
+            .retain(|(_, autosave)| autosave.map.name != map_name);
+    }
+
+    /// Save the map to the oldest autosave slot.
+    fn autosave(&mut self, map: MapMeta) {
+        let Some(slots) = &mut self.slots else {
+            return;
+        };
+        let idx = slots
+            .iter()
+            .position(|slot| slot.is_none())
+            .unwrap_or_else(|| {
+                (0..AUTOSAVE_SLOTS)
+                    .min_by_key(|idx| slots[*idx].as_ref().map(|slot| slot.saved_at))
+                    .unwrap()
+            });
+        let autosave = EditorAutosave {
+            saved_at: unix_time(),
+            map,
+        };
+        slots[idx] = Some(AutosaveSlot {
+            map_name: autosave.map.name.clone(),
+            saved_at: autosave.saved_at,
+        });
+
+        // Serialize the map in the background, so that big maps don't make the editor hitch
+        IoTaskPool::get()
+            .spawn(async move {
+                match serde_yaml::to_string(&autosave) {
+                    Ok(contents) => backend::write_slot(idx, &contents),
+                    Err(e) => error!("Could not serialize autosave: {e}"),
+                }
+            })
+            .detach();
+    }
+}
+
+/// Start reading the autosave slots when the editor is opened.
+pub(super) fn load_autosaves(mut autosaves: ResMut<EditorAutosaves>) {
+    if autosaves.checked {
+        return;
+    }
+    autosaves.checked = true;
+
+    let sender = autosaves.load_sender.clone();
+    IoTaskPool::get()
+        .spawn(async move {
+            sender.try_send(backend::read_slots()).ok();
+        })
+        .detach();
+}
+
+/// Forget the autosaves offered for recovery when the editor is closed, so that they are checked
+/// again the next time it is opened.
+pub(super) fn reset_autosaves(mut autosaves: ResMut<EditorAutosaves>) {
+    autosaves.checked = false;
+    autosaves.elapsed = 0.0;
+    autosaves.recoverable.clear();
+}
+
+/// Receive the autosaves once they are read, and autosave the map while it is being edited.
+pub(super) fn update_autosaves(
+    mut autosaves: ResMut<EditorAutosaves>,
+    mut session: ResMut<Session>,
+    mut storage: ResMut<Storage>,
+    game: Res<GameMeta>,
+    map_export: Res<EditorMapExport>,
+    time: Res<Time>,
+    playtest: Option<Res<EditorPlaytest>>,
+) {
+    let autosaves = &mut *autosaves;
+
+    if let Ok(loaded) = autosaves.load_receiver.try_recv() {
+        let save_times = storage.get_item::<EditorMapSaveTimes>();
+        let mut slots: [Option<AutosaveSlot>; AUTOSAVE_SLOTS] = default();
+        autosaves.recoverable.clear();
+        for (idx, autosave) in loaded {
+            let saved_at = save_times.get(&autosave.map.name).copied().unwrap_or(0);
+            // Remove autosaves that are older than the map's last save
+            if autosave.saved_at <= saved_at {
+                delete_slot(idx);
+                continue;
+            }
+            slots[idx] = Some(AutosaveSlot {
+                map_name: autosave.map.name.clone(),
+                saved_at: autosave.saved_at,
+            });
+            autosaves.recoverable.push((idx, autosave));
+        }
+        autosaves
+            .recoverable
+            .sort_by(|a, b| b.1.saved_at.cmp(&a.1.saved_at));
+        autosaves.slots = Some(slots);
+    }
+
+    // A new map was opened
+    if session.is_added() {
+        autosaves.elapsed = 0.0;
+        autosaves.saved_edits = 0;
+    }
+
+    let interval = Settings::get_stored_or_default(&game, &mut storage).editor_autosave_interval;
+    if interval == 0 || playtest.is_some() || autosaves.slots.is_none() {
+        return;
+    }
+
+    let edits = session.world().resource::<EditorHistory>().borrow().edits();
+    if edits == autosaves.saved_edits {
+        // Only count the time spent editing since the last change
+        autosaves.elapsed = 0.0;
+        return;
+    }
+
+    autosaves.elapsed += time.delta_seconds();
+    if autosaves.elapsed < interval as f32 {
+        return;
+    }
+    let Some(map) = map_export.0.clone() else {
+        return;
+    };
+    autosaves.elapsed = 0.0;
+    autosaves.saved_edits = edits;
+    autosaves.autosave(map);
+}
+
+/// Offers to recover the autosaves that are newer than their map's last save.
+#[derive(SystemParam)]
+pub(super) struct AutosaveRecoveryDialog<'w, 's> {
+    autosaves: ResMut<'w, EditorAutosaves>,
+    game: Res<'w, GameMeta>,
+    core_meta: Res<'w, CoreMetaArc>,
+    localization: Res<'w, Localization>,
+    session_manager: SessionManager<'w, 's>,
+}
+
+impl<'w, 's> WidgetSystem for AutosaveRecoveryDialog<'w, 's> {
+    type Args = ();
+
+    fn system(
+        world: &mut World,
+        state: &mut SystemState<Self>,
+        ui: &mut egui::Ui,
+        _id: WidgetId,
+        _args: Self::Args,
+    ) {
+        let mut params: AutosaveRecoveryDialog = state.get_mut(world);
+
+        if params.autosaves.recoverable.is_empty() {
+            return;
+        }
+        let space = ui.spacing().icon_width;
+        let now = unix_time();
+
+        overlay_window(
+            ui,
+            "autosave-recovery-window",
+            &params.localization.get("recover-autosave"),
+            params.game.main_menu.menu_width,
+            |ui| {
+                ui.label(params.localization.get("recover-autosave-hint"));
+                ui.add_space(space / 2.0);
+
+                let mut recovered = None;
+                for (idx, autosave) in &params.autosaves.recoverable {
+                    ui.horizontal(|ui| {
+                        let age = autosave_age(&params.localization, now, autosave.saved_at);
+                        ui.label(format!("{} ({age})", autosave.map.name));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button(params.localization.get("recover")).clicked() {
+                                recovered = Some(*idx);
+                            }
+                        });
+                    });
+                }
+
+                ui.add_space(space);
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                    // Discarding deletes the autosaves, while closing the dialog keeps them
+                    // around until the next time the editor is opened.
+                    if BorderedButton::themed(
+                        &params.game.ui_theme.button_styles.small,
+                        &params.localization.get("discard"),
+                    )
+                    .focus_on_hover(false)
+                    .show(ui)
+                    .clicked()
+                    {
+                        let autosaves = &mut *params.autosaves;
+                        for (idx, _) in autosaves.recoverable.drain(..) {
+                            if let Some(slots) = &mut autosaves.slots {
+                                slots[idx] = None;
+                            }
+                            delete_slot(idx);
+                        }
+                    }
+
+                    ui.add_space(space);
+
+                    if BorderedButton::themed(
+                        &params.game.ui_theme.button_styles.small,
+                        &params.localization.get("close"),
+                    )
+                    .focus_on_hover(false)
+                    .show(ui)
+                    .clicked()
+                    {
+                        params.autosaves.recoverable.clear();
+                    }
+                });
+
+                if let Some(idx) = recovered {
+                    let autosave = params
+                        .autosaves
+                        .recoverable
+                        .iter()
+                        .find(|(slot, _)| *slot == idx)
+                        .map(|(_, autosave)| autosave.map.clone());
+                    if let Some(map_meta) = autosave {
+                        params.session_manager.start_local(CoreSessionInfo {
+                            meta: params.core_meta.0.clone(),
+                            map_meta,
+                            player_info: default(),
+                            mutators: default(),
+                            seed: GlobalRng::SEED,
+                        });
+                    }
+                    params.autosaves.recoverable.clear();
+                }
+            },
+        );
+    }
+}
+
+/// Get how long ago an autosave was made, as a localized message.
+fn autosave_age(localization: &Localization, now: u64, saved_at: u64) -> String {
+    let minutes = now.saturating_sub(saved_at) / 60;
+    if minutes < 60 {
+        localization.get(&format!("minutes-ago?minutes={minutes}"))
+    } else if minutes < 60 * 24 {
+        localization.get(&format!("hours-ago?hours={}", minutes / 60))
+    } else {
+        localization.get(&format!("days-ago?days={}", minutes / (60 * 24)))
+    }
+}
+
+/// Delete the autosave in a slot in the background.
+fn delete_slot(idx: usize) {
+    IoTaskPool::get()
+        .spawn(async move { backend::delete_slot(idx) })
+        .detach();
+}
+
+/// Autosaves are kept in files next to the platform storage file.
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::path::PathBuf;
+
+    use bevy::prelude::{error, warn};
+
+    use super::{EditorAutosave, AUTOSAVE_SLOTS};
+
+    fn slot_path(idx: usize) -> PathBuf {
+        crate::platform::data_dir()
+            .join("autosaves")
+            .join(format!("autosave-{idx}.yml"))
+    }
+
+    pub fn read_slots() -> Vec<(usize, EditorAutosave)> {
+        (0..AUTOSAVE_SLOTS)
+            .filter_map(|idx| {
+                let contents = std::fs::read(slot_path(idx)).ok()?;
+                match serde_yaml::from_slice(&contents) {
+                    Ok(autosave) => Some((idx, autosave)),
+                    Err(e) => {
+                        warn!(slot = idx, "Ignoring unreadable autosave: {e}");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    pub fn write_slot(idx: usize, contents: &str) {
+        let path = slot_path(idx);
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                error!("Could not create autosave dir: {e}");
+                return;
+            }
+        }
+        // Write to a temporary file first, so that a crash while writing doesn't leave a broken
+        // autosave behind.
+        let temp_path = path.with_extension("yml.tmp");
+        if let Err(e) =
+            std::fs::write(&temp_path, contents).and_then(|_| std::fs::rename(&temp_path, &path))
+        {
+            error!("Could not write autosave: {e}");
+        }
+    }
+
+    pub fn delete_slot(idx: usize) {
+        std::fs::remove_file(slot_path(idx)).ok();
+    }
+}
+
+/// Autosaves are kept in the browser's local storage, which only has a few megabytes of room.
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use bevy::prelude::{error, warn};
+
+    use super::{EditorAutosave, AUTOSAVE_SLOTS};
+
+    /// The largest autosave that is written to local storage, in bytes.
+    const MAX_AUTOSAVE_LEN: usize = 1024 * 1024;
+
+    fn slot_key(idx: usize) -> String {
+        format!("jumpy-editor-autosave-{idx}")
+    }
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn read_slots() -> Vec<(usize, EditorAutosave)> {
+        let Some(local_storage) = local_storage() else {
+            return Vec::new();
+        };
+        (0..AUTOSAVE_SLOTS)
+            .filter_map(|idx| {
+                let contents = local_storage.get_item(&slot_key(idx)).ok()??;
+                match serde_yaml::from_str(&contents) {
+                    Ok(autosave) => Some((idx, autosave)),
+                    Err(e) => {
+                        warn!(slot = idx, "Ignoring unreadable autosave: {e}");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    pub fn write_slot(idx: usize, contents: &str) {
+        if contents.len() > MAX_AUTOSAVE_LEN {
+            warn!(
+                len = contents.len(),
+                "Map is too big to autosave to local storage"
+            );
+            return;
+        }
+        let Some(local_storage) = local_storage() else {
+            return;
+        };
+        if let Err(e) = local_storage.set_item(&slot_key(idx), contents) {
+            error!("Could not write autosave: {e:?}");
+        }
+    }
+
+    pub fn delete_slot(idx: usize) {
+        if let Some(local_storage) = local_storage() {
+            local_storage.remove_item(&slot_key(idx)).ok();
+        }
+    }
+}
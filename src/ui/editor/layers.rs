@@ -0,0 +1,115 @@
+//! The player's local preferences for the layers of the map being edited, which aren't part of the
+//! map or shared with the other players editing it.
+
+use jumpy_core::editor::set_hidden_map_layers;
+
+use crate::prelude::*;
+
+use super::EditorMapExport;
+
+/// The [`Storage`] scope that the editor preferences for each map are kept in, nested in a scope
+/// for the map name.
+const MAP_PREFS_SCOPE: &str = "editor_map";
+
+/// The layers of a map that are hidden or locked in the editor, by layer name.
+///
+/// Hidden layers aren't drawn in the editor, and locked layers can't be painted on or have their
+/// elements selected.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct EditorLayerPrefs {
+    pub hidden: HashSet<String>,
+    pub locked: HashSet<String>,
+}
+
+impl StorageItem for EditorLayerPrefs {
+    const STORAGE_KEY: &'static str = "layer_prefs";
+}
+
+impl EditorLayerPrefs {
+    /// Load the layer preferences for the map with the given name.
+    pub fn load(storage: &mut Storage, map_name: &str) -> Self {
+        storage
+            .scoped(MAP_PREFS_SCOPE)
+            .scoped(map_name)
+            .get_item::<Self>()
+    }
+
+    /// Save the layer preferences for the map with the given name.
+    pub fn save(&self, storage: &mut Storage, map_name: &str) {
+        storage
+            .scoped(MAP_PREFS_SCOPE)
+            .scoped(map_name)
+            .set_item(self);
+        storage.save();
+    }
+
+    pub fn is_hidden(&self, layer: &MapLayerMeta) -> bool {
+        self.hidden.contains(&layer.id)
+    }
+
+    pub fn is_locked(&self, layer: &MapLayerMeta) -> bool {
+        self.locked.contains(&layer.id)
+    }
+
+    /// Keep the preferences of a layer when it is renamed.
+    pub fn rename(&mut self, old_name: &str, new_name: &str) {
+        for layers in [&mut self.hidden, &mut self.locked] {
+            if layers.remove(old_name) {
+                layers.insert(new_name.to_string());
+            }
+        }
+    }
+}
+
+/// Hide the layers of the edited map that the player has hidden, and show the rest.
+pub(super) fn apply_hidden_layers(world: &mut World) {
+    let hidden = world.resource_scope(|world, map: Mut<EditorMapExport>| {
+        let map = map.0.as_ref()?;
+        let prefs = EditorLayerPrefs::load(&mut world.resource_mut::<Storage>(), &map.name);
+        Some(
+            map.layers
+                .iter()
+                .enumerate()
+                .filter(|(_, layer)| prefs.is_hidden(layer))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>(),
+        )
+    });
+
+    if let (Some(hidden), Some(mut session)) = (hidden, world.get_resource_mut::<Session>()) {
+        set_hidden_map_layers(session.world(), &hidden);
+    }
+}
+
+/// Get the index that the layer at `idx` will have after the layer at `from` is moved to `to`.
+pub(super) fn reordered_layer_idx(idx: usize, from: usize, to: usize) -> usize {
+    if idx == from {
+        to
+    } else if from < idx && idx <= to {
+        idx - 1
+    } else if to <= idx && idx < from {
+        idx + 1
+    } else {
+        idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reordering_shifts_the_layers_in_between() {
+        // Moving layer 1 to the end of four layers
+        let moved = (0..4)
+            .map(|idx| reordered_layer_idx(idx, 1, 3))
+            .collect::<Vec<_>>();
+        assert_eq!(moved, [0, 3, 1, 2]);
+
+        // Moving layer 3 to the start
+        let moved = (0..4)
+            .map(|idx| reordered_layer_idx(idx, 3, 0))
+            .collect::<Vec<_>>();
+        assert_eq!(moved, [1, 2, 3, 0]);
+    }
+}
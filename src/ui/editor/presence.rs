@@ -0,0 +1,165 @@
+//! Showing the other players editing a map over the network where their cursors are and which
+//! layers they are working on.
+
+use bevy_egui::egui;
+use bevy_fluent::Localization;
+
+use crate::{
+    networking::{
+        proto::{EditorPresence, UnreliableMessage},
+        NetworkMatchSocket, SocketTarget,
+    },
+    prelude::*,
+};
+
+use super::{EditorMapExport, EditorState, EditorTool};
+
+/// How often, in seconds, our presence is sent to the other players.
+const PRESENCE_INTERVAL: f32 = 0.1;
+
+/// How long, in seconds, a remote player's presence is shown after the last one we received from
+/// them.
+const PRESENCE_TIMEOUT: f32 = 2.0;
+
+/// The color each player's cursor is drawn with, by player index.
+const PLAYER_COLORS: [egui::Color32; MAX_PLAYERS] = [
+    egui::Color32::from_rgb(77, 166, 255),
+    egui::Color32::from_rgb(255, 107, 107),
+    egui::Color32::from_rgb(107, 217, 107),
+    egui::Color32::from_rgb(255, 200, 77),
+];
+
+/// Resource containing the [`EditorPresence`] of the other players editing the map.
+#[derive(Resource, Default)]
+pub struct RemoteEditorPresence {
+    /// The latest presence received from each player, and the elapsed time it was received at.
+    players: [Option<(EditorPresence, f32)>; MAX_PLAYERS],
+    /// The elapsed time that we last sent our own presence at.
+    last_sent: Option<f32>,
+}
+
+impl RemoteEditorPresence {
+    /// Iterate over the players that we have heard from recently, with their presence.
+    pub fn active(&self, now: f32) -> impl Iterator<Item = (usize, &EditorPresence)> {
+        self.players
+            .iter()
+            .enumerate()
+            .filter_map(move |(player, presence)| match presence {
+                Some((presence, time)) if now - time <= PRESENCE_TIMEOUT => {
+                    Some((player, presence))
+                }
+                _ => None,
+            })
+    }
+}
+
+/// Send our presence to the other players at most every [`PRESENCE_INTERVAL`], and receive theirs.
+pub(super) fn exchange_editor_presence(
+    socket: Res<NetworkMatchSocket>,
+    state: Res<EditorState>,
+    map: Res<EditorMapExport>,
+    time: Res<Time>,
+    mut presence: ResMut<RemoteEditorPresence>,
+) {
+    let now = time.elapsed_seconds();
+
+    if presence
+        .last_sent
+        .map(|last_sent| now - last_sent >= PRESENCE_INTERVAL)
+        .unwrap_or(true)
+    {
+        let cursor_tile = state
+            .cursor
+            .current_pos
+            .zip(map.0.as_ref())
+            .and_then(|(cursor_pos, map)| map_tile_at(map, cursor_pos));
+        let message = UnreliableMessage::EditorPresence(EditorPresence {
+            cursor_tile,
+            tool: state.current_tool,
+            layer: state.current_layer_idx as u32,
+        });
+        socket.send_unreliable(SocketTarget::All, &postcard::to_allocvec(&message).unwrap());
+        presence.last_sent = Some(now);
+    }
+
+    for (player, data) in socket.recv_unreliable() {
+        match postcard::from_bytes::<UnreliableMessage>(&data) {
+            Ok(UnreliableMessage::EditorPresence(remote_presence)) => {
+                if let Some(slot) = presence.players.get_mut(player) {
+                    *slot = Some((remote_presence, now));
+                }
+            }
+            Err(e) => warn!(%player, "Ignoring invalid network message: {e}"),
+        }
+    }
+}
+
+/// Get the tile of the map at the given position, if the position is on the map.
+fn map_tile_at(map: &MapMeta, pos: Vec2) -> Option<UVec2> {
+    let tile = (pos / map.tile_size).floor();
+    let in_bounds = tile.cmpge(Vec2::ZERO).all() && tile.cmplt(map.grid_size.as_vec2()).all();
+
+    in_bounds.then(|| tile.as_uvec2())
+}
+
+/// Get the name and tool of a remote player, to label their cursor with.
+fn cursor_label(localization: &Localization, player: usize, tool: EditorTool) -> String {
+    let name = localization.get(&format!("remote-editor?player={}", player + 1));
+    let tool = match tool {
+        EditorTool::Element => localization.get("elements"),
+        EditorTool::Tile => localization.get("tiles"),
+        EditorTool::Collision => localization.get("collisions"),
+    };
+
+    format!("{name} · {tool}")
+}
+
+/// Draw the cursors of the other players editing the map.
+///
+/// `tile_rect` gets the screen rect of a tile on the map, if it is on screen.
+pub(super) fn remote_cursors(
+    ui: &mut egui::Ui,
+    presence: &RemoteEditorPresence,
+    localization: &Localization,
+    now: f32,
+    clip_rect: egui::Rect,
+    tile_rect: impl Fn(UVec2) -> Option<egui::Rect>,
+) {
+    let mut painter = ui.painter_at(clip_rect);
+    painter.set_clip_rect(clip_rect);
+
+    for (player, presence) in presence.active(now) {
+        let Some(rect) = presence.cursor_tile.and_then(&tile_rect) else {
+            continue;
+        };
+        let color = PLAYER_COLORS[player];
+
+        painter.rect_stroke(rect, 1.0, (2.0, color));
+        painter.text(
+            rect.left_top(),
+            egui::Align2::LEFT_BOTTOM,
+            cursor_label(localization, player, presence.tool),
+            egui::FontId::new(13.0, egui::FontFamily::Proportional),
+            color,
+        );
+    }
+}
+
+/// Show which of the other players have the given layer selected, so that people don't
+/// unknowingly edit the same layer.
+pub(super) fn layer_editors(
+    ui: &mut egui::Ui,
+    presence: &RemoteEditorPresence,
+    localization: &Localization,
+    now: f32,
+    layer: usize,
+) {
+    for (player, presence) in presence.active(now) {
+        if presence.layer as usize != layer {
+            continue;
+        }
+        let number = player + 1;
+        ui.colored_label(PLAYER_COLORS[player], format!("✏{number}"))
+            .on_hover_text(localization.get(&format!("editing-layer?player={number}")));
+    }
+}
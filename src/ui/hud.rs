@@ -0,0 +1,273 @@
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use jumpy_core::{
+    elements::{control_zone::ControlZone, ElementHandle},
+    input::PlayerInputs,
+    item::{Inventory, ItemAmmo},
+    metadata::CoreMetaArc,
+    player::PlayerIdx,
+    score::MatchScore,
+    stocks::PlayerStocks,
+};
+
+use crate::prelude::*;
+
+use super::{
+    main_menu::player_select::{player_image, PlayerAtlasEguiTextures, PlayerNames},
+    stocks::lives_label,
+    widgets::{bordered_frame::BorderedFrame, EguiUiExt},
+};
+
+/// The size of the player portrait in the HUD.
+const PORTRAIT_SIZE: f32 = 40.0;
+/// The size of the held item icon in the HUD.
+const ITEM_ICON_SIZE: f32 = 16.0;
+/// The space between the HUD and the edge of the screen.
+const HUD_MARGIN: f32 = 8.0;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            player_hud
+                .run_if(in_state(EngineState::InGame))
+                .run_if(in_state(GameEditorState::Hidden))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// The HUD info for a player, read from the game session.
+struct PlayerHudInfo {
+    player_idx: usize,
+    skin: bones::Handle<PlayerMeta>,
+    /// How many lives the player has left, in a stock match.
+    lives: Option<u32>,
+    /// The player's points and the points needed to win, if the map has control zones.
+    score: Option<(f32, f32)>,
+}
+
+/// The HUD info for the item a player is holding.
+struct HeldItemInfo {
+    element: Option<bones::Handle<ElementMeta>>,
+    /// The texture atlas and sprite index the item is currently rendered with.
+    icon: Option<(Handle<TextureAtlas>, usize)>,
+    /// The ammo the item has left and the most it can hold.
+    ammo: Option<(u32, u32)>,
+    /// How far along the item is in reloading, from `0.0` to `1.0`.
+    reload_progress: Option<f32>,
+}
+
+/// Shows each player's portrait, held item, ammo and lives or score in their corner of the screen.
+fn player_hud(
+    mut session: ResMut<Session>,
+    mut contexts: EguiContexts,
+    mut storage: ResMut<Storage>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    player_meta_assets: Res<Assets<PlayerMeta>>,
+    element_assets: Res<Assets<ElementMeta>>,
+    atlas_assets: Res<Assets<TextureAtlas>>,
+    player_atlas_egui_textures: Res<PlayerAtlasEguiTextures>,
+    player_names: Res<PlayerNames>,
+) {
+    if !Settings::get_stored_or_default(&game, &mut storage).show_hud {
+        return;
+    }
+
+    let players = session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             core_meta: bones::Res<CoreMetaArc>,
+             player_inputs: bones::Res<PlayerInputs>,
+             stocks: bones::Res<PlayerStocks>,
+             score: bones::Res<MatchScore>,
+             control_zones: bones::Comp<ControlZone>| {
+                let has_control_zones = entities.iter_with(&control_zones).next().is_some();
+                Ok(player_inputs
+                    .players
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, input)| input.active)
+                    .map(|(player_idx, input)| PlayerHudInfo {
+                        player_idx,
+                        skin: input.selected_player.clone(),
+                        lives: stocks.lives[player_idx],
+                        score: has_control_zones
+                            .then(|| (score.points[player_idx], core_meta.config.points_to_win)),
+                    })
+                    .collect::<Vec<_>>())
+            },
+        )
+        .unwrap();
+
+    if players.is_empty() {
+        return;
+    }
+
+    let mut held_items = session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             player_indexes: bones::Comp<PlayerIdx>,
+             inventories: bones::Comp<Inventory>,
+             element_handles: bones::Comp<ElementHandle>,
+             item_ammo: bones::Comp<ItemAmmo>,
+             sprites: bones::Comp<bones::AtlasSprite>| {
+                let mut held_items: [Option<HeldItemInfo>; MAX_PLAYERS] = default();
+                for (_ent, (player_idx, inventory)) in
+                    entities.iter_with((&player_indexes, &inventories))
+                {
+                    let Some(item) = inventory.0 else { continue };
+                    let ammo = item_ammo.get(item);
+                    held_items[player_idx.0] = Some(HeldItemInfo {
+                        element: element_handles.get(item).map(|x| x.0.clone()),
+                        icon: sprites
+                            .get(item)
+                            .map(|x| (x.atlas.get_bevy_handle_untyped().typed(), x.index)),
+                        ammo: ammo.map(|x| (x.ammo, x.meta.max_ammo)),
+                        reload_progress: ammo.and_then(|x| {
+                            let frames_left = x.reload_frames_left?;
+                            let reload_frames = x.meta.reload_frames.unwrap_or_default().max(1);
+                            Some(1.0 - frames_left as f32 / reload_frames as f32)
+                        }),
+                    });
+                }
+                Ok(held_items)
+            },
+        )
+        .unwrap();
+
+    let ui_theme = &game.ui_theme;
+    let ctx = contexts.ctx_mut().clone();
+    for player in players {
+        let Some(player_meta) = player_meta_assets.get(&player.skin.get_bevy_handle()) else {
+            continue;
+        };
+        let held_item = held_items[player.player_idx].take();
+        let name = player_names
+            .get(player.player_idx)
+            .unwrap_or(player_meta.name.as_str());
+        let font = ui_theme
+            .font_styles
+            .normal
+            .colored(ui_theme.colors.player(player.player_idx));
+        let small_font = ui_theme
+            .font_styles
+            .smaller
+            .colored(ui_theme.panel.font_color);
+
+        // Give each player their own corner of the screen
+        let (anchor, offset) = match player.player_idx % 4 {
+            0 => (egui::Align2::LEFT_TOP, egui::vec2(HUD_MARGIN, HUD_MARGIN)),
+            1 => (egui::Align2::RIGHT_TOP, egui::vec2(-HUD_MARGIN, HUD_MARGIN)),
+            2 => (
+                egui::Align2::LEFT_BOTTOM,
+                egui::vec2(HUD_MARGIN, -HUD_MARGIN),
+            ),
+            _ => (
+                egui::Align2::RIGHT_BOTTOM,
+                egui::vec2(-HUD_MARGIN, -HUD_MARGIN),
+            ),
+        };
+
+        egui::Area::new(("player_hud", player.player_idx))
+            .anchor(anchor, offset)
+            .interactable(false)
+            .show(&ctx, |ui| {
+                BorderedFrame::new(&ui_theme.panel.border)
+                    .padding(ui_theme.panel.padding.into())
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.allocate_ui(egui::vec2(PORTRAIT_SIZE, PORTRAIT_SIZE), |ui| {
+                                player_image(
+                                    ui,
+                                    player_meta,
+                                    &atlas_assets,
+                                    &player_atlas_egui_textures,
+                                );
+                            });
+
+                            ui.vertical(|ui| {
+                                ui.themed_label(&font, name);
+
+                                // The held item
+                                let Some(item) = held_item else {
+                                    ui.themed_label(&small_font, &localization.get("no-item"));
+                                    return;
+                                };
+                                ui.horizontal(|ui| {
+                                    if let Some((atlas_handle, index)) = &item.icon {
+                                        if let Some(atlas) = atlas_assets.get(atlas_handle) {
+                                            let texture_id =
+                                                contexts.add_image(atlas.texture.clone_weak());
+                                            item_icon(ui, atlas, *index, texture_id);
+                                        }
+                                    }
+                                    let name = item
+                                        .element
+                                        .as_ref()
+                                        .and_then(|x| element_assets.get(&x.get_bevy_handle()))
+                                        .map(|x| x.name.as_str())
+                                        .unwrap_or_default();
+                                    ui.themed_label(&small_font, name);
+                                });
+
+                                if let Some(progress) = item.reload_progress {
+                                    ui.add(
+                                        egui::ProgressBar::new(progress)
+                                            .desired_width(PORTRAIT_SIZE * 2.0)
+                                            .text(localization.get("reloading")),
+                                    );
+                                } else if let Some((ammo, max_ammo)) = item.ammo {
+                                    ui.themed_label(
+                                        &small_font,
+                                        &localization.get(&format!(
+                                            "item-ammo?ammo={ammo}&max_ammo={max_ammo}"
+                                        )),
+                                    );
+                                }
+                            });
+                        });
+
+                        if let Some(lives) = player.lives {
+                            ui.themed_label(&small_font, &lives_label(&localization, name, lives));
+                        }
+                        if let Some((points, points_to_win)) = player.score {
+                            ui.themed_label(
+                                &small_font,
+                                &localization.get(&format!(
+                                    "player-score?score={}&points_to_win={}",
+                                    points.floor(),
+                                    points_to_win
+                                )),
+                            );
+                        }
+                    });
+            });
+    }
+}
+
+/// Render the sprite at `index` in an atlas as the held item icon, keeping its aspect ratio.
+fn item_icon(ui: &mut egui::Ui, atlas: &TextureAtlas, index: usize, texture_id: egui::TextureId) {
+    let Some(sprite_rect) = atlas.textures.get(index) else {
+        return;
+    };
+    let uv_min = sprite_rect.min / atlas.size;
+    let uv_max = sprite_rect.max / atlas.size;
+    let sprite_size = sprite_rect.size();
+    let scale = ITEM_ICON_SIZE / sprite_size.max_element();
+
+    ui.add(
+        egui::Image::new(
+            texture_id,
+            egui::vec2(sprite_size.x * scale, sprite_size.y * scale),
+        )
+        .uv(egui::Rect {
+            min: egui::pos2(uv_min.x, uv_min.y),
+            max: egui::pos2(uv_max.x, uv_max.y),
+        }),
+    );
+}
@@ -13,7 +13,8 @@ use crate::{
     metadata::{GameMeta, Settings},
     platform::Storage,
     prelude::*,
-    ui::ui_input::MenuAction,
+    presence::PresenceState,
+    ui::{tutorial, ui_input::MenuAction},
 };
 
 use self::settings::ModifiedSettings;
@@ -29,25 +30,38 @@ use super::{
 
 pub mod credits;
 pub mod map_select;
+pub mod mutators;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod network_game;
 pub mod player_select;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod replays;
 pub mod settings;
+pub mod stats;
 
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<MainMenuBackground>()
+            .register_storage_item::<Mutators>()
             .init_resource::<MenuPage>()
             .init_resource::<settings::SettingsTab>()
             .init_resource::<settings::ModifiedSettings>()
             .init_resource::<player_select::PlayerSelectState>()
+            .init_resource::<player_select::PlayerNames>()
+            .init_resource::<map_select::MapVoteState>()
+            .init_resource::<map_select::MapPlaylistState>()
+            .init_resource::<stats::StatsMenuState>()
             .add_systems((
                 main_menu_system.run_if(in_state(EngineState::MainMenu)),
+                player_select::claim_devices.run_if(in_state(EngineState::MainMenu)),
                 setup_main_menu.in_schedule(OnEnter(EngineState::MainMenu)),
                 clean_up_main_menu.in_schedule(OnExit(EngineState::MainMenu)),
             ));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.init_resource::<replays::ReplaysMenuState>();
     }
 }
 
@@ -61,8 +75,10 @@ pub fn setup_main_menu(
     game: Res<GameMeta>,
     core: Res<CoreMetaArc>,
     mut session_manager: SessionManager,
+    mut presence: ResMut<PresenceState>,
 ) {
     session_manager.stop();
+    *presence = PresenceState::InMenu;
 
     // Make sure the game editor is hidden
     commands.insert_resource(NextState(Some(GameEditorState::Hidden)));
@@ -123,10 +139,14 @@ pub fn setup_main_menu(
 pub fn clean_up_main_menu(
     mut commands: Commands,
     backgrounds: Query<Entity, With<MainMenuBackground>>,
+    mut disable_menu_input: ResMut<DisableMenuInput>,
 ) {
     for bg in &backgrounds {
         commands.entity(bg).despawn_recursive();
     }
+
+    // The player select page disables menu input, so make sure it doesn't stay disabled in game.
+    **disable_menu_input = false;
 }
 
 /// Which page of the menu we are on
@@ -135,13 +155,21 @@ pub enum MenuPage {
     Home,
     Settings,
     PlayerSelect,
+    /// Picking the mutators of a local game, between the player and map selection.
+    Mutators,
     MapSelect {
         /// Indicates the client is waiting for the map to be selected, not actually picking the
         /// map.
         is_waiting: bool,
+        /// Indicates the players are voting on the map, instead of one player picking it.
+        is_voting: bool,
+        /// Indicates the map is being picked for a training session.
+        is_training: bool,
     },
     Credits,
     NetworkGame,
+    Replays,
+    Stats,
 }
 
 impl Default for MenuPage {
@@ -196,15 +224,31 @@ impl<'w, 's> WidgetSystem for MainMenu<'w, 's> {
                 #[cfg(not(target_arch = "wasm32"))]
                 widget::<network_game::MatchmakingMenu>(world, ui, id.with("network-game"), ())
             }
+            MenuPage::Replays =>
+            {
+                #[cfg(not(target_arch = "wasm32"))]
+                widget::<replays::ReplaysMenu>(world, ui, id.with("replays"), ())
+            }
             MenuPage::PlayerSelect => {
                 widget::<player_select::PlayerSelectMenu>(world, ui, id.with("player-select"), ())
             }
-            MenuPage::MapSelect { is_waiting } => {
-                widget::<map_select::MapSelectMenu>(world, ui, id.with("map-select"), is_waiting)
+            MenuPage::Mutators => {
+                widget::<mutators::MutatorsMenu>(world, ui, id.with("mutators"), ())
             }
+            MenuPage::MapSelect {
+                is_waiting,
+                is_voting,
+                is_training,
+            } => widget::<map_select::MapSelectMenu>(
+                world,
+                ui,
+                id.with("map-select"),
+                (is_waiting, is_voting, is_training),
+            ),
             MenuPage::Settings => {
                 widget::<settings::SettingsMenu>(world, ui, id.with("settings"), ())
             }
+            MenuPage::Stats => widget::<stats::StatsMenu>(world, ui, id.with("stats"), ()),
             MenuPage::Credits => widget::<credits::CreditsMenu>(world, ui, id.with("credits"), ()),
         }
     }
@@ -215,11 +259,17 @@ struct HomeMenu<'w, 's> {
     commands: Commands<'w, 's>,
     menu_page: ResMut<'w, MenuPage>,
     player_select_state: ResMut<'w, player_select::PlayerSelectState>,
+    player_devices: ResMut<'w, PlayerDevices>,
     modified_settings: ResMut<'w, ModifiedSettings>,
     game: Res<'w, GameMeta>,
     localization: Res<'w, Localization>,
     app_exit: EventWriter<'w, AppExit>,
     storage: ResMut<'w, Storage>,
+    stats_state: ResMut<'w, stats::StatsMenuState>,
+    core: Res<'w, CoreMetaArc>,
+    map_assets: Res<'w, Assets<MapMeta>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    replays_state: ResMut<'w, replays::ReplaysMenuState>,
 }
 
 impl<'w, 's> WidgetSystem for HomeMenu<'w, 's> {
@@ -236,6 +286,7 @@ impl<'w, 's> WidgetSystem for HomeMenu<'w, 's> {
         // Reset player selection when comming to the home menu
         if params.player_select_state.is_changed() {
             *params.player_select_state = default();
+            *params.player_devices = default();
         }
 
         let ui_theme = &params.game.ui_theme;
@@ -298,6 +349,62 @@ impl<'w, 's> WidgetSystem for HomeMenu<'w, 's> {
                         });
                     }
 
+                    // Replays
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        ui.scope(|ui| {
+                            let replays_button = BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &params.localization.get("replays"),
+                            )
+                            .min_size(min_button_size)
+                            .show(ui);
+
+                            if replays_button.clicked() {
+                                *params.menu_page = MenuPage::Replays;
+                                params.replays_state.refresh();
+                            }
+                        });
+                    }
+
+                    // Training
+                    ui.scope(|ui| {
+                        if BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &params.localization.get("training"),
+                        )
+                        .min_size(min_button_size)
+                        .show(ui)
+                        .clicked()
+                        {
+                            *params.menu_page = MenuPage::MapSelect {
+                                is_waiting: false,
+                                is_voting: false,
+                                is_training: true,
+                            };
+                        }
+                    });
+
+                    // Tutorial
+                    if params.core.tutorial_map.is_some() {
+                        ui.scope(|ui| {
+                            if BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &params.localization.get("tutorial"),
+                            )
+                            .min_size(min_button_size)
+                            .show(ui)
+                            .clicked()
+                            {
+                                tutorial::start_tutorial(
+                                    &mut params.commands,
+                                    &params.core,
+                                    &params.map_assets,
+                                );
+                            }
+                        });
+                    }
+
                     // Map editor
                     ui.scope(|ui| {
                         if BorderedButton::themed(
@@ -331,13 +438,26 @@ impl<'w, 's> WidgetSystem for HomeMenu<'w, 's> {
                     {
                         *params.menu_page = MenuPage::Settings;
                         **params.modified_settings = Some(
-                            params
-                                .storage
-                                .get(Settings::STORAGE_KEY)
-                                .unwrap_or_else(|| params.game.default_settings.clone()),
+                            Settings::get_stored_or_default(&params.game, &mut params.storage)
+                                .into_owned(),
                         );
                     }
 
+                    // Stats button
+                    if BorderedButton::themed(
+                        &ui_theme.button_styles.normal,
+                        &params.localization.get("stats"),
+                    )
+                    .min_size(min_button_size)
+                    .show(ui)
+                    .clicked()
+                    {
+                        *params.menu_page = MenuPage::Stats;
+                        params
+                            .stats_state
+                            .refresh(&params.game, &mut params.storage);
+                    }
+
                     // Credits button
                     if BorderedButton::themed(
                         &ui_theme.button_styles.normal,
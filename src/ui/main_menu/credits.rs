@@ -2,6 +2,17 @@ use super::*;
 
 const CREDITS_STR: &str = include_str!("../../../CREDITS.md");
 
+/// The crates that the game is built with, generated from the `Cargo.lock` by the build script.
+pub const DEPENDENCY_LICENSES: &[DependencyLicense] =
+    include!(concat!(env!("OUT_DIR"), "/dependency_licenses.rs"));
+
+/// A crate that the game is built with, and the license that it is used under.
+pub struct DependencyLicense {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub license: &'static str,
+}
+
 static CREDITS: Lazy<Credits> = Lazy::new(|| credits_parser::credits(CREDITS_STR).unwrap());
 
 pub struct Credits {
@@ -66,6 +77,7 @@ peg::parser! {
 #[derive(SystemParam)]
 pub struct CreditsMenu<'w, 's> {
     game: Res<'w, GameMeta>,
+    credits_assets: Res<'w, Assets<CreditsMeta>>,
     menu_page: ResMut<'w, MenuPage>,
     localization: Res<'w, Localization>,
     keyboard_input: Res<'w, Input<KeyCode>>,
@@ -98,6 +110,18 @@ impl<'w, 's> WidgetSystem for CreditsMenu<'w, 's> {
             .normal
             .colored(ui_theme.panel.font_color);
 
+        let credits = params.credits_assets.get(&params.game.credits.inner);
+
+        // Scroll with the menu inputs, so that the page can be read with a gamepad
+        let menu_input = params.menu_input.single();
+        let mut scroll = 0.0;
+        if menu_input.pressed(MenuAction::Up) {
+            scroll += normal_font.size / 2.0;
+        }
+        if menu_input.pressed(MenuAction::Down) {
+            scroll -= normal_font.size / 2.0;
+        }
+
         let outer_margin =
             egui::style::Margin::symmetric(ui.available_width() * 0.1, bigger_font.size);
 
@@ -131,6 +155,22 @@ impl<'w, 's> WidgetSystem for CreditsMenu<'w, 's> {
                     ui.with_layout(default(), |ui| {
                         egui::ScrollArea::vertical().show(ui, |ui| {
                             ui.set_width(ui.available_width());
+                            if scroll != 0.0 {
+                                ui.scroll_with_delta(egui::vec2(0.0, scroll));
+                            }
+
+                            // Logo
+                            if let Some(credits) = credits {
+                                let logo = &credits.logo;
+                                let scale = (ui.available_width() / logo.image_size.x).min(1.0);
+                                ui.vertical_centered(|ui| {
+                                    ui.image(
+                                        logo.egui_texture_id,
+                                        egui::vec2(logo.image_size.x, logo.image_size.y) * scale,
+                                    );
+                                });
+                            }
+
                             for section in &CREDITS.sections {
                                 ui.add_space(heading_font.size / 2.0);
                                 ui.themed_label(&heading_font, &section.title);
@@ -175,6 +215,59 @@ impl<'w, 's> WidgetSystem for CreditsMenu<'w, 's> {
                                 }
                                 ui.add(egui::Separator::default().spacing(normal_font.size));
                             }
+
+                            // Asset attributions
+                            for section in credits.iter().flat_map(|x| &x.sections) {
+                                ui.add_space(heading_font.size / 2.0);
+                                ui.themed_label(&heading_font, &section.title);
+                                ui.add_space(heading_font.size / 2.0);
+
+                                for entry in &section.entries {
+                                    ui.add(egui::Separator::default().spacing(normal_font.size));
+                                    ui.horizontal(|ui| {
+                                        ui.add_space(bigger_font.size);
+                                        ui.themed_label(&bigger_font, &entry.name);
+                                        ui.with_layout(
+                                            egui::Layout::right_to_left(egui::Align::Center),
+                                            |ui| {
+                                                ui.add_space(bigger_font.size);
+                                                let details = [&entry.author, &entry.license]
+                                                    .into_iter()
+                                                    .flatten()
+                                                    .map(String::as_str)
+                                                    .collect::<Vec<_>>()
+                                                    .join(" - ");
+                                                ui.themed_label(&normal_font, &details);
+                                            },
+                                        );
+                                    });
+                                }
+                                ui.add(egui::Separator::default().spacing(normal_font.size));
+                            }
+
+                            // Open-source licenses of the crates the game is built with
+                            ui.add_space(heading_font.size / 2.0);
+                            ui.themed_label(
+                                &heading_font,
+                                &params.localization.get("open-source-licenses"),
+                            );
+                            ui.add_space(heading_font.size / 2.0);
+                            for dependency in DEPENDENCY_LICENSES {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(bigger_font.size);
+                                    ui.themed_label(
+                                        &normal_font,
+                                        &format!("{} {}", dependency.name, dependency.version),
+                                    );
+                                    ui.with_layout(
+                                        egui::Layout::right_to_left(egui::Align::Center),
+                                        |ui| {
+                                            ui.add_space(bigger_font.size);
+                                            ui.themed_label(&normal_font, dependency.license);
+                                        },
+                                    );
+                                });
+                            }
                         });
                     });
                 });
@@ -1,55 +1,234 @@
-use crate::{editor::UserMapStorage, ui::pause_menu::PauseMenuPage};
+use crate::{
+    editor::UserMapStorage,
+    ui::{
+        map_loading::{MapLoadError, MapLoading, MapLoadingStart},
+        map_thumbnails::{MapThumbnails, ThumbnailKey, THUMBNAIL_SIZE},
+        map_validation,
+        pause_menu::PauseMenuPage,
+        training::training_session_info,
+    },
+};
+use rand::Rng;
+
+use super::player_select::{PlayerNames, PlayerSelectState};
 
 #[cfg(not(target_arch = "wasm32"))]
-use crate::networking::{GgrsSessionRunnerInfo, NetworkMatchSocket, SocketTarget};
+use crate::networking::{proto::ReliableMessage, LanBeacon, NetworkMatchSocket, SocketTarget};
 
 use super::*;
 
 /// Network message that may be sent when selecting a map.
 #[derive(Serialize, Deserialize)]
 pub enum MapSelectMessage {
-    SelectMap(bones::Handle<MapMeta>),
+    /// Start the game on a map, sent by player 0.
+    SelectMap {
+        map: bones::Handle<MapMeta>,
+        /// The seed of the game session, so that every player's session starts the same.
+        seed: u64,
+    },
+    /// Vote for a map in an online game.
+    VoteMap {
+        /// The index of the map in the list of core maps, stable maps first.
+        ///
+        /// An index one past the end of the list is a vote for a random map.
+        map_idx: usize,
+        /// The voting player's random seed, used to break ties.
+        seed: u64,
+    },
+}
+
+/// Resource containing the state of the map vote in an online game.
+#[derive(Resource, Default)]
+pub struct MapVoteState {
+    /// The index of the map that each player voted for, and the seed they sent with their vote.
+    pub votes: [Option<(usize, u64)>; MAX_PLAYERS],
+    /// The random seed that we send with our votes.
+    pub local_seed: u64,
+    /// The elapsed time, in seconds, that voting started at.
+    pub started_at: Option<f32>,
+}
+
+impl MapVoteState {
+    /// Get the number of votes for each of the `map_count` maps.
+    pub fn vote_counts(&self, map_count: usize) -> Vec<usize> {
+        let mut counts = vec![0; map_count];
+        for (map_idx, _) in self.votes.iter().flatten() {
+            if let Some(count) = counts.get_mut(*map_idx) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    /// Get the index of the map with the most votes, or [`None`] if nobody has voted yet.
+    ///
+    /// Ties are broken using the seeds of all of the votes combined, so that every player that has
+    /// received the same votes agrees on the winner.
+    pub fn winner(&self, map_count: usize) -> Option<usize> {
+        let counts = self.vote_counts(map_count);
+        let most_votes = counts.iter().copied().max().filter(|&count| count > 0)?;
+        let tied = counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count == most_votes)
+            .map(|(map_idx, _)| map_idx)
+            .collect::<Vec<_>>();
+        let seed = self
+            .votes
+            .iter()
+            .flatten()
+            .fold(0, |seed, (_, vote_seed)| seed ^ vote_seed);
+
+        Some(tied[(seed % tied.len() as u64) as usize])
+    }
+}
+
+/// The player's map rotation playlist, saved in [`Storage`].
+#[derive(Serialize, Deserialize, Clone, Default, Deref, DerefMut)]
+pub struct MapPlaylistStorage(pub Vec<bones::Handle<MapMeta>>);
+
+impl MapPlaylistStorage {
+    pub const STORAGE_KEY: &str = "map_playlist";
+
+    /// Get the index of the first map in the playlist that is still available, starting at `start`
+    /// and wrapping around to the beginning.
+    ///
+    /// Maps that can't be found are skipped with a warning.
+    pub fn next_available(&self, start: usize, map_assets: &Assets<MapMeta>) -> Option<usize> {
+        (0..self.len())
+            .map(|offset| (start + offset) % self.len())
+            .find(|&i| {
+                let available = map_assets.get(&self[i].get_bevy_handle()).is_some();
+                if !available {
+                    warn!(index = i, "Skipping playlist map that could not be found");
+                }
+                available
+            })
+    }
+}
+
+/// Resource tracking our place in the map playlist, if we are playing through it.
+#[derive(Resource, Default)]
+pub struct MapPlaylistState {
+    /// Whether the current game was started from the playlist.
+    pub is_playing: bool,
+    /// The index of the playlist map that is being played.
+    pub index: usize,
+}
+
+impl MapPlaylistState {
+    /// If we are playing the playlist, move to the next map in it and return its metadata.
+    pub fn advance(
+        &mut self,
+        storage: &mut Storage,
+        map_assets: &Assets<MapMeta>,
+    ) -> Option<MapMeta> {
+        if !self.is_playing {
+            return None;
+        }
+        let playlist: MapPlaylistStorage = storage
+            .get(MapPlaylistStorage::STORAGE_KEY)
+            .unwrap_or_default();
+        let Some(index) = playlist.next_available(self.index + 1, map_assets) else {
+            self.is_playing = false;
+            return None;
+        };
+        self.index = index;
+
+        map_assets.get(&playlist[index].get_bevy_handle()).cloned()
+    }
+}
+
+/// A map that has been picked to play.
+pub enum MapChoice {
+    /// One of the maps that comes with the game.
+    Core(bones::Handle<MapMeta>),
+    /// A map the player made in the editor.
+    User(MapMeta),
+}
+
+/// The problems found in a map that the player tried to play.
+pub struct MapProblems {
+    pub choice: MapChoice,
+    pub map_meta: MapMeta,
+    pub errors: Vec<MapValidationError>,
 }
 
 #[derive(SystemParam)]
 pub struct MapSelectMenu<'w, 's> {
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
     menu_page: ResMut<'w, MenuPage>,
-    session_manager: SessionManager<'w, 's>,
     game: Res<'w, GameMeta>,
     core: Res<'w, CoreMetaArc>,
-    player_select_state: Res<'w, super::player_select::PlayerSelectState>,
+    player_select_state: Res<'w, PlayerSelectState>,
     game_state: Res<'w, State<EngineState>>,
     pause_page: ResMut<'w, PauseMenuPage>,
     commands: Commands<'w, 's>,
     localization: Res<'w, Localization>,
     map_assets: Res<'w, Assets<MapMeta>>,
+    element_assets: Res<'w, Assets<ElementMeta>>,
+    atlas_assets: Res<'w, Assets<TextureAtlas>>,
     storage: ResMut<'w, Storage>,
+    playlist_state: ResMut<'w, MapPlaylistState>,
+    adjacencies: ResMut<'w, WidgetAdjacencies>,
+    is_editing_playlist: Local<'s, bool>,
+    /// Whether the map is being picked for a training session.
+    is_training: Local<'s, bool>,
+    map_problems: Local<'s, Option<MapProblems>>,
+    map_loading: Option<Res<'w, MapLoading>>,
+    map_load_error: Option<Res<'w, MapLoadError>>,
+    thumbnails: ResMut<'w, MapThumbnails>,
+    /// The map whose button is focused or hovered, shown larger above the map list.
+    highlighted_map: Local<'s, Option<(ThumbnailKey, MapMeta)>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    time: Res<'w, Time>,
+    #[cfg(not(target_arch = "wasm32"))]
+    map_vote_state: ResMut<'w, MapVoteState>,
     #[cfg(not(target_arch = "wasm32"))]
     network_socket: Option<Res<'w, NetworkMatchSocket>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    lan_beacon: Option<ResMut<'w, LanBeacon>>,
 }
 
 impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
-    type Args = bool;
+    /// Whether we are waiting for another player to pick the map, whether we are voting on it, and
+    /// whether we are picking it for a training session.
+    type Args = (bool, bool, bool);
 
     fn system(
         world: &mut World,
         state: &mut SystemState<Self>,
         ui: &mut egui::Ui,
         _id: WidgetId,
-        is_waiting: Self::Args,
+        (is_waiting, is_voting, is_training): Self::Args,
     ) {
         let mut params: MapSelectMenu = state.get_mut(world);
+        *params.is_training = is_training;
+
+        // The loading screen is shown instead while the picked map loads
+        if params.map_loading.is_some() {
+            return;
+        }
 
         #[cfg(not(target_arch = "wasm32"))]
         handle_match_setup_messages(&mut params);
 
         let in_game = params.game_state.0 == EngineState::InGame;
 
-        if params.menu_input.single().just_pressed(MenuAction::Back) {
+        // The playlist editor and the map problems list handle going back themselves
+        let is_sub_menu = *params.is_editing_playlist || params.map_problems.is_some();
+
+        if !is_sub_menu && params.menu_input.single().just_pressed(MenuAction::Back) {
             // If we are on the main menu
             if params.game_state.0 == EngineState::MainMenu {
-                *params.menu_page = MenuPage::PlayerSelect;
+                // Training skips the player selection, and only local games have mutators
+                *params.menu_page = if is_training {
+                    MenuPage::Home
+                } else if is_waiting || is_voting {
+                    MenuPage::PlayerSelect
+                } else {
+                    MenuPage::Mutators
+                };
 
             // If we're on a map selection in game, we must be in the pause menu
             } else if in_game {
@@ -64,7 +243,12 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
 
             if !in_game {
                 ui.add_space(heading_text_style.size / 4.0);
-                ui.themed_label(heading_text_style, &params.localization.get("local-game"));
+                let title = if is_training {
+                    "training"
+                } else {
+                    "local-game"
+                };
+                ui.themed_label(heading_text_style, &params.localization.get(title));
                 ui.themed_label(
                     bigger_text_style,
                     &params.localization.get("map-select-title"),
@@ -77,21 +261,90 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
             let x_margin = (available_size.x - menu_width) / 2.0;
             let outer_margin = egui::style::Margin::symmetric(x_margin, heading_text_style.size);
 
+            if let Some(error) = &params.map_load_error {
+                ui.themed_label(
+                    &small_button_style.font,
+                    &format!(
+                        "{}: {}",
+                        params.localization.get("map-load-failed"),
+                        error.0
+                    ),
+                );
+                ui.add_space(small_button_style.font.size);
+            }
+
             if is_waiting {
                 ui.themed_label(
                     bigger_text_style,
                     &params.localization.get("waiting-for-map"),
                 );
+            } else if is_voting {
+                #[cfg(not(target_arch = "wasm32"))]
+                map_vote_menu(ui, &mut params, outer_margin);
+            } else if params.map_problems.is_some() {
+                map_problems_menu(ui, &mut params, outer_margin);
+            } else if *params.is_editing_playlist {
+                playlist_editor(ui, &mut params, outer_margin);
             } else {
+                #[cfg(not(target_arch = "wasm32"))]
+                let is_network = params.network_socket.is_some();
+                #[cfg(target_arch = "wasm32")]
+                let is_network = false;
+
+                let mut selected_map = None;
+                let mut selected_user_map = None;
+                let mut pick_random = false;
+                let mut play_playlist = false;
+                let mut edit_playlist = false;
+
                 BorderedFrame::new(&params.game.ui_theme.panel.border)
                     .margin(outer_margin)
                     .padding(params.game.ui_theme.panel.padding.into())
                     .show(ui, |ui| {
                         ui.set_width(ui.available_width());
 
+                        if let Some((key, map_meta)) = params.highlighted_map.as_ref() {
+                            map_preview(
+                                ui,
+                                &params.game,
+                                &params.localization,
+                                &mut params.thumbnails,
+                                key,
+                                map_meta,
+                            );
+                            ui.add_space(bigger_text_style.size / 2.0);
+                        }
+
                         let mut first_button = true;
 
                         egui::ScrollArea::vertical().show(ui, |ui| {
+                            pick_random = BorderedButton::themed(
+                                small_button_style,
+                                &params.localization.get("random-map"),
+                            )
+                            .show(ui)
+                            .focus_by_default(ui)
+                            .clicked();
+                            first_button = false;
+
+                            // The playlist is only for local games, since it advances on restart
+                            if !is_network && !is_training {
+                                ui.add_space(ui.spacing().item_spacing.y);
+                                play_playlist = BorderedButton::themed(
+                                    small_button_style,
+                                    &params.localization.get("map-playlist"),
+                                )
+                                .show(ui)
+                                .clicked();
+                                ui.add_space(ui.spacing().item_spacing.y);
+                                edit_playlist = BorderedButton::themed(
+                                    small_button_style,
+                                    &params.localization.get("edit-map-playlist"),
+                                )
+                                .show(ui)
+                                .clicked();
+                            }
+
                             for (section_title, map_handles) in [
                                 (
                                     &params.localization.get("default-maps"),
@@ -117,9 +370,15 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                         .expect("Error loading map");
                                     ui.add_space(ui.spacing().item_spacing.y);
 
-                                    let mut button =
-                                        BorderedButton::themed(small_button_style, &map_meta.name)
-                                            .show(ui);
+                                    let mut button = map_list_button(
+                                        ui,
+                                        small_button_style,
+                                        &mut params.thumbnails,
+                                        &mut params.highlighted_map,
+                                        ThumbnailKey::core(&map_handle, map_meta),
+                                        map_meta,
+                                        &map_meta.name,
+                                    );
 
                                     if first_button {
                                         first_button = false;
@@ -127,187 +386,699 @@ impl<'w, 's> WidgetSystem for MapSelectMenu<'w, 's> {
                                     }
 
                                     if button.clicked() {
-                                        *params.pause_page = PauseMenuPage::Default;
-                                        *params.menu_page = MenuPage::Home;
-
-                                        // TODO: This code to start a game is duplicated 3 or 4
-                                        // times throughout this file, which isn't good. We should
-                                        // try to abstract it into a function or something.
-                                        let mut player_info = <[Option<GameSessionPlayerInfo>;
-                                            MAX_PLAYERS]>::default(
-                                        );
-                                        (0..MAX_PLAYERS).for_each(|i| {
-                                            let slot = &params.player_select_state.slots[i];
-                                            if slot.active {
-                                                player_info[i] = Some(GameSessionPlayerInfo {
-                                                    handle: slot.selected_player.clone(),
-                                                    is_ai: slot.is_ai,
-                                                });
-                                            }
-                                        });
-                                        let core_info = CoreSessionInfo {
-                                            meta: params.core.0.clone(),
-                                            map_meta: map_meta.clone(),
-                                            player_info,
-                                        };
-                                        #[cfg(not(target_arch = "wasm32"))]
-                                        if let Some(socket) = &params.network_socket {
-                                            info!("Selected map, starting network game");
-                                            params.session_manager.start_network(
-                                                core_info,
-                                                GgrsSessionRunnerInfo {
-                                                    socket: socket.ggrs_socket(),
-                                                    player_is_local: socket.player_is_local(),
-                                                    player_count: socket.player_count(),
-                                                },
-                                            );
-                                        } else {
-                                            info!("Selected map, starting game");
-                                            params.session_manager.start_local(core_info);
-                                        }
-                                        #[cfg(target_arch = "wasm32")]
-                                        {
-                                            info!("Selected map, starting game");
-                                            params.session_manager.start_local(core_info);
-                                        }
-
-                                        params
-                                            .commands
-                                            .insert_resource(NextState(Some(EngineState::InGame)));
-                                        params
-                                            .commands
-                                            .insert_resource(NextState(Some(InGameState::Playing)));
-
-                                        #[cfg(not(target_arch = "wasm32"))]
-                                        if let Some(socket) = &params.network_socket {
-                                            socket.send_reliable(
-                                                SocketTarget::All,
-                                                &postcard::to_allocvec(
-                                                    &MapSelectMessage::SelectMap(map_handle),
-                                                )
-                                                .unwrap(),
-                                            );
-                                        }
+                                        selected_map = Some(map_handle);
                                     }
                                 }
+                            }
 
+                            // For now, network games can only play core maps.
+                            ui.set_enabled(!is_network);
+                            for (section_title, storage_key) in [
+                                ("user-maps", UserMapStorage::STORAGE_KEY),
+                                ("custom-maps", UserMapStorage::CUSTOM_STORAGE_KEY),
+                            ] {
                                 let user_maps: Option<UserMapStorage> =
-                                    params.storage.get(UserMapStorage::STORAGE_KEY);
-                                if let Some(user_maps) = user_maps {
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    let is_network = params.network_socket.is_some();
-                                    #[cfg(target_arch = "wasm32")]
-                                    let is_network = false;
-
-                                    // For now, network games can only play core maps.
-                                    ui.set_enabled(!is_network);
-                                    ui.add_space(bigger_text_style.size / 2.0);
-                                    ui.themed_label(
-                                        bigger_text_style,
-                                        &params.localization.get("user-maps"),
-                                    );
+                                    params.storage.get(storage_key);
+                                let Some(user_maps) = user_maps else {
+                                    continue;
+                                };
+                                if user_maps.is_empty() {
+                                    continue;
+                                }
+                                ui.add_space(bigger_text_style.size / 2.0);
+                                ui.themed_label(
+                                    bigger_text_style,
+                                    &params.localization.get(section_title),
+                                );
 
-                                    let mut maps =
-                                        user_maps.0.clone().into_iter().collect::<Vec<_>>();
-                                    maps.sort_by(|a, b| a.0.cmp(&b.0));
-
-                                    for (name, map_meta) in maps {
-                                        ui.add_space(ui.spacing().item_spacing.y);
-                                        let button =
-                                            BorderedButton::themed(small_button_style, &name)
-                                                .show(ui);
-                                        if button.clicked() {
-                                            *params.pause_page = PauseMenuPage::Default;
-                                            *params.menu_page = MenuPage::Home;
-
-                                            let mut player_info = <[Option<GameSessionPlayerInfo>;
-                                                MAX_PLAYERS]>::default(
-                                            );
-                                            (0..MAX_PLAYERS).for_each(|i| {
-                                                let slot = &params.player_select_state.slots[i];
-                                                if slot.active {
-                                                    player_info[i] = Some(GameSessionPlayerInfo {
-                                                        handle: slot.selected_player.clone(),
-                                                        is_ai: slot.is_ai,
-                                                    });
-                                                }
-                                            });
-                                            params.session_manager.start_local(CoreSessionInfo {
-                                                meta: params.core.0.clone(),
-                                                map_meta,
-                                                player_info,
-                                            });
-                                            params.commands.insert_resource(NextState(Some(
-                                                EngineState::InGame,
-                                            )));
-                                            params.commands.insert_resource(NextState(Some(
-                                                InGameState::Playing,
-                                            )));
-                                        };
+                                let mut maps = user_maps.0.into_iter().collect::<Vec<_>>();
+                                maps.sort_by(|a, b| a.0.cmp(&b.0));
+
+                                for (name, map_meta) in maps {
+                                    ui.add_space(ui.spacing().item_spacing.y);
+                                    let button = map_list_button(
+                                        ui,
+                                        small_button_style,
+                                        &mut params.thumbnails,
+                                        &mut params.highlighted_map,
+                                        ThumbnailKey::user(storage_key, &map_meta),
+                                        &map_meta,
+                                        &name,
+                                    );
+                                    if button.clicked() {
+                                        selected_user_map = Some(map_meta);
                                     }
                                 }
                             }
                         });
                     });
+
+                if pick_random {
+                    let maps = core_maps(&params.core);
+                    selected_map = Some(maps[rand::thread_rng().gen_range(0..maps.len())].clone());
+                }
+
+                if let Some(map_handle) = selected_map {
+                    params.playlist_state.is_playing = false;
+                    try_start_game(&mut params, MapChoice::Core(map_handle));
+                } else if let Some(map_meta) = selected_user_map {
+                    params.playlist_state.is_playing = false;
+                    try_start_game(&mut params, MapChoice::User(map_meta));
+                } else if play_playlist {
+                    let playlist: MapPlaylistStorage = params
+                        .storage
+                        .get(MapPlaylistStorage::STORAGE_KEY)
+                        .unwrap_or_default();
+                    if let Some(index) = playlist.next_available(0, &params.map_assets) {
+                        params.playlist_state.is_playing = true;
+                        params.playlist_state.index = index;
+                        try_start_game(&mut params, MapChoice::Core(playlist[index].clone()));
+                    } else {
+                        // There's nothing to play, so let the player fill the playlist
+                        *params.is_editing_playlist = true;
+                    }
+                } else if edit_playlist {
+                    *params.is_editing_playlist = true;
+                }
+            }
+        });
+    }
+}
+
+/// Renders a map's button in the map list, with the map's thumbnail next to it.
+///
+/// The map is remembered in `highlighted_map` while its button is focused or hovered, so that it
+/// can be shown in the [`map_preview`].
+fn map_list_button(
+    ui: &mut egui::Ui,
+    button_style: &ButtonThemeMeta,
+    thumbnails: &mut MapThumbnails,
+    highlighted_map: &mut Option<(ThumbnailKey, MapMeta)>,
+    key: ThumbnailKey,
+    map_meta: &MapMeta,
+    label: &str,
+) -> egui::Response {
+    let height = button_style.font.size * 2.0;
+    let size = egui::vec2(
+        height * THUMBNAIL_SIZE.x as f32 / THUMBNAIL_SIZE.y as f32,
+        height,
+    );
+    let texture_id = thumbnails.get(&key, map_meta);
+
+    let button = ui
+        .horizontal(|ui| {
+            match texture_id {
+                Some(texture_id) => {
+                    ui.image(texture_id, size);
+                }
+                None => {
+                    ui.allocate_space(size);
+                }
+            }
+            BorderedButton::themed(button_style, label).show(ui)
+        })
+        .inner;
+
+    let is_highlighted = highlighted_map
+        .as_ref()
+        .map(|(highlighted, _)| highlighted == &key)
+        .unwrap_or_default();
+    if (button.has_focus() || button.hovered()) && !is_highlighted {
+        *highlighted_map = Some((key, map_meta.clone()));
+    }
+
+    button
+}
+
+/// Renders the highlighted map's thumbnail at full size, along with its name, author, and the
+/// number of players it is made for.
+fn map_preview(
+    ui: &mut egui::Ui,
+    game: &GameMeta,
+    localization: &Localization,
+    thumbnails: &mut MapThumbnails,
+    key: &ThumbnailKey,
+    map_meta: &MapMeta,
+) {
+    let bigger_text_style = &game.ui_theme.font_styles.bigger;
+    let font = &game.ui_theme.button_styles.small.font;
+    let size = THUMBNAIL_SIZE.as_vec2();
+    let size = egui::vec2(size.x, size.y);
+
+    ui.horizontal(|ui| {
+        match thumbnails.get(key, map_meta) {
+            Some(texture_id) => {
+                ui.image(texture_id, size);
+            }
+            None => {
+                ui.allocate_space(size);
+            }
+        }
+        ui.vertical(|ui| {
+            ui.themed_label(bigger_text_style, &map_meta.name);
+            // The author and player counts are added outside of the localized messages, because
+            // the author's name may contain characters that aren't allowed in message arguments.
+            if let Some(author) = &map_meta.author {
+                ui.themed_label(
+                    font,
+                    &format!("{}: {author}", localization.get("map-author")),
+                );
+            }
+            if let Some(players) = &map_meta.recommended_players {
+                let count = if players.min == players.max {
+                    players.min.to_string()
+                } else {
+                    format!("{} - {}", players.min, players.max)
+                };
+                ui.themed_label(
+                    font,
+                    &format!("{}: {count}", localization.get("map-recommended-players")),
+                );
+            }
+        });
+    });
+}
+
+/// Get the handles for all of the core maps, stable maps first.
+fn core_maps(core: &CoreMetaArc) -> Vec<bones::Handle<MapMeta>> {
+    core.stable_maps
+        .iter()
+        .chain(core.experimental_maps.iter())
+        .cloned()
+        .collect()
+}
+
+/// Check the chosen map for problems, and start the game on it if there are none.
+///
+/// If there are problems, they are shown to the player instead.
+fn try_start_game(params: &mut MapSelectMenu, choice: MapChoice) {
+    let map_meta = match &choice {
+        MapChoice::Core(map_handle) => {
+            let Some(map_meta) = params.map_assets.get(&map_handle.get_bevy_handle()) else {
+                error!("Selected map is not loaded");
+                return;
+            };
+            map_meta.clone()
+        }
+        MapChoice::User(map_meta) => map_meta.clone(),
+    };
+    let errors =
+        map_validation::validate_map(&map_meta, &params.element_assets, &params.atlas_assets);
+
+    if errors.is_empty() {
+        start_game(params, choice);
+    } else {
+        warn!(map = %map_meta.name, ?errors, "Selected map has problems");
+        *params.map_problems = Some(MapProblems {
+            choice,
+            map_meta,
+            errors,
+        });
+    }
+}
+
+/// Start a game on the given map, over the network if we are in a network match.
+fn start_game(params: &mut MapSelectMenu, choice: MapChoice) {
+    let map_meta = match choice {
+        MapChoice::Core(map_handle) => {
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(socket) = &params.network_socket {
+                info!("Selected map, starting network game");
+                let seed = rand::random();
+                socket.send_message(
+                    SocketTarget::All,
+                    MapSelectMessage::SelectMap {
+                        map: map_handle.clone(),
+                        seed,
+                    },
+                );
+                start_network_game(params, map_handle, seed);
+                return;
+            }
+
+            let Some(map_meta) = params.map_assets.get(&map_handle.get_bevy_handle()) else {
+                error!("Selected map is not loaded");
+                return;
+            };
+            map_meta.clone()
+        }
+        MapChoice::User(map_meta) => map_meta,
+    };
+
+    info!("Selected map, loading game");
+    let is_training = *params.is_training;
+    let info = if is_training {
+        let player = params.core.players[0].clone();
+        params.commands.insert_resource(PlayerNames::default());
+        training_session_info(&params.core, map_meta, player)
+    } else {
+        params
+            .commands
+            .insert_resource(PlayerNames::from_slots(&params.player_select_state));
+        let mut player_info = <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
+        (0..MAX_PLAYERS).for_each(|i| {
+            let slot = &params.player_select_state.slots[i];
+            if slot.active {
+                player_info[i] = Some(GameSessionPlayerInfo {
+                    handle: slot.selected_player.clone(),
+                    is_ai: slot.is_ai,
+                    ai_difficulty: slot.ai_difficulty,
+                });
             }
         });
+        CoreSessionInfo {
+            meta: params.core.0.clone(),
+            map_meta,
+            player_info,
+            // Mutators are only played in local games for now
+            mutators: params.storage.get_item(),
+            seed: rand::random(),
+        }
+    };
+    params.commands.insert_resource(MapLoading::new(
+        info,
+        MapLoadingStart::Local { is_training },
+    ));
+}
+
+/// Renders the list of problems found in the map the player tried to play.
+///
+/// Maps with only warnings may still be played, but maps with errors may not.
+fn map_problems_menu(
+    ui: &mut egui::Ui,
+    params: &mut MapSelectMenu,
+    outer_margin: egui::style::Margin,
+) {
+    let Some(problems) = params.map_problems.as_ref() else {
+        return;
+    };
+    let is_fatal = problems.errors.iter().any(|error| error.is_fatal());
+    let bigger_text_style = &params.game.ui_theme.font_styles.bigger;
+    let small_button_style = &params.game.ui_theme.button_styles.small;
+
+    let mut go_back = false;
+    let mut play_anyway = false;
+    BorderedFrame::new(&params.game.ui_theme.panel.border)
+        .margin(outer_margin)
+        .padding(params.game.ui_theme.panel.padding.into())
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+
+            ui.themed_label(
+                bigger_text_style,
+                &format!(
+                    "{}: {}",
+                    params.localization.get("map-problems"),
+                    problems.map_meta.name
+                ),
+            );
+            ui.themed_label(
+                &small_button_style.font,
+                &params.localization.get(if is_fatal {
+                    "map-has-errors"
+                } else {
+                    "map-has-warnings"
+                }),
+            );
+            ui.add_space(ui.spacing().item_spacing.y);
+
+            ui.horizontal(|ui| {
+                let back_button =
+                    BorderedButton::themed(small_button_style, &params.localization.get("back"))
+                        .show(ui)
+                        .focus_by_default(ui);
+                go_back = back_button.clicked()
+                    || params.menu_input.single().just_pressed(MenuAction::Back);
+
+                if !is_fatal {
+                    let play_button = BorderedButton::themed(
+                        small_button_style,
+                        &params.localization.get("play-anyway"),
+                    )
+                    .show(ui);
+                    params
+                        .adjacencies
+                        .widget(&play_button)
+                        .to_right_of(&back_button);
+                    play_anyway = play_button.clicked();
+                }
+            });
+
+            ui.add_space(bigger_text_style.size / 2.0);
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for error in &problems.errors {
+                    ui.themed_label(
+                        &small_button_style.font,
+                        &map_validation::map_problem_message(
+                            &params.localization,
+                            &problems.map_meta,
+                            error,
+                        ),
+                    );
+                }
+            });
+        });
+
+    if go_back {
+        *params.map_problems = None;
+    } else if play_anyway {
+        if let Some(problems) = params.map_problems.take() {
+            start_game(params, problems.choice);
+        }
     }
 }
 
+/// Renders the editor for the map rotation playlist.
+fn playlist_editor(
+    ui: &mut egui::Ui,
+    params: &mut MapSelectMenu,
+    outer_margin: egui::style::Margin,
+) {
+    let mut playlist: MapPlaylistStorage = params
+        .storage
+        .get(MapPlaylistStorage::STORAGE_KEY)
+        .unwrap_or_default();
+    let bigger_text_style = &params.game.ui_theme.font_styles.bigger;
+    let small_button_style = &params.game.ui_theme.button_styles.small;
+
+    let mut changed = false;
+    BorderedFrame::new(&params.game.ui_theme.panel.border)
+        .margin(outer_margin)
+        .padding(params.game.ui_theme.panel.padding.into())
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+
+            let done_button =
+                BorderedButton::themed(small_button_style, &params.localization.get("done"))
+                    .show(ui)
+                    .focus_by_default(ui);
+            if done_button.clicked() || params.menu_input.single().just_pressed(MenuAction::Back) {
+                *params.is_editing_playlist = false;
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.add_space(bigger_text_style.size / 2.0);
+                ui.themed_label(bigger_text_style, &params.localization.get("map-playlist"));
+
+                if playlist.is_empty() {
+                    ui.themed_label(
+                        &small_button_style.font,
+                        &params.localization.get("map-playlist-empty"),
+                    );
+                }
+
+                let mut action = None;
+                let mut above: Option<egui::Response> = None;
+                for (i, map_handle) in playlist.iter().enumerate() {
+                    // Maps that have been removed since the playlist was saved can still be
+                    // removed from it.
+                    let name = params
+                        .map_assets
+                        .get(&map_handle.get_bevy_handle())
+                        .map(|map_meta| map_meta.name.clone())
+                        .unwrap_or_else(|| params.localization.get("missing-map"));
+
+                    ui.add_space(ui.spacing().item_spacing.y);
+                    ui.horizontal(|ui| {
+                        let up_button = BorderedButton::themed(
+                            small_button_style,
+                            &params.localization.get("playlist-move-up"),
+                        )
+                        .show(ui);
+                        let down_button = BorderedButton::themed(
+                            small_button_style,
+                            &params.localization.get("playlist-move-down"),
+                        )
+                        .show(ui);
+                        let remove_button = BorderedButton::themed(
+                            small_button_style,
+                            &params.localization.get("playlist-remove"),
+                        )
+                        .show(ui);
+                        ui.themed_label(&small_button_style.font, &name);
+
+                        params
+                            .adjacencies
+                            .widget(&down_button)
+                            .to_right_of(&up_button);
+                        params
+                            .adjacencies
+                            .widget(&remove_button)
+                            .to_right_of(&down_button);
+                        if let Some(above) = &above {
+                            params.adjacencies.widget(&up_button).below(above);
+                        } else {
+                            params.adjacencies.widget(&up_button).below(&done_button);
+                        }
+
+                        if up_button.clicked() && i > 0 {
+                            action = Some(PlaylistAction::Swap(i, i - 1));
+                        } else if down_button.clicked() && i + 1 < playlist.len() {
+                            action = Some(PlaylistAction::Swap(i, i + 1));
+                        } else if remove_button.clicked() {
+                            action = Some(PlaylistAction::Remove(i));
+                        }
+                        above = Some(up_button);
+                    });
+                }
+
+                ui.add_space(bigger_text_style.size / 2.0);
+                ui.themed_label(
+                    bigger_text_style,
+                    &params.localization.get("add-to-playlist"),
+                );
+
+                for map_handle in core_maps(&params.core) {
+                    let Some(map_meta) = params.map_assets.get(&map_handle.get_bevy_handle()) else {
+                        continue;
+                    };
+                    ui.add_space(ui.spacing().item_spacing.y);
+                    if BorderedButton::themed(small_button_style, &map_meta.name)
+                        .show(ui)
+                        .clicked()
+                    {
+                        action = Some(PlaylistAction::Add(map_handle));
+                    }
+                }
+
+                match action {
+                    Some(PlaylistAction::Swap(a, b)) => playlist.swap(a, b),
+                    Some(PlaylistAction::Remove(i)) => {
+                        playlist.remove(i);
+                    }
+                    Some(PlaylistAction::Add(map_handle)) => playlist.push(map_handle),
+                    None => return,
+                }
+                changed = true;
+            });
+        });
+
+    if changed {
+        params
+            .storage
+            .set(MapPlaylistStorage::STORAGE_KEY, &playlist);
+        params.storage.save();
+    }
+}
+
+/// A change made to the playlist in the [`playlist_editor`].
+enum PlaylistAction {
+    Swap(usize, usize),
+    Remove(usize),
+    Add(bones::Handle<MapMeta>),
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 fn handle_match_setup_messages(params: &mut MapSelectMenu) {
-    if let Some(socket) = &params.network_socket {
-        let datas: Vec<(usize, Vec<u8>)> = socket.recv_reliable();
+    let Some(socket) = &params.network_socket else {
+        return;
+    };
+    for (player, message) in socket.recv_messages() {
+        let ReliableMessage::MapSelect(message) = message else {
+            continue;
+        };
+        match message {
+            MapSelectMessage::SelectMap { map, seed } => {
+                if player != 0 {
+                    warn!(%player, "Ignoring map selection: only player 0 may select the map");
+                    continue;
+                }
+                info!("Other player selected map, starting game");
+                start_network_game(params, map, seed);
+            }
+            MapSelectMessage::VoteMap { map_idx, seed } => {
+                params.map_vote_state.votes[player] = Some((map_idx, seed));
+            }
+        }
+    }
+}
+
+/// Start the network game on the given map, with the session seed chosen by player 0.
+#[cfg(not(target_arch = "wasm32"))]
+fn start_network_game(params: &mut MapSelectMenu, map_handle: bones::Handle<MapMeta>, seed: u64) {
+    if params.network_socket.is_none() {
+        return;
+    }
+    *params.map_vote_state = default();
+
+    let map_meta = params
+        .map_assets
+        .get(&map_handle.get_bevy_handle())
+        .unwrap()
+        .clone();
 
-        for (player, data) in datas {
-            assert_eq!(player, 0, "Only player 0 may select the map.");
+    // Show the map in the beacon of the LAN game we are hosting, until the match starts
+    if let Some(beacon) = &mut params.lan_beacon {
+        beacon.update(|info| info.map = Some(map_meta.name.clone()));
+    }
+
+    let mut player_info = <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
+    (0..MAX_PLAYERS).for_each(|i| {
+        let slot = &params.player_select_state.slots[i];
+        if slot.active {
+            player_info[i] = Some(GameSessionPlayerInfo {
+                handle: slot.selected_player.clone(),
+                is_ai: slot.is_ai,
+                ai_difficulty: slot.ai_difficulty,
+            });
+        }
+    });
+    params
+        .commands
+        .insert_resource(PlayerNames::from_slots(&params.player_select_state));
+    params.commands.insert_resource(MapLoading::new(
+        CoreSessionInfo {
+            meta: params.core.0.clone(),
+            map_meta,
+            player_info,
+            mutators: default(),
+            seed,
+        },
+        MapLoadingStart::Network,
+    ));
+}
+
+/// Renders the map list for voting on the map in an online game.
+///
+/// Player 0 ends the vote once everybody has voted, once the countdown runs out, or when they
+/// choose to start early, and then tells everybody else which map won.
+#[cfg(not(target_arch = "wasm32"))]
+fn map_vote_menu(ui: &mut egui::Ui, params: &mut MapSelectMenu, outer_margin: egui::style::Margin) {
+    let Some(socket) = &params.network_socket else {
+        return;
+    };
+    let player_idx = socket.player_idx();
+    let player_count = socket.player_count();
+    let now = params.time.elapsed_seconds();
+
+    let vote_state = &mut *params.map_vote_state;
+    if vote_state.started_at.is_none() {
+        vote_state.started_at = Some(now);
+        vote_state.local_seed = rand::random();
+    }
+    let started_at = vote_state.started_at.unwrap();
+    let remaining = (params.game.main_menu.map_vote_duration - (now - started_at)).max(0.0);
 
-            match postcard::from_bytes::<MapSelectMessage>(&data) {
-                Ok(message) => match message {
-                    MapSelectMessage::SelectMap(map_handle) => {
-                        info!("Other player selected map, starting game");
-                        *params.pause_page = PauseMenuPage::Default;
-                        *params.menu_page = MenuPage::Home;
+    let maps = core_maps(&params.core);
+    let random_idx = maps.len();
+    let vote_counts = params.map_vote_state.vote_counts(maps.len() + 1);
+    let own_vote = params.map_vote_state.votes[player_idx].map(|(map_idx, _)| map_idx);
 
-                        let map_meta = params
+    let bigger_text_style = &params.game.ui_theme.font_styles.bigger;
+    let small_button_style = &params.game.ui_theme.button_styles.small;
+
+    let seconds = remaining.ceil() as u32;
+    ui.themed_label(
+        bigger_text_style,
+        &params
+            .localization
+            .get(&format!("map-vote-countdown?seconds={seconds}")),
+    );
+
+    let mut voted_map = None;
+    let mut force_start = false;
+    BorderedFrame::new(&params.game.ui_theme.panel.border)
+        .margin(outer_margin)
+        .padding(params.game.ui_theme.panel.padding.into())
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+
+            if player_idx == 0 {
+                force_start = BorderedButton::themed(
+                    small_button_style,
+                    &params.localization.get("map-vote-start-now"),
+                )
+                .show(ui)
+                .clicked();
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let random_map = params.localization.get("random-map");
+                let names = maps
+                    .iter()
+                    .map(|map_handle| {
+                        &params
                             .map_assets
                             .get(&map_handle.get_bevy_handle())
-                            .unwrap()
-                            .clone();
-
-                        let mut player_info =
-                            <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
-                        (0..MAX_PLAYERS).for_each(|i| {
-                            let slot = &params.player_select_state.slots[i];
-                            if slot.active {
-                                player_info[i] = Some(GameSessionPlayerInfo {
-                                    handle: slot.selected_player.clone(),
-                                    is_ai: slot.is_ai,
-                                });
-                            }
-                        });
-                        params.session_manager.start_network(
-                            CoreSessionInfo {
-                                meta: params.core.0.clone(),
-                                map_meta,
-                                player_info,
-                            },
-                            GgrsSessionRunnerInfo {
-                                socket: socket.ggrs_socket(),
-                                player_is_local: socket.player_is_local(),
-                                player_count: socket.player_count(),
-                            },
-                        );
-                        params
-                            .commands
-                            .insert_resource(NextState(Some(EngineState::InGame)));
-                        params
-                            .commands
-                            .insert_resource(NextState(Some(InGameState::Playing)));
+                            .expect("Error loading map")
+                            .name
+                    })
+                    .chain([&random_map]);
+                for (map_idx, name) in names.enumerate() {
+                    ui.add_space(ui.spacing().item_spacing.y);
+
+                    let votes = vote_counts[map_idx];
+                    let label = if own_vote == Some(map_idx) {
+                        format!("> {name} ({votes}) <")
+                    } else {
+                        format!("{name} ({votes})")
+                    };
+
+                    let mut button = BorderedButton::themed(small_button_style, &label).show(ui);
+                    if map_idx == 0 {
+                        button = button.focus_by_default(ui);
                     }
-                },
-                Err(e) => warn!("Ignoring network message that was not understood: {e}"),
-            }
+                    if button.clicked() {
+                        voted_map = Some(map_idx);
+                    }
+                }
+            });
+        });
+
+    if let Some(map_idx) = voted_map {
+        let seed = params.map_vote_state.local_seed;
+        params.map_vote_state.votes[player_idx] = Some((map_idx, seed));
+        socket.send_message(
+            SocketTarget::All,
+            MapSelectMessage::VoteMap { map_idx, seed },
+        );
+    }
+
+    if player_idx != 0 {
+        return;
+    }
+
+    let everybody_voted = (0..player_count).all(|player| {
+        params.map_vote_state.votes[player].is_some() || !socket.player_is_connected(player)
+    });
+    if everybody_voted || remaining <= 0.0 || force_start {
+        // Fall back to the first map if nobody voted
+        let mut map_idx = params.map_vote_state.winner(maps.len() + 1).unwrap_or(0);
+        if map_idx == random_idx {
+            // Only player 0 rolls the random map, and everybody else gets it from `SelectMap`
+            map_idx = rand::thread_rng().gen_range(0..maps.len());
         }
+        let map_handle = maps[map_idx].clone();
+        info!(%map_idx, "Map vote finished, starting network game");
+
+        let seed = rand::random();
+        socket.send_message(
+            SocketTarget::All,
+            MapSelectMessage::SelectMap {
+                map: map_handle.clone(),
+                seed,
+            },
+        );
+        start_network_game(params, map_handle, seed);
     }
 }
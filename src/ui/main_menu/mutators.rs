@@ -0,0 +1,156 @@
+use super::*;
+
+/// The last used mutators are stored, so they stay turned on for the next local game.
+impl StorageItem for Mutators {
+    const STORAGE_KEY: &'static str = "mutators";
+}
+
+/// Get the localization key of a mutator's name.
+pub fn mutator_localization_key(mutator: Mutator) -> &'static str {
+    match mutator {
+        Mutator::LowGravity => "mutator-low-gravity",
+        Mutator::BouncyBodies => "mutator-bouncy-bodies",
+        Mutator::OneHitKill => "mutator-one-hit-kill",
+        Mutator::FastFish => "mutator-fast-fish",
+        Mutator::ItemRain => "mutator-item-rain",
+    }
+}
+
+/// The page between the player and map selection of a local game, where the [`Mutators`] that
+/// change the rules of the match are turned on and off.
+#[derive(SystemParam)]
+pub struct MutatorsMenu<'w, 's> {
+    game: Res<'w, GameMeta>,
+    menu_page: ResMut<'w, MenuPage>,
+    storage: ResMut<'w, Storage>,
+    localization: Res<'w, Localization>,
+    adjacencies: ResMut<'w, WidgetAdjacencies>,
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+}
+
+impl<'w, 's> WidgetSystem for MutatorsMenu<'w, 's> {
+    type Args = ();
+
+    fn system(
+        world: &mut World,
+        state: &mut SystemState<Self>,
+        ui: &mut egui::Ui,
+        _id: WidgetId,
+        _args: Self::Args,
+    ) {
+        let mut params: MutatorsMenu = state.get_mut(world);
+
+        let ui_theme = &params.game.ui_theme;
+        let heading_font = ui_theme
+            .font_styles
+            .heading
+            .colored(ui_theme.panel.font_color);
+        let bigger_font = ui_theme
+            .font_styles
+            .bigger
+            .colored(ui_theme.panel.font_color);
+
+        let menu_width = params.game.main_menu.menu_width;
+        let x_margin = (ui.available_width() - menu_width) / 2.0;
+        let outer_margin = egui::style::Margin::symmetric(x_margin, bigger_font.size);
+
+        let menu_input = params.menu_input.single();
+        let back_pressed = menu_input.just_pressed(MenuAction::Back)
+            || params.keyboard_input.just_pressed(KeyCode::Escape);
+        let start_pressed = menu_input.just_pressed(MenuAction::Start);
+
+        let mut mutators: Mutators = params.storage.get_item();
+
+        BorderedFrame::new(&ui_theme.panel.border)
+            .margin(outer_margin)
+            .padding(ui_theme.panel.padding.into())
+            .show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.themed_label(&heading_font, &params.localization.get("mutators"));
+                });
+                ui.set_min_width(ui.available_width());
+
+                ui.add_space(bigger_font.size);
+
+                let toggles = Mutator::ALL.map(|mutator| {
+                    ui.horizontal(|ui| {
+                        ui.themed_label(
+                            &bigger_font,
+                            &format!(
+                                "{}:",
+                                params.localization.get(mutator_localization_key(mutator))
+                            ),
+                        );
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            let enabled = mutators.contains(mutator);
+                            let toggle_button = BorderedButton::themed(
+                                &ui_theme.button_styles.small,
+                                &params.localization.get(if enabled { "on" } else { "off" }),
+                            )
+                            .show(ui);
+                            if toggle_button.clicked() {
+                                mutators.set(mutator, !enabled);
+                                params.storage.set_item(&mutators);
+                            }
+
+                            toggle_button
+                        })
+                        .inner
+                    })
+                    .inner
+                });
+
+                ui.add_space(bigger_font.size);
+
+                let (back_button, continue_button) = ui
+                    .horizontal(|ui| {
+                        let back_button = BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &params.localization.get("back"),
+                        )
+                        .show(ui);
+
+                        let continue_button = ui
+                            .with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                BorderedButton::themed(
+                                    &ui_theme.button_styles.normal,
+                                    &params.localization.get("continue"),
+                                )
+                                .show(ui)
+                                .focus_by_default(ui)
+                            })
+                            .inner;
+
+                        (back_button, continue_button)
+                    })
+                    .inner;
+
+                for pair in toggles.windows(2) {
+                    params.adjacencies.widget(&pair[1]).below(&pair[0]);
+                }
+                let last_toggle = toggles.last().unwrap();
+                params.adjacencies.widget(&back_button).below(last_toggle);
+                params
+                    .adjacencies
+                    .widget(&continue_button)
+                    .below(last_toggle)
+                    .to_right_of(&back_button);
+
+                if back_button.clicked() || back_pressed {
+                    *params.menu_page = MenuPage::PlayerSelect;
+                    ui.ctx().clear_focus();
+                } else if continue_button.clicked() || start_pressed {
+                    // Remember the mutators for the next local game
+                    params.storage.save();
+                    *params.menu_page = MenuPage::MapSelect {
+                        is_waiting: false,
+                        is_voting: false,
+                        is_training: false,
+                    };
+                    ui.ctx().clear_focus();
+                }
+            });
+    }
+}
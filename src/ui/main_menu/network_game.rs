@@ -1,20 +1,19 @@
 use std::{
-    net::{IpAddr, Ipv4Addr},
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
     time::Duration,
 };
 
 use bevy::utils::Instant;
 use smallvec::SmallVec;
 
-use crate::networking::{NetworkMatchSocket, LAN_MATCHMAKER, NETWORK_ENDPOINT, ONLINE_MATCHMAKER};
+use egui_extras::Column;
 
-use super::*;
-
-const MDNS_SERVICE_TYPE: &str = "_jumpy._udp.local.";
+use crate::networking::{
+    DiscoveredLanGame, HandshakeError, HandshakeStatus, LanBeacon, LanBrowser, LanHostInfo,
+    NetworkMatchSocket, LAN_MATCHMAKER, NETWORK_ENDPOINT, ONLINE_MATCHMAKER,
+};
 
-static MDNS: Lazy<mdns_sd::ServiceDaemon> = Lazy::new(|| {
-    mdns_sd::ServiceDaemon::new().expect("Couldn't start MDNS service discovery thread.")
-});
+use super::*;
 
 #[derive(DerefMut, Deref)]
 pub struct Pinger(
@@ -64,15 +63,24 @@ pub struct MatchmakingMenu<'w, 's> {
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
     commands: Commands<'w, 's>,
     storage: ResMut<'w, Storage>,
+    lan_beacon: Option<ResMut<'w, LanBeacon>>,
+    network_socket: Option<Res<'w, NetworkMatchSocket>>,
 }
 
 pub struct State {
     match_kind: MatchKind,
-    lan_service_discovery_recv: Option<mdns_sd::Receiver<mdns_sd::ServiceEvent>>,
-    service_info: Option<mdns_sd::ServiceInfo>,
+    /// Lists the LAN games being hosted, or the error that kept it from starting.
+    lan_browser: Option<std::io::Result<LanBrowser>>,
+    /// The pings of the discovered LAN hosts, in milliseconds.
+    lan_pings: HashMap<Ipv4Addr, u16>,
+    /// The address typed in to join a LAN game that wasn't discovered.
+    join_address: String,
+    /// The localization key of the error from the last attempt to join a LAN game.
+    join_error: Option<&'static str>,
+    /// The reason that the handshake with the other players failed, shown until it is dismissed.
+    handshake_error: Option<HandshakeError>,
     status: Status,
     joined_players: usize,
-    lan_servers: Vec<ServerInfo>,
     ping_update_timer: Timer,
 }
 
@@ -83,28 +91,27 @@ pub enum Status {
     Joining,
     Hosting,
     Searching,
+    /// Connected to the other players, and checking that they have a compatible version of the
+    /// game.
+    Handshaking,
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
             match_kind: default(),
-            lan_service_discovery_recv: default(),
-            service_info: default(),
+            lan_browser: default(),
+            lan_pings: default(),
+            join_address: default(),
+            join_error: default(),
+            handshake_error: default(),
             status: default(),
-            lan_servers: default(),
             joined_players: default(),
             ping_update_timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating),
         }
     }
 }
 
-pub struct ServerInfo {
-    pub service: mdns_sd::ServiceInfo,
-    /// The ping in milliseconds
-    pub ping: Option<u16>,
-}
-
 #[derive(Clone)]
 pub enum MatchKind {
     Lan(LanMode),
@@ -277,9 +284,11 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
 
                 let State {
                     match_kind,
-                    lan_service_discovery_recv,
-                    lan_servers,
-                    service_info: host_info,
+                    lan_browser,
+                    lan_pings,
+                    join_address,
+                    join_error,
+                    handshake_error,
                     status,
                     ping_update_timer,
                     joined_players,
@@ -289,29 +298,86 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                 ui.add_space(normal_text_style.size);
 
                 match match_kind {
+                    // Connected, and checking the versions of the other players
+                    _ if *status == Status::Handshaking || handshake_error.is_some() => {
+                        if let Some(error) = handshake_error {
+                            ui.themed_label(
+                                &normal_text_style.colored(params.game.ui_theme.colors.negative),
+                                &params.localization.get(&error.localization_query()),
+                            );
+                            ui.add_space(normal_text_style.size / 2.0);
+                            if BorderedButton::themed(
+                                small_button_style,
+                                &params.localization.get("ok"),
+                            )
+                            .show(ui)
+                            .clicked()
+                            {
+                                *handshake_error = None;
+                            }
+                        } else {
+                            ui.themed_label(
+                                normal_text_style,
+                                &params.localization.get("checking-versions"),
+                            );
+
+                            // The socket is only added at the end of the frame that the match
+                            // started on.
+                            let handshake_status = params
+                                .network_socket
+                                .as_ref()
+                                .map(|socket| socket.handshake_status());
+                            match handshake_status {
+                                None | Some(HandshakeStatus::Waiting) => (),
+                                Some(HandshakeStatus::Complete) => {
+                                    *status = default();
+                                    *params.menu_page = MenuPage::PlayerSelect;
+                                }
+                                Some(HandshakeStatus::Failed(error)) => {
+                                    warn!(?error, "Handshake with network players failed");
+                                    disconnect(
+                                        &mut params.commands,
+                                        params.network_socket.as_deref(),
+                                    );
+                                    *status = Status::Idle;
+                                    *handshake_error = Some(error);
+                                }
+                            }
+                        }
+                    }
+
                     // LAN game
                     MatchKind::Lan(mode) => match mode {
                         LanMode::Join => {
                             // Stop any running server
-                            if let Some(service_info) = host_info.take() {
-                                loop {
-                                    match MDNS.unregister(service_info.get_fullname()) {
-                                        Ok(_) => break,
-                                        Err(mdns_sd::Error::Again) => (),
-                                        Err(e) => panic!("Error unregistering MDNS service: {e}"),
-                                    }
-                                }
+                            if *status == Status::Hosting {
+                                stop_hosting(&mut params.commands);
                                 *status = Status::Idle;
                             }
 
+                            let browser = lan_browser.get_or_insert_with(|| {
+                                LanBrowser::start().map_err(|e| {
+                                    warn!("Could not listen for LAN games: {e}");
+                                    e
+                                })
+                            });
+                            if let Ok(browser) = browser.as_mut() {
+                                browser.update();
+                            }
+                            let games = browser
+                                .as_ref()
+                                .map(|x| x.games())
+                                .unwrap_or_default();
+
                             // Update server pings
                             if ping_update_timer.finished() {
                                 PINGER
                                     .try_send(
-                                        lan_servers
+                                        games
                                             .iter()
-                                            .map(|x| {
-                                                *x.service.get_addresses().iter().next().unwrap()
+                                            .filter_map(|game| match game.addr {
+                                                SocketAddr::V4(addr) => Some(*addr.ip()),
+                                                SocketAddr::V6(_) => None,
                                             })
                                             .collect(),
                                     )
@@ -319,32 +385,10 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                             }
                             if let Ok(pings) = PINGER.try_recv() {
                                 for (server, ping) in pings {
-                                    for info in lan_servers.iter_mut() {
-                                        if info.service.get_addresses().contains(&server) {
-                                            info.ping = ping;
-                                        }
-                                    }
-                                }
-                            }
-
-                            let events = lan_service_discovery_recv.get_or_insert_with(|| {
-                                MDNS.browse(MDNS_SERVICE_TYPE)
-                                    .expect("Couldn't start service discovery")
-                            });
-
-                            while let Ok(event) = events.try_recv() {
-                                match event {
-                                    mdns_sd::ServiceEvent::ServiceResolved(info) => lan_servers
-                                        .push(ServerInfo {
-                                            service: info,
-                                            ping: None,
-                                        }),
-                                    mdns_sd::ServiceEvent::ServiceRemoved(_, full_name) => {
-                                        lan_servers.retain(|server| {
-                                            server.service.get_fullname() != full_name
-                                        });
-                                    }
-                                    _ => (),
+                                    match ping {
+                                        Some(ping) => lan_pings.insert(server, ping),
+                                        None => lan_pings.remove(&server),
+                                    };
                                 }
                             }
 
@@ -355,50 +399,71 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                 );
                                 ui.add_space(normal_text_style.size / 2.0);
 
+                                let mut join = None;
                                 ui.indent("servers", |ui| {
-                                    for server in lan_servers.iter() {
-                                        ui.horizontal(|ui| {
-                                            if BorderedButton::themed(
-                                                &params.game.ui_theme.button_styles.normal,
-                                                server.service.get_hostname().trim_end_matches('.'),
-                                            )
-                                            .min_size(egui::vec2(ui.available_width() * 0.8, 0.0))
-                                            .show(ui)
-                                            .clicked()
-                                            {
-                                                *status = Status::Joining;
-                                                LAN_MATCHMAKER.try_send(
-                                                    networking::LanMatchmakerRequest::JoinServer {
-                                                        ip: *server
-                                                            .service
-                                                            .get_addresses()
-                                                            .iter()
-                                                            .next()
-                                                            .unwrap(),
-                                                        port: server.service.get_port(),
-                                                    },
-                                                ).unwrap();
-                                            }
-
-                                            let label_text = egui::RichText::new(format!(
-                                                "🖧 {}ms",
-                                                server
-                                                    .ping
-                                                    .map(|x| x.to_string())
-                                                    .unwrap_or("?".into())
-                                            ))
-                                            .size(normal_text_style.size);
-                                            ui.label(label_text)
-                                        });
-                                    }
-
-                                    if lan_servers.is_empty() {
+                                    if browser.is_err() {
+                                        ui.themed_label(
+                                            smaller_text_style,
+                                            &params.localization.get("lan-discovery-unavailable"),
+                                        );
+                                    } else if games.is_empty() {
                                         ui.themed_label(
                                             normal_text_style,
                                             &params.localization.get("no-servers"),
                                         );
+                                    } else {
+                                        join = lan_games_table(
+                                            ui,
+                                            &params.game,
+                                            &params.localization,
+                                            games,
+                                            lan_pings,
+                                        );
+                                    }
+                                });
+
+                                // Join by address, for when the beacons can't get through
+                                ui.add_space(normal_text_style.size / 2.0);
+                                ui.horizontal(|ui| {
+                                    ui.themed_label(
+                                        normal_text_style,
+                                        &params.localization.get("join-by-address"),
+                                    );
+                                    ui.add(
+                                        egui::TextEdit::singleline(join_address)
+                                            .hint_text("192.168.1.2:10500")
+                                            .font(normal_text_style.font_id()),
+                                    );
+                                    if BorderedButton::themed(
+                                        small_button_style,
+                                        &params.localization.get("join"),
+                                    )
+                                    .show(ui)
+                                    .clicked()
+                                    {
+                                        match join_address.trim().parse::<SocketAddrV4>() {
+                                            Ok(addr) => join = Some(addr),
+                                            Err(_) => *join_error = Some("invalid-address"),
+                                        }
                                     }
                                 });
+                                if let Some(error) = join_error {
+                                    let error_style = smaller_text_style
+                                        .colored(params.game.ui_theme.colors.negative);
+                                    ui.themed_label(&error_style, &params.localization.get(error));
+                                }
+
+                                if let Some(addr) = join {
+                                    info!(%addr, "Joining LAN game");
+                                    *status = Status::Joining;
+                                    *join_error = None;
+                                    LAN_MATCHMAKER
+                                        .try_send(networking::LanMatchmakerRequest::JoinServer {
+                                            ip: *addr.ip(),
+                                            port: addr.port(),
+                                        })
+                                        .unwrap();
+                                }
 
                             // If we are trying to join a match.
                             } else {
@@ -411,18 +476,21 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                     match message {
                                         networking::LanMatchmakerResponse::ServerStarted => (),
                                         networking::LanMatchmakerResponse::PlayerCount(_) => (),
+                                        networking::LanMatchmakerResponse::JoinFailed => {
+                                            *status = Status::Idle;
+                                            *join_error = Some("join-failed");
+                                        }
                                         networking::LanMatchmakerResponse::GameStarting {
                                             lan_socket,
                                             player_idx,
                                             player_count: _,
                                         } => {
                                             info!(?player_idx, "Starting network game");
-                                            params.commands.insert_resource(NetworkMatchSocket(
-                                                Box::new(lan_socket),
-                                            ));
+                                            params.commands.insert_resource(
+                                                NetworkMatchSocket::new(Box::new(lan_socket)),
+                                            );
 
-                                            *status = default();
-                                            *params.menu_page = MenuPage::PlayerSelect;
+                                            *status = Status::Handshaking;
                                         }
                                     }
                                 }
@@ -434,6 +502,9 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                             service_name,
                             player_count,
                         } => {
+                            // Stop listening for other games while hosting our own
+                            *lan_browser = None;
+
                             ui.scope(|ui| {
                                 ui.set_enabled(*status != Status::Hosting);
                                 ui.horizontal(|ui| {
@@ -445,7 +516,6 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                         egui::TextEdit::singleline(service_name)
                                             .font(normal_text_style.font_id()),
                                     );
-                                    *service_name = service_name.replace(' ', "-");
                                 });
                                 ui.add_space(normal_text_style.size / 2.0);
                                 ui.horizontal(|ui| {
@@ -479,39 +549,9 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                                 .clamp(2, MAX_PLAYERS);
                                         }
                                     });
-
-                                    *service_name = service_name.replace(' ', "-");
                                 });
                             });
 
-                            let create_service_info = || {
-                                let port = NETWORK_ENDPOINT.local_addr().unwrap().port();
-                                mdns_sd::ServiceInfo::new(
-                                    MDNS_SERVICE_TYPE,
-                                    service_name,
-                                    service_name,
-                                    "",
-                                    port,
-                                    None,
-                                )
-                                .unwrap()
-                                .enable_addr_auto()
-                            };
-
-                            let service_info = host_info.get_or_insert_with(create_service_info);
-
-                            if service_info.get_hostname() != service_name {
-                                loop {
-                                    match MDNS.unregister(service_info.get_fullname()) {
-                                        Ok(_) => break,
-                                        Err(mdns_sd::Error::Again) => (),
-                                        Err(e) => panic!("Error unregistering MDNS service: {e}"),
-                                    }
-                                }
-                                *status = Status::Idle;
-                                *service_info = create_service_info();
-                            }
-
                             ui.add_space(params.game.ui_theme.font_styles.normal.size);
 
                             if *status == Status::Idle {
@@ -523,13 +563,25 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                 .clicked()
                                 {
                                     *status = Status::Hosting;
-                                    MDNS.register(service_info.clone())
-                                        .expect("Could not register MDNS service.");
+                                    *joined_players = 0;
                                     LAN_MATCHMAKER
                                         .try_send(networking::LanMatchmakerRequest::StartServer {
                                             player_count: *player_count,
                                         })
                                         .unwrap();
+
+                                    // The game can still be joined by address if the beacon
+                                    // can't be broadcast.
+                                    match LanBeacon::start(LanHostInfo {
+                                        host_name: service_name.clone(),
+                                        port: NETWORK_ENDPOINT.local_addr().unwrap().port(),
+                                        player_count: 1,
+                                        max_players: *player_count,
+                                        map: None,
+                                    }) {
+                                        Ok(beacon) => params.commands.insert_resource(beacon),
+                                        Err(e) => warn!("Could not start LAN beacon: {e}"),
+                                    }
                                 }
 
                             // If we are hosting a match currently
@@ -538,28 +590,29 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                     match response {
                                         networking::LanMatchmakerResponse::PlayerCount(count) => {
                                             *joined_players = count;
+                                            if let Some(beacon) = &mut params.lan_beacon {
+                                                beacon.update(|info| info.player_count = count + 1);
+                                            }
                                         }
                                         networking::LanMatchmakerResponse::GameStarting {
                                             lan_socket,
                                             player_idx,
-                                            player_count: _,
+                                            player_count,
                                         } => {
                                             info!(?player_idx, "Starting network game");
-                                            params.commands.insert_resource(NetworkMatchSocket(
-                                                Box::new(lan_socket),
-                                            ));
-
-                                            *status = default();
-                                            *params.menu_page = MenuPage::PlayerSelect;
-                                            loop {
-                                                match MDNS.unregister(service_info.get_fullname()) {
-                                                    Ok(_) => break,
-                                                    Err(mdns_sd::Error::Again) => (),
-                                                    Err(e) => panic!(
-                                                        "Error unregistering MDNS service: {e}"
-                                                    ),
-                                                }
+                                            params.commands.insert_resource(
+                                                NetworkMatchSocket::new(Box::new(lan_socket)),
+                                            );
+
+                                            // The beacon keeps going until the match starts, to
+                                            // show the game as full, and the map once it is picked.
+                                            if let Some(beacon) = &mut params.lan_beacon {
+                                                beacon.update(|info| {
+                                                    info.player_count = player_count;
+                                                });
                                             }
+
+                                            *status = Status::Handshaking;
                                         }
                                         _ => (),
                                     }
@@ -573,15 +626,7 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                     .show(ui)
                                     .clicked()
                                     {
-                                        loop {
-                                            match MDNS.unregister(service_info.get_fullname()) {
-                                                Ok(_) => break,
-                                                Err(mdns_sd::Error::Again) => (),
-                                                Err(e) => {
-                                                    panic!("Error unregistering MDNS service: {e}")
-                                                }
-                                            }
-                                        }
+                                        stop_hosting(&mut params.commands);
                                         *status = Status::Idle;
                                     }
 
@@ -595,6 +640,16 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                         ),
                                     );
                                 });
+
+                                // Show the port, for players that have to join by address
+                                ui.add_space(normal_text_style.size / 2.0);
+                                ui.themed_label(
+                                    smaller_text_style,
+                                    &params.localization.get(&format!(
+                                        "hosting-on-port?port={}",
+                                        NETWORK_ENDPOINT.local_addr().unwrap().port()
+                                    )),
+                                );
                             }
                         }
                     },
@@ -607,11 +662,10 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                     }) => {
                         // Get the matchmaking server from the settings.
                         if matchmaking_server.is_empty() {
-                            *matchmaking_server = params
-                                .storage
-                                .get::<Settings>(Settings::STORAGE_KEY)
-                                .unwrap_or_else(|| params.game.default_settings.clone())
-                                .matchmaking_server;
+                            *matchmaking_server =
+                                Settings::get_stored_or_default(&params.game, &mut params.storage)
+                                    .matchmaking_server
+                                    .clone();
                         }
 
                         ui.horizontal(|ui| {
@@ -679,13 +733,12 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                         player_count: _,
                                     } => {
                                         info!(?player_idx, "Starting network game");
-                                        params.commands.insert_resource(NetworkMatchSocket(
-                                            Box::new(online_socket),
-                                        ));
+                                        params.commands.insert_resource(
+                                            NetworkMatchSocket::new(Box::new(online_socket)),
+                                        );
 
-                                        *status = default();
+                                        *status = Status::Handshaking;
                                         search_state = default();
-                                        *params.menu_page = MenuPage::PlayerSelect;
                                     }
                                 }
                             }
@@ -748,23 +801,119 @@ impl<'w, 's> WidgetSystem for MatchmakingMenu<'w, 's> {
                                 *status = Status::Idle;
                             }
                             Status::Hosting => {
-                                if let Some(service_info) = host_info.take() {
-                                    loop {
-                                        match MDNS.unregister(service_info.get_fullname()) {
-                                            Ok(_) => break,
-                                            Err(mdns_sd::Error::Again) => (),
-                                            Err(e) => {
-                                                panic!("Error unregistering MDNS service: {e}")
-                                            }
-                                        }
-                                    }
-                                }
+                                stop_hosting(&mut params.commands);
+                                *status = Status::Idle;
+                            }
+                            Status::Handshaking => {
+                                disconnect(&mut params.commands, params.network_socket.as_deref());
                                 *status = Status::Idle;
                             }
                         }
+                        *handshake_error = None;
                         *params.menu_page = MenuPage::Home;
                     }
                 });
             });
     }
 }
+
+/// Render the table of discovered LAN games, returning the address to join if one was clicked.
+fn lan_games_table(
+    ui: &mut egui::Ui,
+    game_meta: &GameMeta,
+    localization: &Localization,
+    games: &[DiscoveredLanGame],
+    pings: &HashMap<Ipv4Addr, u16>,
+) -> Option<SocketAddrV4> {
+    let ui_theme = &game_meta.ui_theme;
+    let normal_text_style = &ui_theme.font_styles.normal;
+    let smaller_text_style = &ui_theme.font_styles.smaller;
+    let small_button_style = &ui_theme.button_styles.small;
+    let incompatible_style = normal_text_style.colored(ui_theme.colors.negative);
+
+    let columns = ["server-name", "players", "map", "version", "ping"];
+    let mut join = None;
+    egui_extras::TableBuilder::new(ui)
+        .columns(Column::auto(), columns.len())
+        .column(Column::remainder())
+        .header(normal_text_style.size * 1.5, |mut row| {
+            for title in columns {
+                row.col(|ui| {
+                    ui.themed_label(smaller_text_style, &localization.get(title));
+                });
+            }
+            row.col(|_| ());
+        })
+        .body(|body| {
+            let row_height = small_button_style.font.size * 2.0;
+            body.rows(row_height, games.len(), |row_idx, mut row| {
+                let game = &games[row_idx];
+                let info = game.info.as_ref();
+                let ping = match game.addr {
+                    SocketAddr::V4(addr) => pings.get(addr.ip()),
+                    SocketAddr::V6(_) => None,
+                };
+                let players = info.map_or("-".into(), |info| {
+                    format!("{} / {}", info.player_count, info.max_players)
+                });
+                let map = info.and_then(|info| info.map.clone());
+                let version_style = if game.is_compatible() {
+                    normal_text_style
+                } else {
+                    &incompatible_style
+                };
+                let cells = [
+                    (normal_text_style, game.host_name.clone()),
+                    (normal_text_style, players),
+                    (normal_text_style, map.unwrap_or_else(|| "-".into())),
+                    (version_style, game.game_version.clone()),
+                    (
+                        normal_text_style,
+                        ping.map_or("?".into(), |ping| format!("{ping}ms")),
+                    ),
+                ];
+                for (style, cell) in cells {
+                    row.col(|ui| {
+                        ui.themed_label(style, &cell);
+                    });
+                }
+
+                // Games hosted with another version of the game, or that are full, can't be joined
+                row.col(|ui| {
+                    let join_addr = game.join_addr();
+                    let label = if !game.is_compatible() {
+                        "incompatible-version"
+                    } else if join_addr.is_none() {
+                        "server-full"
+                    } else {
+                        "join"
+                    };
+                    ui.set_enabled(join_addr.is_some());
+                    if BorderedButton::themed(small_button_style, &localization.get(label))
+                        .show(ui)
+                        .clicked()
+                    {
+                        join = join_addr;
+                    }
+                });
+            });
+        });
+    join
+}
+
+/// Stop hosting a LAN game, and broadcasting its beacon.
+fn stop_hosting(commands: &mut Commands) {
+    LAN_MATCHMAKER
+        .try_send(networking::LanMatchmakerRequest::StopServer)
+        .unwrap();
+    commands.remove_resource::<LanBeacon>();
+}
+
+/// Close the connection to the other players before the match has started.
+fn disconnect(commands: &mut Commands, socket: Option<&NetworkMatchSocket>) {
+    if let Some(socket) = socket {
+        socket.close();
+    }
+    commands.remove_resource::<NetworkMatchSocket>();
+    commands.remove_resource::<LanBeacon>();
+}
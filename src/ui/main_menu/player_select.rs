@@ -1,18 +1,23 @@
 use crate::loading::PlayerInputCollector;
 #[cfg(not(target_arch = "wasm32"))]
-use crate::networking::{NetworkMatchSocket, SocketTarget};
+use crate::{
+    custom_players::CustomPlayers,
+    networking::{proto::ReliableMessage, NetworkMatchSocket, SocketTarget},
+};
 
 use bones_lib::prelude::{key, Key, KeyError};
-use rand::Rng;
+use leafwing_input_manager::user_input::InputKind;
 
 use super::*;
 
-const GAMEPAD_ACTION_IDX: usize = 0;
-const KEYPAD_ACTION_IDX: usize = 1;
-
 #[derive(Resource, Default)]
 pub struct PlayerSelectState {
     pub slots: [PlayerSlot; MAX_PLAYERS],
+    /// Whether we have sent our profile to the other players in an online game.
+    pub sent_profile: bool,
+    /// Which players were connected the last time we checked, in an online game, to notice
+    /// players joining and leaving.
+    pub connected: [bool; MAX_PLAYERS],
 }
 
 #[derive(Default)]
@@ -21,6 +26,83 @@ pub struct PlayerSlot {
     pub confirmed: bool,
     pub selected_player: bones::Handle<PlayerMeta>,
     pub is_ai: bool,
+    pub ai_difficulty: AiDifficulty,
+    /// Whether a device just joined this slot, and the button it joined with hasn't been released
+    /// yet, so it doesn't also lock in the player's selection.
+    pub joining: bool,
+    /// The player's display name, from their profile or, for remote players, from the network.
+    pub name: Option<String>,
+    /// Whether the player picked a custom fish that somebody else in the online game doesn't
+    /// have, so the default fish is used instead.
+    pub missing_custom_player: bool,
+    /// The index of the fish under the cursor of the slot's fish picker grid, if it is open.
+    ///
+    /// The selected fish only changes once a fish is picked, so closing the picker keeps the
+    /// previous selection.
+    pub picker_cursor: Option<usize>,
+}
+
+impl PlayerSlot {
+    /// Use a player's profile for this slot, picking their preferred fish if it still exists.
+    pub fn apply_profile(&mut self, profile: Option<&PlayerProfile>, core: &CoreMeta) {
+        let Some(profile) = profile else {
+            return;
+        };
+        self.name = profile.display_name();
+        if let Some(fish) = profile
+            .preferred_fish
+            .as_ref()
+            .and_then(|fish| core.players.iter().find(|x| x.path == fish.path))
+        {
+            self.selected_player = fish.clone();
+        }
+    }
+}
+
+/// The number of fish in each row of the fish picker grid.
+const FISH_PICKER_COLUMNS: usize = 3;
+/// The number of rows on each page of the fish picker grid.
+const FISH_PICKER_ROWS: usize = 3;
+/// The number of fish on each page of the fish picker grid.
+const FISH_PICKER_PAGE_SIZE: usize = FISH_PICKER_COLUMNS * FISH_PICKER_ROWS;
+
+/// Move the cursor of the fish picker grid, out of `count` fish, in the given direction.
+///
+/// Moving sideways wraps around to the previous or next row, and moving up or down goes through
+/// the pages, which are laid out one below the other, stopping at the first and last fish.
+fn move_picker_cursor(cursor: usize, count: usize, direction: NavDirection) -> usize {
+    let last = count.saturating_sub(1);
+    match direction {
+        NavDirection::Left if cursor == 0 => last,
+        NavDirection::Left => cursor - 1,
+        NavDirection::Right if cursor >= last => 0,
+        NavDirection::Right => cursor + 1,
+        NavDirection::Up if cursor >= FISH_PICKER_COLUMNS => cursor - FISH_PICKER_COLUMNS,
+        NavDirection::Up => cursor,
+        NavDirection::Down if cursor + FISH_PICKER_COLUMNS <= last => cursor + FISH_PICKER_COLUMNS,
+        // Go to the last fish when there is a shorter row below
+        NavDirection::Down if cursor / FISH_PICKER_COLUMNS < last / FISH_PICKER_COLUMNS => last,
+        NavDirection::Down => cursor,
+    }
+}
+
+/// Resource containing the display names of the players in the current match, shown in the HUD,
+/// the match results, and the chat.
+#[derive(Resource, Default, Clone)]
+pub struct PlayerNames(pub [Option<String>; MAX_PLAYERS]);
+
+impl PlayerNames {
+    pub fn from_slots(state: &PlayerSelectState) -> Self {
+        Self(std::array::from_fn(|i| {
+            let slot = &state.slots[i];
+            slot.active.then(|| slot.name.clone()).flatten()
+        }))
+    }
+
+    /// Get the display name of a player, if they have one.
+    pub fn get(&self, player_idx: usize) -> Option<&str> {
+        self.0.get(player_idx)?.as_deref()
+    }
 }
 
 /// Network message that may be sent during player selection.
@@ -28,18 +110,44 @@ pub struct PlayerSlot {
 pub enum PlayerSelectMessage {
     SelectPlayer(bones::Handle<PlayerMeta>),
     ConfirmSelection(bool),
+    /// Set the display name of the sending player.
+    SetName(String),
+    /// Select a custom player by its content hash, since its path is different on every computer.
+    SelectCustomPlayer(u64),
+    /// Tell a player that the custom player they selected isn't available to the sender.
+    MissingCustomPlayer,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PlayerSelectMessage {
+    /// Get the message that selects the given player, which is sent by its hash if it is a custom
+    /// player.
+    pub fn select_player(
+        player: &bones::Handle<PlayerMeta>,
+        custom_players: &CustomPlayers,
+    ) -> Self {
+        match custom_players.content_hash(player) {
+            Some(content_hash) => Self::SelectCustomPlayer(content_hash),
+            None => Self::SelectPlayer(player.clone()),
+        }
+    }
 }
 
 #[derive(SystemParam)]
 pub struct PlayerSelectMenu<'w, 's> {
     game: Res<'w, GameMeta>,
+    core: Res<'w, CoreMetaArc>,
+    storage: ResMut<'w, Storage>,
     menu_page: ResMut<'w, MenuPage>,
     localization: Res<'w, Localization>,
     keyboard_input: Res<'w, Input<KeyCode>>,
     player_select_state: ResMut<'w, PlayerSelectState>,
+    player_devices: Res<'w, PlayerDevices>,
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
     #[cfg(not(target_arch = "wasm32"))]
     network_socket: Option<Res<'w, NetworkMatchSocket>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    custom_players: Res<'w, CustomPlayers>,
 }
 
 impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
@@ -74,10 +182,12 @@ impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
         #[cfg(not(target_arch = "wasm32"))]
         if let Some(socket) = &params.network_socket {
             if may_continue {
-                // The first player picks the map
-                let is_waiting = socket.player_idx() != 0;
-
-                *params.menu_page = MenuPage::MapSelect { is_waiting };
+                // All of the players vote on the map
+                *params.menu_page = MenuPage::MapSelect {
+                    is_waiting: false,
+                    is_voting: true,
+                    is_training: false,
+                };
             }
         }
 
@@ -158,12 +268,16 @@ impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
                         })
                         .inner;
 
+                    // Enter only continues once the first keyboard has joined, since it is also
+                    // used to join.
+                    let keyboard_continue = params.keyboard_input.just_pressed(KeyCode::Return)
+                        && params.player_devices.keyboard_slot(0).is_some();
                     if continue_button.clicked()
                         || ((params.menu_input.single().just_pressed(MenuAction::Start)
-                            || params.keyboard_input.just_pressed(KeyCode::Return))
+                            || keyboard_continue)
                             && may_continue)
                     {
-                        *params.menu_page = MenuPage::MapSelect { is_waiting: false };
+                        *params.menu_page = MenuPage::Mutators;
                     }
                 });
 
@@ -194,76 +308,108 @@ impl<'w, 's> WidgetSystem for PlayerSelectMenu<'w, 's> {
 #[cfg(not(target_arch = "wasm32"))]
 fn handle_match_setup_messages(params: &mut PlayerSelectMenu) {
     if let Some(socket) = &params.network_socket {
-        let datas: Vec<(usize, Vec<u8>)> = socket.recv_reliable();
+        if !params.player_select_state.sent_profile {
+            params.player_select_state.sent_profile = true;
+            let settings = Settings::get_stored_or_default(&params.game, &mut params.storage);
+            let slot = &mut params.player_select_state.slots[socket.player_idx()];
+            slot.apply_profile(settings.player_profile(0), &params.core);
+        }
 
-        for (player, data) in datas {
-            match postcard::from_bytes::<PlayerSelectMessage>(&data) {
-                Ok(message) => match message {
-                    PlayerSelectMessage::SelectPlayer(player_handle) => {
-                        params.player_select_state.slots[player].selected_player = player_handle
-                    }
-                    PlayerSelectMessage::ConfirmSelection(confirmed) => {
-                        params.player_select_state.slots[player].confirmed = confirmed;
-                    }
-                },
-                Err(e) => warn!("Ignoring network message that was not understood: {e}"),
+        // Activate the slots of players that join, and clear the slots of players that leave
+        let state = &mut *params.player_select_state;
+        let mut player_joined = false;
+        for player in 0..MAX_PLAYERS {
+            let connected = player < socket.player_count()
+                && (player == socket.player_idx() || socket.player_is_connected(player));
+            let slot = &mut state.slots[player];
+            if connected && !state.connected[player] {
+                player_joined = true;
+                slot.active = true;
+            } else if !connected && state.connected[player] {
+                *slot = default();
             }
+            state.connected[player] = connected;
         }
-    }
-}
 
-#[derive(Debug)]
-struct PlayerActionMap<'a>(HashMap<PlayerAction, Vec<Option<&'a UserInput>>>);
+        // Tell the players that just joined, including everybody when we first connect, our name
+        // and current selection, so they don't have to wait for it to change to see it.
+        if player_joined {
+            let slot = &state.slots[socket.player_idx()];
+            if let Some(name) = &slot.name {
+                socket.send_message(
+                    SocketTarget::All,
+                    PlayerSelectMessage::SetName(name.clone()),
+                );
+            }
+            if slot.selected_player.path != default() {
+                socket.send_message(
+                    SocketTarget::All,
+                    PlayerSelectMessage::select_player(
+                        &slot.selected_player,
+                        &params.custom_players,
+                    ),
+                );
+            }
+            socket.send_message(
+                SocketTarget::All,
+                PlayerSelectMessage::ConfirmSelection(slot.confirmed),
+            );
+        }
 
-impl PlayerActionMap<'_> {
-    fn get_text(&self, action: PlayerAction) -> String {
-        self.0
-            .get(&action)
-            .unwrap_or(&vec![])
-            .iter()
-            .filter_map(|action| *action)
-            .map(|action| action.to_string())
-            .fold("".to_string(), |acc, curr| {
-                if acc.is_empty() {
-                    curr
-                } else {
-                    format!("{acc} / {curr}")
+        for (player, message) in socket.recv_messages() {
+            let ReliableMessage::PlayerSelect(message) = message else {
+                continue;
+            };
+            match message {
+                PlayerSelectMessage::SelectPlayer(player_handle) => {
+                    let slot = &mut params.player_select_state.slots[player];
+                    // The default fish is also what a player switches to when somebody is
+                    // missing their custom fish, so keep showing the notice in that case.
+                    if player_handle.path != params.core.players[0].path {
+                        slot.missing_custom_player = false;
+                    }
+                    slot.selected_player = player_handle;
                 }
-            })
-    }
-}
-
-fn get_player_actions(idx: usize, map: &InputMap<PlayerAction>) -> PlayerActionMap {
-    let map_idx = if idx > 1 {
-        GAMEPAD_ACTION_IDX
-    } else {
-        KEYPAD_ACTION_IDX
-    };
-
-    let mut jump_actions = vec![get_user_action(map_idx, PlayerAction::Jump, map)];
-    let mut grab_actions = vec![get_user_action(map_idx, PlayerAction::Grab, map)];
-
-    if idx <= 1 {
-        jump_actions.push(get_user_action(GAMEPAD_ACTION_IDX, PlayerAction::Jump, map));
-        grab_actions.push(get_user_action(GAMEPAD_ACTION_IDX, PlayerAction::Grab, map));
-    }
-
-    PlayerActionMap(HashMap::from_iter(vec![
-        (PlayerAction::Jump, jump_actions),
-        (PlayerAction::Grab, grab_actions),
-    ]))
-}
-
-fn get_user_action(
-    idx: usize,
-    action: PlayerAction,
-    map: &InputMap<PlayerAction>,
-) -> Option<&'_ UserInput> {
-    let action = map.get(action).get_at(idx);
-    if let Some(action) = action {
-        Some(action)
-    } else {
-        None
+                PlayerSelectMessage::SelectCustomPlayer(content_hash) => {
+                    let slot = &mut params.player_select_state.slots[player];
+                    if let Some(handle) = params.custom_players.find_by_hash(content_hash) {
+                        slot.selected_player = handle.clone();
+                        slot.missing_custom_player = false;
+                    } else {
+                        // We don't have the fish, so both of us fall back to the default one
+                        slot.selected_player = params.core.players[0].clone();
+                        slot.missing_custom_player = true;
+                        socket.send_message(
+                            SocketTarget::Player(player),
+                            PlayerSelectMessage::MissingCustomPlayer,
+                        );
+                    }
+                }
+                PlayerSelectMessage::MissingCustomPlayer => {
+                    let slot = &mut params.player_select_state.slots[socket.player_idx()];
+                    if params
+                        .custom_players
+                        .content_hash(&slot.selected_player)
+                        .is_some()
+                    {
+                        slot.selected_player = params.core.players[0].clone();
+                        slot.missing_custom_player = true;
+                        socket.send_message(
+                            SocketTarget::All,
+                            PlayerSelectMessage::SelectPlayer(slot.selected_player.clone()),
+                        );
+                    }
+                }
+                PlayerSelectMessage::ConfirmSelection(confirmed) => {
+                    params.player_select_state.slots[player].confirmed = confirmed;
+                }
+                PlayerSelectMessage::SetName(name) => {
+                    let name = sanitize_player_name(&name);
+                    params.player_select_state.slots[player].name =
+                        (!name.is_empty()).then_some(name);
+                }
+            }
+        }
     }
 }
 
@@ -276,6 +422,7 @@ struct PlayerSelectPanel<'w, 's> {
     player_select_state: ResMut<'w, PlayerSelectState>,
     atlas_meta_assets: Res<'w, Assets<TextureAtlas>>,
     player_atlas_egui_textures: Res<'w, PlayerAtlasEguiTextures>,
+    player_meta_diagnostics: Res<'w, PlayerMetaDiagnostics>,
     players: Query<
         'w,
         's,
@@ -285,8 +432,16 @@ struct PlayerSelectPanel<'w, 's> {
             &'static InputMap<PlayerAction>,
         ),
     >,
+    player_devices: ResMut<'w, PlayerDevices>,
+    storage: ResMut<'w, Storage>,
+    /// Turns the player's movement into steps through the fish, the fish picker, and the AI
+    /// difficulties.
+    navigator: Local<'s, MenuNavigator>,
+    time: Res<'w, Time>,
     #[cfg(not(target_arch = "wasm32"))]
     network_socket: Option<Res<'w, NetworkMatchSocket>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    custom_players: Res<'w, CustomPlayers>,
 }
 
 impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
@@ -344,18 +499,41 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                 .find(|(player_idx, _, _)| player_idx.0 == player_id)
                 .unwrap()
                 .1;
-            let map = Some(get_player_actions(player_id, player_map));
+            let map = Some(PlayerActionMap::new(
+                player_map,
+                &[PlayerAction::Jump, PlayerAction::Grab],
+            ));
             (actions, map)
         };
 
+        let device_text = player_device_text(&params, player_id);
+        let move_axis = player_actions
+            .clamped_axis_pair(PlayerAction::Move)
+            .map(|axis| axis.xy())
+            .unwrap_or_default();
+        let navigation = params
+            .navigator
+            .update(move_axis, params.time.delta_seconds());
+        // Only sideways navigation changes the selection, by one step to the left or right
+        let step = match navigation {
+            Some(NavDirection::Left) => Some(false),
+            Some(NavDirection::Right) => Some(true),
+            _ => None,
+        };
         let slot = &mut params.player_select_state.slots[player_id];
+        // Whether this slot is played on this computer, so its selection can be changed here
+        let is_local_slot = true;
+        #[cfg(not(target_arch = "wasm32"))]
+        let is_local_slot = match &params.network_socket {
+            Some(socket) => player_id == socket.player_idx(),
+            None => is_local_slot,
+        };
         #[cfg(not(target_arch = "wasm32"))]
         if let Some(socket) = &params.network_socket {
-            // Don't show panels for non-connected players.
-            if player_id + 1 > socket.player_count() {
+            // Don't show panels for players that aren't in the match. The slots of the players
+            // that are in it are activated in `handle_match_setup_messages()`.
+            if player_id >= socket.player_count() {
                 return;
-            } else {
-                slot.active = true;
             }
         }
 
@@ -367,12 +545,47 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
             *player_handle = params.core.players[0].clone();
         }
 
-        if player_actions.just_pressed(PlayerAction::Jump) {
+        // Online, the local player always uses the first profile
+        let profile_idx = if is_network { 0 } else { player_id };
+
+        // Wait for the button the player joined with to be released before handling their input
+        if slot.joining {
+            if player_actions.get_pressed().is_empty() {
+                slot.joining = false;
+            }
+        } else if let Some(cursor) = slot.picker_cursor {
+            // The fish picker takes all of the player's input while it is open
+            if player_actions.just_pressed(PlayerAction::Jump) {
+                slot.picker_cursor = None;
+                if let Some(fish) = params.core.players.get(cursor) {
+                    *player_handle = fish.clone();
+                    slot.missing_custom_player = false;
+                    save_preferred_fish(&params.game, &mut params.storage, profile_idx, fish);
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(socket) = &params.network_socket {
+                        socket.send_message(
+                            SocketTarget::All,
+                            PlayerSelectMessage::select_player(
+                                player_handle,
+                                &params.custom_players,
+                            ),
+                        );
+                    }
+                }
+            } else if player_actions.just_pressed(PlayerAction::Grab) {
+                slot.picker_cursor = None;
+            } else if let Some(direction) = navigation {
+                slot.picker_cursor = Some(move_picker_cursor(
+                    cursor,
+                    params.core.players.len(),
+                    direction,
+                ));
+            }
+        } else if player_actions.just_pressed(PlayerAction::Jump) {
             if !is_network {
                 if slot.active {
                     slot.confirmed = true;
-                } else {
-                    slot.active = true;
                 }
             } else {
                 slot.confirmed = true;
@@ -380,18 +593,19 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
 
             #[cfg(not(target_arch = "wasm32"))]
             if let Some(socket) = &params.network_socket {
-                socket.send_reliable(
+                socket.send_message(
                     SocketTarget::All,
-                    &postcard::to_allocvec(&PlayerSelectMessage::ConfirmSelection(slot.confirmed))
-                        .unwrap(),
+                    PlayerSelectMessage::ConfirmSelection(slot.confirmed),
                 );
             }
         } else if player_actions.just_pressed(PlayerAction::Grab) {
             if !is_network {
                 if slot.confirmed {
                     slot.confirmed = false;
-                } else {
+                } else if !slot.is_ai {
+                    // Leave the slot, returning its devices to the unassigned pool
                     slot.active = false;
+                    params.player_devices.release(player_id);
                 }
             } else {
                 slot.confirmed = false;
@@ -399,17 +613,18 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
 
             #[cfg(not(target_arch = "wasm32"))]
             if let Some(socket) = &params.network_socket {
-                socket.send_reliable(
+                socket.send_message(
                     SocketTarget::All,
-                    &postcard::to_allocvec(&PlayerSelectMessage::ConfirmSelection(slot.confirmed))
-                        .unwrap(),
+                    PlayerSelectMessage::ConfirmSelection(slot.confirmed),
                 );
             }
-        } else if player_actions.just_pressed(PlayerAction::Move) && !slot.confirmed {
-            let direction = player_actions
-                .clamped_axis_pair(PlayerAction::Move)
-                .unwrap();
-
+        } else if let Some(right) = step.filter(|_| slot.is_ai) {
+            slot.ai_difficulty = if right {
+                slot.ai_difficulty.next()
+            } else {
+                slot.ai_difficulty.prev()
+            };
+        } else if let Some(right) = step.filter(|_| !slot.confirmed) {
             let current_player_handle_idx = params
                 .core
                 .players
@@ -419,36 +634,52 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                 .map(|(i, _)| i)
                 .unwrap_or(0);
 
-            if direction.x() > 0.0 {
+            if right {
                 *player_handle = params
                     .core
                     .players
                     .get(current_player_handle_idx + 1)
                     .cloned()
                     .unwrap_or_else(|| params.core.players[0].clone());
-            } else if direction.x() <= 0.0 {
-                if current_player_handle_idx > 0 {
-                    *player_handle = params
-                        .core
-                        .players
-                        .get(current_player_handle_idx - 1)
-                        .cloned()
-                        .unwrap();
-                } else {
-                    *player_handle = params.core.players.iter().last().unwrap().clone();
-                }
+            } else if current_player_handle_idx > 0 {
+                *player_handle = params
+                    .core
+                    .players
+                    .get(current_player_handle_idx - 1)
+                    .cloned()
+                    .unwrap();
+            } else {
+                *player_handle = params.core.players.iter().last().unwrap().clone();
             }
 
+            slot.missing_custom_player = false;
+
             #[cfg(not(target_arch = "wasm32"))]
             if let Some(socket) = &params.network_socket {
-                socket.send_reliable(
+                socket.send_message(
                     SocketTarget::All,
-                    &postcard::to_allocvec(&PlayerSelectMessage::SelectPlayer(
-                        player_handle.clone(),
-                    ))
-                    .unwrap(),
+                    PlayerSelectMessage::select_player(player_handle, &params.custom_players),
                 );
             }
+        } else if navigation == Some(NavDirection::Up)
+            && slot.active
+            && !slot.confirmed
+            && !slot.is_ai
+            && is_local_slot
+        {
+            // Open the fish picker on the fish the player picked last time
+            let settings = Settings::get_stored_or_default(&params.game, &mut params.storage);
+            let last_used = settings
+                .player_profile(profile_idx)
+                .and_then(|profile| profile.preferred_fish.as_ref())
+                .unwrap_or(&*player_handle);
+            let cursor = params
+                .core
+                .players
+                .iter()
+                .position(|fish| fish.path == last_used.path)
+                .unwrap_or(0);
+            slot.picker_cursor = Some(cursor);
         }
 
         BorderedFrame::new(&params.game.ui_theme.panel.border)
@@ -459,37 +690,85 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
 
                 let normal_font = &params.game.ui_theme.font_styles.normal;
                 let heading_font = &params.game.ui_theme.font_styles.heading;
+                // The player's color, matching their in-game indicator
+                let player_font =
+                    &normal_font.colored(params.game.ui_theme.colors.player(player_id));
 
                 // Marker for current player in online matches
                 #[cfg(not(target_arch = "wasm32"))]
                 if let Some(socket) = &params.network_socket {
                     if socket.player_idx() == player_id {
+                        let marker = match &slot.name {
+                            Some(name) => params
+                                .localization
+                                .get(&format!("you-marker-named?name={name}")),
+                            None => params.localization.get("you-marker"),
+                        };
                         ui.vertical_centered(|ui| {
-                            ui.themed_label(normal_font, &params.localization.get("you-marker"));
+                            ui.themed_label(player_font, &marker);
+                        });
+                    } else if player_id < socket.player_count()
+                        && !socket.player_is_connected(player_id)
+                    {
+                        ui.vertical_centered(|ui| {
+                            ui.themed_label(
+                                normal_font,
+                                &params.localization.get("disconnected-marker"),
+                            );
+                        });
+                    } else if let Some(name) = &slot.name {
+                        ui.vertical_centered(|ui| {
+                            ui.themed_label(
+                                player_font,
+                                &params
+                                    .localization
+                                    .get(&format!("remote-marker?name={name}")),
+                            );
                         });
                     } else {
                         ui.add_space(normal_font.size);
                     }
+                } else if slot.active && !slot.is_ai {
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(player_font, &device_text);
+                    });
                 } else {
                     ui.add_space(normal_font.size);
                 }
                 #[cfg(target_arch = "wasm32")]
-                ui.add_space(normal_font.size);
+                if slot.active && !slot.is_ai {
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(player_font, &device_text);
+                    });
+                } else {
+                    ui.add_space(normal_font.size);
+                }
 
                 if slot.active {
                     ui.vertical_centered(|ui| {
+                        // Show the default fish until a remote player's fish is loaded
                         let Some(player_meta) = params
                             .player_meta_assets
-                            .get(&player_handle.get_bevy_handle()) else { return; };
+                            .get(&player_handle.get_bevy_handle())
+                            .or_else(|| {
+                                params
+                                    .player_meta_assets
+                                    .get(&params.core.players[0].get_bevy_handle())
+                            }) else { return; };
 
                         ui.themed_label(normal_font, &params.localization.get("pick-a-fish"));
 
                         if !slot.confirmed {
                             if let Some(player_action_map) = &player_action_map {
+                                let (jump_hint, grab_hint) = if slot.picker_cursor.is_some() {
+                                    ("press-button-to-pick", "press-button-to-cancel")
+                                } else {
+                                    ("press-button-to-lock-in", "press-button-to-remove")
+                                };
                                 ui.themed_label(
                                     normal_font,
                                     &params.localization.get(&format!(
-                                        "press-button-to-lock-in?button={}",
+                                        "{jump_hint}?button={}",
                                         player_action_map.get_text(PlayerAction::Jump)
                                     )),
                                 );
@@ -497,7 +776,7 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                                 ui.themed_label(
                                     normal_font,
                                     &params.localization.get(&format!(
-                                        "press-button-to-remove?button={}",
+                                        "{grab_hint}?button={}",
                                         player_action_map.get_text(PlayerAction::Grab)
                                     )),
                                 );
@@ -530,6 +809,18 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                                     &heading_font.colored(params.game.ui_theme.colors.positive),
                                     &params.localization.get("ai-player"),
                                 );
+                                let difficulty = params
+                                    .localization
+                                    .get(slot.ai_difficulty.localization_key());
+                                if BorderedButton::themed(
+                                    &params.game.ui_theme.button_styles.small,
+                                    &format!("<  {difficulty}  >"),
+                                )
+                                .show(ui)
+                                .clicked()
+                                {
+                                    slot.ai_difficulty = slot.ai_difficulty.next();
+                                }
                                 if BorderedButton::themed(
                                     &params.game.ui_theme.button_styles.normal,
                                     &params.localization.get("remove-ai-player"),
@@ -540,38 +831,144 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                                     slot.confirmed = false;
                                     slot.active = false;
                                     slot.is_ai = false;
+                                    slot.ai_difficulty = default();
                                 }
                             }
                         });
 
                         ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+                            if slot.is_ai {
+                                ui.themed_label(
+                                    normal_font,
+                                    &params.localization.get("ai-random-fish"),
+                                );
+                                return;
+                            }
+
+                            if let Some(cursor) = slot.picker_cursor {
+                                let fish = &params.core.players;
+                                let page = cursor / FISH_PICKER_PAGE_SIZE;
+                                let page_count = (fish.len() + FISH_PICKER_PAGE_SIZE - 1)
+                                    / FISH_PICKER_PAGE_SIZE;
+                                if page_count > 1 {
+                                    ui.themed_label(
+                                        normal_font,
+                                        &params.localization.get(&format!(
+                                            "fish-picker-page?page={}&pages={page_count}",
+                                            page + 1
+                                        )),
+                                    );
+                                }
+
+                                let cell_size = ui.available_width() / FISH_PICKER_COLUMNS as f32;
+                                let page_fish = fish
+                                    .iter()
+                                    .enumerate()
+                                    .skip(page * FISH_PICKER_PAGE_SIZE)
+                                    .take(FISH_PICKER_PAGE_SIZE)
+                                    .collect::<Vec<_>>();
+                                // Rows are added from the bottom up, so go through them in reverse
+                                for row in page_fish.chunks(FISH_PICKER_COLUMNS).rev() {
+                                    ui.horizontal(|ui| {
+                                        ui.spacing_mut().item_spacing.x = 0.0;
+                                        for &(idx, handle) in row {
+                                            let Some(meta) = params
+                                                .player_meta_assets
+                                                .get(&handle.get_bevy_handle()) else {
+                                                ui.add_space(cell_size);
+                                                continue;
+                                            };
+                                            let cell = ui
+                                                .allocate_ui_with_layout(
+                                                    egui::vec2(cell_size, cell_size),
+                                                    egui::Layout::top_down(egui::Align::Center),
+                                                    |ui| {
+                                                        ui.set_width(cell_size);
+                                                        player_image(
+                                                            ui,
+                                                            meta,
+                                                            &params.atlas_meta_assets,
+                                                            &params.player_atlas_egui_textures,
+                                                        );
+                                                    },
+                                                )
+                                                .response;
+                                            if idx == cursor {
+                                                ui.painter().rect_stroke(
+                                                    cell.rect,
+                                                    0.0,
+                                                    egui::Stroke::new(
+                                                        2.0,
+                                                        params
+                                                            .game
+                                                            .ui_theme
+                                                            .colors
+                                                            .player(player_id)
+                                                            .into_egui(),
+                                                    ),
+                                                );
+                                            }
+                                        }
+                                    });
+                                }
+
+                                if let Some(meta) = fish.get(cursor).and_then(|handle| {
+                                    params.player_meta_assets.get(&handle.get_bevy_handle())
+                                }) {
+                                    ui.themed_label(normal_font, &meta.name);
+                                }
+                                return;
+                            }
+
                             let name_with_arrows = format!("<  {}  >", player_meta.name);
                             ui.themed_label(
                                 normal_font,
-                                if slot.confirmed {
+                                if slot.confirmed || !is_local_slot {
                                     &player_meta.name
                                 } else {
                                     &name_with_arrows
                                 },
                             );
 
+                            // Let skin authors know when something is wrong with the player
+                            let warnings = params.player_meta_diagnostics.get(player_handle);
+                            if !warnings.is_empty() {
+                                warning_icon(
+                                    ui,
+                                    normal_font.size,
+                                    params.game.ui_theme.colors.negative.into_egui(),
+                                )
+                                .on_hover_text(warnings.join("\n"));
+                            }
+
+                            if slot.missing_custom_player {
+                                ui.themed_label(
+                                    &normal_font.colored(params.game.ui_theme.colors.negative),
+                                    &params.localization.get("custom-player-unavailable"),
+                                );
+                            }
+
                             player_image(
                                 ui,
                                 player_meta,
                                 &params.atlas_meta_assets,
                                 &params.player_atlas_egui_textures,
                             );
+
+                            // Online players' names are shown in their markers instead
+                            if !is_network {
+                                if let Some(name) = &slot.name {
+                                    ui.themed_label(player_font, name);
+                                }
+                            }
                         });
                     });
                 } else {
                     ui.vertical_centered(|ui| {
-                        if let Some(player_action_map) = &player_action_map {
+                        if player_action_map.is_some() {
                             ui.themed_label(
                                 normal_font,
-                                &params.localization.get(&format!(
-                                    "press-button-to-join?button={}",
-                                    player_action_map.get_text(PlayerAction::Jump)
-                                )),
+                                &params.localization.get("press-any-button-to-join"),
                             );
                         }
 
@@ -587,10 +984,7 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
                                 slot.is_ai = true;
                                 slot.confirmed = true;
                                 slot.active = true;
-                                let mut rng = rand::thread_rng();
-                                *player_handle = params.core.players
-                                    [rng.gen_range(0..params.core.players.len())]
-                                .clone();
+                                // The session picks the AI's fish when the game starts
                             }
                         }
                     });
@@ -599,10 +993,176 @@ impl<'w, 's> WidgetSystem for PlayerSelectPanel<'w, 's> {
     }
 }
 
+/// Remember the fish that a local player picked as their profile's preferred fish, so that it is
+/// selected when they join, and the fish picker starts on it.
+fn save_preferred_fish(
+    game: &GameMeta,
+    storage: &mut Storage,
+    profile_idx: usize,
+    fish: &bones::Handle<PlayerMeta>,
+) {
+    let mut settings = Settings::get_stored_or_default(game, storage).into_owned();
+    if settings.player_profiles.len() <= profile_idx {
+        settings.player_profiles.resize(profile_idx + 1, default());
+    }
+    settings.player_profiles[profile_idx].preferred_fish = Some(fish.clone());
+    storage.set(Settings::STORAGE_KEY, &settings);
+    storage.save();
+}
+
+/// Get the text describing the input devices used by a local player.
+fn player_device_text(params: &PlayerSelectPanel, player_id: usize) -> String {
+    let keyboard = params.player_devices.keyboard(player_id).map(|x| x + 1);
+    match (keyboard, params.player_devices.gamepad(player_id)) {
+        (Some(keyboard), Some(gamepad)) if gamepad.connected => params.localization.get(&format!(
+            "player-device-gamepad?keyboard={keyboard}&number={}",
+            gamepad.gamepad.id + 1
+        )),
+        (Some(keyboard), Some(gamepad)) => params.localization.get(&format!(
+            "player-device-gamepad-disconnected?keyboard={keyboard}&number={}",
+            gamepad.gamepad.id + 1
+        )),
+        (None, Some(gamepad)) if gamepad.connected => params.localization.get(&format!(
+            "player-device-gamepad-only?number={}",
+            gamepad.gamepad.id + 1
+        )),
+        (None, Some(gamepad)) => params.localization.get(&format!(
+            "player-device-gamepad-only-disconnected?number={}",
+            gamepad.gamepad.id + 1
+        )),
+        (Some(keyboard), None) => params
+            .localization
+            .get(&format!("player-device-keyboard?number={keyboard}")),
+        (None, None) => String::new(),
+    }
+}
+
+/// The gamepad buttons that join the game. The east button is left out, since it is used to go
+/// back to the main menu.
+const JOIN_GAMEPAD_BUTTONS: [GamepadButtonType; 3] = [
+    GamepadButtonType::South,
+    GamepadButtonType::North,
+    GamepadButtonType::West,
+];
+
+/// Let any device that isn't assigned to a player claim the first free player slot.
+///
+/// Gamepads join by pressing a face button, and keyboard binding sets join by pressing any of their
+/// action keys, or enter for the first keyboard. All devices return to the unassigned pool when
+/// the player select screen is opened.
+pub fn claim_devices(
+    menu_page: Res<MenuPage>,
+    game: Res<GameMeta>,
+    core: Res<CoreMetaArc>,
+    mut storage: ResMut<Storage>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut player_devices: ResMut<PlayerDevices>,
+    mut player_select_state: ResMut<PlayerSelectState>,
+    #[cfg(not(target_arch = "wasm32"))] network_socket: Option<Res<NetworkMatchSocket>>,
+) {
+    #[cfg(not(target_arch = "wasm32"))]
+    if network_socket.is_some() {
+        return;
+    }
+    if !matches!(*menu_page, MenuPage::PlayerSelect) {
+        return;
+    }
+
+    // Free all of the devices when coming to the player select screen
+    if menu_page.is_changed() && player_select_state.slots.iter().all(|x| !x.active) {
+        player_devices.release_all();
+    }
+
+    if gamepad_buttons.get_just_pressed().next().is_none()
+        && keyboard_input.get_just_pressed().next().is_none()
+    {
+        return;
+    }
+    let settings = Settings::get_stored_or_default(&game, &mut storage);
+
+    // Each slot uses the profile with the same index, so that local players can all use their own
+    let mut next_free_slot = |player_devices: &PlayerDevices| {
+        let slot_idx = (0..MAX_PLAYERS)
+            .find(|&i| !player_select_state.slots[i].active && !player_devices.has_devices(i))?;
+        let slot = &mut player_select_state.slots[slot_idx];
+        slot.active = true;
+        slot.joining = true;
+        slot.apply_profile(settings.player_profile(slot_idx), &core);
+        Some(slot_idx)
+    };
+
+    for button in gamepad_buttons.get_just_pressed() {
+        if !JOIN_GAMEPAD_BUTTONS.contains(&button.button_type)
+            || player_devices.slot(button.gamepad).is_some()
+        {
+            continue;
+        }
+        let Some(slot_idx) = next_free_slot(&player_devices) else { return };
+        player_devices.assign(slot_idx, button.gamepad);
+    }
+
+    if keyboard_input.get_just_pressed().next().is_none() {
+        return;
+    }
+    for keyboard in 0..MAX_PLAYERS {
+        let Some(controls) = settings.player_controls.keyboard(keyboard) else { continue };
+        if player_devices.keyboard_slot(keyboard).is_some() {
+            continue;
+        }
+
+        let join_pressed = [
+            controls.jump,
+            controls.grab,
+            controls.shoot,
+            controls.slide,
+            controls.emote,
+            controls.dash,
+        ]
+        .iter()
+        .any(
+            |input| matches!(input, InputKind::Keyboard(key) if keyboard_input.just_pressed(*key)),
+        ) || (keyboard == 0 && keyboard_input.just_pressed(KeyCode::Return));
+
+        if join_pressed {
+            let Some(slot_idx) = next_free_slot(&player_devices) else { return };
+            player_devices.assign_keyboard(slot_idx, keyboard);
+        }
+    }
+}
+
+/// Draw a small warning triangle with an exclamation mark in it.
+fn warning_icon(ui: &mut egui::Ui, size: f32, color: egui::Color32) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.add(egui::Shape::convex_polygon(
+        vec![rect.center_top(), rect.right_bottom(), rect.left_bottom()],
+        color,
+        egui::Stroke::NONE,
+    ));
+
+    let x = rect.center().x;
+    painter.line_segment(
+        [
+            egui::pos2(x, rect.top() + size * 0.35),
+            egui::pos2(x, rect.top() + size * 0.7),
+        ],
+        egui::Stroke::new(size * 0.12, egui::Color32::BLACK),
+    );
+    painter.circle_filled(
+        egui::pos2(x, rect.top() + size * 0.85),
+        size * 0.07,
+        egui::Color32::BLACK,
+    );
+
+    response
+}
+
 #[derive(Resource)]
 pub struct PlayerAtlasEguiTextures(pub HashMap<bones::AssetPath, egui::TextureId>);
 
-fn player_image(
+/// Render the player's idle animation, filling the available width.
+pub fn player_image(
     ui: &mut egui::Ui,
     player_meta: &PlayerMeta,
     atlas_assets: &Assets<TextureAtlas>,
@@ -623,20 +1183,22 @@ fn player_image(
             .get(&atlas_handle.get_bevy_handle_untyped().typed())
             .unwrap();
         let atlas_path = &atlas_handle.path;
-        let anim_clip = player_meta
-            .layers
-            .body
-            .animations
-            .frames
-            .get(&key!("idle"))
-            .unwrap();
+        let body_animations = &player_meta.layers.body.animations;
+        let anim_key = animation_or_fallback(&body_animations.frames, key!("idle"));
+        let Some(anim_clip) = body_animations.frames.get(&anim_key) else {
+            return;
+        };
         let fps = anim_clip.fps;
         let frame_in_time_idx = (time * fps).round() as usize;
         let frame_in_clip_idx = frame_in_time_idx % anim_clip.frames.len();
         let frame_in_sheet_idx = anim_clip.frames[frame_in_clip_idx];
         let sprite_rect = &atlas.textures[frame_in_sheet_idx];
-        body_offset =
-            player_meta.layers.body.animations.body_offsets[&key!("idle")][frame_in_clip_idx];
+        body_offset = body_animations
+            .body_offsets
+            .get(&anim_key)
+            .and_then(|offsets| offsets.get(frame_in_clip_idx))
+            .copied()
+            .unwrap_or_default();
 
         let sprite_aspect = sprite_rect.height() / sprite_rect.width();
         let height = sprite_aspect * width;
@@ -670,12 +1232,10 @@ fn player_image(
             .get(&atlas_handle.get_bevy_handle_untyped().typed())
             .unwrap();
         let atlas_path = &atlas_handle.path;
-        let anim_clip = player_meta
-            .layers
-            .fin
-            .animations
-            .get(&key!("idle"))
-            .unwrap();
+        let animations = &player_meta.layers.fin.animations;
+        let Some(anim_clip) = animations.get(&animation_or_fallback(animations, key!("idle"))) else {
+            return;
+        };
         let fps = anim_clip.fps;
         let frame_in_time_idx = (time * fps).round() as usize;
         let frame_in_clip_idx = frame_in_time_idx % anim_clip.frames.len();
@@ -712,12 +1272,10 @@ fn player_image(
             .get(&atlas_handle.get_bevy_handle_untyped().typed())
             .unwrap();
         let atlas_path = &atlas_handle.path;
-        let anim_clip = player_meta
-            .layers
-            .face
-            .animations
-            .get(&key!("idle"))
-            .unwrap();
+        let animations = &player_meta.layers.face.animations;
+        let Some(anim_clip) = animations.get(&animation_or_fallback(animations, key!("idle"))) else {
+            return;
+        };
         let fps = anim_clip.fps;
         let frame_in_time_idx = (time * fps).round() as usize;
         let frame_in_clip_idx = frame_in_time_idx % anim_clip.frames.len();
@@ -747,3 +1305,25 @@ fn player_image(
         ui.painter().add(mesh);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picker_cursor_moves_through_rows_and_pages() {
+        // Eleven fish make a full first page and a second page with two fish
+        let count = 11;
+        assert_eq!(move_picker_cursor(2, count, NavDirection::Right), 3);
+        assert_eq!(move_picker_cursor(3, count, NavDirection::Left), 2);
+        assert_eq!(move_picker_cursor(10, count, NavDirection::Right), 0);
+        assert_eq!(move_picker_cursor(0, count, NavDirection::Left), 10);
+
+        assert_eq!(move_picker_cursor(1, count, NavDirection::Up), 1);
+        assert_eq!(move_picker_cursor(4, count, NavDirection::Up), 1);
+        assert_eq!(move_picker_cursor(7, count, NavDirection::Down), 10);
+        // There is no fish below the last column of the third row, so the last fish is picked
+        assert_eq!(move_picker_cursor(8, count, NavDirection::Down), 10);
+        assert_eq!(move_picker_cursor(10, count, NavDirection::Down), 10);
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+use super::*;
+
+/// The state of the replay list on the [`ReplaysMenu`].
+#[derive(Resource, Default)]
+pub struct ReplaysMenuState {
+    /// The replay files that can be played.
+    pub files: Vec<PathBuf>,
+    /// Whether or not the last attempt to load a replay failed.
+    pub load_failed: bool,
+}
+
+impl ReplaysMenuState {
+    /// Reload the list of replay files from disk.
+    pub fn refresh(&mut self) {
+        self.files = crate::replay::replay_files();
+        self.load_failed = false;
+    }
+}
+
+#[derive(SystemParam)]
+pub struct ReplaysMenu<'w, 's> {
+    commands: Commands<'w, 's>,
+    game: Res<'w, GameMeta>,
+    menu_page: ResMut<'w, MenuPage>,
+    state: ResMut<'w, ReplaysMenuState>,
+    session_manager: SessionManager<'w, 's>,
+    localization: Res<'w, Localization>,
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+}
+
+impl<'w, 's> WidgetSystem for ReplaysMenu<'w, 's> {
+    type Args = ();
+
+    fn system(
+        world: &mut World,
+        state: &mut SystemState<Self>,
+        ui: &mut egui::Ui,
+        _id: WidgetId,
+        _args: Self::Args,
+    ) {
+        let mut params: ReplaysMenu = state.get_mut(world);
+
+        let ui_theme = &params.game.ui_theme;
+        let heading_font = ui_theme
+            .font_styles
+            .heading
+            .colored(ui_theme.panel.font_color);
+        let normal_font = ui_theme
+            .font_styles
+            .normal
+            .colored(ui_theme.panel.font_color);
+
+        let menu_width = params.game.main_menu.menu_width;
+        let x_margin = (ui.available_width() - menu_width) / 2.0;
+        let outer_margin = egui::style::Margin::symmetric(x_margin, normal_font.size);
+
+        BorderedFrame::new(&ui_theme.panel.border)
+            .margin(outer_margin)
+            .padding(ui_theme.panel.padding.into())
+            .show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.themed_label(&heading_font, &params.localization.get("replays"));
+                });
+                ui.set_min_width(ui.available_width());
+
+                ui.with_layout(egui::Layout::bottom_up(egui::Align::Min), |ui| {
+                    // Back button
+                    let back_button = BorderedButton::themed(
+                        &ui_theme.button_styles.normal,
+                        &params.localization.get("back"),
+                    )
+                    .show(ui)
+                    .focus_by_default(ui);
+
+                    ui.add_space(normal_font.size / 2.0);
+
+                    if back_button.clicked()
+                        || params.menu_input.single().just_pressed(MenuAction::Back)
+                        || params.keyboard_input.just_pressed(KeyCode::Escape)
+                    {
+                        *params.menu_page = MenuPage::Home;
+                    }
+
+                    if params.state.load_failed {
+                        ui.themed_label(
+                            &normal_font,
+                            &params.localization.get("replay-load-error"),
+                        );
+                    }
+
+                    ui.with_layout(default(), |ui| {
+                        if params.state.files.is_empty() {
+                            ui.themed_label(&normal_font, &params.localization.get("no-replays"));
+                        }
+
+                        let mut selected = None;
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            let min_button_size = egui::vec2(ui.available_width(), 0.0);
+                            for path in &params.state.files {
+                                let Some(name) = path.file_stem().and_then(|x| x.to_str()) else {
+                                    continue;
+                                };
+
+                                if BorderedButton::themed(&ui_theme.button_styles.small, name)
+                                    .min_size(min_button_size)
+                                    .show(ui)
+                                    .clicked()
+                                {
+                                    selected = Some(path.clone());
+                                }
+                            }
+                        });
+
+                        let Some(path) = selected else {
+                            return;
+                        };
+                        match crate::replay::load_replay(&path) {
+                            Ok(replay) => {
+                                info!(?path, "Starting replay");
+                                *params.menu_page = MenuPage::Home;
+                                params.session_manager.start_replay(replay);
+                                params
+                                    .commands
+                                    .insert_resource(NextState(Some(EngineState::InGame)));
+                                params
+                                    .commands
+                                    .insert_resource(NextState(Some(InGameState::Playing)));
+                            }
+                            Err(e) => {
+                                error!(?path, "Error loading replay: {e:?}");
+                                params.state.load_failed = true;
+                            }
+                        }
+                    });
+                });
+            });
+    }
+}
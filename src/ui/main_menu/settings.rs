@@ -4,14 +4,19 @@ use leafwing_input_manager::{axislike::SingleAxis, user_input::InputKind, Action
 use super::*;
 
 mod controls;
+mod display;
+mod gameplay;
 mod networking;
+mod profiles;
 mod sound;
 
 /// Which settings tab we are on
 #[derive(Resource, Clone, Copy, PartialEq, Eq)]
 pub enum SettingsTab {
     Controls,
-    #[allow(unused)] // TODO: Just for now until we get sound settings setup
+    Gameplay,
+    Profiles,
+    Display,
     Sound,
     Networking,
 }
@@ -19,8 +24,11 @@ pub enum SettingsTab {
 impl SettingsTab {
     const TABS: &'static [(Self, &'static str)] = &[
         (Self::Controls, "controls"),
-        (Self::Networking, "networking"), // For now, hide the sound tab because we don't have it working yet.
-                                          // (Self::Sound, "sound")
+        (Self::Gameplay, "gameplay"),
+        (Self::Profiles, "profiles"),
+        (Self::Display, "display"),
+        (Self::Sound, "sound"),
+        (Self::Networking, "networking"),
     ];
 }
 
@@ -36,6 +44,8 @@ pub struct ModifiedSettings(pub Option<Settings>);
 #[derive(SystemParam)]
 pub struct SettingsMenu<'w, 's> {
     game: Res<'w, GameMeta>,
+    core: Res<'w, CoreMetaArc>,
+    player_meta_assets: Res<'w, Assets<PlayerMeta>>,
     current_tab: ResMut<'w, SettingsTab>,
     menu_page: ResMut<'w, MenuPage>,
     modified_settings: ResMut<'w, ModifiedSettings>,
@@ -45,6 +55,20 @@ pub struct SettingsMenu<'w, 's> {
     storage: ResMut<'w, Storage>,
     control_inputs: controls::ControlInputBindingEvents<'w, 's>,
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+    menu_input_maps: Query<'w, 's, &'static mut InputMap<MenuAction>>,
+    player_devices: Res<'w, PlayerDevices>,
+    windows: Query<'w, 's, &'static mut Window, With<PrimaryWindow>>,
+    ui_scale: ResMut<'w, UiScaleSetting>,
+    locale: ResMut<'w, bevy_fluent::Locale>,
+    locale_bundles: Res<'w, Assets<bevy_fluent::BundleAsset>>,
+    input_collectors: Query<
+        'w,
+        's,
+        (
+            &'static PlayerInputCollector,
+            &'static mut InputMap<PlayerAction>,
+        ),
+    >,
     #[system_param(ignore)]
     _phantom: PhantomData<(&'w (), &'s ())>,
 }
@@ -144,6 +168,17 @@ impl<'w, 's> WidgetSystem for SettingsMenu<'w, 's> {
                                 if cancel_button.clicked()
                                     || params.menu_input.single().just_pressed(MenuAction::Back)
                                 {
+                                    // Switch back to the saved language, in case it was changed
+                                    let settings = Settings::get_stored_or_default(
+                                        &params.game,
+                                        &mut params.storage,
+                                    );
+                                    let locale =
+                                        params.game.translations.locale(settings.locale.as_ref());
+                                    if locale.requested != params.locale.requested {
+                                        *params.locale = locale;
+                                    }
+
                                     *params.menu_page = MenuPage::Home;
                                     ui.ctx().clear_focus();
                                 }
@@ -178,6 +213,29 @@ impl<'w, 's> WidgetSystem for SettingsMenu<'w, 's> {
                                     // Persist to storage
                                     params.storage.save();
 
+                                    // Update the players' controls
+                                    let settings = params.modified_settings.0.as_ref().unwrap();
+                                    for (collector, mut input_map) in &mut params.input_collectors {
+                                        *input_map = params
+                                            .player_devices
+                                            .input_map(&settings.player_controls, collector.0);
+                                    }
+                                    for mut input_map in &mut params.menu_input_maps {
+                                        bind_screenshot_key(
+                                            &mut input_map,
+                                            settings.screenshot_key,
+                                        );
+                                    }
+
+                                    // Update the window and UI scale
+                                    if let Ok(mut window) = params.windows.get_single_mut() {
+                                        apply_display_settings(
+                                            settings,
+                                            &mut window,
+                                            &mut params.ui_scale,
+                                        );
+                                    }
+
                                     // Go to main menu
                                     *params.menu_page = MenuPage::Home;
                                     ui.ctx().clear_focus();
@@ -207,6 +265,20 @@ impl<'w, 's> WidgetSystem for SettingsMenu<'w, 's> {
                                         &bottom_buttons,
                                     )
                                 }
+                                SettingsTab::Gameplay => gameplay::gameplay_settings_ui(
+                                    &mut params,
+                                    ui,
+                                    bottom_buttons[1].clicked(),
+                                    &tabs,
+                                    &bottom_buttons,
+                                ),
+                                SettingsTab::Profiles => profiles::profiles_settings_ui(
+                                    &mut params,
+                                    ui,
+                                    bottom_buttons[1].clicked(),
+                                    &tabs,
+                                    &bottom_buttons,
+                                ),
                                 SettingsTab::Networking => networking::networking_settings_ui(
                                     &mut params,
                                     ui,
@@ -214,7 +286,20 @@ impl<'w, 's> WidgetSystem for SettingsMenu<'w, 's> {
                                     &tabs,
                                     &bottom_buttons,
                                 ),
-                                SettingsTab::Sound => sound::sound_settings_ui(ui, &params.game),
+                                SettingsTab::Display => display::display_settings_ui(
+                                    &mut params,
+                                    ui,
+                                    bottom_buttons[1].clicked(),
+                                    &tabs,
+                                    &bottom_buttons,
+                                ),
+                                SettingsTab::Sound => sound::sound_settings_ui(
+                                    &mut params,
+                                    ui,
+                                    bottom_buttons[1].clicked(),
+                                    &tabs,
+                                    &bottom_buttons,
+                                ),
                             }
                         });
                     });
@@ -15,8 +15,9 @@ pub fn controls_settings_ui(
 
     // Reset the settings when reset button is clicked
     if should_reset {
-        params.modified_settings.0.as_mut().unwrap().player_controls =
-            params.game.default_settings.player_controls.clone();
+        let settings = params.modified_settings.0.as_mut().unwrap();
+        settings.player_controls = params.game.default_settings.player_controls.clone();
+        settings.screenshot_key = params.game.default_settings.screenshot_key;
     }
 
     // Get the font meta for the table headings and labels
@@ -38,108 +39,90 @@ pub fn controls_settings_ui(
         + small_button_style.padding.bottom;
 
     // Mutably borrow the player controlls settings
-    let controls = &mut params.modified_settings.0.as_mut().unwrap().player_controls;
-
-    // Build the table rows of control bindings as an array of mutable InputKind's
-    let mut input_rows = [
-        (
-            &params.localization.get("move-up"),
-            [
-                &mut controls.keyboard1.movement.up,
-                &mut controls.keyboard2.movement.up,
-                &mut controls.gamepad.movement.up,
-            ],
-        ),
-        (
-            &params.localization.get("move-down"),
-            [
-                &mut controls.keyboard1.movement.down,
-                &mut controls.keyboard2.movement.down,
-                &mut controls.gamepad.movement.down,
-            ],
-        ),
-        (
-            &params.localization.get("move-left"),
-            [
-                &mut controls.keyboard1.movement.left,
-                &mut controls.keyboard2.movement.left,
-                &mut controls.gamepad.movement.left,
-            ],
-        ),
-        (
-            &params.localization.get("move-right"),
-            [
-                &mut controls.keyboard1.movement.right,
-                &mut controls.keyboard2.movement.right,
-                &mut controls.gamepad.movement.right,
-            ],
-        ),
-        (
-            &params.localization.get("jump"),
-            [
-                &mut controls.keyboard1.jump,
-                &mut controls.keyboard2.jump,
-                &mut controls.gamepad.jump,
-            ],
-        ),
-        (
-            &params.localization.get("grab-drop"),
-            [
-                &mut controls.keyboard1.grab,
-                &mut controls.keyboard2.grab,
-                &mut controls.gamepad.grab,
-            ],
-        ),
-        (
-            &params.localization.get("shoot"),
-            [
-                &mut controls.keyboard1.shoot,
-                &mut controls.keyboard2.shoot,
-                &mut controls.gamepad.shoot,
-            ],
-        ),
-        (
-            &params.localization.get("slide"),
-            [
-                &mut controls.keyboard1.slide,
-                &mut controls.keyboard2.slide,
-                &mut controls.gamepad.slide,
-            ],
-        ),
+    let settings = params.modified_settings.0.as_mut().unwrap();
+    let screenshot_key = &mut settings.screenshot_key;
+    let controls = &mut settings.player_controls;
+
+    // The titles of the table rows, in the same order as `PlayerControls::inputs_mut()`
+    let row_titles = [
+        "move-up",
+        "move-down",
+        "move-left",
+        "move-right",
+        "jump",
+        "grab-drop",
+        "shoot",
+        "slide",
+        "emote",
+        "dash",
+    ]
+    .map(|x| params.localization.get(x));
+
+    // Build the table columns of control bindings, one for each local keyboard player, and one for
+    // the gamepads.
+    let column_titles = [
+        "keyboard-1",
+        "keyboard-2",
+        "keyboard-3",
+        "keyboard-4",
+        "gamepad",
+    ]
+    .map(|x| params.localization.get(x));
+    let mut input_columns = [
+        controls.keyboard1.inputs_mut(),
+        controls.keyboard2.inputs_mut(),
+        controls.keyboard3.inputs_mut(),
+        controls.keyboard4.inputs_mut(),
+        controls.gamepad.inputs_mut(),
     ];
+    let column_count = input_columns.len();
+    let row_count = row_titles.len();
+
+    // Find the inputs that are bound to more than one action for the same player
+    let mut conflicts = [[false; 10]; 5];
+    for (column, inputs) in input_columns.iter().enumerate() {
+        for (row, input) in inputs.iter().enumerate() {
+            conflicts[column][row] = inputs
+                .iter()
+                .enumerate()
+                .any(|(other_row, other)| other_row != row && **other == **input);
+        }
+    }
+    let has_conflicts = conflicts.iter().flatten().any(|x| *x);
+
+    // The screenshot key works for every player, so it conflicts with any keyboard player's input
+    let screenshot_input = InputKind::Keyboard(*screenshot_key);
+    let screenshot_conflict = input_columns[..column_count - 1]
+        .iter()
+        .flatten()
+        .any(|input| **input == screenshot_input);
 
     // Collect input button responses for building adjacency graph
     let mut input_buttons = Vec::new();
 
     // Create input table
-    egui_extras::TableBuilder::new(ui)
+    let mut table = egui_extras::TableBuilder::new(ui)
         .cell_layout(egui::Layout::centered_and_justified(
             egui::Direction::LeftToRight,
         ))
-        .column(Column::exact(label_font.size * 7.0))
-        .column(Column::remainder())
-        .column(Column::remainder())
-        .column(Column::remainder())
+        .column(Column::exact(label_font.size * 7.0));
+    for _ in 0..column_count {
+        table = table.column(Column::remainder());
+    }
+    table
         .header(bigger_font.size * 1.5, |mut row| {
             row.col(|ui| {
                 ui.themed_label(bigger_font, &params.localization.get("action"));
             });
-            row.col(|ui| {
-                ui.themed_label(bigger_font, &params.localization.get("keyboard-1"));
-            });
-            row.col(|ui| {
-                ui.themed_label(bigger_font, &params.localization.get("keyboard-2"));
-            });
-            row.col(|ui| {
-                ui.themed_label(bigger_font, &params.localization.get("gamepad"));
-            });
+            for title in &column_titles {
+                row.col(|ui| {
+                    ui.themed_label(bigger_font, title);
+                });
+            }
         })
         .body(|mut body| {
-            // Keep track of the input button index we are on
-            let mut input_idx = 0;
-
             // Loop through the input rows
-            for (title, inputs) in &mut input_rows {
+            for (row_idx, title) in row_titles.iter().enumerate() {
                 body.row(row_height, |mut row| {
                     // Add row label
                     row.col(|ui| {
@@ -147,21 +130,30 @@ pub fn controls_settings_ui(
                     });
 
                     // Add buttons for each kind of input
-                    for (button_idx, input) in inputs.iter_mut().enumerate() {
+                    for column_idx in 0..column_count {
+                        // Keep track of the input button index we are on
+                        let input_idx = row_idx * column_count + column_idx;
+                        let input = &mut input_columns[column_idx][row_idx];
+
                         // The last button is a gamepad binding, the others are keyboard
-                        let binding_kind = if button_idx == 2 {
+                        let binding_kind = if column_idx == column_count - 1 {
                             BindingKind::Gamepad
                         } else {
                             BindingKind::KeyboardMouse
                         };
 
+                        // Mark inputs that conflict with another input for the same player
+                        let label = if conflicts[column_idx][row_idx] {
+                            format!("* {}", format_input(input))
+                        } else {
+                            format_input(input)
+                        };
+
                         // Render the button
                         row.col(|ui| {
-                            let button = BorderedButton::themed(
-                                &ui_theme.button_styles.small,
-                                format_input(input),
-                            )
-                            .show(ui);
+                            let button =
+                                BorderedButton::themed(&ui_theme.button_styles.small, label)
+                                    .show(ui);
 
                             // Start an input binding if the button is clicked
                             if button.clicked() {
@@ -170,92 +162,116 @@ pub fn controls_settings_ui(
 
                             // If we are binding an input for this button
                             if *params.currently_binding_input_idx == Some(input_idx) {
-                                // Render the binding window
-                                egui::Window::new("input_binding_overlay")
-                                    .auto_sized()
-                                    .collapsible(false)
-                                    .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-                                    .frame(egui::Frame::none())
-                                    .title_bar(false)
-                                    .show(ui.ctx(), |ui| {
-                                        let font = params
-                                            .game
-                                            .ui_theme
-                                            .font_styles
-                                            .normal
-                                            .colored(params.game.ui_theme.panel.font_color);
-
-                                        let border = &params.game.ui_theme.panel.border;
-                                        let m = &border.border_size;
-                                        let s = border.scale;
-                                        BorderedFrame::new(border)
-                                            // Just enough padding to fit the frame's border image
-                                            .padding(Margin {
-                                                left: m.left * s,
-                                                right: m.right * s,
-                                                top: m.top * s,
-                                                bottom: m.bottom * s,
-                                            })
-                                            .show(ui, |ui| {
-                                                ui.themed_label(
-                                                    &font,
-                                                    &params.localization.get("bind-input"),
-                                                );
-
-                                                // See if there has been any inputs of the kind we
-                                                // are binding.
-                                                let get_input =
-                                                    params.control_inputs.get_event(binding_kind);
-
-                                                // If there has been an input
-                                                if let Ok(Some(input_kind)) = get_input {
-                                                    // Stop listening for inputs
-                                                    *params.currently_binding_input_idx = None;
-
-                                                    // Reset the focus on the input button
-                                                    button.request_focus();
-
-                                                    // Set the input for this button to the pressed
-                                                    // input
-                                                    **input = input_kind;
-
-                                                // If the user cancelled the input binding
-                                                } else if get_input.is_err() {
-                                                    // Set the focus back on the button
-                                                    button.request_focus();
-                                                    // And stop listening for inputs
-                                                    *params.currently_binding_input_idx = None;
-                                                }
-
-                                                // Make sure we don't double-trigger any menu
-                                                // actions while on this menu by consuming all menu
-                                                // actions.
-                                                let mut menu_input = params.menu_input.single_mut();
-                                                for action in MenuAction::variants() {
-                                                    menu_input.consume(action);
-                                                }
-                                            });
-                                    });
+                                show_binding_overlay(
+                                    ui.ctx(),
+                                    &params.game,
+                                    &[params.localization.get("bind-input")],
+                                );
+
+                                // See if there has been any inputs of the kind we are binding.
+                                let get_input = params.control_inputs.get_event(binding_kind);
+
+                                // If there has been an input
+                                if let Ok(Some(input_kind)) = get_input {
+                                    // Stop listening for inputs
+                                    *params.currently_binding_input_idx = None;
+
+                                    // Reset the focus on the input button
+                                    button.request_focus();
+
+                                    // Set the input for this button to the pressed input
+                                    **input = input_kind;
+
+                                // If the user cancelled the input binding
+                                } else if get_input.is_err() {
+                                    // Set the focus back on the button
+                                    button.request_focus();
+                                    // And stop listening for inputs
+                                    *params.currently_binding_input_idx = None;
+                                }
+
+                                // Make sure we don't double-trigger any menu actions while on
+                                // this menu by consuming all menu actions.
+                                consume_menu_actions(&mut params.menu_input);
                             }
 
                             // Add input button to the list
                             input_buttons.push(button);
                         });
-
-                        // Increment the input button index
-                        input_idx += 1;
                     }
                 });
             }
         });
 
+    // Add the screenshot key binding below the player controls
+    let screenshot_input_idx = row_count * column_count;
+    let screenshot_button = ui
+        .horizontal(|ui| {
+            ui.themed_label(label_font, &params.localization.get("screenshot-key"));
+
+            let label = if screenshot_conflict {
+                format!("* {}", format_input(&screenshot_input))
+            } else {
+                format_input(&screenshot_input)
+            };
+            BorderedButton::themed(&ui_theme.button_styles.small, label).show(ui)
+        })
+        .inner;
+
+    // Start binding the screenshot key if the button is clicked
+    if screenshot_button.clicked() {
+        *params.currently_binding_input_idx = Some(screenshot_input_idx);
+    }
+
+    // If we are binding the screenshot key
+    if *params.currently_binding_input_idx == Some(screenshot_input_idx) {
+        show_binding_overlay(
+            ui.ctx(),
+            &params.game,
+            &[
+                params.localization.get("bind-input"),
+                params.localization.get("screenshot-key-reserved"),
+            ],
+        );
+
+        match params.control_inputs.get_event(BindingKind::KeyboardMouse) {
+            // Only accept keys that aren't used by any other shortcut, and keep listening otherwise
+            Ok(Some(InputKind::Keyboard(key))) if !is_reserved_screenshot_key(key) => {
+                *params.currently_binding_input_idx = None;
+                screenshot_button.request_focus();
+                *screenshot_key = key;
+            }
+            Ok(_) => (),
+            Err(()) => {
+                screenshot_button.request_focus();
+                *params.currently_binding_input_idx = None;
+            }
+        }
+
+        consume_menu_actions(&mut params.menu_input);
+    }
+
+    // Warn about inputs that are bound to more than one action
+    if has_conflicts {
+        ui.themed_label(
+            &label_font.colored(ui_theme.colors.negative),
+            &params.localization.get("conflicting-inputs"),
+        );
+    }
+    if screenshot_conflict {
+        ui.themed_label(
+            &label_font.colored(ui_theme.colors.negative),
+            &params.localization.get("conflicting-screenshot-key"),
+        );
+    }
+
     // Set adjacency for all of the gamepad input buttons
-    for row_idx in 0..input_rows.len() {
+    for row_idx in 0..row_count {
         if row_idx == 0 {
             // Reverse button order here so that the first input button gets priority when
             // navigating down from the tabs.
-            for i in (0..3).rev() {
-                let button = &input_buttons[row_idx * 3 + i];
+            for i in (0..column_count).rev() {
+                let button = &input_buttons[row_idx * column_count + i];
 
                 // The top row of buttons is below the settings tabs
                 for tab in settings_tabs {
@@ -272,19 +288,20 @@ pub fn controls_settings_ui(
             }
 
         // If this is the last row, the input buttons are above the bottom buttons
-        } else if row_idx == input_rows.len() - 1 {
-            for i in 0..3 {
-                let button_above = &input_buttons[(row_idx - 1) * 3 + i];
-                let button = &input_buttons[row_idx * 3 + i];
+        } else if row_idx == row_count - 1 {
+            for i in 0..column_count {
+                let button_above = &input_buttons[(row_idx - 1) * column_count + i];
+                let button = &input_buttons[row_idx * column_count + i];
+                let bottom_button = &bottom_buttons[i * bottom_buttons.len() / column_count];
 
                 params
                     .adjacencies
                     .widget(button)
-                    .above(&bottom_buttons[i])
+                    .above(bottom_button)
                     .below(button_above);
 
                 // The first bottom button is to the right of the last input button
-                if i == 2 {
+                if i == column_count - 1 {
                     params
                         .adjacencies
                         .widget(button)
@@ -294,14 +311,64 @@ pub fn controls_settings_ui(
 
         // If this is a middle row, set the input buttons to be below the ones in the row above
         } else {
-            for i in 0..3 {
-                let button_above = &input_buttons[(row_idx - 1) * 3 + i];
-                let button = &input_buttons[row_idx * 3 + i];
+            for i in 0..column_count {
+                let button_above = &input_buttons[(row_idx - 1) * column_count + i];
+                let button = &input_buttons[row_idx * column_count + i];
 
                 params.adjacencies.widget(button).below(button_above);
             }
         }
     }
+
+    // The screenshot key button is between the first column of input buttons and the bottom
+    // buttons.
+    params
+        .adjacencies
+        .widget(&screenshot_button)
+        .below(&input_buttons[(row_count - 1) * column_count])
+        .above(&bottom_buttons[0]);
+}
+
+/// Show the window asking for the input to bind, with the given lines of text.
+fn show_binding_overlay(ctx: &egui::Context, game: &GameMeta, lines: &[String]) {
+    egui::Window::new("input_binding_overlay")
+        .auto_sized()
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .frame(egui::Frame::none())
+        .title_bar(false)
+        .show(ctx, |ui| {
+            let font = game
+                .ui_theme
+                .font_styles
+                .normal
+                .colored(game.ui_theme.panel.font_color);
+
+            let border = &game.ui_theme.panel.border;
+            let m = &border.border_size;
+            let s = border.scale;
+            BorderedFrame::new(border)
+                // Just enough padding to fit the frame's border image
+                .padding(Margin {
+                    left: m.left * s,
+                    right: m.right * s,
+                    top: m.top * s,
+                    bottom: m.bottom * s,
+                })
+                .show(ui, |ui| {
+                    for line in lines {
+                        ui.themed_label(&font, line);
+                    }
+                });
+        });
+}
+
+/// Consume all of the menu actions, so that the input being bound doesn't also trigger them.
+fn consume_menu_actions(menu_input: &mut Query<&mut ActionState<MenuAction>>) {
+    let mut menu_input = menu_input.single_mut();
+    for action in MenuAction::variants() {
+        menu_input.consume(action);
+    }
 }
 
 /// Format an InputKind as a user-facing string
@@ -0,0 +1,134 @@
+use super::*;
+
+/// Render the display settings UI
+pub fn display_settings_ui(
+    params: &mut SettingsMenu,
+    ui: &mut egui::Ui,
+    should_reset: bool,
+    settings_tabs: &[egui::Response],
+    bottom_buttons: &[egui::Response],
+) {
+    let settings = params.modified_settings.0.as_mut().unwrap();
+
+    let bigger_font = &params.game.ui_theme.font_styles.bigger;
+    let small_button_style = &params.game.ui_theme.button_styles.small;
+
+    if should_reset {
+        let defaults = &params.game.default_settings;
+        settings.window_resolution = defaults.window_resolution;
+        settings.fullscreen = defaults.fullscreen;
+        settings.vsync = defaults.vsync;
+        settings.ui_scale = defaults.ui_scale;
+    }
+
+    ui.add_space(bigger_font.size);
+
+    // Helper to render a settings row with a label
+    let row = |ui: &mut egui::Ui,
+               label: &str,
+               add_contents: &mut dyn FnMut(&mut egui::Ui) -> egui::Response| {
+        ui.horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(bigger_font, &format!("{}:", params.localization.get(label)));
+            add_contents(ui)
+        })
+        .inner
+    };
+
+    // Clicking the resolution button cycles through the available resolutions
+    let resolution_button = row(ui, "window-resolution", &mut |ui| {
+        let [width, height] = settings.window_resolution;
+        let button =
+            BorderedButton::themed(small_button_style, &format!("{width} x {height}")).show(ui);
+        if button.clicked() {
+            let resolutions = Settings::WINDOW_RESOLUTIONS;
+            let next_idx = resolutions
+                .iter()
+                .position(|x| *x == settings.window_resolution)
+                .map(|i| (i + 1) % resolutions.len())
+                .unwrap_or(0);
+            settings.window_resolution = resolutions[next_idx];
+        }
+        button
+    });
+
+    let fullscreen_button = row(ui, "window-mode", &mut |ui| {
+        let label = if settings.fullscreen {
+            "fullscreen"
+        } else {
+            "windowed"
+        };
+        let button =
+            BorderedButton::themed(small_button_style, &params.localization.get(label)).show(ui);
+        if button.clicked() {
+            settings.fullscreen = !settings.fullscreen;
+        }
+        button
+    });
+
+    let vsync_button = row(ui, "vsync", &mut |ui| {
+        let label = if settings.vsync { "on" } else { "off" };
+        let button =
+            BorderedButton::themed(small_button_style, &params.localization.get(label)).show(ui);
+        if button.clicked() {
+            settings.vsync = !settings.vsync;
+        }
+        button
+    });
+
+    let ui_scale_slider = row(ui, "ui-scale", &mut |ui| {
+        // Edit the scale as a percentage, so that the slider steps in 10% increments.
+        let mut percent = (settings.ui_scale * 100.0).round();
+        let slider = ui.add(
+            egui::Slider::new(
+                &mut percent,
+                Settings::MIN_UI_SCALE * 100.0..=Settings::MAX_UI_SCALE * 100.0,
+            )
+            .step_by(10.0)
+            .suffix("%"),
+        );
+        if slider.changed() {
+            settings.ui_scale =
+                (percent / 100.0).clamp(Settings::MIN_UI_SCALE, Settings::MAX_UI_SCALE);
+        }
+        slider
+    });
+    params.adjacencies.sliders.insert(ui_scale_slider.id);
+
+    let widgets = [
+        resolution_button,
+        fullscreen_button,
+        vsync_button,
+        ui_scale_slider,
+    ];
+
+    let first_widget = widgets.iter().next().unwrap();
+    let last_widget = widgets.iter().last().unwrap();
+    let first_bottom_button = bottom_buttons.iter().next().unwrap();
+    let last_bottom_button = bottom_buttons.iter().last().unwrap();
+    let first_top_tab = settings_tabs.iter().next().unwrap();
+    let last_top_tab = settings_tabs.iter().last().unwrap();
+
+    params
+        .adjacencies
+        .widget(first_widget)
+        .to_right_of(last_top_tab);
+    for tab in settings_tabs {
+        params.adjacencies.widget(first_widget).below(tab);
+        params.adjacencies.widget(tab).below(first_bottom_button);
+    }
+    for pair in widgets.windows(2) {
+        params.adjacencies.widget(&pair[1]).below(&pair[0]);
+    }
+    for button in bottom_buttons {
+        params.adjacencies.widget(button).below(last_widget);
+    }
+    params
+        .adjacencies
+        .widget(last_widget)
+        .above(first_bottom_button);
+    params
+        .adjacencies
+        .widget(last_bottom_button)
+        .to_left_of(first_top_tab);
+}
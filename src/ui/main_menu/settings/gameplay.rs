@@ -0,0 +1,199 @@
+use super::*;
+
+pub fn gameplay_settings_ui(
+    params: &mut SettingsMenu,
+    ui: &mut egui::Ui,
+    should_reset: bool,
+    settings_tabs: &[egui::Response],
+    bottom_buttons: &[egui::Response],
+) {
+    let settings = params.modified_settings.0.as_mut().unwrap();
+
+    let bigger_font = &params.game.ui_theme.font_styles.bigger;
+
+    if should_reset {
+        settings.show_throw_preview = params.game.default_settings.show_throw_preview;
+        settings.camera_shake = params.game.default_settings.camera_shake;
+        settings.kill_cam = params.game.default_settings.kill_cam;
+        settings.double_tap_dash = params.game.default_settings.double_tap_dash;
+        settings.player_indicator = params.game.default_settings.player_indicator;
+        settings.show_hud = params.game.default_settings.show_hud;
+        settings.discord_presence = params.game.default_settings.discord_presence;
+        settings.editor_autosave_interval = params.game.default_settings.editor_autosave_interval;
+        settings.locale = params.game.default_settings.locale.clone();
+        *params.locale = params.game.translations.locale(settings.locale.as_ref());
+    }
+
+    ui.add_space(bigger_font.size);
+
+    // The languages that have finished loading, along with their names
+    let languages = params
+        .game
+        .translations
+        .locales
+        .iter()
+        .filter_map(|handle| params.locale_bundles.get(&handle.inner))
+        .map(|bundle| (bundle.locales[0].clone(), language_name(bundle)))
+        .collect::<Vec<_>>();
+
+    // Clicking the language button cycles through the available languages, switching to them
+    // right away.
+    let language_button = ui
+        .horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &format!("{}:", params.localization.get("language")),
+            );
+
+            let current_idx = languages
+                .iter()
+                .position(|(locale, _)| *locale == params.locale.requested);
+            let current_name = current_idx
+                .map(|i| languages[i].1.clone())
+                .unwrap_or_else(|| params.locale.requested.to_string());
+
+            let language_button =
+                BorderedButton::themed(&params.game.ui_theme.button_styles.small, &current_name)
+                    .show(ui);
+            if language_button.clicked() && !languages.is_empty() {
+                let next_idx = current_idx.map(|i| (i + 1) % languages.len()).unwrap_or(0);
+                let next_locale = languages[next_idx].0.clone();
+                *params.locale = params.game.translations.locale(Some(&next_locale));
+                settings.locale = Some(next_locale);
+            }
+
+            language_button
+        })
+        .inner;
+
+    let toggles = [
+        ("throw-preview", &mut settings.show_throw_preview),
+        ("camera-shake", &mut settings.camera_shake),
+        ("kill-cam", &mut settings.kill_cam),
+        ("double-tap-dash", &mut settings.double_tap_dash),
+        ("show-hud", &mut settings.show_hud),
+        ("discord-presence", &mut settings.discord_presence),
+    ]
+    .map(|(label, value)| {
+        ui.horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(bigger_font, &format!("{}:", params.localization.get(label)));
+
+            let toggle_button = BorderedButton::themed(
+                &params.game.ui_theme.button_styles.small,
+                &params.localization.get(if *value { "on" } else { "off" }),
+            )
+            .show(ui);
+            if toggle_button.clicked() {
+                *value = !*value;
+            }
+
+            toggle_button
+        })
+        .inner
+    });
+
+    // Clicking the player indicator button cycles through the indicator options
+    let player_indicator_button = ui
+        .horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &format!("{}:", params.localization.get("player-indicator")),
+            );
+
+            let indicator = settings.player_indicator;
+            let button = BorderedButton::themed(
+                &params.game.ui_theme.button_styles.small,
+                &params.localization.get(indicator.localization_key()),
+            )
+            .show(ui);
+            if button.clicked() {
+                let options = PlayerIndicator::ALL;
+                let idx = options.iter().position(|x| *x == indicator).unwrap_or(0);
+                settings.player_indicator = options[(idx + 1) % options.len()];
+            }
+
+            button
+        })
+        .inner;
+
+    // Clicking the autosave button cycles through the autosave intervals
+    let autosave_button = ui
+        .horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &format!("{}:", params.localization.get("editor-autosave")),
+            );
+
+            let interval = settings.editor_autosave_interval;
+            let label = if interval == 0 {
+                params.localization.get("off")
+            } else {
+                params
+                    .localization
+                    .get(&format!("editor-autosave-interval?seconds={interval}"))
+            };
+            let button =
+                BorderedButton::themed(&params.game.ui_theme.button_styles.small, &label).show(ui);
+            if button.clicked() {
+                let options = Settings::EDITOR_AUTOSAVE_INTERVALS;
+                let idx = options.iter().position(|x| *x == interval).unwrap_or(0);
+                settings.editor_autosave_interval = options[(idx + 1) % options.len()];
+            }
+
+            button
+        })
+        .inner;
+
+    let widgets = std::iter::once(language_button)
+        .chain(toggles)
+        .chain([player_indicator_button, autosave_button])
+        .collect::<Vec<_>>();
+
+    let first_widget = widgets.iter().next().unwrap();
+    let last_widget = widgets.iter().last().unwrap();
+    let first_bottom_button = bottom_buttons.iter().next().unwrap();
+    let last_bottom_button = bottom_buttons.iter().last().unwrap();
+    let first_top_tab = settings_tabs.iter().next().unwrap();
+    let last_top_tab = settings_tabs.iter().last().unwrap();
+
+    params
+        .adjacencies
+        .widget(first_widget)
+        .to_right_of(last_top_tab);
+    for tab in settings_tabs {
+        params.adjacencies.widget(first_widget).below(tab);
+        params.adjacencies.widget(tab).below(first_bottom_button);
+    }
+    for pair in widgets.windows(2) {
+        params.adjacencies.widget(&pair[1]).below(&pair[0]);
+    }
+    for button in bottom_buttons {
+        params.adjacencies.widget(button).below(last_widget);
+    }
+    params
+        .adjacencies
+        .widget(last_widget)
+        .above(first_bottom_button);
+    params
+        .adjacencies
+        .widget(last_bottom_button)
+        .to_left_of(first_top_tab);
+}
+
+/// Get the name of a language from its bundle's `language-name` message, falling back to the
+/// locale identifier.
+fn language_name(bundle: &bevy_fluent::BundleAsset) -> String {
+    bundle
+        .get_message("language-name")
+        .and_then(|message| message.value())
+        .map(|pattern| {
+            bundle
+                .format_pattern(pattern, None, &mut Vec::new())
+                .to_string()
+        })
+        .unwrap_or_else(|| bundle.locales[0].to_string())
+}
@@ -1,3 +1,5 @@
+use jumpy_core::input::MAX_INPUT_DELAY;
+
 use super::*;
 
 pub fn networking_settings_ui(
@@ -12,48 +14,107 @@ pub fn networking_settings_ui(
     let bigger_font = &params.game.ui_theme.font_styles.bigger;
     let normal_font = &params.game.ui_theme.font_styles.normal;
 
+    if should_reset {
+        settings.matchmaking_server = params.game.default_settings.matchmaking_server.clone();
+        settings.input_delay = params.game.default_settings.input_delay;
+        settings.adaptive_input_delay = params.game.default_settings.adaptive_input_delay;
+    }
+
     ui.add_space(bigger_font.size);
 
-    ui.horizontal(|ui| {
-        ui.add_space(bigger_font.size * 2.0);
-        ui.themed_label(
-            bigger_font,
-            &format!("{}:", params.localization.get("matchmaking-server")),
-        );
-
-        if should_reset {
-            settings.matchmaking_server = params.game.default_settings.matchmaking_server.clone();
-        }
-
-        let text_box = &ui.add(
-            egui::TextEdit::singleline(&mut settings.matchmaking_server)
-                .font(normal_font.clone())
-                .desired_width(ui.available_width() - bigger_font.size * 2.0),
-        );
-        let first_bottom_button = bottom_buttons.iter().next().unwrap();
-        let last_bottom_button = bottom_buttons.iter().last().unwrap();
-        let first_top_tab = settings_tabs.iter().next().unwrap();
-        let last_top_tab = settings_tabs.iter().last().unwrap();
-        params.adjacencies.text_boxes.insert(text_box.id);
-
-        params
-            .adjacencies
-            .widget(text_box)
-            .to_right_of(last_top_tab);
-        for tab in settings_tabs {
-            params.adjacencies.widget(text_box).below(tab);
-            params.adjacencies.widget(tab).below(first_bottom_button);
-        }
-        for button in bottom_buttons {
-            params.adjacencies.widget(button).below(text_box);
-        }
-        params
-            .adjacencies
-            .widget(text_box)
-            .above(first_bottom_button);
-        params
-            .adjacencies
-            .widget(last_bottom_button)
-            .to_left_of(first_top_tab);
-    });
+    let text_box = ui
+        .horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &format!("{}:", params.localization.get("matchmaking-server")),
+            );
+
+            ui.add(
+                egui::TextEdit::singleline(&mut settings.matchmaking_server)
+                    .font(normal_font.clone())
+                    .desired_width(ui.available_width() - bigger_font.size * 2.0),
+            )
+        })
+        .inner;
+    params.adjacencies.text_boxes.insert(text_box.id);
+
+    // Clicking the input delay button cycles through the delays
+    let input_delay_button = ui
+        .horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &format!("{}:", params.localization.get("input-delay")),
+            );
+
+            let button = BorderedButton::themed(
+                &params.game.ui_theme.button_styles.small,
+                &settings.input_delay.to_string(),
+            )
+            .show(ui);
+            if button.clicked() {
+                settings.input_delay = (settings.input_delay + 1) % (MAX_INPUT_DELAY + 1);
+            }
+
+            button
+        })
+        .inner;
+
+    let adaptive_button = ui
+        .horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(
+                bigger_font,
+                &format!("{}:", params.localization.get("adaptive-input-delay")),
+            );
+
+            let button = BorderedButton::themed(
+                &params.game.ui_theme.button_styles.small,
+                &params.localization.get(if settings.adaptive_input_delay {
+                    "on"
+                } else {
+                    "off"
+                }),
+            )
+            .show(ui);
+            if button.clicked() {
+                settings.adaptive_input_delay = !settings.adaptive_input_delay;
+            }
+
+            button
+        })
+        .inner;
+
+    let widgets = [text_box, input_delay_button, adaptive_button];
+
+    let first_widget = widgets.iter().next().unwrap();
+    let last_widget = widgets.iter().last().unwrap();
+    let first_bottom_button = bottom_buttons.iter().next().unwrap();
+    let last_bottom_button = bottom_buttons.iter().last().unwrap();
+    let first_top_tab = settings_tabs.iter().next().unwrap();
+    let last_top_tab = settings_tabs.iter().last().unwrap();
+
+    params
+        .adjacencies
+        .widget(first_widget)
+        .to_right_of(last_top_tab);
+    for tab in settings_tabs {
+        params.adjacencies.widget(first_widget).below(tab);
+        params.adjacencies.widget(tab).below(first_bottom_button);
+    }
+    for pair in widgets.windows(2) {
+        params.adjacencies.widget(&pair[1]).below(&pair[0]);
+    }
+    for button in bottom_buttons {
+        params.adjacencies.widget(button).below(last_widget);
+    }
+    params
+        .adjacencies
+        .widget(last_widget)
+        .above(first_bottom_button);
+    params
+        .adjacencies
+        .widget(last_bottom_button)
+        .to_left_of(first_top_tab);
 }
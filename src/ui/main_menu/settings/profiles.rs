@@ -0,0 +1,117 @@
+use super::*;
+
+/// Render the player profiles UI, with a name and preferred fish for each local player.
+pub fn profiles_settings_ui(
+    params: &mut SettingsMenu,
+    ui: &mut egui::Ui,
+    should_reset: bool,
+    settings_tabs: &[egui::Response],
+    bottom_buttons: &[egui::Response],
+) {
+    let settings = params.modified_settings.0.as_mut().unwrap();
+
+    let bigger_font = &params.game.ui_theme.font_styles.bigger;
+    let normal_font = &params.game.ui_theme.font_styles.normal;
+
+    if should_reset {
+        settings.player_profiles = params.game.default_settings.player_profiles.clone();
+    }
+    if settings.player_profiles.len() < MAX_PLAYERS {
+        settings.player_profiles.resize(MAX_PLAYERS, default());
+    }
+
+    ui.add_space(bigger_font.size);
+
+    let mut rows = Vec::new();
+    for (i, profile) in settings
+        .player_profiles
+        .iter_mut()
+        .take(MAX_PLAYERS)
+        .enumerate()
+    {
+        let row = ui
+            .horizontal(|ui| {
+                ui.add_space(bigger_font.size * 2.0);
+                ui.themed_label(
+                    &bigger_font.colored(params.game.ui_theme.colors.player(i)),
+                    &format!(
+                        "{}:",
+                        params
+                            .localization
+                            .get(&format!("player-number?number={}", i + 1))
+                    ),
+                );
+
+                let text_box = ui.add(
+                    egui::TextEdit::singleline(&mut profile.name)
+                        .font(normal_font.clone())
+                        .char_limit(MAX_PLAYER_NAME_LEN)
+                        .hint_text(params.localization.get("player-name-hint"))
+                        .desired_width(bigger_font.size * 8.0),
+                );
+                params.adjacencies.text_boxes.insert(text_box.id);
+
+                // Clicking the fish button cycles through the fish, starting with no preference
+                let fish_idx = profile
+                    .preferred_fish
+                    .as_ref()
+                    .and_then(|fish| params.core.players.iter().position(|x| x.path == fish.path));
+                let fish_name = fish_idx
+                    .and_then(|idx| {
+                        params
+                            .player_meta_assets
+                            .get(&params.core.players[idx].get_bevy_handle())
+                    })
+                    .map(|meta| meta.name.clone())
+                    .unwrap_or_else(|| params.localization.get("no-preferred-fish"));
+                let fish_button = BorderedButton::themed(
+                    &params.game.ui_theme.button_styles.small,
+                    &format!("<  {fish_name}  >"),
+                )
+                .show(ui);
+                if fish_button.clicked() {
+                    let next_idx = fish_idx.map(|idx| idx + 1).unwrap_or(0);
+                    profile.preferred_fish = params.core.players.get(next_idx).cloned();
+                }
+                params
+                    .adjacencies
+                    .widget(&fish_button)
+                    .to_right_of(&text_box);
+
+                [text_box, fish_button]
+            })
+            .inner;
+        rows.push(row);
+    }
+
+    let first_row = rows.first().unwrap();
+    let last_row = rows.last().unwrap();
+    let first_bottom_button = bottom_buttons.iter().next().unwrap();
+    let last_bottom_button = bottom_buttons.iter().last().unwrap();
+    let first_top_tab = settings_tabs.iter().next().unwrap();
+    let last_top_tab = settings_tabs.iter().last().unwrap();
+
+    params
+        .adjacencies
+        .widget(&first_row[0])
+        .to_right_of(last_top_tab);
+    for tab in settings_tabs {
+        params.adjacencies.widget(&first_row[0]).below(tab);
+        params.adjacencies.widget(tab).below(first_bottom_button);
+    }
+    for pair in rows.windows(2) {
+        params.adjacencies.widget(&pair[1][0]).below(&pair[0][0]);
+        params.adjacencies.widget(&pair[1][1]).below(&pair[0][1]);
+    }
+    for button in bottom_buttons {
+        params.adjacencies.widget(button).below(&last_row[0]);
+    }
+    params
+        .adjacencies
+        .widget(&last_row[0])
+        .above(first_bottom_button);
+    params
+        .adjacencies
+        .widget(last_bottom_button)
+        .to_left_of(first_top_tab);
+}
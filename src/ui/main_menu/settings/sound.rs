@@ -1,7 +1,113 @@
 use super::*;
 
 /// Render the sound settings UI
-pub fn sound_settings_ui(_ui: &mut egui::Ui, _game: &GameMeta) {
-    // This is un-reachable right now
-    todo!("Implement sound settings UI");
+pub fn sound_settings_ui(
+    params: &mut SettingsMenu,
+    ui: &mut egui::Ui,
+    should_reset: bool,
+    settings_tabs: &[egui::Response],
+    bottom_buttons: &[egui::Response],
+) {
+    let settings = params.modified_settings.0.as_mut().unwrap();
+
+    let bigger_font = &params.game.ui_theme.font_styles.bigger;
+
+    if should_reset {
+        let defaults = &params.game.default_settings;
+        settings.master_volume = defaults.master_volume;
+        settings.music_volume = defaults.music_volume;
+        settings.effects_volume = defaults.effects_volume;
+        settings.mute_audio = defaults.mute_audio;
+        settings.positional_audio = defaults.positional_audio;
+    }
+
+    ui.add_space(bigger_font.size);
+
+    let sliders = [
+        ("master-volume", &mut settings.master_volume),
+        ("music-volume", &mut settings.music_volume),
+        ("effects-volume", &mut settings.effects_volume),
+    ]
+    .map(|(label, value)| {
+        ui.horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(bigger_font, &format!("{}:", params.localization.get(label)));
+
+            // Edit the volume as a percentage, so that the slider steps in 5% increments.
+            let mut percent = (value.clamp(0.0, 1.0) * 100.0).round();
+            let slider = ui.add(
+                egui::Slider::new(&mut percent, 0.0..=100.0)
+                    .step_by(5.0)
+                    .suffix("%"),
+            );
+            if slider.changed() {
+                *value = (percent / 100.0).clamp(0.0, 1.0);
+            }
+
+            slider
+        })
+        .inner
+    });
+    for slider in &sliders {
+        params.adjacencies.sliders.insert(slider.id);
+    }
+
+    let toggles = [
+        ("mute-audio", &mut settings.mute_audio),
+        ("positional-audio", &mut settings.positional_audio),
+    ]
+    .map(|(label, value)| {
+        ui.horizontal(|ui| {
+            ui.add_space(bigger_font.size * 2.0);
+            ui.themed_label(bigger_font, &format!("{}:", params.localization.get(label)));
+
+            let toggle_button = BorderedButton::themed(
+                &params.game.ui_theme.button_styles.small,
+                &params.localization.get(if *value { "on" } else { "off" }),
+            )
+            .show(ui);
+            if toggle_button.clicked() {
+                *value = !*value;
+            }
+
+            toggle_button
+        })
+        .inner
+    });
+
+    let first_slider = sliders.iter().next().unwrap();
+    let last_slider = sliders.iter().last().unwrap();
+    let first_bottom_button = bottom_buttons.iter().next().unwrap();
+    let last_bottom_button = bottom_buttons.iter().last().unwrap();
+    let first_top_tab = settings_tabs.iter().next().unwrap();
+    let last_top_tab = settings_tabs.iter().last().unwrap();
+
+    params
+        .adjacencies
+        .widget(first_slider)
+        .to_right_of(last_top_tab);
+    for tab in settings_tabs {
+        params.adjacencies.widget(first_slider).below(tab);
+        params.adjacencies.widget(tab).below(first_bottom_button);
+    }
+    for pair in sliders.windows(2) {
+        params.adjacencies.widget(&pair[1]).below(&pair[0]);
+    }
+    let first_toggle = toggles.iter().next().unwrap();
+    let last_toggle = toggles.iter().last().unwrap();
+    params.adjacencies.widget(first_toggle).below(last_slider);
+    for pair in toggles.windows(2) {
+        params.adjacencies.widget(&pair[1]).below(&pair[0]);
+    }
+    for button in bottom_buttons {
+        params.adjacencies.widget(button).below(last_toggle);
+    }
+    params
+        .adjacencies
+        .widget(last_toggle)
+        .above(first_bottom_button);
+    params
+        .adjacencies
+        .widget(last_bottom_button)
+        .to_left_of(first_top_tab);
 }
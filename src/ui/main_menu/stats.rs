@@ -0,0 +1,257 @@
+use egui_extras::Column;
+
+use super::*;
+
+/// The stats shown on the [`StatsMenu`].
+#[derive(Resource, Default)]
+pub struct StatsMenuState {
+    /// The stored player stats.
+    pub stats: PlayerStats,
+    /// The display names of the player profiles, by player slot.
+    pub profile_names: Vec<Option<String>>,
+    /// Whether the dialog asking to confirm resetting the stats is open.
+    pub confirming_reset: bool,
+}
+
+impl StatsMenuState {
+    /// Reload the stats and profile names from storage.
+    pub fn refresh(&mut self, game: &GameMeta, storage: &mut Storage) {
+        self.stats = storage.get_item();
+        self.profile_names = Settings::get_stored_or_default(game, storage)
+            .player_profiles
+            .iter()
+            .map(|profile| profile.display_name())
+            .collect();
+        self.confirming_reset = false;
+    }
+}
+
+#[derive(SystemParam)]
+pub struct StatsMenu<'w, 's> {
+    game: Res<'w, GameMeta>,
+    menu_page: ResMut<'w, MenuPage>,
+    state: ResMut<'w, StatsMenuState>,
+    storage: ResMut<'w, Storage>,
+    localization: Res<'w, Localization>,
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
+}
+
+impl<'w, 's> WidgetSystem for StatsMenu<'w, 's> {
+    type Args = ();
+
+    fn system(
+        world: &mut World,
+        state: &mut SystemState<Self>,
+        ui: &mut egui::Ui,
+        _id: WidgetId,
+        _args: Self::Args,
+    ) {
+        let mut params: StatsMenu = state.get_mut(world);
+
+        let ui_theme = &params.game.ui_theme;
+        let heading_font = ui_theme
+            .font_styles
+            .heading
+            .colored(ui_theme.panel.font_color);
+        let bigger_font = ui_theme
+            .font_styles
+            .bigger
+            .colored(ui_theme.panel.font_color);
+        let normal_font = ui_theme
+            .font_styles
+            .normal
+            .colored(ui_theme.panel.font_color);
+
+        let menu_width = params.game.main_menu.menu_width;
+        let x_margin = (ui.available_width() - menu_width) / 2.0;
+        let outer_margin = egui::style::Margin::symmetric(x_margin, normal_font.size);
+
+        let back_pressed = params.menu_input.single().just_pressed(MenuAction::Back)
+            || params.keyboard_input.just_pressed(KeyCode::Escape);
+
+        BorderedFrame::new(&ui_theme.panel.border)
+            .margin(outer_margin)
+            .padding(ui_theme.panel.padding.into())
+            .show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.themed_label(&heading_font, &params.localization.get("stats"));
+                });
+                ui.set_min_width(ui.available_width());
+
+                ui.with_layout(egui::Layout::bottom_up(egui::Align::Min), |ui| {
+                    ui.horizontal(|ui| {
+                        // Back button
+                        let back_button = BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &params.localization.get("back"),
+                        )
+                        .show(ui);
+                        // Leave the focus to the reset dialog while it is open
+                        let back_button = if params.state.confirming_reset {
+                            back_button
+                        } else {
+                            back_button.focus_by_default(ui)
+                        };
+
+                        if back_button.clicked() || (back_pressed && !params.state.confirming_reset)
+                        {
+                            *params.menu_page = MenuPage::Home;
+                        }
+
+                        // Reset button
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &params.localization.get("reset"),
+                            )
+                            .show(ui)
+                            .clicked()
+                            {
+                                params.state.confirming_reset = true;
+                                ui.ctx().clear_focus();
+                            }
+                        });
+                    });
+
+                    ui.add_space(normal_font.size / 2.0);
+
+                    ui.with_layout(default(), |ui| {
+                        let profiles = params
+                            .state
+                            .stats
+                            .profiles
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, stats)| stats.matches_played > 0)
+                            .collect::<Vec<_>>();
+                        if profiles.is_empty() {
+                            ui.themed_label(&normal_font, &params.localization.get("no-stats"));
+                            return;
+                        }
+
+                        let columns = [
+                            "player",
+                            "matches-played",
+                            "wins",
+                            "losses",
+                            "kills",
+                            "deaths",
+                            "favorite-weapon",
+                            "fish-played",
+                        ];
+                        let row_height = normal_font.size * 1.5;
+                        egui::ScrollArea::horizontal().show(ui, |ui| {
+                            egui_extras::TableBuilder::new(ui)
+                                .columns(Column::auto().at_least(normal_font.size * 3.0), 7)
+                                .column(Column::remainder())
+                                .header(bigger_font.size * 1.5, |mut row| {
+                                    for title in columns {
+                                        row.col(|ui| {
+                                            ui.themed_label(
+                                                &bigger_font,
+                                                &params.localization.get(title),
+                                            );
+                                        });
+                                    }
+                                })
+                                .body(|body| {
+                                    body.rows(row_height, profiles.len(), |row_idx, mut row| {
+                                        let (slot, stats) = profiles[row_idx];
+                                        let name = params
+                                            .state
+                                            .profile_names
+                                            .get(slot)
+                                            .cloned()
+                                            .flatten()
+                                            .unwrap_or_else(|| {
+                                                params.localization.get(&format!(
+                                                    "player-number?number={}",
+                                                    slot + 1
+                                                ))
+                                            });
+                                        let fish = stats
+                                            .fish_by_usage()
+                                            .iter()
+                                            .map(|(fish, count)| format!("{fish} ({count})"))
+                                            .collect::<Vec<_>>()
+                                            .join(", ");
+                                        let cells = [
+                                            name,
+                                            stats.matches_played.to_string(),
+                                            stats.wins.to_string(),
+                                            stats.losses.to_string(),
+                                            stats.kills.to_string(),
+                                            stats.deaths.to_string(),
+                                            stats.favorite_weapon().unwrap_or("-").to_string(),
+                                            fish,
+                                        ];
+                                        for cell in cells {
+                                            row.col(|ui| {
+                                                ui.themed_label(&normal_font, &cell);
+                                            });
+                                        }
+                                    });
+                                });
+                        });
+                    });
+                });
+            });
+
+        if params.state.confirming_reset {
+            reset_dialog(ui, &mut params, back_pressed);
+        }
+    }
+}
+
+/// Asks for confirmation before resetting the stats, because it can't be undone.
+fn reset_dialog(ui: &mut egui::Ui, params: &mut StatsMenu, back_pressed: bool) {
+    let ui_theme = &params.game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Window::new("reset_stats_dialog")
+        .auto_sized()
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .frame(egui::Frame::none())
+        .title_bar(false)
+        .show(ui.ctx(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&font, &params.localization.get("reset-stats-warning"));
+                    ui.add_space(font.size);
+
+                    ui.horizontal(|ui| {
+                        let cancel_button = BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &params.localization.get("cancel"),
+                        )
+                        .show(ui)
+                        .focus_by_default(ui);
+
+                        if cancel_button.clicked() || back_pressed {
+                            params.state.confirming_reset = false;
+                            ui.ctx().clear_focus();
+                        }
+
+                        if BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &params.localization.get("reset"),
+                        )
+                        .show(ui)
+                        .clicked()
+                        {
+                            params.state.stats = default();
+                            params.state.confirming_reset = false;
+                            ui.ctx().clear_focus();
+                            params.storage.set_item(&params.state.stats);
+                            params.storage.save();
+                        }
+                    });
+                });
+        });
+}
@@ -0,0 +1,250 @@
+//! The loading screen shown between picking a map and starting the match on it.
+
+use bevy::asset::LoadState;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::networking::{GgrsSessionRunnerInfo, NetworkMatchSocket};
+use crate::{
+    prelude::*,
+    presence::PresenceState,
+    ui::{
+        main_menu::MenuPage, pause_menu::PauseMenuPage, training::TrainingMode,
+        tutorial::TutorialMode,
+    },
+};
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+pub struct MapLoadingPlugin;
+
+impl Plugin for MapLoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_map_loading.run_if(resource_exists::<MapLoading>()));
+    }
+}
+
+/// How the match is started once its map has loaded.
+pub enum MapLoadingStart {
+    /// A local game, or a training session.
+    Local { is_training: bool },
+    /// The tutorial, that new players are taken through to learn the controls.
+    Tutorial,
+    /// A network game, using the current [`NetworkMatchSocket`].
+    #[cfg(not(target_arch = "wasm32"))]
+    Network,
+}
+
+/// Resource that exists while the assets of the map that was picked are loading.
+///
+/// The [`CoreSession`] is only created once everything the map uses has loaded, so that a match
+/// never starts half-loaded. In network games this also means we don't tell the other players we
+/// are ready until our map has loaded.
+#[derive(Resource)]
+pub struct MapLoading {
+    pub info: CoreSessionInfo,
+    pub start: MapLoadingStart,
+    /// Handles to assets that weren't being loaded yet, kept so that they stay loaded.
+    requested: Vec<HandleUntyped>,
+}
+
+impl MapLoading {
+    pub fn new(info: CoreSessionInfo, start: MapLoadingStart) -> Self {
+        Self {
+            info,
+            start,
+            requested: default(),
+        }
+    }
+}
+
+/// Resource containing the path of the asset that kept the last map from loading, shown on the map
+/// select screen.
+#[derive(Resource, Clone, Debug)]
+pub struct MapLoadError(pub String);
+
+/// Get the handles of the assets used by a map: its background, tilesets, and elements.
+///
+/// The tileset images are only known once their atlases have loaded, so more handles may be
+/// returned once those finish.
+pub(crate) fn map_asset_handles(
+    map_meta: &MapMeta,
+    atlas_assets: &Assets<TextureAtlas>,
+) -> Vec<HandleUntyped> {
+    let mut handles = Vec::new();
+    for layer in &map_meta.background.layers {
+        handles.push(layer.image.get_bevy_handle_untyped());
+    }
+    for layer in &map_meta.layers {
+        if let Some(tilemap) = &layer.tilemap {
+            let handle = tilemap.get_bevy_handle_untyped();
+            if let Some(atlas) = atlas_assets.get(&handle.clone().typed()) {
+                handles.push(atlas.texture.clone_weak_untyped());
+            }
+            handles.push(handle);
+        }
+        for element in &layer.elements {
+            handles.push(element.element.get_bevy_handle_untyped());
+        }
+    }
+    handles.sort_by_key(|handle| handle.id());
+    handles.dedup_by_key(|handle| handle.id());
+    handles
+}
+
+/// Show the loading progress, and start the match once all of the map's assets have loaded.
+///
+/// If an asset fails to load, we go back to the map select screen, which shows a [`MapLoadError`].
+fn update_map_loading(
+    mut commands: Commands,
+    mut loading: ResMut<MapLoading>,
+    mut session_manager: SessionManager,
+    mut menu_page: ResMut<MenuPage>,
+    mut pause_page: ResMut<PauseMenuPage>,
+    mut presence: ResMut<PresenceState>,
+    mut egui_ctxs: EguiContexts,
+    asset_server: Res<AssetServer>,
+    atlas_assets: Res<Assets<TextureAtlas>>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    #[cfg(not(target_arch = "wasm32"))] network_socket: Option<Res<NetworkMatchSocket>>,
+    #[cfg(not(target_arch = "wasm32"))] mut storage: ResMut<Storage>,
+) {
+    let handles = map_asset_handles(&loading.info.map_meta, &atlas_assets);
+    let mut loaded = 0;
+    let mut failed = None;
+    for handle in &handles {
+        match asset_server.get_load_state(handle.id()) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed => failed = Some(handle.id()),
+            // Start loading assets that nothing has asked for yet
+            LoadState::NotLoaded | LoadState::Unloaded => {
+                match asset_server.get_handle_path(handle.id()) {
+                    Some(path) => {
+                        let handle = asset_server.load_untyped(path);
+                        loading.requested.push(handle);
+                    }
+                    None => failed = Some(handle.id()),
+                }
+            }
+            LoadState::Loading => (),
+        }
+    }
+
+    if let Some(failed) = failed {
+        let path = asset_server
+            .get_handle_path(failed)
+            .map(|path| path.path().display().to_string())
+            .unwrap_or_default();
+        error!(map = %loading.info.map_meta.name, %path, "Map asset failed to load");
+        commands.insert_resource(MapLoadError(path));
+        commands.remove_resource::<MapLoading>();
+        return;
+    }
+
+    if loaded < handles.len() {
+        loading_screen(
+            egui_ctxs.ctx_mut(),
+            &game,
+            &localization,
+            &loading.info.map_meta.name,
+            loaded,
+            handles.len(),
+        );
+        return;
+    }
+
+    info!(map = %loading.info.map_meta.name, "Map loaded, starting game");
+    commands.remove_resource::<MapLoading>();
+    commands.remove_resource::<MapLoadError>();
+    *menu_page = MenuPage::Home;
+    *pause_page = PauseMenuPage::Default;
+    let info = loading.info.clone();
+    *presence = PresenceState::LocalMatch {
+        map: info.map_meta.name.clone(),
+        players: info.player_info.iter().flatten().count(),
+    };
+    match loading.start {
+        MapLoadingStart::Local { is_training } => {
+            session_manager.start_local(info);
+            if is_training {
+                commands.init_resource::<TrainingMode>();
+            }
+        }
+        MapLoadingStart::Tutorial => {
+            session_manager.start_local(info);
+            commands.init_resource::<TutorialMode>();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        MapLoadingStart::Network => {
+            let Some(socket) = network_socket else {
+                warn!("Network game ended while its map was loading");
+                return;
+            };
+            *presence = PresenceState::OnlineMatch {
+                players: socket.player_count(),
+                max_players: MAX_PLAYERS,
+            };
+            let settings = Settings::get_stored_or_default(&game, &mut storage);
+            session_manager.start_network(
+                info,
+                GgrsSessionRunnerInfo {
+                    player_is_local: socket.player_is_local(),
+                    player_count: socket.player_count(),
+                    input_delay: settings.input_delay,
+                    adaptive_input_delay: settings.adaptive_input_delay,
+                },
+            );
+        }
+    }
+    commands.insert_resource(NextState(Some(EngineState::InGame)));
+    commands.insert_resource(NextState(Some(InGameState::Playing)));
+}
+
+/// Render the loading screen over the rest of the UI.
+///
+/// It only shows the progress: the match can't be started until the map has loaded.
+fn loading_screen(
+    ctx: &egui::Context,
+    game: &GameMeta,
+    localization: &Localization,
+    map_name: &str,
+    loaded: usize,
+    total: usize,
+) {
+    let ui_theme = &game.ui_theme;
+    let heading = ui_theme
+        .font_styles
+        .heading
+        .colored(ui_theme.panel.font_color);
+    let font = ui_theme
+        .font_styles
+        .bigger
+        .colored(ui_theme.panel.font_color);
+    let menu_width = game.main_menu.menu_width;
+
+    egui::Area::new("map_loading_screen")
+        .order(egui::Order::Foreground)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            // Cover up the menu behind the loading screen
+            ui.painter()
+                .rect_filled(ctx.screen_rect(), 0.0, egui::Color32::BLACK);
+
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.set_width(menu_width);
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(&heading, &localization.get("loading-map"));
+                        ui.themed_label(&font, map_name);
+                        ui.add_space(font.size / 2.0);
+                        ui.add(
+                            egui::ProgressBar::new(loaded as f32 / total.max(1) as f32)
+                                .text(format!("{loaded} / {total}")),
+                        );
+                    });
+                });
+        });
+}
@@ -0,0 +1,536 @@
+//! Small preview images of maps, shown on the map select screen.
+//!
+//! Thumbnails are drawn on the CPU from the map's tilesets, so that they can be made without
+//! spawning the map. On native platforms they are cached in the data directory, keyed by the map's
+//! path and a hash of its contents, so that edited maps get fresh thumbnails.
+
+use bevy::{
+    asset::LoadState,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use bevy_egui::EguiContexts;
+
+use crate::{prelude::*, ui::map_loading::map_asset_handles};
+
+/// The size of map thumbnails, in pixels.
+pub const THUMBNAIL_SIZE: UVec2 = UVec2::new(320, 180);
+
+/// The size of the markers drawn on thumbnails where elements are placed, in pixels.
+const ELEMENT_MARKER_SIZE: i32 = 3;
+
+pub struct MapThumbnailsPlugin;
+
+impl Plugin for MapThumbnailsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapThumbnails>()
+            .add_system(update_map_thumbnails);
+    }
+}
+
+/// Identifies a thumbnail by the map it was made for and the contents of that map.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ThumbnailKey {
+    /// The asset path of a core map, or a name identifying a map from [`Storage`].
+    pub path: String,
+    /// The [`map_content_hash`] of the map.
+    pub content_hash: u64,
+}
+
+impl ThumbnailKey {
+    pub fn new(path: impl Into<String>, map_meta: &MapMeta) -> Self {
+        Self {
+            path: path.into(),
+            content_hash: map_content_hash(map_meta),
+        }
+    }
+
+    /// Get the key for a core map.
+    pub fn core(map_handle: &bones::Handle<MapMeta>, map_meta: &MapMeta) -> Self {
+        Self::new(map_handle.path.path.display().to_string(), map_meta)
+    }
+
+    /// Get the key for a map saved in [`Storage`] under the given storage key.
+    pub fn user(storage_key: &str, map_meta: &MapMeta) -> Self {
+        Self::new(format!("{storage_key}:{}", map_meta.name), map_meta)
+    }
+
+    /// The start of the names of all of the cache files for this key's map.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn file_prefix(&self) -> String {
+        format!("{:016x}-", fnv1a_hash(self.path.as_bytes()))
+    }
+
+    /// The name of the cache file for this key.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn file_name(&self) -> String {
+        format!("{}{:016x}.rgba", self.file_prefix(), self.content_hash)
+    }
+}
+
+/// Hash the contents of a map, for telling whether a cached thumbnail is still up to date.
+///
+/// This must stay the same between runs of the game, so it doesn't use the std hasher.
+pub fn map_content_hash(map_meta: &MapMeta) -> u64 {
+    let contents = serde_yaml::to_string(map_meta).unwrap_or_default();
+    fnv1a_hash(contents.as_bytes())
+}
+
+/// 64-bit FNV-1a hash, which is simple and stable across platforms and Rust versions.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Resource containing the map thumbnails that have been made, and the ones waiting to be made.
+#[derive(Resource)]
+pub struct MapThumbnails {
+    thumbnails: HashMap<ThumbnailKey, ThumbnailState>,
+    /// Handles to map assets that were loaded so that thumbnails could be drawn.
+    requested_assets: Vec<HandleUntyped>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_sender: async_channel::Sender<(ThumbnailKey, Option<Vec<u8>>)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_receiver: async_channel::Receiver<(ThumbnailKey, Option<Vec<u8>>)>,
+}
+
+impl Default for MapThumbnails {
+    fn default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let (cache_sender, cache_receiver) = async_channel::unbounded();
+        Self {
+            thumbnails: default(),
+            requested_assets: default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            cache_sender,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache_receiver,
+        }
+    }
+}
+
+enum ThumbnailState {
+    /// The thumbnail was asked for, but we haven't started looking for it yet.
+    Requested { map_meta: MapMeta },
+    /// We are reading the thumbnail from the disk cache.
+    #[cfg(not(target_arch = "wasm32"))]
+    ReadingCache { map_meta: MapMeta },
+    /// The thumbnail needs to be drawn once the map's assets have loaded.
+    Rendering { map_meta: MapMeta },
+    Ready {
+        /// Kept so that the image stays loaded while egui uses it.
+        _image: Handle<Image>,
+        texture_id: egui::TextureId,
+    },
+}
+
+impl MapThumbnails {
+    /// Get the egui texture for a map's thumbnail, or [`None`] if it isn't ready yet.
+    ///
+    /// Thumbnails that aren't ready are made in the background.
+    pub fn get(&mut self, key: &ThumbnailKey, map_meta: &MapMeta) -> Option<egui::TextureId> {
+        match self.thumbnails.get(key) {
+            Some(ThumbnailState::Ready { texture_id, .. }) => Some(*texture_id),
+            Some(_) => None,
+            None => {
+                self.thumbnails.insert(
+                    key.clone(),
+                    ThumbnailState::Requested {
+                        map_meta: map_meta.clone(),
+                    },
+                );
+                None
+            }
+        }
+    }
+
+    /// Draw a new thumbnail for a map that has just been saved, without checking the cache.
+    ///
+    /// Thumbnails made for older versions of the map are forgotten.
+    pub fn regenerate(&mut self, key: ThumbnailKey, map_meta: &MapMeta) {
+        self.thumbnails.retain(|other, _| other.path != key.path);
+        self.thumbnails.insert(
+            key,
+            ThumbnailState::Rendering {
+                map_meta: map_meta.clone(),
+            },
+        );
+    }
+}
+
+/// Get the directory that thumbnails are cached in.
+#[cfg(not(target_arch = "wasm32"))]
+fn cache_dir() -> std::path::PathBuf {
+    data_dir().join("map_thumbnails")
+}
+
+/// Look for requested thumbnails in the cache, and draw the ones that aren't there.
+fn update_map_thumbnails(
+    mut thumbnails: ResMut<MapThumbnails>,
+    mut image_assets: ResMut<Assets<Image>>,
+    mut egui_ctxs: EguiContexts,
+    asset_server: Res<AssetServer>,
+    atlas_assets: Res<Assets<TextureAtlas>>,
+    element_assets: Res<Assets<ElementMeta>>,
+) {
+    let thumbnails = &mut *thumbnails;
+
+    // Receive thumbnails read from the cache
+    #[cfg(not(target_arch = "wasm32"))]
+    while let Ok((key, data)) = thumbnails.cache_receiver.try_recv() {
+        // The map may have been saved again while we were reading
+        let Some(state) = thumbnails.thumbnails.get_mut(&key) else {
+            continue;
+        };
+        let ThumbnailState::ReadingCache { map_meta } = state else {
+            continue;
+        };
+        let expected_len = (THUMBNAIL_SIZE.x * THUMBNAIL_SIZE.y * 4) as usize;
+        *state = match data {
+            Some(data) if data.len() == expected_len => {
+                trace!(path = %key.path, "Read map thumbnail from cache");
+                thumbnail_ready(data, &mut image_assets, &mut egui_ctxs)
+            }
+            _ => ThumbnailState::Rendering {
+                map_meta: std::mem::take(map_meta),
+            },
+        };
+    }
+
+    // Start looking for newly requested thumbnails
+    for (key, state) in thumbnails.thumbnails.iter_mut() {
+        let ThumbnailState::Requested { map_meta } = state else {
+            continue;
+        };
+        let map_meta = std::mem::take(map_meta);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = cache_dir().join(key.file_name());
+            let sender = thumbnails.cache_sender.clone();
+            let key = key.clone();
+            bevy::tasks::IoTaskPool::get()
+                .spawn(async move {
+                    sender.try_send((key, std::fs::read(path).ok())).ok();
+                })
+                .detach();
+            *state = ThumbnailState::ReadingCache { map_meta };
+        }
+
+        // There is no disk cache on web, so thumbnails are drawn every time.
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = key;
+            *state = ThumbnailState::Rendering { map_meta };
+        }
+    }
+
+    // Draw one thumbnail per frame, so that opening the map list doesn't hitch
+    let mut rendered = None;
+    for (key, state) in thumbnails.thumbnails.iter() {
+        let ThumbnailState::Rendering { map_meta } = state else {
+            continue;
+        };
+
+        let mut is_loading = false;
+        for handle in map_asset_handles(map_meta, &atlas_assets) {
+            match asset_server.get_load_state(handle.id()) {
+                LoadState::Loading => is_loading = true,
+                LoadState::NotLoaded | LoadState::Unloaded => {
+                    is_loading = true;
+                    if let Some(path) = asset_server.get_handle_path(handle.id()) {
+                        thumbnails
+                            .requested_assets
+                            .push(asset_server.load_untyped(path));
+                    }
+                }
+                // Missing assets are left out of the thumbnail
+                LoadState::Loaded | LoadState::Failed => (),
+            }
+        }
+        if is_loading {
+            continue;
+        }
+
+        let data = render_thumbnail(map_meta, &atlas_assets, &image_assets, &element_assets);
+        rendered = Some((key.clone(), data));
+        break;
+    }
+
+    if let Some((key, data)) = rendered {
+        debug!(path = %key.path, "Rendered map thumbnail");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let dir = cache_dir();
+            let prefix = key.file_prefix();
+            let file_name = key.file_name();
+            let data = data.clone();
+            bevy::tasks::IoTaskPool::get()
+                .spawn(async move {
+                    if let Err(e) = write_cached_thumbnail(&dir, &prefix, &file_name, &data) {
+                        warn!("Could not cache map thumbnail: {e}");
+                    }
+                })
+                .detach();
+        }
+
+        let state = thumbnail_ready(data, &mut image_assets, &mut egui_ctxs);
+        thumbnails.thumbnails.insert(key, state);
+    }
+
+    // Let go of the map assets once nothing is waiting on them
+    if !thumbnails
+        .thumbnails
+        .values()
+        .any(|state| matches!(state, ThumbnailState::Rendering { .. }))
+    {
+        thumbnails.requested_assets.clear();
+    }
+}
+
+/// Write a thumbnail to the cache, removing the thumbnails made for older versions of the map.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_cached_thumbnail(
+    dir: &std::path::Path,
+    prefix: &str,
+    file_name: &str,
+    data: &[u8],
+) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(prefix) && name != file_name {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    std::fs::write(dir.join(file_name), data)
+}
+
+/// Turn the pixels of a thumbnail into an image that egui can draw.
+fn thumbnail_ready(
+    data: Vec<u8>,
+    image_assets: &mut Assets<Image>,
+    egui_ctxs: &mut EguiContexts,
+) -> ThumbnailState {
+    let image = Image::new(
+        Extent3d {
+            width: THUMBNAIL_SIZE.x,
+            height: THUMBNAIL_SIZE.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    let image = image_assets.add(image);
+    let texture_id = egui_ctxs.add_image(image.clone_weak());
+
+    ThumbnailState::Ready {
+        _image: image,
+        texture_id,
+    }
+}
+
+/// Draw a map's tile layers and element positions into the pixels of a [`THUMBNAIL_SIZE`] image.
+///
+/// The map is scaled to fit inside of the thumbnail, and centered on the background color.
+fn render_thumbnail(
+    map_meta: &MapMeta,
+    atlas_assets: &Assets<TextureAtlas>,
+    image_assets: &Assets<Image>,
+    element_assets: &Assets<ElementMeta>,
+) -> Vec<u8> {
+    let mut canvas = ThumbnailCanvas::new(color_bytes(map_meta.background_color.0));
+
+    let map_size = map_meta.grid_size.as_vec2() * map_meta.tile_size;
+    if map_size.cmple(Vec2::ZERO).any() {
+        return canvas.data;
+    }
+    let scale = (THUMBNAIL_SIZE.as_vec2() / map_size).min_element();
+    let offset = (THUMBNAIL_SIZE.as_vec2() - map_size * scale) / 2.0;
+    // Map positions go up from the bottom, but image rows go down from the top
+    let to_thumbnail = |pos: Vec2| {
+        Vec2::new(
+            offset.x + pos.x * scale,
+            offset.y + (map_size.y - pos.y) * scale,
+        )
+    };
+
+    for layer in &map_meta.layers {
+        let Some(tilemap) = &layer.tilemap else {
+            continue;
+        };
+        let Some(atlas) = atlas_assets.get(&tilemap.get_bevy_handle_untyped().typed()) else {
+            continue;
+        };
+        let Some(image) = image_assets.get(&atlas.texture) else {
+            continue;
+        };
+        if !matches!(
+            image.texture_descriptor.format,
+            TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm
+        ) {
+            warn!(layer = %layer.id, "Skipping tileset with unsupported format in map thumbnail");
+            continue;
+        }
+
+        for tile in &layer.tiles {
+            let Some(src) = atlas.textures.get(tile.idx as usize) else {
+                continue;
+            };
+            let top_left = to_thumbnail(
+                Vec2::new(tile.pos.x as f32, tile.pos.y as f32 + 1.0) * map_meta.tile_size,
+            );
+            canvas.draw_image_rect(
+                top_left,
+                map_meta.tile_size * scale,
+                image,
+                src.min,
+                src.size(),
+            );
+        }
+    }
+
+    // Elements don't have icons, so they are marked with a color for their category
+    for layer in &map_meta.layers {
+        for element in &layer.elements {
+            let Some(element_meta) = element_assets.get(&element.element.get_bevy_handle()) else {
+                continue;
+            };
+            let pos = to_thumbnail(element.pos).as_ivec2();
+            canvas.fill_rect(
+                pos - IVec2::splat(ELEMENT_MARKER_SIZE / 2),
+                IVec2::splat(ELEMENT_MARKER_SIZE),
+                element_marker_color(&element_meta.category),
+            );
+        }
+    }
+
+    canvas.data
+}
+
+/// Get the color that elements in the given editor category are marked with on thumbnails.
+fn element_marker_color(category: &str) -> [u8; 4] {
+    match category {
+        "Gameplay" => [80, 220, 100, 255],
+        "Weapons" => [230, 70, 60, 255],
+        "Critters" => [250, 200, 60, 255],
+        _ => [200, 200, 200, 255],
+    }
+}
+
+/// Convert a color to sRGB bytes.
+fn color_bytes(color: Color) -> [u8; 4] {
+    color
+        .as_rgba_f32()
+        .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// The RGBA pixels of a thumbnail being drawn.
+struct ThumbnailCanvas {
+    data: Vec<u8>,
+}
+
+impl ThumbnailCanvas {
+    fn new(background: [u8; 4]) -> Self {
+        let pixel_count = (THUMBNAIL_SIZE.x * THUMBNAIL_SIZE.y) as usize;
+        Self {
+            data: background.repeat(pixel_count),
+        }
+    }
+
+    /// Blend a color over the pixel at the given position, if it is inside the thumbnail.
+    fn blend_pixel(&mut self, x: i32, y: i32, color: [u8; 4]) {
+        if x < 0 || y < 0 || x >= THUMBNAIL_SIZE.x as i32 || y >= THUMBNAIL_SIZE.y as i32 {
+            return;
+        }
+        let i = (y as usize * THUMBNAIL_SIZE.x as usize + x as usize) * 4;
+        let alpha = color[3] as u32;
+        for channel in 0..3 {
+            let dst = self.data[i + channel] as u32;
+            self.data[i + channel] =
+                ((color[channel] as u32 * alpha + dst * (255 - alpha)) / 255) as u8;
+        }
+        self.data[i + 3] = 255;
+    }
+
+    fn fill_rect(&mut self, pos: IVec2, size: IVec2, color: [u8; 4]) {
+        for y in pos.y..pos.y + size.y {
+            for x in pos.x..pos.x + size.x {
+                self.blend_pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Draw part of an RGBA image into a rect of the thumbnail, using nearest neighbor sampling.
+    fn draw_image_rect(
+        &mut self,
+        top_left: Vec2,
+        size: Vec2,
+        image: &Image,
+        src_min: Vec2,
+        src_size: Vec2,
+    ) {
+        let image_width = image.texture_descriptor.size.width as usize;
+        let image_height = image.texture_descriptor.size.height as usize;
+        let start = top_left.floor().as_ivec2();
+        let end = (top_left + size).ceil().as_ivec2();
+        for y in start.y..end.y {
+            for x in start.x..end.x {
+                let uv = ((Vec2::new(x as f32, y as f32) + 0.5 - top_left) / size)
+                    .clamp(Vec2::ZERO, Vec2::splat(0.999));
+                let src = (src_min + uv * src_size).as_uvec2();
+                let (src_x, src_y) = (src.x as usize, src.y as usize);
+                if src_x >= image_width || src_y >= image_height {
+                    continue;
+                }
+                let i = (src_y * image_width + src_x) * 4;
+                let Some(pixel) = image.data.get(i..i + 4) else {
+                    continue;
+                };
+                self.blend_pixel(x, y, [pixel[0], pixel[1], pixel[2], pixel[3]]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hash_changes_with_map() {
+        let map = MapMeta {
+            name: "Test".into(),
+            grid_size: UVec2::new(10, 10),
+            tile_size: Vec2::splat(16.0),
+            ..default()
+        };
+        let mut edited = map.clone();
+        edited.grid_size.x += 1;
+
+        assert_eq!(map_content_hash(&map), map_content_hash(&map.clone()));
+        assert_ne!(map_content_hash(&map), map_content_hash(&edited));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn cache_files_for_a_map_share_a_prefix() {
+        let map = MapMeta {
+            name: "Test".into(),
+            ..default()
+        };
+        let mut edited = map.clone();
+        edited.name = "Edited".into();
+        let key = ThumbnailKey::new("maps/test.map.yaml", &map);
+        let edited_key = ThumbnailKey::new("maps/test.map.yaml", &edited);
+        let other_key = ThumbnailKey::new("maps/other.map.yaml", &map);
+
+        assert_ne!(key.file_name(), edited_key.file_name());
+        assert!(edited_key.file_name().starts_with(&key.file_prefix()));
+        assert!(!other_key.file_name().starts_with(&key.file_prefix()));
+    }
+}
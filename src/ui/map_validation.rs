@@ -0,0 +1,109 @@
+//! Checking maps for problems, and describing those problems to the player.
+
+use bevy_fluent::Localization;
+
+use crate::prelude::*;
+
+/// Check a map for problems using the loaded element and tilemap assets.
+pub fn validate_map(
+    map_meta: &MapMeta,
+    element_assets: &Assets<ElementMeta>,
+    atlas_assets: &Assets<TextureAtlas>,
+) -> Vec<MapValidationError> {
+    map_meta.validate(
+        |element| element_assets.get(&element.get_bevy_handle()),
+        |tilemap| {
+            atlas_assets
+                .get(&tilemap.get_bevy_handle_untyped().typed())
+                .map(|atlas| atlas.textures.len())
+        },
+    )
+}
+
+/// Get a description of a map problem, including whether it is an error or only a warning, and the
+/// layer and position it was found at.
+pub fn map_problem_message(
+    localization: &Localization,
+    map_meta: &MapMeta,
+    error: &MapValidationError,
+) -> String {
+    // Layer names and asset paths are added outside of the localized message, because they may
+    // contain characters that aren't allowed in the message arguments.
+    let layer_name = |layer: usize| {
+        map_meta
+            .layers
+            .get(layer)
+            .map(|layer| layer.id.as_str())
+            .unwrap_or_default()
+    };
+
+    let message = match error {
+        MapValidationError::EmptyGrid => localization.get("map-problem-empty-grid"),
+        MapValidationError::DuplicateLayerName { layer, name } => format!(
+            "{}: {name}",
+            localization.get(&format!("map-problem-duplicate-layer-name?layer={layer}"))
+        ),
+        MapValidationError::TileOutOfBounds { layer, pos } => format!(
+            "[{}] {}",
+            layer_name(*layer),
+            localization.get(&format!(
+                "map-problem-tile-out-of-bounds?x={}&y={}",
+                pos.x, pos.y
+            ))
+        ),
+        MapValidationError::TileIndexOutOfRange {
+            layer,
+            pos,
+            idx,
+            tile_count,
+        } => format!(
+            "[{}] {}",
+            layer_name(*layer),
+            localization.get(&format!(
+                "map-problem-tile-index-out-of-range?x={}&y={}&idx={idx}&count={tile_count}",
+                pos.x, pos.y
+            ))
+        ),
+        MapValidationError::MissingTilemap { layer } => format!(
+            "[{}] {}",
+            layer_name(*layer),
+            localization.get("map-problem-missing-tilemap")
+        ),
+        MapValidationError::UnknownElement { layer, pos, path } => format!(
+            "[{}] {}: {path}",
+            layer_name(*layer),
+            localization.get(&format!(
+                "map-problem-unknown-element?x={:.0}&y={:.0}",
+                pos.x, pos.y
+            ))
+        ),
+        MapValidationError::UnknownElementBehavior {
+            layer,
+            pos,
+            behavior,
+        } => format!(
+            "[{}] {}: {behavior}",
+            layer_name(*layer),
+            localization.get(&format!(
+                "map-problem-unknown-element-behavior?x={:.0}&y={:.0}",
+                pos.x, pos.y
+            ))
+        ),
+        MapValidationError::SpawnPointOutOfBounds { layer, pos } => format!(
+            "[{}] {}",
+            layer_name(*layer),
+            localization.get(&format!(
+                "map-problem-spawn-point-out-of-bounds?x={:.0}&y={:.0}",
+                pos.x, pos.y
+            ))
+        ),
+        MapValidationError::NoSpawnPoints => localization.get("map-problem-no-spawn-points"),
+    };
+    let severity = if error.is_fatal() {
+        localization.get("map-problem-error")
+    } else {
+        localization.get("map-problem-warning")
+    };
+
+    format!("{severity}: {message}")
+}
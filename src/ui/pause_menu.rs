@@ -2,10 +2,18 @@ use bevy::window::PrimaryWindow;
 use bevy_egui::*;
 use bevy_fluent::Localization;
 
+use jumpy_core::input::PlayerInputs;
+
 use crate::{prelude::*, widgets::EguiResponseExt};
 
 use super::{
-    main_menu::map_select::MapSelectMenu,
+    main_menu::{
+        map_select::MapSelectMenu,
+        settings::{ModifiedSettings, SettingsMenu},
+        MenuPage,
+    },
+    photo_mode::PhotoMode,
+    training::TrainingMode,
     widget,
     widgets::{
         bordered_button::BorderedButton, bordered_frame::BorderedFrame, EguiContextExt, EguiUiExt,
@@ -27,6 +35,11 @@ impl Plugin for PausePlugin {
                         .run_if(in_state(EngineState::InGame))
                         .run_if(in_state(GameEditorState::Hidden))
                         .run_if(in_state(InGameState::Playing)),
+                    pause_on_gamepad_disconnect
+                        .run_if(in_state(EngineState::InGame))
+                        .run_if(in_state(GameEditorState::Hidden))
+                        .run_if(in_state(InGameState::Playing))
+                        .run_if(resource_exists::<Session>()),
                 )
                     .in_base_set(CoreSet::PostUpdate),
             )
@@ -40,10 +53,42 @@ impl Plugin for PausePlugin {
                         .run_if(in_state(EngineState::InGame))
                         .run_if(in_state(InGameState::Paused))
                         .run_if(resource_equals(PauseMenuPage::MapSelect)),
+                    pause_menu_settings
+                        .run_if(in_state(EngineState::InGame))
+                        .run_if(in_state(InGameState::Paused))
+                        .run_if(resource_equals(PauseMenuPage::Settings)),
                 )
                     .in_base_set(CoreSet::Update),
-            );
+            )
+            .add_system(freeze_local_session.in_schedule(OnEnter(InGameState::Paused)))
+            .add_system(unfreeze_session.in_schedule(OnExit(InGameState::Paused)));
+    }
+}
+
+/// Stop the game session from advancing while the pause menu is open, if all of the players are
+/// local.
+///
+/// Online games can't be stopped, so they keep running while the menu is shown.
+fn freeze_local_session(mut commands: Commands, session: Option<ResMut<Session>>) {
+    if let Some(mut session) = session {
+        if session.network_player_idx().is_none() {
+            commands.init_resource::<Paused>();
+        }
+    }
+}
+
+/// Let the game session advance again when the pause menu is closed.
+fn unfreeze_session(
+    mut commands: Commands,
+    mut pause_page: ResMut<PauseMenuPage>,
+    mut menu_page: ResMut<MenuPage>,
+) {
+    // Leave the settings if they were open
+    if matches!(*menu_page, MenuPage::Settings) {
+        *menu_page = MenuPage::Home;
     }
+    *pause_page = default();
+    commands.remove_resource::<Paused>();
 }
 
 /// Transition game to pause state
@@ -54,6 +99,35 @@ fn pause_system(mut commands: Commands, input: Query<&ActionState<MenuAction>>)
     }
 }
 
+/// Pause the game when the gamepad of a player in the game is disconnected.
+fn pause_on_gamepad_disconnect(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    mut disconnected_events: EventReader<PlayerGamepadDisconnected>,
+) {
+    let slots = disconnected_events
+        .iter()
+        .map(|x| x.slot)
+        .collect::<Vec<_>>();
+    if slots.is_empty() {
+        return;
+    }
+
+    let active_players = session
+        .world()
+        .run_initialized_system(|player_inputs: bones::Res<PlayerInputs>| {
+            Ok(player_inputs
+                .players
+                .iter()
+                .map(|x| x.active)
+                .collect::<Vec<_>>())
+        })
+        .unwrap();
+    if slots.iter().any(|slot| active_players[*slot]) {
+        commands.insert_resource(NextState(Some(InGameState::Paused)));
+    }
+}
+
 // Transition game out of paused state
 fn unpause_system(
     mut commands: Commands,
@@ -61,6 +135,15 @@ fn unpause_system(
     mut pause_page: ResMut<PauseMenuPage>,
 ) {
     let input = input.single();
+
+    // Go back to the pause menu from photo mode
+    if *pause_page == PauseMenuPage::PhotoMode {
+        if input.just_pressed(MenuAction::Pause) || input.just_pressed(MenuAction::Back) {
+            *pause_page = PauseMenuPage::Default;
+        }
+        return;
+    }
+
     if input.just_pressed(MenuAction::Pause) {
         *pause_page = default();
         commands.insert_resource(NextState(Some(InGameState::Playing)));
@@ -72,6 +155,9 @@ pub enum PauseMenuPage {
     #[default]
     Default,
     MapSelect,
+    Settings,
+    /// The menu is hidden to take screenshots in [`PhotoMode`].
+    PhotoMode,
 }
 
 pub fn pause_menu_default(
@@ -82,9 +168,17 @@ pub fn pause_menu_default(
     map_assets: Res<Assets<MapMeta>>,
     mut pause_page: ResMut<PauseMenuPage>,
     mut session_manager: SessionManager,
+    mut storage: ResMut<Storage>,
     mut contexts: EguiContexts,
+    player_devices: Res<PlayerDevices>,
+    mut menu_page: ResMut<MenuPage>,
+    mut modified_settings: ResMut<ModifiedSettings>,
 ) {
-    let is_online = false;
+    let is_online = session_manager
+        .session
+        .as_mut()
+        .map(|session| session.network_player_idx().is_some())
+        .unwrap_or(false);
     let ui_theme = &game.ui_theme;
 
     egui::CentralPanel::default()
@@ -119,7 +213,21 @@ pub fn pause_menu_default(
                         {
                             ui.themed_label(&bigger_font, &map_meta.name);
                         }
-                        ui.themed_label(&heading_font, &localization.get("paused"));
+                        if is_online {
+                            // The game can't be stopped online, so let the player know
+                            ui.themed_label(&heading_font, &localization.get("menu"));
+                            ui.themed_label(&bigger_font, &localization.get("game-still-running"));
+                        } else {
+                            ui.themed_label(&heading_font, &localization.get("paused"));
+                        }
+
+                        for slot in player_devices.disconnected_slots() {
+                            ui.themed_label(
+                                &bigger_font.colored(ui_theme.colors.negative),
+                                &localization
+                                    .get(&format!("controller-disconnected?player={}", slot + 1)),
+                            );
+                        }
 
                         ui.add_space(10.0);
 
@@ -160,11 +268,43 @@ pub fn pause_menu_default(
                             .show(ui)
                             .clicked()
                             {
+                                // Restart the match from the beginning, with the same settings
                                 session_manager.restart();
                                 commands.insert_resource(NextState(Some(InGameState::Playing)));
                             }
+
+                            // Photo mode needs the game to be paused, so it's only available
+                            // offline.
+                            if BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &localization.get("photo-mode"),
+                            )
+                            .min_size(egui::vec2(width, 0.0))
+                            .show(ui)
+                            .clicked()
+                            {
+                                commands.init_resource::<PhotoMode>();
+                                *pause_page = PauseMenuPage::PhotoMode;
+                                ui.ctx().clear_focus();
+                            }
                         });
 
+                        if BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &localization.get("settings"),
+                        )
+                        .min_size(egui::vec2(width, 0.0))
+                        .show(ui)
+                        .clicked()
+                        {
+                            *menu_page = MenuPage::Settings;
+                            **modified_settings = Some(
+                                Settings::get_stored_or_default(&game, &mut storage).into_owned(),
+                            );
+                            *pause_page = PauseMenuPage::Settings;
+                            ui.ctx().clear_focus();
+                        }
+
                         ui.scope(|ui| {
                             if BorderedButton::themed(
                                 &ui_theme.button_styles.normal,
@@ -196,15 +336,39 @@ pub fn pause_menu_default(
         });
 }
 
+fn pause_menu_settings(world: &mut World) {
+    let mut egui_context = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .single(world)
+        .clone();
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none())
+        .show(egui_context.get_mut(), |ui| {
+            widget::<SettingsMenu>(world, ui, WidgetId::new("settings"), ());
+        });
+
+    // Go back to the pause menu once the settings have been saved or cancelled
+    if !matches!(*world.resource::<MenuPage>(), MenuPage::Settings) {
+        *world.resource_mut::<PauseMenuPage>() = PauseMenuPage::Default;
+    }
+}
+
 fn pause_menu_map_select(world: &mut World) {
     let mut egui_context = world
         .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
         .single(world)
         .clone();
+    let is_training = world.contains_resource::<TrainingMode>();
 
     egui::CentralPanel::default()
         .frame(egui::Frame::none())
         .show(egui_context.get_mut(), |ui| {
-            widget::<MapSelectMenu>(world, ui, WidgetId::new("map-select"), false);
+            widget::<MapSelectMenu>(
+                world,
+                ui,
+                WidgetId::new("map-select"),
+                (false, false, is_training),
+            );
         });
 }
@@ -0,0 +1,169 @@
+use bevy::ecs::schedule::common_conditions::not;
+use bevy_egui::EguiRenderOutput;
+
+use crate::{loading::PlayerInputCollector, prelude::*};
+
+use super::pause_menu::PauseMenuPage;
+
+/// How fast the camera pans, in camera heights per second.
+const PAN_SPEED: f32 = 0.75;
+
+/// How fast the camera zooms, as the factor the camera height changes by per second.
+const ZOOM_SPEED: f32 = 2.0;
+
+/// How far the camera can zoom in and out, as a factor of the camera height when photo mode
+/// started.
+const MAX_ZOOM: f32 = 4.0;
+
+pub struct PhotoModePlugin;
+
+impl Plugin for PhotoModePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            move_photo_camera
+                .run_if(in_state(EngineState::InGame))
+                .run_if(resource_equals(PauseMenuPage::PhotoMode))
+                .run_if(resource_exists::<PhotoMode>())
+                .run_if(resource_exists::<Session>()),
+        )
+        .add_system(
+            exit_photo_mode
+                .run_if(resource_exists::<PhotoMode>())
+                .run_if(not(resource_equals(PauseMenuPage::PhotoMode))),
+        )
+        .add_system(exit_photo_mode.in_schedule(OnExit(InGameState::Paused)))
+        .add_system(
+            hide_egui
+                .run_if(resource_exists::<PhotoMode>())
+                .in_base_set(CoreSet::PostUpdate)
+                .after(bevy_egui::EguiSet::ProcessOutput),
+        );
+    }
+}
+
+/// While this resource exists the game is in photo mode, where the game session is paused and the
+/// camera can be moved freely to take screenshots, without the HUD or any menus in the way.
+///
+/// Photo mode is started from the pause menu in local games, and shows the
+/// [`PauseMenuPage::PhotoMode`] page.
+#[derive(Resource, Default)]
+pub struct PhotoMode {
+    /// The camera before photo mode started, which is restored when it ends.
+    original_camera: Option<PhotoCamera>,
+    /// The camera that is moved around in photo mode.
+    camera: Option<PhotoCamera>,
+}
+
+/// The view of the game session's camera.
+#[derive(Clone, Copy, Debug)]
+struct PhotoCamera {
+    pos: Vec3,
+    height: f32,
+}
+
+impl PhotoCamera {
+    /// Get the view of the game session's camera.
+    fn get(session: &mut Session) -> Option<Self> {
+        session
+            .world()
+            .run_initialized_system(
+                |entities: bones::Res<bones::Entities>,
+                 cameras: bones::Comp<bones::Camera>,
+                 transforms: bones::Comp<bones::Transform>| {
+                    Ok(entities.iter_with((&cameras, &transforms)).next().map(
+                        |(_, (camera, transform))| PhotoCamera {
+                            pos: transform.translation,
+                            height: camera.height,
+                        },
+                    ))
+                },
+            )
+            .unwrap()
+    }
+
+    /// Move the game session's camera to this view.
+    ///
+    /// This only lasts while the session is paused, since the camera controller moves the camera
+    /// whenever the session advances.
+    fn apply(self, session: &mut Session) {
+        session
+            .world()
+            .run_initialized_system(
+                move |entities: bones::Res<bones::Entities>,
+                      mut cameras: bones::CompMut<bones::Camera>,
+                      mut transforms: bones::CompMut<bones::Transform>| {
+                    if let Some((_, (camera, transform))) =
+                        entities.iter_with((&mut cameras, &mut transforms)).next()
+                    {
+                        camera.height = self.height;
+                        transform.translation = self.pos;
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+    }
+}
+
+/// Pan the camera with the players' movement inputs, and zoom it in and out with jump and slide.
+fn move_photo_camera(
+    mut photo_mode: ResMut<PhotoMode>,
+    mut session: ResMut<Session>,
+    time: Res<Time>,
+    player_inputs: Query<&ActionState<PlayerAction>, With<PlayerInputCollector>>,
+) {
+    let original_camera = match photo_mode.original_camera {
+        Some(camera) => camera,
+        None => {
+            let Some(camera) = PhotoCamera::get(&mut session) else { return };
+            photo_mode.original_camera = Some(camera);
+            camera
+        }
+    };
+    let mut camera = photo_mode.camera.unwrap_or(original_camera);
+
+    let mut movement = Vec2::ZERO;
+    let mut zoom = 0.0;
+    for input in &player_inputs {
+        movement += input
+            .axis_pair(PlayerAction::Move)
+            .map(|axis| axis.xy())
+            .unwrap_or_default();
+        if input.pressed(PlayerAction::Jump) {
+            zoom -= 1.0;
+        }
+        if input.pressed(PlayerAction::Slide) {
+            zoom += 1.0;
+        }
+    }
+
+    let dt = time.delta_seconds();
+    camera.height = (camera.height * ZOOM_SPEED.powf(zoom * dt)).clamp(
+        original_camera.height / MAX_ZOOM,
+        original_camera.height * MAX_ZOOM,
+    );
+    camera.pos += (movement.clamp_length_max(1.0) * PAN_SPEED * camera.height * dt).extend(0.0);
+
+    camera.apply(&mut session);
+    photo_mode.camera = Some(camera);
+}
+
+/// Put the camera back where it was and leave photo mode.
+fn exit_photo_mode(
+    mut commands: Commands,
+    photo_mode: Option<Res<PhotoMode>>,
+    session: Option<ResMut<Session>>,
+) {
+    let Some(photo_mode) = photo_mode else { return };
+    if let (Some(camera), Some(mut session)) = (photo_mode.original_camera, session) {
+        camera.apply(&mut session);
+    }
+    commands.remove_resource::<PhotoMode>();
+}
+
+/// Hide everything drawn with egui, which includes the HUD and the menus.
+fn hide_egui(mut render_outputs: Query<&mut EguiRenderOutput>) {
+    for mut render_output in &mut render_outputs {
+        render_output.shapes.clear();
+    }
+}
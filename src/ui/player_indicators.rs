@@ -0,0 +1,217 @@
+use jumpy_core::{input::PlayerInputs, player::PlayerIdx};
+
+use bevy::ecs::schedule::common_conditions::not;
+
+use crate::prelude::*;
+
+use super::photo_mode::PhotoMode;
+
+/// How far, in pixels, the outline extends past the player's sprite.
+const OUTLINE_WIDTH: f32 = 1.0;
+/// How high above the player's position the arrow is drawn.
+const ARROW_HEIGHT: f32 = 40.0;
+/// The length and thickness of each of the two lines making up the arrow.
+const ARROW_LINE_SIZE: Vec2 = Vec2::new(6.0, 2.0);
+/// How long, in seconds, the indicators take to fade out once the player starts moving.
+const FADE_OUT_TIME: f32 = 0.5;
+/// How long, in seconds, the player has to stand still before the indicators fade back in.
+const FADE_IN_DELAY: f32 = 2.0;
+/// How long, in seconds, the indicators take to fade back in.
+const FADE_IN_TIME: f32 = 0.5;
+
+pub struct PlayerIndicatorsPlugin;
+
+impl Plugin for PlayerIndicatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            update_player_indicators
+                .run_if(in_state(EngineState::InGame))
+                .run_if(in_state(GameEditorState::Hidden))
+                .run_if(resource_exists::<Session>())
+                .run_if(not(resource_exists::<PhotoMode>())),
+        )
+        .add_system(clear_player_indicators.run_if(resource_exists::<PhotoMode>()))
+        .add_system(clear_player_indicators.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// Marker component for the sprites that make up the player indicators.
+#[derive(Component)]
+pub struct PlayerIndicatorSprite;
+
+/// The fade state of a player's indicators.
+#[derive(Clone, Copy)]
+struct IndicatorFade {
+    last_position: Option<Vec2>,
+    still_time: f32,
+    opacity: f32,
+}
+
+impl Default for IndicatorFade {
+    fn default() -> Self {
+        Self {
+            last_position: None,
+            still_time: 0.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// The sprite and transform of a local player, read from the game session.
+struct LocalPlayer {
+    player_idx: usize,
+    atlas: Handle<TextureAtlas>,
+    sprite_index: usize,
+    flip_x: bool,
+    flip_y: bool,
+    transform: Transform,
+}
+
+/// Draw an outline around and an arrow above each local player's fish, in the player's color.
+///
+/// Like the throw preview, this only reads the game session and draws with Bevy sprites outside
+/// of it, so it doesn't change the simulation and can't affect rollback.
+fn update_player_indicators(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    time: Res<Time>,
+    mut storage: ResMut<Storage>,
+    mut fades: Local<[IndicatorFade; MAX_PLAYERS]>,
+    indicators: Query<Entity, With<PlayerIndicatorSprite>>,
+) {
+    for indicator in &indicators {
+        commands.entity(indicator).despawn();
+    }
+
+    let indicator = Settings::get_stored_or_default(&game, &mut storage).player_indicator;
+    if indicator == PlayerIndicator::Off {
+        return;
+    }
+
+    let network_player_idx = session.network_player_idx();
+    let players = session
+        .world()
+        .run_initialized_system(
+            move |entities: bones::Res<bones::Entities>,
+                  player_inputs: bones::Res<PlayerInputs>,
+                  player_indexes: bones::Comp<PlayerIdx>,
+                  sprites: bones::Comp<bones::AtlasSprite>,
+                  transforms: bones::Comp<bones::Transform>| {
+                let mut players = Vec::new();
+                for (_ent, (player_idx, sprite, transform)) in
+                    entities.iter_with((&player_indexes, &sprites, &transforms))
+                {
+                    // Only mark local, human players
+                    let is_local = if let Some(idx) = network_player_idx {
+                        player_idx.0 == idx
+                    } else {
+                        !player_inputs.players[player_idx.0].is_ai
+                    };
+                    if !is_local {
+                        continue;
+                    }
+
+                    players.push(LocalPlayer {
+                        player_idx: player_idx.0,
+                        atlas: sprite.atlas.get_bevy_handle_untyped().typed(),
+                        sprite_index: sprite.index,
+                        flip_x: sprite.flip_x,
+                        flip_y: sprite.flip_y,
+                        transform: Transform {
+                            translation: transform.translation,
+                            rotation: transform.rotation,
+                            scale: transform.scale,
+                        },
+                    });
+                }
+
+                Ok(players)
+            },
+        )
+        .unwrap();
+
+    let delta = time.delta_seconds();
+    for player in players {
+        // Fade the indicators out while the player moves, and back in once they stand still
+        let fade = &mut fades[player.player_idx];
+        let position = player.transform.translation.truncate();
+        let moved = fade
+            .last_position
+            .map(|last| last.distance_squared(position) > 0.01)
+            .unwrap_or(false);
+        fade.last_position = Some(position);
+        if moved {
+            fade.still_time = 0.0;
+            fade.opacity = (fade.opacity - delta / FADE_OUT_TIME).max(0.0);
+        } else {
+            fade.still_time += delta;
+            if fade.still_time >= FADE_IN_DELAY {
+                fade.opacity = (fade.opacity + delta / FADE_IN_TIME).min(1.0);
+            }
+        }
+        if fade.opacity <= 0.0 {
+            continue;
+        }
+
+        let mut color = game.ui_theme.colors.player(player.player_idx).into_bevy();
+        color.set_a(color.a() * fade.opacity);
+
+        // Draw the outline as tinted copies of the player's current sprite, offset in each
+        // direction and placed just behind it.
+        if indicator.shows_outline() {
+            for offset in [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y] {
+                let mut transform = player.transform;
+                transform.translation += (offset * OUTLINE_WIDTH).extend(-0.01);
+                commands.spawn((
+                    SpriteSheetBundle {
+                        sprite: TextureAtlasSprite {
+                            index: player.sprite_index,
+                            flip_x: player.flip_x,
+                            flip_y: player.flip_y,
+                            color,
+                            ..default()
+                        },
+                        texture_atlas: player.atlas.clone(),
+                        transform,
+                        ..default()
+                    },
+                    PlayerIndicatorSprite,
+                ));
+            }
+        }
+
+        // Draw the arrow as two lines making a "V" pointing down at the player
+        if indicator.shows_arrow() {
+            let tip = player.transform.translation + Vec3::new(0.0, ARROW_HEIGHT, 1.0);
+            for side in [-1.0, 1.0] {
+                let angle = side * std::f32::consts::FRAC_PI_4;
+                let line_center =
+                    Vec2::new(side * ARROW_LINE_SIZE.x / 2.0, ARROW_LINE_SIZE.x / 2.0)
+                        * std::f32::consts::FRAC_1_SQRT_2;
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(ARROW_LINE_SIZE),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(tip + line_center.extend(0.0))
+                            .with_rotation(Quat::from_rotation_z(angle)),
+                        ..default()
+                    },
+                    PlayerIndicatorSprite,
+                ));
+            }
+        }
+    }
+}
+
+fn clear_player_indicators(
+    mut commands: Commands,
+    indicators: Query<Entity, With<PlayerIndicatorSprite>>,
+) {
+    for indicator in &indicators {
+        commands.entity(indicator).despawn();
+    }
+}
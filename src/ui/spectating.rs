@@ -0,0 +1,93 @@
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use jumpy_core::{camera::Spectating, input::PlayerInputs, player::PlayerIdx};
+
+use crate::prelude::*;
+
+use super::widgets::EguiUiExt;
+
+pub struct SpectatingPlugin;
+
+impl Plugin for SpectatingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            spectating_overlay
+                .run_if(in_state(EngineState::InGame))
+                .run_if(in_state(GameEditorState::Hidden))
+                .run_if(resource_exists::<Session>()),
+        );
+    }
+}
+
+/// Shows which player is being watched for every local player that is currently spectating.
+fn spectating_overlay(
+    mut session: ResMut<Session>,
+    mut contexts: EguiContexts,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    player_meta_assets: Res<Assets<PlayerMeta>>,
+) {
+    let network_player_idx = session.network_player_idx();
+
+    // Get the spectating players and the skin of the player they are watching
+    let spectators = session
+        .world()
+        .run_initialized_system(
+            move |entities: bones::Res<bones::Entities>,
+                  spectators: bones::Comp<Spectating>,
+                  player_indexes: bones::Comp<PlayerIdx>,
+                  player_inputs: bones::Res<PlayerInputs>| {
+                let mut spectators = entities
+                    .iter_with(&spectators)
+                    .filter(|(_, spectating)| {
+                        // Only show the label for local, human players
+                        if let Some(idx) = network_player_idx {
+                            spectating.player == idx
+                        } else {
+                            !player_inputs.players[spectating.player].is_ai
+                        }
+                    })
+                    .map(|(_, spectating)| {
+                        let target_skin = spectating
+                            .target
+                            .and_then(|target| player_indexes.get(target))
+                            .map(|idx| player_inputs.players[idx.0].selected_player.clone());
+                        (spectating.player, target_skin)
+                    })
+                    .collect::<Vec<_>>();
+                spectators.sort_by_key(|(idx, _)| *idx);
+
+                Ok(spectators)
+            },
+        )
+        .unwrap();
+
+    if spectators.is_empty() {
+        return;
+    }
+
+    let font = &game.ui_theme.font_styles.bigger;
+    egui::Area::new("spectating_overlay")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, font.size))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                for (player_idx, target_skin) in spectators {
+                    let Some(player_meta) = target_skin
+                        .and_then(|skin| player_meta_assets.get(&skin.get_bevy_handle())) else {
+                        continue;
+                    };
+
+                    let name = &player_meta.name;
+                    let label = if network_player_idx.is_some() {
+                        localization.get(&format!("spectating?player={name}"))
+                    } else {
+                        let number = player_idx + 1;
+                        localization
+                            .get(&format!("player-spectating?number={number}&player={name}"))
+                    };
+                    ui.themed_label(font, &label);
+                }
+            });
+        });
+}
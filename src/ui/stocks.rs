@@ -0,0 +1,270 @@
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use jumpy_core::{
+    camera::KillCam,
+    input::PlayerInputs,
+    score::{MatchResult, MatchScore},
+};
+
+use crate::{prelude::*, session::LocalSessionRunner};
+
+use super::{
+    main_menu::{
+        map_select::MapPlaylistState, mutators::mutator_localization_key,
+        player_select::PlayerNames,
+    },
+    training::TrainingMode,
+    widgets::{bordered_button::BorderedButton, bordered_frame::BorderedFrame, EguiUiExt},
+};
+
+pub struct StocksPlugin;
+
+impl Plugin for StocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_storage_item::<PlayerStats>()
+            .add_system(
+                match_result_overlay
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(in_state(InGameState::Playing))
+                    .run_if(in_state(GameEditorState::Hidden))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(
+                record_match_stats
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(in_state(GameEditorState::Hidden))
+                    .run_if(resource_exists::<Session>()),
+            )
+            .add_system(record_left_match_stats.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// Get the label showing how many lives a player has left.
+pub(super) fn lives_label(localization: &Localization, name: &str, lives: u32) -> String {
+    if lives == 0 {
+        localization.get(&format!("player-eliminated?player={name}"))
+    } else {
+        localization.get(&format!("player-lives?player={name}&lives={lives}"))
+    }
+}
+
+/// Adds the match to the local players' [`PlayerStats`] once it is over.
+fn record_match_stats(
+    mut recorded: Local<bool>,
+    mut session: ResMut<Session>,
+    mut storage: ResMut<Storage>,
+    player_meta_assets: Res<Assets<PlayerMeta>>,
+    training: Option<Res<TrainingMode>>,
+) {
+    let is_over = session
+        .world()
+        .run_initialized_system(|score: bones::Res<MatchScore>| Ok(score.result.is_some()))
+        .unwrap();
+    if !is_over {
+        // The match was restarted, so record the next one when it ends
+        *recorded = false;
+        return;
+    }
+    if *recorded || training.is_some() {
+        return;
+    }
+    *recorded = true;
+
+    record_stats(&mut session, &mut storage, &player_meta_assets);
+}
+
+/// Counts leaving a network match before it is over as a loss for the local player.
+///
+/// Leaving a local match doesn't count at all.
+fn record_left_match_stats(
+    session: Option<ResMut<Session>>,
+    mut storage: ResMut<Storage>,
+    player_meta_assets: Res<Assets<PlayerMeta>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if session.network_player_idx().is_none() {
+        return;
+    }
+    let is_over = session
+        .world()
+        .run_initialized_system(|score: bones::Res<MatchScore>| Ok(score.result.is_some()))
+        .unwrap();
+    if !is_over {
+        record_stats(&mut session, &mut storage, &player_meta_assets);
+    }
+}
+
+/// Add the current match to the stats of the local players and save them.
+///
+/// Saving happens in the background, so it doesn't hold up the results screen.
+fn record_stats(
+    session: &mut Session,
+    storage: &mut Storage,
+    player_meta_assets: &Assets<PlayerMeta>,
+) {
+    let network_player_idx = session.network_player_idx();
+    // Replays and other kinds of sessions don't have any local players to record
+    if network_player_idx.is_none() && session.downcast_ref::<LocalSessionRunner>().is_none() {
+        return;
+    }
+
+    let (score, players) = session
+        .world()
+        .run_initialized_system(
+            |score: bones::Res<MatchScore>, player_inputs: bones::Res<PlayerInputs>| {
+                Ok(((*score).clone(), player_inputs.players.clone()))
+            },
+        )
+        .unwrap();
+
+    // Pairs of the local players' profile slots and their player indexes. The local player of a
+    // network match uses the first profile.
+    let local_players = match network_player_idx {
+        Some(idx) => vec![(0, idx)],
+        None => players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.active && !player.is_ai)
+            .map(|(idx, _)| (idx, idx))
+            .collect(),
+    };
+
+    let mut stats = storage.get_item::<PlayerStats>();
+    for (slot, player_idx) in local_players {
+        let fish = player_meta_assets
+            .get(&players[player_idx].selected_player.get_bevy_handle())
+            .map(|meta| meta.name.as_str())
+            .unwrap_or_default();
+        stats.record_match(slot, player_idx, fish, &score);
+    }
+    storage.set_item(&stats);
+    storage.save();
+}
+
+/// Announces the winner of the match once it is over.
+fn match_result_overlay(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut session_manager: SessionManager,
+    mut playlist_state: ResMut<MapPlaylistState>,
+    mut storage: ResMut<Storage>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    map_assets: Res<Assets<MapMeta>>,
+    player_meta_assets: Res<Assets<PlayerMeta>>,
+    player_names: Res<PlayerNames>,
+) {
+    let Some(session) = session_manager.session.as_mut() else {
+        return;
+    };
+    let is_online = session.network_player_idx().is_some();
+    let mutators = session
+        .core_session()
+        .info
+        .mutators
+        .iter()
+        .map(|mutator| localization.get(mutator_localization_key(mutator)))
+        .collect::<Vec<_>>();
+    let result = session
+        .world()
+        .run_initialized_system(
+            |score: bones::Res<MatchScore>,
+             player_inputs: bones::Res<PlayerInputs>,
+             kill_cam: bones::Res<KillCam>| {
+                // Wait for the kill cam to finish before showing the results
+                if kill_cam.is_playing() {
+                    return Ok(None);
+                }
+                Ok(score.result.map(|result| match result {
+                    MatchResult::Winner(idx) => {
+                        Some((idx, player_inputs.players[idx].selected_player.clone()))
+                    }
+                    MatchResult::Draw => None,
+                }))
+            },
+        )
+        .unwrap();
+    let Some(winner) = result else {
+        return;
+    };
+
+    let title = match winner {
+        Some((player_idx, skin)) => {
+            let name = player_names.get(player_idx).unwrap_or_else(|| {
+                player_meta_assets
+                    .get(&skin.get_bevy_handle())
+                    .map(|meta| meta.name.as_str())
+                    .unwrap_or_default()
+            });
+            localization.get(&format!("player-wins?player={name}"))
+        }
+        None => localization.get("match-draw"),
+    };
+
+    let ui_theme = &game.ui_theme;
+    egui::Area::new("match_result")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    let heading_font = ui_theme
+                        .font_styles
+                        .heading
+                        .colored(ui_theme.panel.font_color);
+                    let width = game.main_menu.menu_width / 2.0;
+
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(&heading_font, &title);
+                        // Show the mutators, so that the results explain how the match was played
+                        if !mutators.is_empty() {
+                            let normal_font = ui_theme
+                                .font_styles
+                                .normal
+                                .colored(ui_theme.panel.font_color);
+                            ui.themed_label(
+                                &normal_font,
+                                &localization.get(&format!(
+                                    "match-mutators?mutators={}",
+                                    mutators.join(", ")
+                                )),
+                            );
+                        }
+                        ui.add_space(10.0);
+
+                        // Only the local game can be restarted for now
+                        if !is_online
+                            && BorderedButton::themed(
+                                &ui_theme.button_styles.normal,
+                                &localization.get("restart"),
+                            )
+                            .min_size(egui::vec2(width, 0.0))
+                            .show(ui)
+                            .clicked()
+                        {
+                            if let Some(map_meta) =
+                                playlist_state.advance(&mut storage, &map_assets)
+                            {
+                                session_manager.restart_with_map(map_meta);
+                            } else {
+                                session_manager.restart();
+                            }
+                        }
+
+                        if BorderedButton::themed(
+                            &ui_theme.button_styles.normal,
+                            &localization.get("main-menu"),
+                        )
+                        .min_size(egui::vec2(width, 0.0))
+                        .show(ui)
+                        .clicked()
+                        {
+                            commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+                            ui.ctx().clear_focus();
+                        }
+                    });
+                });
+        });
+}
@@ -0,0 +1,68 @@
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+use crate::prelude::*;
+
+use super::widgets::{bordered_frame::BorderedFrame, EguiUiExt};
+
+/// How long each storage warning toast stays on screen.
+const TOAST_DURATION: f32 = 8.0;
+
+pub struct StorageWarningsPlugin;
+
+impl Plugin for StorageWarningsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            storage_warning_toast
+                .run_if(resource_exists::<GameMeta>())
+                .run_if(|warnings: Res<StorageWarnings>| !warnings.is_empty()),
+        );
+    }
+}
+
+/// Shows the [`StorageWarnings`] one after the other, so the player knows when their settings
+/// weren't loaded or saved properly.
+fn storage_warning_toast(
+    mut shown_at: Local<Option<f32>>,
+    mut warnings: ResMut<StorageWarnings>,
+    time: Res<Time>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    mut contexts: EguiContexts,
+) {
+    let toast_start = *shown_at.get_or_insert(time.elapsed_seconds());
+    if time.elapsed_seconds() - toast_start > TOAST_DURATION {
+        warnings.remove(0);
+        *shown_at = None;
+        return;
+    }
+
+    let message = match &warnings[0] {
+        StorageWarning::RecoveredFromBackup => localization.get("settings-recovered-from-backup"),
+        StorageWarning::Reset => localization.get("settings-reset"),
+        StorageWarning::SaveFailed(error) => {
+            format!("{}\n{error}", localization.get("settings-save-failed"))
+        }
+        StorageWarning::QuotaExceeded => localization.get("settings-quota-exceeded"),
+    };
+
+    let ui_theme = &game.ui_theme;
+    let font = ui_theme
+        .font_styles
+        .normal
+        .colored(ui_theme.panel.font_color);
+
+    egui::Area::new("storage_warning_toast")
+        .anchor(
+            egui::Align2::RIGHT_BOTTOM,
+            egui::vec2(-font.size, -font.size),
+        )
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            BorderedFrame::new(&ui_theme.panel.border)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(&font, &message);
+                });
+        });
+}
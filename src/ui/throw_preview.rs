@@ -0,0 +1,173 @@
+use bones_bevy_asset::BevyAssets;
+use jumpy_core::{
+    elements::ElementHandle,
+    input::PlayerInputs,
+    item::{Inventory, ItemThrow, ThrowKind},
+    metadata::{CoreMetaArc, ElementMeta},
+    physics::{collisions::CollisionWorld, KinematicBody},
+    player::PlayerIdx,
+};
+
+use bevy::ecs::schedule::common_conditions::not;
+
+use crate::prelude::*;
+
+use super::photo_mode::PhotoMode;
+
+/// How many frames of the throw to preview.
+const PREVIEW_FRAMES: usize = 45;
+/// Only every this many simulated frames is drawn as a dot, to make the arc dotted.
+const DOT_INTERVAL: usize = 3;
+/// The size of the dots in the arc.
+const DOT_SIZE: f32 = 2.0;
+
+pub struct ThrowPreviewPlugin;
+
+impl Plugin for ThrowPreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            update_throw_preview
+                .run_if(in_state(EngineState::InGame))
+                .run_if(in_state(GameEditorState::Hidden))
+                .run_if(resource_exists::<Session>())
+                .run_if(not(resource_exists::<PhotoMode>())),
+        )
+        .add_system(clear_throw_preview.run_if(resource_exists::<PhotoMode>()))
+        .add_system(clear_throw_preview.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// Marker component for the sprites that make up the throw preview arcs.
+#[derive(Component)]
+pub struct ThrowPreviewDot;
+
+/// Draw the arc that the item held by each local player would follow if they threw it.
+///
+/// This only reads the game session, and draws the arc with Bevy sprites outside of it, so it
+/// doesn't change the simulation and can't affect rollback.
+fn update_throw_preview(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    game: Res<GameMeta>,
+    mut storage: ResMut<Storage>,
+    dots: Query<Entity, With<ThrowPreviewDot>>,
+) {
+    for dot in &dots {
+        commands.entity(dot).despawn();
+    }
+
+    if !Settings::get_stored_or_default(&game, &mut storage).show_throw_preview {
+        return;
+    }
+
+    let network_player_idx = session.network_player_idx();
+    let arcs = session
+        .world()
+        .run_initialized_system(
+            move |entities: bones::Res<bones::Entities>,
+                  core_meta: bones::Res<CoreMetaArc>,
+                  player_inputs: bones::Res<PlayerInputs>,
+                  player_indexes: bones::Comp<PlayerIdx>,
+                  inventories: bones::Comp<Inventory>,
+                  item_throws: bones::Comp<ItemThrow>,
+                  element_handles: bones::Comp<ElementHandle>,
+                  element_assets: BevyAssets<ElementMeta>,
+                  bodies: bones::Comp<KinematicBody>,
+                  sprites: bones::Comp<bones::AtlasSprite>,
+                  transforms: bones::Comp<bones::Transform>,
+                  collision_world: CollisionWorld| {
+                let mut arcs = Vec::new();
+                for (player_ent, (player_idx, inventory)) in
+                    entities.iter_with((&player_indexes, &inventories))
+                {
+                    // Only show the arc for local, human players
+                    let player_input = &player_inputs.players[player_idx.0];
+                    let is_local = if let Some(idx) = network_player_idx {
+                        player_idx.0 == idx
+                    } else {
+                        !player_input.is_ai
+                    };
+                    if !is_local {
+                        continue;
+                    }
+                    let Some(item) = inventory.0 else { continue };
+                    let (Some(item_throw), Some(body), Some(transform), Some(sprite)) = (
+                        item_throws.get(item),
+                        bodies.get(item),
+                        transforms.get(item),
+                        sprites.get(player_ent),
+                    ) else {
+                        continue;
+                    };
+
+                    // Simulate the throw the same way `throw_dropped_items` and the physics do
+                    let flip = if sprite.flip_x { -1.0 } else { 1.0 };
+                    let overrides = element_handles
+                        .get(item)
+                        .and_then(|handle| element_assets.get(&handle.get_bevy_handle()))
+                        .map(|element_meta| &element_meta.throw);
+                    let mut velocity = item_throw.velocity_with_overrides(
+                        ThrowKind::from_control(&player_input.control),
+                        overrides,
+                    ) * Vec2::new(flip, 1.0);
+                    let mut position = transform.translation.truncate();
+                    let mut points = Vec::new();
+                    for frame in 0..PREVIEW_FRAMES {
+                        let mut hit = false;
+                        let next = position + Vec2::new(0.0, velocity.y);
+                        if collision_world.solid_at(next) {
+                            velocity.y *= -body.bounciness;
+                            hit = true;
+                        } else {
+                            position = next;
+                        }
+                        let next = position + Vec2::new(velocity.x, 0.0);
+                        if collision_world.solid_at(next) {
+                            velocity.x *= -body.bounciness;
+                            hit = true;
+                        } else {
+                            position = next;
+                        }
+
+                        if frame % DOT_INTERVAL == 0 || hit {
+                            points.push(position.extend(transform.translation.z + 1.0));
+                        }
+                        // Stop where the item would hit a wall, unless it would bounce off of it
+                        if hit && body.bounciness <= 0.0 {
+                            break;
+                        }
+
+                        if body.has_mass {
+                            velocity.y = (velocity.y - body.gravity)
+                                .max(-core_meta.physics.terminal_velocity);
+                        }
+                    }
+                    arcs.push(points);
+                }
+
+                Ok(arcs)
+            },
+        )
+        .unwrap();
+
+    for point in arcs.into_iter().flatten() {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1.0, 1.0, 1.0, 0.7),
+                    custom_size: Some(Vec2::splat(DOT_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_translation(point),
+                ..default()
+            },
+            ThrowPreviewDot,
+        ));
+    }
+}
+
+fn clear_throw_preview(mut commands: Commands, dots: Query<Entity, With<ThrowPreviewDot>>) {
+    for dot in &dots {
+        commands.entity(dot).despawn();
+    }
+}
@@ -0,0 +1,253 @@
+use std::{sync::Arc, time::Duration};
+
+use bevy::window::PrimaryWindow;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use bones_bevy_renderer::BevyBonesEntity;
+use jumpy_core::{
+    elements::player_spawner::PlayerSpawner,
+    lifetime::Invincibility,
+    physics::KinematicBody,
+    player::{PlayerIdx, PlayerState},
+};
+
+use crate::{prelude::*, session::LocalSessionRunner};
+
+/// The element category of the items that can be spawned in training mode.
+const ITEM_CATEGORY: &str = "Weapons";
+
+/// The simulation speeds that can be picked in training mode.
+const TIME_SCALES: [(f64, &str); 3] = [(1.0, "100%"), (0.5, "50%"), (0.25, "25%")];
+
+pub struct TrainingPlugin;
+
+impl Plugin for TrainingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            training_window
+                .run_if(in_state(EngineState::InGame))
+                .run_if(in_state(GameEditorState::Hidden))
+                .run_if(resource_exists::<Session>())
+                .run_if(resource_exists::<TrainingMode>()),
+        )
+        .add_system(stop_training.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// Resource that exists while playing a training session, containing the training settings.
+#[derive(Resource, Default)]
+pub struct TrainingMode {
+    /// Whether the player can't be killed.
+    pub invincible: bool,
+    /// The index in the [`CoreMeta::map_elements`] of the item spawned by clicking on the map.
+    pub selected_item: Option<usize>,
+}
+
+/// Get the info for a single player training session, where nobody can win and the player
+/// respawns immediately.
+pub fn training_session_info(
+    core_meta: &CoreMeta,
+    mut map_meta: MapMeta,
+    player: bones::Handle<PlayerMeta>,
+) -> CoreSessionInfo {
+    let mut meta = core_meta.clone();
+    meta.config.respawn_delay = 0;
+    meta.config.start_countdown_frames = 0;
+    meta.config.stock_lives = None;
+    meta.config.time_limit = None;
+    meta.config.points_to_win = f32::INFINITY;
+    map_meta.respawn.delay = None;
+
+    let mut player_info = <[Option<GameSessionPlayerInfo>; MAX_PLAYERS]>::default();
+    player_info[0] = Some(GameSessionPlayerInfo {
+        handle: player,
+        is_ai: false,
+        ai_difficulty: default(),
+    });
+
+    CoreSessionInfo {
+        meta: Arc::new(meta),
+        map_meta,
+        player_info,
+        mutators: default(),
+        seed: rand::random(),
+    }
+}
+
+fn stop_training(mut commands: Commands) {
+    commands.remove_resource::<TrainingMode>();
+}
+
+/// Renders the training tools, and spawns the selected item when the map is clicked.
+fn training_window(
+    mut training: ResMut<TrainingMode>,
+    mut session: ResMut<Session>,
+    mut editor_input: ResMut<CurrentEditorInput>,
+    mut egui_ctxs: EguiContexts,
+    core_meta: Res<CoreMetaArc>,
+    element_assets: Res<Assets<ElementMeta>>,
+    localization: Res<Localization>,
+    mouse: Res<Input<MouseButton>>,
+    cameras: Query<(&Camera, &Transform), With<BevyBonesEntity>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Some(runner) = session.downcast_mut::<LocalSessionRunner>() else {
+        return;
+    };
+    let training = &mut *training;
+
+    let invincible = training.invincible;
+    let (player_state, player_velocity) = runner
+        .core
+        .world
+        .run_initialized_system(
+            move |entities: bones::Res<bones::Entities>,
+                  player_indexes: bones::Comp<PlayerIdx>,
+                  player_states: bones::Comp<PlayerState>,
+                  bodies: bones::Comp<KinematicBody>,
+                  mut invincibles: bones::CompMut<Invincibility>| {
+                let Some((player_ent, _)) = entities
+                    .iter_with(&player_indexes)
+                    .find(|(_, idx)| idx.0 == 0) else {
+                    return Ok((None, None));
+                };
+                if invincible {
+                    invincibles.insert(player_ent, Invincibility::new(Duration::from_secs(1)));
+                }
+                Ok((
+                    player_states.get(player_ent).map(|state| state.current),
+                    bodies.get(player_ent).map(|body| body.velocity),
+                ))
+            },
+        )
+        .unwrap();
+
+    let items = core_meta
+        .map_elements
+        .iter()
+        .enumerate()
+        .filter_map(|(i, handle)| {
+            let meta = element_assets.get(&handle.get_bevy_handle())?;
+            (meta.category == ITEM_CATEGORY).then_some((i, meta.name.as_str()))
+        })
+        .collect::<Vec<_>>();
+
+    let ctx = egui_ctxs.ctx_mut();
+    let mut reset_position = false;
+    egui::Window::new(localization.get("training"))
+        // ID is needed because title comes from localizaition which can change
+        .id(egui::Id::new("training"))
+        .show(ctx, |ui| {
+            let none = localization.get("training-no-item");
+            let selected_name = training
+                .selected_item
+                .and_then(|selected| items.iter().find(|(i, _)| *i == selected))
+                .map(|(_, name)| *name)
+                .unwrap_or(&none);
+            ui.label(localization.get("training-spawn-item"));
+            egui::ComboBox::from_id_source("training_item")
+                .selected_text(selected_name)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut training.selected_item, None, &none);
+                    for (i, name) in &items {
+                        ui.selectable_value(&mut training.selected_item, Some(*i), *name);
+                    }
+                });
+
+            ui.separator();
+            ui.checkbox(
+                &mut training.invincible,
+                localization.get("training-invincible"),
+            );
+            reset_position = ui
+                .button(localization.get("training-reset-position"))
+                .clicked();
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(localization.get("training-speed"));
+                for (time_scale, label) in TIME_SCALES {
+                    ui.radio_value(&mut runner.time_scale, time_scale, label);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut runner.frozen, localization.get("training-freeze"));
+                let step = ui.add_enabled(
+                    runner.frozen,
+                    egui::Button::new(localization.get("training-step")),
+                );
+                if step.clicked() {
+                    runner.step_frames += 1;
+                }
+            });
+
+            ui.separator();
+            if let (Some(state), Some(velocity)) = (player_state, player_velocity) {
+                ui.label(format!("{}: {state:?}", localization.get("training-state")));
+                ui.label(localization.get(&format!(
+                    "training-velocity?x={:.2}&y={:.2}",
+                    velocity.x, velocity.y
+                )));
+            }
+        });
+
+    if reset_position {
+        runner
+            .core
+            .world
+            .run_initialized_system(
+                |entities: bones::Res<bones::Entities>,
+                 player_indexes: bones::Comp<PlayerIdx>,
+                 player_spawners: bones::Comp<PlayerSpawner>,
+                 mut transforms: bones::CompMut<bones::Transform>,
+                 mut bodies: bones::CompMut<KinematicBody>| {
+                    let Some(spawn_point) = entities
+                        .iter_with((&player_spawners, &transforms))
+                        .map(|(_ent, (_spawner, transform))| transform.translation)
+                        .next() else {
+                        return Ok(());
+                    };
+                    let players = entities
+                        .iter_with(&player_indexes)
+                        .filter(|(_, idx)| idx.0 == 0)
+                        .map(|(ent, _)| ent)
+                        .collect::<Vec<_>>();
+                    for player_ent in players {
+                        if let Some(transform) = transforms.get_mut(player_ent) {
+                            transform.translation =
+                                spawn_point.truncate().extend(transform.translation.z);
+                        }
+                        if let Some(body) = bodies.get_mut(player_ent) {
+                            body.velocity = Vec2::ZERO;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+    }
+
+    // Spawn the selected item where the map is clicked
+    let Some(selected_item) = training.selected_item else {
+        return;
+    };
+    if !mouse.just_pressed(MouseButton::Left) || ctx.is_pointer_over_area() {
+        return;
+    }
+    let (Ok((camera, transform)), Ok(window)) = (cameras.get_single(), windows.get_single()) else {
+        return;
+    };
+    let Some(translation) = window
+        .cursor_position()
+        .and_then(|pos| camera.viewport_to_world(&GlobalTransform::from(*transform), pos))
+        .map(|ray| ray.origin.truncate()) else {
+        return;
+    };
+    // Put the item in front of all of the map layers
+    let layer = runner.core.info.map_meta.layers.len().saturating_sub(1);
+    **editor_input = Some(EditorInput::SpawnElement {
+        handle: core_meta.map_elements[selected_item].clone(),
+        translation,
+        layer: layer.try_into().unwrap(),
+    });
+}
@@ -0,0 +1,239 @@
+//! The tutorial that new players are taken through the first time they start the game.
+//!
+//! The tutorial is a regular session on the [`CoreMeta::tutorial_map`], which is made of
+//! [`TriggerRegion`]s that each wait for the player to perform an action. This module only shows
+//! the instructions of the regions, with the inputs bound to their actions, and goes back to the
+//! main menu once all of them have been triggered.
+
+use bevy::{ecs::schedule::common_conditions::not, window::PrimaryWindow};
+use bevy_egui::*;
+use bevy_fluent::Localization;
+use bones_bevy_renderer::BevyBonesEntity;
+use jumpy_core::elements::trigger_region::{TriggerProgress, TriggerRegion};
+
+use crate::{
+    loading::PlayerInputCollector,
+    prelude::*,
+    ui::{
+        main_menu::player_select::PlayerNames,
+        map_loading::{MapLoading, MapLoadingStart},
+        training::training_session_info,
+        widgets::{bordered_button::BorderedButton, EguiUiExt},
+    },
+};
+
+/// How far above the top of a trigger region its instruction is shown, in world pixels.
+const LABEL_MARGIN: f32 = 8.0;
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_storage_item::<Onboarding>()
+            .add_system(
+                start_first_run_tutorial
+                    .run_if(in_state(EngineState::MainMenu))
+                    .run_if(not(resource_exists::<MapLoading>())),
+            )
+            .add_system(
+                tutorial_overlay
+                    .run_if(in_state(EngineState::InGame))
+                    .run_if(in_state(GameEditorState::Hidden))
+                    .run_if(resource_exists::<Session>())
+                    .run_if(resource_exists::<TutorialMode>()),
+            )
+            .add_system(stop_tutorial.in_schedule(OnExit(EngineState::InGame)));
+    }
+}
+
+/// The player's progress through the first-run flow, stored in [`Storage`].
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Onboarding {
+    /// Whether the player has completed or skipped the tutorial.
+    pub tutorial_done: bool,
+}
+
+impl StorageItem for Onboarding {
+    const STORAGE_KEY: &'static str = "onboarding";
+}
+
+/// Resource that exists while playing the tutorial.
+#[derive(Resource, Default)]
+pub struct TutorialMode;
+
+/// Start loading the tutorial, returning `false` if the game doesn't have a tutorial map.
+pub fn start_tutorial(
+    commands: &mut Commands,
+    core_meta: &CoreMeta,
+    map_assets: &Assets<MapMeta>,
+) -> bool {
+    let Some(map_handle) = &core_meta.tutorial_map else {
+        return false;
+    };
+    let Some(map_meta) = map_assets.get(&map_handle.get_bevy_handle()) else {
+        error!("Tutorial map is not loaded");
+        return false;
+    };
+
+    info!("Starting tutorial");
+    let player = core_meta.players[0].clone();
+    commands.insert_resource(PlayerNames::default());
+    commands.insert_resource(MapLoading::new(
+        training_session_info(core_meta, map_meta.clone(), player),
+        MapLoadingStart::Tutorial,
+    ));
+    true
+}
+
+/// Send the player from the title screen into the tutorial, the first time the game is started.
+fn start_first_run_tutorial(
+    mut commands: Commands,
+    mut checked: Local<bool>,
+    mut storage: ResMut<Storage>,
+    core_meta: Res<CoreMetaArc>,
+    map_assets: Res<Assets<MapMeta>>,
+) {
+    // Only check once, so that leaving the tutorial from the pause menu doesn't start it again
+    if *checked {
+        return;
+    }
+    *checked = true;
+
+    // Don't get in the way of launching straight into a match from the command line
+    if ENGINE_CONFIG.skips_menu() {
+        return;
+    }
+
+    if !storage.get_item::<Onboarding>().tutorial_done {
+        start_tutorial(&mut commands, &core_meta, &map_assets);
+    }
+}
+
+fn stop_tutorial(mut commands: Commands) {
+    commands.remove_resource::<TutorialMode>();
+}
+
+/// Mark the tutorial as done, so that it isn't started again.
+fn finish_tutorial(commands: &mut Commands, storage: &mut Storage) {
+    let mut onboarding = storage.get_item::<Onboarding>();
+    onboarding.tutorial_done = true;
+    storage.set_item(&onboarding);
+    storage.save();
+    commands.insert_resource(NextState(Some(EngineState::MainMenu)));
+}
+
+/// Get the player action whose inputs are shown in the instruction for a trigger action.
+fn trigger_input(action: TriggerAction) -> PlayerAction {
+    match action {
+        TriggerAction::Move | TriggerAction::Crouch => PlayerAction::Move,
+        TriggerAction::Jump => PlayerAction::Jump,
+        TriggerAction::Grab | TriggerAction::Throw => PlayerAction::Grab,
+    }
+}
+
+/// Show the instructions of the active trigger regions above them, with the skip button, and go
+/// back to the main menu once the tutorial has been completed or skipped.
+fn tutorial_overlay(
+    mut commands: Commands,
+    mut session: ResMut<Session>,
+    mut storage: ResMut<Storage>,
+    mut egui_ctxs: EguiContexts,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    egui_settings: Res<EguiSettings>,
+    input_maps: Query<(&PlayerInputCollector, &InputMap<PlayerAction>)>,
+    cameras: Query<(&Camera, &Transform), With<BevyBonesEntity>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+) {
+    let (progress, regions) = session
+        .world()
+        .run_initialized_system(
+            |entities: bones::Res<bones::Entities>,
+             trigger_regions: bones::Comp<TriggerRegion>,
+             transforms: bones::Comp<bones::Transform>| {
+                let progress =
+                    TriggerProgress::new(entities.iter_with(&trigger_regions).map(|(_, x)| x));
+                let regions = entities
+                    .iter_with((&trigger_regions, &transforms))
+                    .filter(|(_, (region, _))| progress.is_active(region))
+                    .map(|(_, (region, transform))| (region.clone(), transform.translation))
+                    .collect::<Vec<_>>();
+                Ok((progress, regions))
+            },
+        )
+        .unwrap();
+
+    if progress.is_complete() {
+        info!("Tutorial completed");
+        finish_tutorial(&mut commands, &mut storage);
+        return;
+    }
+
+    let ui_theme = &game.ui_theme;
+    let ctx = egui_ctxs.ctx_mut();
+
+    // Show the instructions above the regions, with the inputs bound to their actions
+    let input_map = input_maps
+        .iter()
+        .find(|(collector, _)| collector.0 == 0)
+        .map(|(_, map)| map);
+    if let (Some(input_map), Ok((camera, transform)), Ok(window)) =
+        (input_map, cameras.get_single(), windows.get_single())
+    {
+        let actions = PlayerActionMap::new(
+            input_map,
+            &[PlayerAction::Move, PlayerAction::Jump, PlayerAction::Grab],
+        );
+        let font = &ui_theme.font_styles.bigger;
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Background,
+            egui::Id::new("tutorial_labels"),
+        ));
+        let scale = egui_settings.scale_factor as f32;
+
+        for (region, translation) in regions {
+            let label_pos = translation + Vec3::Y * (region.size.y / 2.0 + LABEL_MARGIN);
+            let Some(viewport_pos) =
+                camera.world_to_viewport(&GlobalTransform::from(*transform), label_pos) else {
+                continue;
+            };
+            let input = actions.get_text(trigger_input(region.action));
+            painter.text(
+                egui::pos2(viewport_pos.x, window.height() - viewport_pos.y) / scale,
+                egui::Align2::CENTER_BOTTOM,
+                localization.get(&format!("{}?input={input}", region.label)),
+                font.font_id(),
+                ColorMeta(region.color).into_egui(),
+            );
+        }
+    }
+
+    // Show the progress, and the button to skip the tutorial
+    let mut skip = false;
+    let font = &ui_theme.font_styles.normal;
+    egui::Area::new("tutorial_progress")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-font.size, font.size))
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.themed_label(
+                    font,
+                    &localization.get(&format!(
+                        "tutorial-progress?done={}&total={}",
+                        progress.triggered, progress.total
+                    )),
+                );
+                skip = BorderedButton::themed(
+                    &ui_theme.button_styles.small,
+                    &localization.get("skip-tutorial"),
+                )
+                .show(ui)
+                .clicked();
+            });
+        });
+
+    if skip {
+        info!("Tutorial skipped");
+        finish_tutorial(&mut commands, &mut storage);
+    }
+}
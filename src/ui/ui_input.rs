@@ -1,3 +1,5 @@
+use leafwing_input_manager::user_input::{InputKind, UserInput};
+
 use crate::prelude::*;
 
 pub struct UiInputPlugin;
@@ -19,4 +21,233 @@ pub enum MenuAction {
     Back,
     Pause,
     ToggleFullscreen,
+    Chat,
+    EditorUndo,
+    EditorRedo,
+    /// Start playtesting the map from the editor, or return to the editor from a playtest.
+    ToggleEditor,
+    /// Save a screenshot of the game.
+    Screenshot,
+}
+
+/// The function keys, which are all used by hard-coded shortcuts, like the debug tools, replay
+/// controls, and fullscreen toggle.
+const FUNCTION_KEYS: [KeyCode; 12] = [
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+];
+
+/// Whether a key can't be used for [`MenuAction::Screenshot`], because it is already bound to
+/// another menu action or used by a hard-coded shortcut.
+pub fn is_reserved_screenshot_key(key: KeyCode) -> bool {
+    let menu_input_map = crate::loading::menu_input_map();
+    let input = UserInput::Single(InputKind::Keyboard(key));
+    FUNCTION_KEYS.contains(&key)
+        || MenuAction::variants()
+            .filter(|action| *action != MenuAction::Screenshot)
+            .any(|action| menu_input_map.get(action).iter().any(|x| *x == input))
+}
+
+/// Bind [`MenuAction::Screenshot`] to the key chosen in the settings, replacing the old binding.
+///
+/// Reserved keys are never bound, so that a key doesn't trigger a screenshot and something else.
+pub fn bind_screenshot_key(input_map: &mut InputMap<MenuAction>, key: KeyCode) {
+    input_map.clear_action(MenuAction::Screenshot);
+    if is_reserved_screenshot_key(key) {
+        warn!("Not binding screenshots to {key:?}, which is already used by another shortcut");
+        return;
+    }
+    input_map.insert(key, MenuAction::Screenshot);
+}
+
+/// How far an analog stick has to be pushed in a direction to navigate in that direction.
+pub const NAV_PRESS_THRESHOLD: f32 = 0.5;
+/// How far back towards the center a pushed stick has to come before it can navigate again.
+///
+/// This is lower than the [`NAV_PRESS_THRESHOLD`], so that a stick resting near the threshold
+/// doesn't navigate over and over.
+pub const NAV_RELEASE_THRESHOLD: f32 = 0.3;
+/// How long, in seconds, a direction has to be held before it starts repeating.
+pub const NAV_REPEAT_DELAY: f32 = 0.3;
+/// How long, in seconds, there is between repeats while a direction is held.
+pub const NAV_REPEAT_INTERVAL: f32 = 0.12;
+
+/// A direction to navigate a menu in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl NavDirection {
+    /// Get how far the axis is pushed in this direction.
+    fn amount(self, axis: Vec2) -> f32 {
+        match self {
+            NavDirection::Up => axis.y,
+            NavDirection::Down => -axis.y,
+            NavDirection::Left => -axis.x,
+            NavDirection::Right => axis.x,
+        }
+    }
+}
+
+/// Turns an analog axis into menu navigation, treating it like a d-pad.
+///
+/// A direction navigates once when the axis is pushed past the [`NAV_PRESS_THRESHOLD`], and can't
+/// navigate again until the axis comes back under the [`NAV_RELEASE_THRESHOLD`]. Holding a
+/// direction repeats it after the [`NAV_REPEAT_DELAY`], every [`NAV_REPEAT_INTERVAL`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MenuNavigator {
+    /// The direction that the axis is being held in.
+    held: Option<NavDirection>,
+    /// How long, in seconds, the direction has been held for.
+    held_for: f32,
+    /// The time in [`held_for`][Self::held_for] that the held direction repeats next at.
+    next_repeat: f32,
+}
+
+impl MenuNavigator {
+    /// Update the navigator with the current axis and the time since the last update, returning
+    /// the direction to navigate in this frame, if any.
+    pub fn update(&mut self, axis: Vec2, delta_seconds: f32) -> Option<NavDirection> {
+        if let Some(held) = self.held {
+            if held.amount(axis) >= NAV_RELEASE_THRESHOLD {
+                self.held_for += delta_seconds;
+                if self.held_for >= self.next_repeat {
+                    self.next_repeat += NAV_REPEAT_INTERVAL;
+                    return Some(held);
+                }
+                return None;
+            }
+            self.held = None;
+        }
+
+        // Only the axis that is pushed the furthest counts, like on a d-pad
+        let direction = if axis.x.abs() >= axis.y.abs() {
+            if axis.x > 0.0 {
+                NavDirection::Right
+            } else {
+                NavDirection::Left
+            }
+        } else if axis.y > 0.0 {
+            NavDirection::Up
+        } else {
+            NavDirection::Down
+        };
+        if direction.amount(axis) < NAV_PRESS_THRESHOLD {
+            return None;
+        }
+
+        *self = Self {
+            held: Some(direction),
+            held_for: 0.0,
+            next_repeat: NAV_REPEAT_DELAY,
+        };
+        Some(direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn screenshot_key_is_not_bound_to_other_shortcuts() {
+        assert!(!is_reserved_screenshot_key(KeyCode::Snapshot));
+        for key in [KeyCode::F2, KeyCode::F11, KeyCode::Return, KeyCode::Escape] {
+            assert!(is_reserved_screenshot_key(key), "{key:?} isn't reserved");
+        }
+
+        let mut input_map = crate::loading::menu_input_map();
+        bind_screenshot_key(&mut input_map, KeyCode::Snapshot);
+        bind_screenshot_key(&mut input_map, KeyCode::P);
+        assert_eq!(
+            input_map
+                .get(MenuAction::Screenshot)
+                .iter()
+                .collect::<Vec<_>>(),
+            [&UserInput::Single(InputKind::Keyboard(KeyCode::P))]
+        );
+
+        bind_screenshot_key(&mut input_map, KeyCode::F2);
+        assert!(input_map.get(MenuAction::Screenshot).is_empty());
+    }
+
+    /// Feed the navigator an axis value for each frame, returning the frames that navigated.
+    fn navigate(axes: impl IntoIterator<Item = Vec2>) -> Vec<(usize, NavDirection)> {
+        let mut navigator = MenuNavigator::default();
+        axes.into_iter()
+            .enumerate()
+            .filter_map(|(frame, axis)| Some((frame, navigator.update(axis, FRAME)?)))
+            .collect()
+    }
+
+    #[test]
+    fn flick_navigates_once() {
+        // A fast flick that overshoots and bounces back past the center
+        let flick = [0.0, 0.6, 1.0, 1.0, 0.2, -0.25, 0.0].map(|x| Vec2::new(x, 0.0));
+        assert_eq!(navigate(flick), [(1, NavDirection::Right)]);
+
+        // A slow flick that barely crosses the threshold
+        let flick = [0.1, 0.3, 0.45, 0.5, 0.45, 0.2].map(|x| Vec2::new(-x, 0.0));
+        assert_eq!(navigate(flick), [(3, NavDirection::Left)]);
+    }
+
+    #[test]
+    fn jitter_around_the_threshold_navigates_once() {
+        let jitter = [0.55, 0.45, 0.52, 0.35, 0.6, 0.31].map(|x| Vec2::new(x, 0.0));
+        assert_eq!(navigate(jitter), [(0, NavDirection::Right)]);
+    }
+
+    #[test]
+    fn releasing_and_pushing_again_navigates_twice() {
+        let flicks = [0.8, 0.2, 0.8].map(|x| Vec2::new(x, 0.0));
+        assert_eq!(
+            navigate(flicks),
+            [(0, NavDirection::Right), (2, NavDirection::Right)]
+        );
+    }
+
+    #[test]
+    fn vertical_flick_doesnt_navigate_sideways() {
+        let flick = [0.0, 0.7, 1.0, 0.0].map(|y| Vec2::new(0.0, y));
+        assert_eq!(navigate(flick), [(1, NavDirection::Up)]);
+
+        // The larger axis wins on diagonals
+        let diagonal = [Vec2::new(0.6, -0.8)];
+        assert_eq!(navigate(diagonal), [(0, NavDirection::Down)]);
+    }
+
+    #[test]
+    fn holding_repeats_after_a_delay() {
+        let frames = (NAV_REPEAT_DELAY / FRAME).round() as usize;
+        let interval = (NAV_REPEAT_INTERVAL / FRAME).round() as usize;
+        let held = navigate(std::iter::repeat(Vec2::X).take(frames + interval * 2 + 3));
+
+        assert_eq!(held.len(), 4);
+        assert_eq!(held[0].0, 0);
+        // Allow for the repeat to land on either side of a frame boundary
+        for ((frame, direction), expected) in
+            held[1..]
+                .iter()
+                .zip([frames, frames + interval, frames + interval * 2])
+        {
+            assert_eq!(*direction, NavDirection::Right);
+            assert!(frame.abs_diff(expected) <= 1, "{frame} != {expected}");
+        }
+    }
 }
@@ -71,3 +71,19 @@ impl<Req, Res> BiChannelServer<Req, Res> {
         self.request_receiver.try_recv()
     }
 }
+
+/// Get the current time in seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        (js_sys::Date::now() / 1000.0) as u64
+    }
+}